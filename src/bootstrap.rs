@@ -0,0 +1,453 @@
+//! Instruction batches for standing up a new deployment.
+//!
+//! The mints and vaults have to exist with the right PDA authorities before `Initialize` will
+//! accept them, so everything is produced here in the order the program expects it. The last
+//! batch sends the first `UpdatePriceHistory`, which leaves the price history crank running.
+
+use std::collections::HashSet;
+use std::mem::size_of;
+
+use solana_program::{
+    instruction::Instruction as SolInstruction,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+};
+use crate::{
+    helpers::constants::{CREATOR_AUTHORITY, LUCRA_SOL_ORACLE, SOL_MATA_ORACLE, SOL_USDC_ORACLE, SOL_USDT_ORACLE, wsol},
+    id,
+    instruction::{create_price_history, initialize, update_price_history},
+    lucra_core::pda::Pda,
+    state::{
+        ArbState,
+        PriceHistory,
+        StateParams,
+        SystemState,
+        staking::StakingState,
+    },
+};
+
+/// Largest serialized transaction the cluster accepts
+pub const MAX_TRANSACTION_SIZE: usize = 1_232;
+
+pub const MATA_DECIMALS: u8 = 6;
+pub const LUCRA_DECIMALS: u8 = 9;
+pub const REWARD_DECIMALS: u8 = 0;
+pub const STAKED_LUCRA_DECIMALS: u8 = 9;
+
+/// Every account the bootstrap creates. All of them except the marinade accounts are fresh
+/// keypairs chosen by the deployer and have to sign the batch that creates them.
+pub struct BootstrapAccounts {
+    pub payer: Pubkey,
+    pub marinade_state: Pubkey,
+    pub msol_mint: Pubkey,
+    /// Not created, the key SetPause accepts besides the DAO. The default key means no guardian.
+    pub guardian: Pubkey,
+
+    pub system_state: Pubkey,
+    pub staking_state: Pubkey,
+    pub arb_state: Pubkey,
+    pub price_history: Pubkey,
+
+    pub mata_mint: Pubkey,
+    pub lucra_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub staked_lucra_mint: Pubkey,
+
+    pub msol_vault: Pubkey,
+    pub arb_coffer: Pubkey,
+    pub rewards_vault: Pubkey,
+    pub arb_fund: Pubkey,
+    pub wsol_holding_vault: Pubkey,
+    pub mata_holding_vault: Pubkey,
+    pub lucra_holding_vault: Pubkey,
+
+    /// The payer's reward token account, the first UpdatePriceHistory pays its crank reward here
+    pub reward_account: Pubkey,
+}
+
+/// Instructions that are sent together in one transaction along with the keys that must sign it
+#[derive(Clone, Debug, Default)]
+pub struct BootstrapBatch {
+    pub instructions: Vec<SolInstruction>,
+    pub signers: Vec<Pubkey>,
+}
+
+// A step has to land in a single transaction, e.g. creating an account and initializing it
+struct Step {
+    instructions: Vec<SolInstruction>,
+    signers: Vec<Pubkey>,
+}
+
+/// Produces the ordered batches for the whole bootstrap. Sending them one after the other leaves
+/// the program fully initialized.
+pub fn bootstrap_batches(accounts: &BootstrapAccounts, state_params: &StateParams, rent: &Rent) -> Vec<BootstrapBatch> {
+    let mut setup = vec![
+        create_program_account(accounts, &accounts.system_state, size_of::<SystemState>(), rent),
+        create_program_account(accounts, &accounts.staking_state, size_of::<StakingState>(), rent),
+        create_program_account(accounts, &accounts.arb_state, size_of::<ArbState>(), rent),
+        create_program_account(accounts, &accounts.price_history, size_of::<PriceHistory>(), rent),
+
+        create_mint(accounts, &accounts.mata_mint, &Pda::mata_mint_authority(&accounts.system_state).0, MATA_DECIMALS, rent),
+        create_mint(accounts, &accounts.lucra_mint, &Pda::lucra_mint_authority(&accounts.system_state).0, LUCRA_DECIMALS, rent),
+        create_mint(accounts, &accounts.reward_mint, &Pda::reward_mint_authority(&accounts.system_state).0, REWARD_DECIMALS, rent),
+        create_mint(accounts, &accounts.staked_lucra_mint, &Pda::stake_mint_authority(&accounts.staking_state).0, STAKED_LUCRA_DECIMALS, rent),
+    ];
+    setup.append(&mut vault_steps(accounts, rent));
+    setup.push(create_vault(accounts, &accounts.reward_account, &accounts.reward_mint, &accounts.payer, rent));
+
+    let mut batches = pack_steps(&accounts.payer, setup);
+    batches.push(BootstrapBatch {
+        instructions: vec![initialize_instruction(accounts, state_params)],
+        signers: vec![accounts.payer, CREATOR_AUTHORITY],
+    });
+    batches.push(BootstrapBatch {
+        instructions: vec![create_price_history(&accounts.price_history)],
+        signers: vec![accounts.payer, CREATOR_AUTHORITY],
+    });
+    batches.push(BootstrapBatch {
+        instructions: vec![first_price_update(accounts)],
+        signers: vec![accounts.payer],
+    });
+
+    batches
+}
+
+/// Vaults in the order Initialize verifies them, paired with the mint and PDA owner they need
+pub fn vault_owners(accounts: &BootstrapAccounts) -> Vec<(Pubkey, Pubkey, Pubkey)> {
+    vec![
+        (accounts.msol_vault, accounts.msol_mint, Pda::msol_vault_authority(&accounts.system_state).0),
+        (accounts.arb_coffer, accounts.msol_mint, Pda::arb_coffer_authority(&accounts.system_state).0),
+        (accounts.rewards_vault, accounts.msol_mint, Pda::rewards_vault_authority(&accounts.system_state).0),
+        (accounts.arb_fund, accounts.msol_mint, Pda::arb_fund_authority(&accounts.arb_state).0),
+        (accounts.wsol_holding_vault, wsol::id(), Pda::wsol_holding_vault_authority(&accounts.arb_state).0),
+        (accounts.mata_holding_vault, accounts.mata_mint, Pda::mata_holding_vault_authority(&accounts.arb_state).0),
+        (accounts.lucra_holding_vault, accounts.lucra_mint, Pda::lucra_holding_vault_authority(&accounts.arb_state).0),
+    ]
+}
+
+fn vault_steps(accounts: &BootstrapAccounts, rent: &Rent) -> Vec<Step> {
+    vault_owners(accounts)
+        .iter()
+        .map(|(vault, mint, owner)| create_vault(accounts, vault, mint, owner, rent))
+        .collect()
+}
+
+fn create_program_account(accounts: &BootstrapAccounts, account: &Pubkey, space: usize, rent: &Rent) -> Step {
+    Step {
+        instructions: vec![
+            system_instruction::create_account(&accounts.payer, account, rent.minimum_balance(space), space as u64, &id()),
+        ],
+        signers: vec![*account],
+    }
+}
+
+fn create_mint(accounts: &BootstrapAccounts, mint: &Pubkey, mint_authority: &Pubkey, decimals: u8, rent: &Rent) -> Step {
+    let space = spl_token::state::Mint::LEN;
+
+    Step {
+        instructions: vec![
+            system_instruction::create_account(&accounts.payer, mint, rent.minimum_balance(space), space as u64, &spl_token::id()),
+            // Initialize rejects mints with a freeze authority
+            spl_token::instruction::initialize_mint(&spl_token::id(), mint, mint_authority, None, decimals).unwrap(),
+        ],
+        signers: vec![*mint],
+    }
+}
+
+fn create_vault(accounts: &BootstrapAccounts, vault: &Pubkey, mint: &Pubkey, owner: &Pubkey, rent: &Rent) -> Step {
+    let space = spl_token::state::Account::LEN;
+
+    Step {
+        instructions: vec![
+            system_instruction::create_account(&accounts.payer, vault, rent.minimum_balance(space), space as u64, &spl_token::id()),
+            spl_token::instruction::initialize_account(&spl_token::id(), vault, mint, owner).unwrap(),
+        ],
+        signers: vec![*vault],
+    }
+}
+
+fn initialize_instruction(accounts: &BootstrapAccounts, state_params: &StateParams) -> SolInstruction {
+    initialize(
+        &accounts.marinade_state,
+        &accounts.system_state,
+        &accounts.staking_state,
+        &accounts.arb_state,
+        &accounts.mata_mint,
+        &accounts.lucra_mint,
+        &accounts.reward_mint,
+        &accounts.staked_lucra_mint,
+        &accounts.msol_vault,
+        &accounts.arb_coffer,
+        &accounts.rewards_vault,
+        &accounts.arb_fund,
+        &accounts.wsol_holding_vault,
+        &accounts.mata_holding_vault,
+        &accounts.lucra_holding_vault,
+        state_params.min_deposit,
+        state_params.collateral_requirement,
+        state_params.epoch,
+        state_params.loans_enabled,
+        state_params.staking_enabled,
+        state_params.arbitrage_enabled,
+        state_params.peg_check_enabled,
+        state_params.max_amount_of_lucra_to_mint,
+        state_params.daily_arb_limit,
+        state_params.maximum_outstanding_mata,
+        state_params.lcp,
+        state_params.min_loan_amount_mata,
+        state_params.msol_haircut_bps,
+        state_params.peg_lower_bound,
+        state_params.peg_upper_bound,
+        state_params.penalty_band_width,
+        state_params.penalty_multiplier_per_band,
+        state_params.max_oracle_staleness_slots,
+        state_params.max_oracle_deviation_bps,
+        &accounts.guardian,
+    )
+}
+
+fn first_price_update(accounts: &BootstrapAccounts) -> SolInstruction {
+    update_price_history(
+        &accounts.system_state,
+        &accounts.price_history,
+        &SOL_USDC_ORACLE,
+        &SOL_USDT_ORACLE,
+        &LUCRA_SOL_ORACLE,
+        &SOL_MATA_ORACLE,
+        &accounts.reward_account,
+        &accounts.reward_mint,
+        &accounts.arb_state,
+    )
+}
+
+// Greedily fills each transaction with as many whole steps as fit
+fn pack_steps(payer: &Pubkey, steps: Vec<Step>) -> Vec<BootstrapBatch> {
+    let mut batches = vec![];
+    let mut current = BootstrapBatch::default();
+
+    for mut step in steps {
+        let mut candidate = current.clone();
+        candidate.instructions.append(&mut step.instructions.clone());
+        candidate.signers.append(&mut step.signers.clone());
+
+        if transaction_size(payer, &candidate.instructions) <= MAX_TRANSACTION_SIZE {
+            current = candidate;
+        } else {
+            batches.push(current);
+            current = BootstrapBatch::default();
+            current.instructions.append(&mut step.instructions);
+            current.signers.append(&mut step.signers);
+        }
+    }
+    if !current.instructions.is_empty() {
+        batches.push(current);
+    }
+
+    for batch in batches.iter_mut() {
+        batch.signers.insert(0, *payer);
+    }
+
+    batches
+}
+
+fn compact_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+/// Size of the signed legacy transaction that would carry these instructions
+pub fn transaction_size(payer: &Pubkey, instructions: &[SolInstruction]) -> usize {
+    let mut keys = HashSet::new();
+    let mut signers = HashSet::new();
+    keys.insert(*payer);
+    signers.insert(*payer);
+
+    let mut instructions_size = 0;
+    for instruction in instructions {
+        keys.insert(instruction.program_id);
+        for account in instruction.accounts.iter() {
+            keys.insert(account.pubkey);
+            if account.is_signer {
+                signers.insert(account.pubkey);
+            }
+        }
+
+        instructions_size += 1
+            + compact_len(instruction.accounts.len()) + instruction.accounts.len()
+            + compact_len(instruction.data.len()) + instruction.data.len();
+    }
+
+    compact_len(signers.len()) + signers.len() * 64
+        + 3
+        + compact_len(keys.len()) + keys.len() * 32
+        + 32
+        + compact_len(instructions.len()) + instructions_size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::constants::{
+        DEFAULT_MSOL_HAIRCUT_BPS, DEFAULT_ORACLE_MAX_DEVIATION_BPS, DEFAULT_PEG_LOWER_BOUND, DEFAULT_PEG_UPPER_BOUND,
+        DEFAULT_PENALTY_BAND_WIDTH, DEFAULT_PENALTY_MULTIPLIER_PER_BAND, ORACLE_PRICE_MAX_SLOTS, PRICE_HISTORY_ID,
+    };
+    use crate::units::{CollateralRatio, Lamports, Mata, Percent};
+
+    fn bootstrap_accounts() -> BootstrapAccounts {
+        BootstrapAccounts {
+            payer: Pubkey::new_unique(),
+            marinade_state: Pubkey::new_unique(),
+            msol_mint: Pubkey::new_unique(),
+            guardian: Pubkey::new_unique(),
+            system_state: Pubkey::new_unique(),
+            staking_state: Pubkey::new_unique(),
+            arb_state: Pubkey::new_unique(),
+            price_history: PRICE_HISTORY_ID,
+            mata_mint: Pubkey::new_unique(),
+            lucra_mint: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            staked_lucra_mint: Pubkey::new_unique(),
+            msol_vault: Pubkey::new_unique(),
+            arb_coffer: Pubkey::new_unique(),
+            rewards_vault: Pubkey::new_unique(),
+            arb_fund: Pubkey::new_unique(),
+            wsol_holding_vault: Pubkey::new_unique(),
+            mata_holding_vault: Pubkey::new_unique(),
+            lucra_holding_vault: Pubkey::new_unique(),
+            reward_account: Pubkey::new_unique(),
+        }
+    }
+
+    fn state_params() -> StateParams {
+        StateParams {
+            min_deposit: Lamports(0),
+            collateral_requirement: CollateralRatio(150),
+            epoch: 604_800,
+            loans_enabled: true,
+            staking_enabled: true,
+            arbitrage_enabled: true,
+            peg_check_enabled: true,
+            max_amount_of_lucra_to_mint: 0,
+            daily_arb_limit: 0,
+            maximum_outstanding_mata: Mata::MAX,
+            lcp: Percent(0),
+            min_loan_amount_mata: Mata(0),
+            msol_haircut_bps: DEFAULT_MSOL_HAIRCUT_BPS,
+            peg_lower_bound: DEFAULT_PEG_LOWER_BOUND,
+            peg_upper_bound: DEFAULT_PEG_UPPER_BOUND,
+            penalty_band_width: DEFAULT_PENALTY_BAND_WIDTH,
+            penalty_multiplier_per_band: DEFAULT_PENALTY_MULTIPLIER_PER_BAND,
+            max_oracle_staleness_slots: ORACLE_PRICE_MAX_SLOTS,
+            max_oracle_deviation_bps: DEFAULT_ORACLE_MAX_DEVIATION_BPS,
+        }
+    }
+
+    fn position(batches: &[BootstrapBatch], predicate: impl Fn(&SolInstruction) -> bool) -> (usize, usize) {
+        for (batch_index, batch) in batches.iter().enumerate() {
+            if let Some(index) = batch.instructions.iter().position(&predicate) {
+                return (batch_index, index);
+            }
+        }
+        panic!("instruction not found");
+    }
+
+    #[test]
+    fn test_batches_fit_in_a_transaction() {
+        let accounts = bootstrap_accounts();
+        let batches = bootstrap_batches(&accounts, &state_params(), &Rent::default());
+
+        for batch in batches.iter() {
+            assert!(transaction_size(&accounts.payer, &batch.instructions) <= MAX_TRANSACTION_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_initialize_runs_after_every_account_is_created() {
+        let accounts = bootstrap_accounts();
+        let batches = bootstrap_batches(&accounts, &state_params(), &Rent::default());
+        let initialize_batch = batches.len() - 3;
+
+        assert_eq!(batches[initialize_batch].instructions[0].program_id, id());
+        assert_eq!(batches[initialize_batch].instructions[0].accounts.len(), 16);
+        for batch in batches[..initialize_batch].iter() {
+            assert!(batch.instructions.iter().all(|instruction| instruction.program_id != id()));
+        }
+        assert_eq!(batches[initialize_batch + 1].instructions[0].accounts[1].pubkey, PRICE_HISTORY_ID);
+    }
+
+    #[test]
+    fn test_the_first_price_update_comes_last() {
+        let accounts = bootstrap_accounts();
+        let batches = bootstrap_batches(&accounts, &state_params(), &Rent::default());
+        let update = &batches.last().unwrap().instructions[0];
+
+        assert_eq!(update.program_id, id());
+        assert_eq!(update.accounts[1].pubkey, PRICE_HISTORY_ID);
+        assert_eq!(update.accounts[6].pubkey, accounts.reward_account);
+        assert_eq!(batches.last().unwrap().signers, vec![accounts.payer]);
+
+        // The reward account it pays is created with the vaults, after the reward mint
+        let mint_initialized = position(&batches, |instruction| {
+            instruction.program_id == spl_token::id() && instruction.accounts[0].pubkey == accounts.reward_mint
+        });
+        let account_initialized = position(&batches, |instruction| {
+            instruction.program_id == spl_token::id() && instruction.accounts[0].pubkey == accounts.reward_account
+        });
+        assert!(mint_initialized < account_initialized);
+    }
+
+    #[test]
+    fn test_create_and_init_share_a_transaction() {
+        let accounts = bootstrap_accounts();
+        let batches = bootstrap_batches(&accounts, &state_params(), &Rent::default());
+
+        for (vault, _, _) in vault_owners(&accounts) {
+            let created = position(&batches, |instruction| {
+                instruction.program_id == solana_program::system_program::id() && instruction.accounts[1].pubkey == vault
+            });
+            let initialized = position(&batches, |instruction| {
+                instruction.program_id == spl_token::id() && instruction.accounts[0].pubkey == vault
+            });
+
+            assert_eq!(created.0, initialized.0);
+            assert_eq!(created.1 + 1, initialized.1);
+        }
+    }
+
+    #[test]
+    fn test_mints_exist_before_their_vaults() {
+        let accounts = bootstrap_accounts();
+        let batches = bootstrap_batches(&accounts, &state_params(), &Rent::default());
+
+        for (mint, vault) in [(accounts.mata_mint, accounts.mata_holding_vault), (accounts.lucra_mint, accounts.lucra_holding_vault)] {
+            let mint_initialized = position(&batches, |instruction| {
+                instruction.program_id == spl_token::id() && instruction.accounts[0].pubkey == mint
+            });
+            let vault_initialized = position(&batches, |instruction| {
+                instruction.program_id == spl_token::id() && instruction.accounts[0].pubkey == vault
+            });
+
+            assert!(mint_initialized < vault_initialized);
+        }
+    }
+
+    #[test]
+    fn test_every_new_account_signs_its_batch() {
+        let accounts = bootstrap_accounts();
+        let batches = bootstrap_batches(&accounts, &state_params(), &Rent::default());
+
+        for batch in batches.iter() {
+            assert_eq!(batch.signers[0], accounts.payer);
+            for instruction in batch.instructions.iter() {
+                if instruction.program_id == solana_program::system_program::id() {
+                    assert!(batch.signers.contains(&instruction.accounts[1].pubkey));
+                }
+            }
+        }
+    }
+}
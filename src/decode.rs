@@ -0,0 +1,370 @@
+//! Typed reads of the program's accounts from raw RPC account data.
+//!
+//! Each decoder checks the meta data the same way `load_checked` does on chain, then copies the account out,
+//! so nothing here needs an `AccountInfo` or cares how the buffer is aligned.
+
+use std::mem::size_of;
+
+use rust_decimal::Decimal;
+use solana_program::program_pack::Pack;
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        account_layout::{read_meta_data, RESERVED_LAYOUT_VERSION},
+        collateral::calc_loan_collateral_value,
+        constants::LAMPORTS_PER_MATA,
+        loan_history::is_supported_loan_version,
+    },
+    lucra_core::interest::LOAN_INTEREST_VERSION,
+    state::{
+        staking::{PendingWithdrawal, StakeBalance, StakingState},
+        ArbState,
+        DataType,
+        Event,
+        EventQueue,
+        KeeperRegistry,
+        LoanRegistry,
+        MataLoan,
+        PriceHistory,
+        PriceHistoryHeader,
+        PriceHistoryPage,
+        SystemState,
+        PAGED_PRICE_HISTORY_VERSION,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::Decode);
+
+/// An account type that can be read straight out of its account data
+pub trait DecodeAccount: bytemuck::Pod {
+    const DATA_TYPE: DataType;
+    /// Meta data version of the current layout. Accounts that carry a reserved region are only on it once
+    /// MigrateAccountLayout has run, until then their data is too short to decode.
+    const VERSION: u8;
+
+    /// Types whose version changed without their layout changing read the older versions as well
+    fn is_supported_version(version: u8) -> bool {
+        version == Self::VERSION
+    }
+
+    fn try_from_bytes(data: &[u8]) -> LucraResult<Self> {
+        let meta_data = read_meta_data(data)?;
+        check!(meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(meta_data.data_type == Self::DATA_TYPE as u8, LucraErrorCode::InvalidAccountInput)?;
+        check!(Self::is_supported_version(meta_data.version), LucraErrorCode::InvalidAccountInput)?;
+
+        let bytes = data
+            .get(..size_of::<Self>())
+            .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+
+        Ok(bytemuck::pod_read_unaligned(bytes))
+    }
+}
+
+impl DecodeAccount for SystemState {
+    const DATA_TYPE: DataType = DataType::SystemState;
+    const VERSION: u8 = RESERVED_LAYOUT_VERSION;
+}
+
+impl DecodeAccount for ArbState {
+    const DATA_TYPE: DataType = DataType::ArbState;
+    const VERSION: u8 = RESERVED_LAYOUT_VERSION;
+}
+
+impl DecodeAccount for StakingState {
+    const DATA_TYPE: DataType = DataType::StakingState;
+    const VERSION: u8 = 0;
+}
+
+/// A loan opened before the collateral history or interest were kept has the same layout with them zeroed
+impl DecodeAccount for MataLoan {
+    const DATA_TYPE: DataType = DataType::Loan;
+    const VERSION: u8 = LOAN_INTEREST_VERSION;
+
+    fn is_supported_version(version: u8) -> bool {
+        is_supported_loan_version(version)
+    }
+}
+
+impl DecodeAccount for StakeBalance {
+    const DATA_TYPE: DataType = DataType::StakeBalance;
+    const VERSION: u8 = RESERVED_LAYOUT_VERSION;
+}
+
+impl DecodeAccount for PendingWithdrawal {
+    const DATA_TYPE: DataType = DataType::PendingWithdrawal;
+    const VERSION: u8 = 0;
+}
+
+/// Same layout on both versions, once it's paged its older days are read from the pages
+impl DecodeAccount for PriceHistory {
+    const DATA_TYPE: DataType = DataType::PriceHistory;
+    const VERSION: u8 = PAGED_PRICE_HISTORY_VERSION;
+}
+
+impl DecodeAccount for PriceHistoryHeader {
+    const DATA_TYPE: DataType = DataType::PriceHistoryHeader;
+    const VERSION: u8 = PAGED_PRICE_HISTORY_VERSION;
+}
+
+impl DecodeAccount for PriceHistoryPage {
+    const DATA_TYPE: DataType = DataType::PriceHistoryPage;
+    const VERSION: u8 = PAGED_PRICE_HISTORY_VERSION;
+}
+
+impl DecodeAccount for EventQueue {
+    const DATA_TYPE: DataType = DataType::EventQueue;
+    const VERSION: u8 = 0;
+}
+
+/// Clients read `next_loan_index` off it to derive the address of the user's next loan
+impl DecodeAccount for LoanRegistry {
+    const DATA_TYPE: DataType = DataType::LoanRegistry;
+    const VERSION: u8 = 0;
+}
+
+/// Keeper bots read it to check they're registered and what they're paid
+impl DecodeAccount for KeeperRegistry {
+    const DATA_TYPE: DataType = DataType::KeeperRegistry;
+    const VERSION: u8 = 0;
+}
+
+/// Events of the event queue in `data` after the `last_seen` sequence, oldest first, and how many were written
+/// over before they could be read. Keep the sequence of the last event returned for the next read.
+pub fn read_events(data: &[u8], last_seen: u64) -> LucraResult<(Vec<Event>, u64)> {
+    let queue = Box::new(EventQueue::try_from_bytes(data)?);
+
+    Ok((queue.events_after(last_seen).copied().collect(), queue.missed_after(last_seen)))
+}
+
+impl MataLoan {
+    /// Collateral value over the debt, as a multiple to compare with `CollateralRatio::to_decimal`. Values the
+    /// loan the way BorrowMore and LiquidateLoan do: locked stake counts at the value it was locked at, so the
+    /// lucra price doesn't come into it, and `collateral_factor` is the msol haircut. None for a loan with
+    /// no debt.
+    pub fn health_ratio(&self, sol_price: Decimal, collateral_factor: Decimal) -> Option<Decimal> {
+        if self.loan_amount == 0 {
+            return None;
+        }
+
+        let collateral_value = calc_loan_collateral_value(self, sol_price, collateral_factor).ok()?;
+        let debt = Decimal::from(self.loan_amount).checked_div(LAMPORTS_PER_MATA)?;
+
+        collateral_value.checked_div(debt)
+    }
+}
+
+impl StakeBalance {
+    /// Lucra WithdrawStake would release, read from the data of the `balances.deposit_vault` token account.
+    /// Staked and pending lucra have to go through unstaking first.
+    pub fn withdrawable(&self, deposit_vault_data: &[u8]) -> LucraResult<u64> {
+        if self.closed {
+            return Ok(0);
+        }
+
+        Ok(Account::unpack(deposit_vault_data)?.amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use rust_decimal_macros::dec;
+    use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+    use spl_token::state::AccountState;
+    use crate::{
+        helpers::loan_history::{initial_collateral, LOAN_HISTORY_VERSION},
+        state::{EventKind, MetaData, EVENT_QUEUE_LENGTH},
+        units::Bps,
+    };
+
+    fn encode<T: DecodeAccount>(mut account: T, set_meta_data: impl Fn(&mut T, MetaData)) -> Vec<u8> {
+        set_meta_data(&mut account, MetaData::new(T::DATA_TYPE, T::VERSION, true));
+        bytemuck::bytes_of(&account).to_vec()
+    }
+
+    fn assert_invalid<T: DecodeAccount>(data: &[u8]) {
+        assert!(matches!(
+            T::try_from_bytes(data).unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::InvalidAccountInput,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_decoders_round_trip() {
+        let mut loan = MataLoan::zeroed();
+        loan.owner = Pubkey::new_unique();
+        loan.loan_amount = 100_000_000;
+        let data = encode(loan, |loan, meta_data| loan.meta_data = meta_data);
+        let decoded = MataLoan::try_from_bytes(&data).unwrap();
+        assert_eq!(decoded.owner, loan.owner);
+        assert_eq!(decoded.loan_amount, 100_000_000);
+
+        let mut stake_balance = StakeBalance::zeroed();
+        stake_balance.owner = Pubkey::new_unique();
+        stake_balance.balances.deposit_vault = Pubkey::new_unique();
+        let data = encode(stake_balance, |stake_balance, meta_data| stake_balance.meta_data = meta_data);
+        let decoded = StakeBalance::try_from_bytes(&data).unwrap();
+        assert_eq!(decoded.owner, stake_balance.owner);
+        assert_eq!(decoded.balances.deposit_vault, stake_balance.balances.deposit_vault);
+
+        let mut pending_withdrawal = PendingWithdrawal::zeroed();
+        pending_withdrawal.lucra = 42;
+        let data = encode(pending_withdrawal, |pending_withdrawal, meta_data| pending_withdrawal.meta_data = meta_data);
+        assert_eq!(PendingWithdrawal::try_from_bytes(&data).unwrap().lucra, 42);
+
+        let data = encode(SystemState::zeroed(), |system_state, meta_data| system_state.meta_data = meta_data);
+        assert!(SystemState::try_from_bytes(&data).is_ok());
+        let data = encode(ArbState::zeroed(), |arb_state, meta_data| arb_state.meta_data = meta_data);
+        assert!(ArbState::try_from_bytes(&data).is_ok());
+        let data = encode(StakingState::zeroed(), |staking_state, meta_data| staking_state.meta_data = meta_data);
+        assert!(StakingState::try_from_bytes(&data).is_ok());
+        let data = encode(PriceHistory::zeroed(), |price_history, meta_data| price_history.meta_data = meta_data);
+        assert!(PriceHistory::try_from_bytes(&data).is_ok());
+
+        let mut page = PriceHistoryPage::zeroed();
+        page.page_index = 3;
+        page.len = 1;
+        page.prices[0].date = 86_400;
+        let data = encode(page, |page, meta_data| page.meta_data = meta_data);
+        let decoded = PriceHistoryPage::try_from_bytes(&data).unwrap();
+        assert_eq!(decoded.page_index, 3);
+        assert_eq!(decoded.prices()[0].date, 86_400);
+        let data = encode(PriceHistoryHeader::zeroed(), |header, meta_data| header.meta_data = meta_data);
+        assert!(PriceHistoryHeader::try_from_bytes(&data).is_ok());
+
+        let keeper = Pubkey::new_unique();
+        let mut registry = KeeperRegistry::zeroed();
+        registry.init(&Pubkey::new_unique());
+        registry.register(&keeper, Bps(15_000)).unwrap();
+        let data = encode(registry, |registry, meta_data| registry.meta_data = meta_data);
+        assert_eq!(KeeperRegistry::try_from_bytes(&data).unwrap().multiplier_of(&keeper), Some(Bps(15_000)));
+    }
+
+    #[test]
+    fn test_unaligned_and_oversized_buffers_decode() {
+        let mut loan = MataLoan::zeroed();
+        loan.loan_amount = 7;
+        let mut data = vec![0];
+        data.extend(encode(loan, |loan, meta_data| loan.meta_data = meta_data));
+        data.push(0);
+
+        assert_eq!(MataLoan::try_from_bytes(&data[1..]).unwrap().loan_amount, 7);
+    }
+
+    #[test]
+    fn test_wrong_data_type_is_rejected() {
+        let stake_balance = encode(StakeBalance::zeroed(), |stake_balance, meta_data| stake_balance.meta_data = meta_data);
+        assert_invalid::<MataLoan>(&stake_balance);
+
+        let staking_state = encode(StakingState::zeroed(), |staking_state, meta_data| staking_state.meta_data = meta_data);
+        assert_invalid::<PendingWithdrawal>(&staking_state);
+    }
+
+    #[test]
+    fn test_uninitialized_legacy_and_short_buffers_are_rejected() {
+        let mut loan = MataLoan::zeroed();
+        loan.meta_data = MetaData::new(DataType::Loan, RESERVED_LAYOUT_VERSION, false);
+        assert_invalid::<MataLoan>(bytemuck::bytes_of(&loan));
+
+        // Not migrated yet
+        loan.meta_data = MetaData::new(DataType::Loan, 0, true);
+        assert_invalid::<MataLoan>(bytemuck::bytes_of(&loan));
+
+        loan.meta_data = MetaData::new(DataType::Loan, RESERVED_LAYOUT_VERSION, true);
+        let data = bytemuck::bytes_of(&loan);
+        assert_invalid::<MataLoan>(&data[..data.len() - 1]);
+        assert_invalid::<MataLoan>(&[]);
+    }
+
+    #[test]
+    fn test_loans_decode_with_and_without_collateral_history() {
+        let mut loan = MataLoan::zeroed();
+        loan.loan_amount = 7;
+        loan.meta_data = MetaData::new(DataType::Loan, RESERVED_LAYOUT_VERSION, true);
+        let decoded = MataLoan::try_from_bytes(bytemuck::bytes_of(&loan)).unwrap();
+        assert_eq!(decoded.loan_amount, 7);
+        assert_eq!(initial_collateral(&decoded), None);
+
+        loan.meta_data = MetaData::new(DataType::Loan, LOAN_HISTORY_VERSION, true);
+        loan.initial_collateral = 5_000;
+        let decoded = MataLoan::try_from_bytes(bytemuck::bytes_of(&loan)).unwrap();
+        assert_eq!(initial_collateral(&decoded), Some(5_000));
+
+        loan.meta_data = MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true);
+        assert_eq!(MataLoan::try_from_bytes(bytemuck::bytes_of(&loan)).unwrap().loan_amount, 7);
+
+        // A version the program doesn't know yet isn't guessed at
+        loan.meta_data = MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION + 1, true);
+        assert_invalid::<MataLoan>(bytemuck::bytes_of(&loan));
+    }
+
+    #[test]
+    fn test_events_are_read_from_account_data() {
+        let mut queue = Box::new(EventQueue::zeroed());
+        queue.init(&Pubkey::new_unique());
+        let loan = Pubkey::new_unique();
+        for slot in 0..EVENT_QUEUE_LENGTH as u64 + 2 {
+            queue.push(EventKind::CloseMataLoan, &loan, [slot, 0], slot);
+        }
+        let data = bytemuck::bytes_of(queue.as_ref()).to_vec();
+
+        // The first two events were written over before the reader got to them
+        let (events, missed) = read_events(&data, 0).unwrap();
+        assert_eq!(missed, 2);
+        assert_eq!(events.len(), EVENT_QUEUE_LENGTH);
+        assert_eq!(events[0].sequence, 3);
+        assert_eq!(events[0].kind(), Some(EventKind::CloseMataLoan));
+        assert_eq!(events[0].principal, loan);
+
+        let last_seen = events.last().unwrap().sequence;
+        let (events, missed) = read_events(&data, last_seen).unwrap();
+        assert!(events.is_empty());
+        assert_eq!(missed, 0);
+
+        assert_invalid::<EventQueue>(&data[..data.len() - 1]);
+    }
+
+    #[test]
+    fn test_health_ratio() {
+        let mut loan = MataLoan::zeroed();
+        loan.sol_collateral_amount = 10 * LAMPORTS_PER_SOL;
+        loan.loan_amount = 100_000_000;
+
+        // $200 of collateral backs 100 mata twice over, the haircut only applies to the sol side
+        assert_eq!(loan.health_ratio(dec!(20), Decimal::ONE), Some(dec!(2)));
+        assert_eq!(loan.health_ratio(dec!(20), dec!(0.5)), Some(dec!(1)));
+
+        loan.loan_amount = 0;
+        assert_eq!(loan.health_ratio(dec!(20), Decimal::ONE), None);
+    }
+
+    #[test]
+    fn test_withdrawable_is_the_deposit_vault_balance() {
+        let mut data = vec![0; Account::LEN];
+        let deposit_vault = Account {
+            amount: 1_000,
+            state: AccountState::Initialized,
+            ..Account::default()
+        };
+        Account::pack(deposit_vault, &mut data).unwrap();
+
+        let mut stake_balance = StakeBalance::zeroed();
+        assert_eq!(stake_balance.withdrawable(&data).unwrap(), 1_000);
+        assert!(stake_balance.withdrawable(&data[1..]).is_err());
+
+        stake_balance.closed = true;
+        assert_eq!(stake_balance.withdrawable(&data).unwrap(), 0);
+    }
+}
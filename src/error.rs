@@ -1,6 +1,6 @@
 use bytemuck::Contiguous;
 use num_enum::IntoPrimitive;
-use solana_program::program_error::ProgramError;
+use solana_program::{msg, program_error::ProgramError};
 use thiserror::Error;
 
 pub type LucraResult<T = ()> = Result<T, LucraError>;
@@ -9,52 +9,140 @@ pub type LucraResult<T = ()> = Result<T, LucraError>;
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
 pub enum SourceFileId {
     Account = 0,
+    AccountLayout,
+    AcceptAuthority,
     AddCollateral,
+    AddCollateralType,
+    AddTransferDestination,
+    ArbResult,
     ArbState,
+    ArbTrigger,
+    ArbWindow,
     BeginCreateMataLoan,
+    BorrowMore,
     BuyBurnForArb,
+    CancelUnstake,
     ClaimReward,
     CleanUpArb,
     CloseMataLoan,
+    CloseRewardProgram,
+    CloseStakeBalance,
+    CloseStakingAccount,
+    CreateEventQueue,
     CreateOracle,
+    CreatePenaltyHistory,
     CreatePriceHistory,
+    CreatePriceHistoryPage,
+    CreateRevenueLedger,
+    CreateSolVault,
     CreateStakingAccount,
     CreateStakeBalance,
     CofferArb,
+    Collateral,
+    CollateralRegistry,
+    CollateralTypes,
+    ComputeTvl,
+    CoreInterest,
+    CorePenalty,
+    CoreQuote,
+    CorrectPriceHistory,
+    DaoAuthority,
     Decimal,
+    Decode,
     DepositStake,
     DeterminePenalty,
     DropReward,
     EndUnstake,
+    EventQueue,
+    Events,
+    FreezeStakingAccount,
+    GlobalSettlement,
     HarvestPenalty,
     Initialize,
+    KeeperRegistry,
+    Keepers,
+    LiquidateLoan,
+    Liquidation,
+    LoanAddress,
+    LoanHistory,
+    LoanRegistry,
     Loans,
+    LpCollateral,
     LucraMataArb,
+    MigrateAccountLayout,
+    Marinade,
+    MataSupply,
     MataLucraArb,
     Math,
+    MigrateStakeBalance,
     MintFundsForArb,
     Oracle,
     OracleHelper,
+    PagedPriceHistory,
     PendingFunds,
+    PenaltyHistory,
+    PenaltyRebate,
     PendingWithdrawal,
+    PriceCorrection,
     PriceHistory,
+    PriceHistoryPage,
+    ProposeAuthority,
+    Pyth,
+    QuoteMataLoan,
     Rate,
     Raydium,
+    RecomputePenalty,
+    ReclaimExcess,
+    RebalanceRewardsVault,
     RedeemRewardTokens,
+    RedeemRewardTokensForMsol,
+    RegisterKeeper,
+    RemoveKeeper,
+    RemoveTransferDestination,
+    RepayLoanPartial,
     Reward,
+    RevenueLedger,
+    RewardExpiry,
+    RewardIndex,
+    RewardSunset,
+    RewardTokens,
+    RollArbWindow,
+    RolloverLoan,
     SellFundsForArb,
+    SetCollateralType,
+    SetLiquidationParams,
+    SetLpCollateral,
+    SetMinDropAmount,
+    SetPause,
+    SetRewardMetadata,
+    SetRewardRedemptionDeadline,
+    SetStakingRecoveryKey,
+    Settlement,
+    SolVault,
     Spl,
     SplTokenSwap,
     Stake,
     StakeBalance,
     Staking,
+    StakingFreeze,
     StakingState,
     StartUnstake,
+    SweepExpiredReward,
     SystemState,
+    TransferAllowlist,
     TransferFunds,
+    TransferLoanOwnership,
+    TvlSnapshot,
+    UnfreezeStakingAccount,
+    UnstakeTimelock,
     UpdatePrice,
     UpdatePriceHistory,
+    UpdateHarvestParams,
     UpdateState,
+    Valuation,
+    Vaults,
+    Whirlpool,
+    WithdrawCollateral,
     WithdrawStake,
 }
 
@@ -62,55 +150,144 @@ impl std::fmt::Display for SourceFileId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SourceFileId::Account => write!(f, "src/helpers/account.rs"),
+            SourceFileId::AccountLayout => write!(f, "src/helpers/account_layout.rs"),
+            SourceFileId::ArbResult => write!(f, "src/helpers/arb_result.rs"),
+            SourceFileId::ArbTrigger => write!(f, "src/helpers/arb_trigger.rs"),
+            SourceFileId::ArbWindow => write!(f, "src/helpers/arb_window.rs"),
+            SourceFileId::Collateral => write!(f, "src/helpers/collateral.rs"),
+            SourceFileId::CollateralTypes => write!(f, "src/helpers/collateral_types.rs"),
+            SourceFileId::DaoAuthority => write!(f, "src/helpers/dao_authority.rs"),
+            SourceFileId::Events => write!(f, "src/helpers/events.rs"),
+            SourceFileId::Decode => write!(f, "src/decode.rs"),
+            SourceFileId::Marinade => write!(f, "src/helpers/marinade.rs"),
+            SourceFileId::Liquidation => write!(f, "src/helpers/liquidation.rs"),
+            SourceFileId::Keepers => write!(f, "src/helpers/keepers.rs"),
+            SourceFileId::LoanAddress => write!(f, "src/helpers/loan_address.rs"),
+            SourceFileId::LoanHistory => write!(f, "src/helpers/loan_history.rs"),
+            SourceFileId::LpCollateral => write!(f, "src/helpers/lp_collateral.rs"),
             SourceFileId::Math => write!(f, "src/helpers/math.rs"),
             SourceFileId::OracleHelper => write!(f, "src/helpers/oracle.rs"),
+            SourceFileId::PagedPriceHistory => write!(f, "src/helpers/paged_price_history.rs"),
+            SourceFileId::PenaltyRebate => write!(f, "src/helpers/penalty_rebate.rs"),
+            SourceFileId::Pyth => write!(f, "src/helpers/pyth.rs"),
             SourceFileId::Spl => write!(f, "src/helpers/spl.rs"),
             SourceFileId::SplTokenSwap => write!(f, "src/helpers/spl_token_swap.rs"),
             SourceFileId::Raydium => write!(f, "src/helpers/raydium.rs"),
-
+            SourceFileId::RewardExpiry => write!(f, "src/helpers/reward_expiry.rs"),
+            SourceFileId::RewardIndex => write!(f, "src/helpers/reward_index.rs"),
+            SourceFileId::RewardSunset => write!(f, "src/helpers/reward_sunset.rs"),
+            SourceFileId::RewardTokens => write!(f, "src/helpers/reward_tokens.rs"),
+            SourceFileId::Settlement => write!(f, "src/helpers/settlement.rs"),
+            SourceFileId::SolVault => write!(f, "src/helpers/sol_vault.rs"),
+            SourceFileId::StakingFreeze => write!(f, "src/helpers/staking_freeze.rs"),
+            SourceFileId::UnstakeTimelock => write!(f, "src/helpers/unstake_timelock.rs"),
+            SourceFileId::Valuation => write!(f, "src/helpers/valuation.rs"),
+            SourceFileId::Vaults => write!(f, "src/helpers/vaults.rs"),
+            SourceFileId::Whirlpool => write!(f, "src/helpers/whirlpool.rs"),
+
+            SourceFileId::CoreInterest => write!(f, "src/lucra_core/interest.rs"),
+            SourceFileId::CorePenalty => write!(f, "src/lucra_core/penalty.rs"),
+            SourceFileId::CoreQuote => write!(f, "src/lucra_core/quote.rs"),
+
+            SourceFileId::CollateralRegistry => write!(f, "src/state/collateral_registry.rs"),
+            SourceFileId::EventQueue => write!(f, "src/state/event_queue.rs"),
+            SourceFileId::KeeperRegistry => write!(f, "src/state/keeper_registry.rs"),
+            SourceFileId::LoanRegistry => write!(f, "src/state/loan_registry.rs"),
             SourceFileId::Loans => write!(f, "src/state/loans/mataloan.rs"),
+            SourceFileId::MataSupply => write!(f, "src/state/mata_supply.rs"),
             SourceFileId::Oracle => write!(f, "src/state/oracle.rs"),
             SourceFileId::PendingFunds => write!(f, "src/state/pendingfunds.rs"),
+            SourceFileId::PenaltyHistory => write!(f, "src/state/penalty_history.rs"),
+            SourceFileId::PriceCorrection => write!(f, "src/state/price_correction.rs"),
             SourceFileId::PriceHistory => write!(f, "src/state/pricehistory.rs"),
+            SourceFileId::PriceHistoryPage => write!(f, "src/state/price_history_page.rs"),
             SourceFileId::ArbState => write!(f, "src/state/arbitrage/arb_state.rs"),
             SourceFileId::PendingWithdrawal => write!(f, "src/state/staking/pendingwithdrawal.rs"),
             SourceFileId::Reward => write!(f, "src/state/staking/rewards/reward.rs"),
+            SourceFileId::RevenueLedger => write!(f, "src/state/revenue_ledger.rs"),
             SourceFileId::StakeBalance => write!(f, "src/state/staking/stakebalance.rs"),
             SourceFileId::Staking => write!(f, "src/state/staking/stakeaccount.rs"),
             SourceFileId::StakingState => write!(f, "src/state/staking/staking_state.rs"),
             SourceFileId::SystemState => write!(f, "src/state/system_state.rs"),
+            SourceFileId::TransferAllowlist => write!(f, "src/state/transfer_allowlist.rs"),
+            SourceFileId::TvlSnapshot => write!(f, "src/state/tvl_snapshot.rs"),
             
             SourceFileId::Decimal => write!(f, "src/math/decimal.rs"),
             SourceFileId::Rate => write!(f, "src/math/rate.rs"),
             
+            SourceFileId::AcceptAuthority => write!(f, "src/processor/process_accept_authority.rs"),
             SourceFileId::AddCollateral => write!(f, "src/processor/process_add_collateral.rs"),
+            SourceFileId::AddCollateralType => write!(f, "src/processor/process_add_collateral_type.rs"),
+            SourceFileId::AddTransferDestination => write!(f, "src/processor/process_add_transfer_destination.rs"),
             SourceFileId::BeginCreateMataLoan => write!(f, "src/processor/process_begin_create_mata_loan.rs"),
+            SourceFileId::BorrowMore => write!(f, "src/processor/process_borrow_more.rs"),
             SourceFileId::BuyBurnForArb => write!(f, "src/process/process_buy_burn_for_arb.rs"),
+            SourceFileId::CancelUnstake => write!(f, "src/processor/process_cancel_unstake.rs"),
             SourceFileId::LucraMataArb => write!(f, "src/processor/process_lucra_mata_arb.rs"),
             SourceFileId::MataLucraArb => write!(f, "src/processor/process_mata_lucra_arb.rs"),
+            SourceFileId::MigrateAccountLayout => write!(f, "src/processor/process_migrate_account_layout.rs"),
             SourceFileId::ClaimReward => write!(f, "src/processor/process_claim_reward.rs"),
             SourceFileId::CleanUpArb => write!(f, "src/processor/process_clean_up_arb.rs"),
             SourceFileId::CloseMataLoan => write!(f, "src/processor/process_close_mata_loan.rs"),
+            SourceFileId::CloseRewardProgram => write!(f, "src/processor/process_close_reward_program.rs"),
+            SourceFileId::CloseStakeBalance => write!(f, "src/processor/process_close_stake_balance.rs"),
+            SourceFileId::CloseStakingAccount => write!(f, "src/processor/process_close_staking_account.rs"),
+            SourceFileId::ComputeTvl => write!(f, "src/processor/process_compute_tvl.rs"),
+            SourceFileId::CorrectPriceHistory => write!(f, "src/processor/process_correct_price_history.rs"),
+            SourceFileId::CreateEventQueue => write!(f, "src/processor/process_create_event_queue.rs"),
             SourceFileId::CreateOracle => write!(f, "src/processor/process_create_oracle.rs"),
+            SourceFileId::CreatePenaltyHistory => write!(f, "src/processor/process_create_penalty_history.rs"),
             SourceFileId::CreatePriceHistory => write!(f, "src/processor/process_create_price_history.rs"),
+            SourceFileId::CreatePriceHistoryPage => write!(f, "src/processor/process_create_price_history_page.rs"),
+            SourceFileId::CreateRevenueLedger => write!(f, "src/processor/process_create_revenue_ledger.rs"),
+            SourceFileId::CreateSolVault => write!(f, "src/processor/process_create_sol_vault.rs"),
             SourceFileId::CreateStakeBalance => write!(f, "src/processor/process_create_stake_balance.rs"),
             SourceFileId::CreateStakingAccount => write!(f, "src/processor/process_create_staking_account.rs"),
             SourceFileId::DepositStake => write!(f, "src/processor/process_deposit_stake.rs"),
             SourceFileId::DeterminePenalty => write!(f, "src/processor/process_determine_penalty.rs"),
             SourceFileId::DropReward => write!(f, "src/processor/process_drop_reward.rs"),
             SourceFileId::EndUnstake => write!(f, "src/processor/process_end_unstake.rs"),
+            SourceFileId::FreezeStakingAccount => write!(f, "src/processor/process_freeze_staking_account.rs"),
+            SourceFileId::GlobalSettlement => write!(f, "src/processor/process_global_settlement.rs"),
             SourceFileId::HarvestPenalty => write!(f, "src/processor/process_harvest_penalty.rs"),
             SourceFileId::Initialize => write!(f, "src/processor/process_initialize.rs"),
+            SourceFileId::LiquidateLoan => write!(f, "src/processor/process_liquidate_loan.rs"),
             SourceFileId::CofferArb => write!(f, "src/processor/process_coffer_arb.rs"),
+            SourceFileId::MigrateStakeBalance => write!(f, "src/processor/process_migrate_stake_balance.rs"),
             SourceFileId::MintFundsForArb => write!(f, "src/processor/process_mint_funds_for_arb.rs"),
+            SourceFileId::ProposeAuthority => write!(f, "src/processor/process_propose_authority.rs"),
+            SourceFileId::QuoteMataLoan => write!(f, "src/processor/process_quote_mata_loan.rs"),
+            SourceFileId::RecomputePenalty => write!(f, "src/processor/process_recompute_penalty.rs"),
+            SourceFileId::ReclaimExcess => write!(f, "src/processor/process_reclaim_excess.rs"),
+            SourceFileId::RebalanceRewardsVault => write!(f, "src/processor/process_rebalance_rewards_vault.rs"),
             SourceFileId::RedeemRewardTokens => write!(f, "src/process/process_redeem_reward_tokens.rs"),
+            SourceFileId::RedeemRewardTokensForMsol => write!(f, "src/processor/process_redeem_reward_tokens_for_msol.rs"),
+            SourceFileId::RegisterKeeper => write!(f, "src/processor/process_register_keeper.rs"),
+            SourceFileId::RemoveKeeper => write!(f, "src/processor/process_remove_keeper.rs"),
+            SourceFileId::RemoveTransferDestination => write!(f, "src/processor/process_remove_transfer_destination.rs"),
+            SourceFileId::RepayLoanPartial => write!(f, "src/processor/process_repay_loan_partial.rs"),
+            SourceFileId::RollArbWindow => write!(f, "src/processor/process_roll_arb_window.rs"),
+            SourceFileId::RolloverLoan => write!(f, "src/processor/process_rollover_loan.rs"),
             SourceFileId::SellFundsForArb => write!(f, "src/processor/process_sell_funds_for_arb.rs"),
+            SourceFileId::SetLiquidationParams => write!(f, "src/processor/process_set_liquidation_params.rs"),
+            SourceFileId::SetCollateralType => write!(f, "src/processor/process_set_collateral_type.rs"),
+            SourceFileId::SetLpCollateral => write!(f, "src/processor/process_set_lp_collateral.rs"),
+            SourceFileId::SetMinDropAmount => write!(f, "src/processor/process_set_min_drop_amount.rs"),
+            SourceFileId::SetPause => write!(f, "src/processor/process_set_pause.rs"),
+            SourceFileId::SetRewardMetadata => write!(f, "src/processor/process_set_reward_metadata.rs"),
+            SourceFileId::SetRewardRedemptionDeadline => write!(f, "src/processor/process_set_reward_redemption_deadline.rs"),
+            SourceFileId::SetStakingRecoveryKey => write!(f, "src/processor/process_set_staking_recovery_key.rs"),
             SourceFileId::Stake => write!(f, "src/processor/process_stake.rs"),
             SourceFileId::StartUnstake => write!(f, "src/processor/process_start_unstake.rs"),
+            SourceFileId::SweepExpiredReward => write!(f, "src/processor/process_sweep_expired_reward.rs"),
             SourceFileId::TransferFunds => write!(f, "src/processor/process_transfer_funds.rs"),
+            SourceFileId::TransferLoanOwnership => write!(f, "src/processor/process_transfer_loan_ownership.rs"),
+            SourceFileId::UnfreezeStakingAccount => write!(f, "src/processor/process_unfreeze_staking_account.rs"),
             SourceFileId::UpdatePrice => write!(f, "src/processor/process_update_price.rs"),
             SourceFileId::UpdatePriceHistory => write!(f, "src/processor/process_update_price_history.rs"),
+            SourceFileId::UpdateHarvestParams => write!(f, "src/processor/process_update_harvest_params.rs"),
             SourceFileId::UpdateState => write!(f, "src/processor/process_update_state.rs"),
+            SourceFileId::WithdrawCollateral => write!(f, "src/processor/process_withdraw_collateral.rs"),
             SourceFileId::WithdrawStake => write!(f, "src/processor/process_withdraw_stake.rs"),
         }
     }
@@ -122,6 +299,8 @@ pub enum LucraError {
     ProgramError(#[from] ProgramError),
     #[error("{lucra_error_code}; {source_file_id}:{line}")]
     LucraErrorCode { lucra_error_code: LucraErrorCode, line: u32, source_file_id: SourceFileId },
+    #[error("INVARIANT {lucra_error_code}; {source_file_id}:{line}")]
+    Invariant { lucra_error_code: LucraErrorCode, line: u32, source_file_id: SourceFileId },
 }
 
 #[derive(Debug, Error, Clone, Copy, PartialEq, Eq, IntoPrimitive)]
@@ -240,6 +419,100 @@ pub enum LucraErrorCode {
     InvalidState,
 
     // 35
+    #[error("LucraErrorCode::MataBucketUnderflow")]
+    MataBucketUnderflow,
+
+    #[error("LucraErrorCode::SettlementActive")]
+    SettlementActive,
+
+    #[error("LucraErrorCode::SettlementNotActive")]
+    SettlementNotActive,
+
+    #[error("LucraErrorCode::MarinadeLiquidityInsufficient")]
+    MarinadeLiquidityInsufficient,
+
+    #[error("LucraErrorCode::StakingAccountFrozen")]
+    StakingAccountFrozen,
+
+    // 40
+    #[error("LucraErrorCode::UnexpectedBalanceDecrease")]
+    UnexpectedBalanceDecrease,
+
+    #[error("LucraErrorCode::MsolVaultShortfall")]
+    MsolVaultShortfall,
+
+    #[error("LucraErrorCode::OracleVersionNotSupported")]
+    OracleVersionNotSupported,
+
+    #[error("LucraErrorCode::RewardClaimExpired")]
+    RewardClaimExpired,
+
+    #[error("LucraErrorCode::RewardNotExpired")]
+    RewardNotExpired,
+
+    #[error("LucraErrorCode::InstantUnstakeTooLarge amount too large for instant unstake, choose msol or delayed")]
+    InstantUnstakeTooLarge,
+
+    #[error("LucraErrorCode::OpenOrdersNotInitialized")]
+    OpenOrdersNotInitialized,
+
+    #[error("LucraErrorCode::RewardRedemptionClosed the reward redemption deadline has passed")]
+    RewardRedemptionClosed,
+
+    #[error("LucraErrorCode::InvalidProgramAccount the program account isn't the expected executable program")]
+    InvalidProgramAccount,
+
+    #[error("LucraErrorCode::PenaltyNotHarvested the loan's pending penalty has to be harvested first")]
+    PenaltyNotHarvested,
+
+    #[error("LucraErrorCode::LoanNotLiquidatable the loan's collateral is still above the liquidation ratio")]
+    LoanNotLiquidatable,
+
+    #[error("LucraErrorCode::StakeBalanceNotEmpty the stake balance still holds lucra in one of its vaults")]
+    StakeBalanceNotEmpty,
+
+    #[error("LucraErrorCode::StakingAccountNotEmpty the staking account still has lucra staked or pledged to a loan")]
+    StakingAccountNotEmpty,
+
+    #[error("LucraErrorCode::GuardianCannotUnpause only the DAO authority can turn a paused feature back on")]
+    GuardianCannotUnpause,
+
+    #[error("LucraErrorCode::SlippageExceeded the swap returned less than the minimum the caller asked for")]
+    SlippageExceeded,
+
+    #[error("LucraErrorCode::PriceHistoryGap days the loan hasn't been charged for are missing from the price history passed")]
+    PriceHistoryGap,
+
+    #[error("LucraErrorCode::PriceHistoryPageFull the active price history page is full and the next one hasn't been created")]
+    PriceHistoryPageFull,
+
+    #[error("LucraErrorCode::TooFrequent the price history was updated less than 55 minutes ago")]
+    TooFrequent,
+
+    #[error("LucraErrorCode::KeeperRegistryFull the keeper registry already holds as many keepers as it can")]
+    KeeperRegistryFull,
+
+    #[error("LucraErrorCode::KeeperNotRegistered the key isn't a keeper in the keeper registry")]
+    KeeperNotRegistered,
+
+    #[error("LucraErrorCode::CrankingRestricted only registered keepers can run cranks while cranking is restricted")]
+    CrankingRestricted,
+
+    #[error("LucraErrorCode::NothingReceived a swap or deposit meant to pay into an account left its balance where it was")]
+    NothingReceived,
+
+    #[error("LucraErrorCode::StakingAccountLocked the stake left wouldn't cover the loans pledged against it, the most that can be unstaked is in the return data")]
+    StakingAccountLocked,
+
+    #[error("LucraErrorCode::CollateralRegistryFull the collateral registry already holds as many collateral types as it can")]
+    CollateralRegistryFull,
+
+    #[error("LucraErrorCode::TransferAllowlistFull the transfer allowlist already holds as many destinations as it can")]
+    TransferAllowlistFull,
+
+    #[error("LucraErrorCode::TransferDestinationNotActive the account isn't on the transfer allowlist or its delay hasn't passed yet")]
+    TransferDestinationNotActive,
+
     #[error("LucraErrorCode::Default Check the source code for more info")]
     Default = u32::MAX_VALUE,
 
@@ -252,10 +525,59 @@ impl From<LucraError> for ProgramError {
             LucraError::LucraErrorCode { lucra_error_code, line: _, source_file_id: _ } => {
                 ProgramError::Custom(lucra_error_code.into())
             }
+            LucraError::Invariant { lucra_error_code, line: _, source_file_id: _ } => {
+                invariant_code(lucra_error_code).map_or_else(|e| e, ProgramError::Custom)
+            }
         }
     }
 }
 
+// `check!` is for anything the caller can get wrong: accounts, amounts, timing. `invariant!` is for things
+// that can only fail when the program itself is wrong: counters underflowing, the books no longer matching a
+// vault, a CPI moving balances the wrong way. Invariant failures come back as
+// `INVARIANT_CODE_OFFSET + LucraErrorCode` and log a line like
+//
+//     INVARIANT file=src/state/mata_supply.rs line=79 code=MataBucketUnderflow
+//
+// Monitoring pages on the `INVARIANT ` log prefix or any custom error code in
+// `INVARIANT_CODE_OFFSET..INVARIANT_CODE_END`. Neither shows up for user error, so an alert always means the
+// protocol needs looking at. A code with no slot in the range, `Default`, comes back as `InvalidArgument` with
+// the log line still marking it.
+
+pub const INVARIANT_CODE_OFFSET: u32 = 0x1000_0000;
+pub const INVARIANT_CODE_END: u32 = 2 * INVARIANT_CODE_OFFSET;
+
+pub fn invariant_code(lucra_error_code: LucraErrorCode) -> Result<u32, ProgramError> {
+    match INVARIANT_CODE_OFFSET.checked_add(lucra_error_code.into()) {
+        Some(code) if is_invariant_code(code) => Ok(code),
+        _ => Err(ProgramError::InvalidArgument),
+    }
+}
+
+pub fn is_invariant_code(code: u32) -> bool {
+    (INVARIANT_CODE_OFFSET..INVARIANT_CODE_END).contains(&code)
+}
+
+#[cold]
+pub fn invariant_violation(lucra_error_code: LucraErrorCode, line: u32, source_file_id: SourceFileId) -> LucraError {
+    msg!("INVARIANT file={} line={} code={:?}", source_file_id, line, lucra_error_code);
+    LucraError::Invariant { lucra_error_code, line, source_file_id }
+}
+
+#[inline]
+pub fn check_invariant(
+    cond: bool,
+    lucra_error_code: LucraErrorCode,
+    line: u32,
+    source_file_id: SourceFileId,
+) -> LucraResult<()> {
+    if cond {
+        Ok(())
+    } else {
+        Err(invariant_violation(lucra_error_code, line, source_file_id))
+    }
+}
+
 #[inline]
 pub fn check_assert(
     cond: bool,
@@ -287,6 +609,28 @@ macro_rules! declare_check_assert_macros {
             };
         }
 
+        #[allow(unused_macros)]
+        macro_rules! invariant {
+            ($cond:expr, $err:expr) => {
+                $crate::error::check_invariant($cond, $err, line!(), $source_file_id)
+            };
+        }
+
+        #[allow(unused_macros)]
+        macro_rules! invariant_eq {
+            ($x:expr, $y:expr, $err:expr) => {
+                $crate::error::check_invariant($x == $y, $err, line!(), $source_file_id)
+            };
+        }
+
+        // Logs as soon as it is built, so hand it to `ok_or_else` rather than `ok_or`
+        #[allow(unused_macros)]
+        macro_rules! invariant_err {
+            ($err:expr) => {
+                $crate::error::invariant_violation($err, line!(), $source_file_id)
+            };
+        }
+
         #[allow(unused_macros)]
         macro_rules! throw {
             () => {
@@ -320,4 +664,41 @@ macro_rules! declare_check_assert_macros {
             };
         }
     };
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    declare_check_assert_macros!(SourceFileId::MataSupply);
+
+    #[test]
+    fn test_invariants_use_their_own_code_range() {
+        let code: u32 = LucraErrorCode::MataBucketUnderflow.into();
+        assert!(!is_invariant_code(code));
+        assert!(is_invariant_code(invariant_code(LucraErrorCode::MataBucketUnderflow).unwrap()));
+
+        let checked: ProgramError = check!(false, LucraErrorCode::MataBucketUnderflow).unwrap_err().into();
+        let violated: ProgramError = invariant!(false, LucraErrorCode::MataBucketUnderflow).unwrap_err().into();
+        assert_eq!(checked, ProgramError::Custom(code));
+        assert_eq!(violated, ProgramError::Custom(INVARIANT_CODE_OFFSET + code));
+    }
+
+    #[test]
+    fn test_default_has_no_invariant_code() {
+        let default: u32 = LucraErrorCode::Default.into();
+        assert!(!is_invariant_code(default));
+        assert_eq!(invariant_code(LucraErrorCode::Default), Err(ProgramError::InvalidArgument));
+
+        let checked: ProgramError = check!(false, LucraErrorCode::Default).unwrap_err().into();
+        let violated: ProgramError = invariant!(false, LucraErrorCode::Default).unwrap_err().into();
+        assert_eq!(checked, ProgramError::Custom(default));
+        assert_eq!(violated, ProgramError::InvalidArgument);
+    }
+
+    #[test]
+    fn test_invariant_message_is_marked() {
+        let err = invariant_eq!(1, 2, LucraErrorCode::MsolVaultShortfall).unwrap_err();
+        assert!(err.to_string().starts_with("INVARIANT "));
+        assert!(invariant!(true, LucraErrorCode::MsolVaultShortfall).is_ok());
+    }
+}
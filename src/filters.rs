@@ -0,0 +1,95 @@
+//! Byte offsets of account fields clients read directly, for `dataSlice` reads and `memcmp` filters.
+//!
+//! The offsets come from the account structs themselves so they move with the layout.
+
+use std::mem::size_of;
+
+use crate::state::ArbState;
+
+fn offset_of<T, F>(account: &T, field: &F) -> usize {
+    field as *const F as usize - account as *const T as usize
+}
+
+/// Offset of `ArbState::remaining_today`, a little endian u64
+pub fn arb_state_remaining_today_offset() -> usize {
+    let arb_state = ArbState::default();
+    offset_of(&arb_state, &arb_state.remaining_today)
+}
+
+/// Offset of `ArbState::window_reset_at`, a little endian i64 unix timestamp
+pub fn arb_state_window_reset_at_offset() -> usize {
+    let arb_state = ArbState::default();
+    offset_of(&arb_state, &arb_state.window_reset_at)
+}
+
+/// Reads the arb capacity out of raw arb state data, returns (remaining_today, window_reset_at)
+pub fn read_arb_capacity(data: &[u8]) -> Option<(u64, i64)> {
+    if data.len() < size_of::<ArbState>() {
+        return None;
+    }
+
+    let remaining_today = arb_state_remaining_today_offset();
+    let window_reset_at = arb_state_window_reset_at_offset();
+
+    Some((
+        u64::from_le_bytes(data[remaining_today..remaining_today + 8].try_into().ok()?),
+        i64::from_le_bytes(data[window_reset_at..window_reset_at + 8].try_into().ok()?),
+    ))
+}
+
+/// Offset of `ArbState::arb_requested`, one byte that is 1 while an arb request is raised
+pub fn arb_state_arb_requested_offset() -> usize {
+    let arb_state = ArbState::default();
+    offset_of(&arb_state, &arb_state.arb_requested)
+}
+
+/// Reads the arb request out of raw arb state data, returns (arb_requested, deviation_bps, requested_at).
+/// Whether it's still fresh is up to the caller, see `helpers::arb_trigger::is_arb_request_fresh`.
+pub fn read_arb_request(data: &[u8]) -> Option<(bool, u16, i64)> {
+    if data.len() < size_of::<ArbState>() {
+        return None;
+    }
+
+    let arb_state = ArbState::default();
+    let deviation_bps = offset_of(&arb_state, &arb_state.arb_request_deviation_bps);
+    let requested_at = offset_of(&arb_state, &arb_state.arb_requested_at);
+
+    Some((
+        data[arb_state_arb_requested_offset()] != 0,
+        u16::from_le_bytes(data[deviation_bps..deviation_bps + 2].try_into().ok()?),
+        i64::from_le_bytes(data[requested_at..requested_at + 8].try_into().ok()?),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets_read_the_capacity_fields() {
+        let mut arb_state = ArbState::default();
+        arb_state.remaining_today = 123_456;
+        arb_state.window_reset_at = 1_650_067_200;
+
+        let data = bytemuck::bytes_of(&arb_state);
+
+        assert_eq!(read_arb_capacity(data), Some((123_456, 1_650_067_200)));
+        assert_eq!(read_arb_capacity(&data[..data.len() - 1]), None);
+    }
+
+    #[test]
+    fn test_offsets_read_the_arb_request() {
+        let mut arb_state = ArbState::default();
+        assert_eq!(read_arb_request(bytemuck::bytes_of(&arb_state)), Some((false, 0, 0)));
+
+        arb_state.arb_requested = true;
+        arb_state.arb_request_deviation_bps = 450;
+        arb_state.arb_requested_at = 1_650_000_000;
+
+        let data = bytemuck::bytes_of(&arb_state);
+
+        assert_eq!(data[arb_state_arb_requested_offset()], 1);
+        assert_eq!(read_arb_request(data), Some((true, 450, 1_650_000_000)));
+        assert_eq!(read_arb_request(&data[..data.len() - 1]), None);
+    }
+}
@@ -1,8 +1,11 @@
+use std::borrow::Cow;
+
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     pubkey::Pubkey,
-    sysvar::{fees::Fees, Sysvar},
+    sysvar,
 };
 use spl_token_swap::state::SwapVersion;
 use crate::{
@@ -13,15 +16,17 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
-    id,
 };
+
+pub use crate::lucra_core::pda::{find_program_address, find_stake_balance_authority};
+
 declare_check_assert_macros!(SourceFileId::Account);
 
-pub fn find_program_address(state: &Pubkey, seed: &[u8]) -> (Pubkey, u8) {
-    Pubkey::find_program_address(
-        &[&state.to_bytes()[..32], seed],
-        &id(),
-    )
+/// Run before every CPI. The instruction goes to whichever program account the caller passed, so anything
+/// but the expected deployed program would hand them the CPI and whatever it signs for.
+pub fn verify_program_account(program_ai: &AccountInfo, expected_program: &Pubkey) -> LucraResult {
+    check_eq!(program_ai.key, expected_program, LucraErrorCode::InvalidProgramAccount)?;
+    check!(program_ai.executable, LucraErrorCode::InvalidProgramAccount)
 }
 
 pub fn get_raydium_open_orders(raydium_amm: &AccountInfo) -> LucraResult<Pubkey> {
@@ -78,12 +83,199 @@ pub fn add_lamports(account: &AccountInfo, lamports: u64) {
         .unwrap();
 }
 
+/// Balance changes measured around a CPI only add up when the vaults and the user's accounts are all
+/// different accounts, so processors that measure one pass every account involved through here first
+pub fn verify_distinct_accounts(accounts: &[(&str, &Pubkey)]) -> LucraResult {
+    for (i, (name, key)) in accounts.iter().enumerate() {
+        if let Some((other_name, _)) = accounts[i + 1..].iter().find(|(_, other_key)| other_key == key) {
+            msg!("{} and {} are the same account", name, other_name);
+            return Err(throw_err!(LucraErrorCode::InvalidAccountInput));
+        }
+    }
+
+    Ok(())
+}
+
+/// `buffer` is what the account keeps back for fees, see FEE_BUFFER_LAMPORTS
 pub fn verify_account_will_still_have_lamports(
-    fees_ai: &AccountInfo,
     starting_lamports: u64,
     lamports_to_take: u64,
+    buffer: u64,
 ) -> LucraResult {
-    let fees = Fees::from_account_info(fees_ai).unwrap();
-    let lps = fees.fee_calculator.lamports_per_signature;
-    check!(lamports_to_take + (lps * 3) <= starting_lamports, LucraErrorCode::InvalidAmount) // Don't let users accidentally close their accounts...
-}
\ No newline at end of file
+    let lamports_needed = lamports_to_take
+        .checked_add(buffer)
+        .ok_or(math_err!())?;
+    check!(lamports_needed <= starting_lamports, LucraErrorCode::InvalidAmount) // Don't let users accidentally close their accounts...
+}
+
+/// CreateMataLoan, AddCollateral and CleanUpArb used to take the fees sysvar. Clients built against those
+/// layouts still pass it, so it's taken back out before the accounts are unpacked.
+pub fn without_fees_sysvar<'b, 'a>(accounts: &'b [AccountInfo<'a>]) -> Cow<'b, [AccountInfo<'a>]> {
+    match accounts.iter().position(|ai| ai.key == &sysvar::fees::id()) {
+        Some(position) => {
+            let mut accounts = accounts.to_vec();
+            accounts.remove(position);
+            Cow::Owned(accounts)
+        }
+        None => Cow::Borrowed(accounts),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::helpers::vaults::tests::assert_invalid_account_input;
+    use solana_program::{entrypoint::ProgramResult, program_error::ProgramError};
+
+    /// Empty accounts to hand a CPI helper, only the ones made programs are executable
+    pub struct FakeAccounts {
+        keys: Vec<Pubkey>,
+        lamports: Vec<u64>,
+        data: Vec<Vec<u8>>,
+        executable: Vec<bool>,
+        owner: Pubkey,
+    }
+
+    impl FakeAccounts {
+        pub fn new(count: usize) -> Self {
+            FakeAccounts {
+                keys: (0..count).map(|_| Pubkey::new_unique()).collect(),
+                lamports: vec![0; count],
+                data: vec![Vec::new(); count],
+                executable: vec![false; count],
+                owner: Pubkey::new_unique(),
+            }
+        }
+
+        pub fn with_program(mut self, index: usize, program: &Pubkey) -> Self {
+            self.keys[index] = *program;
+            self.executable[index] = true;
+            self
+        }
+
+        pub fn with_key(mut self, index: usize, key: &Pubkey) -> Self {
+            self.keys[index] = *key;
+            self
+        }
+
+        pub fn with_data(mut self, index: usize, data: Vec<u8>) -> Self {
+            self.data[index] = data;
+            self
+        }
+
+        /// Every account shares the one owner
+        pub fn with_owner(mut self, owner: &Pubkey) -> Self {
+            self.owner = *owner;
+            self
+        }
+
+        pub fn key(&self, index: usize) -> Pubkey {
+            self.keys[index]
+        }
+
+        pub fn infos(&mut self) -> Vec<AccountInfo<'_>> {
+            let owner = &self.owner;
+            self.keys
+                .iter()
+                .zip(self.lamports.iter_mut())
+                .zip(self.data.iter_mut())
+                .zip(self.executable.iter())
+                .map(|(((key, lamports), data), executable)| {
+                    AccountInfo::new(key, false, true, lamports, data, owner, *executable, 0)
+                })
+                .collect()
+        }
+    }
+
+    pub fn assert_invalid_program_account(result: LucraResult) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::InvalidProgramAccount,
+                ..
+            }
+        ));
+    }
+
+    /// Same for the helpers that return a ProgramResult
+    pub fn assert_invalid_program_account_code(result: ProgramResult) {
+        assert_eq!(result.unwrap_err(), ProgramError::Custom(LucraErrorCode::InvalidProgramAccount.into()));
+    }
+
+    /// Passes `verify` distinct keys, then every pair of slots aliased to the same key
+    pub fn assert_aliases_are_rejected<F: Fn(&[Pubkey]) -> LucraResult>(slots: usize, verify: F) {
+        let keys: Vec<Pubkey> = (0..slots).map(|_| Pubkey::new_unique()).collect();
+        assert!(verify(&keys).is_ok());
+
+        for i in 0..slots {
+            for j in i + 1..slots {
+                let mut aliased = keys.clone();
+                aliased[j] = aliased[i];
+                assert_invalid_account_input(verify(&aliased));
+            }
+        }
+    }
+
+    #[test]
+    fn test_program_account_must_be_the_expected_executable() {
+        let program = Pubkey::new_unique();
+        let mut accounts = FakeAccounts::new(2).with_program(0, &program);
+        let infos = accounts.infos();
+        assert!(verify_program_account(&infos[0], &program).is_ok());
+
+        // An executable stand in at another address
+        assert_invalid_program_account(verify_program_account(&infos[0], &Pubkey::new_unique()));
+
+        let mut not_executable = FakeAccounts::new(1);
+        not_executable.keys[0] = program;
+        assert_invalid_program_account(verify_program_account(&not_executable.infos()[0], &program));
+    }
+
+    #[test]
+    fn test_distinct_accounts() {
+        assert!(verify_distinct_accounts(&[]).is_ok());
+        assert_aliases_are_rejected(4, |keys| {
+            verify_distinct_accounts(&[("a", &keys[0]), ("b", &keys[1]), ("c", &keys[2]), ("d", &keys[3])])
+        });
+    }
+
+    #[test]
+    fn test_account_keeps_the_buffer() {
+        use crate::helpers::constants::FEE_BUFFER_LAMPORTS;
+
+        let assert_invalid_amount = |result: LucraResult| assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::InvalidAmount,
+                ..
+            }
+        ));
+
+        assert!(verify_account_will_still_have_lamports(1_000_000, 950_000, FEE_BUFFER_LAMPORTS).is_ok());
+        assert_invalid_amount(verify_account_will_still_have_lamports(1_000_000, 950_001, FEE_BUFFER_LAMPORTS));
+        assert!(verify_account_will_still_have_lamports(1_000_000, 1_000_000, 0).is_ok());
+        assert_invalid_amount(verify_account_will_still_have_lamports(1_000_000, 1_000_001, 0));
+        // A buffer that overflows is refused rather than wrapping around
+        assert!(verify_account_will_still_have_lamports(u64::MAX, u64::MAX, FEE_BUFFER_LAMPORTS).is_err());
+    }
+
+    #[test]
+    fn test_fees_sysvar_is_dropped_from_old_layouts() {
+        let owner = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), sysvar::fees::id(), Pubkey::new_unique()];
+        let mut lamports = [0_u64; 3];
+        let mut data = [[0_u8; 0]; 3];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| AccountInfo::new(key, false, false, lamports, data, &owner, false, 0))
+            .collect();
+
+        let current = without_fees_sysvar(&accounts[..1]);
+        assert!(matches!(current, Cow::Borrowed(_)));
+
+        let old = without_fees_sysvar(&accounts);
+        assert_eq!(old.iter().map(|ai| *ai.key).collect::<Vec<_>>(), vec![keys[0], keys[2]]);
+    }
+}
@@ -0,0 +1,232 @@
+use std::mem::size_of;
+
+use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    state::{
+        staking::StakeBalance,
+        ArbState,
+        DataType,
+        MataLoan,
+        MetaData,
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::AccountLayout);
+
+// Accounts that pick up history as features land end in a reserved region, new fields are carved out of
+// it instead of growing the account again. Accounts created before the region existed are reallocated
+// once by MigrateAccountLayout and their meta data version says which layout they're on.
+
+pub const RESERVED_LAYOUT_BYTES: usize = 256;
+pub const RESERVED_LAYOUT_VERSION: u8 = 1;
+
+// Length each data type was deployed at on the layouts before the reserved region. An entry is never
+// edited once its version has shipped, so the migration still recognises those accounts after the structs
+// move on.
+const LEGACY_LAYOUT_LENS: [(DataType, u8, usize); 4] = [
+    (DataType::Loan, 0, MataLoan::V0_LEN),
+    (DataType::StakeBalance, 0, StakeBalance::V0_LEN),
+    (DataType::SystemState, 0, SystemState::V0_LEN),
+    (DataType::ArbState, 0, ArbState::V0_LEN),
+];
+
+/// Size of an account on the reserved layout, None for types that don't carry a reserved region
+pub fn reserved_layout_len(data_type: u8) -> Option<usize> {
+    match data_type {
+        t if t == DataType::Loan as u8 => Some(size_of::<MataLoan>()),
+        t if t == DataType::StakeBalance as u8 => Some(size_of::<StakeBalance>()),
+        t if t == DataType::SystemState as u8 => Some(size_of::<SystemState>()),
+        t if t == DataType::ArbState as u8 => Some(size_of::<ArbState>()),
+        _ => None,
+    }
+}
+
+/// Length an account of this type was created at on a layout version, None for a layout that was never
+/// deployed. Fields added from the reserved layout on are carved out of the reserved region, so every
+/// version from it on has the reserved layout's size.
+pub fn layout_len(data_type: u8, version: u8) -> Option<usize> {
+    if version >= RESERVED_LAYOUT_VERSION {
+        return reserved_layout_len(data_type);
+    }
+
+    LEGACY_LAYOUT_LENS
+        .iter()
+        .find(|(legacy_type, legacy_version, _)| *legacy_type as u8 == data_type && *legacy_version == version)
+        .map(|(_, _, len)| *len)
+}
+
+/// Protocol wide accounts are migrated by the DAO, anyone can pay to migrate a loan or a stake balance
+pub fn is_dao_migrated(data_type: u8) -> bool {
+    data_type == DataType::SystemState as u8 || data_type == DataType::ArbState as u8
+}
+
+pub fn read_meta_data(data: &[u8]) -> LucraResult<MetaData> {
+    let bytes = data
+        .get(..size_of::<MetaData>())
+        .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+
+    Ok(bytemuck::pod_read_unaligned(bytes))
+}
+
+pub fn write_meta_data(data: &mut [u8], meta_data: &MetaData) -> LucraResult {
+    let bytes = data
+        .get_mut(..size_of::<MetaData>())
+        .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    bytes.copy_from_slice(bytemuck::bytes_of(meta_data));
+
+    Ok(())
+}
+
+/// Length the account is reallocated to. Only an account still on a layout from before the reserved
+/// region can be migrated, only once, and only from the length its version was deployed at.
+pub fn plan_layout_migration(meta_data: &MetaData, data_len: usize) -> LucraResult<usize> {
+    check!(meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+    let new_len = reserved_layout_len(meta_data.data_type)
+        .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    check!(meta_data.version < RESERVED_LAYOUT_VERSION, LucraErrorCode::AlreadyProcessed)?;
+    let deployed_len = layout_len(meta_data.data_type, meta_data.version)
+        .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    check_eq!(data_len, deployed_len, LucraErrorCode::InvalidAccountInput)?;
+    check!(data_len < new_len, LucraErrorCode::InvalidAccountInput)?;
+    check!(new_len - data_len <= MAX_PERMITTED_DATA_INCREASE, LucraErrorCode::InvalidAccountInput)?;
+
+    Ok(new_len)
+}
+
+/// Lamports the payer adds so the bigger account stays rent exempt
+pub fn calc_rent_top_up(lamports: u64, rent_exempt_minimum: u64) -> u64 {
+    rent_exempt_minimum.saturating_sub(lamports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    fn legacy_meta_data(data_type: DataType) -> MetaData {
+        MetaData::new(data_type, 0, true)
+    }
+
+    fn assert_reserved_tail<T: bytemuck::Pod>(mut account: T, fill_reserved: impl Fn(&mut T)) {
+        fill_reserved(&mut account);
+        let bytes = bytemuck::bytes_of(&account);
+
+        assert!(bytes[bytes.len() - RESERVED_LAYOUT_BYTES..].iter().all(|byte| *byte == 0xff));
+        assert!(bytes[..bytes.len() - RESERVED_LAYOUT_BYTES].iter().all(|byte| *byte == 0));
+    }
+
+    // New fields come out of the front of the reserved region, so it has to stay the last field
+    #[test]
+    fn test_reserved_region_is_the_tail_of_each_layout() {
+        assert_reserved_tail(MataLoan::zeroed(), |loan| loan.reserved.fill(0xff));
+        assert_reserved_tail(StakeBalance::zeroed(), |stake_balance| {
+            bytemuck::bytes_of_mut(&mut stake_balance.stake_checkpoints).fill(0xff);
+            stake_balance.reserved.fill(0xff);
+        });
+        assert_reserved_tail(SystemState::zeroed(), |system_state| system_state.reserved.fill(0xff));
+        assert_reserved_tail(ArbState::zeroed(), |arb_state| arb_state.reserved.fill(0xff));
+    }
+
+    const MIGRATED_TYPES: [DataType; 4] = [DataType::Loan, DataType::StakeBalance, DataType::SystemState, DataType::ArbState];
+
+    #[test]
+    fn test_migration_fits_in_one_realloc() {
+        for data_type in MIGRATED_TYPES {
+            let legacy_len = layout_len(data_type as u8, 0).unwrap();
+            let new_len = reserved_layout_len(data_type as u8).unwrap();
+
+            assert!(legacy_len < new_len);
+            assert!(new_len - legacy_len <= MAX_PERMITTED_DATA_INCREASE);
+        }
+    }
+
+    #[test]
+    fn test_legacy_accounts_grow_to_the_reserved_layout() {
+        for data_type in MIGRATED_TYPES {
+            let new_len = reserved_layout_len(data_type as u8).unwrap();
+            let legacy_len = layout_len(data_type as u8, 0).unwrap();
+
+            assert_eq!(plan_layout_migration(&legacy_meta_data(data_type), legacy_len).unwrap(), new_len);
+            assert!(plan_layout_migration(&legacy_meta_data(data_type), new_len).is_err());
+        }
+    }
+
+    // The legacy length is the one the account was deployed at, not one worked out from today's struct
+    #[test]
+    fn test_only_the_deployed_legacy_length_is_migrated() {
+        let legacy_len = layout_len(DataType::Loan as u8, 0).unwrap();
+        let legacy = legacy_meta_data(DataType::Loan);
+
+        assert!(plan_layout_migration(&legacy, legacy_len - 1).is_err());
+        assert!(plan_layout_migration(&legacy, legacy_len + 1).is_err());
+        assert!(plan_layout_migration(&legacy, 0).is_err());
+    }
+
+    #[test]
+    fn test_later_versions_keep_the_reserved_layout_size() {
+        for data_type in MIGRATED_TYPES {
+            for version in RESERVED_LAYOUT_VERSION..=RESERVED_LAYOUT_VERSION + 2 {
+                assert_eq!(layout_len(data_type as u8, version), reserved_layout_len(data_type as u8));
+            }
+        }
+        assert_eq!(layout_len(DataType::Reward as u8, 0), None);
+    }
+
+    #[test]
+    fn test_accounts_are_only_migrated_once() {
+        let legacy_len = layout_len(DataType::Loan as u8, 0).unwrap();
+        let migrated = MetaData::new(DataType::Loan, RESERVED_LAYOUT_VERSION, true);
+
+        assert!(matches!(
+            plan_layout_migration(&migrated, legacy_len).unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::AlreadyProcessed,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_other_accounts_are_not_migrated() {
+        let reward = legacy_meta_data(DataType::Reward);
+        assert!(plan_layout_migration(&reward, 0).is_err());
+
+        let uninitialized = MetaData::new(DataType::Loan, 0, false);
+        assert!(plan_layout_migration(&uninitialized, layout_len(DataType::Loan as u8, 0).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_only_protocol_accounts_need_the_dao() {
+        assert!(is_dao_migrated(DataType::SystemState as u8));
+        assert!(is_dao_migrated(DataType::ArbState as u8));
+        assert!(!is_dao_migrated(DataType::Loan as u8));
+        assert!(!is_dao_migrated(DataType::StakeBalance as u8));
+    }
+
+    #[test]
+    fn test_meta_data_version_is_rewritten_in_place() {
+        let mut data = vec![0; size_of::<MetaData>() + 8];
+        write_meta_data(&mut data, &legacy_meta_data(DataType::StakeBalance)).unwrap();
+
+        let mut meta_data = read_meta_data(&data).unwrap();
+        meta_data.version = RESERVED_LAYOUT_VERSION;
+        write_meta_data(&mut data, &meta_data).unwrap();
+
+        assert_eq!(read_meta_data(&data).unwrap().version, RESERVED_LAYOUT_VERSION);
+        assert!(read_meta_data(&data[..1]).is_err());
+    }
+
+    #[test]
+    fn test_rent_top_up() {
+        assert_eq!(calc_rent_top_up(1_000, 1_500), 500);
+        assert_eq!(calc_rent_top_up(2_000, 1_500), 0);
+    }
+}
@@ -0,0 +1,80 @@
+// min_amount_out rounding leaves a few thousand base units of mata or lucra in the holding vaults after a
+// full arb cycle. CleanUpArb burns the mata that's left, and burns the lucra too when it's no more than the
+// DAO's dust threshold on the arb state. Lucra above the threshold stays in the lucra holding vault for the
+// next cycle to sell.
+
+/// What CleanUpArb does with the mata and lucra left in the holding vaults
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArbDust {
+    /// Burned from the mata holding vault
+    pub mata_to_burn: u64,
+    /// Burned from the lucra holding vault
+    pub lucra_to_burn: u64,
+    /// Left in the lucra holding vault
+    pub lucra_to_keep: u64,
+    /// Only leftovers above the threshold pay the caller, sweeping dust on its own isn't worth a reward
+    pub reward_caller: bool,
+}
+
+pub fn plan_arb_dust(mata_left: u64, lucra_left: u64, dust_threshold: u64) -> ArbDust {
+    let (lucra_to_burn, lucra_to_keep) = if lucra_left <= dust_threshold {
+        (lucra_left, 0)
+    } else {
+        (0, lucra_left)
+    };
+
+    ArbDust {
+        mata_to_burn: mata_left,
+        lucra_to_burn,
+        lucra_to_keep,
+        reward_caller: mata_left > dust_threshold || lucra_left > dust_threshold,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLD: u64 = 5_000;
+
+    #[test]
+    fn test_empty_vaults_pay_nothing() {
+        assert_eq!(plan_arb_dust(0, 0, THRESHOLD), ArbDust::default());
+        assert_eq!(plan_arb_dust(0, 0, 0), ArbDust::default());
+    }
+
+    #[test]
+    fn test_dust_is_burned_without_a_reward() {
+        let dust = plan_arb_dust(3_000, THRESHOLD, THRESHOLD);
+
+        assert_eq!(dust.mata_to_burn, 3_000);
+        assert_eq!(dust.lucra_to_burn, THRESHOLD);
+        assert_eq!(dust.lucra_to_keep, 0);
+        assert!(!dust.reward_caller);
+    }
+
+    #[test]
+    fn test_leftovers_above_the_threshold_are_rewarded() {
+        // All the mata is burned whatever its size
+        let dust = plan_arb_dust(THRESHOLD + 1, 100, THRESHOLD);
+        assert_eq!(dust.mata_to_burn, THRESHOLD + 1);
+        assert_eq!(dust.lucra_to_burn, 100);
+        assert!(dust.reward_caller);
+
+        // Lucra above the threshold is kept for the next cycle
+        let dust = plan_arb_dust(0, THRESHOLD + 1, THRESHOLD);
+        assert_eq!(dust.lucra_to_burn, 0);
+        assert_eq!(dust.lucra_to_keep, THRESHOLD + 1);
+        assert!(dust.reward_caller);
+    }
+
+    #[test]
+    fn test_no_threshold_keeps_every_lucra() {
+        let dust = plan_arb_dust(1, 1, 0);
+
+        assert_eq!(dust.mata_to_burn, 1);
+        assert_eq!(dust.lucra_to_burn, 0);
+        assert_eq!(dust.lucra_to_keep, 1);
+        assert!(dust.reward_caller);
+    }
+}
@@ -0,0 +1,111 @@
+use bincode::{deserialize, serialize};
+use serde::{Deserialize, Serialize};
+use solana_program::program::set_return_data;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::arb_window::calc_remaining_today,
+    state::ArbState,
+};
+
+declare_check_assert_macros!(SourceFileId::ArbResult);
+
+// MintFundsForArb, SellFundsForArb, BuyBurnForArb and CleanUpArb each end by setting an ArbResult as return
+// data, so a keeper can size the next step of an arb from the transaction it just sent instead of fetching the
+// arb state again.
+
+/// Return data of the arb instructions, bincode encoded: four little endian fields in this order, 25 bytes
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArbResult {
+    /// Native amount the instruction minted, sold, bought and burned, or sent to the coffer
+    pub amount_processed: u64,
+    /// The arb state's StateEnum after the instruction, as its u8 discriminant
+    pub state: u8,
+    /// Limit left to spend on the arb window's current day
+    pub remaining_today: u64,
+    /// Whole reward tokens minted to the caller for running the step
+    pub reward_tokens_minted: u64,
+}
+
+pub const ARB_RESULT_LEN: usize = 8 + 1 + 8 + 8;
+
+impl ArbResult {
+    /// Read after every change to the arb state has been made
+    pub fn new(arb_state: &ArbState, amount_processed: u64, reward_tokens_minted: u64) -> Self {
+        ArbResult {
+            amount_processed,
+            state: arb_state.state as u8,
+            remaining_today: calc_remaining_today(arb_state),
+            reward_tokens_minted,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serialize(self).unwrap()
+    }
+
+    /// Reads the return data of a transaction that ran an arb instruction
+    pub fn try_from_bytes(data: &[u8]) -> LucraResult<Self> {
+        check_eq!(data.len(), ARB_RESULT_LEN, LucraErrorCode::InvalidAccountInput)?;
+        deserialize(data).map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))
+    }
+}
+
+pub fn set_arb_return_data(arb_state: &ArbState, amount_processed: u64, reward_tokens_minted: u64) {
+    set_return_data(&ArbResult::new(arb_state, amount_processed, reward_tokens_minted).to_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{arb_window::roll_arb_window, constants::UNIX_DAY};
+
+    const START: i64 = 19_000 * UNIX_DAY;
+
+    #[test]
+    fn test_layout_is_fixed() {
+        let result = ArbResult {
+            amount_processed: 1,
+            state: 2,
+            remaining_today: 3,
+            reward_tokens_minted: 4,
+        };
+
+        let bytes = result.to_bytes();
+        assert_eq!(bytes.len(), ARB_RESULT_LEN);
+        assert_eq!(&bytes[..9], &[1, 0, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(&bytes[9..17], &3_u64.to_le_bytes());
+        assert_eq!(&bytes[17..], &4_u64.to_le_bytes());
+        assert_eq!(ArbResult::try_from_bytes(&bytes).unwrap(), result);
+    }
+
+    #[test]
+    fn test_result_reads_the_arb_state_it_left_behind() {
+        let mut arb_state = ArbState::default();
+        arb_state.daily_limit = 1_000;
+        arb_state.start_of_day_timestamp = START;
+        arb_state.rolling_limits[0].date = START;
+        arb_state.rolling_limits[0].limit = 400;
+
+        let spent_today = ArbResult::try_from_bytes(&ArbResult::new(&arb_state, 600, 1).to_bytes()).unwrap();
+        assert_eq!(spent_today.amount_processed, 600);
+        assert_eq!(spent_today.state, arb_state.state as u8);
+        assert_eq!(spent_today.remaining_today, 400);
+        assert_eq!(spent_today.reward_tokens_minted, 1);
+
+        // A roll over gives the keeper the fresh day's limit
+        roll_arb_window(&mut arb_state, START + UNIX_DAY);
+        assert_eq!(ArbResult::new(&arb_state, 0, 1).remaining_today, 1_000);
+    }
+
+    #[test]
+    fn test_other_return_data_is_rejected() {
+        assert!(ArbResult::try_from_bytes(&[]).is_err());
+        assert!(ArbResult::try_from_bytes(&[0; ARB_RESULT_LEN + 1]).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::ARB_REQUEST_TTL,
+    state::ArbState,
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::ArbTrigger);
+
+// UpdatePriceHistory raises an arb request on the arb state when mata is further off the peg than the DAO's
+// trigger, so the condition is on chain instead of in whoever happens to be watching. MintFundsForArb still
+// has to be called, a fresh request only waives its profitability minimums and is cleared when it's used.
+// A request nobody acts on goes stale after ARB_REQUEST_TTL.
+
+/// Bps mata trades away from the peg on either side
+pub fn calc_abs_peg_deviation_bps(mata_price: Decimal) -> u16 {
+    (mata_price.max(Decimal::ZERO) - Decimal::ONE)
+        .abs()
+        .checked_mul(Decimal::from(10_000))
+        .and_then(|deviation| deviation.floor().to_u16())
+        .unwrap_or(u16::MAX)
+}
+
+/// A trigger of 0 turns the auto trigger off
+pub fn verify_arb_trigger(trigger_deviation_bps: Bps) -> LucraResult {
+    check!(trigger_deviation_bps <= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)
+}
+
+/// Raises the request while the deviation is past the trigger and drops it once mata is back inside
+pub fn record_arb_trigger(arb_state: &mut ArbState, deviation_bps: u16, timestamp: i64) {
+    let trigger_deviation_bps = arb_state.arb_trigger_deviation_bps;
+    if trigger_deviation_bps == Bps(0) {
+        return;
+    }
+
+    if u32::from(deviation_bps) > trigger_deviation_bps.get() {
+        arb_state.arb_requested = true;
+        arb_state.arb_request_deviation_bps = deviation_bps;
+        arb_state.arb_requested_at = timestamp;
+    } else {
+        arb_state.arb_requested = false;
+    }
+}
+
+pub fn is_arb_request_fresh(arb_state: &ArbState, timestamp: i64) -> bool {
+    arb_state.arb_requested && timestamp.saturating_sub(arb_state.arb_requested_at) <= ARB_REQUEST_TTL
+}
+
+/// Called when an arb cycle starts. Returns whether the cycle can skip the profitability minimums and
+/// always clears the request, a stale one included.
+pub fn take_arb_request(arb_state: &mut ArbState, timestamp: i64) -> bool {
+    let fresh = is_arb_request_fresh(arb_state, timestamp);
+    arb_state.arb_requested = false;
+
+    fresh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    const NOW: i64 = 1_650_000_000;
+
+    fn triggered_arb_state() -> ArbState {
+        ArbState {
+            arb_trigger_deviation_bps: Bps(300),
+            ..ArbState::zeroed()
+        }
+    }
+
+    fn assert_error(result: LucraResult, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_deviation_is_measured_on_both_sides() {
+        assert_eq!(calc_abs_peg_deviation_bps(Decimal::ONE), 0);
+        assert_eq!(calc_abs_peg_deviation_bps(Decimal::new(96, 2)), 400);
+        assert_eq!(calc_abs_peg_deviation_bps(Decimal::new(104, 2)), 400);
+        assert_eq!(calc_abs_peg_deviation_bps(Decimal::ZERO), 10_000);
+        assert_eq!(calc_abs_peg_deviation_bps(Decimal::from(10_000)), u16::MAX);
+    }
+
+    #[test]
+    fn test_trigger_bounds() {
+        assert!(verify_arb_trigger(Bps(0)).is_ok());
+        assert!(verify_arb_trigger(Bps::ONE_HUNDRED_PERCENT).is_ok());
+        assert_error(verify_arb_trigger(Bps(10_001)), LucraErrorCode::InvalidAmount);
+    }
+
+    #[test]
+    fn test_request_is_set_then_consumed() {
+        let mut arb_state = triggered_arb_state();
+
+        // At the trigger isn't past it
+        record_arb_trigger(&mut arb_state, 300, NOW);
+        assert!(!arb_state.arb_requested);
+
+        record_arb_trigger(&mut arb_state, 450, NOW);
+        assert!(arb_state.arb_requested);
+        assert_eq!(arb_state.arb_request_deviation_bps, 450);
+        assert_eq!(arb_state.arb_requested_at, NOW);
+
+        assert!(take_arb_request(&mut arb_state, NOW + 60));
+        assert!(!arb_state.arb_requested);
+        // The next cycle goes through the normal checks
+        assert!(!take_arb_request(&mut arb_state, NOW + 120));
+    }
+
+    #[test]
+    fn test_request_expires() {
+        let mut arb_state = triggered_arb_state();
+        record_arb_trigger(&mut arb_state, 450, NOW);
+
+        assert!(is_arb_request_fresh(&arb_state, NOW + ARB_REQUEST_TTL));
+        assert!(!is_arb_request_fresh(&arb_state, NOW + ARB_REQUEST_TTL + 1));
+
+        // A stale request doesn't waive anything but is still cleared
+        assert!(!take_arb_request(&mut arb_state, NOW + ARB_REQUEST_TTL + 1));
+        assert!(!arb_state.arb_requested);
+    }
+
+    #[test]
+    fn test_request_follows_the_latest_measurement() {
+        let mut arb_state = triggered_arb_state();
+        record_arb_trigger(&mut arb_state, 450, NOW);
+
+        // Still past the trigger, the request is refreshed
+        record_arb_trigger(&mut arb_state, 350, NOW + 3_600);
+        assert_eq!((arb_state.arb_request_deviation_bps, arb_state.arb_requested_at), (350, NOW + 3_600));
+
+        // Back inside the trigger before anyone acted on it
+        record_arb_trigger(&mut arb_state, 100, NOW + 7_200);
+        assert!(!arb_state.arb_requested);
+    }
+
+    #[test]
+    fn test_zero_trigger_never_requests() {
+        let mut arb_state = ArbState::zeroed();
+        record_arb_trigger(&mut arb_state, 10_000, NOW);
+
+        assert!(!arb_state.arb_requested);
+    }
+}
@@ -0,0 +1,294 @@
+use rust_decimal::Decimal;
+use solana_program::clock::UnixTimestamp;
+use time::{OffsetDateTime, Time};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::UNIX_DAY,
+    lucra_core::quote::calc_msol_lamport_value,
+    state::{ArbState, Limit},
+};
+
+declare_check_assert_macros!(SourceFileId::ArbWindow);
+
+// The arb state keeps the limits for the last 30 days, newest first. The window has to be on today before
+// today's limit can be spent. RollArbWindow lets anyone roll it ahead of the arb instructions.
+
+/// Number of days the rolling limits cover
+pub const ARB_WINDOW_DAYS: usize = 30;
+
+/// Rolls the arb window forward to the day of the timestamp. Cheap when the window is already current,
+/// returns whether anything changed.
+pub fn roll_arb_window(arb_state: &mut ArbState, timestamp: UnixTimestamp) -> bool {
+    let today = OffsetDateTime::from_unix_timestamp(timestamp)
+        .unwrap()
+        .replace_time(Time::from_hms(0, 0, 0).unwrap())
+        .unix_timestamp();
+
+    if !roll_limits(&mut arb_state.rolling_limits, arb_state.start_of_day_timestamp, today, arb_state.daily_limit) {
+        refresh_arb_capacity(arb_state);
+        return false;
+    }
+
+    arb_state.start_of_day_timestamp = today;
+    arb_state.agg_limit = aggregate_limit(&arb_state.rolling_limits);
+    refresh_arb_capacity(arb_state);
+
+    true
+}
+
+/// Limit left to spend on the window's current day
+pub fn calc_remaining_today(arb_state: &ArbState) -> u64 {
+    arb_state.rolling_limits[0].limit
+}
+
+/// When the window next rolls over. Once this has passed the full daily limit is available again.
+pub fn calc_window_reset_at(arb_state: &ArbState) -> UnixTimestamp {
+    arb_state.start_of_day_timestamp.saturating_add(UNIX_DAY)
+}
+
+/// remaining_today and window_reset_at are only there so bots can size an arb from one account fetch.
+/// They are always recomputed from the window and nothing reads them back, so anything that rolls
+/// or spends the limits calls this last.
+pub fn refresh_arb_capacity(arb_state: &mut ArbState) {
+    arb_state.remaining_today = calc_remaining_today(arb_state);
+    arb_state.window_reset_at = calc_window_reset_at(arb_state);
+}
+
+/// Pushes a fresh daily limit for every day between the current start of day and today. A gap longer than
+/// the window only replays the days that are still in it.
+pub fn roll_limits(limits: &mut [Limit; ARB_WINDOW_DAYS], start_of_day: UnixTimestamp, today: UnixTimestamp, daily_limit: u64) -> bool {
+    if today <= start_of_day {
+        return false;
+    }
+
+    let missed_days = (today - start_of_day) / UNIX_DAY;
+    let first_day = missed_days - missed_days.min(ARB_WINDOW_DAYS as i64) + 1;
+    for day in first_day..=missed_days {
+        limits.rotate_right(1);
+        limits[0] = Limit {
+            date: start_of_day + day * UNIX_DAY,
+            limit: daily_limit,
+        };
+    }
+
+    true
+}
+
+pub fn aggregate_limit(limits: &[Limit; ARB_WINDOW_DAYS]) -> u64 {
+    limits
+        .iter()
+        .fold(0_u64, |total, limit| total.saturating_add(limit.limit))
+}
+
+/// Takes `lamports` off today's limit. Spending more than is left today fails and leaves the window as it was.
+pub fn spend_arb_limit(arb_state: &mut ArbState, lamports: u64) -> LucraResult {
+    let remaining_today = calc_remaining_today(arb_state);
+    check!(lamports <= remaining_today, LucraErrorCode::InvalidAmount)?;
+
+    arb_state.rolling_limits[0].limit = remaining_today - lamports;
+    arb_state.agg_limit = aggregate_limit(&arb_state.rolling_limits);
+    refresh_arb_capacity(arb_state);
+
+    Ok(())
+}
+
+/// Lamports an mSOL leg spends of the arb limits. Swapping the mSOL straight through the MSOL/SOL pool skips
+/// the marinade unstake, it still counts for what the mSOL redeems for at the marinade rate so the pool's
+/// price can't stretch the limit.
+pub fn calc_msol_arb_limit_amount(msol_amount: u64, marinade_rate: Decimal) -> LucraResult<u64> {
+    calc_msol_lamport_value(msol_amount, marinade_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const START: i64 = 19_000 * UNIX_DAY;
+    const DAILY_LIMIT: u64 = 1_000;
+
+    fn initial_limits() -> [Limit; ARB_WINDOW_DAYS] {
+        let mut limits = [Limit { date: 0, limit: 0 }; ARB_WINDOW_DAYS];
+        limits[0] = Limit { date: START, limit: DAILY_LIMIT };
+        limits
+    }
+
+    #[test]
+    fn test_roll_is_idempotent_within_a_day() {
+        let mut limits = initial_limits();
+
+        assert!(!roll_limits(&mut limits, START, START, DAILY_LIMIT));
+        assert_eq!(limits[0].date, START);
+
+        assert!(roll_limits(&mut limits, START, START + UNIX_DAY, DAILY_LIMIT));
+        let rolled = limits;
+        assert!(!roll_limits(&mut limits, START + UNIX_DAY, START + UNIX_DAY, DAILY_LIMIT));
+        assert!(limits.iter().zip(rolled.iter()).all(|(a, b)| a.date == b.date && a.limit == b.limit));
+    }
+
+    #[test]
+    fn test_rolling_missed_days_matches_rolling_daily() {
+        for missed_days in [1, 2, 7, 29, 30, 31, 45, 400] {
+            // Arbs used to roll the window inline on the first arb of every day
+            let mut daily = initial_limits();
+            let mut start_of_day = START;
+            for _ in 0..missed_days {
+                roll_limits(&mut daily, start_of_day, start_of_day + UNIX_DAY, DAILY_LIMIT);
+                start_of_day += UNIX_DAY;
+            }
+
+            let mut at_once = initial_limits();
+            roll_limits(&mut at_once, START, START + missed_days * UNIX_DAY, DAILY_LIMIT);
+
+            for (a, b) in at_once.iter().zip(daily.iter()) {
+                assert_eq!(a.date, b.date);
+                assert_eq!(a.limit, b.limit);
+            }
+            assert_eq!(aggregate_limit(&at_once), aggregate_limit(&daily));
+            assert_eq!(at_once[0].date, START + missed_days * UNIX_DAY);
+        }
+    }
+
+    #[test]
+    fn test_spent_day_drops_out_after_the_window() {
+        let mut arb_state = ArbState::default();
+        arb_state.daily_limit = DAILY_LIMIT;
+        arb_state.start_of_day_timestamp = START;
+        arb_state.rolling_limits = initial_limits();
+        spend_arb_limit(&mut arb_state, 600).unwrap();
+
+        // The last day of the window is still the one that was spent
+        roll_arb_window(&mut arb_state, START + 29 * UNIX_DAY);
+        assert_eq!(arb_state.rolling_limits[ARB_WINDOW_DAYS - 1].date, START);
+        assert_eq!(arb_state.rolling_limits[ARB_WINDOW_DAYS - 1].limit, 400);
+        assert_eq!(arb_state.agg_limit, ARB_WINDOW_DAYS as u64 * DAILY_LIMIT - 600);
+
+        // One more day wraps it out
+        roll_arb_window(&mut arb_state, START + 30 * UNIX_DAY);
+        assert_eq!(arb_state.rolling_limits[ARB_WINDOW_DAYS - 1].date, START + UNIX_DAY);
+        assert_eq!(arb_state.agg_limit, ARB_WINDOW_DAYS as u64 * DAILY_LIMIT);
+    }
+
+    #[test]
+    fn test_quiet_days_never_drift_the_window() {
+        let mut arb_state = ArbState::default();
+        arb_state.daily_limit = DAILY_LIMIT;
+        arb_state.start_of_day_timestamp = START;
+        arb_state.rolling_limits = initial_limits();
+
+        // Rolls land at odd times of day after gaps shorter and longer than the window
+        let mut now = START;
+        for gap in [3 * UNIX_DAY + 5 * 3_600, 40 * UNIX_DAY + 1, 2 * UNIX_DAY - 1, UNIX_DAY, 12 * UNIX_DAY + 86_399] {
+            now += gap;
+            roll_arb_window(&mut arb_state, now);
+
+            let today = now - now.rem_euclid(UNIX_DAY);
+            assert_eq!(arb_state.start_of_day_timestamp, today);
+            for (index, limit) in arb_state.rolling_limits.iter().enumerate().filter(|(_, limit)| limit.date != 0) {
+                assert_eq!(limit.date, today - index as i64 * UNIX_DAY);
+            }
+        }
+        assert_eq!(arb_state.agg_limit, ARB_WINDOW_DAYS as u64 * DAILY_LIMIT);
+    }
+
+    #[test]
+    fn test_roll_uses_the_current_daily_limit() {
+        let mut limits = initial_limits();
+        roll_limits(&mut limits, START, START + 2 * UNIX_DAY, 500);
+
+        assert_eq!(limits[0].limit, 500);
+        assert_eq!(limits[1].limit, 500);
+        assert_eq!(limits[2].limit, DAILY_LIMIT);
+        assert_eq!(aggregate_limit(&limits), 2_000);
+    }
+
+    #[test]
+    fn test_roll_arb_window_keeps_state_in_sync() {
+        let mut arb_state = ArbState::default();
+        arb_state.daily_limit = DAILY_LIMIT;
+        arb_state.start_of_day_timestamp = START;
+        arb_state.rolling_limits = initial_limits();
+        arb_state.agg_limit = DAILY_LIMIT;
+
+        // Partway through the day still lands on midnight
+        assert!(roll_arb_window(&mut arb_state, START + 3 * UNIX_DAY + 1_234));
+        assert_eq!(arb_state.start_of_day_timestamp, START + 3 * UNIX_DAY);
+        assert_eq!(arb_state.agg_limit, 4 * DAILY_LIMIT);
+
+        assert!(!roll_arb_window(&mut arb_state, START + 3 * UNIX_DAY + 5_000));
+        assert_eq!(arb_state.agg_limit, 4 * DAILY_LIMIT);
+    }
+
+    #[test]
+    fn test_capacity_fields_match_the_window() {
+        let mut arb_state = ArbState::default();
+        arb_state.daily_limit = DAILY_LIMIT;
+        arb_state.start_of_day_timestamp = START;
+        arb_state.rolling_limits = initial_limits();
+        arb_state.rolling_limits[0].limit = 250; // Part of today's limit already spent
+        arb_state.remaining_today = 999_999; // Stale values are overwritten, never trusted
+        arb_state.window_reset_at = 0;
+
+        assert!(!roll_arb_window(&mut arb_state, START + 60));
+        assert_eq!(arb_state.remaining_today, calc_remaining_today(&arb_state));
+        assert_eq!(arb_state.remaining_today, 250);
+        assert_eq!(arb_state.window_reset_at, START + UNIX_DAY);
+
+        assert!(roll_arb_window(&mut arb_state, START + UNIX_DAY));
+        assert_eq!(arb_state.remaining_today, calc_remaining_today(&arb_state));
+        assert_eq!(arb_state.remaining_today, DAILY_LIMIT);
+        assert_eq!(arb_state.window_reset_at, calc_window_reset_at(&arb_state));
+        assert_eq!(arb_state.window_reset_at, START + 2 * UNIX_DAY);
+    }
+
+    #[test]
+    fn test_spending_takes_from_today_only() {
+        let mut arb_state = ArbState::default();
+        arb_state.daily_limit = DAILY_LIMIT;
+        arb_state.start_of_day_timestamp = START;
+        arb_state.rolling_limits = initial_limits();
+        arb_state.rolling_limits[1] = Limit { date: START - UNIX_DAY, limit: 300 };
+        arb_state.agg_limit = aggregate_limit(&arb_state.rolling_limits);
+
+        spend_arb_limit(&mut arb_state, 600).unwrap();
+        assert_eq!(arb_state.rolling_limits[0].limit, 400);
+        assert_eq!(arb_state.rolling_limits[1].limit, 300);
+        assert_eq!(arb_state.agg_limit, 700);
+        assert_eq!(arb_state.remaining_today, 400);
+
+        // Yesterday's leftover can't be spent today
+        assert!(spend_arb_limit(&mut arb_state, 401).is_err());
+        assert_eq!(arb_state.rolling_limits[0].limit, 400);
+
+        spend_arb_limit(&mut arb_state, 400).unwrap();
+        assert_eq!(arb_state.remaining_today, 0);
+    }
+
+    #[test]
+    fn test_msol_leg_is_valued_at_the_marinade_rate() {
+        let mut arb_state = ArbState::default();
+        arb_state.daily_limit = DAILY_LIMIT;
+        arb_state.start_of_day_timestamp = START;
+        arb_state.rolling_limits = initial_limits();
+
+        // 900 mSOL redeem for 990 lamports at 1.1, more than they are in mSOL
+        let lamports = calc_msol_arb_limit_amount(900, dec!(1.1)).unwrap();
+        assert_eq!(lamports, 990);
+        spend_arb_limit(&mut arb_state, lamports).unwrap();
+        assert_eq!(calc_remaining_today(&arb_state), 10);
+
+        // 10 mSOL fit in the raw limit left but not once they are valued in SOL
+        let lamports = calc_msol_arb_limit_amount(10, dec!(1.1)).unwrap();
+        assert_eq!(lamports, 11);
+        assert!(spend_arb_limit(&mut arb_state, lamports).is_err());
+
+        // Rounded down like every other mSOL valuation
+        assert_eq!(calc_msol_arb_limit_amount(9, dec!(1.1)).unwrap(), 9);
+    }
+}
@@ -0,0 +1,269 @@
+use std::convert::TryFrom;
+
+use anchor_lang::AccountDeserialize;
+use marinade_finance::state::State as MarinadeState;
+use rust_decimal::Decimal;
+use solana_program::{
+    account_info::AccountInfo,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    sysvar::clock::Clock,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::oracle::get_oracle_price,
+    state::{LoanType, MataLoan, SystemState},
+};
+
+pub use crate::lucra_core::{
+    penalty::{calc_harvest_msol, calc_harvestable_penalty, calc_remaining_penalty_collateral},
+    quote::{calc_collateral_valuation_factor, calc_msol_lamport_value},
+};
+
+declare_check_assert_macros!(SourceFileId::Collateral);
+
+// Collateral sits in the vault as msol but loans are sized and penalized on its SOL value. During a
+// depeg msol trades under its redemption value, so a harvest recovers less SOL than the books assume.
+// These helpers only affect valuation, unstaking always converts at the marinade rate.
+
+/// SOL redeemable per msol according to marinade
+pub fn get_marinade_msol_rate(marinade_state_ai: &AccountInfo) -> LucraResult<Decimal> {
+    check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    let mut marinade_data: &[u8] = &marinade_state_ai.try_borrow_data()?;
+    let marinade_state = MarinadeState::try_deserialize(&mut marinade_data)
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+
+    Decimal::from(marinade_state.msol_price)
+        .checked_div(MarinadeState::PRICE_DENOMINATOR.into())
+        .ok_or(math_err!())
+}
+
+/// Returns the MSOL/SOL oracle the DAO registered if the caller passed it in
+pub fn find_msol_sol_oracle<'a, 'b>(accounts: &'a [AccountInfo<'b>], system_state: &SystemState) -> Option<&'a AccountInfo<'b>> {
+    if system_state.msol_sol_oracle == Pubkey::default() {
+        return None;
+    }
+
+    accounts.iter().find(|ai| ai.key == &system_state.msol_sol_oracle)
+}
+
+/// Valuation factor for the current system state. Once the DAO registers an MSOL/SOL oracle it has
+/// to be passed in, leaving it out would let a borrower skip the depeg discount.
+pub fn get_collateral_valuation_factor(
+    system_state: &SystemState,
+    msol_sol_oracle_ai: Option<&AccountInfo>,
+    marinade_state_ai: Option<&AccountInfo>,
+    clock: &Clock,
+) -> LucraResult<Decimal> {
+    if system_state.msol_sol_oracle == Pubkey::default() {
+        return calc_collateral_valuation_factor(system_state.msol_haircut_bps, Decimal::ONE, None);
+    }
+
+    let msol_sol_oracle_ai = msol_sol_oracle_ai.ok_or(throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    let marinade_state_ai = marinade_state_ai.ok_or(throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    check_eq!(msol_sol_oracle_ai.key, &system_state.msol_sol_oracle, LucraErrorCode::InvalidAccountInput)?;
+
+    let marinade_rate = get_marinade_msol_rate(marinade_state_ai)?;
+    let market_rate = get_oracle_price(system_state, msol_sol_oracle_ai, clock)?;
+
+    calc_collateral_valuation_factor(system_state.msol_haircut_bps, marinade_rate, Some(market_rate))
+}
+
+/// Dollar value of what's left of a loan's collateral. Harvested and pending penalty don't count, a lucra
+/// backed loan's locked stake counts at the value it was locked at.
+pub fn calc_loan_collateral_value(loan: &MataLoan, sol_price: Decimal, collateral_factor: Decimal) -> LucraResult<Decimal> {
+    Decimal::from(calc_remaining_penalty_collateral(loan))
+        .checked_mul(sol_price)
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())?
+        .checked_mul(collateral_factor)
+        .ok_or(math_err!())?
+        .checked_add(Decimal::from(loan.staking_collateral_amount))
+        .ok_or(math_err!())
+}
+
+/// Loans opened before they recorded their msol read msol_collateral_amount as zero, and every path capped at it
+/// would pay them nothing. They're given the share of the msol vault their collateral is of all the msol backed
+/// collateral the first time it's needed, and keep it from then on. Native and LP backed loans hold no msol.
+pub fn backfill_msol_collateral(loan: &mut MataLoan, system_state: &SystemState, msol_vault_balance: u64) -> LucraResult {
+    if loan.msol_collateral_amount > 0 || matches!(loan.loan_type, LoanType::NativeSol | LoanType::LpBacked) {
+        return Ok(());
+    }
+
+    loan.msol_collateral_amount = calc_msol_share(
+        loan.sol_collateral_amount.saturating_sub(loan.penalty_harvested),
+        system_state.total_sol_collateral,
+        msol_vault_balance,
+    )?;
+
+    Ok(())
+}
+
+fn calc_msol_share(sol_collateral: u64, total_sol_collateral: u64, msol_vault_balance: u64) -> LucraResult<u64> {
+    if total_sol_collateral == 0 {
+        return Ok(0);
+    }
+
+    let share = u128::from(sol_collateral.min(total_sol_collateral))
+        .checked_mul(msol_vault_balance.into())
+        .ok_or(math_err!())?
+        / u128::from(total_sol_collateral);
+
+    u64::try_from(share).map_err(|_| math_err!())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use crate::units::Bps;
+
+    #[test]
+    fn test_haircut_without_a_market_price() {
+        assert_eq!(calc_collateral_valuation_factor(Bps(0), Decimal::ONE, None).unwrap(), dec!(1));
+        assert_eq!(calc_collateral_valuation_factor(Bps(100), Decimal::ONE, None).unwrap(), dec!(0.99));
+        assert_eq!(calc_collateral_valuation_factor(Bps(10_000), Decimal::ONE, None).unwrap(), dec!(0));
+        assert!(calc_collateral_valuation_factor(Bps(10_001), Decimal::ONE, None).is_err());
+    }
+
+    #[test]
+    fn test_market_price_above_redemption_is_ignored() {
+        let actual = calc_collateral_valuation_factor(Bps(100), dec!(1.08), Some(dec!(1.1))).unwrap();
+
+        assert_eq!(actual, dec!(0.99));
+    }
+
+    #[test]
+    fn test_msol_lamport_value_rounds_down() {
+        assert_eq!(calc_msol_lamport_value(1_000_000_000, dec!(1.08)).unwrap(), 1_080_000_000);
+        assert_eq!(calc_msol_lamport_value(3, dec!(1.5)).unwrap(), 4);
+        assert_eq!(calc_msol_lamport_value(0, dec!(1.08)).unwrap(), 0);
+    }
+
+    fn legacy_loan(sol_collateral_amount: u64) -> MataLoan {
+        MataLoan { sol_collateral_amount, loan_type: LoanType::Default, ..MataLoan::default() }
+    }
+
+    #[test]
+    fn test_legacy_loan_is_backfilled_with_its_vault_share() {
+        let system_state = SystemState { total_sol_collateral: 40 * LAMPORTS_PER_SOL, ..SystemState::default() };
+        let mut loan = MataLoan { penalty_harvested: 2 * LAMPORTS_PER_SOL, ..legacy_loan(12 * LAMPORTS_PER_SOL) };
+
+        // 10 of the 40 SOL backed by 36 msol
+        backfill_msol_collateral(&mut loan, &system_state, 36 * LAMPORTS_PER_SOL).unwrap();
+        assert_eq!(loan.msol_collateral_amount, 9 * LAMPORTS_PER_SOL);
+
+        // Kept from then on, whatever the vault holds
+        backfill_msol_collateral(&mut loan, &system_state, 80 * LAMPORTS_PER_SOL).unwrap();
+        assert_eq!(loan.msol_collateral_amount, 9 * LAMPORTS_PER_SOL);
+    }
+
+    #[test]
+    fn test_backfill_never_hands_out_more_than_the_vault() {
+        // A loan booked for more than the total still can't claim past the whole vault
+        let system_state = SystemState { total_sol_collateral: 5 * LAMPORTS_PER_SOL, ..SystemState::default() };
+        let mut loan = legacy_loan(10 * LAMPORTS_PER_SOL);
+        backfill_msol_collateral(&mut loan, &system_state, 4 * LAMPORTS_PER_SOL).unwrap();
+        assert_eq!(loan.msol_collateral_amount, 4 * LAMPORTS_PER_SOL);
+
+        let mut loan = legacy_loan(10 * LAMPORTS_PER_SOL);
+        backfill_msol_collateral(&mut loan, &SystemState::default(), 4 * LAMPORTS_PER_SOL).unwrap();
+        assert_eq!(loan.msol_collateral_amount, 0);
+    }
+
+    #[test]
+    fn test_loans_without_msol_are_not_backfilled() {
+        let system_state = SystemState { total_sol_collateral: 10 * LAMPORTS_PER_SOL, ..SystemState::default() };
+        for loan_type in [LoanType::NativeSol, LoanType::LpBacked] {
+            let mut loan = MataLoan { loan_type, ..legacy_loan(10 * LAMPORTS_PER_SOL) };
+            backfill_msol_collateral(&mut loan, &system_state, 9 * LAMPORTS_PER_SOL).unwrap();
+            assert_eq!(loan.msol_collateral_amount, 0);
+        }
+
+        let mut loan = MataLoan { msol_collateral_amount: 3, ..legacy_loan(10 * LAMPORTS_PER_SOL) };
+        backfill_msol_collateral(&mut loan, &system_state, 9 * LAMPORTS_PER_SOL).unwrap();
+        assert_eq!(loan.msol_collateral_amount, 3);
+    }
+
+    #[test]
+    fn test_pending_penalty_counts_against_remaining_collateral() {
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            penalty_harvested: 4 * LAMPORTS_PER_SOL,
+            penalty_to_harvest: 5 * LAMPORTS_PER_SOL,
+            ..MataLoan::default()
+        };
+        assert_eq!(calc_remaining_penalty_collateral(&loan), LAMPORTS_PER_SOL);
+
+        // Over accrued before the cap included pending penalty
+        let loan = MataLoan { penalty_to_harvest: 9 * LAMPORTS_PER_SOL, ..loan };
+        assert_eq!(calc_remaining_penalty_collateral(&loan), 0);
+    }
+
+    #[test]
+    fn test_over_accrued_stale_loan_harvests_what_is_left() {
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            msol_collateral_amount: 100 * LAMPORTS_PER_SOL,
+            penalty_harvested: 4 * LAMPORTS_PER_SOL,
+            penalty_to_harvest: 9 * LAMPORTS_PER_SOL,
+            ..MataLoan::default()
+        };
+
+        // Only the 6 SOL the loan has left
+        let actual = calc_harvestable_penalty(&loan, dec!(1.08)).unwrap();
+        assert_eq!(actual, 6 * LAMPORTS_PER_SOL);
+
+        // The loan's msol is worth less than that, 5 msol redeems for 5.4 SOL
+        let loan = MataLoan { msol_collateral_amount: 5 * LAMPORTS_PER_SOL, ..loan };
+        let actual = calc_harvestable_penalty(&loan, dec!(1.08)).unwrap();
+        assert_eq!(actual, 5_400_000_000);
+
+        // Nothing left to take
+        let loan = MataLoan { msol_collateral_amount: 0, ..loan };
+        let actual = calc_harvestable_penalty(&loan, dec!(1.08)).unwrap();
+        assert_eq!(actual, 0);
+    }
+
+    #[test]
+    fn test_harvestable_penalty_is_untouched_when_covered() {
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            msol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            penalty_to_harvest: LAMPORTS_PER_SOL,
+            ..MataLoan::default()
+        };
+
+        assert_eq!(calc_harvestable_penalty(&loan, dec!(1.08)).unwrap(), LAMPORTS_PER_SOL);
+    }
+
+    #[test]
+    fn test_harvest_never_takes_another_loans_msol() {
+        let loan = MataLoan {
+            msol_collateral_amount: 3 * LAMPORTS_PER_SOL,
+            ..MataLoan::default()
+        };
+
+        // The marinade rate fell since accrual, so the penalty converts to more msol than the loan put in
+        assert_eq!(calc_harvest_msol(4 * LAMPORTS_PER_SOL, &loan), 3 * LAMPORTS_PER_SOL);
+        assert_eq!(calc_harvest_msol(LAMPORTS_PER_SOL, &loan), LAMPORTS_PER_SOL);
+    }
+
+    #[test]
+    fn test_depeg_discounts_the_collateral() {
+        // msol redeems for 1.08 SOL but trades 5% below that
+        let marinade_rate = dec!(1.08);
+        let market_rate = marinade_rate * dec!(0.95);
+
+        let actual = calc_collateral_valuation_factor(Bps(100), marinade_rate, Some(market_rate)).unwrap();
+
+        assert_eq!(actual, dec!(0.9405));
+    }
+}
@@ -0,0 +1,211 @@
+use rust_decimal::Decimal;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+    sysvar::clock::Clock,
+};
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        oracle::get_oracle_price,
+        spl::spl_token_transfer,
+    },
+    lucra_core::{
+        pda::{collateral_vault_authority_seeds, find_collateral_vault_authority},
+        quote::calc_collateral_valuation_factor,
+    },
+    state::{CollateralType, SystemState},
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::CollateralTypes);
+
+// Collateral types from the registry are valued at their own oracle's dollar price less their haircut, there's
+// no redemption rate to compare a market price against like msol has. Each type's tokens sit in a vault of
+// their own, owned by an authority derived from the type's mint, so one type's vault can never pay out
+// another's collateral.
+
+/// Dollar value of `amount` base units of a collateral type priced at `price` per whole token
+pub fn calc_collateral_type_value(amount: u64, decimals: u8, price: Decimal, haircut_bps: Bps) -> LucraResult<Decimal> {
+    let base_units_per_token = 10u64
+        .checked_pow(decimals.into())
+        .ok_or(math_err!())?;
+
+    Decimal::from(amount)
+        .checked_mul(price)
+        .ok_or(math_err!())?
+        .checked_div(base_units_per_token.into())
+        .ok_or(math_err!())?
+        .checked_mul(calc_collateral_valuation_factor(haircut_bps, Decimal::ONE, None)?)
+        .ok_or(math_err!())
+}
+
+pub fn get_collateral_type_value(
+    system_state: &SystemState,
+    collateral_type: &CollateralType,
+    oracle_ai: &AccountInfo,
+    amount: u64,
+    clock: &Clock,
+) -> LucraResult<Decimal> {
+    check_eq!(oracle_ai.key, &collateral_type.oracle, LucraErrorCode::InvalidAccountInput)?;
+    let price = get_oracle_price(system_state, oracle_ai, clock)?;
+
+    calc_collateral_type_value(amount, collateral_type.decimals, price, collateral_type.haircut_bps)
+}
+
+pub fn verify_collateral_type_vault(collateral_type: &CollateralType, vault: &Pubkey) -> LucraResult {
+    check_eq!(&collateral_type.vault, vault, LucraErrorCode::InvalidAccountInput)
+}
+
+/// A vault being registered has to be an empty vault of the mint that only its vault authority can move
+/// tokens out of. Returns the authority's bump seed.
+pub fn verify_new_collateral_vault(system_state: &Pubkey, mint: &Pubkey, vault: &Account) -> LucraResult<u8> {
+    let (vault_authority, bump_seed) = find_collateral_vault_authority(system_state, mint);
+    check_eq!(&vault.mint, mint, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&vault.owner, &vault_authority, LucraErrorCode::InvalidAccountInput)?;
+    check!(vault.delegate.is_none(), LucraErrorCode::InvalidAccountInput)?;
+    check!(vault.close_authority.is_none(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(vault.amount, 0, LucraErrorCode::InvalidAccountInput)?;
+
+    Ok(bump_seed)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_from_collateral_type_vault<'a>(
+    program_id: &Pubkey,
+    system_state: &Pubkey,
+    collateral_type: &CollateralType,
+    vault_ai: &AccountInfo<'a>,
+    destination_ai: &AccountInfo<'a>,
+    vault_authority_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+    amount: u64,
+) -> LucraResult {
+    verify_collateral_type_vault(collateral_type, vault_ai.key)?;
+
+    let bump_seed = [collateral_type.vault_authority_bump_seed];
+    let authority_signer_seeds: &[&[u8]] = &collateral_vault_authority_seeds(system_state, &collateral_type.mint, &bump_seed);
+    let vault_authority = Pubkey::create_program_address(authority_signer_seeds, program_id)
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidNonce))?;
+    check_eq!(vault_authority_ai.key, &vault_authority, LucraErrorCode::InvalidAccountInput)?;
+
+    spl_token_transfer(
+        vault_ai,
+        destination_ai,
+        amount,
+        vault_authority_ai,
+        &[authority_signer_seeds],
+        token_program_ai,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use crate::helpers::constants::COLLATERAL_VAULT_AUTHORITY_SEED;
+    use solana_program::program_option::COption;
+    use spl_token::state::AccountState;
+    use crate::helpers::vaults::tests::assert_invalid_account_input;
+
+    fn registered_type(system_state: &Pubkey, haircut_bps: Bps) -> CollateralType {
+        let mint = Pubkey::new_unique();
+        CollateralType {
+            mint,
+            oracle: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            haircut_bps,
+            decimals: 9,
+            enabled: 1,
+            vault_authority_bump_seed: find_collateral_vault_authority(system_state, &mint).1,
+            ..CollateralType::default()
+        }
+    }
+
+    fn empty_vault(mint: Pubkey, owner: Pubkey) -> Account {
+        Account {
+            mint,
+            owner,
+            state: AccountState::Initialized,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn test_haircut_comes_off_the_oracle_price() {
+        // 10 jitoSOL at $45
+        let amount = 10_000_000_000;
+        assert_eq!(calc_collateral_type_value(amount, 9, dec!(45), Bps(0)).unwrap(), dec!(450));
+        assert_eq!(calc_collateral_type_value(amount, 9, dec!(45), Bps(250)).unwrap(), dec!(438.75));
+        assert_eq!(calc_collateral_type_value(amount, 9, dec!(45), Bps(10_000)).unwrap(), dec!(0));
+
+        // Decimals other than 9 scale the amount, not the haircut
+        assert_eq!(calc_collateral_type_value(10_000_000, 6, dec!(45), Bps(250)).unwrap(), dec!(438.75));
+    }
+
+    #[test]
+    fn test_haircut_over_the_whole_value_is_refused() {
+        assert!(calc_collateral_type_value(1, 9, dec!(45), Bps(10_001)).is_err());
+        assert!(calc_collateral_type_value(1, 20, dec!(45), Bps(0)).is_err());
+    }
+
+    #[test]
+    fn test_every_type_has_its_own_vault_authority() {
+        let system_state = Pubkey::new_unique();
+        let jito_sol = registered_type(&system_state, Bps(200));
+        let b_sol = registered_type(&system_state, Bps(300));
+
+        let jito_sol_authority = find_collateral_vault_authority(&system_state, &jito_sol.mint).0;
+        let b_sol_authority = find_collateral_vault_authority(&system_state, &b_sol.mint).0;
+        assert_ne!(jito_sol_authority, b_sol_authority);
+
+        // The bump seed the type recorded signs for its own authority only
+        let derived = Pubkey::create_program_address(
+            &[system_state.as_ref(), COLLATERAL_VAULT_AUTHORITY_SEED, jito_sol.mint.as_ref(), &[jito_sol.vault_authority_bump_seed]],
+            &crate::id(),
+        )
+        .unwrap();
+        assert_eq!(derived, jito_sol_authority);
+
+        // Another system state's authority for the same mint is another account
+        assert_ne!(find_collateral_vault_authority(&Pubkey::new_unique(), &jito_sol.mint).0, jito_sol_authority);
+    }
+
+    #[test]
+    fn test_a_types_vault_is_only_its_own() {
+        let system_state = Pubkey::new_unique();
+        let jito_sol = registered_type(&system_state, Bps(200));
+        let b_sol = registered_type(&system_state, Bps(300));
+
+        assert!(verify_collateral_type_vault(&jito_sol, &jito_sol.vault).is_ok());
+        assert_invalid_account_input(verify_collateral_type_vault(&jito_sol, &b_sol.vault));
+        assert_invalid_account_input(verify_collateral_type_vault(&b_sol, &jito_sol.vault));
+    }
+
+    #[test]
+    fn test_new_vault_has_to_belong_to_the_types_authority() {
+        let system_state = Pubkey::new_unique();
+        let (jito_sol, b_sol) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (authority, bump_seed) = find_collateral_vault_authority(&system_state, &jito_sol);
+        let vault = empty_vault(jito_sol, authority);
+        assert_eq!(verify_new_collateral_vault(&system_state, &jito_sol, &vault).unwrap(), bump_seed);
+
+        // bSOL's vault authority can't hold jitoSOL, and neither can another mint's vault
+        let b_sol_authority = find_collateral_vault_authority(&system_state, &b_sol).0;
+        assert!(verify_new_collateral_vault(&system_state, &jito_sol, &empty_vault(jito_sol, b_sol_authority)).is_err());
+        assert!(verify_new_collateral_vault(&system_state, &jito_sol, &empty_vault(b_sol, authority)).is_err());
+
+        assert!(verify_new_collateral_vault(&system_state, &jito_sol, &Account { amount: 1, ..vault }).is_err());
+        assert!(verify_new_collateral_vault(&system_state, &jito_sol, &Account { delegate: COption::Some(Pubkey::new_unique()), ..vault }).is_err());
+        assert!(verify_new_collateral_vault(&system_state, &jito_sol, &Account { close_authority: COption::Some(Pubkey::new_unique()), ..vault }).is_err());
+    }
+}
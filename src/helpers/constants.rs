@@ -1,10 +1,58 @@
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use solana_program::pubkey::Pubkey;
+use crate::units::{Bps, Lamports, Percent, RewardFee};
 
 pub const SOL_FEE_PLUS_INTEREST: u32 = 5_500;
 pub const LAMPORTS_PER_LUCRA: Decimal = dec!(1_000_000_000);
 pub const LAMPORTS_PER_MATA: Decimal = dec!(1_000_000);
+pub const ABOVE_PEG_HARVEST_TOLERANCE: Decimal = dec!(0.005); // Penalties go to the arb coffer once mata trades this far over $1
+pub const MAX_HARVEST_SLIPPAGE: Bps = Bps(200); // A harvest swap has to pay at least 98% of the mata the oracles value its sol at
+pub const DEFAULT_MSOL_HAIRCUT_BPS: Bps = Bps(100); // Collateral is booked 1% under its redemption value
+pub const DEFAULT_PEG_LOWER_BOUND: Bps = Bps(9_800); // The peg counts as broken under $0.98
+pub const DEFAULT_PEG_UPPER_BOUND: Bps = Bps(10_200); // or over $1.02
+pub const DEFAULT_PENALTY_BAND_WIDTH: Bps = Bps(500); // The penalty multiplier steps up every 5 cents under the peg
+pub const DEFAULT_PENALTY_MULTIPLIER_PER_BAND: u32 = 2; // by 2x
+pub const MAX_REWARD_DECIMALS: u8 = 9; // Crank rewards are minted in whole tokens, scaled by the reward mint decimals
+pub const REWARD_MINT_AUTHORITY_SEED: &[u8] = b"reward_mint_authority";
+pub const LP_VAULT_AUTHORITY_SEED: &[u8] = b"lp_vault_authority";
+pub const EVENT_QUEUE_SEED: &[u8] = b"event_queue";
+pub const PRICE_CORRECTION_SEED: &[u8] = b"price_correction";
+pub const PENALTY_HISTORY_SEED: &[u8] = b"penalty_history";
+pub const TVL_SNAPSHOT_SEED: &[u8] = b"tvl_snapshot";
+pub const LOAN_SEED: &[u8] = b"loan";
+pub const LOAN_REGISTRY_SEED: &[u8] = b"loan_registry";
+pub const SOL_VAULT_SEED: &[u8] = b"sol_vault";
+pub const PRICE_HISTORY_HEADER_SEED: &[u8] = b"price_history_header";
+pub const PRICE_HISTORY_PAGE_SEED: &[u8] = b"price_history_page";
+pub const KEEPER_REGISTRY_SEED: &[u8] = b"keeper_registry";
+pub const COLLATERAL_REGISTRY_SEED: &[u8] = b"collateral_registry";
+pub const COLLATERAL_VAULT_AUTHORITY_SEED: &[u8] = b"collateral_vault_authority";
+pub const TRANSFER_ALLOWLIST_SEED: &[u8] = b"transfer_allowlist";
+pub const MAX_REWARD_TOKENS_PER_MINT: u64 = 10; // Cranks are paid one token, anything far above that is a bug
+pub const MAX_KEEPER_MULTIPLIER: Bps = Bps(30_000); // A registered keeper is paid at most three times a crank's reward
+pub const SETTLEMENT_RECLAIM_DELAY: i64 = 15_552_000; // 180 days after settlement before leftover vault funds can be reclaimed
+pub const UNSTAKE_ALL: u64 = u64::MAX; // StartUnstake for this many lucra unstakes all of the balance the pledges leave unlocked
+pub const UNSTAKE_TIMELOCKS: [i64; 3] = [604_800, 2_592_000, 31_536_000]; // An unstake is claimable a week, a month or a year later, by the stake balance's timeframe
+pub const STAKING_UNFREEZE_DELAY: i64 = 172_800; // 48 hours between an owner asking to unfreeze their staking account and it unfreezing
+pub const TRANSFER_DESTINATION_DELAY: i64 = 172_800; // 48 hours between the DAO allowing a TransferFunds destination and funds going to it
+pub const MAX_TRANSFER_DESTINATIONS: usize = 32;
+pub const ARB_REQUEST_TTL: i64 = 7_200; // An arb request raised by the hourly price history crank stays usable for 2 hours
+pub const MIN_PRICE_UPDATE_INTERVAL: i64 = 3_300; // The hourly price history crank can run up to 5 minutes early
+pub const MIN_DAILY_PRICE_SAMPLES: u16 = 12; // Days with fewer price history updates are zeroed out, cranks are paid less until a day has them
+pub const REWARD_REDEMPTION_NOTICE: i64 = 2_592_000; // 30 days between the DAO setting a reward redemption deadline and it passing
+pub const MAX_REWARD_GRACE_BOOST: Bps = Bps(2_000); // Redemptions in the grace period pay at most 20% more
+pub const MAX_LIQUIDATION_BONUS: Bps = Bps(2_000); // Liquidators are paid at most 20% over the debt they repay
+pub const DEFAULT_REWARD_EXPIRY_EPOCHS: u64 = 26; // Rewards can be claimed for about half a year after they drop
+pub const DEFAULT_MAX_INSTANT_UNSTAKE: Lamports = Lamports(1_000_000_000_000); // 1,000 SOL, closes above this skip the liq pool
+pub const FEE_BUFFER_LAMPORTS: u64 = 50_000; // Kept back in a wallet funding collateral, ten signatures at the 5,000 lamport base fee
+pub const MAX_LCP: Percent = Percent(200); // A loan never needs more than twice its SOL's value staked alongside it
+pub const MAX_REWARD_FEE: RewardFee = RewardFee(50_000); // A redeemed reward token pays at most ten times the 5,000 lamport base fee
+pub const MIN_HARVEST_REWARD_FEES: u64 = 10; // A harvest is worth at least ten reward fees
+pub const DEFAULT_ORACLE_MAX_DEVIATION_BPS: Bps = Bps(100); // SOL/USDC and SOL/USDT can be at most 1% apart
+pub const MAX_ORACLE_DEVIATION_BPS: Bps = Bps(1_000); // and the DAO can't allow more than 10%
+pub const MAX_ORACLE_STALENESS_SLOTS: u64 = 750; // A price is never trusted once it's about 5 minutes old
+pub const PYTH_MAX_CONFIDENCE_BPS: Bps = Bps(200); // A Pyth price is only read while its publishers agree within 2%
 
 #[cfg(not(feature = "devnet"))]
 pub const UNIX_HOUR: i64 = 3_600;
@@ -32,10 +80,26 @@ pub mod orca_swap {
     solana_program::declare_id!("3xQ8SWv2GaFXXpHZNqkXsdxq5DZciHBz6ZFoPPfbFd7U");
 }
 
+pub mod orca_whirlpool {
+    solana_program::declare_id!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+}
+
 pub mod raydium_v4 {
     solana_program::declare_id!("9rpQHSyFVM1dkkHFQ2TtTzPEW7DVmEyPmN8wVniqJtuC");
 }
 
+pub mod spl_token_2022 {
+    solana_program::declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+pub mod token_metadata {
+    solana_program::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
+
+pub mod pyth {
+    solana_program::declare_id!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+}
+
 pub mod wsol {
     solana_program::declare_id!("So11111111111111111111111111111111111111112");
 }
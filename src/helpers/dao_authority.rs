@@ -0,0 +1,102 @@
+use solana_program::pubkey::Pubkey;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::DAO_AUTHORITY,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::DaoAuthority);
+
+// The DAO authority lives in the system state so the multisig can be rotated without a redeploy. The current
+// authority proposes the next one and the handoff only happens once the proposed key signs to accept it, so a
+// mistyped key can't lock the DAO out.
+
+/// System states initialized before the field existed still answer to the compiled in authority
+pub fn get_dao_authority(system_state: &SystemState) -> Pubkey {
+    if system_state.dao_authority == Pubkey::default() {
+        DAO_AUTHORITY
+    } else {
+        system_state.dao_authority
+    }
+}
+
+pub fn check_dao_authority(system_state: &SystemState, authority: &Pubkey) -> LucraResult {
+    check_eq!(authority, &get_dao_authority(system_state), LucraErrorCode::InvalidAccountInput)
+}
+
+/// Proposing the default key withdraws a proposal that hasn't been accepted
+pub fn propose_dao_authority(system_state: &mut SystemState, new_authority: Pubkey) {
+    system_state.pending_dao_authority = new_authority;
+}
+
+pub fn accept_dao_authority(system_state: &mut SystemState, authority: &Pubkey) -> LucraResult {
+    check!(system_state.pending_dao_authority != Pubkey::default(), LucraErrorCode::InvalidStateTransition)?;
+    check_eq!(authority, &system_state.pending_dao_authority, LucraErrorCode::InvalidAccountInput)?;
+
+    system_state.dao_authority = *authority;
+    system_state.pending_dao_authority = Pubkey::default();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_error(result: LucraResult, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_unset_authority_falls_back_to_the_constant() {
+        let system_state = SystemState::default();
+
+        assert_eq!(get_dao_authority(&system_state), DAO_AUTHORITY);
+        assert!(check_dao_authority(&system_state, &DAO_AUTHORITY).is_ok());
+        assert_error(check_dao_authority(&system_state, &Pubkey::new_unique()), LucraErrorCode::InvalidAccountInput);
+    }
+
+    #[test]
+    fn test_old_authority_loses_power_after_the_handoff() {
+        let old_authority = Pubkey::new_unique();
+        let new_authority = Pubkey::new_unique();
+        let mut system_state = SystemState::default();
+        system_state.dao_authority = old_authority;
+
+        propose_dao_authority(&mut system_state, new_authority);
+        // Nothing changes until the new key accepts
+        assert!(check_dao_authority(&system_state, &old_authority).is_ok());
+        assert_error(check_dao_authority(&system_state, &new_authority), LucraErrorCode::InvalidAccountInput);
+
+        accept_dao_authority(&mut system_state, &new_authority).unwrap();
+        assert!(check_dao_authority(&system_state, &new_authority).is_ok());
+        assert_error(check_dao_authority(&system_state, &old_authority), LucraErrorCode::InvalidAccountInput);
+        assert_eq!(system_state.pending_dao_authority, Pubkey::default());
+    }
+
+    #[test]
+    fn test_only_the_proposed_key_can_accept() {
+        let proposed = Pubkey::new_unique();
+        let mut system_state = SystemState::default();
+
+        assert_error(accept_dao_authority(&mut system_state, &proposed), LucraErrorCode::InvalidStateTransition);
+
+        propose_dao_authority(&mut system_state, proposed);
+        assert_error(accept_dao_authority(&mut system_state, &Pubkey::new_unique()), LucraErrorCode::InvalidAccountInput);
+        assert_error(accept_dao_authority(&mut system_state, &DAO_AUTHORITY), LucraErrorCode::InvalidAccountInput);
+        assert_eq!(get_dao_authority(&system_state), DAO_AUTHORITY);
+
+        // A withdrawn proposal can't be accepted either
+        propose_dao_authority(&mut system_state, Pubkey::default());
+        assert_error(accept_dao_authority(&mut system_state, &proposed), LucraErrorCode::InvalidStateTransition);
+    }
+}
@@ -0,0 +1,128 @@
+use std::{cell::RefMut, mem::size_of};
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Slot,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::account_layout::read_meta_data,
+    state::{
+        DataType,
+        EventKind,
+        EventQueue,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::Events);
+
+// The event queue is passed as the last account of an instruction that emits events. It's split off before
+// the instruction reads its accounts, so layouts told apart by their length or with optional accounts read
+// by position look the same as without it. Without it emitting does nothing.
+
+pub fn is_event_queue(program_id: &Pubkey, account_ai: &AccountInfo) -> bool {
+    account_ai.owner == program_id
+        && account_ai.data_len() == size_of::<EventQueue>()
+        && account_ai
+            .try_borrow_data()
+            .ok()
+            .and_then(|data| read_meta_data(&data).ok())
+            .map_or(false, |meta_data| meta_data.is_initialized && meta_data.data_type == DataType::EventQueue as u8)
+}
+
+/// The accounts without a trailing event queue, and the event queue if there was one
+pub fn split_event_queue<'a, 'b>(
+    program_id: &Pubkey,
+    accounts: &'a [AccountInfo<'b>],
+) -> (&'a [AccountInfo<'b>], Option<&'a AccountInfo<'b>>) {
+    match accounts.split_last() {
+        Some((last, rest)) if is_event_queue(program_id, last) => (rest, Some(last)),
+        _ => (accounts, None),
+    }
+}
+
+/// Appends the event to the queue of `system_state`. A no-op without a queue.
+pub fn emit_event(
+    program_id: &Pubkey,
+    event_queue_ai: Option<&AccountInfo>,
+    system_state: &Pubkey,
+    kind: EventKind,
+    principal: &Pubkey,
+    amounts: [u64; 2],
+    slot: Slot,
+) -> LucraResult {
+    let event_queue_ai = match event_queue_ai {
+        Some(event_queue_ai) => event_queue_ai,
+        None => return Ok(()),
+    };
+
+    let mut event_queue: RefMut<EventQueue> = EventQueue::load_mut_checked(event_queue_ai, program_id)?;
+    check_eq!(&event_queue.system_state, system_state, LucraErrorCode::InvalidAccountInput)?;
+    event_queue.push(kind, principal, amounts, slot);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use crate::helpers::account::tests::FakeAccounts;
+
+    fn queue_data(system_state: &Pubkey) -> Vec<u8> {
+        let mut queue = Box::new(EventQueue::zeroed());
+        queue.init(system_state);
+        bytemuck::bytes_of(queue.as_ref()).to_vec()
+    }
+
+    #[test]
+    fn test_trailing_event_queue_is_split_off() {
+        let program_id = Pubkey::new_unique();
+        let system_state = Pubkey::new_unique();
+        let mut accounts = FakeAccounts::new(3)
+            .with_owner(&program_id)
+            .with_data(2, queue_data(&system_state));
+        let infos = accounts.infos();
+
+        let (rest, event_queue_ai) = split_event_queue(&program_id, &infos);
+        assert_eq!(rest.len(), 2);
+        assert_eq!(event_queue_ai.unwrap().key, infos[2].key);
+
+        emit_event(&program_id, event_queue_ai, &system_state, EventKind::Stake, infos[0].key, [7, 0], 9).unwrap();
+        let data = infos[2].try_borrow_data().unwrap();
+        let queue: &EventQueue = bytemuck::from_bytes(&data);
+        assert_eq!(queue.sequence, 1);
+        assert_eq!(queue.events[0].principal, *infos[0].key);
+
+        // Another system state's queue isn't written to
+        drop(data);
+        let other = Pubkey::new_unique();
+        assert!(emit_event(&program_id, event_queue_ai, &other, EventKind::Stake, infos[0].key, [7, 0], 9).is_err());
+    }
+
+    #[test]
+    fn test_accounts_without_a_queue_are_left_alone() {
+        let program_id = Pubkey::new_unique();
+
+        // The last account is the program's but isn't a queue
+        let mut accounts = FakeAccounts::new(2).with_owner(&program_id).with_data(1, vec![0; 64]);
+        let infos = accounts.infos();
+        let (rest, event_queue_ai) = split_event_queue(&program_id, &infos);
+        assert_eq!(rest.len(), 2);
+        assert!(event_queue_ai.is_none());
+        assert!(emit_event(&program_id, None, &Pubkey::new_unique(), EventKind::Stake, infos[0].key, [1, 0], 1).is_ok());
+
+        // A queue owned by another program
+        let mut accounts = FakeAccounts::new(1).with_data(0, queue_data(&Pubkey::new_unique()));
+        let infos = accounts.infos();
+        assert!(split_event_queue(&program_id, &infos).1.is_none());
+
+        assert_eq!(split_event_queue(&program_id, &[]).0.len(), 0);
+    }
+}
@@ -0,0 +1,179 @@
+use std::mem::size_of;
+
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::account_layout::read_meta_data,
+    state::{
+        DataType,
+        KeeperRegistry,
+        SystemState,
+    },
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::Keepers);
+
+// A keeper runs a crank by passing the keeper registry and signing as the last two accounts. Like the event
+// queue they're split off before the crank reads its accounts, so optional accounts read by position are where
+// they were without them. Anyone else runs it anonymously and is paid the crank's reward as it is, unless the
+// DAO restricted cranking to keepers.
+
+pub fn is_keeper_registry(program_id: &Pubkey, account_ai: &AccountInfo) -> bool {
+    account_ai.owner == program_id
+        && account_ai.data_len() == size_of::<KeeperRegistry>()
+        && account_ai
+            .try_borrow_data()
+            .ok()
+            .and_then(|data| read_meta_data(&data).ok())
+            .map_or(false, |meta_data| meta_data.is_initialized && meta_data.data_type == DataType::KeeperRegistry as u8)
+}
+
+/// The accounts without the trailing keeper registry and keeper, and those two if they were passed
+#[allow(clippy::type_complexity)]
+pub fn split_keeper<'a, 'b>(
+    program_id: &Pubkey,
+    accounts: &'a [AccountInfo<'b>],
+) -> (&'a [AccountInfo<'b>], Option<(&'a AccountInfo<'b>, &'a AccountInfo<'b>)>) {
+    match accounts {
+        [rest @ .., registry_ai, keeper_ai] if is_keeper_registry(program_id, registry_ai) => (rest, Some((registry_ai, keeper_ai))),
+        _ => (accounts, None),
+    }
+}
+
+/// What the caller's crank reward is multiplied by, its multiplier for a keeper that signed and the reward as
+/// it is for anyone else
+pub fn resolve_crank_caller(
+    program_id: &Pubkey,
+    system_state: &SystemState,
+    keeper: Option<(&AccountInfo, &AccountInfo)>,
+) -> LucraResult<Bps> {
+    let multiplier = match keeper {
+        Some((registry_ai, keeper_ai)) => {
+            check_eq!(keeper_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+            let registry = KeeperRegistry::load_checked(registry_ai, program_id)?;
+            check_eq!(&registry.system_state, &system_state.key, LucraErrorCode::InvalidAccountInput)?;
+            registry.multiplier_of(keeper_ai.key)
+        }
+        None => None,
+    };
+
+    check_crank_caller(system_state, multiplier)
+}
+
+/// While cranking is restricted a caller that isn't a registered keeper can't run a crank at all
+pub fn check_crank_caller(system_state: &SystemState, keeper_multiplier: Option<Bps>) -> LucraResult<Bps> {
+    match keeper_multiplier {
+        Some(multiplier) => Ok(multiplier),
+        None => {
+            check!(!system_state.restricted_cranking, LucraErrorCode::CrankingRestricted)?;
+            Ok(Bps::ONE_HUNDRED_PERCENT)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use crate::{
+        error::LucraError,
+        helpers::account::tests::FakeAccounts,
+    };
+
+    fn registry_data(system_state: &Pubkey, keepers: &[(Pubkey, Bps)]) -> Vec<u8> {
+        let mut registry = Box::new(KeeperRegistry::zeroed());
+        registry.init(system_state);
+        for (keeper, multiplier) in keepers {
+            registry.register(keeper, *multiplier).unwrap();
+        }
+        bytemuck::bytes_of(registry.as_ref()).to_vec()
+    }
+
+    fn keeper_state(restricted_cranking: bool) -> SystemState {
+        let mut system_state = SystemState::zeroed();
+        system_state.key = Pubkey::new_unique();
+        system_state.restricted_cranking = restricted_cranking;
+        system_state
+    }
+
+    fn assert_error(result: LucraResult<Bps>, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_trailing_keeper_is_split_off() {
+        let program_id = Pubkey::new_unique();
+        let system_state = Pubkey::new_unique();
+        let mut accounts = FakeAccounts::new(4)
+            .with_owner(&program_id)
+            .with_data(2, registry_data(&system_state, &[]));
+        let infos = accounts.infos();
+
+        let (rest, keeper) = split_keeper(&program_id, &infos);
+        assert_eq!(rest.len(), 2);
+        let (registry_ai, keeper_ai) = keeper.unwrap();
+        assert_eq!(registry_ai.key, infos[2].key);
+        assert_eq!(keeper_ai.key, infos[3].key);
+
+        // Anywhere but second to last it's left where it is
+        let (rest, keeper) = split_keeper(&program_id, &infos[..3]);
+        assert_eq!(rest.len(), 3);
+        assert!(keeper.is_none());
+    }
+
+    #[test]
+    fn test_anonymous_callers_are_paid_the_base_reward() {
+        assert_eq!(check_crank_caller(&keeper_state(false), None).unwrap(), Bps::ONE_HUNDRED_PERCENT);
+        assert_eq!(check_crank_caller(&keeper_state(false), Some(Bps(15_000))).unwrap(), Bps(15_000));
+    }
+
+    #[test]
+    fn test_restricted_cranking_only_lets_keepers_in() {
+        let system_state = keeper_state(true);
+        assert_error(check_crank_caller(&system_state, None), LucraErrorCode::CrankingRestricted);
+        assert_eq!(check_crank_caller(&system_state, Some(Bps(10_000))).unwrap(), Bps(10_000));
+        // A keeper the DAO zeroed out can still crank, it's just not paid
+        assert_eq!(check_crank_caller(&system_state, Some(Bps(0))).unwrap(), Bps(0));
+    }
+
+    #[test]
+    fn test_keeper_is_looked_up_in_the_registry() {
+        let program_id = Pubkey::new_unique();
+        let system_state = keeper_state(true);
+        let keeper = Pubkey::new_unique();
+        let mut accounts = FakeAccounts::new(2)
+            .with_owner(&program_id)
+            .with_key(1, &keeper)
+            .with_data(0, registry_data(&system_state.key, &[(keeper, Bps(20_000))]));
+        let mut infos = accounts.infos();
+
+        // The keeper has to sign
+        assert_error(resolve_crank_caller(&program_id, &system_state, Some((&infos[0], &infos[1]))), LucraErrorCode::AccountNotSigner);
+
+        infos[1].is_signer = true;
+        assert_eq!(resolve_crank_caller(&program_id, &system_state, Some((&infos[0], &infos[1]))).unwrap(), Bps(20_000));
+
+        // A signer that isn't in the registry is as anonymous as passing no keeper at all
+        let mut stranger = FakeAccounts::new(1);
+        let mut stranger_infos = stranger.infos();
+        stranger_infos[0].is_signer = true;
+        assert_error(resolve_crank_caller(&program_id, &system_state, Some((&infos[0], &stranger_infos[0]))), LucraErrorCode::CrankingRestricted);
+        assert_error(resolve_crank_caller(&program_id, &system_state, None), LucraErrorCode::CrankingRestricted);
+
+        // Another system state's registry doesn't count
+        let other = keeper_state(true);
+        assert_error(resolve_crank_caller(&program_id, &other, Some((&infos[0], &infos[1]))), LucraErrorCode::InvalidAccountInput);
+    }
+}
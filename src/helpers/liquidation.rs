@@ -0,0 +1,190 @@
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use solana_program::native_token::LAMPORTS_PER_SOL;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::{LAMPORTS_PER_MATA, MAX_LIQUIDATION_BONUS},
+    state::SystemState,
+    units::{Bps, CollateralRatio},
+};
+
+declare_check_assert_macros!(SourceFileId::Liquidation);
+
+// Penalties only wear a loan's collateral down a day at a time. Once its collateral is worth less than the
+// liquidation ratio of its debt anyone can repay the debt and take the collateral, at the debt's value plus
+// the bonus. Whatever collateral is left over goes to the arb coffer, or stays in the LP vault for LP tokens,
+// the owner forfeits it.
+
+/// A ratio of 0 turns liquidation off. Otherwise it has to sit between the debt itself and the collateral
+/// requirement, a loan can't be liquidated right after it's opened.
+pub fn set_liquidation_params(system_state: &mut SystemState, liquidation_ratio: CollateralRatio, liquidation_bonus: Bps) -> LucraResult {
+    if liquidation_ratio != CollateralRatio(0) {
+        check!(liquidation_ratio >= CollateralRatio(100), LucraErrorCode::InvalidAmount)?;
+        check!(liquidation_ratio < system_state.collateral_requirement, LucraErrorCode::InvalidAmount)?;
+    }
+    check!(liquidation_bonus <= MAX_LIQUIDATION_BONUS, LucraErrorCode::InvalidAmount)?;
+
+    system_state.liquidation_ratio = liquidation_ratio;
+    system_state.liquidation_bonus = liquidation_bonus;
+
+    Ok(())
+}
+
+/// Collateral worth strictly less than the liquidation ratio of the debt can be liquidated
+pub fn is_liquidatable(collateral_value: Decimal, loan_amount: u64, liquidation_ratio: CollateralRatio) -> LucraResult<bool> {
+    if liquidation_ratio == CollateralRatio(0) {
+        return Ok(false);
+    }
+
+    let threshold = Decimal::from(loan_amount)
+        .checked_div(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .checked_mul(liquidation_ratio.to_decimal())
+        .ok_or(math_err!())?;
+
+    Ok(collateral_value < threshold)
+}
+
+/// Lamports of collateral a liquidation hands out
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LiquidationSplit {
+    pub to_liquidator: u64,
+    pub to_coffer: u64,
+}
+
+/// The liquidator gets the debt's value in SOL plus the bonus, rounded down and never more than the loan has
+/// left. The rest goes to the coffer.
+pub fn calc_liquidation_split(collateral_lamports: u64, loan_amount: u64, sol_price: Decimal, liquidation_bonus: Bps) -> LucraResult<LiquidationSplit> {
+    let owed = Decimal::from(loan_amount)
+        .checked_div(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .checked_mul(Decimal::ONE + liquidation_bonus.to_decimal())
+        .ok_or(math_err!())?
+        .checked_div(sol_price)
+        .ok_or(math_err!())?
+        .checked_mul(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())?
+        .floor();
+    let to_liquidator = owed
+        .min(Decimal::from(collateral_lamports))
+        .to_u64()
+        .ok_or(math_err!())?;
+
+    Ok(LiquidationSplit {
+        to_liquidator,
+        to_coffer: collateral_lamports - to_liquidator,
+    })
+}
+
+/// LP tokens a liquidation hands out. The liquidator gets the LP tokens worth the debt plus the bonus at
+/// `collateral_value`, what `lp_amount` of them is worth, rounded down and never more than the loan has left.
+/// The rest stays in the LP vault.
+pub fn calc_lp_liquidation_split(lp_amount: u64, collateral_value: Decimal, loan_amount: u64, liquidation_bonus: Bps) -> LucraResult<LiquidationSplit> {
+    let owed_value = Decimal::from(loan_amount)
+        .checked_div(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .checked_mul(Decimal::ONE + liquidation_bonus.to_decimal())
+        .ok_or(math_err!())?;
+    let to_liquidator = if owed_value >= collateral_value {
+        lp_amount
+    } else {
+        Decimal::from(lp_amount)
+            .checked_mul(owed_value)
+            .ok_or(math_err!())?
+            .checked_div(collateral_value)
+            .ok_or(math_err!())?
+            .floor()
+            .to_u64()
+            .ok_or(math_err!())?
+    };
+
+    Ok(LiquidationSplit {
+        to_liquidator,
+        to_coffer: lp_amount - to_liquidator,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn assert_error<T: std::fmt::Debug>(result: LucraResult<T>, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_liquidatable_only_under_the_ratio() {
+        // 100 mata at 110% needs $110 of collateral
+        assert!(is_liquidatable(dec!(109.999999), 100_000_000, CollateralRatio(110)).unwrap());
+        assert!(!is_liquidatable(dec!(110), 100_000_000, CollateralRatio(110)).unwrap());
+        assert!(!is_liquidatable(dec!(150), 100_000_000, CollateralRatio(110)).unwrap());
+    }
+
+    #[test]
+    fn test_no_ratio_never_liquidates() {
+        assert!(!is_liquidatable(Decimal::ZERO, 100_000_000, CollateralRatio(0)).unwrap());
+    }
+
+    #[test]
+    fn test_liquidator_gets_the_debt_plus_the_bonus() {
+        // 100 mata plus 5% is $105, 5.25 SOL at $20. The loan's other 0.25 SOL goes to the coffer
+        let split = calc_liquidation_split(11 * LAMPORTS_PER_SOL / 2, 100_000_000, dec!(20), Bps(500)).unwrap();
+
+        assert_eq!(split, LiquidationSplit { to_liquidator: 5_250_000_000, to_coffer: 250_000_000 });
+    }
+
+    #[test]
+    fn test_liquidator_share_is_capped_at_the_collateral() {
+        // $105 is owed but the 5 SOL is only worth $100
+        let split = calc_liquidation_split(5 * LAMPORTS_PER_SOL, 100_000_000, dec!(20), Bps(500)).unwrap();
+
+        assert_eq!(split, LiquidationSplit { to_liquidator: 5 * LAMPORTS_PER_SOL, to_coffer: 0 });
+    }
+
+    #[test]
+    fn test_liquidator_share_rounds_down() {
+        // $1 at $3 is 0.333333333_3 SOL
+        let split = calc_liquidation_split(LAMPORTS_PER_SOL, 1_000_000, dec!(3), Bps(0)).unwrap();
+
+        assert_eq!(split, LiquidationSplit { to_liquidator: 333_333_333, to_coffer: 666_666_667 });
+    }
+
+    #[test]
+    fn test_lp_liquidator_gets_the_debt_plus_the_bonus() {
+        // 100 mata plus 5% is $105 of the $120 the 4 LP tokens are worth
+        let split = calc_lp_liquidation_split(4_000_000, dec!(120), 100_000_000, Bps(500)).unwrap();
+        assert_eq!(split, LiquidationSplit { to_liquidator: 3_500_000, to_coffer: 500_000 });
+
+        // Worth less than what's owed, the liquidator takes all of it
+        let split = calc_lp_liquidation_split(4_000_000, dec!(100), 100_000_000, Bps(500)).unwrap();
+        assert_eq!(split, LiquidationSplit { to_liquidator: 4_000_000, to_coffer: 0 });
+    }
+
+    #[test]
+    fn test_params_stay_under_the_collateral_requirement() {
+        let mut system_state = SystemState::default();
+        system_state.collateral_requirement = CollateralRatio(150);
+
+        set_liquidation_params(&mut system_state, CollateralRatio(110), Bps(500)).unwrap();
+        assert_eq!(system_state.liquidation_ratio, CollateralRatio(110));
+        assert_eq!(system_state.liquidation_bonus, Bps(500));
+
+        assert_error(set_liquidation_params(&mut system_state, CollateralRatio(150), Bps(500)), LucraErrorCode::InvalidAmount);
+        assert_error(set_liquidation_params(&mut system_state, CollateralRatio(99), Bps(500)), LucraErrorCode::InvalidAmount);
+        assert_error(set_liquidation_params(&mut system_state, CollateralRatio(110), Bps(2_001)), LucraErrorCode::InvalidAmount);
+        assert_eq!(system_state.liquidation_ratio, CollateralRatio(110));
+
+        // Turning it off is always allowed
+        set_liquidation_params(&mut system_state, CollateralRatio(0), Bps(0)).unwrap();
+        assert_eq!(system_state.liquidation_ratio, CollateralRatio(0));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{cell::RefMut, mem::size_of};
+
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::solana::create_pda_account,
+    lucra_core::pda::{find_loan_address, find_loan_registry, loan_registry_seeds, loan_seeds},
+    state::{
+        LoanRegistry,
+        MataLoan,
+        LEGACY_LOAN_INDEX,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::LoanAddress);
+
+/// Creates the account of `user`'s next loan at its PDA, and the user's loan registry with it on their first
+/// loan. Returns the index the loan was opened with, the loan itself is left for the caller to initialize.
+pub fn create_loan_account<'a>(
+    program_id: &Pubkey,
+    user_ai: &AccountInfo<'a>,
+    loan_ai: &AccountInfo<'a>,
+    loan_registry_ai: &AccountInfo<'a>,
+    system_program_ai: &AccountInfo<'a>,
+) -> LucraResult<u64> {
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let (loan_registry, registry_bump_seed) = find_loan_registry(user_ai.key);
+    check_eq!(loan_registry_ai.key, &loan_registry, LucraErrorCode::InvalidAccountInput)?;
+
+    let rent = Rent::get()?;
+    if loan_registry_ai.data_is_empty() {
+        create_pda_account(
+            user_ai,
+            loan_registry_ai,
+            rent.minimum_balance(size_of::<LoanRegistry>()),
+            size_of::<LoanRegistry>() as u64,
+            program_id,
+            &[&loan_registry_seeds(user_ai.key, &[registry_bump_seed])],
+            system_program_ai,
+        )?;
+        LoanRegistry::load_mut(loan_registry_ai)?.init(user_ai.key);
+    }
+
+    let mut registry: RefMut<LoanRegistry> = LoanRegistry::load_mut_checked(loan_registry_ai, program_id)?;
+    check_eq!(&registry.owner, user_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let loan_index = registry.next_loan_index()?;
+    let (loan, loan_bump_seed) = find_loan_address(user_ai.key, loan_index);
+    check_eq!(loan_ai.key, &loan, LucraErrorCode::InvalidAccountInput)?;
+    check!(loan_ai.data_is_empty(), LucraErrorCode::AlreadyProcessed)?;
+
+    create_pda_account(
+        user_ai,
+        loan_ai,
+        rent.minimum_balance(size_of::<MataLoan>()),
+        size_of::<MataLoan>() as u64,
+        program_id,
+        &[&loan_seeds(user_ai.key, &loan_index.to_le_bytes(), &[loan_bump_seed])],
+        system_program_ai,
+    )?;
+
+    registry.record_loan()
+}
+
+/// A loan has to be at the address of its creator and index. The owner can't stand in for the creator, the
+/// loan keeps its address when its ownership is transferred. A legacy loan has no index and was opened at an
+/// address of its creator's choosing, so it's left alone.
+pub fn verify_loan_address(loan: &Pubkey, creator: &Pubkey, loan_index: u64) -> LucraResult {
+    if loan_index == LEGACY_LOAN_INDEX {
+        return Ok(());
+    }
+
+    check_eq!(loan, &find_loan_address(creator, loan_index).0, LucraErrorCode::InvalidAccountInput)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loan_must_be_at_its_derived_address() {
+        let creator = Pubkey::new_unique();
+        let loan = find_loan_address(&creator, 2).0;
+
+        assert!(verify_loan_address(&loan, &creator, 2).is_ok());
+        assert!(verify_loan_address(&loan, &creator, 1).is_err());
+        assert!(verify_loan_address(&loan, &creator, 3).is_err());
+        assert!(verify_loan_address(&loan, &Pubkey::new_unique(), 2).is_err());
+        assert!(verify_loan_address(&Pubkey::new_unique(), &creator, 2).is_err());
+    }
+
+    #[test]
+    fn test_legacy_loans_keep_working_at_any_address() {
+        let creator = Pubkey::new_unique();
+
+        assert!(verify_loan_address(&Pubkey::new_unique(), &creator, LEGACY_LOAN_INDEX).is_ok());
+        assert!(verify_loan_address(&Pubkey::new_unique(), &Pubkey::default(), LEGACY_LOAN_INDEX).is_ok());
+        assert!(verify_loan_address(&find_loan_address(&creator, 1).0, &creator, LEGACY_LOAN_INDEX).is_ok());
+    }
+}
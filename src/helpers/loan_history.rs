@@ -0,0 +1,118 @@
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::account_layout::RESERVED_LAYOUT_VERSION,
+    lucra_core::interest::LOAN_INTEREST_VERSION,
+    state::MataLoan,
+};
+
+declare_check_assert_macros!(SourceFileId::LoanHistory);
+
+// Loans opened on LOAN_HISTORY_VERSION remember the collateral they were opened with and every top up since, so
+// the first deposit can be told apart from what was added later. The fields are carved out of the reserved region
+// and the layout is otherwise the same, a loan from before reads them as zero and has no opening collateral on
+// record. Collateral is counted in the loan's own units, lamports or LP tokens for an LP backed loan.
+
+pub const LOAN_HISTORY_VERSION: u8 = 2;
+
+/// Loans on the reserved layout are read on every version since, each one only adds fields out of it
+pub fn is_supported_loan_version(version: u8) -> bool {
+    (RESERVED_LAYOUT_VERSION..=LOAN_INTEREST_VERSION).contains(&version)
+}
+
+/// Starts the history of a loan that's being opened, or one that rolled over and starts over like a new loan
+pub fn open_collateral_history(loan: &mut MataLoan, collateral: u64) {
+    loan.initial_collateral = collateral;
+    loan.total_collateral_added = 0;
+    loan.times_collateral_added = 0;
+}
+
+/// Loans from before the history keep counting top ups from the first one after it
+pub fn record_collateral_added(loan: &mut MataLoan, collateral: u64) -> LucraResult {
+    loan.total_collateral_added = loan.total_collateral_added
+        .checked_add(collateral)
+        .ok_or(math_err!())?;
+    loan.times_collateral_added = loan.times_collateral_added.saturating_add(1);
+
+    Ok(())
+}
+
+/// What the loan was opened with, None for a loan opened before the history was kept
+pub fn initial_collateral(loan: &MataLoan) -> Option<u64> {
+    if loan.meta_data.version < LOAN_HISTORY_VERSION {
+        return None;
+    }
+
+    Some(loan.initial_collateral)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{DataType, MetaData};
+
+    fn loan_on(version: u8) -> MataLoan {
+        MataLoan {
+            meta_data: MetaData::new(DataType::Loan, version, true),
+            ..MataLoan::default()
+        }
+    }
+
+    #[test]
+    fn test_history_counts_every_top_up() {
+        let mut loan = loan_on(LOAN_HISTORY_VERSION);
+        open_collateral_history(&mut loan, 5_000);
+
+        record_collateral_added(&mut loan, 1_000).unwrap();
+        record_collateral_added(&mut loan, 250).unwrap();
+        assert_eq!(initial_collateral(&loan), Some(5_000));
+        assert_eq!(loan.total_collateral_added, 1_250);
+        assert_eq!(loan.times_collateral_added, 2);
+
+        // A rollover starts it over
+        open_collateral_history(&mut loan, 8_000);
+        assert_eq!(initial_collateral(&loan), Some(8_000));
+        assert_eq!(loan.total_collateral_added, 0);
+        assert_eq!(loan.times_collateral_added, 0);
+
+        loan.total_collateral_added = u64::MAX;
+        assert!(record_collateral_added(&mut loan, 1).is_err());
+        assert_eq!(loan.total_collateral_added, u64::MAX);
+    }
+
+    #[test]
+    fn test_count_saturates() {
+        let mut loan = loan_on(LOAN_HISTORY_VERSION);
+        loan.times_collateral_added = u16::MAX;
+
+        record_collateral_added(&mut loan, 1).unwrap();
+        assert_eq!(loan.times_collateral_added, u16::MAX);
+    }
+
+    #[test]
+    fn test_older_loans_have_no_opening_collateral() {
+        // The reserved bytes read as zero, that isn't what the loan was opened with
+        let mut loan = loan_on(RESERVED_LAYOUT_VERSION);
+        assert_eq!(initial_collateral(&loan), None);
+
+        // Top ups after the upgrade are still counted
+        record_collateral_added(&mut loan, 1_000).unwrap();
+        assert_eq!(loan.total_collateral_added, 1_000);
+        assert_eq!(loan.times_collateral_added, 1);
+        assert_eq!(initial_collateral(&loan), None);
+    }
+
+    #[test]
+    fn test_supported_loan_versions() {
+        assert!(!is_supported_loan_version(0));
+        assert!(is_supported_loan_version(RESERVED_LAYOUT_VERSION));
+        assert!(is_supported_loan_version(LOAN_HISTORY_VERSION));
+        assert!(is_supported_loan_version(LOAN_INTEREST_VERSION));
+        assert!(!is_supported_loan_version(LOAN_INTEREST_VERSION + 1));
+    }
+}
@@ -0,0 +1,153 @@
+use rust_decimal::Decimal;
+use solana_program::{
+    account_info::AccountInfo,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_token::state::Mint;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        account::get_orca_pool_accounts,
+        constants::{orca_swap, SOL_MATA_ORCA_AMM},
+        spl::{get_token_balance, spl_token_transfer},
+        spltokenswap::{calc_lp_fair_value, get_orca_pool_mint},
+        vaults::verify_lp_vault,
+    },
+    lucra_core::pda::lp_vault_authority_seeds,
+    state::SystemState,
+    units::CollateralRatio,
+};
+
+pub use crate::lucra_core::pda::find_lp_vault_authority;
+
+declare_check_assert_macros!(SourceFileId::LpCollateral);
+
+// LP backed loans hold SOL/MATA orca LP tokens in the LP vault instead of msol. The DAO registers the
+// vault and a collateral requirement of its own, until it does there's nothing to borrow against.
+
+pub fn check_lp_loans_enabled(system_state: &SystemState) -> LucraResult {
+    check!(system_state.lp_vault.address != Pubkey::default(), LucraErrorCode::LoansNotEnabled)?;
+    check!(system_state.lp_collateral_requirement > CollateralRatio(0), LucraErrorCode::LoansNotEnabled)
+}
+
+/// LP collateral has to be overcollateralized at least as much as msol
+pub fn verify_lp_collateral_requirement(system_state: &SystemState, lp_collateral_requirement: CollateralRatio) -> LucraResult {
+    if lp_collateral_requirement == CollateralRatio(0) {
+        return Ok(());
+    }
+
+    check!(lp_collateral_requirement >= system_state.collateral_requirement, LucraErrorCode::InvalidAmount)
+}
+
+/// Dollar value of `lp_amount` LP tokens. Mata is never counted above the peg.
+#[allow(clippy::too_many_arguments)]
+pub fn get_lp_collateral_value(
+    system_state: &SystemState,
+    pool_ai: &AccountInfo,
+    pool_sol_vault_ai: &AccountInfo,
+    pool_mata_vault_ai: &AccountInfo,
+    lp_mint_ai: &AccountInfo,
+    lp_amount: u64,
+    sol_usd_price: Decimal,
+    mata_usd_price: Decimal,
+) -> LucraResult<Decimal> {
+    check_eq!(pool_ai.key, &SOL_MATA_ORCA_AMM, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(pool_ai.owner, &orca_swap::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(lp_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(lp_mint_ai.key, &system_state.lp_mint, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&get_orca_pool_mint(pool_ai)?, lp_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let (sol_vault, mata_vault) = get_orca_pool_accounts(pool_ai)?;
+    check_eq!(pool_sol_vault_ai.key, &sol_vault, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(pool_mata_vault_ai.key, &mata_vault, LucraErrorCode::InvalidAccountInput)?;
+
+    let lp_supply = Mint::unpack(&lp_mint_ai.try_borrow_data()?)?.supply;
+
+    calc_lp_fair_value(
+        lp_amount,
+        lp_supply,
+        get_token_balance(pool_sol_vault_ai)?,
+        get_token_balance(pool_mata_vault_ai)?,
+        sol_usd_price,
+        mata_usd_price.min(Decimal::ONE),
+    )
+}
+
+pub fn transfer_from_lp_vault<'a>(
+    program_id: &Pubkey,
+    system_state: &SystemState,
+    lp_vault_ai: &AccountInfo<'a>,
+    destination_ai: &AccountInfo<'a>,
+    lp_vault_authority_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+    amount: u64,
+) -> LucraResult {
+    verify_lp_vault(system_state, lp_vault_ai.key)?;
+    let bump_seed = [system_state.lp_vault.authority_bump_seed];
+    let authority_signer_seeds: &[&[u8]] = &lp_vault_authority_seeds(&system_state.key, &bump_seed);
+    let lp_vault_authority = Pubkey::create_program_address(authority_signer_seeds, program_id)
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidNonce))?;
+    check_eq!(lp_vault_authority_ai.key, &lp_vault_authority, LucraErrorCode::InvalidAccountInput)?;
+
+    spl_token_transfer(
+        lp_vault_ai,
+        destination_ai,
+        amount,
+        lp_vault_authority_ai,
+        &[authority_signer_seeds],
+        token_program_ai,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::constants::LP_VAULT_AUTHORITY_SEED;
+    use crate::helpers::vaults::tests::system_state_with_vaults;
+
+    #[test]
+    fn test_lp_loans_need_a_vault_and_a_requirement() {
+        let mut system_state = SystemState::default();
+        assert!(check_lp_loans_enabled(&system_state).is_err());
+
+        system_state.lp_vault.address = Pubkey::new_unique();
+        assert!(check_lp_loans_enabled(&system_state).is_err());
+
+        system_state.lp_collateral_requirement = CollateralRatio(200);
+        assert!(check_lp_loans_enabled(&system_state).is_ok());
+    }
+
+    #[test]
+    fn test_lp_requirement_is_at_least_the_msol_one() {
+        let mut system_state = system_state_with_vaults();
+        system_state.collateral_requirement = CollateralRatio(150);
+
+        assert!(verify_lp_collateral_requirement(&system_state, CollateralRatio(0)).is_ok());
+        assert!(verify_lp_collateral_requirement(&system_state, CollateralRatio(150)).is_ok());
+        assert!(verify_lp_collateral_requirement(&system_state, CollateralRatio(200)).is_ok());
+        assert!(verify_lp_collateral_requirement(&system_state, CollateralRatio(149)).is_err());
+    }
+
+    #[test]
+    fn test_signer_seeds_derive_the_lp_vault_authority() {
+        let system_state = Pubkey::new_unique();
+        let (authority, bump_seed) = find_lp_vault_authority(&system_state);
+
+        let derived = Pubkey::create_program_address(
+            &[system_state.as_ref(), LP_VAULT_AUTHORITY_SEED, &[bump_seed]],
+            &crate::id(),
+        )
+        .unwrap();
+
+        assert_eq!(derived, authority);
+    }
+}
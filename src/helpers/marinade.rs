@@ -6,7 +6,66 @@ use anchor_lang::{
 use marinade_onchain_helper::cpi_context_accounts::{MarinadeLiquidUnstake, MarinadeDeposit};
 use solana_program::{
     account_info::AccountInfo,
+    rent::Rent,
+    sysvar::Sysvar,
 };
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        account::verify_program_account,
+        constants::DEFAULT_MAX_INSTANT_UNSTAKE,
+    },
+    state::SystemState,
+    units::Lamports,
+};
+
+declare_check_assert_macros!(SourceFileId::Marinade);
+
+pub fn find_msol_mint_authority(marinade_state: &Pubkey) -> (Pubkey, u8) {
+    marinade_finance::state::State::find_msol_mint_authority(marinade_state)
+}
+
+pub fn find_liq_pool_msol_leg_authority(marinade_state: &Pubkey) -> (Pubkey, u8) {
+    marinade_finance::liq_pool::LiqPool::find_msol_leg_authority(marinade_state)
+}
+
+/// Lamports the liq pool can pay out, the sol leg has to stay rent exempt
+pub fn calc_liq_pool_available_lamports(sol_leg_lamports: u64, rent_floor: u64) -> u64 {
+    sol_leg_lamports.saturating_sub(rent_floor)
+}
+
+pub fn verify_liq_pool_covers(sol_leg_lamports: u64, rent_floor: u64, lamports: u64) -> LucraResult {
+    check!(
+        calc_liq_pool_available_lamports(sol_leg_lamports, rent_floor) >= lamports,
+        LucraErrorCode::MarinadeLiquidityInsufficient
+    )
+}
+
+/// Run before a liquid unstake of `lamports` worth of msol. A shallow pool fails deep in the marinade CPI
+/// otherwise, which keepers can't tell apart from any other failure.
+pub fn verify_liquid_unstake_liquidity(liq_pool_sol_leg_pda: &AccountInfo, lamports: u64) -> LucraResult {
+    let rent = Rent::get()?;
+
+    verify_liq_pool_covers(liq_pool_sol_leg_pda.lamports(), rent.minimum_balance(0), lamports)
+}
+
+/// System states initialized before the limit existed read it as zero, they get the default
+pub fn max_instant_unstake(system_state: &SystemState) -> Lamports {
+    match system_state.max_instant_unstake {
+        Lamports(0) => DEFAULT_MAX_INSTANT_UNSTAKE,
+        max_instant_unstake => max_instant_unstake,
+    }
+}
+
+/// Closes over the DAO's limit would drain the liq pool for everyone else, those take the msol instead
+pub fn verify_instant_unstake_size(lamports: u64, max_instant_unstake: Lamports) -> LucraResult {
+    check!(Lamports(lamports) <= max_instant_unstake, LucraErrorCode::InstantUnstakeTooLarge)
+}
 
 #[allow(clippy::too_many_arguments)]
 pub fn deposit<'a>(
@@ -25,6 +84,8 @@ pub fn deposit<'a>(
     marinade_program: &AccountInfo<'a>,
     lamports: u64,
 ) -> ProgramResult {
+    verify_program_account(marinade_program, &marinade_finance::id())?;
+
     let cpi_accounts = MarinadeDeposit {
         state: marinade_state.clone(),
         msol_mint: msol_mint.clone(),
@@ -69,6 +130,8 @@ pub fn liquid_unstake<'a>(
     marinade_program: &AccountInfo<'a>,
     msol_amount: u64,
 ) -> ProgramResult {
+    verify_program_account(marinade_program, &marinade_finance::id())?;
+
     let cpi_accounts = MarinadeLiquidUnstake {
         state: marinade_state.clone(),
         msol_mint: msol_mint.clone(),
@@ -94,4 +157,80 @@ pub fn liquid_unstake<'a>(
         &ix,
         &cpi_ctx.to_account_infos(),
     )
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::LucraError;
+    use crate::helpers::account::tests::{assert_invalid_program_account_code, FakeAccounts};
+
+    const RENT_FLOOR: u64 = 890_880;
+
+    #[test]
+    fn test_available_lamports_leave_the_rent_floor() {
+        assert_eq!(calc_liq_pool_available_lamports(10_000_000_000, RENT_FLOOR), 10_000_000_000 - RENT_FLOOR);
+        assert_eq!(calc_liq_pool_available_lamports(RENT_FLOOR, RENT_FLOOR), 0);
+        assert_eq!(calc_liq_pool_available_lamports(0, RENT_FLOOR), 0);
+    }
+
+    #[test]
+    fn test_deep_pool_covers_the_unstake() {
+        assert!(verify_liq_pool_covers(10_000_000_000, RENT_FLOOR, 5_000_000_000).is_ok());
+        assert!(verify_liq_pool_covers(5_000_000_000 + RENT_FLOOR, RENT_FLOOR, 5_000_000_000).is_ok());
+    }
+
+    #[test]
+    fn test_shallow_pool_fails_with_a_distinct_error() {
+        let result = verify_liq_pool_covers(5_000_000_000, RENT_FLOOR, 5_000_000_000);
+
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::MarinadeLiquidityInsufficient,
+                ..
+            }
+        ));
+        assert!(verify_liq_pool_covers(RENT_FLOOR, RENT_FLOOR, 1).is_err());
+    }
+
+    #[test]
+    fn test_large_closes_cannot_instant_unstake() {
+        let max_instant_unstake = Lamports(1_000_000_000_000);
+
+        assert!(verify_instant_unstake_size(999_999_999_999, max_instant_unstake).is_ok());
+        assert!(verify_instant_unstake_size(1_000_000_000_000, max_instant_unstake).is_ok());
+        assert!(matches!(
+            verify_instant_unstake_size(1_000_000_000_001, max_instant_unstake).unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::InstantUnstakeTooLarge,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_an_unset_limit_uses_the_default() {
+        let mut system_state = SystemState::default();
+        assert_eq!(max_instant_unstake(&system_state), DEFAULT_MAX_INSTANT_UNSTAKE);
+        assert!(verify_instant_unstake_size(1, max_instant_unstake(&system_state)).is_ok());
+
+        system_state.max_instant_unstake = Lamports(5_000);
+        assert_eq!(max_instant_unstake(&system_state), Lamports(5_000));
+    }
+
+    #[test]
+    fn test_cpis_refuse_a_fake_marinade_program() {
+        let mut accounts = FakeAccounts::new(12).with_program(11, &Pubkey::new_unique());
+        let a = accounts.infos();
+        assert_invalid_program_account_code(deposit(
+            &a[0], &a[1], &a[2], &a[3], &a[4], &a[5], &a[6], &a[7], &a[8], &[], &a[9], &a[10], &a[11], 1,
+        ));
+
+        let mut accounts = FakeAccounts::new(11).with_program(10, &Pubkey::new_unique());
+        let a = accounts.infos();
+        assert_invalid_program_account_code(liquid_unstake(
+            &a[0], &a[1], &a[2], &a[3], &a[4], &a[5], &a[6], &a[7], &[], &a[8], &a[9], &a[10], 1,
+        ));
+    }
+}
@@ -1,11 +1,13 @@
 use rust_decimal::{Decimal, prelude::ToPrimitive};
 use crate::{
     error::{
+        check_assert,
         LucraError,
         LucraErrorCode,
         LucraResult,
         SourceFileId,
     },
+    units::Mata,
 };
 
 declare_check_assert_macros!(SourceFileId::Math);
@@ -69,6 +71,70 @@ pub fn get_no_fee_amount(
         .ok_or(math_err!())
 }
 
+// Loans below the minimum cost keepers the same to harvest as a large loan
+pub fn verify_minimum_loan_amount(loan_amount: Mata, min_loan_amount_mata: Mata) -> LucraResult {
+    check!(loan_amount >= min_loan_amount_mata, LucraErrorCode::InvalidAmount)
+}
+
+// A repayment must either clear the loan or leave it above the minimum
+pub fn verify_remaining_loan_amount(remaining_loan_amount: Mata, min_loan_amount_mata: Mata) -> LucraResult {
+    if remaining_loan_amount == Mata::ZERO {
+        return Ok(());
+    }
+
+    verify_minimum_loan_amount(remaining_loan_amount, min_loan_amount_mata)
+}
+
+fn calc_stake_value(staked_lucra: u64, lucra_price: Decimal) -> LucraResult<u64> {
+    Decimal::from(staked_lucra)
+        .checked_mul(lucra_price)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+// Only staked lucra can back a loan. StakingAccount.total is kept by Stake and StartUnstake, lucra sitting in
+// a deposit vault can be withdrawn at any time and never adds to it.
+pub fn calc_stake_value_left_to_lock(staked_lucra: u64, lucra_price: Decimal, locked_total: u64) -> LucraResult<u64> {
+    // A lucra price drop can leave the pledges worth more than the stake, there is nothing left to lock then
+    Ok(calc_stake_value(staked_lucra, lucra_price)?.saturating_sub(locked_total))
+}
+
+// Unstaking is the only way staked lucra can leave, so the stake that remains has to keep covering the pledges
+pub fn verify_unstake_keeps_pledges(staked_lucra: u64, lucra_to_unstake: u64, lucra_price: Decimal, locked_total: u64) -> LucraResult {
+    let remaining_lucra = staked_lucra
+        .checked_sub(lucra_to_unstake)
+        .ok_or(throw_err!(LucraErrorCode::InvalidAmount))?;
+
+    check!(calc_stake_value(remaining_lucra, lucra_price)? >= locked_total, LucraErrorCode::OutstandingLoans)
+}
+
+/// The most lucra that can be unstaked with the stake left still covering the pledges
+pub fn calc_unstakeable_lucra(staked_lucra: u64, lucra_price: Decimal, locked_total: u64) -> LucraResult<u64> {
+    if locked_total == 0 {
+        return Ok(staked_lucra);
+    }
+    if lucra_price.is_zero() {
+        return Ok(0);
+    }
+
+    // Fewest lucra still worth the pledges. A share too small to fit in a u64 keeps the whole stake
+    let mut lucra_to_keep = Decimal::from(locked_total)
+        .checked_div(lucra_price)
+        .and_then(|lucra| lucra.ceil().to_u64())
+        .unwrap_or(u64::MAX);
+    if lucra_to_keep >= staked_lucra {
+        return Ok(0);
+    }
+    // The division rounds, the value is floored the way verify_unstake_keeps_pledges floors it
+    if calc_stake_value(lucra_to_keep, lucra_price)? < locked_total {
+        lucra_to_keep += 1;
+    }
+
+    Ok(staked_lucra - lucra_to_keep)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,4 +149,84 @@ mod tests {
         let actual = calculate_annual_interest_rate(inflation_rate, amount, time).unwrap();
         assert_eq!(expected, actual);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_verify_minimum_loan_amount() {
+        let min_loan_amount_mata = Mata(10_000_000); // 10 mata
+
+        assert!(verify_minimum_loan_amount(min_loan_amount_mata, min_loan_amount_mata).is_ok());
+        assert!(verify_minimum_loan_amount(Mata(10_000_001), min_loan_amount_mata).is_ok());
+        assert!(verify_minimum_loan_amount(Mata(9_999_999), min_loan_amount_mata).is_err());
+        assert!(verify_minimum_loan_amount(Mata(0), Mata(0)).is_ok());
+    }
+
+    #[test]
+    fn test_verify_remaining_loan_amount() {
+        let min_loan_amount_mata = Mata(10_000_000); // 10 mata
+
+        assert!(verify_remaining_loan_amount(Mata(0), min_loan_amount_mata).is_ok());
+        assert!(verify_remaining_loan_amount(min_loan_amount_mata, min_loan_amount_mata).is_ok());
+        assert!(verify_remaining_loan_amount(Mata(1), min_loan_amount_mata).is_err());
+        assert!(verify_remaining_loan_amount(Mata(9_999_999), min_loan_amount_mata).is_err());
+    }
+
+    #[test]
+    fn test_stake_value_left_to_lock() {
+        let lucra_price = Decimal::new(5, 1); // $0.50
+
+        assert_eq!(calc_stake_value_left_to_lock(1_000, lucra_price, 0).unwrap(), 500);
+        assert_eq!(calc_stake_value_left_to_lock(1_000, lucra_price, 300).unwrap(), 200);
+        assert_eq!(calc_stake_value_left_to_lock(1_000, lucra_price, 500).unwrap(), 0);
+        assert_eq!(calc_stake_value_left_to_lock(1_000, lucra_price, 800).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pledge_then_unstake_is_rejected() {
+        // 1_000 lucra staked at $1, 600 of it pledged to a loan
+        let lucra_price = Decimal::ONE;
+        let staked_lucra = 1_000;
+        let locked_total = 600;
+        assert!(600 <= calc_stake_value_left_to_lock(staked_lucra, lucra_price, 0).unwrap());
+
+        assert!(verify_unstake_keeps_pledges(staked_lucra, 400, lucra_price, locked_total).is_ok());
+        assert!(verify_unstake_keeps_pledges(staked_lucra, 401, lucra_price, locked_total).is_err());
+        assert!(verify_unstake_keeps_pledges(staked_lucra, 1_000, lucra_price, locked_total).is_err());
+        // Unstaking through a staking account that holds less stake than the balance being unstaked
+        assert!(verify_unstake_keeps_pledges(0, 1, lucra_price, 0).is_err());
+    }
+
+    #[test]
+    fn test_unstakeable_lucra_is_the_most_the_pledges_allow() {
+        assert_eq!(calc_unstakeable_lucra(1_000, Decimal::ONE, 0).unwrap(), 1_000);
+        assert_eq!(calc_unstakeable_lucra(1_000, Decimal::ONE, 600).unwrap(), 400);
+        assert_eq!(calc_unstakeable_lucra(1_000, Decimal::new(5, 1), 300).unwrap(), 400);
+        assert_eq!(calc_unstakeable_lucra(1_000, Decimal::ONE, 1_000).unwrap(), 0);
+        assert_eq!(calc_unstakeable_lucra(1_000, Decimal::ZERO, 1).unwrap(), 0);
+        // A price this small needs more lucra than there are to cover one lamport
+        assert_eq!(calc_unstakeable_lucra(1_000, Decimal::new(1, 28), 1_000).unwrap(), 0);
+
+        for (staked_lucra, lucra_price, locked_total) in [
+            (1_000, Decimal::from(3), 1_000),
+            (1_000_000_007, Decimal::new(7, 3), 333_333),
+            (99, Decimal::new(101, 2), 13),
+        ] {
+            let unstakeable = calc_unstakeable_lucra(staked_lucra, lucra_price, locked_total).unwrap();
+            assert!(verify_unstake_keeps_pledges(staked_lucra, unstakeable, lucra_price, locked_total).is_ok());
+            assert!(verify_unstake_keeps_pledges(staked_lucra, unstakeable + 1, lucra_price, locked_total).is_err());
+        }
+    }
+
+    #[test]
+    fn test_pledge_then_price_drop_locks_the_stake() {
+        // The pledge is now worth more than the whole stake, nothing can be unstaked or pledged again
+        let lucra_price = Decimal::new(5, 1);
+
+        assert!(verify_unstake_keeps_pledges(1_000, 1, lucra_price, 600).is_err());
+        assert_eq!(calc_stake_value_left_to_lock(1_000, lucra_price, 600).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_unpledged_stake_can_leave() {
+        assert!(verify_unstake_keeps_pledges(1_000, 1_000, Decimal::ONE, 0).is_ok());
+    }
+}
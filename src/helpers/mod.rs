@@ -1,10 +1,61 @@
-pub mod account;
+// Pure helpers are always built, the ones that touch another program's accounts or CPI into it need the
+// program feature
+pub mod account_layout;
+pub mod arb_dust;
+pub mod arb_result;
+pub mod arb_trigger;
+pub mod arb_window;
 pub mod constants;
+pub mod dao_authority;
+pub mod liquidation;
+pub mod loan_history;
+pub mod math;
+pub mod paged_price_history;
+pub mod peg_band;
+pub mod penalty_rebate;
+pub mod price_history;
+pub mod pyth;
+pub mod reward_expiry;
+pub mod reward_index;
+pub mod reward_sunset;
+pub mod settlement;
+pub mod staking_freeze;
+pub mod unstake_timelock;
+pub mod valuation;
+pub mod vaults;
+
+#[cfg(feature = "program")]
+pub mod account;
+#[cfg(feature = "program")]
+pub mod collateral;
+#[cfg(feature = "program")]
+pub mod collateral_types;
+#[cfg(feature = "program")]
+pub mod events;
+#[cfg(feature = "program")]
+pub mod keepers;
+#[cfg(feature = "program")]
 pub mod spl;
+#[cfg(feature = "program")]
 pub mod solana;
+#[cfg(feature = "program")]
 pub mod oracle;
+#[cfg(feature = "program")]
+pub mod loan_address;
+#[cfg(feature = "program")]
+pub mod lp_collateral;
+#[cfg(feature = "program")]
 pub mod raydium;
-pub mod math;
+#[cfg(feature = "program")]
+pub mod sol_vault;
+#[cfg(feature = "program")]
+pub mod reward_tokens;
+#[cfg(feature = "program")]
 pub mod marinade;
+#[cfg(feature = "program")]
+pub mod token_metadata;
 
-pub mod spltokenswap;
\ No newline at end of file
+#[cfg(feature = "program")]
+pub mod spltokenswap;
+#[cfg(feature = "program")]
+pub mod whirlpool;
@@ -4,7 +4,7 @@ use solana_program::{
     sysvar::clock::Clock,
     pubkey::Pubkey,
 };
-use rust_decimal::{Decimal, MathematicalOps};
+use rust_decimal::Decimal;
 use oracles::state::Oracle;
 use crate::{
     error::{
@@ -14,51 +14,283 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
-    helpers::constants::ORACLE_PRICE_MAX_SLOTS,
+    helpers::{
+        constants::{
+            DEFAULT_ORACLE_MAX_DEVIATION_BPS,
+            MAX_ORACLE_DEVIATION_BPS,
+            MAX_ORACLE_STALENESS_SLOTS,
+            ORACLE_PRICE_MAX_SLOTS,
+            PYTH_MAX_CONFIDENCE_BPS,
+            SOL_MATA_ORCA_AMM,
+            SOL_MATA_RAYDIUM_AMM,
+            pyth,
+        },
+        pyth::PythPrice,
+    },
+    state::SystemState,
+    units::Bps,
 };
 
+pub use crate::lucra_core::quote::get_price;
+
 declare_check_assert_macros!(SourceFileId::OracleHelper);
 
-pub fn verify_orca_has_more_volume(oracle: &Oracle, raydium_market: &Pubkey, orca_market: &Pubkey) -> LucraResult {
-    let raydium_vol = get_volume(oracle, raydium_market)?;
-    let orca_vol = get_volume(oracle, orca_market)?;
+/// Both layouts start with the oracles program's metadata header, its second byte is the layout version
+const ORACLE_VERSION_OFFSET: usize = 1;
+const ORACLE_V1: u8 = 0;
+const ORACLE_V2: u8 = 1;
+
+const ORACLE_V1_EXPO: usize = 72;
+const ORACLE_V1_PRICE: usize = 11_097;
+const ORACLE_V1_VALID_SLOT: usize = 11_105;
+const ORACLE_V1_STATUS: usize = 11_222;
+const ORACLE_V1_LEN: usize = ORACLE_V1_STATUS + 1;
+
+// Forward-declared from the oracles v2 spec, every price source is a market key followed by its volume
+const ORACLE_V2_DECIMALS: usize = 8;
+const ORACLE_V2_STATUS: usize = 9;
+const ORACLE_V2_PRICE: usize = 16;
+const ORACLE_V2_VALID_SLOT: usize = 24;
+const ORACLE_V2_SOURCE_COUNT: usize = 32;
+const ORACLE_V2_SOURCES: usize = 40;
+const ORACLE_V2_SOURCE_LEN: usize = 40;
+const ORACLE_V2_MAX_SOURCES: usize = 16;
+const ORACLE_V2_LEN: usize = ORACLE_V2_SOURCES + ORACLE_V2_MAX_SOURCES * ORACLE_V2_SOURCE_LEN;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OracleVersion {
+    V1,
+    V2,
+}
+
+impl OracleVersion {
+    /// Once the DAO requires v2, accounts still written in the v1 layout are rejected
+    pub fn detect(data: &[u8], require_v2: bool) -> LucraResult<Self> {
+        check!(data.len() > ORACLE_VERSION_OFFSET, LucraErrorCode::InvalidAccountInput)?;
+        let version = match data[ORACLE_VERSION_OFFSET] {
+            ORACLE_V1 if !require_v2 => OracleVersion::V1,
+            ORACLE_V2 => OracleVersion::V2,
+            _ => return Err(throw_err!(LucraErrorCode::OracleVersionNotSupported)),
+        };
+
+        let len = match version {
+            OracleVersion::V1 => ORACLE_V1_LEN,
+            OracleVersion::V2 => ORACLE_V2_LEN,
+        };
+        check!(data.len() >= len, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(version)
+    }
+}
+
+/// The fields read off an oracle account, whichever layout it was written in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub price: u64,
+    pub decimals: u8,
+    pub valid_slot: u64,
+    pub status: u8,
+}
+
+impl OraclePrice {
+    pub fn decode(version: OracleVersion, data: &[u8]) -> Self {
+        match version {
+            OracleVersion::V1 => OraclePrice {
+                price: u64::from_le_bytes(*array_ref![data, ORACLE_V1_PRICE, 8]),
+                decimals: data[ORACLE_V1_EXPO],
+                valid_slot: u64::from_le_bytes(*array_ref![data, ORACLE_V1_VALID_SLOT, 8]),
+                status: data[ORACLE_V1_STATUS],
+            },
+            OracleVersion::V2 => OraclePrice {
+                price: u64::from_le_bytes(*array_ref![data, ORACLE_V2_PRICE, 8]),
+                decimals: data[ORACLE_V2_DECIMALS],
+                valid_slot: u64::from_le_bytes(*array_ref![data, ORACLE_V2_VALID_SLOT, 8]),
+                status: data[ORACLE_V2_STATUS],
+            },
+        }
+    }
+}
+
+fn decode_v2_volume(data: &[u8], market: &Pubkey) -> LucraResult<u64> {
+    let source_count = data[ORACLE_V2_SOURCE_COUNT] as usize;
+    check!(source_count <= ORACLE_V2_MAX_SOURCES, LucraErrorCode::InvalidAccountInput)?;
+
+    (0..source_count)
+        .map(|i| ORACLE_V2_SOURCES + i * ORACLE_V2_SOURCE_LEN)
+        .find(|offset| array_ref![data, *offset, 32] == market.as_ref())
+        .map(|offset| u64::from_le_bytes(*array_ref![data, offset + 32, 8]))
+        .ok_or(throw_err!(LucraErrorCode::InvalidAccountInput))
+}
+
+/// An oracle account whose layout version has been checked against the system state
+pub struct OracleAccount<'a, 'b> {
+    pub oracle_ai: &'a AccountInfo<'b>,
+    pub version: OracleVersion,
+}
+
+impl<'a, 'b> OracleAccount<'a, 'b> {
+    pub fn load(system_state: &SystemState, oracle_ai: &'a AccountInfo<'b>) -> LucraResult<Self> {
+        let version = OracleVersion::detect(&oracle_ai.try_borrow_data()?, system_state.require_oracle_v2)?;
+
+        Ok(OracleAccount { oracle_ai, version })
+    }
+
+    pub fn price(&self) -> LucraResult<OraclePrice> {
+        Ok(OraclePrice::decode(self.version, &self.oracle_ai.try_borrow_data()?))
+    }
+
+    pub fn volume(&self, market: &Pubkey) -> LucraResult<u64> {
+        match self.version {
+            OracleVersion::V1 => {
+                let oracle = Oracle::load_checked(self.oracle_ai, &oracles::id())?;
+                let price_source = oracle
+                    .find_price_source_by_market(market)
+                    .ok_or(throw_err!(LucraErrorCode::InvalidAccountInput))?;
+
+                Ok(price_source.agg_price.vol)
+            },
+            OracleVersion::V2 => decode_v2_volume(&self.oracle_ai.try_borrow_data()?, market),
+        }
+    }
+}
+
+pub fn verify_orca_has_more_volume(oracle: &OracleAccount, raydium_market: &Pubkey, orca_market: &Pubkey) -> LucraResult {
+    let raydium_vol = oracle.volume(raydium_market)?;
+    let orca_vol = oracle.volume(orca_market)?;
 
     check!(orca_vol >= raydium_vol, LucraErrorCode::InvalidAmount)?;
     Ok(())
 }
 
-pub fn verify_raydium_has_more_volume(oracle: &Oracle, raydium_market: &Pubkey, orca_market: &Pubkey) -> LucraResult {
-    let raydium_vol = get_volume(oracle, raydium_market)?;
-    let orca_vol = get_volume(oracle, orca_market)?;
+pub fn verify_raydium_has_more_volume(oracle: &OracleAccount, raydium_market: &Pubkey, orca_market: &Pubkey) -> LucraResult {
+    let raydium_vol = oracle.volume(raydium_market)?;
+    let orca_vol = oracle.volume(orca_market)?;
 
     check!(raydium_vol >= orca_vol, LucraErrorCode::InvalidAmount)?;
     Ok(())
 }
 
-pub fn check_raydium_has_more_volume(oracle: &Oracle, raydium_market: &Pubkey, orca_market: &Pubkey) -> LucraResult<bool> {
-    let raydium_vol = get_volume(oracle, raydium_market)?;
-    let orca_vol = get_volume(oracle, orca_market)?;
+/// The whirlpool has to out trade both constant product pools, the oracle has to track it as a market
+pub fn verify_whirlpool_has_more_volume(oracle: &OracleAccount, whirlpool: &Pubkey, raydium_market: &Pubkey, orca_market: &Pubkey) -> LucraResult {
+    let whirlpool_vol = oracle.volume(whirlpool)?;
+    let raydium_vol = oracle.volume(raydium_market)?;
+    let orca_vol = oracle.volume(orca_market)?;
+
+    check!(whirlpool_vol >= raydium_vol && whirlpool_vol >= orca_vol, LucraErrorCode::InvalidAmount)?;
+    Ok(())
+}
+
+pub fn check_raydium_has_more_volume(oracle: &OracleAccount, raydium_market: &Pubkey, orca_market: &Pubkey) -> LucraResult<bool> {
+    let raydium_vol = oracle.volume(raydium_market)?;
+    let orca_vol = oracle.volume(orca_market)?;
 
     Ok(raydium_vol >= orca_vol)
 }
 
-pub fn get_volume(oracle: &Oracle, market: &Pubkey) -> LucraResult<u64> {
-    let price_source = oracle.find_price_source_by_market(market).unwrap();
-    let volume = price_source.agg_price.vol;
+/// Checked by Initialize and UpdateState. Zero would reject every read, and a loose limit lets loans open at
+/// prices the market has already moved away from.
+pub fn verify_oracle_limits(max_oracle_staleness_slots: u64, max_oracle_deviation_bps: Bps) -> LucraResult {
+    check!(max_oracle_staleness_slots > 0, LucraErrorCode::InvalidAmount)?;
+    check!(max_oracle_staleness_slots <= MAX_ORACLE_STALENESS_SLOTS, LucraErrorCode::InvalidAmount)?;
+    check!(max_oracle_deviation_bps > Bps(0), LucraErrorCode::InvalidAmount)?;
+    check!(max_oracle_deviation_bps <= MAX_ORACLE_DEVIATION_BPS, LucraErrorCode::InvalidAmount)
+}
+
+/// System states initialized before the limits existed read zero and keep the compiled in ones
+pub fn get_max_oracle_staleness_slots(system_state: &SystemState) -> u64 {
+    if system_state.max_oracle_staleness_slots == 0 {
+        ORACLE_PRICE_MAX_SLOTS
+    } else {
+        system_state.max_oracle_staleness_slots
+    }
+}
+
+pub fn get_max_oracle_deviation_bps(system_state: &SystemState) -> Bps {
+    if system_state.max_oracle_deviation_bps == Bps(0) {
+        DEFAULT_ORACLE_MAX_DEVIATION_BPS
+    } else {
+        system_state.max_oracle_deviation_bps
+    }
+}
+
+/// Where a SOL/USD price is read from, told apart by the owner of the account
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PriceSource {
+    Lucra,
+    Pyth,
+}
+
+impl PriceSource {
+    pub fn of(oracle_ai: &AccountInfo) -> Self {
+        if oracle_ai.owner == &pyth::id() {
+            PriceSource::Pyth
+        } else {
+            PriceSource::Lucra
+        }
+    }
+}
+
+/// Either SOL/USD slot can hold the Pyth SOL/USD feed instead of the program's oracle, so a stale in-house
+/// oracle can be swapped out. Its key is checked against the system state when it's read.
+pub fn verify_sol_usd_oracle(oracle_ai: &AccountInfo, expected: &Pubkey, program_id: &Pubkey) -> LucraResult {
+    match PriceSource::of(oracle_ai) {
+        PriceSource::Pyth => Ok(()),
+        PriceSource::Lucra => {
+            check_eq!(oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+            check_eq!(oracle_ai.key, expected, LucraErrorCode::InvalidAccountInput)
+        }
+    }
+}
+
+/// Only the feed the DAO set is read, the default key means Pyth isn't accepted
+pub fn get_pyth_price(system_state: &SystemState, pyth_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
+    check!(system_state.pyth_sol_usd_feed != Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(pyth_ai.key, &system_state.pyth_sol_usd_feed, LucraErrorCode::InvalidAccountInput)?;
+
+    PythPrice::decode(&pyth_ai.try_borrow_data()?)?.to_decimal(
+        clock.slot,
+        get_max_oracle_staleness_slots(system_state),
+        PYTH_MAX_CONFIDENCE_BPS,
+    )
+}
+
+fn get_usd_price(system_state: &SystemState, oracle_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
+    match PriceSource::of(oracle_ai) {
+        PriceSource::Lucra => get_oracle_price(system_state, oracle_ai, clock),
+        PriceSource::Pyth => get_pyth_price(system_state, oracle_ai, clock),
+    }
+}
 
-    Ok(volume)
+/// The SOL/USDC and SOL/USDT oracles should agree, a gap wider than the limit means one of them is off
+fn verify_sol_prices_agree(sol_usdc_price: Decimal, sol_usdt_price: Decimal, max_deviation_bps: Bps) -> LucraResult {
+    let lower = sol_usdc_price.min(sol_usdt_price);
+    let higher = sol_usdc_price.max(sol_usdt_price);
+    check!(lower > Decimal::ZERO, LucraErrorCode::PriceDataInvalid)?;
+
+    let deviation_bps = higher
+        .checked_sub(lower)
+        .ok_or(math_err!())?
+        .checked_mul(Decimal::from(Bps::ONE_HUNDRED_PERCENT.get()))
+        .ok_or(math_err!())?
+        .checked_div(lower)
+        .ok_or(math_err!())?;
+
+    check!(deviation_bps <= Decimal::from(max_deviation_bps.get()), LucraErrorCode::PriceDataInvalid)
 }
 
-pub fn get_sol_price(sol_usdc_oracle_ai: &AccountInfo, sol_usdt_oracle_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
-    let sol_usdc_price = get_oracle_price(sol_usdc_oracle_ai, clock)?;
-    let sol_usdt_price = get_oracle_price(sol_usdt_oracle_ai, clock)?;
+pub fn get_sol_price(system_state: &SystemState, sol_usdc_oracle_ai: &AccountInfo, sol_usdt_oracle_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
+    // The Pyth feed in both slots would only ever agree with itself
+    check!(sol_usdc_oracle_ai.key != sol_usdt_oracle_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let sol_usdc_price = get_usd_price(system_state, sol_usdc_oracle_ai, clock)?;
+    let sol_usdt_price = get_usd_price(system_state, sol_usdt_oracle_ai, clock)?;
+    verify_sol_prices_agree(sol_usdc_price, sol_usdt_price, get_max_oracle_deviation_bps(system_state))?;
 
     Ok(if sol_usdc_price > sol_usdt_price { sol_usdt_price } else { sol_usdc_price })
 }
 
-pub fn get_lucra_price(lucra_sol_oracle_ai: &AccountInfo, sol_usdc_oracle_ai: &AccountInfo, sol_usdt_oracle_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
-    let lucra_sol_price = get_oracle_price(lucra_sol_oracle_ai, clock)?;
-    let sol_usd_price = get_sol_price(sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+pub fn get_lucra_price(system_state: &SystemState, lucra_sol_oracle_ai: &AccountInfo, sol_usdc_oracle_ai: &AccountInfo, sol_usdt_oracle_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
+    let lucra_sol_price = get_oracle_price(system_state, lucra_sol_oracle_ai, clock)?;
+    let sol_usd_price = get_sol_price(system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
 
     let usd_sol = Decimal::from(1_u64)
         .checked_div(sol_usd_price)
@@ -72,9 +304,9 @@ pub fn get_lucra_price(lucra_sol_oracle_ai: &AccountInfo, sol_usdc_oracle_ai: &A
     Ok(lucra_usd_price)
 }
 
-pub fn get_mata_price(sol_mata_oracle_ai: &AccountInfo, sol_usdc_oracle_ai: &AccountInfo, sol_usdt_oracle_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
-    let sol_mata_price = get_oracle_price(sol_mata_oracle_ai, clock)?;
-    let sol_usd_price = get_sol_price(sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+pub fn get_mata_price(system_state: &SystemState, sol_mata_oracle_ai: &AccountInfo, sol_usdc_oracle_ai: &AccountInfo, sol_usdt_oracle_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
+    let sol_mata_price = get_oracle_price(system_state, sol_mata_oracle_ai, clock)?;
+    let sol_usd_price = get_sol_price(system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
 
     let sol_mata = Decimal::from(1_u64)
         .checked_div(sol_mata_price)
@@ -86,57 +318,55 @@ pub fn get_mata_price(sol_mata_oracle_ai: &AccountInfo, sol_usdc_oracle_ai: &Acc
     Ok(mata_usd_price)
 }
 
-pub fn get_oracle_price(oracle_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
-    let price_data = oracle_ai.try_borrow_data()?;
-    let price = u64::from_le_bytes(*array_ref![price_data, 11_097, 8]);
-    let valid_slot = u64::from_le_bytes(*array_ref![price_data, 11_105, 8]);
-    let expo = u8::from_le_bytes(*array_ref![price_data, 72, 1]);
-    let status = u8::from_le_bytes(*array_ref![price_data, 11_222, 1]);
+pub fn get_oracle_price(system_state: &SystemState, oracle_ai: &AccountInfo, clock: &Clock) -> LucraResult<Decimal> {
+    let oracle = OracleAccount::load(system_state, oracle_ai)?.price()?;
 
-    calc_oracle_price(price, expo, valid_slot, status, clock.slot)
+    calc_oracle_price(
+        oracle.price,
+        oracle.decimals,
+        oracle.valid_slot,
+        oracle.status,
+        clock.slot,
+        get_max_oracle_staleness_slots(system_state),
+    )
 }
 
-fn calc_oracle_price(price: u64, expo: u8, valid_slot: u64, status: u8, current_slot: u64) -> LucraResult<Decimal> {
+fn calc_oracle_price(price: u64, expo: u8, valid_slot: u64, status: u8, current_slot: u64, max_staleness_slots: u64) -> LucraResult<Decimal> {
     if status != 1 {
         return Err(throw_err!(LucraErrorCode::OracleStatusNotValid));
     }
 
-    if valid_slot + ORACLE_PRICE_MAX_SLOTS < current_slot {
+    if valid_slot.saturating_add(max_staleness_slots) < current_slot {
         return Err(throw_err!(LucraErrorCode::OracleStale));
     }
 
     get_price(price, expo)
 }
 
-pub fn get_price(price: u64, expo: u8) -> LucraResult<Decimal> {
-    Ok(
-        Decimal::from(price)
-            .checked_div(
-                Decimal::TEN.powi(expo as i64)
-            )
-            .unwrap()
-    )
+pub fn is_sol_mata_amm(key: &Pubkey) -> bool {
+    key == &SOL_MATA_ORCA_AMM || key == &SOL_MATA_RAYDIUM_AMM
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::pyth::tests::pyth_data;
 
     #[test]
     fn test_calc_oracle_price() {
-        let result = calc_oracle_price(28_050_000, 6, 1_357_892, 1, 1_357_892).unwrap();
+        let result = calc_oracle_price(28_050_000, 6, 1_357_892, 1, 1_357_892, ORACLE_PRICE_MAX_SLOTS).unwrap();
         let expected = Decimal::from(28_050_000_u64).checked_div(1_000_000.into()).unwrap(); // 28.05
         assert_eq!(result, expected);
     }
 
     #[test]
     fn test_calc_oracle_price_should_fail_when_oracle_status_invalid() {
-        let result = calc_oracle_price(28_050_000, 6, 1_357_892, 0, 1_357_892);
+        let result = calc_oracle_price(28_050_000, 6, 1_357_892, 0, 1_357_892, ORACLE_PRICE_MAX_SLOTS);
         assert!(matches!(
             result.unwrap_err(),
             LucraError::LucraErrorCode {
                 lucra_error_code: LucraErrorCode::OracleStatusNotValid,
-                line: 101,
+                line: 343,
                 source_file_id: SourceFileId::OracleHelper,
             }
         ));
@@ -144,14 +374,296 @@ mod tests {
 
     #[test]
     fn test_calc_oracle_price_should_fail_when_slot_is_stale() {
-        let result = calc_oracle_price(28_050_000, 6, 1_357_892, 1, 1_358_000);
+        let result = calc_oracle_price(28_050_000, 6, 1_357_892, 1, 1_358_000, ORACLE_PRICE_MAX_SLOTS);
         assert!(matches!(
             result.unwrap_err(),
             LucraError::LucraErrorCode {
                 lucra_error_code: LucraErrorCode::OracleStale,
-                line: 105,
+                line: 347,
                 source_file_id: SourceFileId::OracleHelper,
             }
         ));
     }
+
+    fn assert_error(result: LucraResult<Decimal>, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_oracle_limits_come_from_the_system_state() {
+        let mut system_state = SystemState::default();
+        assert_eq!(get_max_oracle_staleness_slots(&system_state), ORACLE_PRICE_MAX_SLOTS);
+        assert_eq!(get_max_oracle_deviation_bps(&system_state), DEFAULT_ORACLE_MAX_DEVIATION_BPS);
+
+        system_state.max_oracle_staleness_slots = 10;
+        system_state.max_oracle_deviation_bps = Bps(50);
+        assert_eq!(get_max_oracle_staleness_slots(&system_state), 10);
+        assert_eq!(get_max_oracle_deviation_bps(&system_state), Bps(50));
+
+        assert!(verify_oracle_limits(MAX_ORACLE_STALENESS_SLOTS, MAX_ORACLE_DEVIATION_BPS).is_ok());
+        assert!(verify_oracle_limits(0, Bps(50)).is_err());
+        assert!(verify_oracle_limits(MAX_ORACLE_STALENESS_SLOTS + 1, Bps(50)).is_err());
+        assert!(verify_oracle_limits(10, Bps(0)).is_err());
+        assert!(verify_oracle_limits(10, Bps(MAX_ORACLE_DEVIATION_BPS.get() + 1)).is_err());
+    }
+
+    #[test]
+    fn test_reads_older_than_the_configured_limit_are_stale() {
+        let clock = Clock { slot: 1_000, ..Clock::default() };
+        let mut system_state = SystemState::default();
+        let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut lamports = 0;
+        let mut data = oracle_data(40_000_000, 6, 990, 1);
+        let oracle = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+        // 10 slots old is fine under the default
+        assert_eq!(get_oracle_price(&system_state, &oracle, &clock).unwrap(), Decimal::from(40_u64));
+
+        system_state.max_oracle_staleness_slots = 10;
+        assert!(get_oracle_price(&system_state, &oracle, &clock).is_ok());
+        system_state.max_oracle_staleness_slots = 9;
+        assert_error(get_oracle_price(&system_state, &oracle, &clock), LucraErrorCode::OracleStale);
+    }
+
+    #[test]
+    fn test_sol_price_rejects_usd_oracles_that_disagree() {
+        let clock = Clock { slot: 1_000, ..Clock::default() };
+        let mut system_state = SystemState::default();
+        system_state.max_oracle_deviation_bps = Bps(100);
+        let (k1, k2, k3, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l1, mut l2, mut l3) = (0, 0, 0);
+
+        // $40.00 against $40.40 is 1% apart, the lower one is used
+        let mut usdc_data = oracle_data(40_000_000, 6, 1_000, 1);
+        let mut usdt_data = oracle_data(40_400_000, 6, 1_000, 1);
+        let usdc = AccountInfo::new(&k1, false, false, &mut l1, &mut usdc_data, &owner, false, 0);
+        let usdt = AccountInfo::new(&k2, false, false, &mut l2, &mut usdt_data, &owner, false, 0);
+        assert_eq!(get_sol_price(&system_state, &usdc, &usdt, &clock).unwrap(), Decimal::from(40_u64));
+        assert_eq!(get_sol_price(&system_state, &usdt, &usdc, &clock).unwrap(), Decimal::from(40_u64));
+
+        system_state.max_oracle_deviation_bps = Bps(99);
+        assert_error(get_sol_price(&system_state, &usdc, &usdt, &clock), LucraErrorCode::PriceDataInvalid);
+        assert_error(get_sol_price(&system_state, &usdt, &usdc, &clock), LucraErrorCode::PriceDataInvalid);
+
+        // Everything priced in dollars goes through the same check
+        let mut sol_mata_data = oracle_data(40_000_000, 6, 1_000, 1);
+        let sol_mata = AccountInfo::new(&k3, false, false, &mut l3, &mut sol_mata_data, &owner, false, 0);
+        assert_error(get_mata_price(&system_state, &sol_mata, &usdc, &usdt, &clock), LucraErrorCode::PriceDataInvalid);
+    }
+
+    #[test]
+    fn test_sol_price_reads_the_pyth_feed_in_either_slot() {
+        let clock = Clock { slot: 1_000, ..Clock::default() };
+        let mut system_state = SystemState::default();
+        system_state.max_oracle_deviation_bps = Bps(100);
+        let (k1, k2, owner, pyth_owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), pyth::id());
+        let (mut l1, mut l2) = (0, 0);
+
+        // $40.00 from the in-house oracle against $40.20 from Pyth
+        let mut usdc_data = oracle_data(40_000_000, 6, 1_000, 1);
+        let mut feed_data = pyth_data(4_020_000_000, 1_000_000, 1, 1_000);
+        let usdc = AccountInfo::new(&k1, false, false, &mut l1, &mut usdc_data, &owner, false, 0);
+        let feed = AccountInfo::new(&k2, false, false, &mut l2, &mut feed_data, &pyth_owner, false, 0);
+        assert_eq!(PriceSource::of(&usdc), PriceSource::Lucra);
+        assert_eq!(PriceSource::of(&feed), PriceSource::Pyth);
+
+        // Not accepted until the DAO sets the feed
+        assert_error(get_sol_price(&system_state, &usdc, &feed, &clock), LucraErrorCode::InvalidAccountInput);
+        system_state.pyth_sol_usd_feed = Pubkey::new_unique();
+        assert_error(get_sol_price(&system_state, &usdc, &feed, &clock), LucraErrorCode::InvalidAccountInput);
+
+        system_state.pyth_sol_usd_feed = k2;
+        assert_eq!(get_sol_price(&system_state, &usdc, &feed, &clock).unwrap(), Decimal::from(40_u64));
+        assert_eq!(get_sol_price(&system_state, &feed, &usdc, &clock).unwrap(), Decimal::from(40_u64));
+        assert_error(get_sol_price(&system_state, &feed, &feed, &clock), LucraErrorCode::InvalidAccountInput);
+
+        // The sources still have to agree
+        system_state.max_oracle_deviation_bps = Bps(49);
+        assert_error(get_sol_price(&system_state, &usdc, &feed, &clock), LucraErrorCode::PriceDataInvalid);
+    }
+
+    #[test]
+    fn test_sol_price_rejects_a_pyth_feed_that_isnt_trading() {
+        let clock = Clock { slot: 1_000, ..Clock::default() };
+        let mut system_state = SystemState::default();
+        let (k1, k2, owner, pyth_owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), pyth::id());
+        let (mut l1, mut l2) = (0, 0);
+        system_state.pyth_sol_usd_feed = k2;
+
+        // Halted
+        let mut usdc_data = oracle_data(40_000_000, 6, 1_000, 1);
+        let mut feed_data = pyth_data(4_000_000_000, 0, 2, 1_000);
+        let usdc = AccountInfo::new(&k1, false, false, &mut l1, &mut usdc_data, &owner, false, 0);
+        let feed = AccountInfo::new(&k2, false, false, &mut l2, &mut feed_data, &pyth_owner, false, 0);
+        assert_error(get_sol_price(&system_state, &usdc, &feed, &clock), LucraErrorCode::OracleStatusNotValid);
+    }
+
+    #[test]
+    fn test_sol_usd_oracle_is_the_program_oracle_or_pyth() {
+        let program_id = Pubkey::new_unique();
+        let expected = Pubkey::new_unique();
+        let (other, pyth_owner) = (Pubkey::new_unique(), pyth::id());
+        let (mut l1, mut l2, mut l3) = (0, 0, 0);
+        let (mut d1, mut d2, mut d3) = (vec![], vec![], vec![]);
+
+        let oracle = AccountInfo::new(&expected, false, false, &mut l1, &mut d1, &program_id, false, 0);
+        let feed = AccountInfo::new(&other, false, false, &mut l2, &mut d2, &pyth_owner, false, 0);
+        let impostor = AccountInfo::new(&other, false, false, &mut l3, &mut d3, &program_id, false, 0);
+        assert!(verify_sol_usd_oracle(&oracle, &expected, &program_id).is_ok());
+        assert!(verify_sol_usd_oracle(&feed, &expected, &program_id).is_ok());
+        assert!(verify_sol_usd_oracle(&impostor, &expected, &program_id).is_err());
+        assert!(verify_sol_usd_oracle(&oracle, &expected, &Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn test_zero_usd_price_is_invalid() {
+        assert!(verify_sol_prices_agree(Decimal::ZERO, Decimal::ONE, Bps(100)).is_err());
+        assert!(verify_sol_prices_agree(Decimal::ONE, Decimal::ONE, Bps(1)).is_ok());
+    }
+
+    fn oracle_data(price: u64, expo: u8, valid_slot: u64, status: u8) -> Vec<u8> {
+        let mut data = vec![0; 11_223];
+        data[72] = expo;
+        data[11_097..11_105].copy_from_slice(&price.to_le_bytes());
+        data[11_105..11_113].copy_from_slice(&valid_slot.to_le_bytes());
+        data[11_222] = status;
+        data
+    }
+
+    fn oracle_v2_data(price: u64, decimals: u8, valid_slot: u64, status: u8, sources: &[(Pubkey, u64)]) -> Vec<u8> {
+        let mut data = vec![0; ORACLE_V2_LEN];
+        data[ORACLE_VERSION_OFFSET] = ORACLE_V2;
+        data[ORACLE_V2_DECIMALS] = decimals;
+        data[ORACLE_V2_STATUS] = status;
+        data[ORACLE_V2_PRICE..ORACLE_V2_PRICE + 8].copy_from_slice(&price.to_le_bytes());
+        data[ORACLE_V2_VALID_SLOT..ORACLE_V2_VALID_SLOT + 8].copy_from_slice(&valid_slot.to_le_bytes());
+        data[ORACLE_V2_SOURCE_COUNT] = sources.len() as u8;
+        for (i, (market, vol)) in sources.iter().enumerate() {
+            let offset = ORACLE_V2_SOURCES + i * ORACLE_V2_SOURCE_LEN;
+            data[offset..offset + 32].copy_from_slice(market.as_ref());
+            data[offset + 32..offset + 40].copy_from_slice(&vol.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_decode_v1_oracle() {
+        let data = oracle_data(28_050_000, 6, 1_357_892, 1);
+        let version = OracleVersion::detect(&data, false).unwrap();
+        assert_eq!(version, OracleVersion::V1);
+        assert_eq!(OraclePrice::decode(version, &data), OraclePrice {
+            price: 28_050_000,
+            decimals: 6,
+            valid_slot: 1_357_892,
+            status: 1,
+        });
+    }
+
+    #[test]
+    fn test_decode_v2_oracle() {
+        let orca_market = Pubkey::new_unique();
+        let raydium_market = Pubkey::new_unique();
+        let data = oracle_v2_data(28_050_000, 6, 1_357_892, 1, &[(orca_market, 700), (raydium_market, 300)]);
+
+        for require_v2 in [false, true] {
+            let version = OracleVersion::detect(&data, require_v2).unwrap();
+            assert_eq!(version, OracleVersion::V2);
+            assert_eq!(OraclePrice::decode(version, &data), OraclePrice {
+                price: 28_050_000,
+                decimals: 6,
+                valid_slot: 1_357_892,
+                status: 1,
+            });
+        }
+
+        assert_eq!(decode_v2_volume(&data, &orca_market).unwrap(), 700);
+        assert_eq!(decode_v2_volume(&data, &raydium_market).unwrap(), 300);
+        assert!(decode_v2_volume(&data, &Pubkey::new_unique()).is_err());
+    }
+
+    // Written byte by byte from the oracles v2 spec rather than through the offsets above, so an offset that
+    // drifts from the spec fails here instead of agreeing with itself
+    #[test]
+    fn test_decode_v2_fixture() {
+        let data = include_bytes!("../../tests/fixtures/oracle_v2_sol_mata.bin");
+        assert_eq!(data.len(), 680);
+
+        let version = OracleVersion::detect(data, true).unwrap();
+        assert_eq!(version, OracleVersion::V2);
+        assert_eq!(OraclePrice::decode(version, data), OraclePrice {
+            price: 24_315_000,
+            decimals: 6,
+            valid_slot: 181_204_337,
+            status: 1,
+        });
+        assert_eq!(decode_v2_volume(data, &SOL_MATA_ORCA_AMM).unwrap(), 1_250_000_000);
+        assert_eq!(decode_v2_volume(data, &SOL_MATA_RAYDIUM_AMM).unwrap(), 830_000_000);
+        assert!(decode_v2_volume(data, &Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn test_v1_oracle_rejected_once_v2_required() {
+        let data = oracle_data(28_050_000, 6, 1_357_892, 1);
+        assert!(matches!(
+            OracleVersion::detect(&data, true).unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::OracleVersionNotSupported,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_unknown_or_truncated_oracle_rejected() {
+        let mut data = oracle_v2_data(28_050_000, 6, 1_357_892, 1, &[]);
+        data[ORACLE_VERSION_OFFSET] = 2;
+        assert!(matches!(
+            OracleVersion::detect(&data, false).unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::OracleVersionNotSupported,
+                ..
+            }
+        ));
+
+        let data = oracle_v2_data(28_050_000, 6, 1_357_892, 1, &[]);
+        assert!(OracleVersion::detect(&data[..ORACLE_V2_LEN - 1], false).is_err());
+        let data = oracle_data(28_050_000, 6, 1_357_892, 1);
+        assert!(OracleVersion::detect(&data[..ORACLE_V1_LEN - 1], false).is_err());
+        assert!(OracleVersion::detect(&[], false).is_err());
+    }
+
+    #[test]
+    fn test_v2_source_count_bounded() {
+        let mut data = oracle_v2_data(28_050_000, 6, 1_357_892, 1, &[]);
+        data[ORACLE_V2_SOURCE_COUNT] = (ORACLE_V2_MAX_SOURCES + 1) as u8;
+        assert!(decode_v2_volume(&data, &Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn test_stale_sol_mata_oracle_fails_closed() {
+        let clock = Clock { slot: 1_000, ..Clock::default() };
+        let system_state = SystemState::default();
+        let owner = Pubkey::new_unique();
+        let (k1, k2, k3, k4) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l1, mut l2, mut l3, mut l4) = (0, 0, 0, 0);
+
+        // 1 sol = 40 usd on both usd oracles
+        let mut usdc_data = oracle_data(40_000_000, 6, 1_000, 1);
+        let mut usdt_data = oracle_data(40_000_000, 6, 1_000, 1);
+        let usdc = AccountInfo::new(&k1, false, false, &mut l1, &mut usdc_data, &owner, false, 0);
+        let usdt = AccountInfo::new(&k2, false, false, &mut l2, &mut usdt_data, &owner, false, 0);
+
+        // 1 sol = 50 mata on a fresh v2 oracle, next to v1 usd oracles
+        let mut fresh_data = oracle_v2_data(50_000_000, 6, 1_000, 1, &[]);
+        let fresh_sol_mata = AccountInfo::new(&k3, false, false, &mut l3, &mut fresh_data, &owner, false, 0);
+        assert_eq!(get_mata_price(&system_state, &fresh_sol_mata, &usdc, &usdt, &clock).unwrap(), Decimal::new(8, 1));
+
+        // Pool reserves can be moved inside the transaction, nothing stands in for a stale oracle
+        let mut stale_data = oracle_data(40_000_000, 6, 900, 1);
+        let stale_sol_mata = AccountInfo::new(&k4, false, false, &mut l4, &mut stale_data, &owner, false, 0);
+        assert_error(get_mata_price(&system_state, &stale_sol_mata, &usdc, &usdt, &clock), LucraErrorCode::OracleStale);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,266 @@
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        constants::UNIX_DAY,
+        price_history::{first_index_after, price_at, price_for_date},
+    },
+    state::{
+        HistoricPrice,
+        PriceHistory,
+        PriceHistoryHeader,
+        PriceHistoryPage,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::PagedPriceHistory);
+
+// Penalties are charged for every day since the loan was last checked. The price history only has the
+// last 30 days, so a loan that went longer unchecked is charged from the pages its days were archived to.
+// A day that's missing is either an error the caller can fix by passing the pages, or a day without
+// prices when it was never recorded at all.
+
+/// Archives the price history's days from the newest one archived on, after every update. That keeps the day
+/// being sampled and the one before it, which may have just been zeroed out, in step with the price history.
+pub fn archive_recent_prices(
+    header: &mut PriceHistoryHeader,
+    pages: &mut [&mut PriceHistoryPage],
+    price_history: &PriceHistory,
+) -> LucraResult {
+    let first_index = first_index_after(price_history, header.last_date.saturating_sub(1));
+    for index in first_index..price_history.len as usize {
+        header.archive(pages, price_at(price_history, index))?;
+    }
+
+    Ok(())
+}
+
+/// Once the price history has written over days, a loan last charged before them can't be charged from it
+/// without skipping them. `first_day` is the first day the loan hasn't been charged for.
+pub fn check_price_history_covers(price_history: &PriceHistory, first_day: i64) -> LucraResult {
+    if price_history.head <= price_history.len {
+        return Ok(());
+    }
+
+    check!(first_day >= price_at(price_history, 0).date, LucraErrorCode::PriceHistoryGap)
+}
+
+/// Every day from `first_day` to `last_day` with its prices. A day that isn't archived is taken from the price
+/// history while it still has it, otherwise it has no prices, the same as a day the crank zeroed out: it's
+/// charged no penalty but still counts for interest. `pages` have to be the consecutive pages holding the
+/// archived days in the range, oldest first.
+pub fn collect_paged_days(
+    header: &PriceHistoryHeader,
+    pages: &[&PriceHistoryPage],
+    price_history: &PriceHistory,
+    first_day: i64,
+    last_day: i64,
+) -> LucraResult<Vec<HistoricPrice>> {
+    if first_day > last_day {
+        return Ok(vec![]);
+    }
+    check_pages_cover(header, pages, first_day, last_day)?;
+
+    let mut days = vec![];
+    let mut date = first_day;
+    while date <= last_day {
+        let price = pages
+            .iter()
+            .find_map(|page| page.price_for_date(date))
+            .or_else(|| price_for_date(price_history, date))
+            .copied()
+            .unwrap_or(HistoricPrice { date, ..HistoricPrice::default() });
+        days.push(price);
+        date = date.checked_add(UNIX_DAY).ok_or(math_err!())?;
+    }
+
+    Ok(days)
+}
+
+fn check_pages_cover(header: &PriceHistoryHeader, pages: &[&PriceHistoryPage], first_day: i64, last_day: i64) -> LucraResult {
+    let from = std::cmp::max(first_day, header.first_date);
+    let to = std::cmp::min(last_day, header.last_date);
+    if header.last_date == 0 || from > to {
+        return Ok(());
+    }
+
+    let (first, last) = match (pages.first(), pages.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err(throw_err!(LucraErrorCode::PriceHistoryGap)),
+    };
+    for pair in pages.windows(2) {
+        check_eq!(pair[1].page_index, pair[0].page_index + 1, LucraErrorCode::InvalidAccountInput)?;
+    }
+
+    // The page before the first one could still hold a day of the range unless the first starts before it
+    check!(
+        first.page_index == 0 || first.first_date().map_or(false, |date| date <= from),
+        LucraErrorCode::PriceHistoryGap
+    )?;
+    check!(
+        last.page_index >= header.active_page || last.last_date().map_or(false, |date| date >= to),
+        LucraErrorCode::PriceHistoryGap
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use solana_program::pubkey::Pubkey;
+    use crate::{
+        helpers::price_history::{last_price_mut, push_price},
+        state::PRICE_HISTORY_PAGE_LENGTH,
+    };
+
+    const DAY: i64 = UNIX_DAY;
+
+    fn push_day(price_history: &mut PriceHistory, day: i64) {
+        push_price(price_history, day * DAY, 20_000_000 + day as u64, 6, 1_000_000, 6);
+    }
+
+    /// Pages with every day from `first` to `last` archived, the price history keeps the last 30 of them
+    fn paged_history(page_count: u64, first: i64, last: i64) -> (PriceHistoryHeader, Vec<PriceHistoryPage>, PriceHistory) {
+        let mut header = PriceHistoryHeader::zeroed();
+        header.init(&Pubkey::new_unique());
+        let mut pages: Vec<PriceHistoryPage> = (0..page_count)
+            .map(|_| {
+                let mut page = PriceHistoryPage::zeroed();
+                page.init(header.record_page().unwrap());
+                page
+            })
+            .collect();
+
+        let mut price_history = PriceHistory::default();
+        for day in first..=last {
+            push_day(&mut price_history, day);
+            let mut page_refs: Vec<&mut PriceHistoryPage> = pages.iter_mut().collect();
+            archive_recent_prices(&mut header, &mut page_refs, &price_history).unwrap();
+        }
+
+        (header, pages, price_history)
+    }
+
+    #[test]
+    fn test_days_are_walked_across_pages() {
+        let length = PRICE_HISTORY_PAGE_LENGTH as i64;
+        let (header, pages, price_history) = paged_history(3, 1, 2 * length + 10);
+        assert_eq!(header.active_page, 2);
+
+        // From the middle of the first page to the middle of the third
+        let pages: Vec<&PriceHistoryPage> = pages.iter().collect();
+        let days = collect_paged_days(&header, &pages, &price_history, 20 * DAY, (2 * length + 5) * DAY).unwrap();
+
+        assert_eq!(days.len(), (2 * length + 5 - 20 + 1) as usize);
+        for (offset, price) in days.iter().enumerate() {
+            let day = 20 + offset as i64;
+            assert_eq!(price.date, day * DAY);
+            assert_eq!(price.sol_price, 20_000_000 + day as u64);
+        }
+    }
+
+    #[test]
+    fn test_pages_left_out_are_a_gap() {
+        let length = PRICE_HISTORY_PAGE_LENGTH as i64;
+        let (header, pages, price_history) = paged_history(3, 1, 2 * length + 10);
+        let first_day = 20 * DAY;
+        let last_day = (2 * length + 5) * DAY;
+
+        // Missing the first page
+        let later: Vec<&PriceHistoryPage> = pages[1..].iter().collect();
+        assert!(collect_paged_days(&header, &later, &price_history, first_day, last_day).is_err());
+        // Missing the active page
+        let earlier: Vec<&PriceHistoryPage> = pages[..2].iter().collect();
+        assert!(collect_paged_days(&header, &earlier, &price_history, first_day, last_day).is_err());
+        // Out of order
+        let shuffled = vec![&pages[0], &pages[2], &pages[1]];
+        assert!(collect_paged_days(&header, &shuffled, &price_history, first_day, last_day).is_err());
+        // None at all
+        assert!(collect_paged_days(&header, &[], &price_history, first_day, last_day).is_err());
+
+        // Only the pages holding the range are needed
+        let second: Vec<&PriceHistoryPage> = pages[1..2].iter().collect();
+        let days = collect_paged_days(&header, &second, &price_history, (length + 2) * DAY, (length + 4) * DAY).unwrap();
+        assert_eq!(days.len(), 3);
+        assert!(days.iter().all(|price| price.sol_price != 0));
+    }
+
+    #[test]
+    fn test_days_never_recorded_have_no_prices() {
+        let (mut header, mut pages, mut price_history) = paged_history(1, 1, 3);
+        // The crank missed day 4 entirely
+        push_day(&mut price_history, 5);
+        let mut page_refs: Vec<&mut PriceHistoryPage> = pages.iter_mut().collect();
+        archive_recent_prices(&mut header, &mut page_refs, &price_history).unwrap();
+
+        let pages: Vec<&PriceHistoryPage> = pages.iter().collect();
+        let days = collect_paged_days(&header, &pages, &price_history, 2 * DAY, 5 * DAY).unwrap();
+        let dates: Vec<i64> = days.iter().map(|price| price.date).collect();
+        assert_eq!(dates, vec![2 * DAY, 3 * DAY, 4 * DAY, 5 * DAY]);
+        assert_eq!(days[2].sol_price, 0);
+        assert_eq!(days[2].lucra_price, 0);
+        assert_ne!(days[3].sol_price, 0);
+
+        // Days from before the price history was paged aren't anywhere either
+        let days = collect_paged_days(&header, &pages, &price_history, -DAY, DAY).unwrap();
+        assert_eq!(days.len(), 3);
+        assert_eq!(days[0].sol_price + days[1].sol_price, 0);
+        assert_ne!(days[2].sol_price, 0);
+
+        assert!(collect_paged_days(&header, &pages, &price_history, 5 * DAY, 4 * DAY).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_days_not_archived_yet_come_from_the_price_history() {
+        let (header, pages, mut price_history) = paged_history(1, 1, 3);
+        // Updated without the pages
+        push_day(&mut price_history, 4);
+
+        let pages: Vec<&PriceHistoryPage> = pages.iter().collect();
+        let days = collect_paged_days(&header, &pages, &price_history, 3 * DAY, 4 * DAY).unwrap();
+        assert_eq!(days[1].date, 4 * DAY);
+        assert_eq!(days[1].sol_price, 20_000_004);
+    }
+
+    #[test]
+    fn test_zeroed_out_day_is_archived_zeroed() {
+        let (mut header, mut pages, mut price_history) = paged_history(1, 1, 3);
+        // Day 3 didn't get enough updates
+        last_price_mut(&mut price_history).unwrap().zero_out_prices();
+        push_day(&mut price_history, 4);
+
+        let mut page_refs: Vec<&mut PriceHistoryPage> = pages.iter_mut().collect();
+        archive_recent_prices(&mut header, &mut page_refs, &price_history).unwrap();
+
+        assert_eq!(pages[0].prices().len(), 4);
+        assert_eq!(pages[0].price_for_date(3 * DAY).unwrap().sol_price, 0);
+        assert_eq!(header.last_date, 4 * DAY);
+    }
+
+    #[test]
+    fn test_price_history_that_wrote_over_unchecked_days_is_a_gap() {
+        let mut price_history = PriceHistory::default();
+        let capacity = price_history.prices.len() as i64;
+        for day in 1..=capacity {
+            push_day(&mut price_history, day);
+        }
+        // Nothing written over yet
+        assert!(check_price_history_covers(&price_history, DAY).is_ok());
+
+        push_day(&mut price_history, capacity + 1);
+        assert!(check_price_history_covers(&price_history, DAY).is_err());
+        assert!(check_price_history_covers(&price_history, 2 * DAY).is_ok());
+        assert!(check_price_history_covers(&price_history, (capacity + 2) * DAY).is_ok());
+
+        // The legacy layout can't tell
+        let mut legacy = price_history;
+        legacy.head = 0;
+        legacy.len = 0;
+        assert!(check_price_history_covers(&legacy, DAY).is_ok());
+    }
+}
@@ -0,0 +1,99 @@
+use rust_decimal::Decimal;
+use crate::{
+    helpers::constants::{
+        DEFAULT_PEG_LOWER_BOUND,
+        DEFAULT_PEG_UPPER_BOUND,
+        DEFAULT_PENALTY_BAND_WIDTH,
+        DEFAULT_PENALTY_MULTIPLIER_PER_BAND,
+    },
+    lucra_core::penalty::is_peg_broken,
+    state::SystemState,
+    units::Bps,
+};
+
+// The peg band and penalty steps the DAO set on the system state. System states initialized before they were
+// settings read them as zero, those get the defaults the fixed steps had.
+
+pub fn peg_lower_bound(system_state: &SystemState) -> Bps {
+    or_default(system_state.peg_lower_bound, DEFAULT_PEG_LOWER_BOUND)
+}
+
+pub fn peg_upper_bound(system_state: &SystemState) -> Bps {
+    or_default(system_state.peg_upper_bound, DEFAULT_PEG_UPPER_BOUND)
+}
+
+pub fn penalty_band_width(system_state: &SystemState) -> Bps {
+    or_default(system_state.penalty_band_width, DEFAULT_PENALTY_BAND_WIDTH)
+}
+
+pub fn penalty_multiplier_per_band(system_state: &SystemState) -> u32 {
+    match system_state.penalty_multiplier_per_band {
+        0 => DEFAULT_PENALTY_MULTIPLIER_PER_BAND,
+        penalty_multiplier_per_band => penalty_multiplier_per_band,
+    }
+}
+
+/// Sets `peg_broken` from the mata price against the DAO's bounds
+pub fn update_peg(system_state: &mut SystemState, mata_price: Decimal) {
+    system_state.peg_broken = is_peg_broken(mata_price, peg_lower_bound(system_state), peg_upper_bound(system_state));
+}
+
+fn or_default(bps: Bps, default: Bps) -> Bps {
+    if bps == Bps::ZERO {
+        return default;
+    }
+
+    bps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_unset_settings_use_the_defaults() {
+        let system_state = SystemState::default();
+
+        assert_eq!(peg_lower_bound(&system_state), DEFAULT_PEG_LOWER_BOUND);
+        assert_eq!(peg_upper_bound(&system_state), DEFAULT_PEG_UPPER_BOUND);
+        assert_eq!(penalty_band_width(&system_state), DEFAULT_PENALTY_BAND_WIDTH);
+        assert_eq!(penalty_multiplier_per_band(&system_state), DEFAULT_PENALTY_MULTIPLIER_PER_BAND);
+    }
+
+    #[test]
+    fn test_set_settings_are_kept() {
+        let mut system_state = SystemState::default();
+        system_state.peg_lower_bound = Bps(9_500);
+        system_state.peg_upper_bound = Bps(10_500);
+        system_state.penalty_band_width = Bps(100);
+        system_state.penalty_multiplier_per_band = 3;
+
+        assert_eq!(peg_lower_bound(&system_state), Bps(9_500));
+        assert_eq!(peg_upper_bound(&system_state), Bps(10_500));
+        assert_eq!(penalty_band_width(&system_state), Bps(100));
+        assert_eq!(penalty_multiplier_per_band(&system_state), 3);
+    }
+
+    #[test]
+    fn test_peg_breaks_outside_the_dao_bounds() {
+        let mut system_state = SystemState::default();
+        system_state.peg_lower_bound = Bps(9_500);
+        system_state.peg_upper_bound = Bps(10_500);
+
+        // Under the old fixed $0.98 but inside the DAO's band
+        update_peg(&mut system_state, dec!(0.97));
+        assert!(!system_state.peg_broken);
+        update_peg(&mut system_state, dec!(0.9499));
+        assert!(system_state.peg_broken);
+        update_peg(&mut system_state, dec!(1.0501));
+        assert!(system_state.peg_broken);
+        update_peg(&mut system_state, dec!(1));
+        assert!(!system_state.peg_broken);
+
+        // An unset band breaks where the fixed one did
+        let mut system_state = SystemState::default();
+        update_peg(&mut system_state, dec!(0.9799));
+        assert!(system_state.peg_broken);
+    }
+}
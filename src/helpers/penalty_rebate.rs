@@ -0,0 +1,212 @@
+use crate::{
+    error::{
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::price_history::price_for_date,
+    state::{
+        HistoricPrice,
+        MataLoan,
+        PenaltyJournal,
+        PriceHistory,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::PenaltyRebate);
+
+// When the DAO corrects a day's prices, penalty a loan was charged for that day is recomputed from its
+// journal. Penalty that hasn't been harvested yet is reduced, penalty that was already harvested is owed
+// back as a rebate and paid out of the loan's share of the vault yield when the loan is closed.
+// Corrections only ever lower a charge, a day that should have been charged more stays as it was.
+
+/// Scales what was charged by how the day's penalty changed. Both day penalties are computed with the
+/// loan as it is now, so collateral added or removed since the day was charged doesn't skew the ratio.
+pub fn calc_corrected_penalty(charged: u64, original_day_penalty: u64, corrected_day_penalty: u64) -> LucraResult<u64> {
+    if original_day_penalty == 0 || corrected_day_penalty >= original_day_penalty {
+        return Ok(charged);
+    }
+
+    let corrected = (charged as u128)
+        .checked_mul(corrected_day_penalty as u128)
+        .ok_or(math_err!())?
+        .checked_div(original_day_penalty as u128)
+        .ok_or(math_err!())?;
+
+    Ok(corrected as u64)
+}
+
+/// Recomputes every journaled day whose prices in the history no longer match the ones it was charged at
+/// and returns how much less the loan should have been charged. Entries are moved to the corrected prices
+/// so running it again for the same correction finds nothing.
+pub fn recompute_journal(
+    journal: &mut PenaltyJournal,
+    price_history: &PriceHistory,
+    day_penalty: impl Fn(&HistoricPrice) -> LucraResult<u64>,
+) -> LucraResult<u64> {
+    let mut reduction = 0_u64;
+
+    for entry in journal.entries.iter_mut().filter(|entry| entry.date != 0) {
+        let history = match price_for_date(price_history, entry.date) {
+            Some(history) => history,
+            None => continue,
+        };
+        if history.sol_price == entry.sol_price && history.lucra_price == entry.lucra_price {
+            continue;
+        }
+
+        let charged_at = HistoricPrice {
+            sol_price: entry.sol_price,
+            lucra_price: entry.lucra_price,
+            ..*history
+        };
+        let original_day_penalty = day_penalty(&charged_at)?;
+        // A day corrected to a zeroed out price wouldn't have been charged
+        let corrected_day_penalty = if history.sol_price == 0 || history.lucra_price == 0 {
+            0
+        } else {
+            day_penalty(history)?
+        };
+        let corrected = calc_corrected_penalty(entry.penalty, original_day_penalty, corrected_day_penalty)?;
+
+        reduction = reduction
+            .checked_add(entry.penalty - corrected)
+            .ok_or(math_err!())?;
+        entry.penalty = corrected;
+        entry.sol_price = history.sol_price;
+        entry.lucra_price = history.lucra_price;
+    }
+
+    Ok(reduction)
+}
+
+/// Takes the reduction off penalty waiting to be harvested first, the rest is credited as a rebate.
+/// The rebate never adds up to more than the loan has had harvested. Returns what was credited.
+pub fn apply_penalty_reduction(loan: &mut MataLoan, reduction: u64) -> LucraResult<u64> {
+    let from_pending = reduction.min(loan.penalty_to_harvest);
+    loan.penalty_to_harvest -= from_pending;
+
+    let rebatable = loan.penalty_harvested.saturating_sub(loan.penalty_rebate);
+    let rebate = (reduction - from_pending).min(rebatable);
+    loan.penalty_rebate = loan.penalty_rebate
+        .checked_add(rebate)
+        .ok_or(math_err!())?;
+
+    Ok(rebate)
+}
+
+/// Lamports of the rebate paid on close, limited to what the loan's msol earned over the collateral returned
+pub fn calc_payable_rebate(penalty_rebate: u64, msol_collateral_lamports: u64, sol_to_return: u64) -> u64 {
+    penalty_rebate.min(msol_collateral_lamports.saturating_sub(sol_to_return))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        helpers::price_history::{price_for_date_mut, push_price},
+        state::PenaltyJournalEntry,
+    };
+
+    const DAY: i64 = 86_400;
+
+    // Stand in for the penalty calculation, charges more the lower sol was
+    fn day_penalty(history: &HistoricPrice) -> LucraResult<u64> {
+        Ok(1_000_000_000_000 / history.sol_price)
+    }
+
+    fn charge(journal: &mut PenaltyJournal, price_history: &PriceHistory, date: i64) -> u64 {
+        let history = price_for_date(price_history, date).unwrap();
+        let penalty = day_penalty(history).unwrap();
+        journal.record(PenaltyJournalEntry {
+            date,
+            penalty,
+            sol_price: history.sol_price,
+            lucra_price: history.lucra_price,
+        });
+        penalty
+    }
+
+    #[test]
+    fn test_corrected_penalty_only_goes_down() {
+        assert_eq!(calc_corrected_penalty(1_000, 200, 50).unwrap(), 250);
+        assert_eq!(calc_corrected_penalty(1_000, 200, 0).unwrap(), 0);
+        assert_eq!(calc_corrected_penalty(1_000, 200, 300).unwrap(), 1_000);
+        assert_eq!(calc_corrected_penalty(1_000, 0, 300).unwrap(), 1_000);
+        assert_eq!(calc_corrected_penalty(u64::MAX, u64::MAX, u64::MAX - 1).unwrap(), u64::MAX - 1);
+    }
+
+    #[test]
+    fn test_reduction_comes_off_pending_penalty_first() {
+        let mut loan = MataLoan {
+            penalty_to_harvest: 300,
+            penalty_harvested: 1_000,
+            ..MataLoan::default()
+        };
+
+        assert_eq!(apply_penalty_reduction(&mut loan, 200).unwrap(), 0);
+        assert_eq!((loan.penalty_to_harvest, loan.penalty_rebate), (100, 0));
+
+        assert_eq!(apply_penalty_reduction(&mut loan, 600).unwrap(), 500);
+        assert_eq!((loan.penalty_to_harvest, loan.penalty_rebate), (0, 500));
+
+        // Capped by what was harvested
+        assert_eq!(apply_penalty_reduction(&mut loan, 900).unwrap(), 500);
+        assert_eq!(loan.penalty_rebate, 1_000);
+    }
+
+    #[test]
+    fn test_payable_rebate_is_limited_to_the_loans_yield() {
+        assert_eq!(calc_payable_rebate(100, 1_500, 1_000), 100);
+        assert_eq!(calc_payable_rebate(800, 1_500, 1_000), 500);
+        assert_eq!(calc_payable_rebate(800, 900, 1_000), 0);
+    }
+
+    #[test]
+    fn test_correction_then_rebate() {
+        let mut price_history = PriceHistory::default();
+        // The oracle misreported sol at $10 on day 2, it traded at $20
+        push_price(&mut price_history, DAY, 20_000_000, 6, 1_000_000, 6);
+        push_price(&mut price_history, 2 * DAY, 10_000_000, 6, 1_000_000, 6);
+        push_price(&mut price_history, 3 * DAY, 20_000_000, 6, 1_000_000, 6);
+
+        let mut loan = MataLoan::default();
+        let mut journal = PenaltyJournal::default();
+        let charged: u64 = [DAY, 2 * DAY, 3 * DAY]
+            .iter()
+            .map(|date| charge(&mut journal, &price_history, *date))
+            .sum();
+        assert_eq!(charged, 200_000);
+        // All of it was harvested before the correction
+        loan.penalty_harvested = charged;
+
+        price_for_date_mut(&mut price_history, 2 * DAY).unwrap().sol_price = 20_000_000;
+        let reduction = recompute_journal(&mut journal, &price_history, day_penalty).unwrap();
+        assert_eq!(reduction, 50_000);
+        assert_eq!(apply_penalty_reduction(&mut loan, reduction).unwrap(), 50_000);
+        assert_eq!(loan.penalty_rebate, 50_000);
+
+        // Running it again for the same correction changes nothing
+        assert_eq!(recompute_journal(&mut journal, &price_history, day_penalty).unwrap(), 0);
+
+        // Corrected again, only the difference from the last correction is owed
+        price_for_date_mut(&mut price_history, 2 * DAY).unwrap().sol_price = 40_000_000;
+        assert_eq!(recompute_journal(&mut journal, &price_history, day_penalty).unwrap(), 25_000);
+        assert_eq!(journal.entry_for_mut(2 * DAY).unwrap().penalty, 25_000);
+    }
+
+    #[test]
+    fn test_days_outside_the_history_are_left_alone() {
+        let mut price_history = PriceHistory::default();
+        push_price(&mut price_history, DAY, 10_000_000, 6, 1_000_000, 6);
+        let mut journal = PenaltyJournal::default();
+        charge(&mut journal, &price_history, DAY);
+
+        let mut rewritten = PriceHistory::default();
+        push_price(&mut rewritten, 2 * DAY, 20_000_000, 6, 1_000_000, 6);
+
+        assert_eq!(recompute_journal(&mut journal, &rewritten, day_penalty).unwrap(), 0);
+        assert_eq!(journal.entry_for_mut(DAY).unwrap().penalty, 100_000);
+    }
+}
@@ -0,0 +1,409 @@
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use crate::{
+    helpers::constants::MIN_PRICE_UPDATE_INTERVAL,
+    state::{
+        HistoricPrice,
+        PriceHistory,
+    },
+};
+
+// The price history is a ring buffer. `head` counts every price ever written so it only increases,
+// `len` is how many of the slots hold a price. Logical index 0 is the oldest price.
+
+pub fn physical_index(price_history: &PriceHistory, logical_index: usize) -> usize {
+    let capacity = price_history.prices.len() as u64;
+    let oldest = price_history.head - price_history.len;
+
+    ((oldest + logical_index as u64) % capacity) as usize
+}
+
+pub fn price_at(price_history: &PriceHistory, logical_index: usize) -> &HistoricPrice {
+    &price_history.prices[physical_index(price_history, logical_index)]
+}
+
+/// Writes a new price over the oldest slot
+pub fn push_price(
+    price_history: &mut PriceHistory,
+    date: i64,
+    sol_price: u64,
+    sol_decimals: u8,
+    lucra_price: u64,
+    lucra_decimals: u8,
+) {
+    let capacity = price_history.prices.len() as u64;
+    let index = (price_history.head % capacity) as usize;
+
+    price_history.prices[index] = HistoricPrice {
+        sol_price,
+        sol_decimals,
+        lucra_price,
+        lucra_decimals,
+        date,
+        mata_deviation_bps: 0,
+        mata_deviation_seconds: 0,
+    };
+    price_history.head += 1;
+    price_history.len = std::cmp::min(price_history.len + 1, capacity);
+}
+
+/// The most recently pushed price
+pub fn last_price_mut(price_history: &mut PriceHistory) -> Option<&mut HistoricPrice> {
+    if price_history.len == 0 {
+        return None;
+    }
+
+    let index = physical_index(price_history, price_history.len as usize - 1);
+    Some(&mut price_history.prices[index])
+}
+
+// Every update is a sample of the day's prices. `samples_today` counts the ones folded into the newest day so it's
+// their plain average however many updates the day got. Updates closer together than the crank's hour are
+// rejected, otherwise a crank could farm rewards and pull the average towards whatever the price was that minute.
+
+pub fn is_update_too_frequent(price_history: &PriceHistory, now: i64) -> bool {
+    now < price_history.last_update_timestamp.saturating_add(MIN_PRICE_UPDATE_INTERVAL)
+}
+
+/// The average of `samples` samples with one more folded in
+pub fn fold_price_sample(average: u64, samples: u16, sample: u64) -> u64 {
+    let samples = u128::from(samples);
+
+    ((u128::from(average) * samples + u128::from(sample)) / (samples + 1)) as u64
+}
+
+/// Folds the sample into the newest price when it's for the same day, otherwise starts the day with it
+pub fn record_price_sample(price_history: &mut PriceHistory, date: i64, sol_price: u64, lucra_price: u64, decimals: u8) {
+    // Accounts from before samples were counted have none for a day that's already started
+    let samples = std::cmp::max(price_history.samples_today, 1);
+
+    match last_price_mut(price_history) {
+        Some(price) if price.date == date => {
+            price.sol_price = fold_price_sample(price.sol_price, samples, sol_price);
+            price.lucra_price = fold_price_sample(price.lucra_price, samples, lucra_price);
+            price_history.samples_today = samples.saturating_add(1);
+        }
+        _ => {
+            push_price(price_history, date, sol_price, decimals, lucra_price, decimals);
+            price_history.samples_today = 1;
+        }
+    }
+}
+
+// Each day also keeps how far under the peg mata traded, averaged over the time it was sampled.
+// Penalties for the day are multiplied off that average so the moment a crank runs doesn't matter.
+
+/// Bps mata trades under the peg, 0 at or above it
+pub fn calc_peg_deviation_bps(mata_price: Decimal) -> u16 {
+    if mata_price >= Decimal::ONE {
+        return 0;
+    }
+
+    (Decimal::ONE - mata_price.max(Decimal::ZERO))
+        .checked_mul(Decimal::from(10_000))
+        .and_then(|deviation| deviation.floor().to_u16())
+        .unwrap_or(10_000)
+}
+
+/// Seconds a sample taken at `now` stands for, from the previous update or the start of the interval
+pub fn calc_deviation_sample_seconds(last_update_timestamp: i64, interval_start: i64, now: i64) -> u32 {
+    let from = std::cmp::max(last_update_timestamp, interval_start);
+
+    now.saturating_sub(from).clamp(1, u32::MAX.into()) as u32
+}
+
+/// Folds a deviation held for `seconds` into the day's time weighted average
+pub fn accumulate_peg_deviation(price: &mut HistoricPrice, deviation_bps: u16, seconds: u32) {
+    let total_seconds = u64::from(price.mata_deviation_seconds) + u64::from(seconds);
+    if total_seconds == 0 {
+        return;
+    }
+
+    let weighted = u64::from(price.mata_deviation_bps) * u64::from(price.mata_deviation_seconds)
+        + u64::from(deviation_bps) * u64::from(seconds);
+    price.mata_deviation_bps = (weighted / total_seconds) as u16;
+    price.mata_deviation_seconds = std::cmp::min(total_seconds, u32::MAX.into()) as u32;
+}
+
+/// Accounts written before the ring buffer kept head/len have them both at 0. Order the existing
+/// prices by date so they can be indexed the same way as new ones.
+pub fn migrate_to_ring_buffer(price_history: &mut PriceHistory) {
+    if price_history.len != 0 {
+        return;
+    }
+
+    let prices = ordered_prices(price_history);
+
+    let len = prices.len();
+    for (index, price) in prices.into_iter().enumerate() {
+        price_history.prices[index] = price;
+    }
+    for price in price_history.prices.iter_mut().skip(len) {
+        *price = HistoricPrice::default();
+    }
+
+    price_history.head = len as u64;
+    price_history.len = len as u64;
+}
+
+/// The prices the history holds oldest first, on either layout
+pub fn ordered_prices(price_history: &PriceHistory) -> Vec<HistoricPrice> {
+    if price_history.len != 0 {
+        return (0..price_history.len as usize)
+            .map(|index| *price_at(price_history, index))
+            .collect();
+    }
+
+    let mut prices: Vec<HistoricPrice> = price_history.prices
+        .iter()
+        .filter(|price| price.date != 0)
+        .copied()
+        .collect();
+    prices.sort_by_key(|price| price.date);
+
+    prices
+}
+
+/// Logical index of the first price dated after `date`
+pub fn first_index_after(price_history: &PriceHistory, date: i64) -> usize {
+    let mut low = 0;
+    let mut high = price_history.len as usize;
+
+    while low < high {
+        let mid = (low + high) / 2;
+        if price_at(price_history, mid).date <= date {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    low
+}
+
+/// Logical index of the price recorded for `date`, None once it has fallen out of the history
+pub fn index_of_date(price_history: &PriceHistory, date: i64) -> Option<usize> {
+    let index = first_index_after(price_history, date.checked_sub(1)?);
+    if index < price_history.len as usize && price_at(price_history, index).date == date {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+pub fn price_for_date(price_history: &PriceHistory, date: i64) -> Option<&HistoricPrice> {
+    index_of_date(price_history, date).map(|index| price_at(price_history, index))
+}
+
+pub fn price_for_date_mut(price_history: &mut PriceHistory, date: i64) -> Option<&mut HistoricPrice> {
+    let index = physical_index(price_history, index_of_date(price_history, date)?);
+    Some(&mut price_history.prices[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+
+    fn push_day(price_history: &mut PriceHistory, day: i64) {
+        push_price(price_history, day * DAY, 20_000_000, 6, 1_000_000, 6);
+    }
+
+    #[test]
+    fn test_push_price_wraps_and_keeps_order() {
+        let mut price_history = PriceHistory::default();
+        let capacity = price_history.prices.len() as i64;
+
+        for day in 1..=capacity + 5 {
+            push_day(&mut price_history, day);
+        }
+
+        assert_eq!(price_history.len, capacity as u64);
+        assert_eq!(price_history.head, (capacity + 5) as u64);
+        assert_eq!(price_at(&price_history, 0).date, 6 * DAY);
+        assert_eq!(price_at(&price_history, capacity as usize - 1).date, (capacity + 5) * DAY);
+        for index in 1..capacity as usize {
+            assert!(price_at(&price_history, index - 1).date < price_at(&price_history, index).date);
+        }
+    }
+
+    #[test]
+    fn test_first_index_after() {
+        let mut price_history = PriceHistory::default();
+        for day in 1..=10 {
+            push_day(&mut price_history, day);
+        }
+
+        assert_eq!(first_index_after(&price_history, 0), 0);
+        assert_eq!(first_index_after(&price_history, 5 * DAY), 5);
+        assert_eq!(first_index_after(&price_history, 5 * DAY + 1), 5);
+        assert_eq!(first_index_after(&price_history, 10 * DAY), 10);
+    }
+
+    #[test]
+    fn test_price_for_date() {
+        let mut price_history = PriceHistory::default();
+        let capacity = price_history.prices.len() as i64;
+        for day in [1, 2, 4] {
+            push_day(&mut price_history, day);
+        }
+
+        assert_eq!(price_for_date(&price_history, 2 * DAY).unwrap().date, 2 * DAY);
+        assert!(price_for_date(&price_history, 3 * DAY).is_none());
+        assert!(price_for_date(&price_history, 2 * DAY + 1).is_none());
+        assert!(price_for_date(&price_history, 5 * DAY).is_none());
+
+        price_for_date_mut(&mut price_history, 4 * DAY).unwrap().sol_price = 1;
+        assert_eq!(price_at(&price_history, 2).sol_price, 1);
+
+        // Days that were written over can't be found anymore
+        for day in 5..=capacity + 2 {
+            push_day(&mut price_history, day);
+        }
+        assert!(price_for_date(&price_history, DAY).is_none());
+        assert!(price_for_date(&price_history, 4 * DAY).is_some());
+    }
+
+    #[test]
+    fn test_updates_within_the_hour_are_too_frequent() {
+        let mut price_history = PriceHistory::default();
+        // Never updated
+        assert!(!is_update_too_frequent(&price_history, DAY));
+
+        price_history.last_update_timestamp = DAY;
+        assert!(is_update_too_frequent(&price_history, DAY));
+        assert!(is_update_too_frequent(&price_history, DAY + MIN_PRICE_UPDATE_INTERVAL - 1));
+        // A crank running a few minutes early is still on time
+        assert!(!is_update_too_frequent(&price_history, DAY + MIN_PRICE_UPDATE_INTERVAL));
+        assert!(!is_update_too_frequent(&price_history, DAY + 3_600));
+    }
+
+    #[test]
+    fn test_day_is_the_average_of_its_samples() {
+        let mut price_history = PriceHistory::default();
+        for (sol_price, lucra_price) in [(20_000_000, 1_000_000), (23_000_000, 1_300_000), (26_000_000, 700_000)] {
+            record_price_sample(&mut price_history, DAY, sol_price, lucra_price, 6);
+        }
+
+        // Halving on every update would have left the last sample counting for half the day
+        let price = *price_at(&price_history, 0);
+        assert_eq!(price_history.len, 1);
+        assert_eq!(price_history.samples_today, 3);
+        assert_eq!(price.sol_price, 23_000_000);
+        assert_eq!(price.lucra_price, 1_000_000);
+
+        assert_eq!(fold_price_sample(10, 0, 20), 20);
+        assert_eq!(fold_price_sample(10, 3, 20), 12);
+        assert_eq!(fold_price_sample(u64::MAX, u16::MAX, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_new_day_starts_its_samples_over() {
+        let mut price_history = PriceHistory::default();
+        for _ in 0..5 {
+            record_price_sample(&mut price_history, DAY, 20_000_000, 1_000_000, 6);
+        }
+        assert_eq!(price_history.samples_today, 5);
+
+        record_price_sample(&mut price_history, 2 * DAY, 30_000_000, 2_000_000, 6);
+        assert_eq!(price_history.samples_today, 1);
+        assert_eq!(price_history.len, 2);
+        assert_eq!(price_at(&price_history, 0).sol_price, 20_000_000);
+        assert_eq!(price_at(&price_history, 1).sol_price, 30_000_000);
+
+        // A day started before samples were counted has the one already in it
+        price_history.samples_today = 0;
+        record_price_sample(&mut price_history, 2 * DAY, 20_000_000, 1_000_000, 6);
+        assert_eq!(price_history.samples_today, 2);
+        assert_eq!(price_at(&price_history, 1).sol_price, 25_000_000);
+    }
+
+    #[test]
+    fn test_peg_deviation_bps() {
+        assert_eq!(calc_peg_deviation_bps(Decimal::new(101, 2)), 0);
+        assert_eq!(calc_peg_deviation_bps(Decimal::ONE), 0);
+        assert_eq!(calc_peg_deviation_bps(Decimal::new(9_999, 4)), 1);
+        assert_eq!(calc_peg_deviation_bps(Decimal::new(95, 2)), 500);
+        assert_eq!(calc_peg_deviation_bps(Decimal::new(60, 2)), 4_000);
+        assert_eq!(calc_peg_deviation_bps(Decimal::ZERO), 10_000);
+    }
+
+    #[test]
+    fn test_deviation_sample_seconds() {
+        // Measured from the later of the last update and the interval start
+        assert_eq!(calc_deviation_sample_seconds(DAY + 3_600, DAY, DAY + 7_200), 3_600);
+        assert_eq!(calc_deviation_sample_seconds(DAY - 3_600, DAY, DAY + 600), 600);
+        // A sample always counts for something
+        assert_eq!(calc_deviation_sample_seconds(DAY, DAY, DAY), 1);
+    }
+
+    #[test]
+    fn test_peg_deviation_is_time_weighted() {
+        let mut price = HistoricPrice::default();
+
+        accumulate_peg_deviation(&mut price, 0, 18 * 3_600);
+        accumulate_peg_deviation(&mut price, 1_000, 6 * 3_600);
+
+        assert_eq!(price.mata_deviation_bps, 250);
+        assert_eq!(price.mata_deviation_seconds, 24 * 3_600);
+
+        // A sample that stands for no time doesn't move the average
+        accumulate_peg_deviation(&mut price, 10_000, 0);
+        assert_eq!(price.mata_deviation_bps, 250);
+    }
+
+    #[test]
+    fn test_last_price_mut() {
+        let mut price_history = PriceHistory::default();
+        assert!(last_price_mut(&mut price_history).is_none());
+
+        let capacity = price_history.prices.len() as i64;
+        for day in 1..=capacity + 2 {
+            push_day(&mut price_history, day);
+        }
+
+        assert_eq!(last_price_mut(&mut price_history).unwrap().date, (capacity + 2) * DAY);
+    }
+
+    #[test]
+    fn test_migrate_to_ring_buffer() {
+        let mut price_history = PriceHistory::default();
+        let days = [7, 3, 0, 9, 1];
+        for (index, day) in days.iter().enumerate() {
+            price_history.prices[index].date = day * DAY;
+            price_history.prices[index].sol_price = *day as u64;
+        }
+
+        migrate_to_ring_buffer(&mut price_history);
+
+        assert_eq!(price_history.len, 4);
+        assert_eq!(price_history.head, 4);
+        let migrated: Vec<i64> = (0..4).map(|index| price_at(&price_history, index).date).collect();
+        assert_eq!(migrated, vec![DAY, 3 * DAY, 7 * DAY, 9 * DAY]);
+        assert_eq!(price_at(&price_history, 2).sol_price, 7);
+
+        // New prices land after the migrated ones
+        push_day(&mut price_history, 10);
+        assert_eq!(price_at(&price_history, 4).date, 10 * DAY);
+    }
+
+    #[test]
+    fn test_ordered_prices_reads_either_layout() {
+        let mut legacy = PriceHistory::default();
+        for (index, day) in [7, 3, 0, 9].iter().enumerate() {
+            legacy.prices[index].date = day * DAY;
+        }
+        let dates: Vec<i64> = ordered_prices(&legacy).iter().map(|price| price.date).collect();
+        assert_eq!(dates, vec![3 * DAY, 7 * DAY, 9 * DAY]);
+
+        let mut ring = PriceHistory::default();
+        let capacity = ring.prices.len() as i64;
+        for day in 1..=capacity + 3 {
+            push_day(&mut ring, day);
+        }
+        let prices = ordered_prices(&ring);
+        assert_eq!(prices.len(), capacity as usize);
+        assert_eq!(prices[0].date, 4 * DAY);
+        assert_eq!(prices[capacity as usize - 1].date, (capacity + 3) * DAY);
+    }
+}
@@ -0,0 +1,176 @@
+use arrayref::array_ref;
+use rust_decimal::Decimal;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    lucra_core::quote::get_price,
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::Pyth);
+
+// Layout of a Pyth v2 price account. Only the header and the aggregate price are read, the publisher
+// components written after them aren't.
+const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+const PYTH_VERSION_2: u32 = 2;
+const PYTH_ACCOUNT_TYPE_PRICE: u32 = 3;
+const PYTH_PRICE_TYPE_PRICE: u32 = 1;
+const PYTH_STATUS_TRADING: u32 = 1;
+
+const PYTH_MAGIC_OFFSET: usize = 0;
+const PYTH_VERSION_OFFSET: usize = 4;
+const PYTH_ACCOUNT_TYPE_OFFSET: usize = 8;
+const PYTH_PRICE_TYPE_OFFSET: usize = 16;
+const PYTH_EXPO_OFFSET: usize = 20;
+const PYTH_AGG_PRICE_OFFSET: usize = 208;
+const PYTH_AGG_CONF_OFFSET: usize = 216;
+const PYTH_AGG_STATUS_OFFSET: usize = 224;
+const PYTH_AGG_PUB_SLOT_OFFSET: usize = 232;
+const PYTH_MIN_LEN: usize = PYTH_AGG_PUB_SLOT_OFFSET + 8;
+
+/// Feeds quote at most 18 decimals, the same as the in-house oracles
+const PYTH_MAX_DECIMALS: i32 = 18;
+
+/// The aggregate price of a Pyth price account, `price * 10^expo` give or take `conf`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PythPrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub status: u32,
+    pub pub_slot: u64,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(*array_ref![data, offset, 4])
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(*array_ref![data, offset, 8])
+}
+
+impl PythPrice {
+    /// Rejects anything that isn't a v2 price account
+    pub fn decode(data: &[u8]) -> LucraResult<Self> {
+        check!(data.len() >= PYTH_MIN_LEN, LucraErrorCode::InvalidAccountInput)?;
+        check_eq!(read_u32(data, PYTH_MAGIC_OFFSET), PYTH_MAGIC, LucraErrorCode::InvalidAccountInput)?;
+        check_eq!(read_u32(data, PYTH_VERSION_OFFSET), PYTH_VERSION_2, LucraErrorCode::OracleVersionNotSupported)?;
+        check_eq!(read_u32(data, PYTH_ACCOUNT_TYPE_OFFSET), PYTH_ACCOUNT_TYPE_PRICE, LucraErrorCode::InvalidAccountInput)?;
+        check_eq!(read_u32(data, PYTH_PRICE_TYPE_OFFSET), PYTH_PRICE_TYPE_PRICE, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(PythPrice {
+            price: read_u64(data, PYTH_AGG_PRICE_OFFSET) as i64,
+            conf: read_u64(data, PYTH_AGG_CONF_OFFSET),
+            expo: read_u32(data, PYTH_EXPO_OFFSET) as i32,
+            status: read_u32(data, PYTH_AGG_STATUS_OFFSET),
+            pub_slot: read_u64(data, PYTH_AGG_PUB_SLOT_OFFSET),
+        })
+    }
+
+    /// Only a price Pyth is trading is read. A confidence interval wider than `max_confidence_bps` of the
+    /// price means the publishers disagree, and it's rejected like a stale price.
+    pub fn to_decimal(&self, current_slot: u64, max_staleness_slots: u64, max_confidence_bps: Bps) -> LucraResult<Decimal> {
+        check_eq!(self.status, PYTH_STATUS_TRADING, LucraErrorCode::OracleStatusNotValid)?;
+        check!(self.pub_slot.saturating_add(max_staleness_slots) >= current_slot, LucraErrorCode::OracleStale)?;
+        check!(self.price > 0, LucraErrorCode::PriceDataInvalid)?;
+        check!(self.expo <= 0 && self.expo >= -PYTH_MAX_DECIMALS, LucraErrorCode::PriceDataInvalid)?;
+
+        let confidence_bps = u128::from(self.conf) * u128::from(Bps::ONE_HUNDRED_PERCENT.get()) / self.price as u128;
+        check!(confidence_bps <= u128::from(max_confidence_bps.get()), LucraErrorCode::PriceDataInvalid)?;
+
+        get_price(self.price as u64, (-self.expo) as u8)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::error::LucraError;
+
+    /// A Pyth v2 price account holding `price * 10^-8`, which is what the SOL/USD feed quotes
+    pub fn pyth_data(price: i64, conf: u64, status: u32, pub_slot: u64) -> Vec<u8> {
+        let mut data = vec![0; 3_312];
+        data[PYTH_MAGIC_OFFSET..PYTH_MAGIC_OFFSET + 4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        data[PYTH_VERSION_OFFSET..PYTH_VERSION_OFFSET + 4].copy_from_slice(&PYTH_VERSION_2.to_le_bytes());
+        data[PYTH_ACCOUNT_TYPE_OFFSET..PYTH_ACCOUNT_TYPE_OFFSET + 4].copy_from_slice(&PYTH_ACCOUNT_TYPE_PRICE.to_le_bytes());
+        data[PYTH_PRICE_TYPE_OFFSET..PYTH_PRICE_TYPE_OFFSET + 4].copy_from_slice(&PYTH_PRICE_TYPE_PRICE.to_le_bytes());
+        data[PYTH_EXPO_OFFSET..PYTH_EXPO_OFFSET + 4].copy_from_slice(&(-8_i32).to_le_bytes());
+        data[PYTH_AGG_PRICE_OFFSET..PYTH_AGG_PRICE_OFFSET + 8].copy_from_slice(&price.to_le_bytes());
+        data[PYTH_AGG_CONF_OFFSET..PYTH_AGG_CONF_OFFSET + 8].copy_from_slice(&conf.to_le_bytes());
+        data[PYTH_AGG_STATUS_OFFSET..PYTH_AGG_STATUS_OFFSET + 4].copy_from_slice(&status.to_le_bytes());
+        data[PYTH_AGG_PUB_SLOT_OFFSET..PYTH_AGG_PUB_SLOT_OFFSET + 8].copy_from_slice(&pub_slot.to_le_bytes());
+        data
+    }
+
+    fn assert_error<T: std::fmt::Debug>(result: LucraResult<T>, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_decode_pyth_price() {
+        let data = pyth_data(4_012_345_678, 1_000_000, PYTH_STATUS_TRADING, 990);
+        let price = PythPrice::decode(&data).unwrap();
+        assert_eq!(price, PythPrice { price: 4_012_345_678, conf: 1_000_000, expo: -8, status: 1, pub_slot: 990 });
+
+        // $40.12345678
+        let expected = Decimal::from(4_012_345_678_u64).checked_div(100_000_000.into()).unwrap();
+        assert_eq!(price.to_decimal(1_000, 25, Bps(200)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_only_pyth_price_accounts_decode() {
+        let valid = pyth_data(4_000_000_000, 0, PYTH_STATUS_TRADING, 1_000);
+
+        let mut data = valid.clone();
+        data[PYTH_MAGIC_OFFSET] ^= 1;
+        assert_error(PythPrice::decode(&data), LucraErrorCode::InvalidAccountInput);
+
+        let mut data = valid.clone();
+        data[PYTH_VERSION_OFFSET] = 1;
+        assert_error(PythPrice::decode(&data), LucraErrorCode::OracleVersionNotSupported);
+
+        // A product or mapping account
+        let mut data = valid.clone();
+        data[PYTH_ACCOUNT_TYPE_OFFSET] = 2;
+        assert_error(PythPrice::decode(&data), LucraErrorCode::InvalidAccountInput);
+
+        // Not a price, e.g. a TWAP
+        let mut data = valid.clone();
+        data[PYTH_PRICE_TYPE_OFFSET] = 0;
+        assert_error(PythPrice::decode(&data), LucraErrorCode::InvalidAccountInput);
+
+        assert_error(PythPrice::decode(&valid[..PYTH_MIN_LEN - 1]), LucraErrorCode::InvalidAccountInput);
+        assert!(PythPrice::decode(&valid[..PYTH_MIN_LEN]).is_ok());
+    }
+
+    #[test]
+    fn test_pyth_price_must_be_trading() {
+        // Unknown, halted and auction
+        for status in [0, 2, 3] {
+            let price = PythPrice::decode(&pyth_data(4_000_000_000, 0, status, 1_000)).unwrap();
+            assert_error(price.to_decimal(1_000, 25, Bps(200)), LucraErrorCode::OracleStatusNotValid);
+        }
+    }
+
+    #[test]
+    fn test_pyth_price_staleness_and_confidence() {
+        let price = PythPrice::decode(&pyth_data(4_000_000_000, 0, PYTH_STATUS_TRADING, 975)).unwrap();
+        assert!(price.to_decimal(1_000, 25, Bps(200)).is_ok());
+        assert_error(price.to_decimal(1_001, 25, Bps(200)), LucraErrorCode::OracleStale);
+
+        // $0.80 on $40.00 is 2%
+        let price = PythPrice::decode(&pyth_data(4_000_000_000, 80_000_000, PYTH_STATUS_TRADING, 1_000)).unwrap();
+        assert!(price.to_decimal(1_000, 25, Bps(200)).is_ok());
+        assert_error(price.to_decimal(1_000, 25, Bps(199)), LucraErrorCode::PriceDataInvalid);
+
+        let price = PythPrice::decode(&pyth_data(-1, 0, PYTH_STATUS_TRADING, 1_000)).unwrap();
+        assert_error(price.to_decimal(1_000, 25, Bps(200)), LucraErrorCode::PriceDataInvalid);
+    }
+}
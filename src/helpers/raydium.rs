@@ -21,6 +21,7 @@ use crate::{
         SourceFileId,
     },
     helpers::{
+        account::verify_program_account,
         constants::{raydium_v4, serum_v3},
         spl::{get_mint_decimals, get_token_balance, verify_balanced_pool, calculate_pool_price},
     },
@@ -30,10 +31,18 @@ declare_check_assert_macros!(SourceFileId::Raydium);
 
 pub const RAYDIUM_FEE: f64 = 0.0025;
 
+// Serum v3 open orders, the account data sits between 5 bytes of padding on each side
+pub const OPEN_ORDERS_LEN: usize = 3228;
+const OPEN_ORDERS_FLAGS_OFFSET: usize = 5;
+const OPEN_ORDERS_MARKET_OFFSET: usize = 13;
+const OPEN_ORDERS_OWNER_OFFSET: usize = 45;
+const OPEN_ORDERS_FLAGS: u64 = 0b101; // Initialized | OpenOrders
+
 pub fn swap(
     accounts: &[AccountInfo],
     token_a_amount_in: u64,
     token_b_amount_in: u64,
+    min_amount_out: u64,
 ) -> ProgramResult {
     #[allow(clippy::deprecated_cfg_attr)]
     #[cfg_attr(rustfmt, rustfmt_skip)]
@@ -59,9 +68,10 @@ pub fn swap(
         serum_vault_signer
         ] = accounts
     {
-        check_eq!(pool_program_id.key, &raydium_v4::id(), LucraErrorCode::InvalidAccountInput)?;
+        verify_program_account(pool_program_id, &raydium_v4::id())?;
+        verify_program_account(serum_program_id, &serum_v3::id())?;
 
-        let (amount_in, min_amount_out) = get_pool_swap_amounts(
+        let (amount_in, pool_min_amount_out) = get_pool_swap_amounts(
             pool_coin_token_account,
             pool_pc_token_account,
             amm_open_orders,
@@ -101,7 +111,8 @@ pub fn swap(
             data: RaydiumSwap {
                 instruction: 9,
                 amount_in,
-                min_amount_out,
+                // The pool's own quote moves with a sandwich, the caller's bound doesn't
+                min_amount_out: pool_min_amount_out.max(min_amount_out),
             }
             .to_vec()?,
         };
@@ -454,6 +465,44 @@ pub fn get_fees(
     ))
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpenOrdersStatus {
+    Initialized,
+    Missing,
+    Mismatched,
+}
+
+pub fn get_open_orders_status(data: &[u8], market: &Pubkey, owner: &Pubkey) -> OpenOrdersStatus {
+    if data.is_empty() {
+        return OpenOrdersStatus::Missing;
+    }
+    if data.len() != OPEN_ORDERS_LEN {
+        return OpenOrdersStatus::Mismatched;
+    }
+
+    let flags = u64::from_le_bytes(*array_ref![data, OPEN_ORDERS_FLAGS_OFFSET, 8]);
+    if flags == 0 {
+        return OpenOrdersStatus::Missing;
+    }
+    let is_for_market = array_ref![data, OPEN_ORDERS_MARKET_OFFSET, 32] == &market.to_bytes();
+    let is_for_owner = array_ref![data, OPEN_ORDERS_OWNER_OFFSET, 32] == &owner.to_bytes();
+
+    if flags & OPEN_ORDERS_FLAGS == OPEN_ORDERS_FLAGS && is_for_market && is_for_owner {
+        OpenOrdersStatus::Initialized
+    } else {
+        OpenOrdersStatus::Mismatched
+    }
+}
+
+/// Run before any Raydium route touches `open_orders`. A missing account fails deep in serum otherwise,
+/// which can't be told apart from any other failure.
+pub fn verify_open_orders(open_orders_ai: &AccountInfo, market_ai: &AccountInfo, owner: &Pubkey) -> LucraResult {
+    let status = get_open_orders_status(&open_orders_ai.try_borrow_data()?, market_ai.key, owner);
+    check!(status != OpenOrdersStatus::Missing, LucraErrorCode::OpenOrdersNotInitialized)?;
+    check_eq!(status, OpenOrdersStatus::Initialized, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(open_orders_ai.owner, &serum_v3::id(), LucraErrorCode::InvalidAccountOwner)
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct RaydiumSwap {
     pub instruction: u8,
@@ -495,6 +544,44 @@ impl RaydiumSwap {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::account::tests::{assert_invalid_program_account_code, FakeAccounts};
+
+    fn open_orders_data(flags: u64, market: &Pubkey, owner: &Pubkey) -> Vec<u8> {
+        let mut data = vec![0; OPEN_ORDERS_LEN];
+        data[..5].copy_from_slice(b"serum");
+        data[OPEN_ORDERS_FLAGS_OFFSET..OPEN_ORDERS_FLAGS_OFFSET + 8].copy_from_slice(&flags.to_le_bytes());
+        data[OPEN_ORDERS_MARKET_OFFSET..OPEN_ORDERS_MARKET_OFFSET + 32].copy_from_slice(market.as_ref());
+        data[OPEN_ORDERS_OWNER_OFFSET..OPEN_ORDERS_OWNER_OFFSET + 32].copy_from_slice(owner.as_ref());
+        data
+    }
+
+    #[test]
+    fn test_open_orders_status() {
+        let market = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+
+        let data = open_orders_data(OPEN_ORDERS_FLAGS, &market, &owner);
+        assert_eq!(get_open_orders_status(&data, &market, &owner), OpenOrdersStatus::Initialized);
+
+        assert_eq!(get_open_orders_status(&[], &market, &owner), OpenOrdersStatus::Missing);
+        assert_eq!(get_open_orders_status(&vec![0; OPEN_ORDERS_LEN], &market, &owner), OpenOrdersStatus::Missing);
+    }
+
+    #[test]
+    fn test_open_orders_for_another_market_or_owner_are_rejected() {
+        let market = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let data = open_orders_data(OPEN_ORDERS_FLAGS, &other, &owner);
+        assert_eq!(get_open_orders_status(&data, &market, &owner), OpenOrdersStatus::Mismatched);
+        let data = open_orders_data(OPEN_ORDERS_FLAGS, &market, &other);
+        assert_eq!(get_open_orders_status(&data, &market, &owner), OpenOrdersStatus::Mismatched);
+        // Initialized, but as some other serum account
+        let data = open_orders_data(0b11, &market, &owner);
+        assert_eq!(get_open_orders_status(&data, &market, &owner), OpenOrdersStatus::Mismatched);
+        assert_eq!(get_open_orders_status(&[1; 165], &market, &owner), OpenOrdersStatus::Mismatched);
+    }
 
     #[test]
     fn test_calculate_new_price() {
@@ -611,4 +698,17 @@ mod tests {
         );
         assert_eq!(result, Ok(()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_swap_refuses_a_fake_raydium_or_serum_program() {
+        let mut fake_raydium = FakeAccounts::new(19)
+            .with_program(3, &Pubkey::new_unique())
+            .with_program(12, &serum_v3::id());
+        assert_invalid_program_account_code(swap(&fake_raydium.infos(), 1, 0, 0));
+
+        let mut fake_serum = FakeAccounts::new(19)
+            .with_program(3, &raydium_v4::id())
+            .with_program(12, &Pubkey::new_unique());
+        assert_invalid_program_account_code(swap(&fake_serum.infos(), 1, 0, 0));
+    }
+}
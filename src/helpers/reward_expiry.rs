@@ -0,0 +1,240 @@
+use std::convert::TryFrom;
+
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::DEFAULT_REWARD_EXPIRY_EPOCHS,
+    state::{
+        staking::{Reward, StakeBalance, StakingState},
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::RewardExpiry);
+
+// A reward can only be claimed for a set number of epochs after it drops. Whatever is left after that is
+// swept to the arb coffer so the rewards vault stops covering stakers who left long ago.
+
+/// System states initialized before the setting existed read it as zero, they get the default
+pub fn reward_expiry_epochs(system_state: &SystemState) -> u64 {
+    match system_state.reward_expiry_epochs {
+        0 => DEFAULT_REWARD_EXPIRY_EPOCHS,
+        reward_expiry_epochs => reward_expiry_epochs,
+    }
+}
+
+pub fn calc_reward_expiry(start_timestamp: i64, epoch: i64, expiry_epochs: u64) -> LucraResult<i64> {
+    let expiry_epochs = i64::try_from(expiry_epochs).map_err(|_| math_err!())?;
+
+    epoch
+        .checked_mul(expiry_epochs)
+        .and_then(|claim_window| start_timestamp.checked_add(claim_window))
+        .ok_or(math_err!())
+}
+
+/// Rewards dropped before expiries were recorded count from their drop with the current setting
+pub fn reward_expires_at(reward: &Reward, system_state: &SystemState) -> LucraResult<i64> {
+    if reward.expires_at != 0 {
+        return Ok(reward.expires_at);
+    }
+
+    calc_reward_expiry(reward.start_timestamp, system_state.epoch, reward_expiry_epochs(system_state))
+}
+
+pub fn is_reward_expired(reward: &Reward, system_state: &SystemState, timestamp: i64) -> LucraResult<bool> {
+    Ok(reward_expires_at(reward, system_state)? <= timestamp)
+}
+
+pub fn check_reward_not_expired(reward: &Reward, system_state: &SystemState, timestamp: i64) -> LucraResult {
+    check!(!is_reward_expired(reward, system_state, timestamp)?, LucraErrorCode::RewardClaimExpired)
+}
+
+pub fn check_reward_expired(reward: &Reward, system_state: &SystemState, timestamp: i64) -> LucraResult {
+    check!(is_reward_expired(reward, system_state, timestamp)?, LucraErrorCode::RewardNotExpired)
+}
+
+pub fn record_reward_claim(reward: &mut Reward, amount: u64) -> LucraResult {
+    reward.claimed = reward.claimed
+        .checked_add(amount)
+        .ok_or(math_err!())?;
+    invariant!(reward.claimed <= reward.total, LucraErrorCode::InvalidAmount)
+}
+
+/// Claims are only counted on rewards that recorded their expiry, older ones have nothing we know is safe to sweep.
+/// Claims through the reward index never touch the account, so what they're owed of it can't be told apart.
+pub fn calc_unclaimed_reward(reward: &Reward) -> u64 {
+    if reward.expires_at == 0 || reward.in_reward_index {
+        return 0;
+    }
+
+    reward.total.saturating_sub(reward.claimed)
+}
+
+/// Sweeps go in drop order so every reward under the swept cursor is known to be gone. The index the reward
+/// left stays on the staking state for balances that sat its drop out.
+pub fn record_reward_sweep(staking_state: &mut StakingState, reward: &Reward) -> LucraResult {
+    check!(reward.reward_cursor == staking_state.swept_reward_cursor, LucraErrorCode::ClaimOutOfOrder)?;
+    staking_state.swept_reward_cursor = staking_state.swept_reward_cursor
+        .checked_add(1)
+        .ok_or(math_err!())?;
+    staking_state.swept_reward_index = staking_state.swept_reward_index.max(reward.reward_index);
+
+    Ok(())
+}
+
+/// A stake balance pointing at a swept reward could never claim it, it moves on to the first reward left
+pub fn skip_swept_rewards(stake_balance: &mut StakeBalance, staking_state: &StakingState) {
+    if stake_balance.reward_cursor < staking_state.swept_reward_cursor {
+        stake_balance.reward_cursor = staking_state.swept_reward_cursor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DROP: i64 = 1_650_000_000;
+    const EPOCH: i64 = 604_800;
+
+    fn system_state() -> SystemState {
+        let mut system_state = SystemState::default();
+        system_state.epoch = EPOCH;
+        system_state.reward_expiry_epochs = 26;
+        system_state
+    }
+
+    fn reward(total: u64) -> Reward {
+        let mut reward = Reward::default();
+        reward.total = total;
+        reward.start_timestamp = DROP;
+        reward.expires_at = calc_reward_expiry(DROP, EPOCH, 26).unwrap();
+        reward
+    }
+
+    #[test]
+    fn test_claim_window_closes_at_expiry() {
+        let system_state = system_state();
+        let reward = reward(1_000);
+        let expires_at = DROP + 26 * EPOCH;
+        assert_eq!(reward.expires_at, expires_at);
+
+        assert!(check_reward_not_expired(&reward, &system_state, expires_at - 1).is_ok());
+        assert!(check_reward_expired(&reward, &system_state, expires_at - 1).is_err());
+
+        assert!(matches!(
+            check_reward_not_expired(&reward, &system_state, expires_at).unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::RewardClaimExpired,
+                ..
+            }
+        ));
+        assert!(check_reward_expired(&reward, &system_state, expires_at).is_ok());
+    }
+
+    #[test]
+    fn test_recorded_expiry_ignores_later_setting_changes() {
+        let mut system_state = system_state();
+        let reward = reward(1_000);
+        system_state.reward_expiry_epochs = 1;
+
+        assert!(!is_reward_expired(&reward, &system_state, DROP + EPOCH).unwrap());
+    }
+
+    #[test]
+    fn test_legacy_reward_expires_with_current_setting_but_sweeps_nothing() {
+        let mut system_state = system_state();
+        let mut reward = reward(1_000);
+        reward.expires_at = 0;
+        system_state.reward_expiry_epochs = 2;
+
+        assert!(!is_reward_expired(&reward, &system_state, DROP + 2 * EPOCH - 1).unwrap());
+        assert!(is_reward_expired(&reward, &system_state, DROP + 2 * EPOCH).unwrap());
+        assert_eq!(calc_unclaimed_reward(&reward), 0);
+    }
+
+    #[test]
+    fn test_unset_expiry_uses_the_default() {
+        let mut system_state = system_state();
+        system_state.reward_expiry_epochs = 0;
+        let mut reward = reward(1_000);
+        reward.expires_at = 0;
+
+        assert_eq!(reward_expiry_epochs(&system_state), DEFAULT_REWARD_EXPIRY_EPOCHS);
+        // A zero would have every older reward expire the moment it dropped
+        let expires_at = DROP + DEFAULT_REWARD_EXPIRY_EPOCHS as i64 * EPOCH;
+        assert_eq!(reward_expires_at(&reward, &system_state).unwrap(), expires_at);
+        assert!(!is_reward_expired(&reward, &system_state, expires_at - 1).unwrap());
+    }
+
+    #[test]
+    fn test_claims_are_counted_against_the_drop() {
+        let mut reward = reward(1_000);
+
+        record_reward_claim(&mut reward, 600).unwrap();
+        assert_eq!(calc_unclaimed_reward(&reward), 400);
+        record_reward_claim(&mut reward, 400).unwrap();
+        assert_eq!(calc_unclaimed_reward(&reward), 0);
+
+        assert!(record_reward_claim(&mut reward, 1).is_err());
+    }
+
+    #[test]
+    fn test_indexed_reward_sweeps_nothing() {
+        let mut reward = reward(1_000);
+        reward.in_reward_index = true;
+
+        record_reward_claim(&mut reward, 600).unwrap();
+        assert_eq!(calc_unclaimed_reward(&reward), 0);
+    }
+
+    #[test]
+    fn test_sweeps_go_in_drop_order() {
+        let mut staking_state = StakingState::default();
+        let mut first = reward(1_000);
+        first.reward_cursor = 0;
+        let mut second = reward(1_000);
+        second.reward_cursor = 1;
+
+        assert!(record_reward_sweep(&mut staking_state, &second).is_err());
+        record_reward_sweep(&mut staking_state, &first).unwrap();
+        assert!(record_reward_sweep(&mut staking_state, &first).is_err());
+        record_reward_sweep(&mut staking_state, &second).unwrap();
+        assert_eq!(staking_state.swept_reward_cursor, 2);
+    }
+
+    #[test]
+    fn test_sweep_keeps_the_index_the_reward_left() {
+        let mut staking_state = StakingState::default();
+        let mut first = reward(1_000);
+        first.reward_index = 5;
+        // Rewards from before the index recorded none
+        let mut second = reward(1_000);
+        second.reward_cursor = 1;
+
+        record_reward_sweep(&mut staking_state, &first).unwrap();
+        assert_eq!(staking_state.swept_reward_index, 5);
+        record_reward_sweep(&mut staking_state, &second).unwrap();
+        assert_eq!(staking_state.swept_reward_index, 5);
+    }
+
+    #[test]
+    fn test_stake_balance_skips_swept_rewards() {
+        let mut staking_state = StakingState::default();
+        staking_state.swept_reward_cursor = 3;
+
+        let mut behind = StakeBalance::default();
+        behind.reward_cursor = 1;
+        skip_swept_rewards(&mut behind, &staking_state);
+        assert_eq!(behind.reward_cursor, 3);
+
+        let mut ahead = StakeBalance::default();
+        ahead.reward_cursor = 5;
+        skip_swept_rewards(&mut ahead, &staking_state);
+        assert_eq!(ahead.reward_cursor, 5);
+    }
+}
@@ -0,0 +1,544 @@
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::reward_expiry::skip_swept_rewards,
+    state::staking::{Reward, StakeBalance, StakingState},
+};
+
+declare_check_assert_macros!(SourceFileId::RewardIndex);
+
+// Every drop adds its reward per staked lucra token to an index in the staking state. A stake balance on the
+// index remembers where the index stood when it last claimed, so one claim pays every drop since then in a
+// single transfer and there's no reward account to claim in order. Stake changes still need the balance
+// claimed up to date first, so the staked amount a claim is sized on is the one it held through those drops.
+//
+// That amount is the balance's indexed_stake, the staked lucra minted for it and not burned since. The user's
+// staked lucra account can't stand in for it, the tokens move freely and one account can sit behind any number
+// of balances.
+//
+// Stake and CancelUnstake move the balance's cursor past the next drop, so stake can't be put in just before a
+// drop and taken straight out after it. The index the balance last claimed at is from before that drop, the
+// claim after it starts from the index the drop left, which its reward records.
+
+/// The index counts reward per staked lucra token in units of 1 / REWARD_INDEX_SCALE lamports
+pub const REWARD_INDEX_SCALE: u128 = 1_000_000_000_000;
+
+/// Reward per staked lucra token a drop of `total` adds to the index. Nobody can claim a drop made with
+/// nothing staked, it adds nothing.
+pub fn calc_reward_index_increment(total: u64, staked_supply: u64) -> LucraResult<u128> {
+    if staked_supply == 0 {
+        return Ok(0);
+    }
+
+    u128::from(total)
+        .checked_mul(REWARD_INDEX_SCALE)
+        .ok_or(math_err!())?
+        .checked_div(u128::from(staked_supply))
+        .ok_or(math_err!())
+}
+
+/// Records a drop in the index. Everything it pays stays owed to stakers until it's claimed, whichever way, so
+/// the rewards vault can't give it up even once the reward account is swept.
+pub fn record_reward_index(staking_state: &mut StakingState, total: u64, staked_supply: u64) -> LucraResult {
+    staking_state.reward_index = staking_state.reward_index
+        .checked_add(calc_reward_index_increment(total, staked_supply)?)
+        .ok_or(math_err!())?;
+    if staked_supply > 0 {
+        staking_state.reward_index_liability = staking_state.reward_index_liability
+            .checked_add(total)
+            .ok_or(math_err!())?;
+    }
+
+    Ok(())
+}
+
+/// A claim paid out of a drop in the index. Drops from before the liability was kept aren't in it, so claims on
+/// them stop at zero.
+pub fn release_index_liability(staking_state: &mut StakingState, claimed: u64) {
+    staking_state.reward_index_liability = staking_state.reward_index_liability.saturating_sub(claimed);
+}
+
+/// Reward owed to `staked_amount` for the index moving from `last_claimed_index` to `reward_index`, rounded
+/// down so the claims on a drop never add up to more than it put in the vault
+pub fn calc_indexed_reward(reward_index: u128, last_claimed_index: u128, staked_amount: u64) -> LucraResult<u64> {
+    let index_delta = reward_index
+        .checked_sub(last_claimed_index)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    let reward = index_delta
+        .checked_mul(u128::from(staked_amount))
+        .ok_or(math_err!())?
+        .checked_div(REWARD_INDEX_SCALE)
+        .ok_or(math_err!())?;
+
+    u64::try_from(reward).map_err(|_| math_err!())
+}
+
+/// Stake balances created once the index exists start on it without anything to claim
+pub fn start_reward_index(stake_balance: &mut StakeBalance, staking_state: &StakingState) {
+    stake_balance.on_reward_index = true;
+    stake_balance.last_claimed_index = staking_state.reward_index;
+    stake_balance.reward_index_cursor = staking_state.reward_cursor;
+    stake_balance.indexed_stake = 0;
+}
+
+/// Moves a stake balance from claiming reward accounts one by one to the index. Rewards dropped before the
+/// move aren't in the index, so every one the balance could still claim has to be claimed first. The balance
+/// never recorded what was minted for it, what its stake vault holds at its weight is what it has staked.
+pub fn migrate_to_reward_index(
+    stake_balance: &mut StakeBalance,
+    staking_state: &StakingState,
+    stake_vault_lucra: u64,
+) -> LucraResult {
+    check!(!stake_balance.on_reward_index, LucraErrorCode::AlreadyProcessed)?;
+    skip_swept_rewards(stake_balance, staking_state);
+    check!(stake_balance.reward_cursor == staking_state.reward_cursor, LucraErrorCode::RewardsOutstanding)?;
+
+    start_reward_index(stake_balance, staking_state);
+    stake_balance.indexed_stake = stake_vault_lucra
+        .checked_mul(stake_balance.staking_timeframe.weight())
+        .ok_or(math_err!())?;
+
+    Ok(())
+}
+
+/// Staked lucra was minted for the balance. Balances still on the cursor are claimed off their reward accounts
+/// and don't keep count.
+pub fn add_indexed_stake(stake_balance: &mut StakeBalance, staked_lucra: u64) -> LucraResult {
+    if !stake_balance.on_reward_index {
+        return Ok(());
+    }
+
+    stake_balance.indexed_stake = stake_balance.indexed_stake
+        .checked_add(staked_lucra)
+        .ok_or(math_err!())?;
+
+    Ok(())
+}
+
+/// Staked lucra was burned for the balance
+pub fn remove_indexed_stake(stake_balance: &mut StakeBalance, staked_lucra: u64) -> LucraResult {
+    if !stake_balance.on_reward_index {
+        return Ok(());
+    }
+
+    stake_balance.indexed_stake = stake_balance.indexed_stake
+        .checked_sub(staked_lucra)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+
+    Ok(())
+}
+
+/// Whether the balance sat out a drop it staked into and the claim needs that drop's reward to skip it. Once the
+/// reward is swept the staking state has the index from after it.
+pub fn needs_skipped_reward(stake_balance: &StakeBalance, staking_state: &StakingState) -> bool {
+    stake_balance.reward_index_cursor < stake_balance.reward_cursor
+        && stake_balance.reward_cursor > staking_state.swept_reward_cursor
+}
+
+/// Moves the index a balance claims from past the drops it sat out. `skipped_reward` is the reward of the last
+/// of them, the drop under the balance's cursor.
+fn skip_staked_into_drops(stake_balance: &mut StakeBalance, staking_state: &StakingState, skipped_reward: Option<&Reward>) -> LucraResult {
+    if stake_balance.reward_index_cursor >= stake_balance.reward_cursor {
+        return Ok(());
+    }
+
+    let reward_index = if needs_skipped_reward(stake_balance, staking_state) {
+        let skipped_reward = skipped_reward.ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+        check_eq!(skipped_reward.reward_cursor + 1, stake_balance.reward_cursor, LucraErrorCode::InvalidAccountInput)?;
+        skipped_reward.reward_index
+    } else {
+        // Swept in drop order, so the index after the last swept drop is at or past the one skipped
+        staking_state.swept_reward_index
+    };
+
+    stake_balance.last_claimed_index = stake_balance.last_claimed_index.max(reward_index);
+    stake_balance.reward_index_cursor = stake_balance.reward_cursor;
+
+    Ok(())
+}
+
+/// What a claim on the index pays out
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IndexedClaim {
+    pub reward: u64,
+    /// Drops since the last claim, inflation is paid for each of them
+    pub drops: u64,
+}
+
+/// Settles a stake balance against the index and moves its cursor up to the last drop. `skipped_reward` is only
+/// read when `needs_skipped_reward`.
+pub fn claim_indexed_reward(
+    stake_balance: &mut StakeBalance,
+    staking_state: &StakingState,
+    skipped_reward: Option<&Reward>,
+) -> LucraResult<IndexedClaim> {
+    check!(stake_balance.on_reward_index, LucraErrorCode::InvalidAccountInput)?;
+    // Skipped drops take no cursor, so a claim with no drop since the last one has nothing to pay
+    check!(stake_balance.reward_cursor < staking_state.reward_cursor, LucraErrorCode::AlreadyProcessed)?;
+    skip_staked_into_drops(stake_balance, staking_state, skipped_reward)?;
+
+    let reward = calc_indexed_reward(staking_state.reward_index, stake_balance.last_claimed_index, stake_balance.indexed_stake)?;
+    let drops = staking_state.reward_cursor - stake_balance.reward_cursor;
+
+    stake_balance.last_claimed_index = staking_state.reward_index;
+    stake_balance.reward_cursor = staking_state.reward_cursor;
+    stake_balance.reward_index_cursor = staking_state.reward_cursor;
+
+    Ok(IndexedClaim { reward, drops })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_error<T: std::fmt::Debug>(result: LucraResult<T>, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    // Stands in for the staked lucra mint and the stake balances around a series of drops
+    struct Pool {
+        staking_state: StakingState,
+        stakers: Vec<StakeBalance>,
+        rewards: Vec<Reward>,
+        paid: u64,
+        dropped: u64,
+    }
+
+    impl Pool {
+        fn new() -> Self {
+            Pool { staking_state: StakingState::default(), stakers: vec![], rewards: vec![], paid: 0, dropped: 0 }
+        }
+
+        fn supply(&self) -> u64 {
+            self.stakers.iter().map(|stake_balance| stake_balance.indexed_stake).sum()
+        }
+
+        fn join(&mut self, staked: u64) -> usize {
+            let mut stake_balance = StakeBalance::default();
+            stake_balance.reward_cursor = self.staking_state.reward_cursor;
+            start_reward_index(&mut stake_balance, &self.staking_state);
+            add_indexed_stake(&mut stake_balance, staked).unwrap();
+            self.stakers.push(stake_balance);
+            self.stakers.len() - 1
+        }
+
+        fn drop_reward(&mut self, total: u64) {
+            record_reward_index(&mut self.staking_state, total, self.supply()).unwrap();
+            let mut reward = Reward::default();
+            reward.reward_cursor = self.staking_state.reward_cursor;
+            reward.reward_index = self.staking_state.reward_index;
+            self.rewards.push(reward);
+            self.staking_state.reward_cursor += 1;
+            self.dropped += total;
+        }
+
+        // Stake moves the cursor past the next drop, the balance sits it out
+        fn stake_more(&mut self, staker: usize, staked: u64) {
+            let stake_balance = &mut self.stakers[staker];
+            assert_eq!(stake_balance.reward_cursor, self.staking_state.reward_cursor);
+            add_indexed_stake(stake_balance, staked).unwrap();
+            stake_balance.increment_reward_cursor(self.staking_state.reward_cursor);
+        }
+
+        // The reward a claim passes, the one under the balance's cursor
+        fn claim_skipping(&mut self, staker: usize) -> LucraResult<IndexedClaim> {
+            let skipped_reward = self.stakers[staker].reward_cursor
+                .checked_sub(1)
+                .and_then(|cursor| self.rewards.get(cursor as usize));
+            let claim = claim_indexed_reward(&mut self.stakers[staker], &self.staking_state, skipped_reward)?;
+            self.paid += claim.reward;
+            Ok(claim)
+        }
+
+        fn claim(&mut self, staker: usize) -> IndexedClaim {
+            let claim = claim_indexed_reward(&mut self.stakers[staker], &self.staking_state, None).unwrap();
+            self.paid += claim.reward;
+            claim
+        }
+
+        // Stake changes need the balance claimed up to date, like Stake and StartUnstake check
+        fn change_stake(&mut self, staker: usize, staked: u64) {
+            let stake_balance = &mut self.stakers[staker];
+            assert_eq!(stake_balance.reward_cursor, self.staking_state.reward_cursor);
+            if staked > stake_balance.indexed_stake {
+                add_indexed_stake(stake_balance, staked - stake_balance.indexed_stake).unwrap();
+            } else {
+                remove_indexed_stake(stake_balance, stake_balance.indexed_stake - staked).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_liability_follows_drops_and_claims() {
+        let mut pool = Pool::new();
+        let alice = pool.join(1_000);
+        let bob = pool.join(3_000);
+        pool.drop_reward(1_000);
+        pool.drop_reward(2_001);
+        assert_eq!(pool.staking_state.reward_index_liability, 3_001);
+
+        let claim = pool.claim(alice).reward;
+        release_index_liability(&mut pool.staking_state, claim);
+        let claim = pool.claim(bob).reward;
+        release_index_liability(&mut pool.staking_state, claim);
+        // Claims round down, what's left over stays owed
+        assert_eq!(pool.staking_state.reward_index_liability, pool.dropped - pool.paid);
+
+        // Nothing staked, nothing owed
+        record_reward_index(&mut pool.staking_state, 1_000, 0).unwrap();
+        assert_eq!(pool.staking_state.reward_index_liability, pool.dropped - pool.paid);
+        release_index_liability(&mut pool.staking_state, u64::MAX);
+        assert_eq!(pool.staking_state.reward_index_liability, 0);
+    }
+
+    #[test]
+    fn test_index_increment() {
+        assert_eq!(calc_reward_index_increment(1_000, 4_000).unwrap(), REWARD_INDEX_SCALE / 4);
+        assert_eq!(calc_reward_index_increment(1_000, 0).unwrap(), 0);
+        assert_eq!(calc_reward_index_increment(0, 4_000).unwrap(), 0);
+
+        // Large drops on a tiny supply still fit
+        assert!(calc_reward_index_increment(u64::MAX, 1).is_ok());
+    }
+
+    #[test]
+    fn test_indexed_reward_rounds_down() {
+        let index = calc_reward_index_increment(1_000, 3).unwrap();
+
+        assert_eq!(calc_indexed_reward(index, 0, 1).unwrap(), 333);
+        assert_eq!(calc_indexed_reward(index, 0, 3).unwrap(), 999);
+        assert_eq!(calc_indexed_reward(index, index, 3).unwrap(), 0);
+        assert!(calc_indexed_reward(0, index, 3).is_err());
+    }
+
+    #[test]
+    fn test_one_claim_pays_every_drop_since_the_last() {
+        let mut pool = Pool::new();
+        let alice = pool.join(1_000);
+        let bob = pool.join(3_000);
+
+        pool.drop_reward(400);
+        pool.drop_reward(800);
+        pool.drop_reward(1_200);
+
+        // Bob claims late, after Alice has claimed twice, and gets the same share
+        assert_eq!(pool.claim(alice), IndexedClaim { reward: 300, drops: 2 });
+        pool.drop_reward(4_000);
+        assert_eq!(pool.claim(alice), IndexedClaim { reward: 1_000, drops: 1 });
+        assert_eq!(pool.claim(bob), IndexedClaim { reward: 4_800, drops: 3 + 1 });
+        assert_eq!(pool.paid, pool.dropped);
+    }
+
+    #[test]
+    fn test_stakers_joining_between_drops_only_share_later_ones() {
+        let mut pool = Pool::new();
+        let alice = pool.join(1_000);
+        pool.drop_reward(1_000);
+
+        let bob = pool.join(1_000);
+        pool.drop_reward(1_000);
+        let carol = pool.join(2_000);
+        pool.drop_reward(2_000);
+
+        assert_eq!(pool.claim(alice).reward, 1_000 + 500 + 500);
+        assert_eq!(pool.claim(bob).reward, 500 + 500);
+        assert_eq!(pool.claim(carol).reward, 1_000);
+        assert_eq!(pool.paid, pool.dropped);
+    }
+
+    #[test]
+    fn test_stakers_leaving_between_drops_keep_what_they_earned() {
+        let mut pool = Pool::new();
+        let alice = pool.join(2_000);
+        let bob = pool.join(2_000);
+        pool.drop_reward(1_000);
+
+        // Bob claims and unstakes half, then all of it
+        assert_eq!(pool.claim(bob).reward, 500);
+        pool.change_stake(bob, 1_000);
+        pool.drop_reward(900);
+        assert_eq!(pool.claim(bob).reward, 300);
+        pool.change_stake(bob, 0);
+        pool.drop_reward(600);
+
+        // Alice never touched her stake and claims everything at once
+        assert_eq!(pool.claim(alice), IndexedClaim { reward: 500 + 600 + 600, drops: 3 });
+        assert_eq!(pool.claim(bob).reward, 0);
+        assert_eq!(pool.paid, pool.dropped);
+    }
+
+    #[test]
+    fn test_claims_never_pay_more_than_was_dropped() {
+        let mut pool = Pool::new();
+        let stakers: Vec<usize> = [7, 11, 13].iter().map(|&staked| pool.join(staked)).collect();
+
+        for total in [1, 10, 99, 1_000_003] {
+            pool.drop_reward(total);
+            for &staker in &stakers {
+                pool.claim(staker);
+            }
+        }
+
+        assert!(pool.paid <= pool.dropped);
+        // Rounding leaves at most a lamport per staker per drop in the vault
+        assert!(pool.dropped - pool.paid <= 3 * 4);
+    }
+
+    #[test]
+    fn test_drop_with_nothing_staked_adds_nothing() {
+        let mut pool = Pool::new();
+        pool.drop_reward(1_000);
+
+        let alice = pool.join(1_000);
+        pool.drop_reward(500);
+        assert_eq!(pool.claim(alice).reward, 500);
+    }
+
+    #[test]
+    fn test_claim_without_a_new_drop_is_rejected() {
+        let mut pool = Pool::new();
+        let alice = pool.join(1_000);
+
+        assert_error(claim_indexed_reward(&mut pool.stakers[alice], &pool.staking_state, None), LucraErrorCode::AlreadyProcessed);
+
+        pool.drop_reward(1_000);
+        pool.claim(alice);
+        assert_error(claim_indexed_reward(&mut pool.stakers[alice], &pool.staking_state, None), LucraErrorCode::AlreadyProcessed);
+
+        // A skipped drop takes no cursor either
+        pool.staking_state.skipped_drops += 1;
+        assert_error(claim_indexed_reward(&mut pool.stakers[alice], &pool.staking_state, None), LucraErrorCode::AlreadyProcessed);
+    }
+
+    #[test]
+    fn test_balance_not_on_the_index_cannot_claim_from_it() {
+        let mut staking_state = StakingState::default();
+        staking_state.reward_cursor = 1;
+        staking_state.reward_index = REWARD_INDEX_SCALE;
+
+        let mut stake_balance = StakeBalance::default();
+        stake_balance.indexed_stake = 1_000;
+        assert_error(claim_indexed_reward(&mut stake_balance, &staking_state, None), LucraErrorCode::InvalidAccountInput);
+    }
+
+    #[test]
+    fn test_staking_into_a_drop_sits_it_out() {
+        let mut pool = Pool::new();
+        let alice = pool.join(1_000);
+        let bob = pool.join(1_000);
+        pool.drop_reward(2_000);
+        pool.claim(bob);
+
+        // Bob adds stake right before the next drop, which splits over 2_000 + 2_000 but pays him nothing
+        pool.stake_more(bob, 2_000);
+        pool.drop_reward(4_000);
+        assert_error(pool.claim_skipping(bob), LucraErrorCode::AlreadyProcessed);
+
+        pool.drop_reward(4_000);
+        assert_eq!(pool.claim_skipping(bob).unwrap(), IndexedClaim { reward: 3_000, drops: 1 });
+        assert_eq!(pool.claim(alice).reward, 1_000 + 1_000 + 1_000);
+        // Bob's share of the drop he sat out stays in the vault
+        assert_eq!(pool.dropped - pool.paid, 3_000);
+    }
+
+    #[test]
+    fn test_skipping_a_drop_needs_its_reward() {
+        let mut pool = Pool::new();
+        let alice = pool.join(1_000);
+        pool.stake_more(alice, 1_000);
+        pool.drop_reward(1_000);
+        pool.drop_reward(1_000);
+
+        assert!(needs_skipped_reward(&pool.stakers[alice], &pool.staking_state));
+        assert_error(claim_indexed_reward(&mut pool.stakers[alice], &pool.staking_state, None), LucraErrorCode::InvalidAccountInput);
+        let later_reward = pool.rewards[1];
+        assert_error(
+            claim_indexed_reward(&mut pool.stakers[alice], &pool.staking_state, Some(&later_reward)),
+            LucraErrorCode::InvalidAccountInput,
+        );
+
+        // Once the reward is swept the index from after the last swept drop stands in for it
+        pool.staking_state.swept_reward_cursor = 1;
+        pool.staking_state.swept_reward_index = pool.rewards[0].reward_index;
+        assert!(!needs_skipped_reward(&pool.stakers[alice], &pool.staking_state));
+        assert_eq!(pool.claim(alice).reward, 1_000);
+    }
+
+    #[test]
+    fn test_balances_sharing_a_staked_lucra_account_claim_their_own_stake() {
+        // One owner stakes 1_000 and 3_000 on two balances, both minting into the same staked lucra account
+        // that holds 4_000. Another staker holds the other 4_000.
+        let mut pool = Pool::new();
+        let first = pool.join(1_000);
+        let second = pool.join(3_000);
+        let other = pool.join(4_000);
+        pool.drop_reward(8_000);
+
+        assert_eq!(pool.claim(first).reward, 1_000);
+        assert_eq!(pool.claim(second).reward, 3_000);
+        assert_eq!(pool.claim(other).reward, 4_000);
+        assert_eq!(pool.paid, pool.dropped);
+
+        // Moving the whole account onto the other staker's wallet doesn't move the stake behind it either
+        pool.drop_reward(800);
+        assert_eq!(pool.claim(other).reward, 400);
+        assert_eq!(pool.claim(first).reward, 100);
+        assert_eq!(pool.claim(second).reward, 300);
+        assert_eq!(pool.paid, pool.dropped);
+    }
+
+    #[test]
+    fn test_only_balances_on_the_index_count_their_stake() {
+        let mut stake_balance = StakeBalance::default();
+        add_indexed_stake(&mut stake_balance, 1_000).unwrap();
+        remove_indexed_stake(&mut stake_balance, 1_000).unwrap();
+        assert_eq!(stake_balance.indexed_stake, 0);
+
+        start_reward_index(&mut stake_balance, &StakingState::default());
+        add_indexed_stake(&mut stake_balance, 1_000).unwrap();
+        remove_indexed_stake(&mut stake_balance, 400).unwrap();
+        assert_eq!(stake_balance.indexed_stake, 600);
+
+        // More can't be burned for the balance than was minted for it
+        assert!(remove_indexed_stake(&mut stake_balance, 601).is_err());
+    }
+
+    #[test]
+    fn test_migration_needs_every_open_reward_claimed() {
+        let mut staking_state = StakingState::default();
+        staking_state.reward_cursor = 5;
+        staking_state.swept_reward_cursor = 2;
+        staking_state.reward_index = 7 * REWARD_INDEX_SCALE;
+
+        let mut stake_balance = StakeBalance::default();
+        stake_balance.reward_cursor = 4;
+        assert_error(migrate_to_reward_index(&mut stake_balance, &staking_state, 2_000), LucraErrorCode::RewardsOutstanding);
+        assert!(!stake_balance.on_reward_index);
+
+        // Swept rewards can't be claimed anymore so they don't hold the migration up
+        staking_state.swept_reward_cursor = 5;
+        migrate_to_reward_index(&mut stake_balance, &staking_state, 2_000).unwrap();
+        assert!(stake_balance.on_reward_index);
+        assert_eq!(stake_balance.last_claimed_index, 7 * REWARD_INDEX_SCALE);
+        assert_eq!(stake_balance.reward_cursor, 5);
+        assert_eq!(stake_balance.indexed_stake, 2_000 * stake_balance.staking_timeframe.weight());
+
+        assert_error(migrate_to_reward_index(&mut stake_balance, &staking_state, 2_000), LucraErrorCode::AlreadyProcessed);
+
+        // Only drops after the migration are paid from the index
+        record_reward_index(&mut staking_state, 1_000, 4_000).unwrap();
+        staking_state.reward_cursor += 1;
+        assert_eq!(
+            claim_indexed_reward(&mut stake_balance, &staking_state, None).unwrap(),
+            IndexedClaim { reward: 500 * stake_balance.staking_timeframe.weight(), drops: 1 }
+        );
+    }
+}
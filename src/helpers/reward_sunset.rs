@@ -0,0 +1,197 @@
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::{MAX_REWARD_GRACE_BOOST, REWARD_REDEMPTION_NOTICE},
+    state::SystemState,
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::RewardSunset);
+
+// The DAO can end crank rewards so outstanding reward tokens stop being an open ended lucra liability.
+// Holders get at least the notice period to redeem, with a boost over the last stretch to get them out.
+// Once the deadline has passed the program can be closed and cranks stop being paid.
+
+pub fn is_reward_redemption_closed(system_state: &SystemState, timestamp: i64) -> bool {
+    system_state.reward_redemption_deadline != 0 && system_state.reward_redemption_deadline <= timestamp
+}
+
+pub fn check_reward_redemption_open(system_state: &SystemState, timestamp: i64) -> LucraResult {
+    check!(!is_reward_redemption_closed(system_state, timestamp), LucraErrorCode::RewardRedemptionClosed)
+}
+
+/// Boost on redemptions inside the grace period before the deadline, nothing without a deadline
+pub fn calc_grace_boost(system_state: &SystemState, timestamp: i64) -> Bps {
+    let deadline = system_state.reward_redemption_deadline;
+    if deadline == 0 || timestamp < deadline.saturating_sub(system_state.reward_grace_period) {
+        return Bps(0);
+    }
+
+    system_state.reward_grace_boost
+}
+
+pub fn apply_grace_boost(lamports: u64, boost: Bps) -> LucraResult<u64> {
+    let boosted = (lamports as u128)
+        .checked_mul(Bps::ONE_HUNDRED_PERCENT.0 as u128 + boost.0 as u128)
+        .ok_or(math_err!())?
+        .checked_div(Bps::ONE_HUNDRED_PERCENT.0 as u128)
+        .ok_or(math_err!())?;
+
+    Ok(boosted as u64)
+}
+
+/// A deadline of 0 cancels the sunset. Any other deadline has to leave holders the full notice from now,
+/// and a deadline that has passed is final.
+pub fn set_reward_redemption_deadline(
+    system_state: &mut SystemState,
+    deadline: i64,
+    grace_period: i64,
+    grace_boost: Bps,
+    timestamp: i64,
+) -> LucraResult {
+    check!(!system_state.reward_program_closed, LucraErrorCode::InvalidStateTransition)?;
+    check!(!is_reward_redemption_closed(system_state, timestamp), LucraErrorCode::InvalidStateTransition)?;
+
+    if deadline != 0 {
+        let earliest_deadline = timestamp
+            .checked_add(REWARD_REDEMPTION_NOTICE)
+            .ok_or(math_err!())?;
+        check!(earliest_deadline <= deadline, LucraErrorCode::Timelock)?;
+    }
+    check!((0..=REWARD_REDEMPTION_NOTICE).contains(&grace_period), LucraErrorCode::InvalidAmount)?;
+    check!(grace_boost <= MAX_REWARD_GRACE_BOOST, LucraErrorCode::InvalidAmount)?;
+
+    system_state.reward_redemption_deadline = deadline;
+    system_state.reward_grace_period = grace_period;
+    system_state.reward_grace_boost = grace_boost;
+
+    Ok(())
+}
+
+/// Closing is permanent, there is no instruction that reopens the program
+pub fn close_reward_program(system_state: &mut SystemState, timestamp: i64) -> LucraResult {
+    check!(!system_state.reward_program_closed, LucraErrorCode::InvalidStateTransition)?;
+    check!(system_state.reward_redemption_deadline != 0, LucraErrorCode::InvalidStateTransition)?;
+    check!(is_reward_redemption_closed(system_state, timestamp), LucraErrorCode::Timelock)?;
+
+    system_state.reward_program_closed = true;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::constants::UNIX_DAY;
+
+    const NOW: i64 = 1_650_000_000;
+    const DEADLINE: i64 = NOW + REWARD_REDEMPTION_NOTICE;
+    const GRACE_PERIOD: i64 = 7 * UNIX_DAY;
+
+    fn sunset_system_state() -> SystemState {
+        let mut system_state = SystemState::default();
+        set_reward_redemption_deadline(&mut system_state, DEADLINE, GRACE_PERIOD, Bps(500), NOW).unwrap();
+        system_state
+    }
+
+    fn assert_error(result: LucraResult, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_no_deadline_never_closes() {
+        let system_state = SystemState::default();
+
+        assert!(check_reward_redemption_open(&system_state, i64::MAX).is_ok());
+        assert_eq!(calc_grace_boost(&system_state, i64::MAX), Bps(0));
+    }
+
+    #[test]
+    fn test_deadline_needs_the_full_notice() {
+        let mut system_state = SystemState::default();
+
+        assert_error(
+            set_reward_redemption_deadline(&mut system_state, DEADLINE - 1, 0, Bps(0), NOW),
+            LucraErrorCode::Timelock,
+        );
+        assert_error(
+            set_reward_redemption_deadline(&mut system_state, DEADLINE, REWARD_REDEMPTION_NOTICE + 1, Bps(0), NOW),
+            LucraErrorCode::InvalidAmount,
+        );
+        assert_error(
+            set_reward_redemption_deadline(&mut system_state, DEADLINE, 0, Bps(MAX_REWARD_GRACE_BOOST.0 + 1), NOW),
+            LucraErrorCode::InvalidAmount,
+        );
+        assert!(set_reward_redemption_deadline(&mut system_state, DEADLINE, 0, MAX_REWARD_GRACE_BOOST, NOW).is_ok());
+
+        // Pulling it in later still has to leave the full notice from then
+        assert_error(
+            set_reward_redemption_deadline(&mut system_state, DEADLINE, 0, Bps(0), NOW + 1),
+            LucraErrorCode::Timelock,
+        );
+    }
+
+    #[test]
+    fn test_redemption_across_the_timeline() {
+        let system_state = sunset_system_state();
+        let grace_starts = DEADLINE - GRACE_PERIOD;
+
+        // Normal rate until the grace period
+        assert!(check_reward_redemption_open(&system_state, NOW).is_ok());
+        assert_eq!(calc_grace_boost(&system_state, grace_starts - 1), Bps(0));
+
+        // Boosted rate for the last stretch
+        assert_eq!(calc_grace_boost(&system_state, grace_starts), Bps(500));
+        assert_eq!(calc_grace_boost(&system_state, DEADLINE - 1), Bps(500));
+        assert!(check_reward_redemption_open(&system_state, DEADLINE - 1).is_ok());
+
+        // Nothing once the deadline is here
+        assert_error(check_reward_redemption_open(&system_state, DEADLINE), LucraErrorCode::RewardRedemptionClosed);
+    }
+
+    #[test]
+    fn test_grace_boost_rounds_down() {
+        assert_eq!(apply_grace_boost(5_500, Bps(0)).unwrap(), 5_500);
+        assert_eq!(apply_grace_boost(5_500, Bps(500)).unwrap(), 5_775);
+        assert_eq!(apply_grace_boost(5_501, Bps(500)).unwrap(), 5_776);
+    }
+
+    #[test]
+    fn test_sunset_can_be_cancelled_before_the_deadline_only() {
+        let mut system_state = sunset_system_state();
+        set_reward_redemption_deadline(&mut system_state, 0, 0, Bps(0), DEADLINE - 1).unwrap();
+        assert!(check_reward_redemption_open(&system_state, DEADLINE).is_ok());
+
+        let mut system_state = sunset_system_state();
+        assert_error(
+            set_reward_redemption_deadline(&mut system_state, 0, 0, Bps(0), DEADLINE),
+            LucraErrorCode::InvalidStateTransition,
+        );
+    }
+
+    #[test]
+    fn test_close_waits_for_the_deadline_and_is_final() {
+        let mut no_deadline = SystemState::default();
+        assert_error(close_reward_program(&mut no_deadline, NOW), LucraErrorCode::InvalidStateTransition);
+
+        let mut system_state = sunset_system_state();
+        assert_error(close_reward_program(&mut system_state, DEADLINE - 1), LucraErrorCode::Timelock);
+
+        close_reward_program(&mut system_state, DEADLINE).unwrap();
+        assert!(system_state.reward_program_closed);
+
+        assert_error(close_reward_program(&mut system_state, DEADLINE), LucraErrorCode::InvalidStateTransition);
+        assert_error(
+            set_reward_redemption_deadline(&mut system_state, 0, 0, Bps(0), DEADLINE),
+            LucraErrorCode::InvalidStateTransition,
+        );
+    }
+}
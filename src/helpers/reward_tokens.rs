@@ -0,0 +1,221 @@
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        constants::MAX_REWARD_TOKENS_PER_MINT,
+        vaults::verify_reward_mint,
+    },
+    lucra_core::pda::reward_mint_authority_seeds,
+    state::SystemState,
+    units::Bps,
+};
+
+pub use crate::lucra_core::quote::{
+    calc_keeper_reward_base_units,
+    calc_partial_reward_base_units,
+    calc_reward_base_units,
+    calc_reward_lamports,
+    calc_reward_lucra,
+    calc_reward_msol,
+    verify_reward_decimals,
+};
+
+declare_check_assert_macros!(SourceFileId::RewardTokens);
+
+// Cranks are paid a whole number of reward tokens and each whole token redeems for the reward fee.
+// The reward mint is created outside the program, so every amount goes through the decimals stored at Initialize.
+
+/// Reward mint authority derived from the bump stored at Initialize
+pub fn create_reward_mint_authority(program_id: &Pubkey, system_state: &SystemState) -> LucraResult<Pubkey> {
+    Pubkey::create_program_address(
+        &reward_mint_authority_seeds(&system_state.key, &[system_state.reward_mint.authority_bump_seed]),
+        program_id,
+    )
+    .map_err(|_| throw_err!(LucraErrorCode::InvalidNonce))
+}
+
+/// Checks shared by every reward mint. Anything that mints reward tokens goes through `mint_partial_reward`
+/// so a processor can't pass a lookalike mint or a different authority.
+pub fn verify_reward_mint_accounts(
+    program_id: &Pubkey,
+    system_state: &SystemState,
+    reward_mint: &Pubkey,
+    reward_mint_authority: &Pubkey,
+) -> LucraResult {
+    verify_reward_mint(system_state, reward_mint)?;
+    check_eq!(
+        reward_mint_authority,
+        &create_reward_mint_authority(program_id, system_state)?,
+        LucraErrorCode::InvalidAccountInput
+    )
+}
+
+pub fn verify_reward_mint_amount(whole_tokens: u64) -> LucraResult {
+    invariant!(whole_tokens > 0, LucraErrorCode::InvalidAmount)?;
+    invariant!(whole_tokens <= MAX_REWARD_TOKENS_PER_MINT, LucraErrorCode::InvalidAmount)
+}
+
+/// `keeper_multiplier` is what `resolve_crank_caller` returned for whoever ran the crank
+#[allow(clippy::too_many_arguments)]
+pub fn mint_whole_reward<'a>(
+    program_id: &Pubkey,
+    system_state: &SystemState,
+    reward_mint_ai: &AccountInfo<'a>,
+    user_reward_account_ai: &AccountInfo<'a>,
+    whole_tokens: u64,
+    keeper_multiplier: Bps,
+    reward_mint_authority_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+) -> LucraResult {
+    mint_partial_reward(
+        program_id,
+        system_state,
+        reward_mint_ai,
+        user_reward_account_ai,
+        whole_tokens,
+        1,
+        1,
+        keeper_multiplier,
+        reward_mint_authority_ai,
+        token_program_ai,
+    )
+}
+
+/// Pays `share` out of `of` parts of `whole_tokens`, for a crank whose work is only worth part of its reward.
+/// A share that rounds down to nothing mints nothing.
+#[allow(clippy::too_many_arguments)]
+pub fn mint_partial_reward<'a>(
+    program_id: &Pubkey,
+    system_state: &SystemState,
+    reward_mint_ai: &AccountInfo<'a>,
+    user_reward_account_ai: &AccountInfo<'a>,
+    whole_tokens: u64,
+    share: u64,
+    of: u64,
+    keeper_multiplier: Bps,
+    reward_mint_authority_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+) -> LucraResult {
+    verify_reward_mint_accounts(program_id, system_state, reward_mint_ai.key, reward_mint_authority_ai.key)?;
+    verify_reward_mint_amount(whole_tokens)?;
+    // Cranks keep running unpaid once the DAO has closed the reward program
+    if system_state.reward_program_closed {
+        return Ok(());
+    }
+    let amount = calc_keeper_reward_base_units(
+        calc_partial_reward_base_units(whole_tokens, system_state.reward_mint_decimals, share, of)?,
+        keeper_multiplier,
+    )?;
+    if amount == 0 {
+        return Ok(());
+    }
+
+    system_state.mint_reward(
+        program_id,
+        reward_mint_ai,
+        user_reward_account_ai,
+        amount,
+        reward_mint_authority_ai,
+        token_program_ai,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::vaults::tests::{assert_invalid_account_input, system_state_with_vaults};
+    use crate::units::RewardFee;
+
+    fn reward_system_state() -> SystemState {
+        let mut system_state = system_state_with_vaults();
+        system_state.key = Pubkey::new_unique();
+        let (_, bump_seed) = SystemState::find_reward_mint_authority(&system_state.key);
+        system_state.reward_mint.authority_bump_seed = bump_seed;
+        system_state
+    }
+
+    #[test]
+    fn test_one_reward_is_one_whole_token() {
+        assert_eq!(calc_reward_base_units(1, 0).unwrap(), 1);
+        assert_eq!(calc_reward_base_units(1, 6).unwrap(), 1_000_000);
+        assert_eq!(calc_reward_base_units(1, 9).unwrap(), 1_000_000_000);
+        assert!(calc_reward_base_units(1, 10).is_err());
+    }
+
+    #[test]
+    fn test_redeemed_value_does_not_depend_on_decimals() {
+        let reward_fee = RewardFee(5_500);
+
+        for decimals in [0, 6, 9] {
+            let paid = calc_reward_base_units(3, decimals).unwrap();
+
+            assert_eq!(calc_reward_lamports(reward_fee, paid, decimals).unwrap(), 16_500);
+        }
+    }
+
+    #[test]
+    fn test_fractional_rewards_round_down() {
+        let reward_fee = RewardFee(5_500);
+
+        // Half a token at 6 decimals
+        assert_eq!(calc_reward_lamports(reward_fee, 500_000, 6).unwrap(), 2_750);
+        // The smallest unit at 9 decimals is worth less than a lamport
+        assert_eq!(calc_reward_lamports(reward_fee, 1, 9).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_partial_rewards_round_down() {
+        assert_eq!(calc_partial_reward_base_units(1, 6, 3, 12).unwrap(), 250_000);
+        assert_eq!(calc_partial_reward_base_units(2, 9, 1, 3).unwrap(), 666_666_666);
+        // Never more than the whole reward
+        assert_eq!(calc_partial_reward_base_units(1, 6, 13, 12).unwrap(), 1_000_000);
+        assert_eq!(calc_partial_reward_base_units(1, 6, 0, 12).unwrap(), 0);
+        assert!(calc_partial_reward_base_units(1, 6, 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_keeper_multiplier_scales_the_reward() {
+        // Anonymous callers are paid the reward as it is
+        assert_eq!(calc_keeper_reward_base_units(1_000_000, Bps::ONE_HUNDRED_PERCENT).unwrap(), 1_000_000);
+        assert_eq!(calc_keeper_reward_base_units(1_000_000, Bps(15_000)).unwrap(), 1_500_000);
+        assert_eq!(calc_keeper_reward_base_units(1_000_000, Bps(2_500)).unwrap(), 250_000);
+        assert_eq!(calc_keeper_reward_base_units(1_000_000, Bps(0)).unwrap(), 0);
+        // On top of a partial reward
+        let partial = calc_partial_reward_base_units(1, 6, 6, 12).unwrap();
+        assert_eq!(calc_keeper_reward_base_units(partial, Bps(20_000)).unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn test_reward_mint_accounts_are_checked() {
+        let program_id = crate::id();
+        let system_state = reward_system_state();
+        let reward_mint = system_state.reward_mint.address;
+        let authority = SystemState::find_reward_mint_authority(&system_state.key).0;
+
+        assert!(verify_reward_mint_accounts(&program_id, &system_state, &reward_mint, &authority).is_ok());
+        assert_invalid_account_input(verify_reward_mint_accounts(&program_id, &system_state, &Pubkey::new_unique(), &authority));
+        assert_invalid_account_input(verify_reward_mint_accounts(&program_id, &system_state, &reward_mint, &Pubkey::new_unique()));
+        // The mata mint authority is a valid PDA of the same system state, but not the reward one
+        let mata_mint_authority = SystemState::find_mata_mint_authority(&system_state.key).0;
+        assert_invalid_account_input(verify_reward_mint_accounts(&program_id, &system_state, &reward_mint, &mata_mint_authority));
+    }
+
+    #[test]
+    fn test_reward_mint_amount_is_bounded() {
+        assert!(verify_reward_mint_amount(1).is_ok());
+        assert!(verify_reward_mint_amount(MAX_REWARD_TOKENS_PER_MINT).is_ok());
+        assert!(verify_reward_mint_amount(0).is_err());
+        assert!(verify_reward_mint_amount(MAX_REWARD_TOKENS_PER_MINT + 1).is_err());
+    }
+}
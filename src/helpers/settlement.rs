@@ -0,0 +1,103 @@
+use solana_program::native_token::LAMPORTS_PER_SOL;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::SETTLEMENT_RECLAIM_DELAY,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::Settlement);
+
+// Rejects the instruction once the DAO has triggered global settlement
+pub fn check_not_settled(system_state: &SystemState) -> LucraResult {
+    check!(!system_state.settlement_active, LucraErrorCode::SettlementActive)
+}
+
+pub fn check_settled(system_state: &SystemState) -> LucraResult {
+    check!(system_state.settlement_active, LucraErrorCode::SettlementNotActive)
+}
+
+/// Mata a borrower has to burn to close their loan during settlement. Sol is valued at the frozen
+/// settlement price (6 decimals) so an underwater loan only repays what its collateral is worth.
+pub fn calc_settlement_mata_to_burn(loan_amount: u64, sol_to_return: u64, settlement_sol_price: u64) -> LucraResult<u64> {
+    let collateral_value = (sol_to_return as u128)
+        .checked_mul(settlement_sol_price as u128)
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_SOL as u128)
+        .ok_or(math_err!())?;
+
+    Ok(std::cmp::min(loan_amount as u128, collateral_value) as u64)
+}
+
+/// Leftover vault funds can only be reclaimed once everyone has had time to close out
+pub fn check_reclaim_deadline_passed(settlement_timestamp: i64, timestamp: i64) -> LucraResult {
+    let deadline = settlement_timestamp
+        .checked_add(SETTLEMENT_RECLAIM_DELAY)
+        .ok_or(math_err!())?;
+
+    check!(deadline <= timestamp, LucraErrorCode::Timelock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{MataBucket, MataSupply};
+
+    const SETTLEMENT_PRICE: u64 = 20_000_000; // 20 dollars
+
+    #[test]
+    fn test_healthy_loan_repays_in_full() {
+        // 10 sol at 20 dollars backing 100 mata
+        let actual = calc_settlement_mata_to_burn(100_000_000, 10 * LAMPORTS_PER_SOL, SETTLEMENT_PRICE).unwrap();
+
+        assert_eq!(actual, 100_000_000);
+    }
+
+    #[test]
+    fn test_underwater_loan_repays_collateral_value() {
+        // 2 sol at 20 dollars backing 100 mata
+        let actual = calc_settlement_mata_to_burn(100_000_000, 2 * LAMPORTS_PER_SOL, SETTLEMENT_PRICE).unwrap();
+
+        assert_eq!(actual, 40_000_000);
+    }
+
+    #[test]
+    fn test_reclaim_deadline() {
+        let settled_at = 1_650_000_000;
+
+        assert!(check_reclaim_deadline_passed(settled_at, settled_at).is_err());
+        assert!(check_reclaim_deadline_passed(settled_at, settled_at + SETTLEMENT_RECLAIM_DELAY - 1).is_err());
+        assert!(check_reclaim_deadline_passed(settled_at, settled_at + SETTLEMENT_RECLAIM_DELAY).is_ok());
+    }
+
+    #[test]
+    fn test_settlement_scenario() {
+        let settled_at = 1_650_000_000;
+        let mut supply = MataSupply::default();
+
+        // Two borrowers take out loans before settlement
+        let healthy_loan = (100_000_000, 10 * LAMPORTS_PER_SOL);
+        let underwater_loan = (50_000_000, LAMPORTS_PER_SOL);
+        supply.mint(MataBucket::Loan, healthy_loan.0).unwrap();
+        supply.mint(MataBucket::Loan, underwater_loan.0).unwrap();
+
+        // Both close out at the frozen price
+        let burned = calc_settlement_mata_to_burn(healthy_loan.0, healthy_loan.1, SETTLEMENT_PRICE).unwrap();
+        supply.burn(MataBucket::Loan, burned).unwrap();
+        let burned = calc_settlement_mata_to_burn(underwater_loan.0, underwater_loan.1, SETTLEMENT_PRICE).unwrap();
+        assert_eq!(burned, 20_000_000);
+        supply.burn(MataBucket::Loan, burned).unwrap();
+
+        // The unbacked part of the underwater loan is still outstanding
+        assert_eq!(supply.total().unwrap(), 30_000_000);
+
+        // The DAO can only sweep the vault after the deadline
+        assert!(check_reclaim_deadline_passed(settled_at, settled_at + 1).is_err());
+        assert!(check_reclaim_deadline_passed(settled_at, settled_at + SETTLEMENT_RECLAIM_DELAY).is_ok());
+    }
+}
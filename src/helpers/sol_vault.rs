@@ -0,0 +1,175 @@
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        solana::transfer,
+        vaults::verify_sol_vault,
+    },
+    state::SystemState,
+};
+
+pub use crate::lucra_core::pda::find_sol_vault;
+
+declare_check_assert_macros!(SourceFileId::SolVault);
+
+// Native SOL loans keep their collateral as lamports in the sol vault instead of depositing it with marinade.
+// The vault is a data-less PDA of the system state owned by the program, so lamports leave it without a CPI.
+// It isn't counted in total_sol_collateral, that's what the msol vault's surplus is measured against.
+
+/// Native loans need the sol vault created and the DAO to have turned them on
+pub fn check_native_loans_enabled(system_state: &SystemState) -> LucraResult {
+    check!(system_state.native_loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    check!(system_state.sol_vault.address != Pubkey::default(), LucraErrorCode::LoansNotEnabled)
+}
+
+pub fn deposit_to_sol_vault<'a>(
+    system_state: &SystemState,
+    user_ai: &AccountInfo<'a>,
+    sol_vault_ai: &AccountInfo<'a>,
+    lamports: u64,
+    system_program_ai: &AccountInfo<'a>,
+) -> LucraResult {
+    verify_sol_vault(system_state, sol_vault_ai.key)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    transfer(user_ai, sol_vault_ai, lamports, &[], system_program_ai)?;
+
+    Ok(())
+}
+
+/// The vault keeps the rent exempt minimum it was created with, only collateral on top of it ever leaves
+pub fn transfer_from_sol_vault(
+    program_id: &Pubkey,
+    system_state: &SystemState,
+    sol_vault_ai: &AccountInfo,
+    destination_ai: &AccountInfo,
+    lamports: u64,
+    rent: &Rent,
+) -> LucraResult {
+    verify_sol_vault(system_state, sol_vault_ai.key)?;
+    check_eq!(sol_vault_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check!(destination_ai.key != sol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let vault_lamports = sol_vault_ai.lamports()
+        .checked_sub(lamports)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    invariant!(vault_lamports >= rent.minimum_balance(0), LucraErrorCode::InvalidState)?;
+    let destination_lamports = destination_ai.lamports()
+        .checked_add(lamports)
+        .ok_or(math_err!())?;
+
+    **sol_vault_ai.try_borrow_mut_lamports()? = vault_lamports;
+    **destination_ai.try_borrow_mut_lamports()? = destination_lamports;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::native_token::LAMPORTS_PER_SOL;
+    use crate::{
+        lucra_core::penalty::calc_harvestable_native_penalty,
+        state::{LoanType, MataLoan},
+    };
+
+    fn sol_vault_state(sol_vault: &Pubkey) -> SystemState {
+        let mut system_state = SystemState::default();
+        system_state.sol_vault.address = *sol_vault;
+        system_state.native_loans_enabled = true;
+        system_state
+    }
+
+    #[test]
+    fn test_native_loans_need_a_vault() {
+        let mut system_state = SystemState::default();
+        system_state.native_loans_enabled = true;
+        assert!(check_native_loans_enabled(&system_state).is_err());
+        assert!(check_native_loans_enabled(&sol_vault_state(&Pubkey::new_unique())).is_ok());
+    }
+
+    #[test]
+    fn test_native_loans_are_off_until_the_dao_turns_them_on() {
+        let mut system_state = sol_vault_state(&Pubkey::new_unique());
+        system_state.native_loans_enabled = false;
+
+        assert!(matches!(
+            check_native_loans_enabled(&system_state).unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::LoansNotEnabled, .. }
+        ));
+    }
+
+    #[test]
+    fn test_native_loan_collateral_round_trip() {
+        let program_id = crate::id();
+        let rent = Rent::default();
+        let system_owner = solana_program::system_program::id();
+        let (vault_key, user_key, wsol_key) = (find_sol_vault(&Pubkey::new_unique()).0, Pubkey::new_unique(), Pubkey::new_unique());
+        let system_state = sol_vault_state(&vault_key);
+
+        // Create: the system program moves the collateral into the vault
+        let lamports = 10 * LAMPORTS_PER_SOL;
+        let mut vault_lamports = rent.minimum_balance(0) + lamports;
+        let mut user_lamports = 0;
+        let mut wsol_lamports = rent.minimum_balance(165);
+        let (mut vault_data, mut user_data, mut wsol_data) = ([], [], [0; 165]);
+        let vault_ai = AccountInfo::new(&vault_key, false, true, &mut vault_lamports, &mut vault_data, &program_id, false, 0);
+        let user_ai = AccountInfo::new(&user_key, true, true, &mut user_lamports, &mut user_data, &system_owner, false, 0);
+        let wsol_ai = AccountInfo::new(&wsol_key, false, true, &mut wsol_lamports, &mut wsol_data, &spl_token::id(), false, 0);
+        let mut loan = MataLoan {
+            loan_type: LoanType::NativeSol,
+            sol_collateral_amount: lamports,
+            penalty_to_harvest: 3 * LAMPORTS_PER_SOL / 2,
+            ..MataLoan::default()
+        };
+
+        // Harvest: the penalty is wrapped straight into the wsol account
+        loan.penalty_to_harvest = calc_harvestable_native_penalty(&loan);
+        transfer_from_sol_vault(&program_id, &system_state, &vault_ai, &wsol_ai, loan.penalty_to_harvest, &rent).unwrap();
+        loan.update_harvested_penalty();
+        assert_eq!(wsol_ai.lamports(), rent.minimum_balance(165) + 3 * LAMPORTS_PER_SOL / 2);
+
+        // Close: what's left of the collateral goes back to the user and the vault is back to its rent
+        transfer_from_sol_vault(&program_id, &system_state, &vault_ai, &user_ai, loan.calc_remaining_sol(), &rent).unwrap();
+        assert_eq!(user_ai.lamports(), lamports - 3 * LAMPORTS_PER_SOL / 2);
+        assert_eq!(vault_ai.lamports(), rent.minimum_balance(0));
+
+        // Nothing more can leave it
+        assert!(transfer_from_sol_vault(&program_id, &system_state, &vault_ai, &user_ai, 1, &rent).is_err());
+    }
+
+    #[test]
+    fn test_only_the_program_owned_vault_pays_out() {
+        let program_id = crate::id();
+        let rent = Rent::default();
+        let (vault_key, user_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let system_owner = solana_program::system_program::id();
+
+        let (mut vault_lamports, mut user_lamports) = (rent.minimum_balance(0) + LAMPORTS_PER_SOL, 0);
+        let (mut vault_data, mut user_data) = ([], []);
+        let vault_ai = AccountInfo::new(&vault_key, false, true, &mut vault_lamports, &mut vault_data, &program_id, false, 0);
+        let user_ai = AccountInfo::new(&user_key, true, true, &mut user_lamports, &mut user_data, &system_owner, false, 0);
+
+        // Not the recorded vault
+        assert!(transfer_from_sol_vault(&program_id, &sol_vault_state(&Pubkey::new_unique()), &vault_ai, &user_ai, 1, &rent).is_err());
+        // The recorded vault but owned by someone else
+        assert!(transfer_from_sol_vault(&system_owner, &sol_vault_state(&vault_key), &vault_ai, &user_ai, 1, &rent).is_err());
+        // Paying itself
+        assert!(transfer_from_sol_vault(&program_id, &sol_vault_state(&vault_key), &vault_ai, &vault_ai, 1, &rent).is_err());
+
+        // Never dips into the rent
+        assert!(transfer_from_sol_vault(&program_id, &sol_vault_state(&vault_key), &vault_ai, &user_ai, LAMPORTS_PER_SOL + 1, &rent).is_err());
+        transfer_from_sol_vault(&program_id, &sol_vault_state(&vault_key), &vault_ai, &user_ai, LAMPORTS_PER_SOL, &rent).unwrap();
+        assert_eq!(user_ai.lamports(), LAMPORTS_PER_SOL);
+    }
+}
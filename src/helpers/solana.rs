@@ -1,6 +1,9 @@
 use solana_program::{
     account_info::AccountInfo,
     entrypoint::ProgramResult,
+    program::invoke_signed,
+    pubkey::Pubkey,
+    system_instruction,
 };
 
 pub fn transfer<'a>(
@@ -21,4 +24,41 @@ pub fn transfer<'a>(
         system_program.clone(),
     ];
     solana_program::program::invoke_signed(transfer_instruction, &accs, authority_signer_seeds)
-}
\ No newline at end of file
+}
+
+/// Creates the PDA signed for by `signer_seeds` with `lamports` and `space`, owned by `owner`. Lamports sent to
+/// the address before it's created would make create_account fail, so a funded address is topped up,
+/// allocated and assigned instead and nobody can keep the account from being created by funding it first.
+#[allow(clippy::too_many_arguments)]
+pub fn create_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+    system_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    let funded = account.lamports();
+    if funded == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(payer.key, account.key, lamports, space, owner),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            signer_seeds,
+        );
+    }
+
+    if lamports > funded {
+        transfer(payer, account, lamports - funded, &[], system_program)?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space),
+        &[account.clone(), system_program.clone()],
+        signer_seeds,
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, owner),
+        &[account.clone(), system_program.clone()],
+        signer_seeds,
+    )
+}
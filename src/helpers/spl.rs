@@ -2,6 +2,8 @@ use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
     entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_error::ProgramError,
     pubkey::Pubkey,
     program_pack::Pack,
     msg,
@@ -16,10 +18,84 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
+    helpers::constants::{spl_token_2022, wsol},
 };
 
 declare_check_assert_macros!(SourceFileId::Spl);
 
+// Token-2022 accounts start with the classic layout. Accounts with extensions follow it with an account
+// type byte and the extensions, each a type and a length ahead of its data.
+const TOKEN_ACCOUNT_TYPE_OFFSET: usize = Account::LEN;
+const TOKEN_ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+const EXTENSION_UNINITIALIZED: u16 = 0;
+const EXTENSION_TRANSFER_FEE_AMOUNT: u16 = 2;
+const EXTENSION_TRANSFER_HOOK_ACCOUNT: u16 = 15;
+
+pub fn is_token_program(key: &Pubkey) -> bool {
+    key == &spl_token::id() || key == &spl_token_2022::id()
+}
+
+pub fn verify_token_program(token_program: &AccountInfo) -> LucraResult {
+    check!(is_token_program(token_program.key), LucraErrorCode::InvalidAccountInput)
+}
+
+/// User token accounts can be under either token program, the program's own vaults and mints stay on the
+/// classic one. The token program passed has to be the account's, it's the one the transfers go through.
+pub fn verify_user_token_account(token_account: &AccountInfo, token_program: &AccountInfo) -> LucraResult {
+    check!(is_token_program(token_account.owner), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_account.owner, token_program.key, LucraErrorCode::InvalidAccountInput)?;
+    if token_account.owner == &spl_token_2022::id() {
+        verify_no_transfer_extensions(&token_account.try_borrow_data()?)?;
+    }
+
+    Ok(())
+}
+
+/// Balances are measured around transfers. A transfer fee keeps part of the amount sent and a transfer hook
+/// runs another program in the middle of it, so accounts of mints with either are rejected.
+fn verify_no_transfer_extensions(data: &[u8]) -> LucraResult {
+    if data.len() == Account::LEN {
+        return Ok(());
+    }
+    check!(data.len() > TOKEN_ACCOUNT_TYPE_OFFSET, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(data[TOKEN_ACCOUNT_TYPE_OFFSET], TOKEN_ACCOUNT_TYPE_ACCOUNT, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut offset = TOKEN_ACCOUNT_TYPE_OFFSET + 1;
+    while offset + 4 <= data.len() {
+        let extension_type = u16::from_le_bytes(*array_ref![data, offset, 2]);
+        let length = u16::from_le_bytes(*array_ref![data, offset + 2, 2]) as usize;
+        // The rest is padding
+        if extension_type == EXTENSION_UNINITIALIZED {
+            break;
+        }
+        check!(extension_type != EXTENSION_TRANSFER_FEE_AMOUNT, LucraErrorCode::InvalidAccountInput)?;
+        check!(extension_type != EXTENSION_TRANSFER_HOOK_ACCOUNT, LucraErrorCode::InvalidAccountInput)?;
+        offset += 4 + length;
+        check!(offset <= data.len(), LucraErrorCode::InvalidAccountInput)?;
+    }
+
+    Ok(())
+}
+
+/// Token-2022 accounts with extensions are longer than the classic layout they start with
+fn verify_token_account_len(token_account: &AccountInfo, data: &[u8]) -> LucraResult {
+    if token_account.owner == &spl_token_2022::id() && data.len() > Account::LEN {
+        check_eq!(data[TOKEN_ACCOUNT_TYPE_OFFSET], TOKEN_ACCOUNT_TYPE_ACCOUNT, LucraErrorCode::InvalidAccountInput)
+    } else {
+        check_eq!(data.len(), Account::LEN, LucraErrorCode::InvalidAccountInput)
+    }
+}
+
+/// spl_token only builds its instructions for the classic program. Token-2022 reads the instructions used
+/// here the same way, so they're built for the classic program and sent to the one passed in.
+fn for_token_program(instruction: Instruction, token_program: &AccountInfo) -> Result<Instruction, ProgramError> {
+    if !is_token_program(token_program.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    Ok(Instruction { program_id: *token_program.key, ..instruction })
+}
+
 pub fn spl_token_mint_to<'a>(
     mint: &AccountInfo<'a>,
     destination: &AccountInfo<'a>,
@@ -28,14 +104,14 @@ pub fn spl_token_mint_to<'a>(
     authority_signer_seeds: &[&[&[u8]]],
     token_program: &AccountInfo<'a>,
 ) -> ProgramResult {
-    let mint_to_instruction = &spl_token::instruction::mint_to(
-        token_program.key, 
+    let mint_to_instruction = &for_token_program(spl_token::instruction::mint_to(
+        &spl_token::id(),
         mint.key, 
         destination.key, 
         authority.key, 
         &[], 
         amount
-    )?; 
+    )?, token_program)?;
     let accs = [
         mint.clone(),
         destination.clone(),
@@ -54,14 +130,14 @@ pub fn spl_token_burn<'a>(
     authority_signer_seeds: &[&[&[u8]]],
     token_program: &AccountInfo<'a>,
 ) -> ProgramResult {
-    let burn_instruction = &spl_token::instruction::burn(
-        token_program.key,
+    let burn_instruction = &for_token_program(spl_token::instruction::burn(
+        &spl_token::id(),
         source.key,
         mint.key,
         authority.key,
         &[],
         amount,
-    )?;
+    )?, token_program)?;
     let accs = [
         source.clone(), 
         mint.clone(), 
@@ -80,14 +156,14 @@ pub fn spl_token_transfer<'a>(
     authority_signer_seeds: &[&[&[u8]]],
     token_program: &AccountInfo<'a>,
 ) -> ProgramResult {
-    let transfer_instruction = &spl_token::instruction::transfer(
-        token_program.key,
+    let transfer_instruction = &for_token_program(spl_token::instruction::transfer(
+        &spl_token::id(),
         source.key,
         destination.key,
         authority.key,
         &[],
         amount,
-    )?;
+    )?, token_program)?;
     let accs = [
         source.clone(),
         destination.clone(),
@@ -105,13 +181,13 @@ pub fn spl_close_account<'a>(
     authority_signer_seeds: &[&[&[u8]]],
     token_program: &AccountInfo<'a>,
 ) -> ProgramResult {
-    let close_account_instruction = &spl_token::instruction::close_account(
-        token_program.key,
+    let close_account_instruction = &for_token_program(spl_token::instruction::close_account(
+        &spl_token::id(),
         account.key,
         destination.key,
         owner.key,
         &[],
-    )?;
+    )?, token_program)?;
     let accs = [
         account.clone(),
         destination.clone(),
@@ -166,20 +242,72 @@ pub fn get_mint_decimals(mint_account: &AccountInfo) -> LucraResult<u8> {
 
 pub fn get_token_balance(token_account: &AccountInfo) -> LucraResult<u64> {
     let data = token_account.try_borrow_data()?;
-    check_eq!(data.len(), Account::LEN, LucraErrorCode::InvalidAccountInput)?;
+    verify_token_account_len(token_account, &data)?;
     let amount = array_ref![data, 64, 8];
 
     Ok(u64::from_le_bytes(*amount))
 }
 
+/// How much `after` grew over `before`. A CPI that was meant to pay into the account and left it
+/// lower (fee-on-transfer mint, substituted token program) errors instead of underflowing.
+pub fn calc_balance_increase(before: u64, after: u64) -> LucraResult<u64> {
+    invariant!(after >= before, LucraErrorCode::UnexpectedBalanceDecrease)?;
+
+    Ok(after - before)
+}
+
+/// Tokens `token_account` received while `f` ran, `f` is usually the CPI paying into it
+pub fn measure_balance_change<F>(token_account: &AccountInfo, f: F) -> LucraResult<u64>
+where
+    F: FnOnce() -> LucraResult,
+{
+    let before = get_token_balance(token_account)?;
+    f()?;
+    let after = get_token_balance(token_account)?;
+
+    calc_balance_increase(before, after)
+}
+
+/// Tokens `swap` paid into `destination`, failing below the caller's `min_amount_out`. Pool math alone
+/// can't stop a sandwich around the swap, the caller's bound is what does. 0 accepts any amount the swap
+/// paid, a swap that paid nothing always fails.
+pub fn measure_swap_output<F>(destination: &AccountInfo, min_amount_out: u64, swap: F) -> LucraResult<u64>
+where
+    F: FnOnce() -> LucraResult,
+{
+    let amount_out = measure_balance_change(destination, swap)?;
+    check!(amount_out > 0, LucraErrorCode::NothingReceived)?;
+    check!(amount_out >= min_amount_out, LucraErrorCode::SlippageExceeded)?;
+
+    Ok(amount_out)
+}
+
+/// Lamports `account` received while `f` ran
+pub fn measure_lamports_change<F>(account: &AccountInfo, f: F) -> LucraResult<u64>
+where
+    F: FnOnce() -> LucraResult,
+{
+    let before = account.lamports();
+    f()?;
+    let after = account.lamports();
+
+    calc_balance_increase(before, after)
+}
+
 pub fn get_token_account_mint(token_account: &AccountInfo) -> LucraResult<Pubkey> {
     let data = token_account.try_borrow_data()?;
-    check_eq!(data.len(), Account::LEN, LucraErrorCode::InvalidAccountInput)?;
+    verify_token_account_len(token_account, &data)?;
     let mint = array_ref![data, 0, 32];
 
     Ok(Pubkey::new_from_array(*mint))
 }
 
+/// Harvests and arbs wrap sol through this account, any other mint would throw off the balance changes around their swaps
+pub fn verify_wsol_account(token_account: &AccountInfo) -> LucraResult {
+    check_eq!(token_account.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(get_token_account_mint(token_account)?, wsol::id(), LucraErrorCode::InvalidAccountInput)
+}
+
 pub fn calculate_pool_price(
     base_amount: Decimal,
     quote_amount: Decimal,
@@ -240,4 +368,275 @@ mod test {
         ).is_ok();        
         assert!(!result);
     }
-}
\ No newline at end of file
+
+    fn token_account_data(amount: u64) -> Vec<u8> {
+        token_account_data_for_mint(Pubkey::new_unique(), amount)
+    }
+
+    fn token_account_data_for_mint(mint: Pubkey, amount: u64) -> Vec<u8> {
+        let mut data = vec![0; Account::LEN];
+        Account {
+            mint,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Account::default()
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    fn set_token_balance(token_account: &AccountInfo, amount: u64) {
+        let mut data = token_account.try_borrow_mut_data().unwrap();
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+    }
+
+    #[test]
+    fn test_measure_balance_change() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = token_account_data(1_000);
+        let token_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &spl_token::ID, false, 0);
+
+        let received = measure_balance_change(&token_account, || {
+            set_token_balance(&token_account, 1_250);
+            Ok(())
+        }).unwrap();
+        assert_eq!(received, 250);
+
+        let received = measure_balance_change(&token_account, || Ok(())).unwrap();
+        assert_eq!(received, 0);
+    }
+
+    #[test]
+    fn test_swap_paying_less_than_the_minimum_fails() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = token_account_data(1_000);
+        let token_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &spl_token::ID, false, 0);
+
+        let received = measure_swap_output(&token_account, 500, || {
+            set_token_balance(&token_account, 1_500);
+            Ok(())
+        }).unwrap();
+        assert_eq!(received, 500);
+
+        // A sandwiched pool pays out one base unit short
+        let result = measure_swap_output(&token_account, 500, || {
+            set_token_balance(&token_account, 1_999);
+            Ok(())
+        });
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::SlippageExceeded,
+                ..
+            }
+        ));
+
+        assert_eq!(measure_swap_output(&token_account, 0, || {
+            set_token_balance(&token_account, 2_000);
+            Ok(())
+        }).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_zero_output_swap_fails() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = token_account_data(1_000);
+        let token_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &spl_token::ID, false, 0);
+
+        // Even without a minimum, a pool that paid nothing out errors before anything is burned against it
+        for min_amount_out in [0, 1, 500] {
+            let result = measure_swap_output(&token_account, min_amount_out, || Ok(()));
+            assert!(matches!(
+                result.unwrap_err(),
+                LucraError::LucraErrorCode {
+                    lucra_error_code: LucraErrorCode::NothingReceived,
+                    ..
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_misbehaving_token_program_errors_instead_of_underflowing() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = token_account_data(1_000);
+        let token_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &spl_token::ID, false, 0);
+
+        // The "CPI" takes tokens out of the account it was meant to pay into
+        let result = measure_balance_change(&token_account, || {
+            set_token_balance(&token_account, 999);
+            Ok(())
+        });
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::Invariant {
+                lucra_error_code: LucraErrorCode::UnexpectedBalanceDecrease,
+                ..
+            }
+        ));
+
+        // A failing CPI is passed through untouched
+        let result = measure_balance_change(&token_account, || Err(throw_err!(LucraErrorCode::InvalidAccountInput)));
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::InvalidAccountInput,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_measure_lamports_change() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 5_000;
+        let mut data = vec![];
+        let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &key, false, 0);
+
+        let received = measure_lamports_change(&account, || {
+            **account.try_borrow_mut_lamports().unwrap() += 7;
+            Ok(())
+        }).unwrap();
+        assert_eq!(received, 7);
+
+        let result = measure_lamports_change(&account, || {
+            **account.try_borrow_mut_lamports().unwrap() -= 1;
+            Ok(())
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_verify_wsol_account() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = token_account_data_for_mint(wsol::id(), 0);
+        let wsol_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &spl_token::ID, false, 0);
+
+        assert!(verify_wsol_account(&wsol_account).is_ok());
+    }
+
+    #[test]
+    fn test_verify_wsol_account_rejects_other_mints() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = token_account_data_for_mint(Pubkey::new_unique(), 0);
+        let token_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &spl_token::ID, false, 0);
+
+        assert!(matches!(
+            verify_wsol_account(&token_account).unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::InvalidAccountInput,
+                ..
+            }
+        ));
+
+        // Native mint data in an account the token program doesn't own
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = token_account_data_for_mint(wsol::id(), 0);
+        let fake_account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+
+        assert!(verify_wsol_account(&fake_account).is_err());
+    }
+
+    /// A Token-2022 account with the extensions given as their type and data length
+    fn token_2022_account_data(amount: u64, extensions: &[(u16, u16)]) -> Vec<u8> {
+        let mut data = token_account_data(amount);
+        data.push(TOKEN_ACCOUNT_TYPE_ACCOUNT);
+        for (extension_type, length) in extensions {
+            data.extend_from_slice(&extension_type.to_le_bytes());
+            data.extend_from_slice(&length.to_le_bytes());
+            data.extend(std::iter::repeat(0).take(*length as usize));
+        }
+        data
+    }
+
+    fn assert_invalid_input(result: LucraResult) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::InvalidAccountInput, .. }
+        ));
+    }
+
+    #[test]
+    fn test_user_token_accounts_under_either_program() {
+        let (key, program_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l1, mut l2, mut l3, mut l4) = (0, 0, 0, 0);
+        let mut program_data = vec![];
+        let mut program_data_2022 = vec![];
+        let classic_program = AccountInfo::new(&spl_token::ID, false, false, &mut l1, &mut program_data, &program_key, true, 0);
+        let program_2022 = AccountInfo::new(&spl_token_2022::ID, false, false, &mut l2, &mut program_data_2022, &program_key, true, 0);
+
+        let mut classic_data = token_account_data(1_000);
+        let classic = AccountInfo::new(&key, false, true, &mut l3, &mut classic_data, &spl_token::ID, false, 0);
+        assert!(verify_user_token_account(&classic, &classic_program).is_ok());
+        assert_invalid_input(verify_user_token_account(&classic, &program_2022));
+
+        // Memo transfer and immutable owner extensions are fine
+        let mut data_2022 = token_2022_account_data(2_000, &[(8, 1), (7, 0)]);
+        let account_2022 = AccountInfo::new(&key, false, true, &mut l4, &mut data_2022, &spl_token_2022::ID, false, 0);
+        assert!(verify_user_token_account(&account_2022, &program_2022).is_ok());
+        assert_invalid_input(verify_user_token_account(&account_2022, &classic_program));
+        assert_eq!(get_token_balance(&account_2022).unwrap(), 2_000);
+
+        assert!(verify_token_program(&classic_program).is_ok());
+        assert!(verify_token_program(&program_2022).is_ok());
+        assert!(verify_token_program(&classic).is_err());
+    }
+
+    #[test]
+    fn test_token_2022_accounts_with_transfer_fees_or_hooks_are_rejected() {
+        assert!(verify_no_transfer_extensions(&token_account_data(1)).is_ok());
+        assert!(verify_no_transfer_extensions(&token_2022_account_data(1, &[])).is_ok());
+
+        // The transfer fee withheld on the account, the transfer hook's flag
+        assert_invalid_input(verify_no_transfer_extensions(&token_2022_account_data(1, &[(2, 8)])));
+        assert_invalid_input(verify_no_transfer_extensions(&token_2022_account_data(1, &[(7, 0), (15, 1)])));
+
+        // Padding after the extensions
+        let mut data = token_2022_account_data(1, &[(7, 0)]);
+        data.extend_from_slice(&[0; 8]);
+        assert!(verify_no_transfer_extensions(&data).is_ok());
+
+        // A mint, and an extension running past the end of the account
+        let mut data = token_2022_account_data(1, &[]);
+        data[TOKEN_ACCOUNT_TYPE_OFFSET] = 1;
+        assert_invalid_input(verify_no_transfer_extensions(&data));
+        let mut data = token_2022_account_data(1, &[(8, 1)]);
+        data.pop();
+        assert_invalid_input(verify_no_transfer_extensions(&data));
+    }
+
+    #[test]
+    fn test_extended_layout_is_only_read_from_token_2022_accounts() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = token_2022_account_data(500, &[(7, 0)]);
+        let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &spl_token::ID, false, 0);
+
+        assert!(get_token_balance(&account).is_err());
+        assert!(get_token_account_mint(&account).is_err());
+    }
+
+    #[test]
+    fn test_token_instructions_go_to_the_program_passed() {
+        let (mint, destination, authority, program_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l1, mut l2) = (0, 0);
+        let (mut d1, mut d2) = (vec![], vec![]);
+        let program_2022 = AccountInfo::new(&spl_token_2022::ID, false, false, &mut l1, &mut d1, &program_key, true, 0);
+        let other_program = AccountInfo::new(&program_key, false, false, &mut l2, &mut d2, &program_key, true, 0);
+
+        let classic = spl_token::instruction::mint_to(&spl_token::id(), &mint, &destination, &authority, &[], 7).unwrap();
+        let retargeted = for_token_program(classic.clone(), &program_2022).unwrap();
+        assert_eq!(retargeted.program_id, spl_token_2022::id());
+        assert_eq!(retargeted.accounts, classic.accounts);
+        assert_eq!(retargeted.data, classic.data);
+
+        assert_eq!(for_token_program(classic, &other_program).unwrap_err(), ProgramError::IncorrectProgramId);
+    }
+}
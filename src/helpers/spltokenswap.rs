@@ -1,10 +1,12 @@
 // Taken from the solana-farm-sdk found here https://docs.rs/solana-farm-sdk/1.1.3/src/solana_farm_sdk/program/protocol/orca.rs.html
 use solana_program::{
     account_info::AccountInfo,
+    native_token::LAMPORTS_PER_SOL,
     program::invoke,
+    pubkey::Pubkey,
 };
 use spl_token_swap::state::SwapVersion;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, MathematicalOps};
 use crate::{
     error::{
         check_assert,
@@ -14,10 +16,12 @@ use crate::{
         SourceFileId,
     },
     helpers::{
+        account::verify_program_account,
         spl::{get_tokens, get_token_balance, get_mint_decimals, verify_balanced_pool},
         math::{ceiling_division, get_no_fee_amount},
     },
-    helpers::constants::orca_swap,
+    helpers::constants::{orca_swap, wsol, LAMPORTS_PER_MATA},
+    state::SystemState,
 };
 
 use super::spl::calculate_pool_price;
@@ -44,8 +48,11 @@ pub fn swap<'a>(
     authority_signer_seeds:&[&[&[u8]]],
     token_a_amount_in: u64,
     token_b_amount_in: u64,
+    min_amount_out: u64,
 ) -> LucraResult {
-    let (amount_in, min_amount_out) = get_pool_swap_amounts(
+    verify_program_account(program_id, &orca_swap::id())?;
+
+    let (amount_in, pool_min_amount_out) = get_pool_swap_amounts(
         swap_base_vault,
         swap_quote_vault,
         token_a_amount_in,
@@ -66,11 +73,12 @@ pub fn swap<'a>(
         fees.clone(),
     ];
 
+    // The pool's own quote moves with a sandwich, the caller's bound doesn't
     swap_with_seeds(
         &accs,
         authority_signer_seeds,
         amount_in,
-        min_amount_out,
+        pool_min_amount_out.max(min_amount_out),
     )
 }
 
@@ -96,10 +104,6 @@ fn swap_with_seeds(
         fees_account
         ] = accounts
     {
-        if pool_program_id.key != &orca_swap::id() {
-            return Err(throw_err!(LucraErrorCode::InvalidAccountInput));
-        }
-
         let data = spl_token_swap::instruction::Swap {
             amount_in,
             minimum_amount_out: min_amount_out,
@@ -127,6 +131,67 @@ fn swap_with_seeds(
     }
 }
 
+/// Trades `pool_token_amount` LP tokens from `source` back for their share of both reserves, signed for by
+/// `user_transfer_authority`
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_all_token_types<'a>(
+    program_id: &AccountInfo<'a>,
+    token_program_id: &AccountInfo<'a>,
+    amm_id: &AccountInfo<'a>,
+    amm_authority_id: &AccountInfo<'a>,
+    user_transfer_authority: &AccountInfo<'a>,
+    pool_mint: &AccountInfo<'a>,
+    fees: &AccountInfo<'a>,
+    source: &AccountInfo<'a>,
+    swap_base_vault: &AccountInfo<'a>,
+    swap_quote_vault: &AccountInfo<'a>,
+    base_destination: &AccountInfo<'a>,
+    quote_destination: &AccountInfo<'a>,
+    pool_token_amount: u64,
+    minimum_base_amount: u64,
+    minimum_quote_amount: u64,
+) -> LucraResult {
+    verify_program_account(program_id, &orca_swap::id())?;
+
+    let data = spl_token_swap::instruction::WithdrawAllTokenTypes {
+        pool_token_amount,
+        minimum_token_a_amount: minimum_base_amount,
+        minimum_token_b_amount: minimum_quote_amount,
+    };
+    let instruction = spl_token_swap::instruction::withdraw_all_token_types(
+        program_id.key,
+        &spl_token::id(),
+        amm_id.key,
+        amm_authority_id.key,
+        user_transfer_authority.key,
+        pool_mint.key,
+        fees.key,
+        source.key,
+        swap_base_vault.key,
+        swap_quote_vault.key,
+        base_destination.key,
+        quote_destination.key,
+        data,
+    )?;
+
+    let accs = [
+        amm_id.clone(),
+        amm_authority_id.clone(),
+        user_transfer_authority.clone(),
+        pool_mint.clone(),
+        source.clone(),
+        swap_base_vault.clone(),
+        swap_quote_vault.clone(),
+        base_destination.clone(),
+        quote_destination.clone(),
+        fees.clone(),
+        token_program_id.clone(),
+        program_id.clone(),
+    ];
+
+    invoke(&instruction, &accs).map_err(|_| throw_err!(LucraErrorCode::TransactionFailed))
+}
+
 fn get_pool_token_balances<'a, 'b>(
     pool_token_a_account: &'a AccountInfo<'b>,
     pool_token_b_account: &'a AccountInfo<'b>,
@@ -354,9 +419,117 @@ pub fn verify_orca_pool_has_proper_mints<'a, 'b>(
     Ok(())
 }
 
+/// The MSOL/SOL pool an arb can swap mSOL through instead of unstaking it with marinade. The DAO registers it
+/// with UpdateState and it can hold the two mints either way round. The caller checks the mSOL mint against the
+/// marinade state.
+pub fn verify_msol_sol_pool<'a, 'b>(
+    system_state: &SystemState,
+    orca_pool_ai: &'a AccountInfo<'b>,
+    msol_mint_ai: &'a AccountInfo<'b>,
+    wsol_mint_ai: &'a AccountInfo<'b>,
+) -> LucraResult {
+    check!(system_state.msol_sol_pool != Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(orca_pool_ai.key, &system_state.msol_sol_pool, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(orca_pool_ai.owner, &orca_swap::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(wsol_mint_ai.key, &wsol::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    verify_orca_pool_has_proper_mints(orca_pool_ai, msol_mint_ai, wsol_mint_ai)
+        .or_else(|_| verify_orca_pool_has_proper_mints(orca_pool_ai, wsol_mint_ai, msol_mint_ai))
+}
+
+pub fn get_orca_pool_mint(orca_pool_ai: &AccountInfo) -> LucraResult<Pubkey> {
+    let amm_id_data = SwapVersion::unpack(&orca_pool_ai.try_borrow_data()?)?;
+
+    Ok(*amm_id_data.pool_mint())
+}
+
+/// Dollar value of `lp_amount` SOL/MATA LP tokens. The reserves are only used through their product,
+/// which a swap can't shrink, and are revalued at the oracle prices. Pushing the pool off its spot
+/// price within a transaction can't inflate the value.
+pub fn calc_lp_fair_value(
+    lp_amount: u64,
+    lp_supply: u64,
+    sol_reserve: u64,
+    mata_reserve: u64,
+    sol_usd_price: Decimal,
+    mata_usd_price: Decimal,
+) -> LucraResult<Decimal> {
+    check!(lp_supply > 0 && sol_reserve > 0 && mata_reserve > 0, LucraErrorCode::EmptyPool)?;
+    check!(lp_amount <= lp_supply, LucraErrorCode::InvalidAmount)?;
+
+    let sol = Decimal::from(sol_reserve)
+        .checked_div(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())?;
+    let mata = Decimal::from(mata_reserve)
+        .checked_div(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?;
+
+    // 2 * sqrt(sol * mata * p_sol * p_mata) is the pool value had it been balanced at the oracle prices
+    let pool_value = sol
+        .checked_mul(sol_usd_price)
+        .ok_or(math_err!())?
+        .checked_mul(mata)
+        .ok_or(math_err!())?
+        .checked_mul(mata_usd_price)
+        .ok_or(math_err!())?
+        .sqrt()
+        .ok_or(math_err!())?
+        .checked_mul(Decimal::TWO)
+        .ok_or(math_err!())?;
+
+    pool_value
+        .checked_mul(lp_amount.into())
+        .ok_or(math_err!())?
+        .checked_div(lp_supply.into())
+        .ok_or(math_err!())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal_macros::dec;
+    use crate::helpers::account::tests::{assert_invalid_program_account, FakeAccounts};
+
+    const SOL: u64 = LAMPORTS_PER_SOL;
+    const MATA: u64 = 1_000_000;
+
+    #[test]
+    fn test_lp_fair_value_of_a_balanced_pool() {
+        // 1,000 SOL at $20 against 20,000 MATA, the pool is worth $40,000
+        let value = calc_lp_fair_value(100, 1_000, 1_000 * SOL, 20_000 * MATA, dec!(20), dec!(1)).unwrap();
+
+        assert_eq!(value, dec!(4_000));
+    }
+
+    #[test]
+    fn test_lp_fair_value_ignores_a_skewed_pool() {
+        let balanced = calc_lp_fair_value(100, 1_000, 1_000 * SOL, 20_000 * MATA, dec!(20), dec!(1)).unwrap();
+
+        // Someone dumps SOL into the pool, the reserves keep their product but spot value goes up to $50,000
+        let skewed = calc_lp_fair_value(100, 1_000, 2_000 * SOL, 10_000 * MATA, dec!(20), dec!(1)).unwrap();
+
+        assert_eq!(skewed, balanced);
+    }
+
+    #[test]
+    fn test_lp_fair_value_follows_the_oracles() {
+        let value = calc_lp_fair_value(1_000, 1_000, 1_000 * SOL, 20_000 * MATA, dec!(20), dec!(0.25)).unwrap();
+
+        assert_eq!(value, dec!(20_000));
+    }
+
+    #[test]
+    fn test_lp_fair_value_of_an_empty_pool() {
+        assert!(matches!(
+            calc_lp_fair_value(100, 0, 1_000 * SOL, 20_000 * MATA, dec!(20), dec!(1)).unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::EmptyPool, .. }
+        ));
+        assert!(matches!(
+            calc_lp_fair_value(100, 1_000, 0, 20_000 * MATA, dec!(20), dec!(1)).unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::EmptyPool, .. }
+        ));
+        assert!(calc_lp_fair_value(1_001, 1_000, 1_000 * SOL, 20_000 * MATA, dec!(20), dec!(1)).is_err());
+    }
 
     #[test]
     fn test_verify_orca_pools_will_be_balanced() {
@@ -397,4 +570,110 @@ mod tests {
         );
         assert_eq!(result, Ok(()))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_swap_refuses_a_fake_orca_program() {
+        let mut accounts = FakeAccounts::new(11).with_program(0, &Pubkey::new_unique());
+        let a = accounts.infos();
+
+        assert_invalid_program_account(swap(
+            &a[0], &a[1], &a[2], &a[3], &a[4], &a[5], &a[6], &a[7], &a[8], &a[9], &a[10], &[], 1, 0, 0,
+        ));
+    }
+
+    #[test]
+    fn test_withdraw_refuses_a_fake_orca_program() {
+        let mut accounts = FakeAccounts::new(12).with_program(0, &Pubkey::new_unique());
+        let a = accounts.infos();
+
+        assert_invalid_program_account(withdraw_all_token_types(
+            &a[0], &a[1], &a[2], &a[3], &a[4], &a[5], &a[6], &a[7], &a[8], &a[9], &a[10], &a[11], 1, 0, 0,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::program_pack::Pack;
+    use spl_token_swap::state::SwapV1;
+    use crate::helpers::account::tests::FakeAccounts;
+
+    const POOL: usize = 0;
+    const MSOL_MINT: usize = 1;
+    const WSOL_MINT: usize = 2;
+
+    fn packed_pool(token_a_mint: Pubkey, token_b_mint: Pubkey) -> Vec<u8> {
+        let pool = SwapVersion::SwapV1(SwapV1 {
+            is_initialized: true,
+            token_a_mint,
+            token_b_mint,
+            ..SwapV1::default()
+        });
+        let mut data = vec![0; SwapVersion::LATEST_LEN];
+        SwapVersion::pack(pool, &mut data).unwrap();
+        data
+    }
+
+    /// The pool, the mSOL mint and the wSOL mint, with the pool holding `mints` as token a and b
+    fn msol_sol_accounts(mints: [usize; 2]) -> (FakeAccounts, SystemState) {
+        let accounts = FakeAccounts::new(3).with_owner(&orca_swap::id()).with_key(WSOL_MINT, &wsol::id());
+        let data = packed_pool(accounts.key(mints[0]), accounts.key(mints[1]));
+        let accounts = accounts.with_data(POOL, data);
+
+        let mut system_state = SystemState::default();
+        system_state.msol_sol_pool = accounts.key(POOL);
+        (accounts, system_state)
+    }
+
+    fn verify(accounts: &mut FakeAccounts, system_state: &SystemState) -> LucraResult {
+        let infos = accounts.infos();
+        verify_msol_sol_pool(system_state, &infos[POOL], &infos[MSOL_MINT], &infos[WSOL_MINT])
+    }
+
+    #[test]
+    fn test_msol_sol_pool_holds_either_mint_first() {
+        for mints in [[MSOL_MINT, WSOL_MINT], [WSOL_MINT, MSOL_MINT]] {
+            let (mut accounts, system_state) = msol_sol_accounts(mints);
+            assert!(verify(&mut accounts, &system_state).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_msol_sol_pool_with_other_mints_is_rejected() {
+        // The mSOL mint on both sides, or another token in place of one of them
+        for mints in [[MSOL_MINT, MSOL_MINT], [WSOL_MINT, WSOL_MINT], [POOL, WSOL_MINT], [MSOL_MINT, POOL]] {
+            let (mut accounts, system_state) = msol_sol_accounts(mints);
+            assert!(verify(&mut accounts, &system_state).is_err());
+        }
+
+        // The pool is right but the wSOL mint handed in isn't the native mint
+        let accounts = FakeAccounts::new(3).with_owner(&orca_swap::id());
+        let data = packed_pool(accounts.key(MSOL_MINT), accounts.key(WSOL_MINT));
+        let mut accounts = accounts.with_data(POOL, data);
+        let mut system_state = SystemState::default();
+        system_state.msol_sol_pool = accounts.key(POOL);
+        assert!(verify(&mut accounts, &system_state).is_err());
+    }
+
+    #[test]
+    fn test_msol_sol_pool_has_to_be_the_registered_one() {
+        let (mut accounts, mut system_state) = msol_sol_accounts([MSOL_MINT, WSOL_MINT]);
+
+        // No pool registered keeps the arb on the marinade unstake
+        system_state.msol_sol_pool = Pubkey::default();
+        assert!(verify(&mut accounts, &system_state).is_err());
+
+        system_state.msol_sol_pool = Pubkey::new_unique();
+        assert!(verify(&mut accounts, &system_state).is_err());
+
+        // A copy of the pool's data under another program
+        let (accounts, system_state) = msol_sol_accounts([MSOL_MINT, WSOL_MINT]);
+        let mut accounts = accounts.with_owner(&Pubkey::new_unique());
+        let result = verify(&mut accounts, &system_state);
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::InvalidAccountOwner, .. }
+        ));
+    }
+}
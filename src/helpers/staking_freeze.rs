@@ -0,0 +1,191 @@
+use solana_program::pubkey::Pubkey;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::STAKING_UNFREEZE_DELAY,
+    state::staking::{StakeBalance, StakingAccount},
+};
+
+declare_check_assert_macros!(SourceFileId::StakingFreeze);
+
+// An owner who thinks their key leaked can freeze their staking account. Whoever holds the key can ask
+// to unfreeze it too, so the owner's unfreeze only lands after a delay. The recovery key skips the delay.
+
+pub fn is_staking_account_frozen(staking_account: &StakingAccount, timestamp: i64) -> bool {
+    staking_account.frozen && (staking_account.unfreeze_at == 0 || timestamp < staking_account.unfreeze_at)
+}
+
+pub fn check_staking_account_not_frozen(staking_account: &StakingAccount, timestamp: i64) -> LucraResult {
+    check!(!is_staking_account_frozen(staking_account, timestamp), LucraErrorCode::StakingAccountFrozen)
+}
+
+pub fn is_recovery_key(staking_account: &StakingAccount, authority: &Pubkey) -> bool {
+    staking_account.recovery_key != Pubkey::default() && &staking_account.recovery_key == authority
+}
+
+/// A stake balance answers to the staking account it was created with, so another account of the same owner
+/// can't stand in for a frozen one. Balances created before they recorded it are bound to the first account
+/// passed whose total covers what the balance has staked, which an account opened after a leak can't.
+pub fn bind_staking_account(
+    stake_balance: &mut StakeBalance,
+    staking_account_key: &Pubkey,
+    staking_account: &StakingAccount,
+    stake_vault_lucra: u64,
+) -> LucraResult {
+    check_eq!(staking_account.owner, stake_balance.owner, LucraErrorCode::InvalidAccountInput)?;
+    if stake_balance.staking_account == Pubkey::default() {
+        check!(stake_vault_lucra <= staking_account.total, LucraErrorCode::InvalidAccountInput)?;
+        stake_balance.staking_account = *staking_account_key;
+    }
+
+    check_eq!(&stake_balance.staking_account, staking_account_key, LucraErrorCode::InvalidAccountInput)
+}
+
+/// Freezing again cancels an unfreeze that hasn't landed yet
+pub fn freeze_staking_account(staking_account: &mut StakingAccount) {
+    staking_account.frozen = true;
+    staking_account.unfreeze_at = 0;
+}
+
+/// The recovery key unfreezes straight away. The owner's first call starts the delay and a
+/// call once it has passed clears the freeze.
+pub fn unfreeze_staking_account(staking_account: &mut StakingAccount, authority: &Pubkey, timestamp: i64) -> LucraResult {
+    check!(staking_account.frozen, LucraErrorCode::InvalidStateTransition)?;
+
+    if is_recovery_key(staking_account, authority) {
+        staking_account.frozen = false;
+        staking_account.unfreeze_at = 0;
+        return Ok(());
+    }
+
+    check_eq!(&staking_account.owner, authority, LucraErrorCode::InvalidAccountInput)?;
+    if staking_account.unfreeze_at == 0 {
+        staking_account.unfreeze_at = timestamp
+            .checked_add(STAKING_UNFREEZE_DELAY)
+            .ok_or(math_err!())?;
+        return Ok(());
+    }
+
+    check!(staking_account.unfreeze_at <= timestamp, LucraErrorCode::Timelock)?;
+    staking_account.frozen = false;
+    staking_account.unfreeze_at = 0;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_650_000_000;
+
+    fn frozen_staking_account(recovery_key: Pubkey) -> StakingAccount {
+        let mut staking_account = StakingAccount::default();
+        staking_account.owner = Pubkey::new_unique();
+        staking_account.recovery_key = recovery_key;
+        freeze_staking_account(&mut staking_account);
+        staking_account
+    }
+
+    #[test]
+    fn test_freeze_blocks_straight_away() {
+        let mut staking_account = StakingAccount::default();
+        assert!(check_staking_account_not_frozen(&staking_account, NOW).is_ok());
+
+        freeze_staking_account(&mut staking_account);
+
+        assert!(check_staking_account_not_frozen(&staking_account, NOW).is_err());
+    }
+
+    #[test]
+    fn test_owner_unfreeze_waits_out_the_delay() {
+        let mut staking_account = frozen_staking_account(Pubkey::default());
+        let owner = staking_account.owner;
+
+        unfreeze_staking_account(&mut staking_account, &owner, NOW).unwrap();
+        assert_eq!(staking_account.unfreeze_at, NOW + STAKING_UNFREEZE_DELAY);
+        assert!(is_staking_account_frozen(&staking_account, NOW + STAKING_UNFREEZE_DELAY - 1));
+        assert!(!is_staking_account_frozen(&staking_account, NOW + STAKING_UNFREEZE_DELAY));
+
+        // Asking again before the delay is up doesn't restart or skip it
+        assert!(unfreeze_staking_account(&mut staking_account, &owner, NOW + 1).is_err());
+        assert_eq!(staking_account.unfreeze_at, NOW + STAKING_UNFREEZE_DELAY);
+
+        unfreeze_staking_account(&mut staking_account, &owner, NOW + STAKING_UNFREEZE_DELAY).unwrap();
+        assert!(!staking_account.frozen);
+        assert_eq!(staking_account.unfreeze_at, 0);
+    }
+
+    #[test]
+    fn test_refreezing_cancels_a_pending_unfreeze() {
+        let mut staking_account = frozen_staking_account(Pubkey::default());
+        let owner = staking_account.owner;
+
+        unfreeze_staking_account(&mut staking_account, &owner, NOW).unwrap();
+        freeze_staking_account(&mut staking_account);
+
+        assert!(is_staking_account_frozen(&staking_account, NOW + STAKING_UNFREEZE_DELAY));
+    }
+
+    #[test]
+    fn test_recovery_key_unfreezes_instantly() {
+        let recovery_key = Pubkey::new_unique();
+        let mut staking_account = frozen_staking_account(recovery_key);
+
+        unfreeze_staking_account(&mut staking_account, &recovery_key, NOW).unwrap();
+
+        assert!(!is_staking_account_frozen(&staking_account, NOW));
+    }
+
+    fn staking_account(owner: Pubkey, total: u64) -> StakingAccount {
+        let mut staking_account = StakingAccount::default();
+        staking_account.owner = owner;
+        staking_account.total = total;
+        staking_account
+    }
+
+    #[test]
+    fn test_stake_balance_answers_to_its_own_staking_account() {
+        let owner = Pubkey::new_unique();
+        let (frozen_key, other_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut stake_balance = StakeBalance::default();
+        stake_balance.owner = owner;
+        stake_balance.staking_account = frozen_key;
+
+        bind_staking_account(&mut stake_balance, &frozen_key, &staking_account(owner, 1_000), 1_000).unwrap();
+        // Another account of the same owner, one opened after the first was frozen say, is refused
+        assert!(bind_staking_account(&mut stake_balance, &other_key, &staking_account(owner, 1_000), 1_000).is_err());
+        assert!(bind_staking_account(&mut stake_balance, &frozen_key, &staking_account(Pubkey::new_unique(), 1_000), 1_000).is_err());
+        assert_eq!(stake_balance.staking_account, frozen_key);
+    }
+
+    #[test]
+    fn test_older_stake_balances_bind_to_an_account_covering_their_stake() {
+        let owner = Pubkey::new_unique();
+        let (staking_account_key, fresh_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut stake_balance = StakeBalance::default();
+        stake_balance.owner = owner;
+
+        assert!(bind_staking_account(&mut stake_balance, &fresh_key, &staking_account(owner, 0), 1_000).is_err());
+        assert_eq!(stake_balance.staking_account, Pubkey::default());
+
+        bind_staking_account(&mut stake_balance, &staking_account_key, &staking_account(owner, 1_500), 1_000).unwrap();
+        assert_eq!(stake_balance.staking_account, staking_account_key);
+        assert!(bind_staking_account(&mut stake_balance, &fresh_key, &staking_account(owner, 1_500), 1_000).is_err());
+    }
+
+    #[test]
+    fn test_unset_recovery_key_grants_nothing() {
+        let mut staking_account = frozen_staking_account(Pubkey::default());
+
+        assert!(!is_recovery_key(&staking_account, &Pubkey::default()));
+        assert!(unfreeze_staking_account(&mut staking_account, &Pubkey::default(), NOW).is_err());
+        assert!(unfreeze_staking_account(&mut staking_account, &Pubkey::new_unique(), NOW).is_err());
+        assert!(staking_account.frozen);
+    }
+}
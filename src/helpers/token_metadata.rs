@@ -0,0 +1,122 @@
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+use crate::helpers::{account::verify_program_account, constants::token_metadata};
+
+pub use crate::lucra_core::pda::find_metadata_address;
+
+// Only the one metaplex instruction the program needs, packed by hand the same way the raydium swap is
+
+pub const MAX_NAME_LENGTH: usize = 32;
+pub const MAX_SYMBOL_LENGTH: usize = 10;
+pub const MAX_URI_LENGTH: usize = 200;
+
+#[derive(Clone, Debug)]
+pub struct CreateMetadataAccountV3 {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl CreateMetadataAccountV3 {
+    pub const INSTRUCTION: u8 = 33;
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut data = vec![CreateMetadataAccountV3::INSTRUCTION];
+        for value in [&self.name, &self.symbol, &self.uri] {
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            data.extend_from_slice(value.as_bytes());
+        }
+        data.extend_from_slice(&0_u16.to_le_bytes()); // seller_fee_basis_points
+        data.push(0); // creators
+        data.push(0); // collection
+        data.push(0); // uses
+        data.push(1); // is_mutable
+        data.push(0); // collection_details
+
+        data
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_metadata_accounts_v3<'a>(
+    metadata: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    mint_authority: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    update_authority: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    metadata_program: &AccountInfo<'a>,
+    authority_signer_seeds: &[&[&[u8]]],
+    data: CreateMetadataAccountV3,
+) -> ProgramResult {
+    verify_program_account(metadata_program, &token_metadata::id())?;
+
+    let instruction = Instruction {
+        program_id: *metadata_program.key,
+        accounts: vec![
+            AccountMeta::new(*metadata.key, false),
+            AccountMeta::new_readonly(*mint.key, false),
+            AccountMeta::new_readonly(*mint_authority.key, true),
+            AccountMeta::new(*payer.key, true),
+            AccountMeta::new_readonly(*update_authority.key, true),
+            AccountMeta::new_readonly(*system_program.key, false),
+        ],
+        data: data.to_vec(),
+    };
+    let accs = [
+        metadata.clone(),
+        mint.clone(),
+        mint_authority.clone(),
+        payer.clone(),
+        update_authority.clone(),
+        system_program.clone(),
+        metadata_program.clone(),
+    ];
+
+    solana_program::program::invoke_signed(&instruction, &accs, authority_signer_seeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::account::tests::{assert_invalid_program_account_code, FakeAccounts};
+
+    #[test]
+    fn test_pack_create_metadata_account() {
+        let data = CreateMetadataAccountV3 {
+            name: "Lucra Reward".to_string(),
+            symbol: "LRW".to_string(),
+            uri: "".to_string(),
+        }
+        .to_vec();
+
+        let mut expected = vec![33];
+        expected.extend_from_slice(&[12, 0, 0, 0]);
+        expected.extend_from_slice(b"Lucra Reward");
+        expected.extend_from_slice(&[3, 0, 0, 0]);
+        expected.extend_from_slice(b"LRW");
+        expected.extend_from_slice(&[0, 0, 0, 0]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 1, 0]);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_create_metadata_refuses_a_fake_metaplex_program() {
+        let mut accounts = FakeAccounts::new(7).with_program(6, &Pubkey::new_unique());
+        let a = accounts.infos();
+        let data = CreateMetadataAccountV3 {
+            name: "Lucra Reward".to_string(),
+            symbol: "LRW".to_string(),
+            uri: String::new(),
+        };
+
+        assert_invalid_program_account_code(create_metadata_accounts_v3(
+            &a[0], &a[1], &a[2], &a[3], &a[4], &a[5], &a[6], &[], data,
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::UNSTAKE_TIMELOCKS,
+    state::staking::{PendingWithdrawal, StakingTimeframe},
+};
+
+declare_check_assert_macros!(SourceFileId::UnstakeTimelock);
+
+// An unstake waits out the timelock of its stake balance's timeframe before EndUnstake moves the lucra back to
+// the deposit vault. Pending withdrawals on UNSTAKE_TIMELOCK_VERSION record when that is in claimable_at, ones
+// opened before it waited an epoch and are claimable at their end_timestamp.
+
+pub const UNSTAKE_TIMELOCK_VERSION: u8 = 1;
+
+pub fn unstake_timelock(staking_timeframe: StakingTimeframe) -> LucraResult<i64> {
+    UNSTAKE_TIMELOCKS
+        .get(staking_timeframe as usize)
+        .copied()
+        .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))
+}
+
+/// When an unstake made at `timestamp` becomes claimable. During settlement it can be ended straight away.
+pub fn calc_claimable_at(staking_timeframe: StakingTimeframe, timestamp: i64, settlement_active: bool) -> LucraResult<i64> {
+    if settlement_active {
+        return Ok(timestamp);
+    }
+
+    timestamp
+        .checked_add(unstake_timelock(staking_timeframe)?)
+        .ok_or(math_err!())
+}
+
+pub fn claimable_at(pending_withdrawal: &PendingWithdrawal) -> i64 {
+    if pending_withdrawal.meta_data.version < UNSTAKE_TIMELOCK_VERSION {
+        return pending_withdrawal.end_timestamp;
+    }
+
+    pending_withdrawal.claimable_at
+}
+
+pub fn check_claimable(pending_withdrawal: &PendingWithdrawal, timestamp: i64) -> LucraResult {
+    check!(claimable_at(pending_withdrawal) <= timestamp, LucraErrorCode::Timelock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use bytemuck::Zeroable;
+    use crate::state::{DataType, MetaData};
+
+    const NOW: i64 = 1_650_000_000;
+
+    fn pending_withdrawal(staking_timeframe: StakingTimeframe) -> PendingWithdrawal {
+        let claimable_at = calc_claimable_at(staking_timeframe, NOW, false).unwrap();
+        let mut pending_withdrawal = PendingWithdrawal::zeroed();
+        pending_withdrawal.meta_data = MetaData::new(DataType::PendingWithdrawal, UNSTAKE_TIMELOCK_VERSION, true);
+        pending_withdrawal.start_timestamp = NOW;
+        pending_withdrawal.end_timestamp = claimable_at;
+        pending_withdrawal.claimable_at = claimable_at;
+        pending_withdrawal
+    }
+
+    fn assert_claimable_after(staking_timeframe: StakingTimeframe, timelock: i64) {
+        assert_eq!(unstake_timelock(staking_timeframe).unwrap(), timelock);
+
+        let pending_withdrawal = pending_withdrawal(staking_timeframe);
+        assert_eq!(claimable_at(&pending_withdrawal), NOW + timelock);
+        assert!(matches!(
+            check_claimable(&pending_withdrawal, NOW + timelock - 1).unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::Timelock, .. }
+        ));
+        assert!(check_claimable(&pending_withdrawal, NOW + timelock).is_ok());
+    }
+
+    #[test]
+    fn test_weekly_stake_is_claimable_after_a_week() {
+        assert_claimable_after(StakingTimeframe::try_from(0).unwrap(), 604_800);
+    }
+
+    #[test]
+    fn test_monthly_stake_is_claimable_after_a_month() {
+        assert_claimable_after(StakingTimeframe::try_from(1).unwrap(), 2_592_000);
+    }
+
+    #[test]
+    fn test_yearly_stake_is_claimable_after_a_year() {
+        assert_claimable_after(StakingTimeframe::try_from(2).unwrap(), 31_536_000);
+    }
+
+    #[test]
+    fn test_every_timeframe_has_a_timelock() {
+        let mut timeframes = 0;
+        while let Ok(staking_timeframe) = StakingTimeframe::try_from(timeframes) {
+            assert!(unstake_timelock(staking_timeframe).unwrap() > 0);
+            timeframes += 1;
+        }
+
+        assert_eq!(timeframes as usize, UNSTAKE_TIMELOCKS.len());
+    }
+
+    #[test]
+    fn test_settlement_skips_the_timelock() {
+        let staking_timeframe = StakingTimeframe::try_from(2).unwrap();
+
+        assert_eq!(calc_claimable_at(staking_timeframe, NOW, true).unwrap(), NOW);
+    }
+
+    #[test]
+    fn test_older_withdrawals_are_claimable_at_their_end_timestamp() {
+        // claimable_at reads as zero on withdrawals opened before it was recorded
+        let mut pending_withdrawal = pending_withdrawal(StakingTimeframe::try_from(2).unwrap());
+        pending_withdrawal.meta_data.version = 0;
+        pending_withdrawal.end_timestamp = NOW + 604_800;
+        pending_withdrawal.claimable_at = 0;
+
+        assert_eq!(claimable_at(&pending_withdrawal), NOW + 604_800);
+        assert!(check_claimable(&pending_withdrawal, NOW).is_err());
+        assert!(check_claimable(&pending_withdrawal, NOW + 604_800).is_ok());
+    }
+}
@@ -0,0 +1,153 @@
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use solana_program::native_token::LAMPORTS_PER_SOL;
+use crate::{
+    error::{
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::{LAMPORTS_PER_LUCRA, LAMPORTS_PER_MATA},
+    lucra_core::quote::calc_msol_lamport_value,
+    state::TvlComponents,
+};
+
+declare_check_assert_macros!(SourceFileId::Valuation);
+
+// Dollar values of what the protocol holds, used by ComputeTvl and by anything checking the vaults against
+// the books. Values are in micro dollars, the 6 decimals mata is minted with, and round down.
+
+/// Token balances of every holding that counts towards the TVL
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TvlBalances {
+    pub msol_vault_msol: u64,
+    pub arb_coffer_msol: u64,
+    pub rewards_vault_msol: u64,
+    pub wsol_holding_vault_lamports: u64,
+    pub mata_holding_vault_mata: u64,
+    pub lucra_holding_vault_lucra: u64,
+}
+
+/// Oracle prices in dollars per whole token, the msol rate in SOL per msol
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TvlPrices {
+    pub marinade_rate: Decimal,
+    pub sol_price: Decimal,
+    pub mata_price: Decimal,
+    pub lucra_price: Decimal,
+}
+
+fn calc_usd_value(amount: u64, base_units_per_token: Decimal, price: Decimal) -> LucraResult<u64> {
+    Decimal::from(amount)
+        .checked_mul(price)
+        .ok_or(math_err!())?
+        .checked_mul(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .checked_div(base_units_per_token)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+pub fn calc_lamports_usd_value(lamports: u64, sol_price: Decimal) -> LucraResult<u64> {
+    calc_usd_value(lamports, Decimal::from(LAMPORTS_PER_SOL), sol_price)
+}
+
+/// msol is valued at what marinade would redeem it for, not at its market price
+pub fn calc_msol_usd_value(msol_amount: u64, marinade_rate: Decimal, sol_price: Decimal) -> LucraResult<u64> {
+    calc_lamports_usd_value(calc_msol_lamport_value(msol_amount, marinade_rate)?, sol_price)
+}
+
+pub fn calc_mata_usd_value(mata_amount: u64, mata_price: Decimal) -> LucraResult<u64> {
+    calc_usd_value(mata_amount, LAMPORTS_PER_MATA, mata_price)
+}
+
+pub fn calc_lucra_usd_value(lucra_amount: u64, lucra_price: Decimal) -> LucraResult<u64> {
+    calc_usd_value(lucra_amount, LAMPORTS_PER_LUCRA, lucra_price)
+}
+
+/// A dollar price as an integer with 6 decimals, the way the price history stores it
+pub fn calc_micro_usd_price(price: Decimal) -> LucraResult<u64> {
+    price
+        .checked_mul(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+pub fn calc_tvl_components(balances: &TvlBalances, prices: &TvlPrices) -> LucraResult<TvlComponents> {
+    Ok(TvlComponents {
+        msol_vault: calc_msol_usd_value(balances.msol_vault_msol, prices.marinade_rate, prices.sol_price)?,
+        arb_coffer: calc_msol_usd_value(balances.arb_coffer_msol, prices.marinade_rate, prices.sol_price)?,
+        rewards_vault: calc_msol_usd_value(balances.rewards_vault_msol, prices.marinade_rate, prices.sol_price)?,
+        wsol_holding_vault: calc_lamports_usd_value(balances.wsol_holding_vault_lamports, prices.sol_price)?,
+        mata_holding_vault: calc_mata_usd_value(balances.mata_holding_vault_mata, prices.mata_price)?,
+        lucra_holding_vault: calc_lucra_usd_value(balances.lucra_holding_vault_lucra, prices.lucra_price)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use crate::state::TvlSnapshot;
+
+    fn fixture_prices() -> TvlPrices {
+        TvlPrices {
+            marinade_rate: dec!(1.1),
+            sol_price: dec!(40),
+            mata_price: dec!(0.99),
+            lucra_price: dec!(0.25),
+        }
+    }
+
+    #[test]
+    fn test_values_are_in_micro_dollars() {
+        assert_eq!(calc_lamports_usd_value(LAMPORTS_PER_SOL, dec!(40)).unwrap(), 40_000_000);
+        assert_eq!(calc_msol_usd_value(LAMPORTS_PER_SOL, dec!(1.1), dec!(40)).unwrap(), 44_000_000);
+        assert_eq!(calc_mata_usd_value(2_000_000, dec!(0.99)).unwrap(), 1_980_000);
+        assert_eq!(calc_lucra_usd_value(4_000_000_000, dec!(0.25)).unwrap(), 1_000_000);
+        assert_eq!(calc_micro_usd_price(dec!(40.1234567)).unwrap(), 40_123_456);
+
+        // Dust under a micro dollar rounds away
+        assert_eq!(calc_lamports_usd_value(24, dec!(40)).unwrap(), 0);
+        assert_eq!(calc_lamports_usd_value(25, dec!(40)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_components_match_each_holding() {
+        let balances = TvlBalances {
+            msol_vault_msol: 1_000 * LAMPORTS_PER_SOL,
+            arb_coffer_msol: 50 * LAMPORTS_PER_SOL,
+            rewards_vault_msol: 10 * LAMPORTS_PER_SOL,
+            wsol_holding_vault_lamports: 2 * LAMPORTS_PER_SOL,
+            mata_holding_vault_mata: 300_000_000,
+            lucra_holding_vault_lucra: 8_000_000_000_000,
+        };
+        let components = calc_tvl_components(&balances, &fixture_prices()).unwrap();
+
+        assert_eq!(components, TvlComponents {
+            msol_vault: 44_000_000_000,
+            arb_coffer: 2_200_000_000,
+            rewards_vault: 440_000_000,
+            wsol_holding_vault: 80_000_000,
+            mata_holding_vault: 297_000_000,
+            lucra_holding_vault: 2_000_000_000,
+        });
+
+        // Summed by hand: $44,000 + $2,200 + $440 + $80 + $297 + $2,000
+        let mut snapshot = TvlSnapshot::default();
+        snapshot.record(components, 40_000_000, 990_000, 250_000, 1).unwrap();
+        assert_eq!(snapshot.total, 49_017_000_000);
+    }
+
+    #[test]
+    fn test_empty_holdings_are_worth_nothing() {
+        let components = calc_tvl_components(&TvlBalances::default(), &fixture_prices()).unwrap();
+
+        assert_eq!(components, TvlComponents::default());
+        assert_eq!(components.total().unwrap(), 0);
+    }
+}
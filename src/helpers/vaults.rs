@@ -0,0 +1,168 @@
+use solana_program::pubkey::Pubkey;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::wsol,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::Vaults);
+
+// Protocol owned accounts are passed in by the caller. Every one of them has to match the address
+// recorded in the system state or funds can be redirected to an account the caller controls.
+
+pub fn verify_msol_vault(system_state: &SystemState, msol_vault: &Pubkey) -> LucraResult {
+    check_eq!(&system_state.msol_vault.address, msol_vault, LucraErrorCode::InvalidAccountInput)
+}
+
+pub fn verify_arb_coffer(system_state: &SystemState, arb_coffer: &Pubkey) -> LucraResult {
+    check_eq!(&system_state.arb_coffer.address, arb_coffer, LucraErrorCode::InvalidAccountInput)
+}
+
+pub fn verify_rewards_vault(system_state: &SystemState, rewards_vault: &Pubkey) -> LucraResult {
+    check_eq!(&system_state.rewards_vault.address, rewards_vault, LucraErrorCode::InvalidAccountInput)
+}
+
+pub fn verify_reward_mint(system_state: &SystemState, reward_mint: &Pubkey) -> LucraResult {
+    check_eq!(&system_state.reward_mint.address, reward_mint, LucraErrorCode::InvalidAccountInput)
+}
+
+pub fn verify_lp_vault(system_state: &SystemState, lp_vault: &Pubkey) -> LucraResult {
+    check_eq!(&system_state.lp_vault.address, lp_vault, LucraErrorCode::InvalidAccountInput)
+}
+
+pub fn verify_sol_vault(system_state: &SystemState, sol_vault: &Pubkey) -> LucraResult {
+    check_eq!(&system_state.sol_vault.address, sol_vault, LucraErrorCode::InvalidAccountInput)
+}
+
+pub fn verify_mata_mint(system_state: &SystemState, mata_mint: &Pubkey) -> LucraResult {
+    check_eq!(&system_state.mata_mint.address, mata_mint, LucraErrorCode::InvalidAccountInput)
+}
+
+pub fn verify_wsol_mint(wsol_mint: &Pubkey) -> LucraResult {
+    check_eq!(&wsol::id(), wsol_mint, LucraErrorCode::InvalidAccountInput)
+}
+
+/// The arb layouts take the mata and wsol mints side by side, each is checked on its own so swapping them fails
+pub fn verify_arb_mints(system_state: &SystemState, mata_mint: &Pubkey, wsol_mint: &Pubkey) -> LucraResult {
+    verify_mata_mint(system_state, mata_mint)?;
+    verify_wsol_mint(wsol_mint)
+}
+
+/// The vault's msol no longer matching the books is reported as such instead of as a failed token transfer
+pub fn verify_msol_vault_covers(vault_msol: u64, msol_amount: u64) -> LucraResult {
+    invariant!(msol_amount <= vault_msol, LucraErrorCode::MsolVaultShortfall)
+}
+
+/// Reward tokens redeemed for msol are paid out of what the drops left in the rewards vault, running it dry
+/// is the caller asking for too much rather than the books being off
+pub fn verify_rewards_vault_covers(rewards_vault_msol: u64, msol_owed: u64) -> LucraResult {
+    check!(msol_owed <= rewards_vault_msol, LucraErrorCode::InvalidAmount)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::error::LucraError;
+
+    pub fn system_state_with_vaults() -> SystemState {
+        let mut system_state = SystemState::default();
+        system_state.msol_vault.address = Pubkey::new_unique();
+        system_state.arb_coffer.address = Pubkey::new_unique();
+        system_state.rewards_vault.address = Pubkey::new_unique();
+        system_state.reward_mint.address = Pubkey::new_unique();
+        system_state.mata_mint.address = Pubkey::new_unique();
+        system_state.lp_vault.address = Pubkey::new_unique();
+        system_state.sol_vault.address = Pubkey::new_unique();
+        system_state
+    }
+
+    pub fn assert_invalid_account_input(result: LucraResult) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::InvalidAccountInput,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_recorded_vaults_are_accepted() {
+        let system_state = system_state_with_vaults();
+
+        assert!(verify_msol_vault(&system_state, &system_state.msol_vault.address).is_ok());
+        assert!(verify_arb_coffer(&system_state, &system_state.arb_coffer.address).is_ok());
+        assert!(verify_rewards_vault(&system_state, &system_state.rewards_vault.address).is_ok());
+        assert!(verify_reward_mint(&system_state, &system_state.reward_mint.address).is_ok());
+        assert!(verify_mata_mint(&system_state, &system_state.mata_mint.address).is_ok());
+        assert!(verify_lp_vault(&system_state, &system_state.lp_vault.address).is_ok());
+        assert!(verify_sol_vault(&system_state, &system_state.sol_vault.address).is_ok());
+    }
+
+    #[test]
+    fn test_attacker_accounts_are_rejected() {
+        let system_state = system_state_with_vaults();
+        let attacker = Pubkey::new_unique();
+
+        assert_invalid_account_input(verify_msol_vault(&system_state, &attacker));
+        assert_invalid_account_input(verify_arb_coffer(&system_state, &attacker));
+        assert_invalid_account_input(verify_rewards_vault(&system_state, &attacker));
+        assert_invalid_account_input(verify_reward_mint(&system_state, &attacker));
+        assert_invalid_account_input(verify_mata_mint(&system_state, &attacker));
+        assert_invalid_account_input(verify_lp_vault(&system_state, &attacker));
+        assert_invalid_account_input(verify_sol_vault(&system_state, &attacker));
+    }
+
+    #[test]
+    fn test_vaults_are_not_interchangeable() {
+        let system_state = system_state_with_vaults();
+
+        // The coffer and rewards vault hold msol too, so they must not pass for one another
+        assert_invalid_account_input(verify_arb_coffer(&system_state, &system_state.rewards_vault.address));
+        assert_invalid_account_input(verify_rewards_vault(&system_state, &system_state.arb_coffer.address));
+        assert_invalid_account_input(verify_msol_vault(&system_state, &system_state.arb_coffer.address));
+        assert_invalid_account_input(verify_reward_mint(&system_state, &system_state.mata_mint.address));
+        assert_invalid_account_input(verify_lp_vault(&system_state, &system_state.msol_vault.address));
+        assert_invalid_account_input(verify_sol_vault(&system_state, &system_state.msol_vault.address));
+    }
+
+    #[test]
+    fn test_rewards_vault_has_to_cover_the_redemption() {
+        assert!(verify_rewards_vault_covers(1_000, 1_000).is_ok());
+        assert!(verify_rewards_vault_covers(1_000, 0).is_ok());
+        assert!(matches!(
+            verify_rewards_vault_covers(1_000, 1_001).unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::InvalidAmount, .. }
+        ));
+    }
+
+    #[test]
+    fn test_swapped_arb_mints_are_rejected() {
+        let system_state = system_state_with_vaults();
+        let mata_mint = system_state.mata_mint.address;
+
+        assert!(verify_arb_mints(&system_state, &mata_mint, &wsol::id()).is_ok());
+        assert_invalid_account_input(verify_arb_mints(&system_state, &wsol::id(), &mata_mint));
+        assert_invalid_account_input(verify_arb_mints(&system_state, &mata_mint, &mata_mint));
+        assert_invalid_account_input(verify_arb_mints(&system_state, &wsol::id(), &wsol::id()));
+        assert_invalid_account_input(verify_wsol_mint(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn test_msol_vault_shortfall_is_reported() {
+        assert!(verify_msol_vault_covers(10, 10).is_ok());
+        assert!(verify_msol_vault_covers(10, 0).is_ok());
+        assert!(matches!(
+            verify_msol_vault_covers(9, 10).unwrap_err(),
+            LucraError::Invariant {
+                lucra_error_code: LucraErrorCode::MsolVaultShortfall,
+                ..
+            }
+        ));
+    }
+}
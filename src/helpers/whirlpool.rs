@@ -0,0 +1,493 @@
+use arrayref::array_ref;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use solana_program::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        account::verify_program_account,
+        constants::orca_whirlpool,
+        math::get_no_fee_amount,
+        spl::{get_mint_decimals, verify_balanced_pool},
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::Whirlpool);
+
+// Orca's concentrated liquidity pools. The account holds the square root of the price of A in B as a Q64.64 and
+// only the liquidity of the current tick range, the rest sits in the tick arrays a swap walks through. Whirlpools
+// order their mints by key, so which side SOL is on depends on the other mint.
+
+pub const WHIRLPOOL_LEN: usize = 653;
+const WHIRLPOOL_DISCRIMINATOR: [u8; 8] = [63, 149, 209, 12, 225, 128, 99, 9];
+const TICK_SPACING_OFFSET: usize = 41;
+const FEE_RATE_OFFSET: usize = 45;
+const LIQUIDITY_OFFSET: usize = 49;
+const SQRT_PRICE_OFFSET: usize = 65;
+const TICK_CURRENT_INDEX_OFFSET: usize = 81;
+const TOKEN_MINT_A_OFFSET: usize = 101;
+const TOKEN_VAULT_A_OFFSET: usize = 133;
+const TOKEN_MINT_B_OFFSET: usize = 181;
+const TOKEN_VAULT_B_OFFSET: usize = 213;
+
+const SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+const TICK_ARRAY_SEED: &[u8] = b"tick_array";
+const ORACLE_SEED: &[u8] = b"oracle";
+
+pub const TICK_ARRAY_SIZE: i32 = 88;
+pub const MIN_SQRT_PRICE_X64: u128 = 4_295_048_016;
+pub const MAX_SQRT_PRICE_X64: u128 = 79_226_673_515_401_279_992_447_579_055;
+// The fee rate is in hundredths of a bip
+const FEE_RATE_DENOMINATOR: u64 = 1_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WhirlpoolState {
+    pub tick_spacing: u16,
+    pub fee_rate: u16,
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current_index: i32,
+    pub token_mint_a: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_vault_b: Pubkey,
+}
+
+pub fn decode_whirlpool(data: &[u8]) -> LucraResult<WhirlpoolState> {
+    check_eq!(data.len(), WHIRLPOOL_LEN, LucraErrorCode::InvalidAccountInput)?;
+    check!(array_ref![data, 0, 8] == &WHIRLPOOL_DISCRIMINATOR, LucraErrorCode::InvalidAccountInput)?;
+
+    Ok(WhirlpoolState {
+        tick_spacing: u16::from_le_bytes(*array_ref![data, TICK_SPACING_OFFSET, 2]),
+        fee_rate: u16::from_le_bytes(*array_ref![data, FEE_RATE_OFFSET, 2]),
+        liquidity: u128::from_le_bytes(*array_ref![data, LIQUIDITY_OFFSET, 16]),
+        sqrt_price_x64: u128::from_le_bytes(*array_ref![data, SQRT_PRICE_OFFSET, 16]),
+        tick_current_index: i32::from_le_bytes(*array_ref![data, TICK_CURRENT_INDEX_OFFSET, 4]),
+        token_mint_a: Pubkey::new_from_array(*array_ref![data, TOKEN_MINT_A_OFFSET, 32]),
+        token_vault_a: Pubkey::new_from_array(*array_ref![data, TOKEN_VAULT_A_OFFSET, 32]),
+        token_mint_b: Pubkey::new_from_array(*array_ref![data, TOKEN_MINT_B_OFFSET, 32]),
+        token_vault_b: Pubkey::new_from_array(*array_ref![data, TOKEN_VAULT_B_OFFSET, 32]),
+    })
+}
+
+pub fn load_whirlpool(whirlpool_ai: &AccountInfo) -> LucraResult<WhirlpoolState> {
+    check_eq!(whirlpool_ai.owner, &orca_whirlpool::id(), LucraErrorCode::InvalidAccountOwner)?;
+    decode_whirlpool(&whirlpool_ai.try_borrow_data()?)
+}
+
+// Decimal holds 96 bits, every sqrt price a whirlpool can reach fits
+fn to_decimal(value: u128) -> LucraResult<Decimal> {
+    if value >> 96 != 0 {
+        return Err(math_err!());
+    }
+
+    Ok(Decimal::from(value))
+}
+
+fn calc_sqrt_price(sqrt_price_x64: u128) -> LucraResult<Decimal> {
+    check!(
+        (MIN_SQRT_PRICE_X64..=MAX_SQRT_PRICE_X64).contains(&sqrt_price_x64),
+        LucraErrorCode::InvalidAmount
+    )?;
+
+    to_decimal(sqrt_price_x64)?
+        .checked_div(Decimal::from(1_u128 << 64))
+        .ok_or(math_err!())
+}
+
+/// Price of a whole token A in whole tokens B
+pub fn calc_whirlpool_price(sqrt_price_x64: u128, decimals_a: u8, decimals_b: u8) -> LucraResult<Decimal> {
+    let sqrt_price = calc_sqrt_price(sqrt_price_x64)?;
+    let price = sqrt_price
+        .checked_mul(sqrt_price)
+        .ok_or(math_err!())?;
+
+    let scale = Decimal::from(10_u64
+        .checked_pow(u32::from(decimals_a.max(decimals_b) - decimals_a.min(decimals_b)))
+        .ok_or(math_err!())?);
+    if decimals_a >= decimals_b {
+        price.checked_mul(scale).ok_or(math_err!())
+    } else {
+        price.checked_div(scale).ok_or(math_err!())
+    }
+}
+
+/// Ok(true) when the base mint is the pool's token A, with the mints in either order
+pub fn verify_whirlpool_has_proper_mints(whirlpool: &WhirlpoolState, base_mint: &Pubkey, quote_mint: &Pubkey) -> LucraResult<bool> {
+    if &whirlpool.token_mint_a == base_mint && &whirlpool.token_mint_b == quote_mint {
+        Ok(true)
+    } else if &whirlpool.token_mint_a == quote_mint && &whirlpool.token_mint_b == base_mint {
+        Ok(false)
+    } else {
+        Err(throw_err!(LucraErrorCode::InvalidAccountInput))
+    }
+}
+
+/// Price of a whole base token in quote tokens, the same way round as the constant product pool prices
+pub fn get_whirlpool_price(whirlpool: &WhirlpoolState, base_mint_ai: &AccountInfo, quote_mint_ai: &AccountInfo) -> LucraResult<Decimal> {
+    let base_is_a = verify_whirlpool_has_proper_mints(whirlpool, base_mint_ai.key, quote_mint_ai.key)?;
+    let base_decimals = get_mint_decimals(base_mint_ai)?;
+    let quote_decimals = get_mint_decimals(quote_mint_ai)?;
+
+    if base_is_a {
+        calc_whirlpool_price(whirlpool.sqrt_price_x64, base_decimals, quote_decimals)
+    } else {
+        Decimal::ONE
+            .checked_div(calc_whirlpool_price(whirlpool.sqrt_price_x64, quote_decimals, base_decimals)?)
+            .ok_or(math_err!())
+    }
+}
+
+// Verify that the pool is balanced to an acceptable tolerance
+pub fn verify_whirlpool_is_balanced(
+    whirlpool_ai: &AccountInfo,
+    base_mint_ai: &AccountInfo,
+    quote_mint_ai: &AccountInfo,
+    desired_price: Decimal,
+) -> LucraResult {
+    let whirlpool = load_whirlpool(whirlpool_ai)?;
+    let new_price = get_whirlpool_price(&whirlpool, base_mint_ai, quote_mint_ai)?;
+
+    let tolerance = Decimal::new(1, 3);
+    verify_balanced_pool(
+        new_price,
+        desired_price,
+        tolerance,
+    )
+}
+
+/// First tick of the tick array `tick_index` is in, they start at multiples of 88 tick spacings
+pub fn calc_tick_array_start_index(tick_index: i32, tick_spacing: u16) -> LucraResult<i32> {
+    check!(tick_spacing > 0, LucraErrorCode::InvalidAccountInput)?;
+    let ticks_in_array = i32::from(tick_spacing) * TICK_ARRAY_SIZE;
+
+    Ok(tick_index.div_euclid(ticks_in_array) * ticks_in_array)
+}
+
+pub fn find_tick_array(whirlpool: &Pubkey, start_tick_index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[TICK_ARRAY_SEED, whirlpool.as_ref(), start_tick_index.to_string().as_bytes()],
+        &orca_whirlpool::id(),
+    ).0
+}
+
+pub fn find_whirlpool_oracle(whirlpool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ORACLE_SEED, whirlpool.as_ref()], &orca_whirlpool::id()).0
+}
+
+/// The three tick arrays a swap walks through from the current price, down for A to B and up for B to A. Going
+/// up the price can sit on the last tick of an array, the swap starts from the next one like Orca's SDK does.
+pub fn calc_swap_tick_arrays(whirlpool_key: &Pubkey, whirlpool: &WhirlpoolState, a_to_b: bool) -> LucraResult<[Pubkey; 3]> {
+    let shift = if a_to_b { 0 } else { i32::from(whirlpool.tick_spacing) };
+    let start = calc_tick_array_start_index(whirlpool.tick_current_index + shift, whirlpool.tick_spacing)?;
+    let step = i32::from(whirlpool.tick_spacing) * TICK_ARRAY_SIZE * if a_to_b { -1 } else { 1 };
+
+    Ok([
+        find_tick_array(whirlpool_key, start),
+        find_tick_array(whirlpool_key, start + step),
+        find_tick_array(whirlpool_key, start + 2 * step),
+    ])
+}
+
+/// A wrong tick array only fails deep inside the whirlpool program, this fails it up front
+pub fn verify_swap_tick_arrays(whirlpool_key: &Pubkey, whirlpool: &WhirlpoolState, a_to_b: bool, tick_arrays: [&Pubkey; 3]) -> LucraResult {
+    let expected = calc_swap_tick_arrays(whirlpool_key, whirlpool, a_to_b)?;
+    for (tick_array, expected) in tick_arrays.iter().zip(expected.iter()) {
+        check_eq!(*tick_array, expected, LucraErrorCode::InvalidAccountInput)?;
+    }
+
+    Ok(())
+}
+
+/// What `amount_in` swaps for inside the current tick range. Past it the liquidity changes, a swap that runs into
+/// thinner liquidity gets less and the slack under the estimate has to cover it.
+pub fn quote_swap_in_current_range(whirlpool: &WhirlpoolState, amount_in: u64, a_to_b: bool) -> LucraResult<u64> {
+    check!(whirlpool.liquidity > 0, LucraErrorCode::EmptyPool)?;
+    check!(u64::from(whirlpool.fee_rate) < FEE_RATE_DENOMINATOR, LucraErrorCode::InvalidAccountInput)?;
+
+    let liquidity = to_decimal(whirlpool.liquidity)?;
+    let sqrt_price = calc_sqrt_price(whirlpool.sqrt_price_x64)?;
+    let amount_in_less_fee = Decimal::from(amount_in)
+        .checked_mul(Decimal::from(FEE_RATE_DENOMINATOR - u64::from(whirlpool.fee_rate)))
+        .ok_or(math_err!())?
+        .checked_div(Decimal::from(FEE_RATE_DENOMINATOR))
+        .ok_or(math_err!())?;
+
+    let amount_out = if a_to_b {
+        // 1 / √P' = 1 / √P + Δa / L and Δb = L (√P - √P')
+        let next_sqrt_price = liquidity
+            .checked_mul(sqrt_price)
+            .ok_or(math_err!())?
+            .checked_div(liquidity
+                .checked_add(amount_in_less_fee.checked_mul(sqrt_price).ok_or(math_err!())?)
+                .ok_or(math_err!())?)
+            .ok_or(math_err!())?;
+        liquidity
+            .checked_mul(sqrt_price.checked_sub(next_sqrt_price).ok_or(math_err!())?)
+            .ok_or(math_err!())?
+    } else {
+        // √P' = √P + Δb / L and Δa = L (√P' - √P) / (√P √P')
+        let next_sqrt_price = sqrt_price
+            .checked_add(amount_in_less_fee.checked_div(liquidity).ok_or(math_err!())?)
+            .ok_or(math_err!())?;
+        liquidity
+            .checked_mul(next_sqrt_price.checked_sub(sqrt_price).ok_or(math_err!())?)
+            .ok_or(math_err!())?
+            .checked_div(sqrt_price.checked_mul(next_sqrt_price).ok_or(math_err!())?)
+            .ok_or(math_err!())?
+    };
+
+    amount_out.floor().to_u64().ok_or(math_err!())
+}
+
+fn pack_swap_data(amount: u64, other_amount_threshold: u64, sqrt_price_limit: u128, amount_specified_is_input: bool, a_to_b: bool) -> Vec<u8> {
+    let mut data = Vec::with_capacity(42);
+    data.extend_from_slice(&SWAP_DISCRIMINATOR);
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&other_amount_threshold.to_le_bytes());
+    data.extend_from_slice(&sqrt_price_limit.to_le_bytes());
+    data.push(amount_specified_is_input as u8);
+    data.push(a_to_b as u8);
+    data
+}
+
+/// Swaps all of `amount_in` from the owner's A account into its B account, or B into A. The swap is allowed to run
+/// to the end of the price range, the estimate less 3% is the least it has to pay out like the constant product
+/// pools.
+#[allow(clippy::too_many_arguments)]
+pub fn swap<'a>(
+    whirlpool_program_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+    token_authority_ai: &AccountInfo<'a>,
+    whirlpool_ai: &AccountInfo<'a>,
+    token_owner_account_a_ai: &AccountInfo<'a>,
+    token_vault_a_ai: &AccountInfo<'a>,
+    token_owner_account_b_ai: &AccountInfo<'a>,
+    token_vault_b_ai: &AccountInfo<'a>,
+    tick_array_0_ai: &AccountInfo<'a>,
+    tick_array_1_ai: &AccountInfo<'a>,
+    tick_array_2_ai: &AccountInfo<'a>,
+    oracle_ai: &AccountInfo<'a>,
+    amount_in: u64,
+    a_to_b: bool,
+    min_amount_out: u64,
+) -> LucraResult {
+    verify_program_account(whirlpool_program_ai, &orca_whirlpool::id())?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let whirlpool = load_whirlpool(whirlpool_ai)?;
+    check_eq!(token_vault_a_ai.key, &whirlpool.token_vault_a, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(token_vault_b_ai.key, &whirlpool.token_vault_b, LucraErrorCode::InvalidAccountInput)?;
+    verify_swap_tick_arrays(whirlpool_ai.key, &whirlpool, a_to_b, [tick_array_0_ai.key, tick_array_1_ai.key, tick_array_2_ai.key])?;
+    check_eq!(oracle_ai.key, &find_whirlpool_oracle(whirlpool_ai.key), LucraErrorCode::InvalidAccountInput)?;
+
+    let estimated_amount_out = quote_swap_in_current_range(&whirlpool, amount_in, a_to_b)?;
+    // The pool's own quote moves with a sandwich, the caller's bound doesn't
+    let other_amount_threshold = get_no_fee_amount(estimated_amount_out.into(), 3_i64.into(), 100_i64.into())?
+        .max(min_amount_out);
+    let sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE_X64 } else { MAX_SQRT_PRICE_X64 };
+
+    let instruction = Instruction {
+        program_id: orca_whirlpool::id(),
+        accounts: vec![
+            AccountMeta::new_readonly(*token_program_ai.key, false),
+            AccountMeta::new_readonly(*token_authority_ai.key, true),
+            AccountMeta::new(*whirlpool_ai.key, false),
+            AccountMeta::new(*token_owner_account_a_ai.key, false),
+            AccountMeta::new(*token_vault_a_ai.key, false),
+            AccountMeta::new(*token_owner_account_b_ai.key, false),
+            AccountMeta::new(*token_vault_b_ai.key, false),
+            AccountMeta::new(*tick_array_0_ai.key, false),
+            AccountMeta::new(*tick_array_1_ai.key, false),
+            AccountMeta::new(*tick_array_2_ai.key, false),
+            AccountMeta::new_readonly(*oracle_ai.key, false),
+        ],
+        data: pack_swap_data(amount_in, other_amount_threshold, sqrt_price_limit, true, a_to_b),
+    };
+
+    invoke(&instruction, &[
+        token_program_ai.clone(),
+        token_authority_ai.clone(),
+        whirlpool_ai.clone(),
+        token_owner_account_a_ai.clone(),
+        token_vault_a_ai.clone(),
+        token_owner_account_b_ai.clone(),
+        token_vault_b_ai.clone(),
+        tick_array_0_ai.clone(),
+        tick_array_1_ai.clone(),
+        tick_array_2_ai.clone(),
+        oracle_ai.clone(),
+        whirlpool_program_ai.clone(),
+    ]).map_err(|_| throw_err!(LucraErrorCode::TransactionFailed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use crate::helpers::vaults::tests::assert_invalid_account_input;
+
+    // SOL/USDC at $150, SOL is token A with 9 decimals and USDC token B with 6
+    const SOL_USDC_SQRT_PRICE_X64: u128 = 7_144_393_258_922_745_604;
+    // The same price with the mints the other way round, a USDC is 1/150 SOL
+    const USDC_SOL_SQRT_PRICE_X64: u128 = 47_629_288_392_818_304_031;
+
+    fn whirlpool_data(whirlpool: &WhirlpoolState) -> Vec<u8> {
+        let mut data = vec![0; WHIRLPOOL_LEN];
+        data[..8].copy_from_slice(&WHIRLPOOL_DISCRIMINATOR);
+        data[TICK_SPACING_OFFSET..TICK_SPACING_OFFSET + 2].copy_from_slice(&whirlpool.tick_spacing.to_le_bytes());
+        data[FEE_RATE_OFFSET..FEE_RATE_OFFSET + 2].copy_from_slice(&whirlpool.fee_rate.to_le_bytes());
+        data[LIQUIDITY_OFFSET..LIQUIDITY_OFFSET + 16].copy_from_slice(&whirlpool.liquidity.to_le_bytes());
+        data[SQRT_PRICE_OFFSET..SQRT_PRICE_OFFSET + 16].copy_from_slice(&whirlpool.sqrt_price_x64.to_le_bytes());
+        data[TICK_CURRENT_INDEX_OFFSET..TICK_CURRENT_INDEX_OFFSET + 4].copy_from_slice(&whirlpool.tick_current_index.to_le_bytes());
+        data[TOKEN_MINT_A_OFFSET..TOKEN_MINT_A_OFFSET + 32].copy_from_slice(whirlpool.token_mint_a.as_ref());
+        data[TOKEN_VAULT_A_OFFSET..TOKEN_VAULT_A_OFFSET + 32].copy_from_slice(whirlpool.token_vault_a.as_ref());
+        data[TOKEN_MINT_B_OFFSET..TOKEN_MINT_B_OFFSET + 32].copy_from_slice(whirlpool.token_mint_b.as_ref());
+        data[TOKEN_VAULT_B_OFFSET..TOKEN_VAULT_B_OFFSET + 32].copy_from_slice(whirlpool.token_vault_b.as_ref());
+        data
+    }
+
+    fn test_whirlpool(sqrt_price_x64: u128, liquidity: u128, fee_rate: u16) -> WhirlpoolState {
+        WhirlpoolState {
+            tick_spacing: 64,
+            fee_rate,
+            liquidity,
+            sqrt_price_x64,
+            tick_current_index: -20_000,
+            token_mint_a: Pubkey::new_unique(),
+            token_vault_a: Pubkey::new_unique(),
+            token_mint_b: Pubkey::new_unique(),
+            token_vault_b: Pubkey::new_unique(),
+        }
+    }
+
+    fn assert_close(price: Decimal, expected: Decimal) {
+        assert!((price - expected).abs() < dec!(0.000000001), "{} is not {}", price, expected);
+    }
+
+    #[test]
+    fn test_sqrt_price_to_price() {
+        // 2^64 is a price of one before the decimals
+        assert_eq!(calc_whirlpool_price(1 << 64, 6, 6).unwrap(), Decimal::ONE);
+        assert_eq!(calc_whirlpool_price(1 << 64, 9, 6).unwrap(), dec!(1000));
+        assert_eq!(calc_whirlpool_price(1 << 64, 6, 9).unwrap(), dec!(0.001));
+
+        assert_close(calc_whirlpool_price(SOL_USDC_SQRT_PRICE_X64, 9, 6).unwrap(), dec!(150));
+        assert_close(calc_whirlpool_price(USDC_SOL_SQRT_PRICE_X64, 6, 9).unwrap(), dec!(1) / dec!(150));
+    }
+
+    #[test]
+    fn test_sqrt_price_bounds() {
+        assert!(calc_whirlpool_price(MIN_SQRT_PRICE_X64, 6, 6).unwrap() > Decimal::ZERO);
+        assert!(calc_whirlpool_price(MAX_SQRT_PRICE_X64, 6, 6).is_ok());
+
+        // Nothing a whirlpool can hold, and more than Decimal fits
+        assert!(calc_whirlpool_price(MIN_SQRT_PRICE_X64 - 1, 6, 6).is_err());
+        assert!(calc_whirlpool_price(MAX_SQRT_PRICE_X64 + 1, 6, 6).is_err());
+        assert!(calc_whirlpool_price(u128::MAX, 6, 6).is_err());
+    }
+
+    #[test]
+    fn test_whirlpool_is_decoded() {
+        let whirlpool = test_whirlpool(SOL_USDC_SQRT_PRICE_X64, 1_000_000_000, 3_000);
+        let data = whirlpool_data(&whirlpool);
+        assert_eq!(decode_whirlpool(&data).unwrap(), whirlpool);
+
+        // Some other anchor account, or one cut short
+        let mut other = data.clone();
+        other[0] ^= 1;
+        assert_invalid_account_input(decode_whirlpool(&other).map(|_| ()));
+        assert_invalid_account_input(decode_whirlpool(&data[..WHIRLPOOL_LEN - 1]).map(|_| ()));
+    }
+
+    #[test]
+    fn test_whirlpool_mints_in_either_order() {
+        let whirlpool = test_whirlpool(SOL_USDC_SQRT_PRICE_X64, 1_000_000_000, 3_000);
+        let (a, b) = (whirlpool.token_mint_a, whirlpool.token_mint_b);
+
+        assert!(verify_whirlpool_has_proper_mints(&whirlpool, &a, &b).unwrap());
+        assert!(!verify_whirlpool_has_proper_mints(&whirlpool, &b, &a).unwrap());
+        assert_invalid_account_input(verify_whirlpool_has_proper_mints(&whirlpool, &a, &Pubkey::new_unique()).map(|_| ()));
+        assert_invalid_account_input(verify_whirlpool_has_proper_mints(&whirlpool, &a, &a).map(|_| ()));
+    }
+
+    #[test]
+    fn test_tick_array_start_index() {
+        // 64 tick spacing, 5632 ticks to an array
+        assert_eq!(calc_tick_array_start_index(0, 64).unwrap(), 0);
+        assert_eq!(calc_tick_array_start_index(5_631, 64).unwrap(), 0);
+        assert_eq!(calc_tick_array_start_index(5_632, 64).unwrap(), 5_632);
+        // Rounded down below zero too
+        assert_eq!(calc_tick_array_start_index(-1, 64).unwrap(), -5_632);
+        assert_eq!(calc_tick_array_start_index(-20_000, 64).unwrap(), -22_528);
+        assert!(calc_tick_array_start_index(0, 0).is_err());
+    }
+
+    #[test]
+    fn test_swap_tick_arrays_follow_the_price() {
+        let key = Pubkey::new_unique();
+        let mut whirlpool = test_whirlpool(SOL_USDC_SQRT_PRICE_X64, 1_000_000_000, 3_000);
+
+        let down = calc_swap_tick_arrays(&key, &whirlpool, true).unwrap();
+        assert_eq!(down, [find_tick_array(&key, -22_528), find_tick_array(&key, -28_160), find_tick_array(&key, -33_792)]);
+        let up = calc_swap_tick_arrays(&key, &whirlpool, false).unwrap();
+        assert_eq!(up, [find_tick_array(&key, -22_528), find_tick_array(&key, -16_896), find_tick_array(&key, -11_264)]);
+
+        verify_swap_tick_arrays(&key, &whirlpool, true, [&down[0], &down[1], &down[2]]).unwrap();
+        assert_invalid_account_input(verify_swap_tick_arrays(&key, &whirlpool, true, [&up[0], &up[1], &up[2]]));
+        assert_invalid_account_input(verify_swap_tick_arrays(&key, &whirlpool, true, [&down[0], &down[2], &down[1]]));
+
+        // On the last tick of an array a swap up starts from the next one
+        whirlpool.tick_current_index = -16_897;
+        assert_eq!(calc_swap_tick_arrays(&key, &whirlpool, true).unwrap()[0], find_tick_array(&key, -22_528));
+        assert_eq!(calc_swap_tick_arrays(&key, &whirlpool, false).unwrap()[0], find_tick_array(&key, -16_896));
+    }
+
+    #[test]
+    fn test_quote_in_current_range() {
+        // At a price of one, 0.1% of the liquidity in gets a little under that back either way
+        let whirlpool = test_whirlpool(1 << 64, 1_000_000_000, 0);
+        assert_eq!(quote_swap_in_current_range(&whirlpool, 1_000_000, true).unwrap(), 999_000);
+        assert_eq!(quote_swap_in_current_range(&whirlpool, 1_000_000, false).unwrap(), 999_000);
+
+        // The fee comes off the amount in first
+        let whirlpool = test_whirlpool(1 << 64, 1_000_000_000, 3_000);
+        assert_eq!(quote_swap_in_current_range(&whirlpool, 1_000_000, true).unwrap(), 996_006);
+        assert_eq!(quote_swap_in_current_range(&whirlpool, 1_000_000, false).unwrap(), 996_006);
+
+        let empty = test_whirlpool(1 << 64, 0, 3_000);
+        assert!(quote_swap_in_current_range(&empty, 1_000_000, true).is_err());
+    }
+
+    #[test]
+    fn test_quote_at_the_pool_price() {
+        // A small swap at $150 pays about the spot price, less the fee
+        let whirlpool = test_whirlpool(SOL_USDC_SQRT_PRICE_X64, 1_000_000_000_000_000, 3_000);
+        let usdc_out = quote_swap_in_current_range(&whirlpool, 1_000_000_000, true).unwrap();
+        assert!(usdc_out < 149_550_000 && usdc_out > 149_500_000);
+
+        let lamports_out = quote_swap_in_current_range(&whirlpool, 150_000_000, false).unwrap();
+        assert!(lamports_out < 997_000_000 && lamports_out > 996_500_000);
+    }
+
+    #[test]
+    fn test_swap_data_layout() {
+        let data = pack_swap_data(1_000, 970, MIN_SQRT_PRICE_X64, true, true);
+        assert_eq!(data.len(), 42);
+        assert_eq!(&data[..8], &SWAP_DISCRIMINATOR);
+        assert_eq!(u64::from_le_bytes(*array_ref![data, 8, 8]), 1_000);
+        assert_eq!(u64::from_le_bytes(*array_ref![data, 16, 8]), 970);
+        assert_eq!(u128::from_le_bytes(*array_ref![data, 24, 16]), MIN_SQRT_PRICE_X64);
+        assert_eq!(&data[40..], &[1, 1]);
+    }
+}
@@ -0,0 +1,2708 @@
+// Declarative description of every instruction layout the builders produce. The IDL in idl/lucra.json is
+// generated from these tables, the layout sized processors take their account counts from them and the
+// tests check every builder against them, so a layout can't change in one place without the others.
+//
+// Each entry is one builder, so variants with several layouts show up once per layout under the same
+// discriminant. The discriminant is the instruction's tag from `instruction::INSTRUCTION_TAGS`, instruction
+// data is the tag byte followed by the variant's bincode fields in order, strings are prefixed with a u64
+// length and options with a one byte tag. An `option<ty>` arg is written as Anchor's `{ "option": ty }`.
+//
+// State accounts are listed under "accounts" with the structs they embed under "types". Each field carries its
+// byte offset, the tests check every offset and size against the struct itself.
+
+use std::mem::size_of;
+
+use crate::state::{
+    CollateralRegistry,
+    CollateralType,
+    Event,
+    EventQueue,
+    Keeper,
+    KeeperRegistry,
+    LoanRegistry,
+    MetaData,
+    PenaltyHistory,
+    PenaltyHistoryEntry,
+    PriceCorrection,
+    PriceHistoryHeader,
+    RevenueEntry,
+    RevenueLedger,
+    TransferAllowlist,
+    TransferDestination,
+    TvlComponents,
+    TvlSnapshot,
+    EVENT_QUEUE_LENGTH,
+    MAX_COLLATERAL_TYPES,
+    MAX_KEEPERS,
+    PENALTY_HISTORY_LENGTH,
+    REVENUE_LEDGER_LENGTH,
+};
+use crate::helpers::constants::MAX_TRANSFER_DESTINATIONS;
+
+pub struct IdlAccount {
+    pub name: &'static str,
+    pub writable: bool,
+    pub signer: bool,
+    pub optional: bool,
+}
+
+impl IdlAccount {
+    const fn optional(self) -> Self {
+        IdlAccount { optional: true, ..self }
+    }
+}
+
+const fn writable(name: &'static str) -> IdlAccount {
+    IdlAccount { name, writable: true, signer: false, optional: false }
+}
+
+const fn readonly(name: &'static str) -> IdlAccount {
+    IdlAccount { name, writable: false, signer: false, optional: false }
+}
+
+const fn writable_signer(name: &'static str) -> IdlAccount {
+    IdlAccount { name, writable: true, signer: true, optional: false }
+}
+
+const fn readonly_signer(name: &'static str) -> IdlAccount {
+    IdlAccount { name, writable: false, signer: true, optional: false }
+}
+
+pub struct IdlArg {
+    pub name: &'static str,
+    pub ty: &'static str,
+}
+
+pub struct IdlInstruction {
+    pub name: &'static str,
+    pub discriminant: u8,
+    pub accounts: &'static [IdlAccount],
+    pub args: &'static [IdlArg],
+}
+
+pub const IDL_VERSION: &str = "0.3.0";
+
+pub const INITIALIZE: &[IdlAccount] = &[
+    readonly("marinade_state"),
+    readonly_signer("creator_authority"),
+    readonly("mata_mint"),
+    readonly("lucra_mint"),
+    readonly("reward_mint"),
+    readonly("staked_lucra_mint"),
+    writable("state"),
+    writable("arb_state"),
+    readonly("msol_vault"),
+    readonly("arb_coffer"),
+    readonly("rewards_vault"),
+    writable("staking_state"),
+    readonly("arb_fund"),
+    readonly("wsol_holding_vault"),
+    readonly("mata_holding_vault"),
+    readonly("lucra_holding_vault"),
+];
+
+pub const UPDATE_STATE: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("arb_state"),
+    readonly_signer("dao_authority"),
+];
+
+pub const CREATE_MATA_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    writable("mata_mint"),
+    readonly("mata_mint_authority"),
+    writable_signer("transfer_from"),
+    writable("user_mata_account"),
+    writable("user_msol_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_address"),
+    writable("liq_pool_msol_leg"),
+    readonly("liq_pool_msol_leg_authority"),
+    writable("reserve_address"),
+    readonly("msol_mint_authority"),
+    readonly("system_program"),
+    readonly("token_program"),
+    readonly("marinade_program"),
+    writable("loan_registry"),
+];
+
+pub const CREATE_MATA_LOAN_WITH_LOCKED_STAKE: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    writable("mata_mint"),
+    readonly("mata_mint_authority"),
+    writable_signer("transfer_from"),
+    writable("user_mata_account"),
+    writable("user_msol_account"),
+    writable("staking_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("lucra_sol_oracle"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_address"),
+    writable("liq_pool_msol_leg"),
+    readonly("liq_pool_msol_leg_authority"),
+    writable("reserve_address"),
+    readonly("msol_mint_authority"),
+    readonly("system_program"),
+    readonly("token_program"),
+    readonly("marinade_program"),
+    writable("loan_registry"),
+];
+
+pub const CLOSE_MATA_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("loan"),
+    writable_signer("user_account"),
+    writable("user_msol_account"),
+    writable("mata_mint"),
+    writable("user_mata_account"),
+    readonly("msol_vault_authority"),
+    writable("msol_vault"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_address"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    readonly("system_program"),
+    readonly("token_program"),
+    readonly("marinade_program"),
+];
+
+pub const CLOSE_MATA_LOAN_WITH_LOCKED_STAKE: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("loan"),
+    writable_signer("user_account"),
+    writable("user_msol_account"),
+    writable("mata_mint"),
+    writable("user_mata_account"),
+    readonly("msol_vault_authority"),
+    writable("msol_vault"),
+    writable("user_staking_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_address"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    readonly("system_program"),
+    readonly("token_program"),
+    readonly("marinade_program"),
+];
+
+pub const CREATE_LP_BACKED_MATA_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("sol_mata_pool"),
+    writable("loan"),
+    writable("lp_vault"),
+    writable("mata_mint"),
+    readonly("mata_mint_authority"),
+    writable_signer("transfer_from"),
+    writable("user_mata_account"),
+    writable("user_lp_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("pool_sol_vault"),
+    readonly("pool_mata_vault"),
+    readonly("lp_mint"),
+    readonly("token_program"),
+    readonly("system_program"),
+    writable("loan_registry"),
+];
+
+pub const CREATE_NATIVE_SOL_MATA_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("sol_vault"),
+    writable("loan"),
+    writable("mata_mint"),
+    readonly("mata_mint_authority"),
+    writable_signer("user_account"),
+    writable("user_mata_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("system_program"),
+    readonly("token_program"),
+    writable("loan_registry"),
+];
+
+pub const CLOSE_NATIVE_SOL_MATA_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("loan"),
+    writable_signer("user_account"),
+    writable("mata_mint"),
+    writable("user_mata_account"),
+    writable("sol_vault"),
+    readonly("token_program"),
+];
+
+pub const CLOSE_LP_BACKED_MATA_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("loan"),
+    writable_signer("user_account"),
+    writable("user_lp_account"),
+    writable("mata_mint"),
+    writable("user_mata_account"),
+    readonly("lp_vault_authority"),
+    writable("lp_vault"),
+    readonly("token_program"),
+];
+
+pub const CREATE_STAKING_ACCOUNT: &[IdlAccount] = &[
+    readonly("system_state"),
+    readonly("staking_state"),
+    writable("staking_account"),
+    readonly_signer("owner"),
+    readonly("recovery_key").optional(),
+];
+
+pub const CREATE_STAKE_BALANCE: &[IdlAccount] = &[
+    readonly("system_state"),
+    readonly("staking_state"),
+    writable("stake_balance"),
+    readonly_signer("owner"),
+    readonly("deposit_vault"),
+    readonly("stake_vault"),
+    readonly("pending_vault"),
+    readonly("staking_account"),
+];
+
+pub const DEPOSIT_STAKE: &[IdlAccount] = &[
+    readonly("system_state"),
+    readonly("stake_balance"),
+    writable("from_account"),
+    writable("to_account"),
+    readonly_signer("owner"),
+    readonly("token_program"),
+];
+
+pub const STAKE: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("staking_state"),
+    writable("staking_account"),
+    writable("stake_balance"),
+    writable("deposit_vault"),
+    writable("stake_vault"),
+    readonly_signer("owner"),
+    readonly("stake_balance_authority"),
+    writable("staked_lucra_mint"),
+    writable("staked_lucra_account"),
+    readonly("staked_lucra_mint_authority"),
+    readonly("token_program"),
+];
+
+pub const START_UNSTAKE: &[IdlAccount] = &[
+    readonly("system_state"),
+    readonly("staking_state"),
+    writable("staking_account"),
+    writable("stake_balance"),
+    writable("staked_lucra_mint"),
+    writable("staked_lucra_account"),
+    readonly_signer("owner"),
+    writable("stake_vault"),
+    writable("pending_vault"),
+    readonly("stake_balance_authority"),
+    writable("pending_withdrawal"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("token_program"),
+];
+
+pub const END_UNSTAKE: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("pending_withdrawal"),
+    readonly("stake_balance"),
+    writable("pending_vault"),
+    writable("deposit_vault"),
+    writable_signer("owner"),
+    readonly("stake_balance_authority"),
+    readonly("token_program"),
+];
+
+pub const WITHDRAW_STAKE: &[IdlAccount] = &[
+    readonly("system_state"),
+    readonly("staking_account"),
+    writable("stake_balance"),
+    writable("deposit_vault"),
+    readonly("stake_vault"),
+    readonly("pending_vault"),
+    writable("to_account"),
+    writable_signer("owner"),
+    readonly("stake_balance_authority"),
+    readonly("token_program"),
+];
+
+pub const CLAIM_REWARD: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("staking_state"),
+    readonly("staking_account"),
+    writable("stake_balance"),
+    writable("reward"),
+    writable("staked_lucra_account"),
+    writable("lucra_vault"),
+    writable("lucra_account"),
+    writable("rewards_vault"),
+    writable("msol_account"),
+    readonly("rewards_vault_authority"),
+    writable("lucra_mint"),
+    readonly("lucra_mint_authority"),
+    readonly("token_program"),
+];
+
+pub const DROP_REWARD: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("staking_state"),
+    readonly("marinade_state"),
+    writable("reward"),
+    readonly("staked_lucra_mint"),
+    readonly("msol_vault"),
+    writable("rewards_vault"),
+    writable("arb_coffer"),
+    readonly("msol_vault_transfer_authority"),
+    writable("user_reward_account"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("token_program"),
+];
+
+pub const TRANSFER_FUNDS: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("dao_authority"),
+    writable("from_account"),
+    writable("to_account"),
+    readonly("msol_vault_authority"),
+    readonly("token_program"),
+    readonly("transfer_allowlist"),
+];
+
+pub const CREATE_PRICE_HISTORY: &[IdlAccount] = &[
+    readonly_signer("creator_authority"),
+    writable("price_history"),
+];
+
+pub const UPDATE_PRICE_HISTORY: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("price_history"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("user_reward_account"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("token_program"),
+    writable("arb_state"),
+];
+
+pub const REDEEM_REWARD_TOKENS: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("user_reward_account"),
+    writable("user_lucra_reward_account"),
+    readonly_signer("user_authority"),
+    writable("reward_mint"),
+    writable("lucra_mint"),
+    readonly("lucra_mint_authority"),
+    readonly("lucra_sol_oracle"),
+    readonly("token_program"),
+];
+
+pub const REDEEM_REWARD_TOKENS_FOR_MSOL: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("user_reward_account"),
+    writable("user_msol_account"),
+    readonly_signer("user_authority"),
+    writable("reward_mint"),
+    writable("rewards_vault"),
+    readonly("rewards_vault_transfer_authority"),
+    readonly("marinade_state"),
+    readonly("lucra_sol_oracle"),
+    readonly("token_program"),
+];
+
+pub const ADD_COLLATERAL: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    writable_signer("owner"),
+    writable("user_msol_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_address"),
+    writable("liq_pool_msol_leg"),
+    readonly("liq_pool_msol_leg_authority"),
+    writable("reserve_address"),
+    readonly("msol_mint_authority"),
+    readonly("system_program"),
+    readonly("token_program"),
+    readonly("marinade_program"),
+];
+
+pub const ADD_COLLATERAL_WITH_LOCKED_STAKE: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    writable_signer("owner"),
+    writable("user_msol_account"),
+    writable("staking_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_address"),
+    writable("liq_pool_msol_leg"),
+    readonly("liq_pool_msol_leg_authority"),
+    writable("reserve_address"),
+    readonly("msol_mint_authority"),
+    readonly("system_program"),
+    readonly("token_program"),
+    readonly("marinade_program"),
+];
+
+pub const ADD_COLLATERAL_MSOL: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    writable_signer("owner"),
+    writable("user_msol_account"),
+    readonly("token_program"),
+];
+
+pub const ADD_COLLATERAL_MSOL_WITH_LOCKED_STAKE: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    writable_signer("owner"),
+    writable("user_msol_account"),
+    writable("staking_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("token_program"),
+];
+
+pub const ADD_NATIVE_SOL_COLLATERAL: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("sol_vault"),
+    writable("loan"),
+    writable_signer("owner"),
+    readonly("system_program"),
+];
+
+pub const DETERMINE_PENALTY: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("loan"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("price_history"),
+    writable("user_reward_account"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("token_program"),
+];
+
+pub const HARVEST_PENALTY_WITH_ORCA: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    writable("mata_mint"),
+    writable("loan"),
+    readonly("sol_mata_oracle"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    writable("arb_coffer"),
+    writable_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_mata_account"),
+    writable("user_msol_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_pda"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    readonly("system_program"),
+    readonly("marinade_program"),
+    writable("sm_amm"),
+    readonly("sm_amm_authority"),
+    writable("sm_pool_base_vault"),
+    writable("sm_pool_quote_vault"),
+    writable("sm_pool_mint"),
+    writable("sm_pool_fees"),
+    readonly("orca_swap_program"),
+    readonly("token_program"),
+];
+
+pub const HARVEST_PENALTY_NATIVE_SOL_WITH_ORCA: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("sol_vault"),
+    writable("mata_mint"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    writable("loan"),
+    readonly("sol_mata_oracle"),
+    writable_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_mata_account"),
+    writable("sm_amm"),
+    readonly("sm_amm_authority"),
+    writable("sm_pool_base_vault"),
+    writable("sm_pool_quote_vault"),
+    writable("sm_pool_mint"),
+    writable("sm_pool_fees"),
+    readonly("token_swap_program"),
+    readonly("token_program"),
+];
+
+pub const HARVEST_PENALTY_DIRECT: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    writable("mata_mint"),
+    writable("loan"),
+    readonly("sol_mata_oracle"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    writable("arb_coffer"),
+    writable_signer("user_account"),
+    writable("user_mata_account"),
+    writable("user_msol_account"),
+    readonly("marinade_program"),
+    readonly("token_program"),
+];
+
+pub const HARVEST_PENALTY_WITH_WHIRLPOOL: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    writable("mata_mint"),
+    writable("loan"),
+    readonly("sol_mata_oracle"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    writable("arb_coffer"),
+    writable_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_mata_account"),
+    writable("user_msol_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_pda"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    readonly("system_program"),
+    readonly("marinade_program"),
+    writable("whirlpool"),
+    writable("whirlpool_vault_a"),
+    writable("whirlpool_vault_b"),
+    writable("tick_array_0"),
+    writable("tick_array_1"),
+    writable("tick_array_2"),
+    readonly("whirlpool_oracle"),
+    readonly("whirlpool_program"),
+    readonly("token_program"),
+];
+
+pub const HARVEST_PENALTY_WITH_RAYDIUM: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    writable("mata_mint"),
+    writable("loan"),
+    readonly("sol_mata_oracle"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    writable("arb_coffer"),
+    writable("user_msol_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_pda"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    readonly("system_program"),
+    readonly("marinade_program"),
+    writable_signer("user_account"),
+    writable("user_lucra_account"),
+    writable("user_wsol_account"),
+    readonly("raydium_program"),
+    writable("pool_lucra_account"),
+    writable("pool_wsol_account"),
+    readonly("token_program"),
+    writable("amm_program"),
+    readonly("amm_authority"),
+    writable("amm_open_orders"),
+    writable("amm_target"),
+    writable("serum_lucra_sol_market"),
+    readonly("serum_program"),
+    writable("serum_bids"),
+    writable("serum_asks"),
+    writable("serum_event_queue"),
+    writable("serum_base_vault"),
+    writable("serum_quote_vault"),
+    readonly("serum_vault_signer"),
+];
+
+pub const SELL_LUCRA_FOR_ARB_FUNDS_USING_RAYDIUM: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    writable("lucra_holding_vault"),
+    readonly("lucra_holding_vault_authority"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("user_reward_account"),
+    writable_signer("user_account"),
+    writable("user_lucra_account"),
+    writable("user_wsol_account"),
+    readonly("raydium_program"),
+    writable("pool_lucra_account"),
+    writable("pool_wsol_account"),
+    readonly("token_program"),
+    writable("amm_program"),
+    readonly("amm_authority"),
+    writable("amm_open_orders"),
+    writable("amm_target"),
+    writable("serum_lucra_sol_market"),
+    readonly("serum_program"),
+    writable("serum_bids"),
+    writable("serum_asks"),
+    writable("serum_event_queue"),
+    writable("serum_base_vault"),
+    writable("serum_quote_vault"),
+    readonly("serum_vault_signer"),
+];
+
+pub const SELL_MATA_FOR_ARB_FUNDS_USING_RAYDIUM: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    writable("mata_holding_vault"),
+    readonly("mata_holding_vault_authority"),
+    writable("mata_mint"),
+    writable("wsol_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("user_reward_account"),
+    writable_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_mata_account"),
+    readonly("raydium_program"),
+    writable("pool_wsol_account"),
+    writable("pool_mata_account"),
+    readonly("token_program"),
+    writable("amm_program"),
+    readonly("amm_authority"),
+    writable("amm_open_orders"),
+    writable("amm_target"),
+    writable("serum_sol_mata_market"),
+    readonly("serum_program"),
+    writable("serum_bids"),
+    writable("serum_asks"),
+    writable("serum_event_queue"),
+    writable("serum_base_vault"),
+    writable("serum_quote_vault"),
+    readonly("serum_vault_signer"),
+];
+
+pub const SELL_LUCRA_FOR_ARB_FUNDS_USING_ORCA: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    writable("lucra_holding_vault"),
+    readonly("lucra_holding_vault_authority"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("user_reward_account"),
+    readonly_signer("user_account"),
+    writable("user_lucra_account"),
+    writable("user_wsol_account"),
+    readonly("ls_amm"),
+    readonly("ls_amm_authority"),
+    writable("ls_pool_base_vault"),
+    writable("ls_pool_quote_vault"),
+    writable("ls_pool_mint"),
+    writable("ls_pool_fees"),
+    readonly("sm_amm"),
+    readonly("sm_pool_base_vault"),
+    readonly("sm_pool_quote_vault"),
+    readonly("orca_swap_program"),
+    readonly("token_program"),
+];
+
+pub const SELL_MATA_FOR_ARB_FUNDS_USING_ORCA: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    writable("mata_holding_vault"),
+    readonly("mata_holding_vault_authority"),
+    writable("mata_mint"),
+    readonly("wsol_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("user_reward_account"),
+    readonly_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_mata_account"),
+    readonly("sm_amm"),
+    readonly("sm_amm_authority"),
+    writable("sm_pool_base_vault"),
+    writable("sm_pool_quote_vault"),
+    writable("sm_pool_mint"),
+    writable("sm_pool_fees"),
+    readonly("orca_swap_program"),
+    readonly("token_program"),
+];
+
+pub const SELL_MATA_FOR_ARB_FUNDS_USING_WHIRLPOOL: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    writable("mata_holding_vault"),
+    readonly("mata_holding_vault_authority"),
+    writable("mata_mint"),
+    readonly("wsol_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("user_reward_account"),
+    readonly_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_mata_account"),
+    writable("whirlpool"),
+    writable("whirlpool_vault_a"),
+    writable("whirlpool_vault_b"),
+    writable("tick_array_0"),
+    writable("tick_array_1"),
+    writable("tick_array_2"),
+    readonly("whirlpool_oracle"),
+    readonly("whirlpool_program"),
+    readonly("token_program"),
+];
+
+pub const MINT_MATA_FOR_ARB_FUNDS_CHECKING_RAYDIUM: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    readonly("arb_fund"),
+    writable("mata_holding_vault"),
+    writable("mata_mint"),
+    readonly("mata_mint_authority"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("sm_base_vault"),
+    readonly("sm_base_mint"),
+    readonly("sm_quote_vault"),
+    readonly("sm_amm_open_orders"),
+    readonly("sm_amm_program"),
+    writable("user_reward_account"),
+    readonly("token_program"),
+];
+
+pub const MINT_LUCRA_FOR_ARB_FUNDS_CHECKING_RAYDIUM: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    readonly("arb_fund"),
+    readonly("arb_coffer"),
+    writable("lucra_holding_vault"),
+    writable("lucra_mint"),
+    readonly("lucra_mint_authority"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("sm_raydium_base_vault"),
+    readonly("sm_raydium_base_mint"),
+    readonly("sm_raydium_quote_vault"),
+    readonly("sm_raydium_quote_mint"),
+    readonly("sm_raydium_amm_open_orders"),
+    readonly("sm_raydium_amm"),
+    readonly("sm_orca_base_vault"),
+    readonly("sm_orca_quote_vault"),
+    readonly("sm_orca_amm"),
+    writable("user_reward_account"),
+    readonly("marinade_state"),
+    readonly("marinade_program"),
+    readonly("token_program"),
+];
+
+pub const MINT_MATA_FOR_ARB_FUNDS_CHECKING_ORCA: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    readonly("arb_fund"),
+    writable("mata_holding_vault"),
+    writable("mata_mint"),
+    readonly("mata_mint_authority"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("sm_amm"),
+    readonly("sm_base_vault"),
+    readonly("sm_base_mint"),
+    readonly("sm_quote_vault"),
+    writable("user_reward_account"),
+    readonly("token_program"),
+];
+
+pub const MINT_LUCRA_FOR_ARB_FUNDS_CHECKING_ORCA: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    readonly("arb_fund"),
+    readonly("arb_coffer"),
+    writable("lucra_holding_vault"),
+    writable("lucra_mint"),
+    readonly("lucra_mint_authority"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("sm_orca_amm"),
+    readonly("sm_orca_base_vault"),
+    readonly("sm_orca_base_mint"),
+    readonly("sm_orca_quote_vault"),
+    readonly("sm_orca_quote_mint"),
+    readonly("sm_raydium_open_orders"),
+    readonly("sm_raydium_base_vault"),
+    readonly("sm_raydium_quote_vault"),
+    readonly("sm_raydium_amm"),
+    writable("user_reward_account"),
+    readonly("marinade_state"),
+    readonly("marinade_program"),
+    readonly("token_program"),
+];
+
+pub const TRANSFER_MSOL_FOR_ARB_FUNDS: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_coffer"),
+    readonly("arb_coffer_authority"),
+    writable("arb_fund"),
+    writable("mata_mint"),
+    writable("wsol_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("sm_raydium_base_vault"),
+    readonly("sm_raydium_quote_vault"),
+    readonly("sm_raydium_amm_open_orders"),
+    readonly("sm_raydium_amm"),
+    readonly("sm_orca_base_vault"),
+    readonly("sm_orca_quote_vault"),
+    readonly("sm_orca_amm"),
+    writable_signer("user_account"),
+    writable("user_msol_account"),
+    writable("user_reward_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_pda"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    writable("marinade_state"),
+    readonly("marinade_program"),
+    readonly("system_program"),
+    readonly("token_program"),
+];
+
+pub const TRANSFER_MSOL_FOR_ARB_FUNDS_CHECKING_ORCA: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_coffer"),
+    readonly("arb_coffer_authority"),
+    writable("arb_fund"),
+    writable("mata_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("sm_base_vault"),
+    readonly("sm_base_mint"),
+    readonly("sm_quote_vault"),
+    readonly("sm_amm"),
+    writable_signer("user_account"),
+    writable("user_msol_account"),
+    writable("user_reward_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_pda"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    writable("marinade_state"),
+    readonly("marinade_program"),
+    readonly("system_program"),
+    readonly("token_program"),
+];
+
+pub const TRANSFER_MSOL_FOR_ARB_FUNDS_CHECKING_RAYDIUM: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_coffer"),
+    readonly("arb_coffer_authority"),
+    writable("arb_fund"),
+    writable("mata_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("sm_base_vault"),
+    readonly("sm_base_mint"),
+    readonly("sm_quote_vault"),
+    readonly("sm_amm_open_orders"),
+    readonly("sm_amm"),
+    writable_signer("user_account"),
+    writable("user_msol_account"),
+    writable("user_reward_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_pda"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    writable("marinade_state"),
+    readonly("marinade_program"),
+    readonly("system_program"),
+    readonly("token_program"),
+];
+
+pub const SPEND_ARB_FUNDS_FOR_LUCRA_USING_RAYDIUM: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    readonly("arb_fund_authority"),
+    writable("lucra_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("lucra_sol_oracle"),
+    writable("wsol_holding_vault"),
+    writable("user_reward_account"),
+    writable_signer("user_account"),
+    writable("user_lucra_account"),
+    writable("user_wsol_account"),
+    readonly("raydium_program"),
+    writable("pool_lucra_account"),
+    writable("pool_wsol_account"),
+    readonly("token_program"),
+    writable("amm_program"),
+    readonly("amm_authority"),
+    writable("amm_open_orders"),
+    writable("amm_target"),
+    writable("serum_lucra_sol_market"),
+    readonly("serum_program"),
+    writable("serum_bids"),
+    writable("serum_asks"),
+    writable("serum_event_queue"),
+    writable("serum_base_vault"),
+    writable("serum_quote_vault"),
+    readonly("serum_vault_signer"),
+];
+
+pub const SPEND_ARB_FUNDS_FOR_MATA_USING_RAYDIUM: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    readonly("arb_fund_authority"),
+    writable("mata_mint"),
+    writable("wsol_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("user_reward_account"),
+    writable_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_mata_account"),
+    readonly("raydium_program"),
+    writable("pool_wsol_account"),
+    writable("pool_mata_account"),
+    readonly("token_program"),
+    writable("amm_program"),
+    readonly("amm_authority"),
+    writable("amm_open_orders"),
+    writable("amm_target"),
+    writable("serum_sol_mata_market"),
+    readonly("serum_program"),
+    writable("serum_bids"),
+    writable("serum_asks"),
+    writable("serum_event_queue"),
+    writable("serum_base_vault"),
+    writable("serum_quote_vault"),
+    readonly("serum_vault_signer"),
+];
+
+pub const SPEND_ARB_FUNDS_FOR_LUCRA_USING_ORCA: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    readonly("arb_fund_authority"),
+    writable("wsol_holding_vault"),
+    writable("lucra_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("lucra_sol_oracle"),
+    writable("user_reward_account"),
+    readonly_signer("user_account"),
+    writable("user_lucra_account"),
+    writable("user_wsol_account"),
+    readonly("ls_amm"),
+    readonly("ls_amm_authority"),
+    writable("ls_pool_base_vault"),
+    writable("ls_pool_quote_vault"),
+    writable("ls_pool_mint"),
+    writable("ls_pool_fees"),
+    readonly("orca_swap_program"),
+    readonly("token_program"),
+];
+
+pub const SPEND_ARB_FUNDS_FOR_MATA_USING_ORCA: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    readonly("arb_fund_authority"),
+    writable("mata_mint"),
+    writable("wsol_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("user_reward_account"),
+    readonly_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_mata_account"),
+    readonly("sm_amm"),
+    readonly("sm_amm_authority"),
+    writable("sm_pool_base_vault"),
+    writable("sm_pool_quote_vault"),
+    writable("sm_pool_mint"),
+    writable("sm_pool_fees"),
+    readonly("orca_swap_program"),
+    readonly("token_program"),
+];
+
+pub const SPEND_ARB_FUNDS_FOR_MATA_USING_WHIRLPOOL: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_fund"),
+    readonly("arb_fund_authority"),
+    writable("mata_mint"),
+    writable("wsol_mint"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("user_reward_account"),
+    readonly_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_mata_account"),
+    writable("whirlpool"),
+    writable("whirlpool_vault_a"),
+    writable("whirlpool_vault_b"),
+    writable("tick_array_0"),
+    writable("tick_array_1"),
+    writable("tick_array_2"),
+    readonly("whirlpool_oracle"),
+    readonly("whirlpool_program"),
+    readonly("token_program"),
+];
+
+pub const CLEAN_UP_ARB: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("arb_coffer"),
+    writable("wsol_holding_vault"),
+    readonly("wsol_holding_vault_authority"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    writable_signer("user_account"),
+    writable("user_wsol_account"),
+    writable("user_msol_account"),
+    writable("user_reward_account"),
+    writable("temp_wsol_account"),
+    writable("marinade_state"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_pda"),
+    writable("liq_pool_msol_leg"),
+    readonly("liq_pool_msol_leg_authority"),
+    writable("reserve_pda"),
+    readonly("msol_mint_authority"),
+    readonly("system_program"),
+    readonly("marinade_program"),
+    readonly("token_program"),
+];
+
+pub const CREATE_REVENUE_LEDGER: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("revenue_ledger"),
+    readonly_signer("creator_authority"),
+];
+
+pub const GLOBAL_SETTLEMENT: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("dao_authority"),
+];
+
+pub const RECLAIM_EXCESS: &[IdlAccount] = &[
+    readonly("system_state"),
+    readonly_signer("dao_authority"),
+    writable("msol_vault"),
+    writable("to_account"),
+    readonly("msol_vault_authority"),
+    readonly("token_program"),
+];
+
+pub const ROLL_ARB_WINDOW: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("arb_state"),
+    writable("user_reward_account"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("token_program"),
+];
+
+pub const SET_REWARD_METADATA: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable_signer("creator_authority"),
+    readonly("reward_mint"),
+    readonly("reward_mint_authority"),
+    writable("metadata"),
+    readonly("metadata_program"),
+    readonly("system_program"),
+];
+
+pub const FREEZE_STAKING_ACCOUNT: &[IdlAccount] = &[
+    writable("staking_account"),
+    readonly_signer("authority"),
+];
+
+pub const UNFREEZE_STAKING_ACCOUNT: &[IdlAccount] = &[
+    writable("staking_account"),
+    readonly_signer("authority"),
+];
+
+pub const SET_STAKING_RECOVERY_KEY: &[IdlAccount] = &[
+    writable("staking_account"),
+    readonly_signer("owner"),
+    readonly_signer("current_recovery_key").optional(),
+];
+
+pub const SET_LP_COLLATERAL: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("dao_authority"),
+    readonly("sol_mata_pool"),
+    readonly("lp_vault"),
+];
+
+pub const SWEEP_EXPIRED_REWARD: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("staking_state"),
+    writable("reward"),
+    writable("rewards_vault"),
+    writable("arb_coffer"),
+    readonly("rewards_vault_authority"),
+    writable("receiver"),
+    writable("user_reward_account"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("token_program"),
+];
+
+pub const SET_MIN_DROP_AMOUNT: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("staking_state"),
+    readonly_signer("dao_authority"),
+];
+
+pub const SET_REWARD_REDEMPTION_DEADLINE: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("dao_authority"),
+];
+
+pub const CLOSE_REWARD_PROGRAM: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("dao_authority"),
+];
+
+pub const MIGRATE_ACCOUNT_LAYOUT: &[IdlAccount] = &[
+    writable("account"),
+    writable_signer("payer"),
+    readonly("system_program"),
+    readonly_signer("dao_authority").optional(),
+];
+
+pub const MIGRATE_ARB_STATE_LAYOUT: &[IdlAccount] = &[
+    writable("account"),
+    writable_signer("payer"),
+    readonly("system_program"),
+    readonly_signer("dao_authority"),
+    readonly("system_state"),
+];
+
+pub const CORRECT_PRICE_HISTORY: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("price_history"),
+    writable("price_correction"),
+    writable_signer("dao_authority"),
+    readonly("system_program"),
+];
+
+pub const RECOMPUTE_PENALTY: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("loan"),
+    readonly("price_history"),
+];
+
+pub const COMPUTE_TVL: &[IdlAccount] = &[
+    readonly("system_state"),
+    readonly("arb_state"),
+    writable("tvl_snapshot"),
+    readonly("marinade_state"),
+    readonly("msol_vault"),
+    readonly("arb_coffer"),
+    readonly("rewards_vault"),
+    readonly("wsol_holding_vault"),
+    readonly("mata_holding_vault"),
+    readonly("lucra_holding_vault"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+    readonly("sol_mata_oracle"),
+    writable_signer("user_account"),
+    writable("user_reward_account"),
+    writable("reward_mint"),
+    readonly("reward_mint_authority"),
+    readonly("system_program"),
+    readonly("token_program"),
+];
+
+pub const ROLLOVER_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    writable("mata_mint"),
+    readonly("mata_mint_authority"),
+    readonly_signer("user_account"),
+    writable("user_mata_account"),
+    writable("user_msol_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("token_program"),
+];
+
+pub const REPAY_LOAN_PARTIAL: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("loan"),
+    readonly_signer("user_account"),
+    writable("user_msol_account"),
+    writable("mata_mint"),
+    writable("user_mata_account"),
+    readonly("msol_vault_authority"),
+    writable("msol_vault"),
+    readonly("token_program"),
+];
+
+pub const REPAY_LOAN_PARTIAL_WITH_LOCKED_STAKE: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("loan"),
+    readonly_signer("user_account"),
+    writable("user_msol_account"),
+    writable("mata_mint"),
+    writable("user_mata_account"),
+    readonly("msol_vault_authority"),
+    writable("msol_vault"),
+    writable("user_staking_account"),
+    readonly("token_program"),
+];
+
+pub const BORROW_MORE: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("loan"),
+    writable("mata_mint"),
+    readonly("mata_mint_authority"),
+    readonly_signer("user_account"),
+    writable("user_mata_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("token_program"),
+];
+
+pub const SET_LIQUIDATION_PARAMS: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("dao_authority"),
+];
+
+pub const LIQUIDATE_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    writable("arb_coffer"),
+    writable("mata_mint"),
+    readonly_signer("liquidator"),
+    writable("liquidator_mata_account"),
+    writable("liquidator_msol_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("token_program"),
+];
+
+pub const LIQUIDATE_LOAN_WITH_LOCKED_STAKE: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    writable("arb_coffer"),
+    writable("mata_mint"),
+    readonly_signer("liquidator"),
+    writable("liquidator_mata_account"),
+    writable("liquidator_msol_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    writable("staking_account"),
+    readonly("token_program"),
+];
+
+pub const LIQUIDATE_LOAN_WITH_ORCA: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    writable("arb_coffer"),
+    writable("mata_mint"),
+    writable_signer("liquidator"),
+    writable("liquidator_mata_account"),
+    writable("liquidator_msol_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("token_program"),
+    readonly("sol_mata_oracle"),
+    writable("liquidator_wsol_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_pda"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    readonly("system_program"),
+    readonly("marinade_program"),
+    writable("sm_amm"),
+    readonly("sm_amm_authority"),
+    writable("sm_pool_base_vault"),
+    writable("sm_pool_quote_vault"),
+    writable("sm_pool_mint"),
+    writable("sm_pool_fees"),
+    readonly("orca_swap_program"),
+];
+
+pub const LIQUIDATE_LOAN_WITH_LOCKED_STAKE_WITH_ORCA: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    writable("arb_coffer"),
+    writable("mata_mint"),
+    writable_signer("liquidator"),
+    writable("liquidator_mata_account"),
+    writable("liquidator_msol_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    writable("staking_account"),
+    readonly("token_program"),
+    readonly("sol_mata_oracle"),
+    writable("liquidator_wsol_account"),
+    writable("msol_mint"),
+    writable("liq_pool_sol_leg_pda"),
+    writable("liq_pool_msol_leg"),
+    writable("treasury_msol_account"),
+    readonly("system_program"),
+    readonly("marinade_program"),
+    writable("sm_amm"),
+    readonly("sm_amm_authority"),
+    writable("sm_pool_base_vault"),
+    writable("sm_pool_quote_vault"),
+    writable("sm_pool_mint"),
+    writable("sm_pool_fees"),
+    readonly("orca_swap_program"),
+];
+
+pub const LIQUIDATE_LP_BACKED_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("sol_mata_pool"),
+    writable("loan"),
+    writable("lp_vault"),
+    readonly("lp_vault_authority"),
+    writable("mata_mint"),
+    readonly_signer("liquidator"),
+    writable("liquidator_mata_account"),
+    writable("liquidator_lp_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    writable("pool_sol_vault"),
+    writable("pool_mata_vault"),
+    writable("lp_mint"),
+    readonly("token_program"),
+    writable("liquidator_wsol_account"),
+    readonly("pool_authority"),
+    writable("pool_fees"),
+    readonly("token_swap_program"),
+];
+
+pub const LIQUIDATE_NATIVE_SOL_LOAN: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("sol_vault"),
+    writable("loan"),
+    writable("mata_mint"),
+    writable_signer("liquidator"),
+    writable("liquidator_mata_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("token_program"),
+];
+
+pub const TRANSFER_LOAN_OWNERSHIP: &[IdlAccount] = &[
+    writable("loan"),
+    readonly_signer("current_owner"),
+    readonly_signer("new_owner"),
+];
+
+pub const TRANSFER_LOAN_OWNERSHIP_WITH_LOCKED_STAKE: &[IdlAccount] = &[
+    writable("loan"),
+    readonly_signer("current_owner"),
+    readonly_signer("new_owner"),
+    readonly("system_state"),
+    writable("current_staking_account"),
+    writable("new_staking_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("lucra_sol_oracle"),
+];
+
+pub const CLOSE_STAKE_BALANCE: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("stake_balance"),
+    writable("deposit_vault"),
+    writable("stake_vault"),
+    writable("pending_vault"),
+    writable_signer("owner"),
+    readonly("stake_balance_authority"),
+    readonly("token_program"),
+];
+
+pub const CLOSE_STAKING_ACCOUNT: &[IdlAccount] = &[
+    writable("staking_account"),
+    writable_signer("owner"),
+];
+
+pub const CANCEL_UNSTAKE: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("staking_state"),
+    writable("staking_account"),
+    writable("stake_balance"),
+    writable("pending_withdrawal"),
+    writable("pending_vault"),
+    writable("stake_vault"),
+    writable_signer("owner"),
+    readonly("stake_balance_authority"),
+    writable("staked_lucra_mint"),
+    writable("staked_lucra_account"),
+    readonly("staked_lucra_mint_authority"),
+    readonly("token_program"),
+];
+
+pub const PROPOSE_AUTHORITY: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("dao_authority"),
+];
+
+pub const ACCEPT_AUTHORITY: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("new_authority"),
+];
+
+pub const SET_PAUSE: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("authority"),
+];
+
+pub const WITHDRAW_COLLATERAL: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly("marinade_state"),
+    writable("loan"),
+    writable("msol_vault"),
+    readonly("msol_vault_authority"),
+    readonly_signer("user_account"),
+    writable("user_msol_account"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+    readonly("token_program"),
+];
+
+pub const MIGRATE_STAKE_BALANCE: &[IdlAccount] = &[
+    readonly("staking_state"),
+    writable("stake_balance"),
+    readonly("stake_vault"),
+];
+
+pub const QUOTE_MATA_LOAN: &[IdlAccount] = &[
+    readonly("system_state"),
+    readonly("marinade_state"),
+    readonly("sol_usdc_oracle"),
+    readonly("sol_usdt_oracle"),
+    readonly("sol_mata_oracle"),
+];
+
+pub const CREATE_PENALTY_HISTORY: &[IdlAccount] = &[
+    readonly("loan"),
+    writable("penalty_history"),
+    writable_signer("payer"),
+    readonly("system_program"),
+];
+
+pub const CLAIM_REWARD_V2: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("staking_state"),
+    writable("staking_account"),
+    writable("stake_balance"),
+    writable("reward"),
+    writable("staked_lucra_account"),
+    writable("lucra_vault"),
+    writable("lucra_account"),
+    writable("rewards_vault"),
+    writable("msol_account"),
+    readonly("rewards_vault_authority"),
+    writable("lucra_mint"),
+    readonly("lucra_mint_authority"),
+    readonly("token_program"),
+    writable("staked_lucra_mint"),
+    readonly("stake_mint_authority"),
+];
+
+pub const UPDATE_HARVEST_PARAMS: &[IdlAccount] = &[
+    writable("system_state"),
+    readonly_signer("dao_authority"),
+];
+
+pub const CREATE_SOL_VAULT: &[IdlAccount] = &[
+    writable("system_state"),
+    writable("sol_vault"),
+    writable_signer("creator_authority"),
+    readonly("system_program"),
+];
+
+pub const CREATE_PRICE_HISTORY_PAGE: &[IdlAccount] = &[
+    writable("price_history"),
+    writable("price_history_header"),
+    writable("price_history_page"),
+    writable_signer("payer"),
+    readonly("system_program"),
+];
+
+pub const REGISTER_KEEPER: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("keeper_registry"),
+    readonly("keeper"),
+    readonly_signer("dao_authority"),
+    writable_signer("payer"),
+    readonly("system_program"),
+];
+
+pub const REMOVE_KEEPER: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("keeper_registry"),
+    readonly("keeper"),
+    readonly_signer("dao_authority"),
+];
+
+pub const ADD_COLLATERAL_TYPE: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("collateral_registry"),
+    readonly("mint"),
+    readonly("oracle"),
+    readonly("vault"),
+    readonly_signer("dao_authority"),
+    writable_signer("payer"),
+    readonly("system_program"),
+];
+
+pub const SET_COLLATERAL_TYPE: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("collateral_registry"),
+    readonly("oracle"),
+    readonly_signer("dao_authority"),
+];
+
+pub const REBALANCE_REWARDS_VAULT: &[IdlAccount] = &[
+    readonly("system_state"),
+    readonly("staking_state"),
+    readonly_signer("dao_authority"),
+    writable("rewards_vault"),
+    writable("arb_coffer"),
+    readonly("rewards_vault_authority"),
+    readonly("token_program"),
+];
+
+pub const ADD_TRANSFER_DESTINATION: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("transfer_allowlist"),
+    readonly("destination"),
+    readonly_signer("dao_authority"),
+    writable_signer("payer"),
+    readonly("system_program"),
+];
+
+pub const REMOVE_TRANSFER_DESTINATION: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("transfer_allowlist"),
+    readonly("destination"),
+    readonly_signer("dao_authority"),
+];
+
+pub const CREATE_EVENT_QUEUE: &[IdlAccount] = &[
+    readonly("system_state"),
+    writable("event_queue"),
+    writable_signer("creator_authority"),
+    readonly("system_program"),
+];
+
+const INITIALIZE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "min_deposit", ty: "u64" },
+    IdlArg { name: "collateral_requirement", ty: "u32" },
+    IdlArg { name: "epoch", ty: "i64" },
+    IdlArg { name: "loans_enabled", ty: "bool" },
+    IdlArg { name: "staking_enabled", ty: "bool" },
+    IdlArg { name: "arbitrage_enabled", ty: "bool" },
+    IdlArg { name: "peg_check_enabled", ty: "bool" },
+    IdlArg { name: "max_amount_of_lucra_to_mint", ty: "u64" },
+    IdlArg { name: "daily_arb_limit", ty: "u64" },
+    IdlArg { name: "maximum_outstanding_mata", ty: "u64" },
+    IdlArg { name: "lcp", ty: "u8" },
+    IdlArg { name: "min_loan_amount_mata", ty: "u64" },
+    IdlArg { name: "msol_haircut_bps", ty: "u32" },
+    IdlArg { name: "peg_lower_bound", ty: "u32" },
+    IdlArg { name: "peg_upper_bound", ty: "u32" },
+    IdlArg { name: "penalty_band_width", ty: "u32" },
+    IdlArg { name: "penalty_multiplier_per_band", ty: "u32" },
+    IdlArg { name: "max_oracle_staleness_slots", ty: "u64" },
+    IdlArg { name: "max_oracle_deviation_bps", ty: "u32" },
+    IdlArg { name: "guardian", ty: "publicKey" },
+];
+
+const UPDATE_STATE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "min_deposit", ty: "option<u64>" },
+    IdlArg { name: "collateral_requirement", ty: "option<u32>" },
+    IdlArg { name: "loans_enabled", ty: "option<bool>" },
+    IdlArg { name: "staking_enabled", ty: "option<bool>" },
+    IdlArg { name: "arbitrage_enabled", ty: "option<bool>" },
+    IdlArg { name: "peg_check_enabled", ty: "option<bool>" },
+    IdlArg { name: "max_amount_of_lucra_to_mint", ty: "option<u64>" },
+    IdlArg { name: "daily_arb_limit", ty: "option<u64>" },
+    IdlArg { name: "maximum_outstanding_mata", ty: "option<u64>" },
+    IdlArg { name: "minimum_harvest_amount", ty: "option<u64>" },
+    IdlArg { name: "reward_fee", ty: "option<u32>" },
+    IdlArg { name: "lcp", ty: "option<u8>" },
+    IdlArg { name: "min_loan_amount_mata", ty: "option<u64>" },
+    IdlArg { name: "msol_haircut_bps", ty: "option<u32>" },
+    IdlArg { name: "msol_sol_oracle", ty: "option<publicKey>" },
+    IdlArg { name: "require_oracle_v2", ty: "option<bool>" },
+    IdlArg { name: "reward_expiry_epochs", ty: "option<u64>" },
+    IdlArg { name: "max_instant_unstake", ty: "option<u64>" },
+    IdlArg { name: "peg_lower_bound", ty: "option<u32>" },
+    IdlArg { name: "peg_upper_bound", ty: "option<u32>" },
+    IdlArg { name: "penalty_band_width", ty: "option<u32>" },
+    IdlArg { name: "penalty_multiplier_per_band", ty: "option<u32>" },
+    IdlArg { name: "arb_trigger_deviation_bps", ty: "option<u32>" },
+    IdlArg { name: "guardian", ty: "option<publicKey>" },
+    IdlArg { name: "max_oracle_staleness_slots", ty: "option<u64>" },
+    IdlArg { name: "max_oracle_deviation_bps", ty: "option<u32>" },
+    IdlArg { name: "msol_sol_pool", ty: "option<publicKey>" },
+    IdlArg { name: "arb_dust_threshold", ty: "option<u64>" },
+    IdlArg { name: "pyth_sol_usd_feed", ty: "option<publicKey>" },
+    IdlArg { name: "annual_interest_bps", ty: "option<u32>" },
+    IdlArg { name: "restricted_cranking", ty: "option<bool>" },
+    IdlArg { name: "sol_mata_whirlpool", ty: "option<publicKey>" },
+    IdlArg { name: "native_loans_enabled", ty: "option<bool>" },
+];
+
+const CREATE_MATA_LOAN_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lamports", ty: "u64" },
+];
+
+const CLOSE_OUT_MATA_LOAN_ARGS: &[IdlArg] = &[
+    IdlArg { name: "unstake_msol", ty: "bool" },
+];
+
+const CREATE_STAKE_BALANCE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "nonce", ty: "u8" },
+    IdlArg { name: "staking_timeframe", ty: "u8" },
+];
+
+const DEPOSIT_STAKE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lucra", ty: "u64" },
+];
+
+const STAKE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lucra", ty: "u64" },
+];
+
+const START_UNSTAKE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lucra", ty: "u64" },
+];
+
+const WITHDRAW_STAKE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lucra", ty: "u64" },
+];
+
+const TRANSFER_FUNDS_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lamports", ty: "u64" },
+    IdlArg { name: "native", ty: "bool" },
+];
+
+const REDEEM_REWARD_TOKENS_ARGS: &[IdlArg] = &[
+    IdlArg { name: "reward_tokens", ty: "u64" },
+];
+
+const ADD_COLLATERAL_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lamports", ty: "u64" },
+];
+
+const HARVEST_PENALTY_ARGS: &[IdlArg] = &[
+    IdlArg { name: "amm_type", ty: "u8" },
+    IdlArg { name: "min_mata_out", ty: "u64" },
+];
+
+const SELL_FUNDS_FOR_ARB_ARGS: &[IdlArg] = &[
+    IdlArg { name: "fund_source", ty: "u8" },
+    IdlArg { name: "amm_type", ty: "u8" },
+    IdlArg { name: "lamports", ty: "u64" },    IdlArg { name: "min_amount_out", ty: "u64" },
+];
+
+const MINT_FUNDS_FOR_ARB_ARGS: &[IdlArg] = &[
+    IdlArg { name: "fund_source", ty: "u8" },
+    IdlArg { name: "amm_type", ty: "u8" },
+    IdlArg { name: "lamports", ty: "u64" },
+];
+
+const BUY_BURN_FOR_ARB_ARGS: &[IdlArg] = &[
+    IdlArg { name: "fund_source", ty: "u8" },
+    IdlArg { name: "amm_type", ty: "u8" },
+    IdlArg { name: "lamports", ty: "u64" },    IdlArg { name: "min_amount_out", ty: "u64" },
+];
+
+const GLOBAL_SETTLEMENT_ARGS: &[IdlArg] = &[
+    IdlArg { name: "settlement_sol_price", ty: "u64" },
+];
+
+const SET_REWARD_METADATA_ARGS: &[IdlArg] = &[
+    IdlArg { name: "name", ty: "string" },
+    IdlArg { name: "symbol", ty: "string" },
+    IdlArg { name: "uri", ty: "string" },
+];
+
+const SET_STAKING_RECOVERY_KEY_ARGS: &[IdlArg] = &[
+    IdlArg { name: "recovery_key", ty: "publicKey" },
+];
+
+const REGISTER_KEEPER_ARGS: &[IdlArg] = &[
+    IdlArg { name: "multiplier", ty: "u32" },
+];
+
+const ADD_COLLATERAL_TYPE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "haircut_bps", ty: "u32" },
+];
+
+const SET_COLLATERAL_TYPE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "collateral_type", ty: "u8" },
+    IdlArg { name: "haircut_bps", ty: "u32" },
+    IdlArg { name: "enabled", ty: "bool" },
+];
+
+const REBALANCE_REWARDS_VAULT_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lamports", ty: "u64" },
+];
+
+const SET_LP_COLLATERAL_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lp_collateral_requirement", ty: "u32" },
+];
+
+const SET_MIN_DROP_AMOUNT_ARGS: &[IdlArg] = &[
+    IdlArg { name: "min_drop_amount", ty: "u64" },
+];
+
+const SET_REWARD_REDEMPTION_DEADLINE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "deadline", ty: "i64" },
+    IdlArg { name: "grace_period", ty: "i64" },
+    IdlArg { name: "grace_boost", ty: "u32" },
+];
+
+const CORRECT_PRICE_HISTORY_ARGS: &[IdlArg] = &[
+    IdlArg { name: "date", ty: "i64" },
+    IdlArg { name: "corrected_sol_price", ty: "u64" },
+    IdlArg { name: "corrected_lucra_price", ty: "u64" },
+];
+
+const ROLLOVER_LOAN_ARGS: &[IdlArg] = &[
+    IdlArg { name: "new_lamports", ty: "u64" },
+];
+
+const REPAY_LOAN_PARTIAL_ARGS: &[IdlArg] = &[
+    IdlArg { name: "mata", ty: "u64" },
+    IdlArg { name: "release_collateral", ty: "bool" },
+];
+
+const BORROW_MORE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "mata", ty: "u64" },
+];
+
+const SET_LIQUIDATION_PARAMS_ARGS: &[IdlArg] = &[
+    IdlArg { name: "liquidation_ratio", ty: "u32" },
+    IdlArg { name: "liquidation_bonus", ty: "u32" },
+];
+
+const LIQUIDATE_LOAN_ARGS: &[IdlArg] = &[
+    IdlArg { name: "amm_type", ty: "u8" },
+];
+
+const PROPOSE_AUTHORITY_ARGS: &[IdlArg] = &[
+    IdlArg { name: "new_authority", ty: "publicKey" },
+];
+
+const SET_PAUSE_ARGS: &[IdlArg] = &[
+    IdlArg { name: "loans", ty: "bool" },
+    IdlArg { name: "staking", ty: "bool" },
+    IdlArg { name: "arbitrage", ty: "bool" },
+];
+
+const WITHDRAW_COLLATERAL_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lamports", ty: "u64" },
+];
+
+const QUOTE_MATA_LOAN_ARGS: &[IdlArg] = &[
+    IdlArg { name: "lamports", ty: "u64" },
+];
+
+const CLAIM_REWARD_V2_ARGS: &[IdlArg] = &[
+    IdlArg { name: "compound", ty: "bool" },
+];
+
+const UPDATE_HARVEST_PARAMS_ARGS: &[IdlArg] = &[
+    IdlArg { name: "minimum_harvest_amount", ty: "option<u64>" },
+    IdlArg { name: "reward_fee", ty: "option<u32>" },
+];
+
+pub const INSTRUCTIONS: &[IdlInstruction] = &[
+    IdlInstruction { name: "initialize", discriminant: 0x80, accounts: INITIALIZE, args: INITIALIZE_ARGS },
+    IdlInstruction { name: "update_state", discriminant: 0x81, accounts: UPDATE_STATE, args: UPDATE_STATE_ARGS },
+    IdlInstruction { name: "create_mata_loan", discriminant: 0x82, accounts: CREATE_MATA_LOAN, args: CREATE_MATA_LOAN_ARGS },
+    IdlInstruction { name: "create_mata_loan_with_locked_stake", discriminant: 0x82, accounts: CREATE_MATA_LOAN_WITH_LOCKED_STAKE, args: CREATE_MATA_LOAN_ARGS },
+    IdlInstruction { name: "close_mata_loan", discriminant: 0x83, accounts: CLOSE_MATA_LOAN, args: CLOSE_OUT_MATA_LOAN_ARGS },
+    IdlInstruction { name: "close_mata_loan_with_locked_stake", discriminant: 0x83, accounts: CLOSE_MATA_LOAN_WITH_LOCKED_STAKE, args: CLOSE_OUT_MATA_LOAN_ARGS },
+    IdlInstruction { name: "create_lp_backed_mata_loan", discriminant: 0x82, accounts: CREATE_LP_BACKED_MATA_LOAN, args: CREATE_MATA_LOAN_ARGS },
+    IdlInstruction { name: "close_lp_backed_mata_loan", discriminant: 0x83, accounts: CLOSE_LP_BACKED_MATA_LOAN, args: CLOSE_OUT_MATA_LOAN_ARGS },
+    IdlInstruction { name: "create_native_sol_mata_loan", discriminant: 0x82, accounts: CREATE_NATIVE_SOL_MATA_LOAN, args: CREATE_MATA_LOAN_ARGS },
+    IdlInstruction { name: "close_native_sol_mata_loan", discriminant: 0x83, accounts: CLOSE_NATIVE_SOL_MATA_LOAN, args: CLOSE_OUT_MATA_LOAN_ARGS },
+    IdlInstruction { name: "create_staking_account", discriminant: 0x84, accounts: CREATE_STAKING_ACCOUNT, args: &[] },
+    IdlInstruction { name: "create_stake_balance", discriminant: 0x85, accounts: CREATE_STAKE_BALANCE, args: CREATE_STAKE_BALANCE_ARGS },
+    IdlInstruction { name: "deposit_stake", discriminant: 0x86, accounts: DEPOSIT_STAKE, args: DEPOSIT_STAKE_ARGS },
+    IdlInstruction { name: "stake", discriminant: 0x87, accounts: STAKE, args: STAKE_ARGS },
+    IdlInstruction { name: "start_unstake", discriminant: 0x88, accounts: START_UNSTAKE, args: START_UNSTAKE_ARGS },
+    IdlInstruction { name: "end_unstake", discriminant: 0x89, accounts: END_UNSTAKE, args: &[] },
+    IdlInstruction { name: "withdraw_stake", discriminant: 0x8a, accounts: WITHDRAW_STAKE, args: WITHDRAW_STAKE_ARGS },
+    IdlInstruction { name: "claim_reward", discriminant: 0x8b, accounts: CLAIM_REWARD, args: &[] },
+    IdlInstruction { name: "drop_reward", discriminant: 0x8c, accounts: DROP_REWARD, args: &[] },
+    IdlInstruction { name: "transfer_funds", discriminant: 0x8d, accounts: TRANSFER_FUNDS, args: TRANSFER_FUNDS_ARGS },
+    IdlInstruction { name: "create_price_history", discriminant: 0x8e, accounts: CREATE_PRICE_HISTORY, args: &[] },
+    IdlInstruction { name: "update_price_history", discriminant: 0x8f, accounts: UPDATE_PRICE_HISTORY, args: &[] },
+    IdlInstruction { name: "redeem_reward_tokens", discriminant: 0x90, accounts: REDEEM_REWARD_TOKENS, args: REDEEM_REWARD_TOKENS_ARGS },
+    IdlInstruction { name: "add_collateral", discriminant: 0x91, accounts: ADD_COLLATERAL, args: ADD_COLLATERAL_ARGS },
+    IdlInstruction { name: "add_collateral_with_locked_stake", discriminant: 0x91, accounts: ADD_COLLATERAL_WITH_LOCKED_STAKE, args: ADD_COLLATERAL_ARGS },
+    IdlInstruction { name: "add_collateral_msol", discriminant: 0x91, accounts: ADD_COLLATERAL_MSOL, args: ADD_COLLATERAL_ARGS },
+    IdlInstruction { name: "add_collateral_msol_with_locked_stake", discriminant: 0x91, accounts: ADD_COLLATERAL_MSOL_WITH_LOCKED_STAKE, args: ADD_COLLATERAL_ARGS },
+    IdlInstruction { name: "add_native_sol_collateral", discriminant: 0x91, accounts: ADD_NATIVE_SOL_COLLATERAL, args: ADD_COLLATERAL_ARGS },
+    IdlInstruction { name: "determine_penalty", discriminant: 0x92, accounts: DETERMINE_PENALTY, args: &[] },
+    IdlInstruction { name: "harvest_penalty_with_orca", discriminant: 0x93, accounts: HARVEST_PENALTY_WITH_ORCA, args: HARVEST_PENALTY_ARGS },
+    IdlInstruction { name: "harvest_penalty_native_sol_with_orca", discriminant: 0x93, accounts: HARVEST_PENALTY_NATIVE_SOL_WITH_ORCA, args: HARVEST_PENALTY_ARGS },
+    IdlInstruction { name: "harvest_penalty_with_raydium", discriminant: 0x93, accounts: HARVEST_PENALTY_WITH_RAYDIUM, args: HARVEST_PENALTY_ARGS },
+    IdlInstruction { name: "harvest_penalty_direct", discriminant: 0x93, accounts: HARVEST_PENALTY_DIRECT, args: HARVEST_PENALTY_ARGS },
+    IdlInstruction { name: "harvest_penalty_with_whirlpool", discriminant: 0x93, accounts: HARVEST_PENALTY_WITH_WHIRLPOOL, args: HARVEST_PENALTY_ARGS },
+    IdlInstruction { name: "sell_lucra_for_arb_funds_using_raydium", discriminant: 0x94, accounts: SELL_LUCRA_FOR_ARB_FUNDS_USING_RAYDIUM, args: SELL_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "sell_mata_for_arb_funds_using_raydium", discriminant: 0x94, accounts: SELL_MATA_FOR_ARB_FUNDS_USING_RAYDIUM, args: SELL_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "sell_lucra_for_arb_funds_using_orca", discriminant: 0x94, accounts: SELL_LUCRA_FOR_ARB_FUNDS_USING_ORCA, args: SELL_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "sell_mata_for_arb_funds_using_orca", discriminant: 0x94, accounts: SELL_MATA_FOR_ARB_FUNDS_USING_ORCA, args: SELL_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "sell_mata_for_arb_funds_using_whirlpool", discriminant: 0x94, accounts: SELL_MATA_FOR_ARB_FUNDS_USING_WHIRLPOOL, args: SELL_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "mint_mata_for_arb_funds_checking_raydium", discriminant: 0x97, accounts: MINT_MATA_FOR_ARB_FUNDS_CHECKING_RAYDIUM, args: MINT_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "mint_lucra_for_arb_funds_checking_raydium", discriminant: 0x97, accounts: MINT_LUCRA_FOR_ARB_FUNDS_CHECKING_RAYDIUM, args: MINT_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "mint_mata_for_arb_funds_checking_orca", discriminant: 0x97, accounts: MINT_MATA_FOR_ARB_FUNDS_CHECKING_ORCA, args: MINT_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "mint_lucra_for_arb_funds_checking_orca", discriminant: 0x97, accounts: MINT_LUCRA_FOR_ARB_FUNDS_CHECKING_ORCA, args: MINT_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "transfer_msol_for_arb_funds", discriminant: 0x97, accounts: TRANSFER_MSOL_FOR_ARB_FUNDS, args: MINT_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "transfer_msol_for_arb_funds_checking_orca", discriminant: 0x97, accounts: TRANSFER_MSOL_FOR_ARB_FUNDS_CHECKING_ORCA, args: MINT_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "transfer_msol_for_arb_funds_checking_raydium", discriminant: 0x97, accounts: TRANSFER_MSOL_FOR_ARB_FUNDS_CHECKING_RAYDIUM, args: MINT_FUNDS_FOR_ARB_ARGS },
+    IdlInstruction { name: "spend_arb_funds_for_lucra_using_raydium", discriminant: 0x95, accounts: SPEND_ARB_FUNDS_FOR_LUCRA_USING_RAYDIUM, args: BUY_BURN_FOR_ARB_ARGS },
+    IdlInstruction { name: "spend_arb_funds_for_mata_using_raydium", discriminant: 0x95, accounts: SPEND_ARB_FUNDS_FOR_MATA_USING_RAYDIUM, args: BUY_BURN_FOR_ARB_ARGS },
+    IdlInstruction { name: "spend_arb_funds_for_lucra_using_orca", discriminant: 0x95, accounts: SPEND_ARB_FUNDS_FOR_LUCRA_USING_ORCA, args: BUY_BURN_FOR_ARB_ARGS },
+    IdlInstruction { name: "spend_arb_funds_for_mata_using_orca", discriminant: 0x95, accounts: SPEND_ARB_FUNDS_FOR_MATA_USING_ORCA, args: BUY_BURN_FOR_ARB_ARGS },
+    IdlInstruction { name: "spend_arb_funds_for_mata_using_whirlpool", discriminant: 0x95, accounts: SPEND_ARB_FUNDS_FOR_MATA_USING_WHIRLPOOL, args: BUY_BURN_FOR_ARB_ARGS },
+    IdlInstruction { name: "clean_up_arb", discriminant: 0x96, accounts: CLEAN_UP_ARB, args: &[] },
+    IdlInstruction { name: "create_revenue_ledger", discriminant: 0x98, accounts: CREATE_REVENUE_LEDGER, args: &[] },
+    IdlInstruction { name: "global_settlement", discriminant: 0x99, accounts: GLOBAL_SETTLEMENT, args: GLOBAL_SETTLEMENT_ARGS },
+    IdlInstruction { name: "reclaim_excess", discriminant: 0x9a, accounts: RECLAIM_EXCESS, args: &[] },
+    IdlInstruction { name: "roll_arb_window", discriminant: 0x9b, accounts: ROLL_ARB_WINDOW, args: &[] },
+    IdlInstruction { name: "set_reward_metadata", discriminant: 0x9c, accounts: SET_REWARD_METADATA, args: SET_REWARD_METADATA_ARGS },
+    IdlInstruction { name: "freeze_staking_account", discriminant: 0x9d, accounts: FREEZE_STAKING_ACCOUNT, args: &[] },
+    IdlInstruction { name: "unfreeze_staking_account", discriminant: 0x9e, accounts: UNFREEZE_STAKING_ACCOUNT, args: &[] },
+    IdlInstruction { name: "set_staking_recovery_key", discriminant: 0x9f, accounts: SET_STAKING_RECOVERY_KEY, args: SET_STAKING_RECOVERY_KEY_ARGS },
+    IdlInstruction { name: "set_lp_collateral", discriminant: 0xa0, accounts: SET_LP_COLLATERAL, args: SET_LP_COLLATERAL_ARGS },
+    IdlInstruction { name: "sweep_expired_reward", discriminant: 0xa1, accounts: SWEEP_EXPIRED_REWARD, args: &[] },
+    IdlInstruction { name: "set_min_drop_amount", discriminant: 0xa3, accounts: SET_MIN_DROP_AMOUNT, args: SET_MIN_DROP_AMOUNT_ARGS },
+    IdlInstruction { name: "set_reward_redemption_deadline", discriminant: 0xa4, accounts: SET_REWARD_REDEMPTION_DEADLINE, args: SET_REWARD_REDEMPTION_DEADLINE_ARGS },
+    IdlInstruction { name: "close_reward_program", discriminant: 0xa5, accounts: CLOSE_REWARD_PROGRAM, args: &[] },
+    IdlInstruction { name: "migrate_account_layout", discriminant: 0xa6, accounts: MIGRATE_ACCOUNT_LAYOUT, args: &[] },
+    IdlInstruction { name: "migrate_arb_state_layout", discriminant: 0xa6, accounts: MIGRATE_ARB_STATE_LAYOUT, args: &[] },
+    IdlInstruction { name: "correct_price_history", discriminant: 0xa7, accounts: CORRECT_PRICE_HISTORY, args: CORRECT_PRICE_HISTORY_ARGS },
+    IdlInstruction { name: "recompute_penalty", discriminant: 0xa8, accounts: RECOMPUTE_PENALTY, args: &[] },
+    IdlInstruction { name: "compute_tvl", discriminant: 0xa9, accounts: COMPUTE_TVL, args: &[] },
+    IdlInstruction { name: "rollover_loan", discriminant: 0xaa, accounts: ROLLOVER_LOAN, args: ROLLOVER_LOAN_ARGS },
+    IdlInstruction { name: "repay_loan_partial", discriminant: 0xab, accounts: REPAY_LOAN_PARTIAL, args: REPAY_LOAN_PARTIAL_ARGS },
+    IdlInstruction { name: "repay_loan_partial_with_locked_stake", discriminant: 0xab, accounts: REPAY_LOAN_PARTIAL_WITH_LOCKED_STAKE, args: REPAY_LOAN_PARTIAL_ARGS },
+    IdlInstruction { name: "borrow_more", discriminant: 0xac, accounts: BORROW_MORE, args: BORROW_MORE_ARGS },
+    IdlInstruction { name: "set_liquidation_params", discriminant: 0xad, accounts: SET_LIQUIDATION_PARAMS, args: SET_LIQUIDATION_PARAMS_ARGS },
+    IdlInstruction { name: "liquidate_loan", discriminant: 0xae, accounts: LIQUIDATE_LOAN, args: LIQUIDATE_LOAN_ARGS },
+    IdlInstruction { name: "liquidate_loan_with_locked_stake", discriminant: 0xae, accounts: LIQUIDATE_LOAN_WITH_LOCKED_STAKE, args: LIQUIDATE_LOAN_ARGS },
+    IdlInstruction { name: "liquidate_loan_with_orca", discriminant: 0xae, accounts: LIQUIDATE_LOAN_WITH_ORCA, args: LIQUIDATE_LOAN_ARGS },
+    IdlInstruction { name: "liquidate_loan_with_locked_stake_with_orca", discriminant: 0xae, accounts: LIQUIDATE_LOAN_WITH_LOCKED_STAKE_WITH_ORCA, args: LIQUIDATE_LOAN_ARGS },
+    IdlInstruction { name: "liquidate_lp_backed_loan", discriminant: 0xae, accounts: LIQUIDATE_LP_BACKED_LOAN, args: LIQUIDATE_LOAN_ARGS },
+    IdlInstruction { name: "liquidate_native_sol_loan", discriminant: 0xae, accounts: LIQUIDATE_NATIVE_SOL_LOAN, args: LIQUIDATE_LOAN_ARGS },
+    IdlInstruction { name: "transfer_loan_ownership", discriminant: 0xaf, accounts: TRANSFER_LOAN_OWNERSHIP, args: &[] },
+    IdlInstruction { name: "transfer_loan_ownership_with_locked_stake", discriminant: 0xaf, accounts: TRANSFER_LOAN_OWNERSHIP_WITH_LOCKED_STAKE, args: &[] },
+    IdlInstruction { name: "close_stake_balance", discriminant: 0xb0, accounts: CLOSE_STAKE_BALANCE, args: &[] },
+    IdlInstruction { name: "close_staking_account", discriminant: 0xb1, accounts: CLOSE_STAKING_ACCOUNT, args: &[] },
+    IdlInstruction { name: "cancel_unstake", discriminant: 0xb2, accounts: CANCEL_UNSTAKE, args: &[] },
+    IdlInstruction { name: "propose_authority", discriminant: 0xb3, accounts: PROPOSE_AUTHORITY, args: PROPOSE_AUTHORITY_ARGS },
+    IdlInstruction { name: "accept_authority", discriminant: 0xb4, accounts: ACCEPT_AUTHORITY, args: &[] },
+    IdlInstruction { name: "set_pause", discriminant: 0xb5, accounts: SET_PAUSE, args: SET_PAUSE_ARGS },
+    IdlInstruction { name: "withdraw_collateral", discriminant: 0xb6, accounts: WITHDRAW_COLLATERAL, args: WITHDRAW_COLLATERAL_ARGS },
+    IdlInstruction { name: "migrate_stake_balance", discriminant: 0xb7, accounts: MIGRATE_STAKE_BALANCE, args: &[] },
+    IdlInstruction { name: "quote_mata_loan", discriminant: 0xb8, accounts: QUOTE_MATA_LOAN, args: QUOTE_MATA_LOAN_ARGS },
+    IdlInstruction { name: "create_penalty_history", discriminant: 0xb9, accounts: CREATE_PENALTY_HISTORY, args: &[] },
+    IdlInstruction { name: "create_event_queue", discriminant: 0xba, accounts: CREATE_EVENT_QUEUE, args: &[] },
+    IdlInstruction { name: "claim_reward_v2", discriminant: 0xbb, accounts: CLAIM_REWARD_V2, args: CLAIM_REWARD_V2_ARGS },
+    IdlInstruction { name: "update_harvest_params", discriminant: 0xbc, accounts: UPDATE_HARVEST_PARAMS, args: UPDATE_HARVEST_PARAMS_ARGS },
+    IdlInstruction { name: "create_sol_vault", discriminant: 0xbd, accounts: CREATE_SOL_VAULT, args: &[] },
+    IdlInstruction { name: "create_price_history_page", discriminant: 0xbe, accounts: CREATE_PRICE_HISTORY_PAGE, args: &[] },
+    IdlInstruction { name: "register_keeper", discriminant: 0xbf, accounts: REGISTER_KEEPER, args: REGISTER_KEEPER_ARGS },
+    IdlInstruction { name: "remove_keeper", discriminant: 0xc0, accounts: REMOVE_KEEPER, args: &[] },
+    IdlInstruction { name: "redeem_reward_tokens_for_msol", discriminant: 0xc1, accounts: REDEEM_REWARD_TOKENS_FOR_MSOL, args: REDEEM_REWARD_TOKENS_ARGS },
+    IdlInstruction { name: "add_collateral_type", discriminant: 0xc2, accounts: ADD_COLLATERAL_TYPE, args: ADD_COLLATERAL_TYPE_ARGS },
+    IdlInstruction { name: "set_collateral_type", discriminant: 0xc3, accounts: SET_COLLATERAL_TYPE, args: SET_COLLATERAL_TYPE_ARGS },
+    IdlInstruction { name: "rebalance_rewards_vault", discriminant: 0xc4, accounts: REBALANCE_REWARDS_VAULT, args: REBALANCE_REWARDS_VAULT_ARGS },
+    IdlInstruction { name: "add_transfer_destination", discriminant: 0xc5, accounts: ADD_TRANSFER_DESTINATION, args: &[] },
+    IdlInstruction { name: "remove_transfer_destination", discriminant: 0xc6, accounts: REMOVE_TRANSFER_DESTINATION, args: &[] },
+];
+
+pub enum IdlType {
+    Primitive(&'static str),
+    Defined(&'static str),
+    Array(&'static IdlType, usize),
+}
+
+pub struct IdlField {
+    pub name: &'static str,
+    pub ty: IdlType,
+    pub offset: usize,
+}
+
+pub struct IdlStruct {
+    pub name: &'static str,
+    pub size: usize,
+    pub fields: &'static [IdlField],
+}
+
+const fn field(name: &'static str, ty: IdlType, offset: usize) -> IdlField {
+    IdlField { name, ty, offset }
+}
+
+const U8: IdlType = IdlType::Primitive("u8");
+const U16: IdlType = IdlType::Primitive("u16");
+const U32: IdlType = IdlType::Primitive("u32");
+const U64: IdlType = IdlType::Primitive("u64");
+const I64: IdlType = IdlType::Primitive("i64");
+const PUBKEY: IdlType = IdlType::Primitive("publicKey");
+
+// MetaData is laid out by the state module, it's described as its raw bytes
+const META_DATA_LEN: usize = size_of::<MetaData>();
+const META_DATA: IdlType = IdlType::Array(&U8, META_DATA_LEN);
+
+/// Program owned accounts whose whole layout is described, in the order the IDL lists them. Offsets are from
+/// the start of the account data, there is no discriminator in front of it.
+pub const STATE_ACCOUNTS: &[IdlStruct] = &[
+    IdlStruct {
+        name: "CollateralRegistry",
+        size: size_of::<CollateralRegistry>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("system_state", PUBKEY, META_DATA_LEN),
+            field("collateral_type_count", U64, META_DATA_LEN + 32),
+            field("collateral_types", IdlType::Array(&IdlType::Defined("CollateralType"), MAX_COLLATERAL_TYPES), META_DATA_LEN + 40),
+        ],
+    },
+    IdlStruct {
+        name: "EventQueue",
+        size: size_of::<EventQueue>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("system_state", PUBKEY, META_DATA_LEN),
+            field("sequence", U64, META_DATA_LEN + 32),
+            field("events", IdlType::Array(&IdlType::Defined("Event"), EVENT_QUEUE_LENGTH), META_DATA_LEN + 40),
+        ],
+    },
+    IdlStruct {
+        name: "KeeperRegistry",
+        size: size_of::<KeeperRegistry>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("system_state", PUBKEY, META_DATA_LEN),
+            field("keeper_count", U64, META_DATA_LEN + 32),
+            field("keepers", IdlType::Array(&IdlType::Defined("Keeper"), MAX_KEEPERS), META_DATA_LEN + 40),
+        ],
+    },
+    IdlStruct {
+        name: "LoanRegistry",
+        size: size_of::<LoanRegistry>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("owner", PUBKEY, META_DATA_LEN),
+            field("loan_count", U64, META_DATA_LEN + 32),
+        ],
+    },
+    IdlStruct {
+        name: "PenaltyHistory",
+        size: size_of::<PenaltyHistory>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("loan", PUBKEY, META_DATA_LEN),
+            field("head", U64, META_DATA_LEN + 32),
+            field("entries", IdlType::Array(&IdlType::Defined("PenaltyHistoryEntry"), PENALTY_HISTORY_LENGTH), META_DATA_LEN + 40),
+        ],
+    },
+    IdlStruct {
+        name: "PriceCorrection",
+        size: size_of::<PriceCorrection>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("system_state", PUBKEY, META_DATA_LEN),
+            field("date", I64, META_DATA_LEN + 32),
+            field("original_sol_price", U64, META_DATA_LEN + 40),
+            field("original_lucra_price", U64, META_DATA_LEN + 48),
+            field("corrected_sol_price", U64, META_DATA_LEN + 56),
+            field("corrected_lucra_price", U64, META_DATA_LEN + 64),
+            field("corrected_at", I64, META_DATA_LEN + 72),
+        ],
+    },
+    IdlStruct {
+        name: "PriceHistoryHeader",
+        size: size_of::<PriceHistoryHeader>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("price_history", PUBKEY, META_DATA_LEN),
+            field("page_count", U64, META_DATA_LEN + 32),
+            field("active_page", U64, META_DATA_LEN + 40),
+            field("first_date", I64, META_DATA_LEN + 48),
+            field("last_date", I64, META_DATA_LEN + 56),
+        ],
+    },
+    IdlStruct {
+        name: "RevenueLedger",
+        size: size_of::<RevenueLedger>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("system_state", PUBKEY, META_DATA_LEN),
+            field("head", U64, META_DATA_LEN + 32),
+            field("entries", IdlType::Array(&IdlType::Defined("RevenueEntry"), REVENUE_LEDGER_LENGTH), META_DATA_LEN + 40),
+        ],
+    },
+    IdlStruct {
+        name: "TransferAllowlist",
+        size: size_of::<TransferAllowlist>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("system_state", PUBKEY, META_DATA_LEN),
+            field("destination_count", U64, META_DATA_LEN + 32),
+            field("destinations", IdlType::Array(&IdlType::Defined("TransferDestination"), MAX_TRANSFER_DESTINATIONS), META_DATA_LEN + 40),
+        ],
+    },
+    IdlStruct {
+        name: "TvlSnapshot",
+        size: size_of::<TvlSnapshot>(),
+        fields: &[
+            field("meta_data", META_DATA, 0),
+            field("system_state", PUBKEY, META_DATA_LEN),
+            field("timestamp", I64, META_DATA_LEN + 32),
+            field("sol_price", U64, META_DATA_LEN + 40),
+            field("mata_price", U64, META_DATA_LEN + 48),
+            field("lucra_price", U64, META_DATA_LEN + 56),
+            field("components", IdlType::Defined("TvlComponents"), META_DATA_LEN + 64),
+            field("total", U64, META_DATA_LEN + 112),
+        ],
+    },
+];
+
+/// The structs the state accounts embed
+pub const STATE_TYPES: &[IdlStruct] = &[
+    IdlStruct {
+        name: "CollateralType",
+        size: size_of::<CollateralType>(),
+        fields: &[
+            field("mint", PUBKEY, 0),
+            field("oracle", PUBKEY, 32),
+            field("vault", PUBKEY, 64),
+            field("haircut_bps", U32, 96),
+            field("decimals", U8, 100),
+            field("enabled", U8, 101),
+            field("vault_authority_bump_seed", U8, 102),
+            field("padding", IdlType::Array(&U8, 1), 103),
+        ],
+    },
+    IdlStruct {
+        name: "Event",
+        size: size_of::<Event>(),
+        fields: &[
+            field("sequence", U64, 0),
+            field("slot", U64, 8),
+            field("principal", PUBKEY, 16),
+            field("amounts", IdlType::Array(&U64, 2), 48),
+            field("kind", U8, 64),
+            field("padding", IdlType::Array(&U8, 7), 65),
+        ],
+    },
+    IdlStruct {
+        name: "Keeper",
+        size: size_of::<Keeper>(),
+        fields: &[
+            field("address", PUBKEY, 0),
+            field("multiplier", U32, 32),
+        ],
+    },
+    IdlStruct {
+        name: "PenaltyHistoryEntry",
+        size: size_of::<PenaltyHistoryEntry>(),
+        fields: &[
+            field("date", I64, 0),
+            field("charge", U64, 8),
+            field("multiplier", U16, 16),
+            field("padding", IdlType::Array(&U8, 6), 18),
+        ],
+    },
+    IdlStruct {
+        name: "RevenueEntry",
+        size: size_of::<RevenueEntry>(),
+        fields: &[
+            field("epoch_start", I64, 0),
+            field("origination_fees", U64, 8),
+            field("arb_profit_msol", U64, 16),
+            field("penalties_captured_sol", U64, 24),
+            field("keeper_fees_paid", U64, 32),
+        ],
+    },
+    IdlStruct {
+        name: "TransferDestination",
+        size: size_of::<TransferDestination>(),
+        fields: &[
+            field("pubkey", PUBKEY, 0),
+            field("activates_at", I64, 32),
+        ],
+    },
+    IdlStruct {
+        name: "TvlComponents",
+        size: size_of::<TvlComponents>(),
+        fields: &[
+            field("msol_vault", U64, 0),
+            field("arb_coffer", U64, 8),
+            field("rewards_vault", U64, 16),
+            field("wsol_holding_vault", U64, 24),
+            field("mata_holding_vault", U64, 32),
+            field("lucra_holding_vault", U64, 40),
+        ],
+    },
+];
+
+/// Anchor style IDL for every entry in `INSTRUCTIONS` and `STATE_ACCOUNTS`
+pub fn generate() -> String {
+    let instructions = INSTRUCTIONS
+        .iter()
+        .map(|instruction| {
+            let accounts = instruction.accounts
+                .iter()
+                .map(|account| {
+                    let mut fields = vec![
+                        ("name", Json::String(camel_case(account.name))),
+                        ("isMut", Json::Bool(account.writable)),
+                        ("isSigner", Json::Bool(account.signer)),
+                    ];
+                    if account.optional {
+                        fields.push(("isOptional", Json::Bool(true)));
+                    }
+                    Json::Object(fields)
+                })
+                .collect();
+            let args = instruction.args
+                .iter()
+                .map(|arg| Json::Object(vec![
+                    ("name", Json::String(camel_case(arg.name))),
+                    ("type", arg_type(arg.ty)),
+                ]))
+                .collect();
+
+            Json::Object(vec![
+                ("name", Json::String(camel_case(instruction.name))),
+                ("accounts", Json::Array(accounts)),
+                ("args", Json::Array(args)),
+                ("discriminant", Json::Number(instruction.discriminant.into())),
+            ])
+        })
+        .collect();
+
+    let idl = Json::Object(vec![
+        ("version", Json::String(IDL_VERSION.to_string())),
+        ("name", Json::String("lucra".to_string())),
+        ("instructions", Json::Array(instructions)),
+        ("accounts", Json::Array(STATE_ACCOUNTS.iter().map(idl_struct).collect())),
+        ("types", Json::Array(STATE_TYPES.iter().map(idl_struct).collect())),
+        ("metadata", Json::Object(vec![
+            ("address", Json::String(crate::id().to_string())),
+            ("origin", Json::String("native".to_string())),
+            ("encoding", Json::String("bincode".to_string())),
+        ])),
+    ]);
+
+    let mut out = String::new();
+    idl.write(&mut out, 0);
+    out.push('\n');
+    out
+}
+
+fn idl_struct(idl_struct: &IdlStruct) -> Json {
+    let fields = idl_struct.fields
+        .iter()
+        .map(|field| Json::Object(vec![
+            ("name", Json::String(camel_case(field.name))),
+            ("type", field_type(&field.ty)),
+            ("offset", Json::Number(field.offset as u64)),
+        ]))
+        .collect();
+
+    Json::Object(vec![
+        ("name", Json::String(idl_struct.name.to_string())),
+        ("size", Json::Number(idl_struct.size as u64)),
+        ("type", Json::Object(vec![
+            ("kind", Json::String("struct".to_string())),
+            ("fields", Json::Array(fields)),
+        ])),
+    ])
+}
+
+fn field_type(ty: &IdlType) -> Json {
+    match ty {
+        IdlType::Primitive(name) => Json::String(name.to_string()),
+        IdlType::Defined(name) => Json::Object(vec![("defined", Json::String(name.to_string()))]),
+        IdlType::Array(inner, len) => Json::Object(vec![
+            ("array", Json::Array(vec![field_type(inner), Json::Number(*len as u64)])),
+        ]),
+    }
+}
+
+fn arg_type(ty: &str) -> Json {
+    match ty.strip_prefix("option<").and_then(|inner| inner.strip_suffix('>')) {
+        Some(inner) => Json::Object(vec![("option", Json::String(inner.to_string()))]),
+        None => Json::String(ty.to_string()),
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    let mut out = String::new();
+    for (index, part) in name.split('_').enumerate() {
+        let mut chars = part.chars();
+        match chars.next() {
+            Some(first) if index > 0 => {
+                out.extend(first.to_uppercase());
+                out.push_str(chars.as_str());
+            }
+            _ => out.push_str(part),
+        }
+    }
+    out
+}
+
+// Just enough JSON to write the IDL, laid out the same way as a two space pretty printer
+enum Json {
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn write(&self, out: &mut String, depth: usize) {
+        match self {
+            Json::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Json::Number(value) => out.push_str(&value.to_string()),
+            Json::String(value) => {
+                out.push('"');
+                out.push_str(value);
+                out.push('"');
+            }
+            Json::Array(items) if items.is_empty() => out.push_str("[]"),
+            Json::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Json::Array(items) => {
+                out.push_str("[\n");
+                for (index, item) in items.iter().enumerate() {
+                    push_indent(out, depth + 1);
+                    item.write(out, depth + 1);
+                    if index + 1 < items.len() {
+                        out.push(',');
+                    }
+                    out.push('\n');
+                }
+                push_indent(out, depth);
+                out.push(']');
+            }
+            Json::Object(fields) => {
+                out.push_str("{\n");
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    push_indent(out, depth + 1);
+                    out.push('"');
+                    out.push_str(key);
+                    out.push_str("\": ");
+                    value.write(out, depth + 1);
+                    if index + 1 < fields.len() {
+                        out.push(',');
+                    }
+                    out.push('\n');
+                }
+                push_indent(out, depth);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn push_indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use solana_program::{instruction::Instruction as SolInstruction, pubkey::Pubkey};
+    use crate::{
+        instruction::*,
+        state::staking::StakingTimeframe,
+        units::{Bps, CollateralRatio, Lamports, Mata, Percent, RewardFee},
+    };
+
+    fn key(n: u8) -> Pubkey {
+        Pubkey::new_from_array([n; 32])
+    }
+
+    fn built_instructions() -> Vec<(&'static str, SolInstruction)> {
+        vec![
+            ("initialize", initialize(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), Lamports(1), CollateralRatio(1), 1, false, false, false, false, 1, 1, Mata(1), Percent(1), Mata(1), Bps(1), Bps(1), Bps(1), Bps(1), 1, 1, Bps(1), &key(16))),
+            ("update_state", update_state(&key(1), &key(2), Lamports(1), CollateralRatio(1), false, false, false, false, 1, 1, Mata(1), Lamports(1), RewardFee(1), Percent(1), Mata(1), Bps(1), key(17), false, 1, Lamports(1), Bps(1), Bps(1), Bps(1), 1, Bps(1), key(18), 1, Bps(1), key(19), 1, key(20), Bps(1), false, key(21), false)),
+            ("create_mata_loan", create_mata_loan_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), 1)),
+            ("create_mata_loan_with_locked_stake", create_mata_loan_with_locked_stake_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), 1)),
+            ("close_mata_loan", close_mata_loan(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), false)),
+            ("close_mata_loan_with_locked_stake", close_mata_loan_with_locked_stake(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), false)),
+            ("create_lp_backed_mata_loan", create_lp_backed_mata_loan(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), 1)),
+            ("close_lp_backed_mata_loan", close_lp_backed_mata_loan(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7))),
+            ("create_native_sol_mata_loan", create_native_sol_mata_loan(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), 1)),
+            ("close_native_sol_mata_loan", close_native_sol_mata_loan(&key(1), &key(2), &key(3), &key(4), &key(5))),
+            ("create_staking_account", create_staking_account(&key(1), &key(2), &key(3), &key(4), Some(&key(5)))),
+            ("create_stake_balance", create_stake_balance(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), 1, StakingTimeframe::try_from(0).unwrap())),
+            ("deposit_stake", deposit_stake(&key(1), &key(2), &key(3), &key(4), &key(5), 1)),
+            ("stake", stake_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), 1)),
+            ("start_unstake", start_unstake_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), 1)),
+            ("end_unstake", end_unstake_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6))),
+            ("withdraw_stake", withdraw_stake_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), 1)),
+            ("claim_reward", claim_reward(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11))),
+            ("drop_reward", drop_reward_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10))),
+            ("transfer_funds", transfer_funds_derived(&key(1), &key(2), &key(3), 1)),
+            ("create_price_history", create_price_history(&key(1))),
+            ("update_price_history", update_price_history(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9))),
+            ("redeem_reward_tokens", redeem_reward_tokens(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), 1)),
+            ("add_collateral", add_collateral_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), 1)),
+            ("add_collateral_with_locked_stake", add_collateral_with_locked_stake_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), 1)),
+            ("add_collateral_msol", add_collateral_msol(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), 1)),
+            ("add_collateral_msol_with_locked_stake", add_collateral_msol_with_locked_stake(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), 1)),
+            ("add_native_sol_collateral", add_native_sol_collateral(&key(1), &key(2), &key(3), 1)),
+            ("determine_penalty", determine_penalty(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8))),
+            ("harvest_penalty_with_orca", harvest_penalty_with_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), 1)),
+            ("harvest_penalty_native_sol_with_orca", harvest_native_sol_penalty_with_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), 1)),
+            ("harvest_penalty_with_raydium", harvest_penalty_with_raydium(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), &key(24), &key(25), &key(26), &key(27), &key(28), &key(29), &key(30), 1)),
+            ("harvest_penalty_direct", harvest_penalty_direct(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), 1)),
+            ("harvest_penalty_with_whirlpool", harvest_penalty_with_whirlpool(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &[key(21), key(22), key(23)], 1)),
+            ("sell_lucra_for_arb_funds_using_raydium", sell_lucra_for_arb_funds_using_raydium(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), &key(24), &key(25), &key(26), 1)),
+            ("sell_mata_for_arb_funds_using_raydium", sell_mata_for_arb_funds_using_raydium(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), &key(24), &key(25), &key(26), &key(27), 1)),
+            ("sell_lucra_for_arb_funds_using_orca", sell_lucra_for_arb_funds_using_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), 1, 1)),
+            ("sell_mata_for_arb_funds_using_orca", sell_mata_for_arb_funds_using_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), 1, 1)),
+            ("sell_mata_for_arb_funds_using_whirlpool", sell_mata_for_arb_funds_using_whirlpool(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &[key(18), key(19), key(20)], 1, 1)),
+            ("mint_mata_for_arb_funds_checking_raydium", mint_mata_for_arb_funds_checking_raydium(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), 1)),
+            ("mint_lucra_for_arb_funds_checking_raydium", mint_lucra_for_arb_funds_checking_raydium(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), 1)),
+            ("mint_mata_for_arb_funds_checking_orca", mint_mata_for_arb_funds_checking_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), 1)),
+            ("mint_lucra_for_arb_funds_checking_orca", mint_lucra_for_arb_funds_checking_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), 1)),
+            ("transfer_msol_for_arb_funds", transfer_msol_for_arb_funds(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), &key(24), &key(25), 1)),
+            ("transfer_msol_for_arb_funds_checking_orca", transfer_msol_for_arb_funds_checking_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), 1)),
+            ("transfer_msol_for_arb_funds_checking_raydium", transfer_msol_for_arb_funds_checking_raydium(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), 1)),
+            ("spend_arb_funds_for_lucra_using_raydium", spend_arb_funds_for_lucra_using_raydium(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), &key(24), 1)),
+            ("spend_arb_funds_for_mata_using_raydium", spend_arb_funds_for_mata_using_raydium(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), &key(24), &key(25), &key(26), 1, 1)),
+            ("spend_arb_funds_for_lucra_using_orca", spend_arb_funds_for_lucra_using_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), 1)),
+            ("spend_arb_funds_for_mata_using_orca", spend_arb_funds_for_mata_using_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), 1, 1)),
+            ("spend_arb_funds_for_mata_using_whirlpool", spend_arb_funds_for_mata_using_whirlpool(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &[key(17), key(18), key(19)], 1, 1)),
+            ("clean_up_arb", clean_up_arb_derived(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15))),
+            ("create_revenue_ledger", create_revenue_ledger(&key(1), &key(2))),
+            ("global_settlement", global_settlement(&key(1), 1)),
+            ("reclaim_excess", reclaim_excess_derived(&key(1), &key(2), &key(3))),
+            ("roll_arb_window", roll_arb_window(&key(1), &key(2), &key(3), &key(4))),
+            ("set_reward_metadata", set_reward_metadata(&key(1), &key(2), String::new(), String::new(), String::new())),
+            ("freeze_staking_account", freeze_staking_account(&key(1), &key(2))),
+            ("unfreeze_staking_account", unfreeze_staking_account(&key(1), &key(2))),
+            ("set_staking_recovery_key", set_staking_recovery_key(&key(1), &key(2), Some(&key(3)), key(4))),
+            ("set_lp_collateral", set_lp_collateral(&key(1), &key(2), CollateralRatio(1))),
+            ("sweep_expired_reward", sweep_expired_reward(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8))),
+            ("set_min_drop_amount", set_min_drop_amount(&key(1), &key(2), 1)),
+            ("set_reward_redemption_deadline", set_reward_redemption_deadline(&key(1), 1, 1, Bps(1))),
+            ("close_reward_program", close_reward_program(&key(1))),
+            ("migrate_account_layout", migrate_account_layout(&key(1), &key(2), true)),
+            ("migrate_arb_state_layout", migrate_arb_state_layout(&key(1), &key(2), &key(3))),
+            ("correct_price_history", correct_price_history(&key(1), &key(2), 1, 1, 1)),
+            ("recompute_penalty", recompute_penalty(&key(1), &key(2), &key(3))),
+            ("compute_tvl", compute_tvl(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16))),
+            ("rollover_loan", rollover_loan(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), 1)),
+            ("repay_loan_partial", repay_loan_partial(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), 1, true)),
+            ("repay_loan_partial_with_locked_stake", repay_loan_partial_with_locked_stake(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), 1, true)),
+            ("borrow_more", borrow_more(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), 1)),
+            ("set_liquidation_params", set_liquidation_params(&key(1), CollateralRatio(1), Bps(1))),
+            ("liquidate_loan", liquidate_loan(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11))),
+            ("liquidate_loan_with_locked_stake", liquidate_loan_with_locked_stake(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12))),
+            ("liquidate_loan_with_orca", liquidate_loan_with_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23))),
+            ("liquidate_loan_with_locked_stake_with_orca", liquidate_loan_with_locked_stake_with_orca(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), &key(24))),
+            ("liquidate_lp_backed_loan", liquidate_lp_backed_loan(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16))),
+            ("liquidate_native_sol_loan", liquidate_native_sol_loan(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7))),
+            ("transfer_loan_ownership", transfer_loan_ownership(&key(1), &key(2), &key(3))),
+            ("transfer_loan_ownership_with_locked_stake", transfer_loan_ownership_with_locked_stake(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9))),
+            ("close_stake_balance", close_stake_balance(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6))),
+            ("close_staking_account", close_staking_account(&key(1), &key(2))),
+            ("cancel_unstake", cancel_unstake(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10))),
+            ("propose_authority", propose_authority(&key(1), &key(2), &key(3))),
+            ("accept_authority", accept_authority(&key(1), &key(2))),
+            ("set_pause", set_pause(&key(1), &key(2), true, false, true)),
+            ("withdraw_collateral", withdraw_collateral(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), 1)),
+            ("migrate_stake_balance", migrate_stake_balance(&key(1), &key(2), &key(3))),
+            ("quote_mata_loan", quote_mata_loan(&key(1), &key(2), &key(3), &key(4), &key(5), 1)),
+            ("create_penalty_history", create_penalty_history(&key(1), &key(2))),
+            ("create_event_queue", create_event_queue(&key(1))),
+            ("claim_reward_v2", claim_reward_v2(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), &key(9), &key(10), &key(11), &key(12), true)),
+            ("update_harvest_params", update_harvest_params(&key(1), Some(Lamports(1)), Some(RewardFee(1)))),
+            ("create_sol_vault", create_sol_vault(&key(1))),
+            ("create_price_history_page", create_price_history_page(&key(1), 1, &key(2))),
+            ("register_keeper", register_keeper(&key(1), &key(2), Bps(1), &key(3))),
+            ("remove_keeper", remove_keeper(&key(1), &key(2))),
+            ("redeem_reward_tokens_for_msol", redeem_reward_tokens_for_msol(&key(1), &key(2), &key(3), &key(4), &key(5), &key(6), &key(7), &key(8), 1)),
+            ("add_collateral_type", add_collateral_type(&key(1), &key(2), &key(3), &key(4), Bps(1), &key(5))),
+            ("set_collateral_type", set_collateral_type(&key(1), 1, &key(2), Bps(1), true)),
+            ("rebalance_rewards_vault", rebalance_rewards_vault(&key(1), &key(2), &key(3), &key(4), &[], 1)),
+            ("add_transfer_destination", add_transfer_destination(&key(1), &key(2), &key(3))),
+            ("remove_transfer_destination", remove_transfer_destination(&key(1), &key(2))),
+        ]
+    }
+
+    #[test]
+    fn test_every_builder_matches_its_table_entry() {
+        let built = built_instructions();
+        assert_eq!(built.len(), INSTRUCTIONS.len());
+
+        for ((name, instruction), entry) in built.iter().zip(INSTRUCTIONS) {
+            assert_eq!(*name, entry.name);
+            // Optional accounts are passed so the full layout is checked
+            assert_eq!(instruction.accounts.len(), entry.accounts.len(), "{}", name);
+            for (index, (meta, account)) in instruction.accounts.iter().zip(entry.accounts).enumerate() {
+                assert_eq!(meta.is_writable, account.writable, "{} account {}", name, index);
+                assert_eq!(meta.is_signer, account.signer, "{} account {}", name, index);
+            }
+            assert_eq!(instruction.data[0], entry.discriminant, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_every_builder_round_trips() {
+        for (name, instruction) in built_instructions() {
+            let decoded = Instruction::try_from_slice_versioned(&instruction.data).unwrap();
+            assert_eq!(decoded.pack(), instruction.data, "{}", name);
+
+            // Clients that still send the legacy bincode layout get the same instruction
+            let legacy = bincode::serialize(&decoded).unwrap();
+            assert_eq!(Instruction::try_from_slice_versioned(&legacy).unwrap(), decoded, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_every_tag_has_an_entry() {
+        let mut discriminants: Vec<u8> = INSTRUCTIONS.iter().map(|entry| entry.discriminant).collect();
+        discriminants.sort_unstable();
+        discriminants.dedup();
+        assert_eq!(discriminants, INSTRUCTION_TAGS);
+    }
+
+    #[test]
+    fn test_checked_in_idl_is_up_to_date() {
+        let idl = generate();
+
+        let target = concat!(env!("CARGO_MANIFEST_DIR"), "/target/idl");
+        std::fs::create_dir_all(target).unwrap();
+        std::fs::write(format!("{}/lucra.json", target), &idl).unwrap();
+
+        // Copy target/idl/lucra.json over idl/lucra.json when a layout changes on purpose
+        assert!(idl == include_str!("../idl/lucra.json"), "idl/lucra.json is out of date");
+    }
+
+    macro_rules! assert_offsets {
+        ($table:expr, $ty:ident, [$($field:ident),* $(,)?]) => {{
+            let entry = $table.iter().find(|entry| entry.name == stringify!($ty)).unwrap();
+            let value = <$ty as bytemuck::Zeroable>::zeroed();
+            let base = &value as *const $ty as usize;
+            let offsets = [$((stringify!($field), &value.$field as *const _ as usize - base)),*];
+
+            assert_eq!(entry.size, size_of::<$ty>(), "{}", entry.name);
+            assert_eq!(entry.fields.len(), offsets.len(), "{}", entry.name);
+            for (field, &(name, offset)) in entry.fields.iter().zip(offsets.iter()) {
+                assert_eq!(field.name, name, "{}", entry.name);
+                assert_eq!(field.offset, offset, "{}.{}", entry.name, name);
+            }
+        }};
+    }
+
+    #[test]
+    fn test_state_offsets_match_the_structs() {
+        assert_offsets!(STATE_ACCOUNTS, CollateralRegistry, [meta_data, system_state, collateral_type_count, collateral_types]);
+        assert_offsets!(STATE_ACCOUNTS, EventQueue, [meta_data, system_state, sequence, events]);
+        assert_offsets!(STATE_ACCOUNTS, KeeperRegistry, [meta_data, system_state, keeper_count, keepers]);
+        assert_offsets!(STATE_ACCOUNTS, LoanRegistry, [meta_data, owner, loan_count]);
+        assert_offsets!(STATE_ACCOUNTS, PenaltyHistory, [meta_data, loan, head, entries]);
+        assert_offsets!(STATE_ACCOUNTS, PriceCorrection, [
+            meta_data,
+            system_state,
+            date,
+            original_sol_price,
+            original_lucra_price,
+            corrected_sol_price,
+            corrected_lucra_price,
+            corrected_at,
+        ]);
+        assert_offsets!(STATE_ACCOUNTS, PriceHistoryHeader, [meta_data, price_history, page_count, active_page, first_date, last_date]);
+        assert_offsets!(STATE_ACCOUNTS, RevenueLedger, [meta_data, system_state, head, entries]);
+        assert_offsets!(STATE_ACCOUNTS, TransferAllowlist, [meta_data, system_state, destination_count, destinations]);
+        assert_offsets!(STATE_ACCOUNTS, TvlSnapshot, [meta_data, system_state, timestamp, sol_price, mata_price, lucra_price, components, total]);
+
+        assert_offsets!(STATE_TYPES, CollateralType, [mint, oracle, vault, haircut_bps, decimals, enabled, vault_authority_bump_seed, padding]);
+        assert_offsets!(STATE_TYPES, Event, [sequence, slot, principal, amounts, kind, padding]);
+        assert_offsets!(STATE_TYPES, Keeper, [address, multiplier]);
+        assert_offsets!(STATE_TYPES, PenaltyHistoryEntry, [date, charge, multiplier, padding]);
+        assert_offsets!(STATE_TYPES, RevenueEntry, [epoch_start, origination_fees, arb_profit_msol, penalties_captured_sol, keeper_fees_paid]);
+        assert_offsets!(STATE_TYPES, TransferDestination, [pubkey, activates_at]);
+        assert_offsets!(STATE_TYPES, TvlComponents, [msol_vault, arb_coffer, rewards_vault, wsol_holding_vault, mata_holding_vault, lucra_holding_vault]);
+    }
+
+    fn type_size(ty: &IdlType) -> usize {
+        match ty {
+            IdlType::Primitive("u8") => 1,
+            IdlType::Primitive("u16") => 2,
+            IdlType::Primitive("u32") => 4,
+            IdlType::Primitive("u64") | IdlType::Primitive("i64") => 8,
+            IdlType::Primitive("publicKey") => 32,
+            IdlType::Primitive(name) => panic!("no size for {}", name),
+            IdlType::Defined(name) => STATE_TYPES.iter().find(|entry| entry.name == *name).unwrap().size,
+            IdlType::Array(inner, len) => type_size(inner) * len,
+        }
+    }
+
+    // A field's type has to cover the bytes up to the next field, so a wrong type can't hide behind a right offset
+    #[test]
+    fn test_state_field_types_fill_the_structs() {
+        for entry in STATE_ACCOUNTS.iter().chain(STATE_TYPES) {
+            let mut end = 0;
+            for field in entry.fields {
+                assert_eq!(field.offset, end, "{}.{}", entry.name, field.name);
+                end += type_size(&field.ty);
+            }
+            assert_eq!(end, entry.size, "{}", entry.name);
+        }
+    }
+
+    #[test]
+    fn test_camel_case() {
+        assert_eq!(camel_case("system_state"), "systemState");
+        assert_eq!(camel_case("liq_pool_sol_leg_pda"), "liqPoolSolLegPda");
+        assert_eq!(camel_case("loan"), "loan");
+    }
+
+    #[test]
+    fn test_option_args_use_the_anchor_type() {
+        let mut out = String::new();
+        arg_type("option<u64>").write(&mut out, 0);
+        assert_eq!(out, "{\n  \"option\": \"u64\"\n}");
+
+        let mut out = String::new();
+        arg_type("u64").write(&mut out, 0);
+        assert_eq!(out, "\"u64\"");
+    }
+}
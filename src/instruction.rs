@@ -1,23 +1,29 @@
-use bincode::serialize;
+use std::io::Read;
+
+use bincode::{deserialize, deserialize_from, serialize};
 use serde::{Deserialize, Serialize};
 use solana_program::{
     instruction::{AccountMeta, Instruction as SolInstruction},
+    program_error::ProgramError,
     pubkey::Pubkey,
 };
 use crate::{
-    helpers::constants::{
-        CREATOR_AUTHORITY, DAO_AUTHORITY, orca_swap,
-        raydium_v4, serum_v3,
+    helpers::{
+        constants::{
+            CREATOR_AUTHORITY, DAO_AUTHORITY, orca_swap, orca_whirlpool,
+            raydium_v4, serum_v3, token_metadata, SOL_MATA_ORCA_AMM,
+        },
+        marinade::{find_liq_pool_msol_leg_authority, find_msol_mint_authority},
+        whirlpool::find_whirlpool_oracle,
     },
     id,
+    lucra_core::pda::Pda,
+    units::{Bps, CollateralRatio, Lamports, Mata, Percent, RewardFee},
     state::{
         AmmTypes,
         CurrencyTypes,
-        SystemState,
-        staking::{
-            StakingTimeframe,
-            StakingState,
-        }, ArbState,
+        UpdateStateParams,
+        staking::StakingTimeframe,
     },
 };
 
@@ -26,6 +32,9 @@ use crate::{
 pub enum Instruction {
     /// Initializes the program state (system + staking + arb)
     /// 
+    /// guardian is the key that can pause features with SetPause, the default key leaves it to the DAO alone.
+    /// The DAO can replace it with UpdateState.
+    /// 
     /// Accounts expected by this instruction (16):
     ///
     /// 0: `[]` marinade_state_ai
@@ -45,8 +54,8 @@ pub enum Instruction {
     /// 14: `[]` mata_holding_vault_ai
     /// 15: `[]` lucra_holding_vault_ai
     Initialize {
-        min_deposit: u64,
-        collateral_requirement: u32,
+        min_deposit: Lamports,
+        collateral_requirement: CollateralRatio,
         epoch: i64,
         loans_enabled: bool,
         staking_enabled: bool,
@@ -54,35 +63,103 @@ pub enum Instruction {
         peg_check_enabled: bool,
         max_amount_of_lucra_to_mint: u64,
         daily_arb_limit: u64,
-        maximum_outstanding_mata: u64,
-        lcp: u8,
+        maximum_outstanding_mata: Mata,
+        lcp: Percent,
+        min_loan_amount_mata: Mata,
+        msol_haircut_bps: Bps,
+        peg_lower_bound: Bps,
+        peg_upper_bound: Bps,
+        penalty_band_width: Bps,
+        penalty_multiplier_per_band: u32,
+        max_oracle_staleness_slots: u64,
+        max_oracle_deviation_bps: Bps,
+        guardian: Pubkey,
     },
 
     /// DAO instruction for updating the state
     /// 
+    /// Every field is optional and None leaves it unchanged, so a proposal only carries the fields it
+    /// changes and can't revert one changed by another proposal in the meantime. In bincode each field
+    /// starts with a one byte tag and a field left unchanged is a single zero byte.
+    /// 
+    /// Setting the guardian to the default key leaves SetPause to the DAO alone.
+    /// 
+    /// The oracle limits apply to every price the program reads: an oracle that hasn't updated in
+    /// max_oracle_staleness_slots is stale, and SOL/USDC and SOL/USDT further apart than
+    /// max_oracle_deviation_bps are rejected.
+    /// 
+    /// msol_sol_pool is the Orca MSOL/SOL pool an arb can swap mSOL through instead of paying the marinade
+    /// unstake fee. The default key leaves every arb on the marinade unstake.
+    /// 
+    /// arb_dust_threshold is the most lucra CleanUpArb burns from the lucra holding vault, anything above it is
+    /// kept for the next cycle. Only leftovers above it earn the caller a reward.
+    /// 
+    /// pyth_sol_usd_feed is the Pyth SOL/USD price account that can stand in for either SOL/USD oracle. It
+    /// still has to agree with the other one. The default key only accepts the program's oracles.
+    /// 
+    /// annual_interest_bps is a base borrow rate DeterminePenalty accrues on every loan's debt on top of the
+    /// penalty, at most 100% a year. 0 turns it off.
+    /// 
+    /// restricted_cranking leaves the cranks that pay a reward to the keepers in the keeper registry, see
+    /// RegisterKeeper. Anyone else's call fails with CrankingRestricted.
+    /// 
+    /// sol_mata_whirlpool is the Orca SOL/MATA whirlpool HarvestPenalty can swap through with AmmTypes::Whirlpool.
+    /// Whirlpools can be opened by anyone, so only the registered one is accepted. The default key turns it off.
+    /// 
+    /// native_loans_enabled lets CreateMataLoan and AddCollateral take native SOL collateral into the sol vault.
+    /// It's off until the DAO turns it on, creating the sol vault alone doesn't open it.
+    /// 
     /// Accounts expected by this instruction (3)
     /// 
     /// 0: `[writable]` system_state_ai
     /// 1: `[writable]` arb_state_ai
     /// 2: `[]` dao_authority_ai
     UpdateState {
-        min_deposit: u64,
-        collateral_requirement: u32,
-        loans_enabled: bool,
-        staking_enabled: bool,
-        arbitrage_enabled: bool,
-        peg_check_enabled: bool,
-        max_amount_of_lucra_to_mint: u64,
-        daily_arb_limit: u64,
-        maximum_outstanding_mata: u64,
-        minimum_harvest_amount: u64,
-        reward_fee: u32,
-        lcp: u8,
+        min_deposit: Option<Lamports>,
+        collateral_requirement: Option<CollateralRatio>,
+        loans_enabled: Option<bool>,
+        staking_enabled: Option<bool>,
+        arbitrage_enabled: Option<bool>,
+        peg_check_enabled: Option<bool>,
+        max_amount_of_lucra_to_mint: Option<u64>,
+        daily_arb_limit: Option<u64>,
+        maximum_outstanding_mata: Option<Mata>,
+        minimum_harvest_amount: Option<Lamports>,
+        reward_fee: Option<RewardFee>,
+        lcp: Option<Percent>,
+        min_loan_amount_mata: Option<Mata>,
+        msol_haircut_bps: Option<Bps>,
+        msol_sol_oracle: Option<Pubkey>,
+        require_oracle_v2: Option<bool>,
+        reward_expiry_epochs: Option<u64>,
+        max_instant_unstake: Option<Lamports>,
+        peg_lower_bound: Option<Bps>,
+        peg_upper_bound: Option<Bps>,
+        penalty_band_width: Option<Bps>,
+        penalty_multiplier_per_band: Option<u32>,
+        arb_trigger_deviation_bps: Option<Bps>,
+        guardian: Option<Pubkey>,
+        max_oracle_staleness_slots: Option<u64>,
+        max_oracle_deviation_bps: Option<Bps>,
+        msol_sol_pool: Option<Pubkey>,
+        arb_dust_threshold: Option<u64>,
+        pyth_sol_usd_feed: Option<Pubkey>,
+        annual_interest_bps: Option<Bps>,
+        restricted_cranking: Option<bool>,
+        sol_mata_whirlpool: Option<Pubkey>,
+        native_loans_enabled: Option<bool>,
     },
 
     /// Creates a mata loan
     /// 
-    /// Accounts expected by this instruction (22 or 24):
+    /// The loan is created by the program at `find_loan_address(user, index)`, the index being the next one in
+    /// the user's loan registry at `find_loan_registry(user)`. The registry is created on the user's first loan.
+    /// 
+    /// The fees sysvar older clients pass after msol_mint_authority_ai is still accepted and ignored
+    /// 
+    /// With the peg check on, a broken peg fails every layout with BrokenPeg and nothing is opened or deposited
+    /// 
+    /// Accounts expected by this instruction (13, 18, 22 or 24):
     /// 
     /// 0: `[writable]` system_state_ai
     /// 1: `[writable]` marinade_state_ai
@@ -102,10 +179,10 @@ pub enum Instruction {
     /// 15: `[]` liq_pool_msol_leg_authority_ai
     /// 16: `[writable]` reserve_pda_ai
     /// 17: `[]` msol_mint_authority_ai
-    /// 18: `[]` fees_ai
-    /// 19: `[]` system_program_ai
-    /// 20: `[]` token_program_ai
-    /// 21: `[]` marinade_program_ai
+    /// 18: `[]` system_program_ai
+    /// 19: `[]` token_program_ai
+    /// 20: `[]` marinade_program_ai
+    /// 21: `[writable]` loan_registry_ai
     /// 
     /// or
     /// 
@@ -129,17 +206,66 @@ pub enum Instruction {
     /// 17: `[]` liq_pool_msol_leg_authority_ai
     /// 18: `[writable]` reserve_pda_ai
     /// 19: `[]` msol_mint_authority_ai
-    /// 20: `[]` fees_ai
-    /// 21: `[]` system_program_ai
-    /// 22: `[]` token_program_ai
-    /// 23: `[]` marinade_program_ai
+    /// 20: `[]` system_program_ai
+    /// 21: `[]` token_program_ai
+    /// 22: `[]` marinade_program_ai
+    /// 23: `[writable]` loan_registry_ai
+    /// 
+    /// or, to borrow against SOL/MATA orca LP tokens once the DAO has registered the LP vault.
+    /// `lamports` is then the amount of LP tokens
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` sol_mata_pool_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` lp_vault_ai
+    /// 4: `[writable]` mata_mint_ai
+    /// 5: `[]` mata_mint_authority_ai
+    /// 6: `[writable]` user_account_ai
+    /// 7: `[writable]` user_mata_account_ai
+    /// 8: `[writable]` user_lp_account_ai
+    /// 9: `[]` sol_usdc_oracle_ai
+    /// 10: `[]` sol_usdt_oracle_ai
+    /// 11: `[]` sol_mata_oracle_ai
+    /// 12: `[]` pool_sol_vault_ai
+    /// 13: `[]` pool_mata_vault_ai
+    /// 14: `[]` lp_mint_ai
+    /// 15: `[]` token_program_ai
+    /// 16: `[]` system_program_ai
+    /// 17: `[writable]` loan_registry_ai
+    /// 
+    /// or, to keep the collateral as lamports in the sol vault instead of depositing it with Marinade, once the
+    /// sol vault has been created
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[writable]` sol_vault_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` mata_mint_ai
+    /// 4: `[]` mata_mint_authority_ai
+    /// 5: `[writable, signer]` user_account_ai
+    /// 6: `[writable]` user_mata_account_ai
+    /// 7: `[]` sol_usdc_oracle_ai
+    /// 8: `[]` sol_usdt_oracle_ai
+    /// 9: `[]` sol_mata_oracle_ai
+    /// 10: `[]` system_program_ai
+    /// 11: `[]` token_program_ai
+    /// 12: `[writable]` loan_registry_ai
+    /// 
+    /// The LP backed layout only takes the optional revenue_ledger_ai after it.
+    /// The native SOL layout takes the optional revenue_ledger_ai too, but no MSOL/SOL oracle.
+    /// Either msol layout may be followed by an optional `[writable]` revenue_ledger_ai. A stale SOL/MATA
+    /// oracle fails the peg check, trailing SOL/MATA pool accounts are no longer read.
+    /// 
+    /// Once the DAO registers an MSOL/SOL oracle, `[]` msol_sol_oracle_ai has to be appended as well
     CreateMataLoan {
         lamports: u64,
     },
 
     /// Closes a Mata `loan`
     /// 
-    /// Accounts expected by this instruction (16 or 17):
+    /// `unstake_msol` liquid unstakes the returned collateral through Marinade. A close returning more than the
+    /// DAO's `max_instant_unstake` fails with `InstantUnstakeTooLarge` and has to take the msol instead
+    /// 
+    /// Accounts expected by this instruction (7, 9, 16 or 17):
     /// 
     /// 0: `[writable]` system_state_ai
     /// 1: `[writable]` marinade_state_ai
@@ -177,23 +303,49 @@ pub enum Instruction {
     /// 14: `[]` system_program_ai
     /// 15: `[]` token_program_ai
     /// 16: `[]` marinade_program_ai
+    /// 
+    /// or, for an LP backed loan. `unstake_msol` has to be false, and the LP tokens its pending penalty took
+    /// stay in the LP vault
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[writable]` loan_ai
+    /// 2: `[writable]` user_account_ai
+    /// 3: `[writable]` user_lp_account_ai
+    /// 4: `[writable]` mata_mint_ai
+    /// 5: `[writable]` user_mata_account_ai
+    /// 6: `[]` lp_vault_authority_ai
+    /// 7: `[writable]` lp_vault_ai
+    /// 8: `[]` token_program_ai
+    /// 
+    /// or, for a native SOL loan. `unstake_msol` has to be false and the lamports are returned to user_account_ai
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[writable]` loan_ai
+    /// 2: `[writable, signer]` user_account_ai
+    /// 3: `[writable]` mata_mint_ai
+    /// 4: `[writable]` user_mata_account_ai
+    /// 5: `[writable]` sol_vault_ai
+    /// 6: `[]` token_program_ai
     CloseOutMataLoan {
         unstake_msol: bool,
     },
 
     /// Creates a new staking account
     /// 
-    /// Accounts expected by this instruction (4):
+    /// Accounts expected by this instruction (4 + 1 optional):
     /// 
     /// 0: `[]` system_state_ai
     /// 1: `[]` staking_state_ai
     /// 2: `[writable]` staking_account_ai
     /// 3: `[]` owner_ai
+    /// 4: `[]` recovery_key_ai - optional, can unfreeze the account without the delay
     CreateStakingAccount {},
 
-    /// Creates a new stake balance account
+    /// Creates a new stake balance account. The three vaults have to be distinct, empty lucra vaults owned by the
+    /// stake balance's transfer authority with no delegate or close authority. The balance is bound to the
+    /// owner's staking account, every later instruction on it has to pass that account.
     /// 
-    /// Accounts expected by this instruction (7):
+    /// Accounts expected by this instruction (8):
     /// 
     /// 0: `[]` system_state_ai
     /// 1: `[]` staking_state_ai
@@ -202,6 +354,7 @@ pub enum Instruction {
     /// 4: `[]` deposit_vault_ai
     /// 5: `[]` stake_vault_ai
     /// 6: `[]` pending_vault_ai
+    /// 7: `[]` staking_account_ai
     CreateStakeBalance { nonce: u8, staking_timeframe: u8 },
 
     /// Deposits lucra into the deposit account
@@ -235,7 +388,10 @@ pub enum Instruction {
     /// 11: `[writable]` token_program_ai
     Stake { lucra: u64 },
 
-    /// Starts the unstake process for an amount of locked stake.
+    /// Starts the unstake process for an amount of locked stake. `lucra` of `UNSTAKE_ALL` unstakes all of the
+    /// stake balance the staking account's pledges to LucraBacked loans leave unlocked. Asking for more than
+    /// they leave fails with StakingAccountLocked, and the return data is then the most that can be unstaked
+    /// as a little endian u64.
     /// 
     /// Accounts expected by this instruction (15):
     /// 
@@ -258,54 +414,61 @@ pub enum Instruction {
 
     /// Ends the unstake process by putting the coins in the deposit account
     /// 
-    /// Accounts expected by this instruction (9)
+    /// Accounts expected by this instruction (8, or 9 in the old layout)
     /// 
     /// 0: `[]` system_state_ai
     /// 1: `[writable]` pending_withdrawal_ai
     /// 2: `[]` stake_balance_ai
     /// 3: `[writable]` pending_vault_ai
     /// 4: `[writable]` deposit_vault_ai
-    /// 5: `[]` owner_ai
+    /// 5: `[writable, signer]` owner_ai, gets the pending withdrawal's rent back
     /// 6: `[]` transfer_authority_ai 
-    /// 7: `[writable]` sol_account_ai 
-    /// 8: `[]` token_program_ai 
+    /// 7: `[]` token_program_ai 
+    ///
+    /// The old layout had a `[writable]` sol_account_ai for the rent at 7, ahead of the token program. It's
+    /// still accepted when it's the owner.
     EndUnstake {},
 
     /// Withdraws the deposited lucra from a staking account back to the user's wallet
     /// 
-    /// Accounts expected by this instruction (9):
+    /// Accounts expected by this instruction (10):
     /// 
     /// 0: `[]` system_state_ai
-    /// 1: `[writable]` stake_balance_ai
-    /// 2: `[writable]` deposit_vault_ai
-    /// 3: `[]` stake_vault_ai
-    /// 4: `[]` pending_vault_ai
-    /// 5: `[writable]` to_account_ai
-    /// 6: `[writable]` owner_ai
-    /// 7: `[]` transfer_authority_ai
-    /// 8: `[]` token_program_ai 
+    /// 1: `[]` staking_account_ai
+    /// 2: `[writable]` stake_balance_ai
+    /// 3: `[writable]` deposit_vault_ai
+    /// 4: `[]` stake_vault_ai
+    /// 5: `[]` pending_vault_ai
+    /// 6: `[writable]` to_account_ai
+    /// 7: `[writable]` owner_ai
+    /// 8: `[]` transfer_authority_ai
+    /// 9: `[]` token_program_ai 
     WithdrawStake { lucra: u64 },
 
-    /// Claims a user's portion of the staking reward
+    /// Claims a user's portion of the staking reward. A stake balance on the reward index is paid every drop
+    /// since its last claim at once and any account can go in reward_ai, the others claim one reward at a time.
     /// 
-    /// Accounts expected by this instruction (13):
+    /// Accounts expected by this instruction (14):
     /// 
     /// 0: `[]` system_state_ai
-    /// 1: `[]` staking_state_ai
-    /// 2: `[writable]` stake_balance_ai
-    /// 3: `[]` reward_ai
-    /// 4: `[]` user_staked_lucra_account_ai
-    /// 5: `[writable]` lucra_vault_ai
-    /// 6: `[writable]` lucra_account_ai
-    /// 7: `[writable]` rewards_vault_ai
-    /// 8: `[writable]` msol_account_ai
-    /// 9: `[]` rewards_vault_transfer_authority_ai
-    /// 10: `[writable]` lucra_mint_ai
-    /// 11: `[]` lucra_mint_authority_ai
-    /// 12: `[]` token_program_ai
+    /// 1: `[writable]` staking_state_ai
+    /// 2: `[]` staking_account_ai
+    /// 3: `[writable]` stake_balance_ai
+    /// 4: `[writable]` reward_ai - unused on the reward index
+    /// 5: `[]` user_staked_lucra_account_ai
+    /// 6: `[writable]` lucra_vault_ai
+    /// 7: `[writable]` lucra_account_ai
+    /// 8: `[writable]` rewards_vault_ai
+    /// 9: `[writable]` msol_account_ai
+    /// 10: `[]` rewards_vault_transfer_authority_ai
+    /// 11: `[writable]` lucra_mint_ai
+    /// 12: `[]` lucra_mint_authority_ai
+    /// 13: `[]` token_program_ai
     ClaimReward {},
 
-    /// Puts a staking reward onchain
+    /// Puts a staking reward onchain, at most once an epoch. A drop under the staking state's minimum is
+    /// skipped, the reward account is left alone and the yield waits in the msol vault for the next drop.
+    /// With no staked lucra the reward account is left alone too and all of the yield goes to the arb coffer.
     /// 
     /// Accounts expected by this instruction (13):
     /// 
@@ -324,18 +487,22 @@ pub enum Instruction {
     /// 12: `[]` token_program_ai
     DropReward {},
 
-    /// DAO instruction for transfer funds from the msol vault
+    /// DAO instruction for transfer funds from the msol vault or the LP vault, or with `native` lamports from
+    /// the sol vault. to_account has to be on the transfer allowlist and past its delay. Out of the LP vault
+    /// `lamports` is an amount of LP tokens and transfer_authority_ai is the LP vault authority.
     /// 
-    /// Accounts expected by this instruction (6)
+    /// Accounts expected by this instruction (7)
     /// 
     /// 0: `[writable]` system_state_ai
     /// 1: `[]` dao_authority_ai
-    /// 2: `[writable]` from_vault_ai
+    /// 2: `[writable]` from_vault_ai - the sol vault on native transfers
     /// 3: `[writable]` to_account_ai
-    /// 4: `[]` transfer_authority_ai
-    /// 5: `[]` token_program_ai
+    /// 4: `[]` transfer_authority_ai - unused on native transfers
+    /// 5: `[]` token_program_ai - unused on native transfers
+    /// 6: `[]` transfer_allowlist_ai
     TransferFunds {
         lamports: u64,
+        native: bool,
     },
 
     /// Creates a price history account
@@ -347,22 +514,33 @@ pub enum Instruction {
     CreatePriceHistory {},
 
     /// Updates a price history account. A price history account will be updated every
-    /// hour and the prices will be averaged for that day.
+    /// hour and the prices will be averaged for that day, an update less than 55 minutes after the
+    /// last one fails with TooFrequent. The reward is paid in part until the day has 12 updates, the
+    /// ones it needs to keep its prices. Each update also records how far
+    /// mata was under the peg, weighted by how long it stayed there. Mata further off the peg than the
+    /// DAO's arb trigger raises an arb request for MintFundsForArb.
     /// 
-    /// Accounts expected by this instruction (9)
+    /// Accounts expected by this instruction (11)
     /// 
     /// 0: `[]` system_state_ai
     /// 1: `[writable]` price_history_ai
     /// 2: `[]` sol_usdc_oracle_ai
     /// 3: `[]` sol_usdt_oracle_ai
     /// 4: `[]` lucra_sol_oracle_ai
-    /// 5: `[writable]` user_reward_account_ai
-    /// 6: `[writable]` reward_mint_ai
-    /// 7: `[]` reward_mint_authority_ai
-    /// 8: `[]` token_program_ai
+    /// 5: `[]` sol_mata_oracle_ai
+    /// 6: `[writable]` user_reward_account_ai
+    /// 7: `[writable]` reward_mint_ai
+    /// 8: `[]` reward_mint_authority_ai
+    /// 9: `[]` token_program_ai
+    /// 10: `[writable]` arb_state_ai
+    /// 
+    /// Once the price history is paged, see CreatePriceHistoryPage, the arb state is followed by
+    /// `[writable]` price_history_header_ai, the `[writable]` active page and the `[writable]` page after it.
+    /// The days written are archived to them, the next page is passed empty until it's created.
     UpdatePriceHistory {},
 
-    /// Redeems reward tokens for Lucra
+    /// Redeems reward tokens for Lucra. Refused once the reward redemption deadline has passed and
+    /// boosted during the grace period before it.
     /// 
     /// Accounts expected by this instruction (9)
     /// 
@@ -381,7 +559,12 @@ pub enum Instruction {
 
     /// Adds collateral to a loan. This does not give back more mata
     /// 
-    /// Accounts expected by this instruction (20 or 16)
+    /// With 7 or 11 accounts the user tops up with msol they already hold, `lamports` is then
+    /// the msol amount and it's booked at the marinade rate without a deposit
+    /// 
+    /// The fees sysvar older clients pass after msol_mint_authority_ai is still accepted and ignored
+    /// 
+    /// Accounts expected by this instruction (19, 15, 11, 7 or 5)
     /// 
     /// 0: `[writable]` system_state_ai
     /// 1: `[writable]` marinade_state_ai
@@ -399,10 +582,9 @@ pub enum Instruction {
     /// 13: `[]` liq_pool_msol_leg_authority_ai
     /// 14: `[writable]` reserve_pda_ai
     /// 15: `[]` msol_mint_authority_ai
-    /// 16: `[]` fees_ai
-    /// 17: `[]` system_program_ai
-    /// 18: `[]` token_program_ai
-    /// 19: `[]` marinade_program_ai
+    /// 16: `[]` system_program_ai
+    /// 17: `[]` token_program_ai
+    /// 18: `[]` marinade_program_ai
     /// 
     /// or
     /// 
@@ -418,10 +600,41 @@ pub enum Instruction {
     /// 9: `[]` liq_pool_msol_leg_authority_ai
     /// 10: `[writable]` reserve_pda_ai
     /// 11: `[]` msol_mint_authority_ai
-    /// 12: `[]` fees_ai
-    /// 13: `[]` system_program_ai
-    /// 14: `[]` token_program_ai
-    /// 15: `[]` marinade_program_ai
+    /// 12: `[]` system_program_ai
+    /// 13: `[]` token_program_ai
+    /// 14: `[]` marinade_program_ai
+    /// 
+    /// or
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` msol_vault_ai
+    /// 4: `[writable]` owner_ai
+    /// 5: `[writable]` user_msol_account_ai
+    /// 6: `[writable]` user_staking_account_ai
+    /// 7: `[]` sol_usdc_oracle_ai
+    /// 8: `[]` sol_usdt_oracle_ai
+    /// 9: `[]` lucra_sol_oracle_ai
+    /// 10: `[]` token_program_ai
+    /// 
+    /// or
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` msol_vault_ai
+    /// 4: `[writable]` owner_ai
+    /// 5: `[writable]` user_msol_account_ai
+    /// 6: `[]` token_program_ai
+    /// 
+    /// or, for a native SOL loan. The lamports go into the sol vault as they are
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[writable]` sol_vault_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable, signer]` owner_ai
+    /// 4: `[]` system_program_ai
     AddCollateral {
         lamports: u64,
     },
@@ -440,11 +653,27 @@ pub enum Instruction {
     /// 7: `[writable]` reward_mint_ai
     /// 8: `[]` reward_mint_authority_ai
     /// 9: `[]` token_program_ai
+    /// 
+    /// The penalty multiplier comes from the peg deviation recorded in the price history, so the
+    /// SOL/MATA oracle and any trailing SOL/MATA pool accounts are no longer read
+    /// 
+    /// Once the DAO registers an MSOL/SOL oracle, `[]` msol_sol_oracle_ai followed by `[]` marinade_state_ai
+    /// have to be appended as well
+    /// 
+    /// A loan with a penalty history also takes `[writable]` penalty_history_ai after those, the charged days
+    /// are recorded in it with their multipliers
+    /// 
+    /// A loan not checked since before the oldest day in the price history is charged from its pages, with
+    /// `[]` price_history_header_ai followed by the `[]` pages holding the unchecked days, oldest first. Without
+    /// them it fails rather than skip the days. Days that were never recorded are charged no penalty.
     DeterminePenalty {},
 
-    /// Harvests the penalty from a loan and rewards a fee to the user for performing the transaction
+    /// Harvests the penalty from a loan and rewards a fee to the user for performing the transaction.
+    /// While mata trades above the peg the penalty msol goes to the arb coffer instead of being swapped and burned.
+    /// The harvest fails if the swap buys less than `min_mata_out`, or less than MAX_HARVEST_SLIPPAGE under what the
+    /// oracles value the sol at when that's higher. The coffer path doesn't swap and ignores it.
     /// 
-    /// Accounts expected by this instruction (25 or 33)
+    /// Accounts expected by this instruction (15, 18, 28, 29 or 36)
     /// 
     /// 0: `[writable]` system_state_ai
     /// 1: `[writable]` marinade_state_ai
@@ -453,9 +682,40 @@ pub enum Instruction {
     /// 4: `[writable]` mata_mint_ai
     /// 5: `[writable]` loan_ai
     /// 6: `[]` sol_mata_oracle_ai
-    /// 7: `[writable]` user_account_ai
-    /// 8: `[writable]` user_wsol_account_ai
-    /// 9: `[writable]` user_mata_account_ai
+    /// 7: `[]` sol_usdc_oracle_ai
+    /// 8: `[]` sol_usdt_oracle_ai
+    /// 9: `[writable]` arb_coffer_ai
+    /// 10: `[writable]` user_account_ai
+    /// 11: `[writable]` user_wsol_account_ai
+    /// 12: `[writable]` user_mata_account_ai
+    /// 13: `[writable]` user_msol_account_ai
+    /// 14: `[writable]` msol_mint_ai
+    /// 15: `[writable]` liq_pool_sol_leg_pda_ai
+    /// 16: `[writable]` liq_pool_msol_leg_ai
+    /// 17: `[writable]` treasury_msol_account_ai
+    /// 18: `[]` system_program_ai
+    /// 19: `[]` marinade_program_ai
+    /// 20: `[writable]` sm_amm_ai
+    /// 21: `[]` sm_amm_authority_ai
+    /// 22: `[writable]` sm_pool_base_vault_ai
+    /// 23: `[writable]` sm_pool_quote_vault_ai
+    /// 24: `[writable]` sm_pool_mint_ai
+    /// 25: `[writable]` sm_pool_fees_ai
+    /// 26: `[]` token_swap_program_ai
+    /// 27: `[]` token_program_ai
+    /// 
+    /// or 
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[writable]` marinade_state_ai
+    /// 2: `[writable]` msol_vault_ai
+    /// 3: `[]` msol_vault_authority_ai
+    /// 4: `[writable]` mata_mint_ai
+    /// 5: `[writable]` loan_ai
+    /// 6: `[]` sol_mata_oracle_ai
+    /// 7: `[]` sol_usdc_oracle_ai
+    /// 8: `[]` sol_usdt_oracle_ai
+    /// 9: `[writable]` arb_coffer_ai
     /// 10: `[writable]` user_msol_account_ai
     /// 11: `[writable]` msol_mint_ai
     /// 12: `[writable]` liq_pool_sol_leg_pda_ai
@@ -463,55 +723,118 @@ pub enum Instruction {
     /// 14: `[writable]` treasury_msol_account_ai
     /// 15: `[]` system_program_ai
     /// 16: `[]` marinade_program_ai
-    /// 17: `[writable]` sm_amm_ai
-    /// 18: `[]` sm_amm_authority_ai
-    /// 19: `[writable]` sm_pool_base_vault_ai
-    /// 20: `[writable]` sm_pool_quote_vault_ai
-    /// 21: `[writable]` sm_pool_mint_ai
-    /// 22: `[writable]` sm_pool_fees_ai
-    /// 23: `[]` token_swap_program_ai
-    /// 24: `[]` token_program_ai
+    /// 17: `[writable]` user_account_ai
+    /// 18: `[writable]` user_wsol_account_ai
+    /// 19: `[write]` user_mata_account_ai
+    /// 20: `[]` pool_program_ai
+    /// 21: `[writable]` _pool_wsol_account_ai
+    /// 22: `[writable]` _pool_mata_account_ai
+    /// 23: `[]` token_program_ai
+    /// 24: `[writable]` amm_program_ai
+    /// 25: `[]` _amm_authority_ai
+    /// 26: `[writable]` _amm_open_orders_ai
+    /// 27: `[writable]` _amm_target_ai
+    /// 28: `[writable]` _serum_sol_mata_market_ai
+    /// 29: `[]` serum_program_ai
+    /// 30: `[writable]` _serum_bids_ai
+    /// 31: `[writable]` _serum_asks_ai
+    /// 32: `[writable]` _serum_event_queue_ai
+    /// 33: `[writable]` _serum_base_vault_ai
+    /// 34: `[writable]` _serum_quote_vault_ai
+    /// 35: `[]` _serum_vault_signer_ai
     /// 
-    /// or 
+    /// or, for a native SOL loan through orca. The lamports are wrapped straight into user_wsol_account_ai, a
+    /// native harvest while mata is above the peg or through raydium fails with `NotImplemented`
     /// 
     /// 0: `[writable]` system_state_ai
-    /// 1: `[writable]` marinade_state_ai
+    /// 1: `[writable]` sol_vault_ai
+    /// 2: `[writable]` mata_mint_ai
+    /// 3: `[]` sol_usdc_oracle_ai
+    /// 4: `[]` sol_usdt_oracle_ai
+    /// 5: `[writable]` loan_ai
+    /// 6: `[]` sol_mata_oracle_ai
+    /// 7: `[writable, signer]` user_account_ai
+    /// 8: `[writable]` user_wsol_account_ai
+    /// 9: `[writable]` user_mata_account_ai
+    /// 10: `[writable]` sm_amm_ai
+    /// 11: `[]` sm_amm_authority_ai
+    /// 12: `[writable]` sm_pool_base_vault_ai
+    /// 13: `[writable]` sm_pool_quote_vault_ai
+    /// 14: `[writable]` sm_pool_mint_ai
+    /// 15: `[writable]` sm_pool_fees_ai
+    /// 16: `[]` token_swap_program_ai
+    /// 17: `[]` token_program_ai
+    /// 
+    /// or, with `AmmTypes::None`, the keeper pays the penalty off directly. The mata the penalty less the reward
+    /// fee is worth at the oracle mata price, rounded up, is burned out of user_mata_account_ai and the penalty's
+    /// msol is transferred to user_msol_account_ai. `min_mata_out` is the most mata the keeper pays, 0 for any
+    /// amount. Above the peg it goes to the arb coffer like the swap layouts, and native SOL loans fail with
+    /// `NotImplemented`
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
     /// 2: `[writable]` msol_vault_ai
     /// 3: `[]` msol_vault_authority_ai
     /// 4: `[writable]` mata_mint_ai
     /// 5: `[writable]` loan_ai
     /// 6: `[]` sol_mata_oracle_ai
-    /// 7: `[writable]` user_msol_account_ai
-    /// 8: `[writable]` msol_mint_ai
-    /// 9: `[writable]` liq_pool_sol_leg_pda_ai
-    /// 10: `[writable]` liq_pool_msol_leg_ai
-    /// 11: `[writable]` treasury_msol_account_ai
-    /// 12: `[]` system_program_ai
+    /// 7: `[]` sol_usdc_oracle_ai
+    /// 8: `[]` sol_usdt_oracle_ai
+    /// 9: `[writable]` arb_coffer_ai
+    /// 10: `[writable, signer]` user_account_ai
+    /// 11: `[writable]` user_mata_account_ai
+    /// 12: `[writable]` user_msol_account_ai
     /// 13: `[]` marinade_program_ai
-    /// 14: `[writable]` user_account_ai
-    /// 15: `[writable]` user_wsol_account_ai
-    /// 16: `[write]` user_mata_account_ai
-    /// 17: `[]` pool_program_ai
-    /// 18: `[writable]` _pool_wsol_account_ai
-    /// 19: `[writable]` _pool_mata_account_ai
-    /// 20: `[]` token_program_ai
-    /// 21: `[writable]` amm_program_ai
-    /// 22: `[]` _amm_authority_ai
-    /// 23: `[writable]` _amm_open_orders_ai
-    /// 24: `[writable]` _amm_target_ai
-    /// 25: `[writable]` _serum_sol_mata_market_ai
-    /// 26: `[]` serum_program_ai
-    /// 27: `[writable]` _serum_bids_ai
-    /// 28: `[writable]` _serum_asks_ai
-    /// 29: `[writable]` _serum_event_queue_ai
-    /// 30: `[writable]` _serum_base_vault_ai
-    /// 31: `[writable]` _serum_quote_vault_ai
-    /// 32: `[]` _serum_vault_signer_ai
-    HarvestPenalty { amm_type: u8 },
+    /// 14: `[]` token_program_ai
+    /// 
+    /// or, with `AmmTypes::Whirlpool`, through the SOL/MATA whirlpool registered as sol_mata_whirlpool with
+    /// UpdateState. The vaults are in the whirlpool's mint order and the tick arrays are the three the swap walks
+    /// through from the current tick, see `whirlpool::calc_swap_tick_arrays`. The whirlpool has to trade at least
+    /// as much as both other pools, and native SOL loans fail with `NotImplemented`
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[writable]` marinade_state_ai
+    /// 2: `[writable]` msol_vault_ai
+    /// 3: `[]` msol_vault_authority_ai
+    /// 4: `[writable]` mata_mint_ai
+    /// 5: `[writable]` loan_ai
+    /// 6: `[]` sol_mata_oracle_ai
+    /// 7: `[]` sol_usdc_oracle_ai
+    /// 8: `[]` sol_usdt_oracle_ai
+    /// 9: `[writable]` arb_coffer_ai
+    /// 10: `[writable, signer]` user_account_ai
+    /// 11: `[writable]` user_wsol_account_ai
+    /// 12: `[writable]` user_mata_account_ai
+    /// 13: `[writable]` user_msol_account_ai
+    /// 14: `[writable]` msol_mint_ai
+    /// 15: `[writable]` liq_pool_sol_leg_pda_ai
+    /// 16: `[writable]` liq_pool_msol_leg_ai
+    /// 17: `[writable]` treasury_msol_account_ai
+    /// 18: `[]` system_program_ai
+    /// 19: `[]` marinade_program_ai
+    /// 20: `[writable]` whirlpool_ai
+    /// 21: `[writable]` whirlpool_vault_a_ai
+    /// 22: `[writable]` whirlpool_vault_b_ai
+    /// 23: `[writable]` tick_array_0_ai
+    /// 24: `[writable]` tick_array_1_ai
+    /// 25: `[writable]` tick_array_2_ai
+    /// 26: `[]` whirlpool_oracle_ai
+    /// 27: `[]` whirlpool_program_ai
+    /// 28: `[]` token_program_ai
+    /// 
+    /// Every layout may be followed by an optional `[writable]` revenue_ledger_ai
+    HarvestPenalty { amm_type: u8, min_mata_out: u64 },
 
     /// Sell the funds generated during the minting process for sol
     /// 
-    /// Accounts expected by this instruction (32 or 31 or 24 or 23)
+    /// Fails if the swap pays less than `min_amount_out` wsol into the arb fund, 0 accepts any amount
+    /// 
+    /// Sets an `ArbResult` as return data, `amount_processed` is the wsol the swap paid into the arb fund
+    /// 
+    /// Every mata layout passes mata_mint at 5 and wsol_mint at 6. The Orca and Whirlpool ones had wsol_mint
+    /// at 8, after the reward mint, before IDL 0.3.0.
+    /// 
+    /// Accounts expected by this instruction (32 or 31 or 24 or 26 or 25)
     /// 
     /// 0: `[]` system_state_ai
     /// 1: `[writable]` arb_state_ai
@@ -519,17 +842,17 @@ pub enum Instruction {
     /// 3: `[writable]` mata_holding_vault_ai
     /// 4: `[]` mata_holding_vault_authority_ai
     /// 5: `[writable]` mata_mint_ai
-    /// 6: `[]` wsol_mint_ai
+    /// 6: `[writable]` wsol_mint_ai
     /// 7: `[writable]` reward_mint_ai
     /// 8: `[]` reward_mint_authority_ai
     /// 9: `[]` sol_usdc_oracle_ai
     /// 10: `[]` sol_usdt_oracle_ai
     /// 11: `[]` sol_mata_oracle_ai
     /// 12: `[writable]` user_reward_account_ai
-    /// 13: `[writable]` user_account_ai
+    /// 13: `[writable, signer]` user_account_ai
     /// 14: `[writable]` user_wsol_account_ai
     /// 15: `[writable]` user_mata_account_ai
-    /// 16: `[]` pool_program_ai
+    /// 16: `[]` raydium_program_ai
     /// 17: `[writable]` pool_wsol_account_ai
     /// 18: `[writable]` pool_mata_account_ai
     /// 19: `[]` token_program_ai
@@ -560,10 +883,10 @@ pub enum Instruction {
     /// 9: `[]` lucra_sol_oracle_ai
     /// 10: `[]` sol_mata_oracle_ai
     /// 11: `[writable]` user_reward_account_ai
-    /// 12: `[writable]` user_account_ai
+    /// 12: `[writable, signer]` user_account_ai
     /// 13: `[writable]` user_lucra_account_ai
     /// 14: `[writable]` user_wsol_account_ai
-    /// 15: `[]` pool_program_ai
+    /// 15: `[]` raydium_program_ai
     /// 16: `[writable]` pool_lucra_account_ai
     /// 17: `[writable]` pool_wsol_account_ai
     /// 18: `[]` token_program_ai
@@ -595,16 +918,16 @@ pub enum Instruction {
     /// 10: `[]` sol_usdt_oracle_ai
     /// 11: `[]` sol_mata_oracle_ai
     /// 12: `[writable]` user_reward_account_ai
-    /// 13: `[]` user_account_ai
+    /// 13: `[signer]` user_account_ai
     /// 14: `[writable]` user_wsol_account_ai
     /// 15: `[writable]` user_mata_account_ai
-    /// 16: `[writable]` sm_amm_ai
+    /// 16: `[]` sm_amm_ai
     /// 17: `[]` sm_amm_authority_ai
     /// 18: `[writable]` sm_pool_base_vault_ai
     /// 19: `[writable]` sm_pool_quote_vault_ai
     /// 20: `[writable]` sm_pool_mint_ai
     /// 21: `[writable]` sm_pool_fees_ai
-    /// 22: `[]` token_swap_program_ai
+    /// 22: `[]` orca_swap_program_ai
     /// 23: `[]` token_program_ai
     /// 
     /// or
@@ -621,26 +944,63 @@ pub enum Instruction {
     /// 9: `[]` lucra_sol_oracle_ai
     /// 10: `[]` sol_mata_oracle_ai
     /// 11: `[writable]` user_reward_account_ai
-    /// 12: `[]` user_account_ai
+    /// 12: `[signer]` user_account_ai
     /// 13: `[writable]` user_lucra_account_ai
     /// 14: `[writable]` user_wsol_account_ai
-    /// 15: `[writable]` ls_amm_ai
+    /// 15: `[]` ls_amm_ai
     /// 16: `[]` ls_amm_authority_ai
     /// 17: `[writable]` ls_pool_base_vault_ai
     /// 18: `[writable]` ls_pool_quote_vault_ai
     /// 19: `[writable]` ls_pool_mint_ai
     /// 20: `[writable]` ls_pool_fees_ai
-    /// 21: `[]` token_swap_program_ai
-    /// 22: `[]` token_program_ai
+    /// 21: `[]` sm_amm_ai
+    /// 22: `[]` sm_pool_base_vault_ai
+    /// 23: `[]` sm_pool_quote_vault_ai
+    /// 24: `[]` orca_swap_program_ai
+    /// 25: `[]` token_program_ai
+    /// 
+    /// or, with `AmmTypes::Whirlpool`, mata through the registered SOL/MATA whirlpool. The vaults are in the
+    /// whirlpool's mint order and the tick arrays are the three a swap of mata walks through from the current tick
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` arb_state_ai
+    /// 2: `[writable]` arb_fund_ai
+    /// 3: `[writable]` mata_holding_vault_ai
+    /// 4: `[]` mata_holding_vault_authority_ai
+    /// 5: `[writable]` mata_mint_ai
+    /// 6: `[]` wsol_mint_ai
+    /// 7: `[writable]` reward_mint_ai
+    /// 8: `[]` reward_mint_authority_ai
+    /// 9: `[]` sol_usdc_oracle_ai
+    /// 10: `[]` sol_usdt_oracle_ai
+    /// 11: `[]` sol_mata_oracle_ai
+    /// 12: `[writable]` user_reward_account_ai
+    /// 13: `[signer]` user_account_ai
+    /// 14: `[writable]` user_wsol_account_ai
+    /// 15: `[writable]` user_mata_account_ai
+    /// 16: `[writable]` whirlpool_ai
+    /// 17: `[writable]` whirlpool_vault_a_ai
+    /// 18: `[writable]` whirlpool_vault_b_ai
+    /// 19: `[writable]` tick_array_0_ai
+    /// 20: `[writable]` tick_array_1_ai
+    /// 21: `[writable]` tick_array_2_ai
+    /// 22: `[]` whirlpool_oracle_ai
+    /// 23: `[]` whirlpool_program_ai
+    /// 24: `[]` token_program_ai
     SellFundsForArb {
         fund_source: u8,
         amm_type: u8,
         lamports: u64,
+        min_amount_out: u64,
     },
 
     /// Buy and burn a token using the sol bought during selling
     /// 
-    /// Accounts expected by this instruction (29 or 31 or 21 or 23)
+    /// Fails if the swap buys less than `min_amount_out` of the token being burned, 0 accepts any amount
+    /// 
+    /// Sets an `ArbResult` as return data, `amount_processed` is the lucra or mata burned
+    /// 
+    /// Accounts expected by this instruction (29 or 31 or 21 or 23 or 24)
     /// 
     /// 0: `[]` system_state_ai
     /// 1: `[writable]` arb_state_ai
@@ -652,10 +1012,10 @@ pub enum Instruction {
     /// 7: `[]` lucra_sol_oracle_ai
     /// 8: `[writable]` wsol_holding_vault_ai
     /// 9: `[writable]` user_reward_account_ai
-    /// 10: `[writable]` user_account_ai
+    /// 10: `[writable, signer]` user_account_ai
     /// 11: `[writable]` user_lucra_account_ai
     /// 12: `[writable]` user_wsol_account_ai
-    /// 13: `[]` pool_program_ai
+    /// 13: `[]` raydium_program_ai
     /// 14: `[writable]` _pool_lucra_account_ai
     /// 15: `[writable]` _pool_wsol_account_ai
     /// 16: `[]` token_program_ai
@@ -677,19 +1037,19 @@ pub enum Instruction {
     /// 0: `[]` system_state_ai
     /// 1: `[writable]` arb_state_ai
     /// 2: `[writable]` arb_fund_ai
-    /// 3: `[]` arb_fun_authority_ai
+    /// 3: `[]` arb_fund_authority_ai
     /// 4: `[writable]` mata_mint_ai
-    /// 5: `[]` wsol_mint_ai
+    /// 5: `[writable]` wsol_mint_ai
     /// 6: `[writable]` reward_mint_ai
     /// 7: `[]` reward_mint_authority_ai
     /// 8: `[]` sol_usdc_oracle_ai
     /// 9: `[]` sol_usdt_oracle_ai
     /// 10: `[]` sol_mata_oracle_ai
     /// 11: `[writable]` user_reward_account_ai
-    /// 12: `[writable]` user_account_ai
+    /// 12: `[writable, signer]` user_account_ai
     /// 13: `[writable]` user_wsol_account_ai
     /// 14: `[writable]` user_mata_account_ai
-    /// 15: `[]` pool_program_ai
+    /// 15: `[]` raydium_program_ai
     /// 16: `[writable]` pool_wsol_account_ai
     /// 17: `[writable]` pool_mata_account_ai
     /// 18: `[]` token_program_ai
@@ -717,17 +1077,17 @@ pub enum Instruction {
     /// 6: `[writable]` reward_mint_ai
     /// 7: `[]` reward_mint_authority_ai
     /// 8: `[]` lucra_sol_oracle_ai
-    /// 9: `[]` user_account_ai
-    /// 10: `[writable]` user_lucra_account_ai
-    /// 11: `[writable]` user_wsol_account_ai
-    /// 12: `[writable]` user_reward_account_ai
-    /// 13: `[writable]` ls_amm_ai
+    /// 9: `[writable]` user_reward_account_ai
+    /// 10: `[signer]` user_account_ai
+    /// 11: `[writable]` user_lucra_account_ai
+    /// 12: `[writable]` user_wsol_account_ai
+    /// 13: `[]` ls_amm_ai
     /// 14: `[]` ls_amm_authority_ai
     /// 15: `[writable]` ls_pool_base_vault_ai
     /// 16: `[writable]` ls_pool_quote_vault_ai
     /// 17: `[writable]` ls_pool_mint_ai
     /// 18: `[writable]` ls_pool_fees_ai
-    /// 19: `[]` token_swap_program_ai
+    /// 19: `[]` orca_swap_program_ai
     /// 20: `[]` token_program_ai
     /// 
     /// or
@@ -737,33 +1097,64 @@ pub enum Instruction {
     /// 2: `[writable]` arb_fund_ai
     /// 3: `[]` arb_fund_authority_ai
     /// 4: `[writable]` mata_mint_ai
-    /// 5: `[]` wsol_mint_ai
+    /// 5: `[writable]` wsol_mint_ai
     /// 6: `[writable]` reward_mint_ai
     /// 7: `[]` reward_mint_authority_ai
     /// 8: `[]` sol_usdc_oracle_ai
     /// 9: `[]` sol_usdt_oracle_ai
     /// 10: `[]` sol_mata_oracle_ai
-    /// 11: `[]` user_account_ai
-    /// 12: `[writable]` user_wsol_account_ai
-    /// 13: `[writable]` user_mata_account_ai 
-    /// 14: `[writable]` user_reward_account_ai 
-    /// 15: `[writable]` sm_amm_ai
+    /// 11: `[writable]` user_reward_account_ai
+    /// 12: `[signer]` user_account_ai
+    /// 13: `[writable]` user_wsol_account_ai
+    /// 14: `[writable]` user_mata_account_ai
+    /// 15: `[]` sm_amm_ai
     /// 16: `[]` sm_amm_authority_ai
     /// 17: `[writable]` sm_pool_base_vault_ai
     /// 18: `[writable]` sm_pool_quote_vault_ai
     /// 19: `[writable]` sm_pool_mint_ai
     /// 20: `[writable]` sm_pool_fees_ai
-    /// 21: `[]` token_swap_program_ai
+    /// 21: `[]` orca_swap_program_ai
     /// 22: `[]` token_program_ai
+    /// 
+    /// or, with `AmmTypes::Whirlpool`, mata through the registered SOL/MATA whirlpool. The vaults are in the
+    /// whirlpool's mint order and the tick arrays are the three a swap of SOL walks through from the current tick
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` arb_state_ai
+    /// 2: `[writable]` arb_fund_ai
+    /// 3: `[]` arb_fund_authority_ai
+    /// 4: `[writable]` mata_mint_ai
+    /// 5: `[writable]` wsol_mint_ai
+    /// 6: `[writable]` reward_mint_ai
+    /// 7: `[]` reward_mint_authority_ai
+    /// 8: `[]` sol_usdc_oracle_ai
+    /// 9: `[]` sol_usdt_oracle_ai
+    /// 10: `[]` sol_mata_oracle_ai
+    /// 11: `[writable]` user_reward_account_ai
+    /// 12: `[signer]` user_account_ai
+    /// 13: `[writable]` user_wsol_account_ai
+    /// 14: `[writable]` user_mata_account_ai
+    /// 15: `[writable]` whirlpool_ai
+    /// 16: `[writable]` whirlpool_vault_a_ai
+    /// 17: `[writable]` whirlpool_vault_b_ai
+    /// 18: `[writable]` tick_array_0_ai
+    /// 19: `[writable]` tick_array_1_ai
+    /// 20: `[writable]` tick_array_2_ai
+    /// 21: `[]` whirlpool_oracle_ai
+    /// 22: `[]` whirlpool_program_ai
+    /// 23: `[]` token_program_ai
     BuyBurnForArb {
         fund_source: u8,
         amm_type: u8,
         lamports: u64,
+        min_amount_out: u64,
     },
 
     /// Stakes the wsol in the holding account and sends it to the arb coffer
     /// 
-    /// Accounts expected by this instruction (23)
+    /// Sets an `ArbResult` as return data, `amount_processed` is the msol sent to the arb coffer
+    /// 
+    /// Accounts expected by this instruction (22)
     /// 
     /// 0: `[]` system_state_ai
     /// 1: `[]` arb_state_ai
@@ -784,14 +1175,18 @@ pub enum Instruction {
     /// 16: `[]` liq_pool_msol_leg_authority_ai
     /// 17: `[writable]` reserve_pda_ai
     /// 18: `[]` msol_mint_authority_ai
-    /// 19: `[]` fees_ai
-    /// 20: `[]` system_program_ai
-    /// 21: `[]` marinade_program_ai
-    /// 22: `[]` token_program_ai
+    /// 19: `[]` system_program_ai
+    /// 20: `[]` marinade_program_ai
+    /// 21: `[]` token_program_ai
     CleanUpArb {},
 
     /// Mints the funds to start the arbitrage process
     /// 
+    /// Sets an `ArbResult` as return data: the bincode encoded amount_processed u64, state u8 (the arb
+    /// state's StateEnum), remaining_today u64 and reward_tokens_minted u64, 25 bytes in all.
+    /// `amount_processed` is the lucra or mata minted. SellFundsForArb, BuyBurnForArb and CleanUpArb set
+    /// the same layout.
+    /// 
     /// Accounts expected by this instruction (19 or 26 or 18 or 26 or 30 or 26 or 27)
     /// 
     /// 0: `[]` system_state_ai
@@ -833,8 +1228,8 @@ pub enum Instruction {
     /// 14: `[]` sm_raydium_base_mint_ai
     /// 15: `[]` sm_raydium_quote_vault_ai
     /// 16: `[]` sm_raydium_quote_mint_ai
-    /// 17: `[]` sm_raydium_amm_ai
-    /// 18: `[]` sm_raydium_amm_open_orders_ai
+    /// 17: `[]` sm_raydium_amm_open_orders_ai
+    /// 18: `[]` sm_raydium_amm_ai
     /// 19: `[]` sm_orca_base_vault_ai
     /// 20: `[]` sm_orca_quote_vault_ai
     /// 21: `[]` sm_orca_amm_ai
@@ -900,8 +1295,8 @@ pub enum Instruction {
     /// 2: `[writable]` arb_coffer_ai
     /// 3: `[]` arb_coffer_authority_ai
     /// 4: `[writable]` arb_fund_ai
-    /// 5: `[]` mata_mint_ai
-    /// 6: `[]` wsol_mint_ai
+    /// 5: `[writable]` mata_mint_ai
+    /// 6: `[writable]` wsol_mint_ai
     /// 7: `[writable]` reward_mint_ai
     /// 8: `[]` reward_mint_authority_ai
     /// 9: `[]` sol_usdc_oracle_ai
@@ -914,7 +1309,7 @@ pub enum Instruction {
     /// 16: `[]` sm_orca_base_vault_ai
     /// 17: `[]` sm_orca_quote_vault_ai
     /// 18: `[]` sm_orca_amm_ai
-    /// 19: `[writable]` user_account_ai
+    /// 19: `[writable, signer]` user_account_ai
     /// 20: `[writable]` user_msol_account_ai
     /// 21: `[writable]` user_reward_account_ai
     /// 22: `[writable]` msol_mint_ai
@@ -928,12 +1323,12 @@ pub enum Instruction {
     /// 
     /// or
     /// 
-    /// 0: `[]` system_state_ai,
+    /// 0: `[]` system_state_ai
     /// 1: `[writable]` arb_state_ai
     /// 2: `[writable]` arb_coffer_ai
     /// 3: `[]` arb_coffer_authority_ai
     /// 4: `[writable]` arb_fund_ai
-    /// 5: `[]` mata_mint_ai
+    /// 5: `[writable]` mata_mint_ai
     /// 6: `[writable]` reward_mint_ai
     /// 7: `[]` reward_mint_authority_ai
     /// 8: `[]` sol_usdc_oracle_ai
@@ -943,7 +1338,7 @@ pub enum Instruction {
     /// 12: `[]` sm_base_mint_ai
     /// 13: `[]` sm_quote_vault_ai
     /// 14: `[]` sm_amm_ai
-    /// 15: `[writable]` user_account_ai
+    /// 15: `[writable, signer]` user_account_ai
     /// 16: `[writable]` user_msol_account_ai
     /// 17: `[writable]` user_reward_account_ai
     /// 18: `[writable]` msol_mint_ai
@@ -962,7 +1357,7 @@ pub enum Instruction {
     /// 2: `[writable]` arb_coffer_ai
     /// 3: `[]` arb_coffer_authority_ai
     /// 4: `[writable]` arb_fund_ai
-    /// 5: `[]` mata_mint_ai
+    /// 5: `[writable]` mata_mint_ai
     /// 6: `[writable]` reward_mint_ai
     /// 7: `[]` reward_mint_authority_ai
     /// 8: `[]` sol_usdc_oracle_ai
@@ -971,9 +1366,9 @@ pub enum Instruction {
     /// 11: `[]` sm_base_vault_ai
     /// 12: `[]` sm_base_mint_ai
     /// 13: `[]` sm_quote_vault_ai
-    /// 14: `[]` sm_open_orders_ai
+    /// 14: `[]` sm_amm_open_orders_ai
     /// 15: `[]` sm_amm_ai
-    /// 16: `[writable]` user_account_ai
+    /// 16: `[writable, signer]` user_account_ai
     /// 17: `[writable]` user_msol_account_ai
     /// 18: `[writable]` user_reward_account_ai
     /// 19: `[writable]` msol_mint_ai
@@ -989,887 +1384,4487 @@ pub enum Instruction {
         amm_type: u8,
         lamports: u64,
     },
-}
 
-#[allow(clippy::too_many_arguments)]
-pub fn initialize(
-    marinade_state: &Pubkey,
-    state: &Pubkey,
-    staking_state: &Pubkey,
-    arb_state: &Pubkey,
-    mata_mint: &Pubkey,
-    lucra_mint: &Pubkey,
-    reward_mint: &Pubkey,
-    staked_lucra_mint: &Pubkey,
-    msol_vault: &Pubkey,
-    arb_coffer: &Pubkey,
+    /// Creates the revenue ledger that tracks protocol revenue per epoch. Can only be run once.
+    /// 
+    /// Accounts expected by this instruction (3)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[writable]` revenue_ledger_ai
+    /// 2: `[]` creator_authority_ai
+    CreateRevenueLedger {},
+
+    /// DAO instruction that puts the protocol into global settlement. New loans, stakes and arbs are
+    /// rejected, penalties stop, loans close out at the settlement price and unstaking skips the timelock.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` dao_authority_ai
+    GlobalSettlement {
+        settlement_sol_price: u64,
+    },
+
+    /// DAO instruction for sweeping the msol vault once the settlement deadline has passed
+    /// 
+    /// Accounts expected by this instruction (6)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[]` dao_authority_ai
+    /// 2: `[writable]` msol_vault_ai
+    /// 3: `[writable]` to_account_ai
+    /// 4: `[]` transfer_authority_ai
+    /// 5: `[]` token_program_ai
+    ReclaimExcess {},
+
+    /// Rolls the arb limits window forward to today. Pays a reward token when there was something to roll.
+    /// 
+    /// Accounts expected by this instruction (6)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` arb_state_ai
+    /// 2: `[writable]` user_reward_account_ai
+    /// 3: `[writable]` reward_mint_ai
+    /// 4: `[]` reward_mint_authority_ai
+    /// 5: `[]` token_program_ai
+    RollArbWindow {},
+
+    /// Creator instruction that creates the token metadata for the reward mint so wallets can show it
+    /// 
+    /// Accounts expected by this instruction (7)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` creator_authority_ai - pays for the metadata account
+    /// 2: `[]` reward_mint_ai
+    /// 3: `[]` reward_mint_authority_ai
+    /// 4: `[writable]` metadata_ai
+    /// 5: `[]` metadata_program_ai
+    /// 6: `[]` system_program_ai
+    SetRewardMetadata {
+        name: String,
+        symbol: String,
+        uri: String,
+    },
+
+    /// Blocks Stake, StartUnstake, WithdrawStake and ClaimReward for the staking account straight away
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` staking_account_ai
+    /// 1: `[]` authority_ai - the owner or the recovery key
+    FreezeStakingAccount {},
+
+    /// Unfreezes a staking account. The recovery key unfreezes it straight away, the owner's
+    /// first call starts a 48 hour delay and a second call once it has passed clears the freeze.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` staking_account_ai
+    /// 1: `[]` authority_ai - the owner or the recovery key
+    UnfreezeStakingAccount {},
+
+    /// Sets the key that can unfreeze a staking account without the delay. Not allowed while frozen.
+    /// 
+    /// Accounts expected by this instruction (2 + 1 optional)
+    /// 
+    /// 0: `[writable]` staking_account_ai
+    /// 1: `[]` owner_ai
+    /// 2: `[]` current_recovery_key_ai - required once a recovery key is set
+    SetStakingRecoveryKey {
+        recovery_key: Pubkey,
+    },
+
+    /// DAO instruction that registers the LP vault and sets the collateral requirement for LP backed loans.
+    /// A requirement of 0 stops new LP backed loans, otherwise it can't be under the msol one.
+    /// 
+    /// Accounts expected by this instruction (4)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` dao_authority_ai
+    /// 2: `[]` sol_mata_pool_ai
+    /// 3: `[]` lp_vault_ai - owned by the LP vault authority, can't change once set
+    SetLpCollateral {
+        lp_collateral_requirement: CollateralRatio,
+    },
+
+    /// Anyone can run this once a reward can't be claimed anymore. The unclaimed remainder goes from the
+    /// rewards vault to the arb coffer and the reward account is closed. Rewards are swept in drop order.
+    /// 
+    /// Accounts expected by this instruction (11)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` staking_state_ai
+    /// 2: `[writable]` reward_ai
+    /// 3: `[writable]` rewards_vault_ai
+    /// 4: `[writable]` arb_coffer_ai
+    /// 5: `[]` rewards_vault_transfer_authority_ai
+    /// 6: `[writable]` receiver_ai - gets the reward account's lamports
+    /// 7: `[writable]` user_reward_account_ai
+    /// 8: `[writable]` reward_mint_ai
+    /// 9: `[]` reward_mint_authority_ai
+    /// 10: `[]` token_program_ai
+    SweepExpiredReward {},
+
+    /// DAO instruction that sets the smallest reward a drop will create. A drop under it only moves the
+    /// drop timestamp and leaves the yield in the msol vault for the next one. 0 drops every epoch.
+    /// 
+    /// Accounts expected by this instruction (3)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` staking_state_ai
+    /// 2: `[]` dao_authority_ai
+    SetMinDropAmount {
+        min_drop_amount: u64,
+    },
+
+    /// DAO instruction that sets when reward tokens stop being redeemable. The deadline has to be at least
+    /// the notice period away, redemptions in the grace period before it get the boost. A deadline of 0
+    /// cancels the sunset, a deadline that has passed can't be changed.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` dao_authority_ai
+    SetRewardRedemptionDeadline {
+        deadline: i64,
+        grace_period: i64,
+        grace_boost: Bps,
+    },
+
+    /// DAO instruction that permanently ends crank rewards once the redemption deadline has passed.
+    /// Cranks keep working but no reward tokens are minted for them.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` dao_authority_ai
+    CloseRewardProgram {},
+
+    /// Reallocs an account created before the reserved layout to its new size, zeroing the reserved
+    /// region. The payer tops the account up to rent exemption. Anyone can migrate a loan or a stake
+    /// balance, the system state and arb state need the DAO the system state names. The arb state is
+    /// migrated after the system state.
+    /// 
+    /// Accounts expected by this instruction (3 + 2 optional)
+    /// 
+    /// 0: `[writable]` account_ai
+    /// 1: `[writable, signer]` payer_ai
+    /// 2: `[]` system_program_ai
+    /// 3: `[signer]` dao_authority_ai - required for the system state and arb state
+    /// 4: `[]` system_state_ai - required for the arb state
+    MigrateAccountLayout {},
+
+    /// DAO instruction that rewrites the prices of a day in the price history an oracle misreported.
+    /// The first correction of a day creates its price correction account, which keeps the prices the
+    /// day was originally recorded with. Today can't be corrected.
+    /// 
+    /// Accounts expected by this instruction (5)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` price_history_ai
+    /// 2: `[writable]` price_correction_ai - derived from the system state and the date
+    /// 3: `[writable, signer]` dao_authority_ai - pays for the price correction account
+    /// 4: `[]` system_program_ai
+    /// 5: `[writable]` price_history_page_ai - once the price history is paged, the page the day is archived on
+    CorrectPriceHistory {
+        date: i64,
+        corrected_sol_price: u64,
+        corrected_lucra_price: u64,
+    },
+
+    /// Recomputes penalty a loan was charged for days the DAO has since corrected. Penalty waiting to be
+    /// harvested is reduced, penalty already harvested is credited as a rebate paid when the loan closes.
+    /// Only the last days kept in the loan's penalty journal can be recomputed.
+    /// 
+    /// Accounts expected by this instruction (3)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` loan_ai
+    /// 2: `[]` price_history_ai
+    /// 
+    /// Once the DAO registers an MSOL/SOL oracle, `[]` msol_sol_oracle_ai followed by `[]` marinade_state_ai
+    /// have to be appended as well
+    RecomputePenalty {},
+
+    /// Crank that values every protocol holding at oracle prices and writes the official TVL to the
+    /// TVL snapshot, see TvlComponents for what counts. Runs at most once an hour and pays the usual
+    /// crank reward. The first run creates the snapshot account.
+    /// 
+    /// Accounts expected by this instruction (20)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[]` arb_state_ai
+    /// 2: `[writable]` tvl_snapshot_ai - derived from the system state
+    /// 3: `[]` marinade_state_ai
+    /// 4: `[]` msol_vault_ai
+    /// 5: `[]` arb_coffer_ai
+    /// 6: `[]` rewards_vault_ai
+    /// 7: `[]` wsol_holding_vault_ai
+    /// 8: `[]` mata_holding_vault_ai
+    /// 9: `[]` lucra_holding_vault_ai
+    /// 10: `[]` sol_usdc_oracle_ai
+    /// 11: `[]` sol_usdt_oracle_ai
+    /// 12: `[]` lucra_sol_oracle_ai
+    /// 13: `[]` sol_mata_oracle_ai
+    /// 14: `[writable, signer]` user_account_ai - pays for the snapshot account on the first run
+    /// 15: `[writable]` user_reward_account_ai
+    /// 16: `[writable]` reward_mint_ai
+    /// 17: `[]` reward_mint_authority_ai
+    /// 18: `[]` system_program_ai
+    /// 19: `[]` token_program_ai
+    ComputeTvl {},
+
+    /// Closes a default loan and opens it again with `new_lamports` of collateral in one go. The user only
+    /// supplies or receives the difference in mata and in msol, the new terms are written onto the same loan.
+    /// The close timelock and every create check apply, the supply cap against the end state.
+    /// 
+    /// Accounts expected by this instruction (14)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` msol_vault_ai
+    /// 4: `[]` msol_vault_authority_ai
+    /// 5: `[writable]` mata_mint_ai
+    /// 6: `[]` mata_mint_authority_ai
+    /// 7: `[signer]` user_account_ai
+    /// 8: `[writable]` user_mata_account_ai
+    /// 9: `[writable]` user_msol_account_ai - supplies or receives the msol difference
+    /// 10: `[]` sol_usdc_oracle_ai
+    /// 11: `[]` sol_usdt_oracle_ai
+    /// 12: `[]` sol_mata_oracle_ai
+    /// 13: `[]` token_program_ai
+    /// 
+    /// Once the DAO registers an MSOL/SOL oracle, `[]` msol_sol_oracle_ai has to be appended. A stale SOL/MATA
+    /// oracle fails the peg check, trailing SOL/MATA pool accounts are no longer read.
+    RolloverLoan { new_lamports: u64 },
+
+    /// Burns `mata` of a loan's debt without closing it. With `release_collateral` the same share of the
+    /// collateral the loan has left comes back as msol, and of a lucra backed loan's locked stake. The
+    /// loan's pending penalty has to be harvested first and what's left has to stay above the minimum loan.
+    /// 
+    /// Accounts expected by this instruction (10)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[signer]` user_account_ai
+    /// 4: `[writable]` user_msol_account_ai
+    /// 5: `[writable]` mata_mint_ai
+    /// 6: `[writable]` user_mata_account_ai
+    /// 7: `[]` msol_vault_authority_ai
+    /// 8: `[writable]` msol_vault_ai
+    /// 9: `[]` token_program_ai
+    /// 
+    /// or, for a lucra backed loan
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[signer]` user_account_ai
+    /// 4: `[writable]` user_msol_account_ai
+    /// 5: `[writable]` mata_mint_ai
+    /// 6: `[writable]` user_mata_account_ai
+    /// 7: `[]` msol_vault_authority_ai
+    /// 8: `[writable]` msol_vault_ai
+    /// 9: `[writable]` staking_account_ai
+    /// 10: `[]` token_program_ai
+    RepayLoanPartial { mata: u64, release_collateral: bool },
+
+    /// Mints `mata` more against a loan's collateral at today's SOL price. The loan has to stay at the
+    /// collateral requirement and the supply under the cap, and the peg has to hold like for a new loan.
+    /// Not for LP backed loans.
+    /// 
+    /// Accounts expected by this instruction (11)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` mata_mint_ai
+    /// 4: `[]` mata_mint_authority_ai
+    /// 5: `[signer]` user_account_ai
+    /// 6: `[writable]` user_mata_account_ai
+    /// 7: `[]` sol_usdc_oracle_ai
+    /// 8: `[]` sol_usdt_oracle_ai
+    /// 9: `[]` sol_mata_oracle_ai
+    /// 10: `[]` token_program_ai
+    /// 
+    /// Once the DAO registers an MSOL/SOL oracle, `[]` msol_sol_oracle_ai has to be appended. A stale SOL/MATA
+    /// oracle fails the peg check, trailing SOL/MATA pool accounts are no longer read.
+    BorrowMore { mata: u64 },
+
+    /// DAO instruction that sets when a loan can be liquidated and what the liquidator is paid for it. The
+    /// ratio has to be at least 100% and under the collateral requirement, a ratio of 0 turns liquidation off.
+    /// The bonus is capped at 20%.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` dao_authority_ai
+    SetLiquidationParams {
+        liquidation_ratio: CollateralRatio,
+        liquidation_bonus: Bps,
+    },
+
+    /// Repays the whole debt of a loan whose collateral fell under the liquidation ratio. The liquidator's
+    /// mata is burned for the debt and its interest, and they get its value plus the bonus out of the
+    /// collateral as msol, whatever is left goes to the arb coffer. An LP backed loan pays out LP tokens and
+    /// keeps what's left in the LP vault. Not for native SOL loans.
+    /// 
+    /// The loan's pending penalty is settled before the split. With `amm_type` Orca its msol is unstaked and
+    /// swapped for mata that's burned, the way HarvestPenalty does, and the liquidator gets the reward fee.
+    /// With None, or under the minimum harvest amount, the penalty's msol goes to the arb coffer. Raydium and
+    /// Whirlpool aren't supported yet, an LP backed loan only takes None.
+    /// 
+    /// Accounts expected by this instruction (13)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` msol_vault_ai
+    /// 4: `[]` msol_vault_authority_ai
+    /// 5: `[writable]` arb_coffer_ai
+    /// 6: `[writable]` mata_mint_ai
+    /// 7: `[signer]` liquidator_ai
+    /// 8: `[writable]` liquidator_mata_account_ai
+    /// 9: `[writable]` liquidator_msol_account_ai
+    /// 10: `[]` sol_usdc_oracle_ai
+    /// 11: `[]` sol_usdt_oracle_ai
+    /// 12: `[]` token_program_ai
+    /// 
+    /// A lucra backed loan unlocks the owner's stake (14)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` msol_vault_ai
+    /// 4: `[]` msol_vault_authority_ai
+    /// 5: `[writable]` arb_coffer_ai
+    /// 6: `[writable]` mata_mint_ai
+    /// 7: `[signer]` liquidator_ai
+    /// 8: `[writable]` liquidator_mata_account_ai
+    /// 9: `[writable]` liquidator_msol_account_ai
+    /// 10: `[]` sol_usdc_oracle_ai
+    /// 11: `[]` sol_usdt_oracle_ai
+    /// 12: `[writable]` staking_account_ai
+    /// 13: `[]` token_program_ai
+    /// 
+    /// With `amm_type` Orca, marinade_state_ai and liquidator_ai become `[writable]` and the penalty swap
+    /// follows either layout (15)
+    /// 
+    /// 0: `[]` sol_mata_oracle_ai
+    /// 1: `[writable]` liquidator_wsol_account_ai
+    /// 2: `[writable]` msol_mint_ai
+    /// 3: `[writable]` liq_pool_sol_leg_pda_ai
+    /// 4: `[writable]` liq_pool_msol_leg_ai
+    /// 5: `[writable]` treasury_msol_account_ai
+    /// 6: `[]` system_program_ai
+    /// 7: `[]` marinade_program_ai
+    /// 8: `[writable]` sm_amm_ai
+    /// 9: `[]` sm_amm_authority_ai
+    /// 10: `[writable]` sm_pool_base_vault_ai
+    /// 11: `[writable]` sm_pool_quote_vault_ai
+    /// 12: `[writable]` sm_pool_mint_ai
+    /// 13: `[writable]` sm_pool_fees_ai
+    /// 14: `[]` token_swap_program_ai
+    /// 
+    /// Once the DAO registers an MSOL/SOL oracle, `[]` msol_sol_oracle_ai has to be appended to any of these.
+    /// 
+    /// An LP backed loan takes `amm_type` Orca. The liquidator's LP tokens, valued off the SOL/MATA pool, are
+    /// withdrawn from it and the SOL leg sold back, so the liquidator is paid in mata (20)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` sol_mata_pool_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` lp_vault_ai
+    /// 4: `[]` lp_vault_authority_ai
+    /// 5: `[writable]` mata_mint_ai
+    /// 6: `[signer]` liquidator_ai
+    /// 7: `[writable]` liquidator_mata_account_ai
+    /// 8: `[writable]` liquidator_lp_account_ai
+    /// 9: `[]` sol_usdc_oracle_ai
+    /// 10: `[]` sol_usdt_oracle_ai
+    /// 11: `[]` sol_mata_oracle_ai
+    /// 12: `[writable]` pool_sol_vault_ai
+    /// 13: `[writable]` pool_mata_vault_ai
+    /// 14: `[writable]` lp_mint_ai
+    /// 15: `[]` token_program_ai
+    /// 16: `[writable]` liquidator_wsol_account_ai
+    /// 17: `[]` pool_authority_ai
+    /// 18: `[writable]` pool_fees_ai
+    /// 19: `[]` token_swap_program_ai
+    LiquidateLoan { amm_type: u8 },
+
+    /// Hands a loan to another wallet. The loan can't have unharvested penalty or be repaid. A lucra backed
+    /// loan moves its pledge to the new owner's staking account, which has to have the stake to hold it.
+    /// 
+    /// Accounts expected by this instruction (3)
+    /// 
+    /// 0: `[writable]` loan_ai
+    /// 1: `[signer]` current_owner_ai
+    /// 2: `[signer]` new_owner_ai
+    /// 
+    /// A lucra backed loan moves the pledge (9)
+    /// 
+    /// 0: `[writable]` loan_ai
+    /// 1: `[signer]` current_owner_ai
+    /// 2: `[signer]` new_owner_ai
+    /// 3: `[]` system_state_ai
+    /// 4: `[writable]` current_staking_account_ai
+    /// 5: `[writable]` new_staking_account_ai
+    /// 6: `[]` sol_usdc_oracle_ai
+    /// 7: `[]` sol_usdt_oracle_ai
+    /// 8: `[]` lucra_sol_oracle_ai
+    TransferLoanOwnership { },
+
+    /// Closes a stake balance once its deposit, stake and pending vaults are all empty. The vaults are
+    /// closed with it and the owner gets the rent of all four accounts back.
+    /// 
+    /// Accounts expected by this instruction (8)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` stake_balance_ai
+    /// 2: `[writable]` deposit_vault_ai
+    /// 3: `[writable]` stake_vault_ai
+    /// 4: `[writable]` pending_vault_ai
+    /// 5: `[writable, signer]` owner_ai
+    /// 6: `[]` stake_balance_authority_ai
+    /// 7: `[]` token_program_ai
+    CloseStakeBalance { },
+
+    /// Closes a staking account with nothing staked and nothing pledged to a lucra backed loan. The owner
+    /// gets the rent back. A frozen account can't be closed.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` staking_account_ai
+    /// 1: `[writable, signer]` owner_ai
+    CloseStakingAccount { },
+
+    /// Cancels a pending withdrawal that hasn't been ended. The lucra goes back into the stake vault and the
+    /// staked lucra is minted again, as if it was staked now. The owner gets the withdrawal's rent back.
+    /// 
+    /// Accounts expected by this instruction (13)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` staking_state_ai
+    /// 2: `[writable]` staking_account_ai
+    /// 3: `[writable]` stake_balance_ai
+    /// 4: `[writable]` pending_withdrawal_ai
+    /// 5: `[writable]` pending_vault_ai
+    /// 6: `[writable]` stake_vault_ai
+    /// 7: `[writable, signer]` owner_ai
+    /// 8: `[]` stake_balance_authority_ai
+    /// 9: `[writable]` staked_lucra_mint_ai
+    /// 10: `[writable]` user_staked_lucra_account_ai
+    /// 11: `[]` mint_authority_ai - mint authority for staked lucra
+    /// 12: `[]` token_program_ai
+    CancelUnstake { },
+
+    /// DAO instruction that proposes the next DAO authority. Nothing changes until the proposed key accepts,
+    /// proposing the default key withdraws a pending proposal.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[signer]` dao_authority_ai
+    ProposeAuthority { new_authority: Pubkey },
+
+    /// Hands the DAO authority to the proposed key, which has to sign. The old authority loses its power.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[signer]` new_authority_ai
+    AcceptAuthority { },
+
+    /// Sets which features are paused, true pauses one. Signed by the DAO authority or the guardian, the
+    /// guardian can only pause and a call from it that would turn a paused feature back on fails.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[signer]` authority_ai - DAO authority or guardian
+    SetPause {
+        loans: bool,
+        staking: bool,
+        arbitrage: bool,
+    },
+
+    /// Returns `lamports` of a loan's SOL collateral to its owner as msol, converted at the marinade rate.
+    /// What's left has to keep the loan at its own collateral rate at today's SOL price, pending penalty
+    /// doesn't count. Waits out the same timelock as a repay and needs the peg to hold like BorrowMore.
+    /// Not for LP backed loans.
+    /// 
+    /// Accounts expected by this instruction (11)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[writable]` loan_ai
+    /// 3: `[writable]` msol_vault_ai
+    /// 4: `[]` msol_vault_authority_ai
+    /// 5: `[signer]` user_account_ai
+    /// 6: `[writable]` user_msol_account_ai
+    /// 7: `[]` sol_usdc_oracle_ai
+    /// 8: `[]` sol_usdt_oracle_ai
+    /// 9: `[]` sol_mata_oracle_ai
+    /// 10: `[]` token_program_ai
+    /// 
+    /// Once the DAO registers an MSOL/SOL oracle, `[]` msol_sol_oracle_ai has to be appended. A stale SOL/MATA
+    /// oracle fails the peg check, trailing SOL/MATA pool accounts are no longer read.
+    WithdrawCollateral { lamports: u64 },
+
+    /// Moves a stake balance created before the reward index onto it. Every reward it can still claim has to
+    /// be claimed first, later drops are claimed through the index. Anyone can call it.
+    /// 
+    /// Accounts expected by this instruction (3)
+    /// 
+    /// 0: `[]` staking_state_ai
+    /// 1: `[writable]` stake_balance_ai
+    /// 2: `[]` stake_vault_ai
+    MigrateStakeBalance {},
+
+    /// Quotes a loan of `lamports` without opening it. Prices and sizes the loan the way CreateMataLoan
+    /// does, with and without locked stake, and sets a bincode `MataLoanQuote` as return data for a
+    /// simulated transaction to read. Nothing is written.
+    /// 
+    /// Accounts expected by this instruction (5)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[]` marinade_state_ai
+    /// 2: `[]` sol_usdc_oracle_ai
+    /// 3: `[]` sol_usdt_oracle_ai
+    /// 4: `[]` sol_mata_oracle_ai
+    /// 
+    /// Once the DAO registers an MSOL/SOL oracle, `[]` msol_sol_oracle_ai has to be appended. A stale SOL/MATA
+    /// oracle fails the peg check, trailing SOL/MATA pool accounts are no longer read.
+    QuoteMataLoan { lamports: u64 },
+
+    /// Creates the penalty history of a loan, DeterminePenalty records the last 30 days it charged the loan
+    /// for in it once it's passed. Anyone can pay for it.
+    /// 
+    /// Accounts expected by this instruction (4)
+    /// 
+    /// 0: `[]` loan_ai
+    /// 1: `[writable]` penalty_history_ai
+    /// 2: `[writable, signer]` payer_ai
+    /// 3: `[]` system_program_ai
+    CreatePenaltyHistory {},
+
+    /// Creates the event queue of the system state. Loans, staking and the arb instructions append what they
+    /// did to it when it's passed as their last account, indexers read it instead of the transaction logs.
+    /// 
+    /// Accounts expected by this instruction (4)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` event_queue_ai
+    /// 2: `[writable, signer]` creator_authority_ai
+    /// 3: `[]` system_program_ai
+    CreateEventQueue {},
+
+    /// ClaimReward, with the reward lucra staked into the stake balance's stake vault instead of paid to
+    /// lucra_account_ai when `compound` is set. Compounding stakes it the way Stake does: the staked lucra
+    /// is minted for it and the stake timestamp moves to now, so it needs staking enabled and every reward
+    /// claimed with this one. Without `compound` this is ClaimReward and the last two accounts are unused.
+    /// 
+    /// Accounts expected by this instruction (16):
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` staking_state_ai
+    /// 2: `[writable]` staking_account_ai
+    /// 3: `[writable]` stake_balance_ai
+    /// 4: `[writable]` reward_ai - unused on the reward index
+    /// 5: `[writable]` user_staked_lucra_account_ai
+    /// 6: `[writable]` lucra_vault_ai
+    /// 7: `[writable]` lucra_account_ai
+    /// 8: `[writable]` rewards_vault_ai
+    /// 9: `[writable]` msol_account_ai
+    /// 10: `[]` rewards_vault_transfer_authority_ai
+    /// 11: `[writable]` lucra_mint_ai
+    /// 12: `[]` lucra_mint_authority_ai
+    /// 13: `[]` token_program_ai
+    /// 14: `[writable]` staked_lucra_mint_ai
+    /// 15: `[]` stake_mint_authority_ai
+    ClaimRewardV2 { compound: bool },
+
+    /// DAO instruction that sets the minimum harvest amount and the reward fee without resubmitting every
+    /// UpdateState field. None leaves a field unchanged. The fee is capped, and the minimum harvest has to be
+    /// worth ten fees, checked against the kept value of a field that isn't set.
+    /// 
+    /// Accounts expected by this instruction (2)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[]` dao_authority_ai
+    UpdateHarvestParams {
+        minimum_harvest_amount: Option<Lamports>,
+        reward_fee: Option<RewardFee>,
+    },
+
+    /// Creates the sol vault of the system state. Native SOL loans keep their collateral in it as lamports,
+    /// none can be opened until it exists and the DAO has set native_loans_enabled with UpdateState.
+    /// 
+    /// Accounts expected by this instruction (4)
+    /// 
+    /// 0: `[writable]` system_state_ai
+    /// 1: `[writable]` sol_vault_ai
+    /// 2: `[writable, signer]` creator_authority_ai
+    /// 3: `[]` system_program_ai
+    CreateSolVault {},
+
+    /// Creates the next page of the price history's archive, anyone can pay for it. The first page also
+    /// creates the header and migrates the price history onto the paged version, the days it has are
+    /// archived to the page. Create the next page before the active one fills up, UpdatePriceHistory
+    /// fails once it can't archive a day.
+    /// 
+    /// Accounts expected by this instruction (5)
+    /// 
+    /// 0: `[writable]` price_history_ai
+    /// 1: `[writable]` price_history_header_ai
+    /// 2: `[writable]` price_history_page_ai - derived from the price history and the header's page count
+    /// 3: `[writable, signer]` payer_ai
+    /// 4: `[]` system_program_ai
+    CreatePriceHistoryPage {},
+
+    /// DAO instruction. Registers a keeper, or sets the multiplier of one that already is. The crank rewards
+    /// it's paid are multiplied by `multiplier` in bps, at most 3x, and it can still run cranks while cranking
+    /// is restricted. The first keeper creates the registry. At most 32 keepers can be registered.
+    /// 
+    /// A keeper runs a crank by appending `[]` keeper_registry_ai and `[signer]` keeper_ai as its last two
+    /// accounts, after any of the crank's own optional accounts.
+    /// 
+    /// Accounts expected by this instruction (6)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` keeper_registry_ai
+    /// 2: `[]` keeper_ai
+    /// 3: `[signer]` dao_authority_ai
+    /// 4: `[writable, signer]` payer_ai
+    /// 5: `[]` system_program_ai
+    RegisterKeeper { multiplier: Bps },
+
+    /// DAO instruction. Removes a keeper from the registry, from then on its cranks are paid the base reward.
+    /// 
+    /// Accounts expected by this instruction (4)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` keeper_registry_ai
+    /// 2: `[]` keeper_ai
+    /// 3: `[signer]` dao_authority_ai
+    RemoveKeeper {},
+
+    /// Redeems reward tokens for msol out of the rewards vault instead of minting Lucra. Pays the SOL value of
+    /// the Lucra RedeemRewardTokens would mint, at the lucra_sol oracle price, in msol at marinade's msol
+    /// price. Fails with InvalidAmount when the rewards vault can't cover it. Refused once the reward
+    /// redemption deadline has passed and boosted during the grace period before it, like RedeemRewardTokens.
+    /// 
+    /// Accounts expected by this instruction (10)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` user_reward_account_ai
+    /// 2: `[writable]` user_msol_account_ai
+    /// 3: `[signer]` user_authority_ai
+    /// 4: `[writable]` reward_mint_ai
+    /// 5: `[writable]` rewards_vault_ai
+    /// 6: `[]` rewards_vault_transfer_authority_ai
+    /// 7: `[]` marinade_state_ai
+    /// 8: `[]` lucra_sol_oracle_ai
+    /// 9: `[]` token_program_ai
+    RedeemRewardTokensForMsol { reward_tokens: u64 },
+
+    /// DAO instruction. Whitelists another liquid staking token as collateral, valued at `oracle_ai`'s dollar
+    /// price less `haircut_bps`. The type is added disabled and its index, from 1 since msol is 0, is logged.
+    /// The vault has to be an empty vault of the mint owned by the type's collateral vault authority, with no
+    /// delegate or close authority. The first type creates the registry. At most 16 types can be added.
+    /// 
+    /// Accounts expected by this instruction (8)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` collateral_registry_ai
+    /// 2: `[]` mint_ai
+    /// 3: `[]` oracle_ai
+    /// 4: `[]` vault_ai
+    /// 5: `[signer]` dao_authority_ai
+    /// 6: `[writable, signer]` payer_ai
+    /// 7: `[]` system_program_ai
+    AddCollateralType { haircut_bps: Bps },
+
+    /// DAO instruction. Sets the oracle and haircut of a registered collateral type and turns loans against it
+    /// on or off. Its mint and vault can't change.
+    /// 
+    /// Accounts expected by this instruction (4)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` collateral_registry_ai
+    /// 2: `[]` oracle_ai
+    /// 3: `[signer]` dao_authority_ai
+    SetCollateralType { collateral_type: u8, haircut_bps: Bps, enabled: bool },
+
+    /// DAO instruction. Moves msol from the rewards vault to the arb coffer, only out of what's left once every
+    /// reward that hasn't been swept is covered. Those rewards follow the fixed accounts, newest first.
+    /// 
+    /// Accounts expected by this instruction (7 + unswept rewards)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[]` staking_state_ai
+    /// 2: `[signer]` dao_authority_ai
+    /// 3: `[writable]` rewards_vault_ai
+    /// 4: `[writable]` arb_coffer_ai
+    /// 5: `[]` rewards_vault_transfer_authority_ai
+    /// 6: `[]` token_program_ai
+    /// 7..: `[]` reward_ai, each unswept reward from the staking state's current reward back
+    RebalanceRewardsVault { lamports: u64 },
+
+    /// DAO instruction. Puts destination_ai on the transfer allowlist, TransferFunds can send to it once
+    /// TRANSFER_DESTINATION_DELAY has passed. The first call creates the allowlist.
+    /// 
+    /// Accounts expected by this instruction (6)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` transfer_allowlist_ai
+    /// 2: `[]` destination_ai
+    /// 3: `[signer]` dao_authority_ai
+    /// 4: `[writable, signer]` payer_ai
+    /// 5: `[]` system_program_ai
+    AddTransferDestination {},
+
+    /// DAO instruction. Takes destination_ai off the transfer allowlist, also while it's still waiting out its
+    /// delay.
+    /// 
+    /// Accounts expected by this instruction (4)
+    /// 
+    /// 0: `[]` system_state_ai
+    /// 1: `[writable]` transfer_allowlist_ai
+    /// 2: `[]` destination_ai
+    /// 3: `[signer]` dao_authority_ai
+    RemoveTransferDestination {},
+}
+
+/// Tag of every variant in the order they're declared. The tag is the first byte of the instruction data and
+/// is never reused or moved, so the enum can be reordered as long as each variant keeps its tag. Tags start at
+/// 0x80: the legacy layout starts with the bincode u32 variant index, whose first byte is below that, so either
+/// layout is told apart by its first byte.
+pub const INSTRUCTION_TAGS: [u8; 70] = [
+    0x80, // Initialize
+    0x81, // UpdateState
+    0x82, // CreateMataLoan
+    0x83, // CloseOutMataLoan
+    0x84, // CreateStakingAccount
+    0x85, // CreateStakeBalance
+    0x86, // DepositStake
+    0x87, // Stake
+    0x88, // StartUnstake
+    0x89, // EndUnstake
+    0x8a, // WithdrawStake
+    0x8b, // ClaimReward
+    0x8c, // DropReward
+    0x8d, // TransferFunds
+    0x8e, // CreatePriceHistory
+    0x8f, // UpdatePriceHistory
+    0x90, // RedeemRewardTokens
+    0x91, // AddCollateral
+    0x92, // DeterminePenalty
+    0x93, // HarvestPenalty
+    0x94, // SellFundsForArb
+    0x95, // BuyBurnForArb
+    0x96, // CleanUpArb
+    0x97, // MintFundsForArb
+    0x98, // CreateRevenueLedger
+    0x99, // GlobalSettlement
+    0x9a, // ReclaimExcess
+    0x9b, // RollArbWindow
+    0x9c, // SetRewardMetadata
+    0x9d, // FreezeStakingAccount
+    0x9e, // UnfreezeStakingAccount
+    0x9f, // SetStakingRecoveryKey
+    0xa0, // SetLpCollateral
+    0xa1, // SweepExpiredReward
+    0xa3, // SetMinDropAmount
+    0xa4, // SetRewardRedemptionDeadline
+    0xa5, // CloseRewardProgram
+    0xa6, // MigrateAccountLayout
+    0xa7, // CorrectPriceHistory
+    0xa8, // RecomputePenalty
+    0xa9, // ComputeTvl
+    0xaa, // RolloverLoan
+    0xab, // RepayLoanPartial
+    0xac, // BorrowMore
+    0xad, // SetLiquidationParams
+    0xae, // LiquidateLoan
+    0xaf, // TransferLoanOwnership
+    0xb0, // CloseStakeBalance
+    0xb1, // CloseStakingAccount
+    0xb2, // CancelUnstake
+    0xb3, // ProposeAuthority
+    0xb4, // AcceptAuthority
+    0xb5, // SetPause
+    0xb6, // WithdrawCollateral
+    0xb7, // MigrateStakeBalance
+    0xb8, // QuoteMataLoan
+    0xb9, // CreatePenaltyHistory
+    0xba, // CreateEventQueue
+    0xbb, // ClaimRewardV2
+    0xbc, // UpdateHarvestParams
+    0xbd, // CreateSolVault
+    0xbe, // CreatePriceHistoryPage
+    0xbf, // RegisterKeeper
+    0xc0, // RemoveKeeper
+    0xc1, // RedeemRewardTokensForMsol
+    0xc2, // AddCollateralType
+    0xc3, // SetCollateralType
+    0xc4, // RebalanceRewardsVault
+    0xc5, // AddTransferDestination
+    0xc6, // RemoveTransferDestination
+];
+
+impl Instruction {
+    /// The tagged layout the builders emit: the variant's tag followed by its bincode fields
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = serialize(self).unwrap();
+        let variant_index = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        data.splice(..4, [INSTRUCTION_TAGS[variant_index as usize]]);
+        data
+    }
+
+    /// Reads the tagged layout, or the legacy bincode one clients built before the tags existed
+    pub fn try_from_slice_versioned(data: &[u8]) -> Result<Self, ProgramError> {
+        let (&first, payload) = data.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+        if first < INSTRUCTION_TAGS[0] {
+            return deserialize(data).map_err(|_| ProgramError::InvalidInstructionData);
+        }
+
+        let variant_index = INSTRUCTION_TAGS
+            .iter()
+            .position(|&tag| tag == first)
+            .ok_or(ProgramError::InvalidInstructionData)? as u32;
+        // The payload is the variant's bincode fields, read behind the variant index they'd follow
+        let variant_index = variant_index.to_le_bytes();
+        deserialize_from((&variant_index[..]).chain(payload)).map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn initialize(
+    marinade_state: &Pubkey,
+    state: &Pubkey,
+    staking_state: &Pubkey,
+    arb_state: &Pubkey,
+    mata_mint: &Pubkey,
+    lucra_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    staked_lucra_mint: &Pubkey,
+    msol_vault: &Pubkey,
+    arb_coffer: &Pubkey,
+    rewards_vault: &Pubkey,
+    arb_fund: &Pubkey,
+    wsol_holding_vault: &Pubkey,
+    mata_holding_vault: &Pubkey,
+    lucra_holding_vault: &Pubkey,
+    min_deposit: Lamports,
+    collateral_requirement: CollateralRatio,
+    epoch: i64,
+    loans_enabled: bool,
+    staking_enabled: bool,
+    arbitrage_enabled: bool,
+    peg_check_enabled: bool,
+    max_amount_of_lucra_to_mint: u64,
+    daily_arb_limit: u64,
+    maximum_outstanding_mata: Mata,
+    lcp: Percent,
+    min_loan_amount_mata: Mata,
+    msol_haircut_bps: Bps,
+    peg_lower_bound: Bps,
+    peg_upper_bound: Bps,
+    penalty_band_width: Bps,
+    penalty_multiplier_per_band: u32,
+    max_oracle_staleness_slots: u64,
+    max_oracle_deviation_bps: Bps,
+    guardian: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new_readonly(CREATOR_AUTHORITY, true),
+        AccountMeta::new_readonly(*mata_mint, false),
+        AccountMeta::new_readonly(*lucra_mint, false),
+        AccountMeta::new_readonly(*reward_mint, false),
+        AccountMeta::new_readonly(*staked_lucra_mint, false),
+        AccountMeta::new(*state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new_readonly(*msol_vault, false),
+        AccountMeta::new_readonly(*arb_coffer, false),
+        AccountMeta::new_readonly(*rewards_vault, false),
+        AccountMeta::new(*staking_state, false),
+        AccountMeta::new_readonly(*arb_fund, false),
+        AccountMeta::new_readonly(*wsol_holding_vault, false),
+        AccountMeta::new_readonly(*mata_holding_vault, false),
+        AccountMeta::new_readonly(*lucra_holding_vault, false),
+    ];
+    let data = Instruction::Initialize { 
+        min_deposit,
+        collateral_requirement,
+        epoch,
+        loans_enabled,
+        staking_enabled,
+        arbitrage_enabled,
+        peg_check_enabled,
+        max_amount_of_lucra_to_mint,
+        daily_arb_limit,
+        maximum_outstanding_mata,
+        lcp,
+        min_loan_amount_mata,
+        msol_haircut_bps,
+        peg_lower_bound,
+        peg_upper_bound,
+        penalty_band_width,
+        penalty_multiplier_per_band,
+        max_oracle_staleness_slots,
+        max_oracle_deviation_bps,
+        guardian: *guardian,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// Sets every field, `update_state_fields` only changes the ones that are set
+#[allow(clippy::too_many_arguments)]
+pub fn update_state(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    min_deposit: Lamports,
+    collateral_requirement: CollateralRatio,
+    loans_enabled: bool,
+    staking_enabled: bool,
+    arbitrage_enabled: bool,
+    peg_check_enabled: bool,
+    max_amount_of_lucra_to_mint: u64,
+    daily_arb_limit: u64,
+    maximum_outstanding_mata: Mata,
+    minimum_harvest_amount: Lamports,
+    reward_fee: RewardFee,
+    lcp: Percent,
+    min_loan_amount_mata: Mata,
+    msol_haircut_bps: Bps,
+    msol_sol_oracle: Pubkey,
+    require_oracle_v2: bool,
+    reward_expiry_epochs: u64,
+    max_instant_unstake: Lamports,
+    peg_lower_bound: Bps,
+    peg_upper_bound: Bps,
+    penalty_band_width: Bps,
+    penalty_multiplier_per_band: u32,
+    arb_trigger_deviation_bps: Bps,
+    guardian: Pubkey,
+    max_oracle_staleness_slots: u64,
+    max_oracle_deviation_bps: Bps,
+    msol_sol_pool: Pubkey,
+    arb_dust_threshold: u64,
+    pyth_sol_usd_feed: Pubkey,
+    annual_interest_bps: Bps,
+    restricted_cranking: bool,
+    sol_mata_whirlpool: Pubkey,
+    native_loans_enabled: bool,
+) -> SolInstruction {
+    update_state_fields(system_state, arb_state, &UpdateStateParams {
+        min_deposit: Some(min_deposit),
+        collateral_requirement: Some(collateral_requirement),
+        loans_enabled: Some(loans_enabled),
+        staking_enabled: Some(staking_enabled),
+        arbitrage_enabled: Some(arbitrage_enabled),
+        peg_check_enabled: Some(peg_check_enabled),
+        max_amount_of_lucra_to_mint: Some(max_amount_of_lucra_to_mint),
+        daily_arb_limit: Some(daily_arb_limit),
+        maximum_outstanding_mata: Some(maximum_outstanding_mata),
+        minimum_harvest_amount: Some(minimum_harvest_amount),
+        reward_fee: Some(reward_fee),
+        lcp: Some(lcp),
+        min_loan_amount_mata: Some(min_loan_amount_mata),
+        msol_haircut_bps: Some(msol_haircut_bps),
+        msol_sol_oracle: Some(msol_sol_oracle),
+        require_oracle_v2: Some(require_oracle_v2),
+        reward_expiry_epochs: Some(reward_expiry_epochs),
+        max_instant_unstake: Some(max_instant_unstake),
+        peg_lower_bound: Some(peg_lower_bound),
+        peg_upper_bound: Some(peg_upper_bound),
+        penalty_band_width: Some(penalty_band_width),
+        penalty_multiplier_per_band: Some(penalty_multiplier_per_band),
+        arb_trigger_deviation_bps: Some(arb_trigger_deviation_bps),
+        guardian: Some(guardian),
+        max_oracle_staleness_slots: Some(max_oracle_staleness_slots),
+        max_oracle_deviation_bps: Some(max_oracle_deviation_bps),
+        msol_sol_pool: Some(msol_sol_pool),
+        arb_dust_threshold: Some(arb_dust_threshold),
+        pyth_sol_usd_feed: Some(pyth_sol_usd_feed),
+        annual_interest_bps: Some(annual_interest_bps),
+        restricted_cranking: Some(restricted_cranking),
+        sol_mata_whirlpool: Some(sol_mata_whirlpool),
+        native_loans_enabled: Some(native_loans_enabled),
+    })
+}
+
+/// Changes only the fields set in `state_params`, see `update_state` to set every field at once
+pub fn update_state_fields(system_state: &Pubkey, arb_state: &Pubkey, state_params: &UpdateStateParams) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+    ];
+    let data = Instruction::UpdateState {
+        min_deposit: state_params.min_deposit,
+        collateral_requirement: state_params.collateral_requirement,
+        loans_enabled: state_params.loans_enabled,
+        staking_enabled: state_params.staking_enabled,
+        arbitrage_enabled: state_params.arbitrage_enabled,
+        peg_check_enabled: state_params.peg_check_enabled,
+        max_amount_of_lucra_to_mint: state_params.max_amount_of_lucra_to_mint,
+        daily_arb_limit: state_params.daily_arb_limit,
+        maximum_outstanding_mata: state_params.maximum_outstanding_mata,
+        minimum_harvest_amount: state_params.minimum_harvest_amount,
+        reward_fee: state_params.reward_fee,
+        lcp: state_params.lcp,
+        min_loan_amount_mata: state_params.min_loan_amount_mata,
+        msol_haircut_bps: state_params.msol_haircut_bps,
+        msol_sol_oracle: state_params.msol_sol_oracle,
+        require_oracle_v2: state_params.require_oracle_v2,
+        reward_expiry_epochs: state_params.reward_expiry_epochs,
+        max_instant_unstake: state_params.max_instant_unstake,
+        peg_lower_bound: state_params.peg_lower_bound,
+        peg_upper_bound: state_params.peg_upper_bound,
+        penalty_band_width: state_params.penalty_band_width,
+        penalty_multiplier_per_band: state_params.penalty_multiplier_per_band,
+        arb_trigger_deviation_bps: state_params.arb_trigger_deviation_bps,
+        guardian: state_params.guardian,
+        max_oracle_staleness_slots: state_params.max_oracle_staleness_slots,
+        max_oracle_deviation_bps: state_params.max_oracle_deviation_bps,
+        msol_sol_pool: state_params.msol_sol_pool,
+        arb_dust_threshold: state_params.arb_dust_threshold,
+        pyth_sol_usd_feed: state_params.pyth_sol_usd_feed,
+        annual_interest_bps: state_params.annual_interest_bps,
+        restricted_cranking: state_params.restricted_cranking,
+        sol_mata_whirlpool: state_params.sol_mata_whirlpool,
+        native_loans_enabled: state_params.native_loans_enabled,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `loan` is `find_loan_address(transfer_from, index)` with the next index of the user's loan registry, 1 on
+/// their first loan
+#[allow(clippy::too_many_arguments)]
+pub fn create_mata_loan_derived(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    transfer_from: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    reserve_address: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*marinade_state, false),
+
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(Pda::mata_mint_authority(system_state).0, false),
+        AccountMeta::new(*transfer_from, true),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(*user_msol_account, false),
+
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+    
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_address, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new_readonly(find_liq_pool_msol_leg_authority(marinade_state).0, false),
+        AccountMeta::new(*reserve_address, false),
+        AccountMeta::new_readonly(find_msol_mint_authority(marinade_state).0, false),
+    
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new(Pda::loan_registry(transfer_from).0, false),
+    ];
+    let data = Instruction::CreateMataLoan { lamports };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `loan` is `find_loan_address(transfer_from, index)` with the next index of the user's loan registry, 1 on
+/// their first loan
+#[allow(clippy::too_many_arguments)]
+pub fn create_mata_loan_with_locked_stake_derived(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    transfer_from: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    staking_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    reserve_address: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*marinade_state, false),
+
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(Pda::mata_mint_authority(system_state).0, false),
+        AccountMeta::new(*transfer_from, true),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*staking_account, false),
+
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+    
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_address, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new_readonly(find_liq_pool_msol_leg_authority(marinade_state).0, false),
+        AccountMeta::new(*reserve_address, false),
+        AccountMeta::new_readonly(find_msol_mint_authority(marinade_state).0, false),
+    
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new(Pda::loan_registry(transfer_from).0, false),
+    ];
+    let data = Instruction::CreateMataLoan { lamports };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn close_mata_loan(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    user_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    mata_mint: &Pubkey,
+    user_mata_account: &Pubkey,
+    msol_vault: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    treasury_msol_account: &Pubkey,
+    unstake_msol: bool,
+) -> SolInstruction {
+    let msol_vault_authority = Pda::msol_vault_authority(system_state).0;
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*marinade_state, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(msol_vault_authority, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_address, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new(*treasury_msol_account, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+    ];
+    let data = Instruction::CloseOutMataLoan {
+        unstake_msol,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn close_mata_loan_with_locked_stake(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    user_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    mata_mint: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_staking_account: &Pubkey,
+    msol_vault: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    treasury_msol_account: &Pubkey,
+    unstake_msol: bool,
+) -> SolInstruction {
+    let msol_vault_authority = Pda::msol_vault_authority(system_state).0;
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*marinade_state, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(msol_vault_authority, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new(*user_staking_account, false),
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_address, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new(*treasury_msol_account, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+    ];
+    let data = Instruction::CloseOutMataLoan {
+        unstake_msol
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `loan` is `find_loan_address(transfer_from, index)` with the next index of the user's loan registry, 1 on
+/// their first loan
+#[allow(clippy::too_many_arguments)]
+pub fn create_lp_backed_mata_loan(
+    system_state: &Pubkey,
+    loan: &Pubkey,
+    lp_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    transfer_from: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_lp_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    pool_sol_vault: &Pubkey,
+    pool_mata_vault: &Pubkey,
+    lp_mint: &Pubkey,
+    lp_amount: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(SOL_MATA_ORCA_AMM, false),
+
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*lp_vault, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(Pda::mata_mint_authority(system_state).0, false),
+        AccountMeta::new(*transfer_from, true),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(*user_lp_account, false),
+
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+
+        AccountMeta::new_readonly(*pool_sol_vault, false),
+        AccountMeta::new_readonly(*pool_mata_vault, false),
+        AccountMeta::new_readonly(*lp_mint, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new(Pda::loan_registry(transfer_from).0, false),
+    ];
+    let data = Instruction::CreateMataLoan { lamports: lp_amount };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn close_lp_backed_mata_loan(
+    system_state: &Pubkey,
+    loan: &Pubkey,
+    user_account: &Pubkey,
+    user_lp_account: &Pubkey,
+    mata_mint: &Pubkey,
+    user_mata_account: &Pubkey,
+    lp_vault: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_lp_account, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(Pda::lp_vault_authority(system_state).0, false),
+        AccountMeta::new(*lp_vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::CloseOutMataLoan {
+        unstake_msol: false,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `loan` is `find_loan_address(user_account, index)` with the next index of the user's loan registry, 1 on
+/// their first loan
+#[allow(clippy::too_many_arguments)]
+pub fn create_native_sol_mata_loan(
+    system_state: &Pubkey,
+    loan: &Pubkey,
+    mata_mint: &Pubkey,
+    user_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(Pda::sol_vault(system_state).0, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(Pda::mata_mint_authority(system_state).0, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_mata_account, false),
+
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(Pda::loan_registry(user_account).0, false),
+    ];
+    let data = Instruction::CreateMataLoan { lamports };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn close_native_sol_mata_loan(
+    system_state: &Pubkey,
+    loan: &Pubkey,
+    user_account: &Pubkey,
+    mata_mint: &Pubkey,
+    user_mata_account: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(Pda::sol_vault(system_state).0, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::CloseOutMataLoan {
+        unstake_msol: false,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_staking_account(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    staking_account: &Pubkey,
+    owner: &Pubkey,
+    recovery_key: Option<&Pubkey>,
+) -> SolInstruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new_readonly(*staking_state, false),
+        AccountMeta::new(*staking_account, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+    if let Some(recovery_key) = recovery_key {
+        accounts.push(AccountMeta::new_readonly(*recovery_key, false));
+    }
+    let data = Instruction::CreateStakingAccount { };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_stake_balance(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    stake_balance: &Pubkey,
+    owner: &Pubkey,
+    deposit_vault: &Pubkey,
+    stake_vault: &Pubkey,
+    pending_vault: &Pubkey,
+    staking_account: &Pubkey,
+    nonce: u8,
+    staking_timeframe: StakingTimeframe,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new_readonly(*staking_state, false),
+        AccountMeta::new(*stake_balance, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(*deposit_vault, false),
+        AccountMeta::new_readonly(*stake_vault, false),
+        AccountMeta::new_readonly(*pending_vault, false),
+        AccountMeta::new_readonly(*staking_account, false),
+    ];
+    let data = Instruction::CreateStakeBalance { nonce, staking_timeframe: staking_timeframe as u8 };
+    
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn deposit_stake(
+    system_state: &Pubkey,
+    stake_balance: &Pubkey,
+    from_account: &Pubkey,
+    to_account: &Pubkey,
+    owner: &Pubkey,
+    lucra: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new_readonly(*stake_balance, false),
+        AccountMeta::new(*from_account, false),
+        AccountMeta::new(*to_account, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::DepositStake { lucra };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn stake_derived(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    staking_account: &Pubkey,
+    stake_balance: &Pubkey,
+    deposit_vault: &Pubkey,
+    stake_vault: &Pubkey,
+    owner: &Pubkey,
+    staked_lucra_account: &Pubkey,
+    staked_lucra_mint: &Pubkey,
+    lucra: u64,
+) -> SolInstruction {
+    let staked_lucra_mint_authority = Pda::stake_mint_authority(staking_state).0;
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*staking_state, false),
+        AccountMeta::new(*staking_account, false),
+        AccountMeta::new(*stake_balance, false),
+        AccountMeta::new(*deposit_vault, false),
+        AccountMeta::new(*stake_vault, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(Pda::stake_balance_authority(owner, system_state).0, false),
+        AccountMeta::new(*staked_lucra_mint, false),
+        AccountMeta::new(*staked_lucra_account, false),
+        AccountMeta::new_readonly(staked_lucra_mint_authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::Stake { lucra };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `lucra` of `UNSTAKE_ALL` unstakes as much of the stake balance as the loans pledged against the staking
+/// account allow
+#[allow(clippy::too_many_arguments)]
+pub fn start_unstake_derived(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    staking_account: &Pubkey,
+    stake_balance: &Pubkey,
+    stake_vault: &Pubkey,
+    pending_vault: &Pubkey,
+    pending_withdrawal: &Pubkey,
+    owner: &Pubkey,
+    staked_lucra_account: &Pubkey,
+    staked_lucra_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    lucra: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new_readonly(*staking_state, false),
+        AccountMeta::new(*staking_account, false),
+        AccountMeta::new(*stake_balance, false),
+        AccountMeta::new(*staked_lucra_mint, false),
+        AccountMeta::new(*staked_lucra_account, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*stake_vault, false),
+        AccountMeta::new(*pending_vault, false),
+        AccountMeta::new_readonly(Pda::stake_balance_authority(owner, system_state).0, false),
+        AccountMeta::new(*pending_withdrawal, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::StartUnstake { lucra };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn end_unstake_derived(
+    system_state: &Pubkey,
+    pending_withdrawal: &Pubkey,
+    stake_balance: &Pubkey,
+    pending_vault: &Pubkey,
+    deposit_vault: &Pubkey,
+    owner: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*pending_withdrawal, false),
+        AccountMeta::new_readonly(*stake_balance, false),
+        AccountMeta::new(*pending_vault, false),
+        AccountMeta::new(*deposit_vault, false),
+        AccountMeta::new(*owner, true),
+        AccountMeta::new_readonly(Pda::stake_balance_authority(owner, system_state).0, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::EndUnstake { };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_stake_derived(
+    system_state: &Pubkey,
+    staking_account: &Pubkey,
+    stake_balance: &Pubkey,
+    to_account: &Pubkey,
+    deposit_vault: &Pubkey,
+    stake_vault: &Pubkey,
+    pending_vault: &Pubkey,
+    owner: &Pubkey,
+    lucra: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new_readonly(*staking_account, false),
+        AccountMeta::new(*stake_balance, false),
+        AccountMeta::new(*deposit_vault, false),
+        AccountMeta::new_readonly(*stake_vault, false),
+        AccountMeta::new_readonly(*pending_vault, false),
+        AccountMeta::new(*to_account, false),
+        AccountMeta::new(*owner, true),
+        AccountMeta::new_readonly(Pda::stake_balance_authority(owner, system_state).0, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::WithdrawStake { lucra };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn claim_reward(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    staking_account: &Pubkey,
+    stake_balance: &Pubkey,
+    reward: &Pubkey,
+    staked_lucra_account: &Pubkey,
+    lucra_vault: &Pubkey,
+    lucra_account: &Pubkey,
+    rewards_vault: &Pubkey,
+    msol_account: &Pubkey,
+    lucra_mint: &Pubkey,
+) -> SolInstruction {
+    let rewards_vault_authority = Pda::rewards_vault_authority(system_state).0;
+    let lucra_mint_authority = Pda::lucra_mint_authority(system_state).0;
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*staking_state, false),
+        AccountMeta::new_readonly(*staking_account, false),
+        AccountMeta::new(*stake_balance, false),
+        AccountMeta::new(*reward, false),
+        AccountMeta::new(*staked_lucra_account, false),
+        AccountMeta::new(*lucra_vault, false),
+        AccountMeta::new(*lucra_account, false),
+        AccountMeta::new(*rewards_vault, false),
+        AccountMeta::new(*msol_account, false),
+        AccountMeta::new_readonly(rewards_vault_authority, false),
+        AccountMeta::new(*lucra_mint, false),
+        AccountMeta::new_readonly(lucra_mint_authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::ClaimReward { };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn drop_reward_derived(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    marinade_state: &Pubkey,
+    reward: &Pubkey,
+    staked_lucra_mint: &Pubkey,
+    msol_vault: &Pubkey,
+    rewards_vault: &Pubkey,
+    arb_coffer: &Pubkey,
+    user_reward_account: &Pubkey,
+    reward_mint: &Pubkey,
+) -> SolInstruction {
+    let msol_vault_transfer_authority = Pda::msol_vault_authority(system_state).0;
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*staking_state, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new(*reward, false),
+        AccountMeta::new_readonly(*staked_lucra_mint, false),
+        AccountMeta::new_readonly(*msol_vault, false),
+        AccountMeta::new(*rewards_vault, false),
+        AccountMeta::new(*arb_coffer, false),
+        AccountMeta::new_readonly(msol_vault_transfer_authority, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::DropReward { };
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_funds_derived(
+    system_state: &Pubkey,
+    from_account: &Pubkey,
+    to_account: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+        AccountMeta::new(*from_account, false),
+        AccountMeta::new(*to_account, false),
+        AccountMeta::new_readonly(Pda::msol_vault_authority(system_state).0, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(Pda::transfer_allowlist(system_state).0, false),
+    ];
+    let data = Instruction::TransferFunds {
+        lamports,
+        native: false,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// Sends lamports from the sol vault, the token accounts of the layout are passed but unused
+pub fn transfer_native_funds(
+    system_state: &Pubkey,
+    to_account: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+        AccountMeta::new(Pda::sol_vault(system_state).0, false),
+        AccountMeta::new(*to_account, false),
+        AccountMeta::new_readonly(Pda::msol_vault_authority(system_state).0, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(Pda::transfer_allowlist(system_state).0, false),
+    ];
+    let data = Instruction::TransferFunds {
+        lamports,
+        native: true,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn add_transfer_destination(system_state: &Pubkey, destination: &Pubkey, payer: &Pubkey) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(Pda::transfer_allowlist(system_state).0, false),
+        AccountMeta::new_readonly(*destination, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+    let data = Instruction::AddTransferDestination {};
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn remove_transfer_destination(system_state: &Pubkey, destination: &Pubkey) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(Pda::transfer_allowlist(system_state).0, false),
+        AccountMeta::new_readonly(*destination, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+    ];
+    let data = Instruction::RemoveTransferDestination {};
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_price_history(
+    price_history: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(CREATOR_AUTHORITY, true),
+        AccountMeta::new(*price_history, false),
+    ];
+    let data = Instruction::CreatePriceHistory {};
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_price_history(
+    system_state: &Pubkey,
+    price_history: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    user_reward_account: &Pubkey,
+    reward_mint: &Pubkey,
+    arb_state: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*price_history, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*arb_state, false),
+    ];
+    let data = Instruction::UpdatePriceHistory {};
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `update_price_history` for a paged price history, `active_page` is the header's
+#[allow(clippy::too_many_arguments)]
+pub fn update_price_history_with_pages(
+    system_state: &Pubkey,
+    price_history: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    user_reward_account: &Pubkey,
+    reward_mint: &Pubkey,
+    arb_state: &Pubkey,
+    active_page: u64,
+) -> SolInstruction {
+    let mut instruction = update_price_history(
+        system_state,
+        price_history,
+        sol_usdc_oracle,
+        sol_usdt_oracle,
+        lucra_sol_oracle,
+        sol_mata_oracle,
+        user_reward_account,
+        reward_mint,
+        arb_state,
+    );
+    instruction.accounts.push(AccountMeta::new(Pda::price_history_header(price_history).0, false));
+    instruction.accounts.push(AccountMeta::new(Pda::price_history_page(price_history, active_page).0, false));
+    instruction.accounts.push(AccountMeta::new(Pda::price_history_page(price_history, active_page + 1).0, false));
+    instruction
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn redeem_reward_tokens(
+    system_state: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_lucra_reward_account: &Pubkey,
+    user_authority: &Pubkey,
+    reward_mint: &Pubkey,
+    lucra_mint: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    reward_tokens: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*user_lucra_reward_account, false),
+        AccountMeta::new_readonly(*user_authority, true),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new(*lucra_mint, false),
+        AccountMeta::new_readonly(Pda::lucra_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::RedeemRewardTokens { reward_tokens };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn redeem_reward_tokens_for_msol(
+    system_state: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    user_authority: &Pubkey,
+    reward_mint: &Pubkey,
     rewards_vault: &Pubkey,
+    marinade_state: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    reward_tokens: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new_readonly(*user_authority, true),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new(*rewards_vault, false),
+        AccountMeta::new_readonly(Pda::rewards_vault_authority(system_state).0, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::RedeemRewardTokensForMsol { reward_tokens };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn add_collateral_derived(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    owner: &Pubkey,
+    user_msol_account: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    reserve_address: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*marinade_state, false),
+
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*user_msol_account, false),
+    
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_address, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new_readonly(find_liq_pool_msol_leg_authority(marinade_state).0, false),
+        AccountMeta::new(*reserve_address, false),
+        AccountMeta::new_readonly(find_msol_mint_authority(marinade_state).0, false),
+    
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+    ];
+    let data = Instruction::AddCollateral { lamports };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn add_collateral_with_locked_stake_derived(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    owner: &Pubkey,
+    user_msol_account: &Pubkey,
+    staking_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    reserve_address: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*marinade_state, false),
+
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*staking_account, false),
+
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+    
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_address, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new_readonly(find_liq_pool_msol_leg_authority(marinade_state).0, false),
+        AccountMeta::new(*reserve_address, false),
+        AccountMeta::new_readonly(find_msol_mint_authority(marinade_state).0, false),
+    
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+    ];
+    let data = Instruction::AddCollateral { lamports };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn add_collateral_msol(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    owner: &Pubkey,
+    user_msol_account: &Pubkey,
+    msol_amount: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*user_msol_account, false),
+
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::AddCollateral { lamports: msol_amount };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn add_collateral_msol_with_locked_stake(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    owner: &Pubkey,
+    user_msol_account: &Pubkey,
+    staking_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    msol_amount: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*staking_account, false),
+
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::AddCollateral { lamports: msol_amount };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn add_native_sol_collateral(
+    system_state: &Pubkey,
+    loan: &Pubkey,
+    owner: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(Pda::sol_vault(system_state).0, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*owner, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+    let data = Instruction::AddCollateral { lamports };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn determine_penalty(
+    system_state: &Pubkey,
+    loan: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    price_history: &Pubkey,
+    user_reward_account: &Pubkey,
+    reward_mint: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*loan, false),
+
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*price_history, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::DeterminePenalty { };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `determine_penalty` for a loan with a penalty history, see `create_penalty_history`
+#[allow(clippy::too_many_arguments)]
+pub fn determine_penalty_with_history(
+    system_state: &Pubkey,
+    loan: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    price_history: &Pubkey,
+    user_reward_account: &Pubkey,
+    reward_mint: &Pubkey,
+) -> SolInstruction {
+    let mut instruction = determine_penalty(
+        system_state,
+        loan,
+        sol_usdc_oracle,
+        sol_usdt_oracle,
+        sol_mata_oracle,
+        price_history,
+        user_reward_account,
+        reward_mint,
+    );
+    instruction.accounts.push(AccountMeta::new(Pda::penalty_history(loan).0, false));
+    instruction
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn harvest_penalty_with_orca(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    msol_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    loan: &Pubkey,
+    user_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    arb_coffer: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_pda: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    treasury_msol_account: &Pubkey,
+    sm_amm: &Pubkey,
+    sm_amm_authority: &Pubkey,
+    sm_pool_base_vault: &Pubkey,
+    sm_pool_quote_vault: &Pubkey,
+    sm_pool_mint: &Pubkey,
+    sm_pool_fees: &Pubkey,
+    min_mata_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*marinade_state, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new_readonly(Pda::msol_vault_authority(system_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new(*arb_coffer, false),
+
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(*user_msol_account, false),
+        
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_pda, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new(*treasury_msol_account, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        
+        AccountMeta::new(*sm_amm, false),
+        AccountMeta::new_readonly(*sm_amm_authority, false),
+        AccountMeta::new(*sm_pool_base_vault, false),
+        AccountMeta::new(*sm_pool_quote_vault, false),
+        AccountMeta::new(*sm_pool_mint, false),
+        AccountMeta::new(*sm_pool_fees, false),
+        
+        AccountMeta::new_readonly(orca_swap::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    let data = Instruction::HarvestPenalty { amm_type: AmmTypes::Orca as u8, min_mata_out };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn harvest_native_sol_penalty_with_orca(
+    system_state: &Pubkey,
+    mata_mint: &Pubkey,
+    loan: &Pubkey,
+    user_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sm_amm: &Pubkey,
+    sm_amm_authority: &Pubkey,
+    sm_pool_base_vault: &Pubkey,
+    sm_pool_quote_vault: &Pubkey,
+    sm_pool_mint: &Pubkey,
+    sm_pool_fees: &Pubkey,
+    min_mata_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(Pda::sol_vault(system_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new(*user_mata_account, false),
+
+        AccountMeta::new(*sm_amm, false),
+        AccountMeta::new_readonly(*sm_amm_authority, false),
+        AccountMeta::new(*sm_pool_base_vault, false),
+        AccountMeta::new(*sm_pool_quote_vault, false),
+        AccountMeta::new(*sm_pool_mint, false),
+        AccountMeta::new(*sm_pool_fees, false),
+
+        AccountMeta::new_readonly(orca_swap::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::HarvestPenalty { amm_type: AmmTypes::Orca as u8, min_mata_out };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn harvest_penalty_with_raydium(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    msol_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    loan: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    arb_coffer: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_pda: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    treasury_msol_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    user_account: &Pubkey,
+    user_lucra_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    pool_lucra_account: &Pubkey,
+    pool_wsol_account: &Pubkey,
+    amm_program: &Pubkey,
+    amm_authority: &Pubkey,
+    amm_open_orders: &Pubkey,
+    amm_target: &Pubkey,
+    serum_lucra_sol_market: &Pubkey,
+    serum_bids: &Pubkey,
+    serum_asks: &Pubkey,
+    serum_event_queue: &Pubkey,
+    serum_base_vault: &Pubkey,
+    serum_quote_vault: &Pubkey,
+    serum_vault_signer: &Pubkey,
+    min_mata_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*marinade_state, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new_readonly(Pda::msol_vault_authority(system_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new(*arb_coffer, false),
+
+        AccountMeta::new(*user_msol_account, false),
+
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_pda, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new(*treasury_msol_account, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_lucra_account, false),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new_readonly(raydium_v4::id(), false),
+        AccountMeta::new(*pool_lucra_account, false),
+        AccountMeta::new(*pool_wsol_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*amm_program, false),
+        AccountMeta::new_readonly(*amm_authority, false),
+        AccountMeta::new(*amm_open_orders, false),
+        AccountMeta::new(*amm_target, false),
+        AccountMeta::new(*serum_lucra_sol_market, false),
+        AccountMeta::new_readonly(serum_v3::id(), false),
+        AccountMeta::new(*serum_bids, false),
+        AccountMeta::new(*serum_asks, false),
+        AccountMeta::new(*serum_event_queue, false),
+        AccountMeta::new(*serum_base_vault, false),
+        AccountMeta::new(*serum_quote_vault, false),
+        AccountMeta::new_readonly(*serum_vault_signer, false),
+    ];
+
+    let data = Instruction::HarvestPenalty { amm_type: AmmTypes::Raydium as u8, min_mata_out };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `tick_arrays` are the ones a swap of SOL into the whirlpool walks through from its current tick
+#[allow(clippy::too_many_arguments)]
+pub fn harvest_penalty_with_whirlpool(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    msol_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    loan: &Pubkey,
+    user_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    arb_coffer: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_pda: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    treasury_msol_account: &Pubkey,
+    whirlpool: &Pubkey,
+    whirlpool_vault_a: &Pubkey,
+    whirlpool_vault_b: &Pubkey,
+    tick_arrays: &[Pubkey; 3],
+    min_mata_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(*marinade_state, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new_readonly(Pda::msol_vault_authority(system_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new(*arb_coffer, false),
+
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(*user_msol_account, false),
+
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_pda, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new(*treasury_msol_account, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+
+        AccountMeta::new(*whirlpool, false),
+        AccountMeta::new(*whirlpool_vault_a, false),
+        AccountMeta::new(*whirlpool_vault_b, false),
+        AccountMeta::new(tick_arrays[0], false),
+        AccountMeta::new(tick_arrays[1], false),
+        AccountMeta::new(tick_arrays[2], false),
+        AccountMeta::new_readonly(find_whirlpool_oracle(whirlpool), false),
+
+        AccountMeta::new_readonly(orca_whirlpool::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::HarvestPenalty { amm_type: AmmTypes::Whirlpool as u8, min_mata_out };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// The keeper pays the penalty off with mata from `user_mata_account`, up to `max_mata_in` of it or any amount for 0
+#[allow(clippy::too_many_arguments)]
+pub fn harvest_penalty_direct(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    msol_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    loan: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    arb_coffer: &Pubkey,
+    user_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    max_mata_in: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new_readonly(Pda::msol_vault_authority(system_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new(*arb_coffer, false),
+
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(*user_msol_account, false),
+
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::HarvestPenalty { amm_type: AmmTypes::None as u8, min_mata_out: max_mata_in };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn sell_lucra_for_arb_funds_using_raydium(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    lucra_holding_vault: &Pubkey,
+    reward_mint: &Pubkey,
+
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_lucra_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    pool_lucra_account: &Pubkey,
+    pool_wsol_account: &Pubkey,
+    amm_program: &Pubkey,
+    amm_authority: &Pubkey,
+    amm_open_orders: &Pubkey,
+    amm_target: &Pubkey,
+    serum_lucra_sol_market: &Pubkey,
+    serum_bids: &Pubkey,
+    serum_asks: &Pubkey,
+    serum_event_queue: &Pubkey,
+    serum_base_vault: &Pubkey,
+    serum_quote_vault: &Pubkey,
+    serum_vault_signer: &Pubkey,
+    min_amount_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new(*lucra_holding_vault, false),
+        AccountMeta::new_readonly(Pda::lucra_holding_vault_authority(arb_state).0, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_lucra_account, false),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new_readonly(raydium_v4::id(), false),
+        AccountMeta::new(*pool_lucra_account, false),
+        AccountMeta::new(*pool_wsol_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*amm_program, false),
+        AccountMeta::new_readonly(*amm_authority, false),
+        AccountMeta::new(*amm_open_orders, false),
+        AccountMeta::new(*amm_target, false),
+        AccountMeta::new(*serum_lucra_sol_market, false),
+        AccountMeta::new_readonly(serum_v3::id(), false),
+        AccountMeta::new(*serum_bids, false),
+        AccountMeta::new(*serum_asks, false),
+        AccountMeta::new(*serum_event_queue, false),
+        AccountMeta::new(*serum_base_vault, false),
+        AccountMeta::new(*serum_quote_vault, false),
+        AccountMeta::new_readonly(*serum_vault_signer, false),
+    ];
+    let data = Instruction::SellFundsForArb { 
+        fund_source: CurrencyTypes::Lucra as u8, 
+        amm_type: AmmTypes::Raydium as u8,
+        lamports: 0,
+        min_amount_out,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn sell_mata_for_arb_funds_using_raydium(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_holding_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    wsol_mint: &Pubkey,
+    reward_mint: &Pubkey,
+
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    pool_wsol_account: &Pubkey,
+    pool_mata_account: &Pubkey,
+    amm_program: &Pubkey,
+    amm_authority: &Pubkey,
+    amm_open_orders: &Pubkey,
+    amm_target: &Pubkey,
+    serum_sol_mata_market: &Pubkey,
+    serum_bids: &Pubkey,
+    serum_asks: &Pubkey,
+    serum_event_queue: &Pubkey,
+    serum_base_vault: &Pubkey,
+    serum_quote_vault: &Pubkey,
+    serum_vault_signer: &Pubkey,
+    min_amount_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new(*mata_holding_vault, false),
+        AccountMeta::new_readonly(Pda::mata_holding_vault_authority(arb_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*wsol_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(raydium_v4::id(), false),
+        AccountMeta::new(*pool_wsol_account, false),
+        AccountMeta::new(*pool_mata_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*amm_program, false),
+        AccountMeta::new_readonly(*amm_authority, false),
+        AccountMeta::new(*amm_open_orders, false),
+        AccountMeta::new(*amm_target, false),
+        AccountMeta::new(*serum_sol_mata_market, false),
+        AccountMeta::new_readonly(serum_v3::id(), false),
+        AccountMeta::new(*serum_bids, false),
+        AccountMeta::new(*serum_asks, false),
+        AccountMeta::new(*serum_event_queue, false),
+        AccountMeta::new(*serum_base_vault, false),
+        AccountMeta::new(*serum_quote_vault, false),
+        AccountMeta::new_readonly(*serum_vault_signer, false),
+    ];
+    let data = Instruction::SellFundsForArb { 
+        fund_source: CurrencyTypes::Mata as u8, 
+        amm_type: AmmTypes::Raydium as u8,
+        lamports: 0,
+        min_amount_out,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn sell_lucra_for_arb_funds_using_orca(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    lucra_holding_vault: &Pubkey,
+    reward_mint: &Pubkey,
+
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_lucra_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    ls_amm: &Pubkey,
+    ls_amm_authority: &Pubkey,
+    ls_pool_base_vault: &Pubkey,
+    ls_pool_quote_vault: &Pubkey,
+    ls_pool_mint: &Pubkey,
+    ls_pool_fees: &Pubkey,
+    sm_amm: &Pubkey,
+    sm_pool_base_vault: &Pubkey,
+    sm_pool_quote_vault: &Pubkey,
+    lamports: u64,
+    min_amount_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new(*lucra_holding_vault, false),
+        AccountMeta::new_readonly(Pda::lucra_holding_vault_authority(arb_state).0, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_lucra_account, false),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new_readonly(*ls_amm, false),
+        AccountMeta::new_readonly(*ls_amm_authority, false),
+        AccountMeta::new(*ls_pool_base_vault, false),
+        AccountMeta::new(*ls_pool_quote_vault, false),
+        AccountMeta::new(*ls_pool_mint, false),
+        AccountMeta::new(*ls_pool_fees, false),
+        AccountMeta::new_readonly(*sm_amm, false),
+        AccountMeta::new_readonly(*sm_pool_base_vault, false),
+        AccountMeta::new_readonly(*sm_pool_quote_vault, false),
+        AccountMeta::new_readonly(orca_swap::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::SellFundsForArb { 
+        fund_source: CurrencyTypes::Lucra as u8, 
+        amm_type: AmmTypes::Orca as u8,
+        lamports,
+        min_amount_out,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn sell_mata_for_arb_funds_using_orca(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_holding_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    wsol_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    sm_amm: &Pubkey,
+    sm_amm_authority: &Pubkey,
+    sm_pool_base_vault: &Pubkey,
+    sm_pool_quote_vault: &Pubkey,
+    sm_pool_mint: &Pubkey,
+    sm_pool_fees: &Pubkey,
+    lamports: u64,
+    min_amount_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new(*mata_holding_vault, false),
+        AccountMeta::new_readonly(Pda::mata_holding_vault_authority(arb_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(*wsol_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(*sm_amm, false),
+        AccountMeta::new_readonly(*sm_amm_authority, false),
+        AccountMeta::new(*sm_pool_base_vault, false),
+        AccountMeta::new(*sm_pool_quote_vault, false),
+        AccountMeta::new(*sm_pool_mint, false),
+        AccountMeta::new(*sm_pool_fees, false),
+        AccountMeta::new_readonly(orca_swap::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::SellFundsForArb { 
+        fund_source: CurrencyTypes::Mata as u8, 
+        amm_type: AmmTypes::Orca as u8,
+        lamports,
+        min_amount_out,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `tick_arrays` are the ones a swap of mata into the whirlpool walks through from its current tick
+#[allow(clippy::too_many_arguments)]
+pub fn sell_mata_for_arb_funds_using_whirlpool(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_holding_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    wsol_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    whirlpool: &Pubkey,
+    whirlpool_vault_a: &Pubkey,
+    whirlpool_vault_b: &Pubkey,
+    tick_arrays: &[Pubkey; 3],
+    lamports: u64,
+    min_amount_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new(*mata_holding_vault, false),
+        AccountMeta::new_readonly(Pda::mata_holding_vault_authority(arb_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(*wsol_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(*whirlpool, false),
+        AccountMeta::new(*whirlpool_vault_a, false),
+        AccountMeta::new(*whirlpool_vault_b, false),
+        AccountMeta::new(tick_arrays[0], false),
+        AccountMeta::new(tick_arrays[1], false),
+        AccountMeta::new(tick_arrays[2], false),
+        AccountMeta::new_readonly(find_whirlpool_oracle(whirlpool), false),
+        AccountMeta::new_readonly(orca_whirlpool::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::SellFundsForArb { 
+        fund_source: CurrencyTypes::Mata as u8, 
+        amm_type: AmmTypes::Whirlpool as u8,
+        lamports,
+        min_amount_out,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn mint_mata_for_arb_funds_checking_raydium(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_holding_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    reward_mint: &Pubkey,
+
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+
+    sm_base_vault: &Pubkey,
+    sm_base_mint: &Pubkey,
+    sm_quote_vault: &Pubkey,
+    sm_amm_open_orders: &Pubkey,
+    sm_amm_program: &Pubkey,
+
+    user_reward_account: &Pubkey,
+
+    mata: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new_readonly(*arb_fund, false),
+        AccountMeta::new(*mata_holding_vault, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(Pda::mata_mint_authority(system_state).0, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sm_base_vault, false),
+        AccountMeta::new_readonly(*sm_base_mint, false),
+        AccountMeta::new_readonly(*sm_quote_vault, false),
+        AccountMeta::new_readonly(*sm_amm_open_orders, false),
+        AccountMeta::new_readonly(*sm_amm_program, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::MintFundsForArb { 
+        fund_source: CurrencyTypes::Mata as u8, 
+        amm_type: AmmTypes::Raydium as u8,
+        lamports: mata,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn mint_lucra_for_arb_funds_checking_raydium(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    arb_coffer: &Pubkey,
+    lucra_holding_vault: &Pubkey,
+    lucra_mint: &Pubkey,
+    reward_mint: &Pubkey,
+
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    
+    sm_raydium_base_vault: &Pubkey,
+    sm_raydium_base_mint: &Pubkey,
+    sm_raydium_quote_vault: &Pubkey,
+    sm_raydium_quote_mint: &Pubkey,
+    sm_raydium_amm_open_orders: &Pubkey,
+    sm_raydium_amm: &Pubkey,
+
+    sm_orca_base_vault: &Pubkey,
+    sm_orca_quote_vault: &Pubkey,
+    sm_orca_amm: &Pubkey,
+
+    user_reward_account: &Pubkey,
+
+    marinade_state: &Pubkey,
+
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new_readonly(*arb_fund, false),
+        AccountMeta::new_readonly(*arb_coffer, false),
+        AccountMeta::new(*lucra_holding_vault, false),
+        AccountMeta::new(*lucra_mint, false),
+        AccountMeta::new_readonly(Pda::lucra_mint_authority(system_state).0, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sm_raydium_base_vault, false),
+        AccountMeta::new_readonly(*sm_raydium_base_mint, false),
+        AccountMeta::new_readonly(*sm_raydium_quote_vault, false),
+        AccountMeta::new_readonly(*sm_raydium_quote_mint, false),
+        AccountMeta::new_readonly(*sm_raydium_amm_open_orders, false),
+        AccountMeta::new_readonly(*sm_raydium_amm, false),
+        AccountMeta::new_readonly(*sm_orca_base_vault, false),
+        AccountMeta::new_readonly(*sm_orca_quote_vault, false),
+        AccountMeta::new_readonly(*sm_orca_amm, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::MintFundsForArb { 
+        fund_source: CurrencyTypes::Lucra as u8, 
+        amm_type: AmmTypes::Raydium as u8,
+        lamports,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn mint_mata_for_arb_funds_checking_orca(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_holding_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    sm_amm: &Pubkey,
+    sm_base_vault: &Pubkey,
+    sm_base_mint: &Pubkey,
+    sm_quote_vault: &Pubkey,
+    user_reward_account: &Pubkey,
+    mata: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new_readonly(*arb_fund, false),
+        AccountMeta::new(*mata_holding_vault, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(Pda::mata_mint_authority(system_state).0, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sm_amm, false),
+        AccountMeta::new_readonly(*sm_base_vault, false),
+        AccountMeta::new_readonly(*sm_base_mint, false),
+        AccountMeta::new_readonly(*sm_quote_vault, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::MintFundsForArb { 
+        fund_source: CurrencyTypes::Mata as u8, 
+        amm_type: AmmTypes::Orca as u8,
+        lamports: mata,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn mint_lucra_for_arb_funds_checking_orca(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    arb_coffer: &Pubkey,
+    lucra_holding_vault: &Pubkey,
+    lucra_mint: &Pubkey,
+    reward_mint: &Pubkey,
+
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    
+    sm_orca_amm: &Pubkey,
+    sm_orca_base_vault: &Pubkey,
+    sm_orca_base_mint: &Pubkey,
+    sm_orca_quote_vault: &Pubkey,
+    sm_orca_quote_mint: &Pubkey,
+    
+    sm_raydium_open_orders: &Pubkey,
+    sm_raydium_base_vault: &Pubkey,
+    sm_raydium_quote_vault: &Pubkey,
+    sm_raydium_amm: &Pubkey,
+
+    user_reward_account: &Pubkey,
+
+    marinade_state: &Pubkey,
+
+    lamports: u64
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new_readonly(*arb_fund, false),
+        AccountMeta::new_readonly(*arb_coffer, false),
+        AccountMeta::new(*lucra_holding_vault, false),
+        AccountMeta::new(*lucra_mint, false),
+        AccountMeta::new_readonly(Pda::lucra_mint_authority(system_state).0, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sm_orca_amm, false),
+        AccountMeta::new_readonly(*sm_orca_base_vault, false),
+        AccountMeta::new_readonly(*sm_orca_base_mint, false),
+        AccountMeta::new_readonly(*sm_orca_quote_vault, false),
+        AccountMeta::new_readonly(*sm_orca_quote_mint, false),
+        AccountMeta::new_readonly(*sm_raydium_open_orders, false),
+        AccountMeta::new_readonly(*sm_raydium_base_vault, false),
+        AccountMeta::new_readonly(*sm_raydium_quote_vault, false),
+        AccountMeta::new_readonly(*sm_raydium_amm, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+
+    ];
+    let data = Instruction::MintFundsForArb { 
+        fund_source: CurrencyTypes::Lucra as u8, 
+        amm_type: AmmTypes::Orca as u8,
+        lamports,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_msol_for_arb_funds(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_coffer: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_mint: &Pubkey,
+    wsol_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    sm_raydium_base_vault: &Pubkey,
+    sm_raydium_quote_vault: &Pubkey,
+    sm_raydium_amm_open_orders: &Pubkey,
+    sm_raydium_amm: &Pubkey,
+    sm_orca_base_vault: &Pubkey,
+    sm_orca_quote_vault: &Pubkey,
+    sm_orca_amm: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_pda: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    treasury_msol_account: &Pubkey,
+    marinade_state: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_coffer, false),
+        AccountMeta::new_readonly(Pda::arb_coffer_authority(system_state).0, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*wsol_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sm_raydium_base_vault, false),
+        AccountMeta::new_readonly(*sm_raydium_quote_vault, false),
+        AccountMeta::new_readonly(*sm_raydium_amm_open_orders, false),
+        AccountMeta::new_readonly(*sm_raydium_amm, false),
+        AccountMeta::new_readonly(*sm_orca_base_vault, false),
+        AccountMeta::new_readonly(*sm_orca_quote_vault, false),
+        AccountMeta::new_readonly(*sm_orca_amm, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_pda, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new(*treasury_msol_account, false),
+        AccountMeta::new(*marinade_state, false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::MintFundsForArb { 
+        fund_source: CurrencyTypes::Msol as u8, 
+        amm_type: AmmTypes::None as u8,
+        lamports,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_msol_for_arb_funds_checking_orca(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_coffer: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    sm_base_vault: &Pubkey,
+    sm_base_mint: &Pubkey,
+    sm_quote_vault: &Pubkey,
+    sm_amm: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_pda: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    treasury_msol_account: &Pubkey,
+    marinade_state: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_coffer, false),
+        AccountMeta::new_readonly(Pda::arb_coffer_authority(system_state).0, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sm_base_vault, false),
+        AccountMeta::new_readonly(*sm_base_mint, false),
+        AccountMeta::new_readonly(*sm_quote_vault, false),
+        AccountMeta::new_readonly(*sm_amm, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_pda, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new(*treasury_msol_account, false),
+        AccountMeta::new(*marinade_state, false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::MintFundsForArb { 
+        fund_source: CurrencyTypes::Msol as u8, 
+        amm_type: AmmTypes::Orca as u8,
+        lamports,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_msol_for_arb_funds_checking_raydium(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_coffer: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    sm_base_vault: &Pubkey,
+    sm_base_mint: &Pubkey,
+    sm_quote_vault: &Pubkey,
+    sm_amm_open_orders: &Pubkey,
+    sm_amm: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_pda: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    treasury_msol_account: &Pubkey,
+    marinade_state: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_coffer, false),
+        AccountMeta::new_readonly(Pda::arb_coffer_authority(system_state).0, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new_readonly(*sm_base_vault, false),
+        AccountMeta::new_readonly(*sm_base_mint, false),
+        AccountMeta::new_readonly(*sm_quote_vault, false),
+        AccountMeta::new_readonly(*sm_amm_open_orders, false),
+        AccountMeta::new_readonly(*sm_amm, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_pda, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new(*treasury_msol_account, false),
+        AccountMeta::new(*marinade_state, false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::MintFundsForArb { 
+        fund_source: CurrencyTypes::Msol as u8, 
+        amm_type: AmmTypes::Raydium as u8,
+        lamports,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spend_arb_funds_for_lucra_using_raydium(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    lucra_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    wsol_holding_vault: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_lucra_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    pool_lucra_account: &Pubkey,
+    pool_wsol_account: &Pubkey,
+    amm_program: &Pubkey,
+    amm_authority: &Pubkey,
+    amm_open_orders: &Pubkey,
+    amm_target: &Pubkey,
+    serum_lucra_sol_market: &Pubkey,
+    serum_bids: &Pubkey,
+    serum_asks: &Pubkey,
+    serum_event_queue: &Pubkey,
+    serum_base_vault: &Pubkey,
+    serum_quote_vault: &Pubkey,
+    serum_vault_signer: &Pubkey,
+    min_amount_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new_readonly(Pda::arb_fund_authority(arb_state).0, false),
+        AccountMeta::new(*lucra_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new(*wsol_holding_vault, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_lucra_account, false),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new_readonly(raydium_v4::id(), false),
+        AccountMeta::new(*pool_lucra_account, false),
+        AccountMeta::new(*pool_wsol_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*amm_program, false),
+        AccountMeta::new_readonly(*amm_authority, false),
+        AccountMeta::new(*amm_open_orders, false),
+        AccountMeta::new(*amm_target, false),
+        AccountMeta::new(*serum_lucra_sol_market, false),
+        AccountMeta::new_readonly(serum_v3::id(), false),
+        AccountMeta::new(*serum_bids, false),
+        AccountMeta::new(*serum_asks, false),
+        AccountMeta::new(*serum_event_queue, false),
+        AccountMeta::new(*serum_base_vault, false),
+        AccountMeta::new(*serum_quote_vault, false),
+        AccountMeta::new_readonly(*serum_vault_signer, false),
+    ];
+    let data = Instruction::BuyBurnForArb {
+        fund_source: CurrencyTypes::Lucra as u8, 
+        amm_type: AmmTypes::Raydium as u8,
+        lamports: 0,
+        min_amount_out,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spend_arb_funds_for_mata_using_raydium(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_mint: &Pubkey,
+    wsol_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    pool_wsol_account: &Pubkey,
+    pool_mata_account: &Pubkey,
+    amm_program: &Pubkey,
+    amm_authority: &Pubkey,
+    amm_open_orders: &Pubkey,
+    amm_target: &Pubkey,
+    serum_sol_mata_market: &Pubkey,
+    serum_bids: &Pubkey,
+    serum_asks: &Pubkey,
+    serum_event_queue: &Pubkey,
+    serum_base_vault: &Pubkey,
+    serum_quote_vault: &Pubkey,
+    serum_vault_signer: &Pubkey,
+    lamports: u64,
+    min_amount_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new_readonly(Pda::arb_fund_authority(arb_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*wsol_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(raydium_v4::id(), false),
+        AccountMeta::new(*pool_wsol_account, false),
+        AccountMeta::new(*pool_mata_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*amm_program, false),
+        AccountMeta::new_readonly(*amm_authority, false),
+        AccountMeta::new(*amm_open_orders, false),
+        AccountMeta::new(*amm_target, false),
+        AccountMeta::new(*serum_sol_mata_market, false),
+        AccountMeta::new_readonly(serum_v3::id(), false),
+        AccountMeta::new(*serum_bids, false),
+        AccountMeta::new(*serum_asks, false),
+        AccountMeta::new(*serum_event_queue, false),
+        AccountMeta::new(*serum_base_vault, false),
+        AccountMeta::new(*serum_quote_vault, false),
+        AccountMeta::new_readonly(*serum_vault_signer, false),
+    ];
+    let data = Instruction::BuyBurnForArb { 
+        fund_source: CurrencyTypes::Mata as u8, 
+        amm_type: AmmTypes::Raydium as u8,
+        lamports,
+        min_amount_out,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spend_arb_funds_for_lucra_using_orca(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
     arb_fund: &Pubkey,
     wsol_holding_vault: &Pubkey,
-    mata_holding_vault: &Pubkey,
-    lucra_holding_vault: &Pubkey,
-    min_deposit: u64,
-    collateral_requirement: u32,
-    epoch: i64,
-    loans_enabled: bool,
-    staking_enabled: bool,
-    arbitrage_enabled: bool,
-    peg_check_enabled: bool,
-    max_amount_of_lucra_to_mint: u64,
-    daily_arb_limit: u64,
-    maximum_outstanding_mata: u64,
-    lcp: u8,
+    lucra_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_lucra_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    ls_amm: &Pubkey,
+    ls_amm_authority: &Pubkey,
+    ls_pool_base_vault: &Pubkey,
+    ls_pool_quote_vault: &Pubkey,
+    ls_pool_mint: &Pubkey,
+    ls_pool_fees: &Pubkey,
+    min_amount_out: u64,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*marinade_state, false),
-        AccountMeta::new_readonly(CREATOR_AUTHORITY, true),
-        AccountMeta::new_readonly(*mata_mint, false),
-        AccountMeta::new_readonly(*lucra_mint, false),
-        AccountMeta::new_readonly(*reward_mint, false),
-        AccountMeta::new_readonly(*staked_lucra_mint, false),
-        AccountMeta::new(*state, false),
+        AccountMeta::new_readonly(*system_state, false),
         AccountMeta::new(*arb_state, false),
-        AccountMeta::new_readonly(*msol_vault, false),
-        AccountMeta::new_readonly(*arb_coffer, false),
-        AccountMeta::new_readonly(*rewards_vault, false),
-        AccountMeta::new(*staking_state, false),
-        AccountMeta::new_readonly(*arb_fund, false),
-        AccountMeta::new_readonly(*wsol_holding_vault, false),
-        AccountMeta::new_readonly(*mata_holding_vault, false),
-        AccountMeta::new_readonly(*lucra_holding_vault, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new_readonly(Pda::arb_fund_authority(arb_state).0, false),
+        AccountMeta::new(*wsol_holding_vault, false),
+        AccountMeta::new(*lucra_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_lucra_account, false),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new_readonly(*ls_amm, false),
+        AccountMeta::new_readonly(*ls_amm_authority, false),
+        AccountMeta::new(*ls_pool_base_vault, false),
+        AccountMeta::new(*ls_pool_quote_vault, false),
+        AccountMeta::new(*ls_pool_mint, false),
+        AccountMeta::new(*ls_pool_fees, false),
+        AccountMeta::new_readonly(orca_swap::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::Initialize { 
-        min_deposit,
-        collateral_requirement,
-        epoch,
-        loans_enabled,
-        staking_enabled,
-        arbitrage_enabled,
-        peg_check_enabled,
-        max_amount_of_lucra_to_mint,
-        daily_arb_limit,
-        maximum_outstanding_mata,
-        lcp,
+    let data = Instruction::BuyBurnForArb { 
+        fund_source: CurrencyTypes::Lucra as u8, 
+        amm_type: AmmTypes::Orca as u8,
+        lamports: 0,
+        min_amount_out,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spend_arb_funds_for_mata_using_orca(
+    system_state: &Pubkey,
+    arb_state: &Pubkey,
+    arb_fund: &Pubkey,
+    mata_mint: &Pubkey,
+    wsol_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey, 
+    sm_amm: &Pubkey,
+    sm_amm_authority: &Pubkey,
+    sm_pool_base_vault: &Pubkey,
+    sm_pool_quote_vault: &Pubkey,
+    sm_pool_mint: &Pubkey,
+    sm_pool_fees: &Pubkey,
+    lamports: u64,
+    min_amount_out: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new_readonly(Pda::arb_fund_authority(arb_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*wsol_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(*sm_amm, false),
+        AccountMeta::new_readonly(*sm_amm_authority, false),
+        AccountMeta::new(*sm_pool_base_vault, false),
+        AccountMeta::new(*sm_pool_quote_vault, false),
+        AccountMeta::new(*sm_pool_mint, false),
+        AccountMeta::new(*sm_pool_fees, false),
+        AccountMeta::new_readonly(orca_swap::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::BuyBurnForArb { 
+        fund_source: CurrencyTypes::Mata as u8, 
+        amm_type: AmmTypes::Orca as u8,
+        lamports,
+        min_amount_out,
     };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
-}
+} 
 
+/// `tick_arrays` are the ones a swap of SOL into the whirlpool walks through from its current tick
 #[allow(clippy::too_many_arguments)]
-pub fn update_state(
+pub fn spend_arb_funds_for_mata_using_whirlpool(
     system_state: &Pubkey,
     arb_state: &Pubkey,
-    min_deposit: u64,
-    collateral_requirement: u32,
-    loans_enabled: bool,
-    staking_enabled: bool,
-    arbitrage_enabled: bool,
-    peg_check_enabled: bool,
-    max_amount_of_lucra_to_mint: u64,
-    daily_arb_limit: u64,
-    maximum_outstanding_mata: u64,
-    minimum_harvest_amount: u64,
-    reward_fee: u32,
-    lcp: u8,
+    arb_fund: &Pubkey,
+    mata_mint: &Pubkey,
+    wsol_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    whirlpool: &Pubkey,
+    whirlpool_vault_a: &Pubkey,
+    whirlpool_vault_b: &Pubkey,
+    tick_arrays: &[Pubkey; 3],
+    lamports: u64,
+    min_amount_out: u64,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*system_state, false),
         AccountMeta::new(*arb_state, false),
-        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+        AccountMeta::new(*arb_fund, false),
+        AccountMeta::new_readonly(Pda::arb_fund_authority(arb_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*wsol_mint, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(*whirlpool, false),
+        AccountMeta::new(*whirlpool_vault_a, false),
+        AccountMeta::new(*whirlpool_vault_b, false),
+        AccountMeta::new(tick_arrays[0], false),
+        AccountMeta::new(tick_arrays[1], false),
+        AccountMeta::new(tick_arrays[2], false),
+        AccountMeta::new_readonly(find_whirlpool_oracle(whirlpool), false),
+        AccountMeta::new_readonly(orca_whirlpool::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::UpdateState { 
-        min_deposit,
-        collateral_requirement,
-        loans_enabled,
-        staking_enabled,
-        arbitrage_enabled,
-        peg_check_enabled,
-        max_amount_of_lucra_to_mint,
-        daily_arb_limit,
-        maximum_outstanding_mata,
-        minimum_harvest_amount,
-        reward_fee,
-        lcp,
+    let data = Instruction::BuyBurnForArb { 
+        fund_source: CurrencyTypes::Mata as u8, 
+        amm_type: AmmTypes::Whirlpool as u8,
+        lamports,
+        min_amount_out,
     };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn create_mata_loan(
+pub fn clean_up_arb_derived(
     system_state: &Pubkey,
-    marinade_state: &Pubkey,
-    loan: &Pubkey,
-    msol_vault: &Pubkey,
-    mata_mint: &Pubkey,
-    mata_mint_authority: &Pubkey,
-    transfer_from: &Pubkey,
-    user_mata_account: &Pubkey,
+    arb_state: &Pubkey,
+    arb_coffer: &Pubkey,
+    wsol_holding_vault: &Pubkey,
+    reward_mint: &Pubkey,
+    temp_wsol_account: &Pubkey,
+    user_account: &Pubkey,
+    user_reward_account: &Pubkey,
+    user_wsol_account: &Pubkey,
     user_msol_account: &Pubkey,
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
+    marinade_state: &Pubkey,
     msol_mint: &Pubkey,
-    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_sol_leg_pda: &Pubkey,
     liq_pool_msol_leg: &Pubkey,
-    liq_pool_msol_leg_authority: &Pubkey,
-    reserve_address: &Pubkey,
-    msol_mint_authority: &Pubkey,
-    lamports: u64,
+    reserve_pda: &Pubkey,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new(*system_state, false),
-        AccountMeta::new(*marinade_state, false),
-
-        AccountMeta::new(*loan, false),
-        AccountMeta::new(*msol_vault, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new_readonly(*mata_mint_authority, false),
-        AccountMeta::new(*transfer_from, true),
-        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*arb_coffer, false),
+        AccountMeta::new(*wsol_holding_vault, false),
+        AccountMeta::new_readonly(Pda::wsol_holding_vault_authority(arb_state).0, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new(*user_account, true),
+        AccountMeta::new(*user_wsol_account, false),
         AccountMeta::new(*user_msol_account, false),
-
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-    
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*temp_wsol_account, false),
+        AccountMeta::new(*marinade_state, false),
         AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_address, false),
+        AccountMeta::new(*liq_pool_sol_leg_pda, false),
         AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new_readonly(*liq_pool_msol_leg_authority, false),
-        AccountMeta::new(*reserve_address, false),
-        AccountMeta::new_readonly(*msol_mint_authority, false),
-    
-        AccountMeta::new_readonly(solana_program::sysvar::fees::id(), false),
+        AccountMeta::new_readonly(find_liq_pool_msol_leg_authority(marinade_state).0, false),
+        AccountMeta::new(*reserve_pda, false),
+        AccountMeta::new_readonly(find_msol_mint_authority(marinade_state).0, false),
         AccountMeta::new_readonly(solana_program::system_program::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
         AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::CreateMataLoan { lamports };
+    let data = Instruction::CleanUpArb {};
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn create_mata_loan_with_locked_stake(
+pub fn create_revenue_ledger(
     system_state: &Pubkey,
-    marinade_state: &Pubkey,
-    loan: &Pubkey,
-    msol_vault: &Pubkey,
-    mata_mint: &Pubkey,
-    mata_mint_authority: &Pubkey,
-    transfer_from: &Pubkey,
-    user_mata_account: &Pubkey,
-    user_msol_account: &Pubkey,
-    staking_account: &Pubkey,
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    msol_mint: &Pubkey,
-    liq_pool_sol_leg_address: &Pubkey,
-    liq_pool_msol_leg: &Pubkey,
-    liq_pool_msol_leg_authority: &Pubkey,
-    reserve_address: &Pubkey,
-    msol_mint_authority: &Pubkey,
-    lamports: u64,
+    revenue_ledger: &Pubkey,
 ) -> SolInstruction {
     let accounts = vec![
         AccountMeta::new(*system_state, false),
-        AccountMeta::new(*marinade_state, false),
+        AccountMeta::new(*revenue_ledger, false),
+        AccountMeta::new_readonly(CREATOR_AUTHORITY, true),
+    ];
+    let data = Instruction::CreateRevenueLedger {};
 
-        AccountMeta::new(*loan, false),
-        AccountMeta::new(*msol_vault, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new_readonly(*mata_mint_authority, false),
-        AccountMeta::new(*transfer_from, true),
-        AccountMeta::new(*user_mata_account, false),
-        AccountMeta::new(*user_msol_account, false),
-        AccountMeta::new(*staking_account, false),
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
 
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-    
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_address, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new_readonly(*liq_pool_msol_leg_authority, false),
-        AccountMeta::new(*reserve_address, false),
-        AccountMeta::new_readonly(*msol_mint_authority, false),
-    
-        AccountMeta::new_readonly(solana_program::sysvar::fees::id(), false),
-        AccountMeta::new_readonly(solana_program::system_program::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
+pub fn global_settlement(
+    system_state: &Pubkey,
+    settlement_sol_price: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
     ];
-    let data = Instruction::CreateMataLoan { lamports };
+    let data = Instruction::GlobalSettlement {
+        settlement_sol_price,
+    };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn close_mata_loan(
+pub fn reclaim_excess_derived(
     system_state: &Pubkey,
-    marinade_state: &Pubkey,
-    loan: &Pubkey,
-    user_account: &Pubkey,
-    user_msol_account: &Pubkey,
-    mata_mint: &Pubkey,
-    user_mata_account: &Pubkey,
     msol_vault: &Pubkey,
-    msol_mint: &Pubkey,
-    liq_pool_sol_leg_address: &Pubkey,
-    liq_pool_msol_leg: &Pubkey,
-    treasury_msol_account: &Pubkey,
-    unstake_msol: bool,
+    to_account: &Pubkey,
 ) -> SolInstruction {
-    let msol_vault_authority = SystemState::find_msol_vault_authority(system_state).0;
     let accounts = vec![
-        AccountMeta::new(*system_state, false),
-        AccountMeta::new(*marinade_state, false),
-        AccountMeta::new(*loan, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_msol_account, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*user_mata_account, false),
-        AccountMeta::new_readonly(msol_vault_authority, false),
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
         AccountMeta::new(*msol_vault, false),
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_address, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new(*treasury_msol_account, false),
-        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new(*to_account, false),
+        AccountMeta::new_readonly(Pda::msol_vault_authority(system_state).0, false),
         AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
     ];
-    let data = Instruction::CloseOutMataLoan {
-        unstake_msol,
-    };
+    let data = Instruction::ReclaimExcess {};
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn close_mata_loan_with_locked_stake(
+pub fn roll_arb_window(
     system_state: &Pubkey,
-    marinade_state: &Pubkey,
-    loan: &Pubkey,
-    user_account: &Pubkey,
-    user_msol_account: &Pubkey,
-    mata_mint: &Pubkey,
-    user_mata_account: &Pubkey,
-    user_staking_account: &Pubkey,
-    msol_vault: &Pubkey,
-    msol_mint: &Pubkey,
-    liq_pool_sol_leg_address: &Pubkey,
-    liq_pool_msol_leg: &Pubkey,
-    treasury_msol_account: &Pubkey,
-    unstake_msol: bool,
+    arb_state: &Pubkey,
+    user_reward_account: &Pubkey,
+    reward_mint: &Pubkey,
 ) -> SolInstruction {
-    let msol_vault_authority = SystemState::find_msol_vault_authority(system_state).0;
     let accounts = vec![
-        AccountMeta::new(*system_state, false),
-        AccountMeta::new(*marinade_state, false),
-        AccountMeta::new(*loan, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_msol_account, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*user_mata_account, false),
-        AccountMeta::new_readonly(msol_vault_authority, false),
-        AccountMeta::new(*msol_vault, false),
-        AccountMeta::new(*user_staking_account, false),
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_address, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new(*treasury_msol_account, false),
-        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*arb_state, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
         AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
     ];
-    let data = Instruction::CloseOutMataLoan {
-        unstake_msol
-    };
+    let data = Instruction::RollArbWindow {};
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn set_reward_metadata(
+    system_state: &Pubkey,
+    reward_mint: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(CREATOR_AUTHORITY, true),
+        AccountMeta::new_readonly(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new(Pda::metadata(reward_mint).0, false),
+        AccountMeta::new_readonly(token_metadata::id(), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+    let data = Instruction::SetRewardMetadata {
+        name,
+        symbol,
+        uri,
+    };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn freeze_staking_account(
+    staking_account: &Pubkey,
+    authority: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*staking_account, false),
+        AccountMeta::new_readonly(*authority, true),
+    ];
+    let data = Instruction::FreezeStakingAccount { };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn create_staking_account(
-    system_state: &Pubkey,
-    staking_state: &Pubkey,
+pub fn unfreeze_staking_account(
     staking_account: &Pubkey,
-    owner: &Pubkey,
+    authority: &Pubkey,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new_readonly(*staking_state, false),
         AccountMeta::new(*staking_account, false),
-        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(*authority, true),
     ];
-    let data = Instruction::CreateStakingAccount { };
+    let data = Instruction::UnfreezeStakingAccount { };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn create_stake_balance(
-    system_state: &Pubkey,
-    staking_state: &Pubkey,
-    stake_balance: &Pubkey,
+pub fn set_staking_recovery_key(
+    staking_account: &Pubkey,
     owner: &Pubkey,
-    deposit_vault: &Pubkey,
-    stake_vault: &Pubkey,
-    pending_vault: &Pubkey,
-    nonce: u8,
-    staking_timeframe: StakingTimeframe,
+    current_recovery_key: Option<&Pubkey>,
+    recovery_key: Pubkey,
 ) -> SolInstruction {
-    let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new_readonly(*staking_state, false),
-        AccountMeta::new(*stake_balance, false),
+    let mut accounts = vec![
+        AccountMeta::new(*staking_account, false),
         AccountMeta::new_readonly(*owner, true),
-        AccountMeta::new_readonly(*deposit_vault, false),
-        AccountMeta::new_readonly(*stake_vault, false),
-        AccountMeta::new_readonly(*pending_vault, false),
     ];
-    let data = Instruction::CreateStakeBalance { nonce, staking_timeframe: staking_timeframe as u8 };
-    
+    if let Some(current_recovery_key) = current_recovery_key {
+        accounts.push(AccountMeta::new_readonly(*current_recovery_key, true));
+    }
+    let data = Instruction::SetStakingRecoveryKey { recovery_key };
+
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn deposit_stake(
+pub fn set_lp_collateral(
     system_state: &Pubkey,
-    stake_balance: &Pubkey,
-    from_account: &Pubkey,
-    to_account: &Pubkey,
-    owner: &Pubkey,
-    lucra: u64,
+    lp_vault: &Pubkey,
+    lp_collateral_requirement: CollateralRatio,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new_readonly(*stake_balance, false),
-        AccountMeta::new(*from_account, false),
-        AccountMeta::new(*to_account, false),
-        AccountMeta::new_readonly(*owner, true),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+        AccountMeta::new_readonly(SOL_MATA_ORCA_AMM, false),
+        AccountMeta::new_readonly(*lp_vault, false),
     ];
-    let data = Instruction::DepositStake { lucra };
+    let data = Instruction::SetLpCollateral { lp_collateral_requirement };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn stake(
+pub fn sweep_expired_reward(
     system_state: &Pubkey,
     staking_state: &Pubkey,
-    staking_account: &Pubkey,
-    stake_balance: &Pubkey,
-    deposit_vault: &Pubkey,
-    stake_vault: &Pubkey,
-    owner: &Pubkey,
-    staked_lucra_account: &Pubkey,
-    transfer_authority: &Pubkey,
-    staked_lucra_mint: &Pubkey,
-    lucra: u64,
+    reward: &Pubkey,
+    rewards_vault: &Pubkey,
+    arb_coffer: &Pubkey,
+    receiver: &Pubkey,
+    user_reward_account: &Pubkey,
+    reward_mint: &Pubkey,
 ) -> SolInstruction {
-    let staked_lucra_mint_authority = StakingState::find_stake_mint_authority(staking_state).0;
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
         AccountMeta::new(*staking_state, false),
-        AccountMeta::new(*staking_account, false),
-        AccountMeta::new(*stake_balance, false),
-        AccountMeta::new(*deposit_vault, false),
-        AccountMeta::new(*stake_vault, false),
-        AccountMeta::new_readonly(*owner, true),
-        AccountMeta::new_readonly(*transfer_authority, false),
-        AccountMeta::new(*staked_lucra_mint, false),
-        AccountMeta::new(*staked_lucra_account, false),
-        AccountMeta::new_readonly(staked_lucra_mint_authority, false),
+        AccountMeta::new(*reward, false),
+        AccountMeta::new(*rewards_vault, false),
+        AccountMeta::new(*arb_coffer, false),
+        AccountMeta::new_readonly(Pda::rewards_vault_authority(system_state).0, false),
+        AccountMeta::new(*receiver, false),
+        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*reward_mint, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
         AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::Stake { lucra };
+    let data = Instruction::SweepExpiredReward {};
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn start_unstake(
+pub fn set_min_drop_amount(
     system_state: &Pubkey,
     staking_state: &Pubkey,
-    staking_account: &Pubkey,
-    stake_balance: &Pubkey,
-    stake_vault: &Pubkey,
-    pending_vault: &Pubkey,
-    pending_withdrawal: &Pubkey,
-    owner: &Pubkey,
-    staked_lucra_account: &Pubkey,
-    transfer_authority: &Pubkey,
-    staked_lucra_mint: &Pubkey,
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    lucra: u64,
+    min_drop_amount: u64,
 ) -> SolInstruction {
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new_readonly(*staking_state, false),
-        AccountMeta::new(*staking_account, false),
-        AccountMeta::new(*stake_balance, false),
-        AccountMeta::new(*staked_lucra_mint, false),
-        AccountMeta::new(*staked_lucra_account, false),
-        AccountMeta::new_readonly(*owner, true),
-        AccountMeta::new(*stake_vault, false),
-        AccountMeta::new(*pending_vault, false),
-        AccountMeta::new_readonly(*transfer_authority, false),
-        AccountMeta::new(*pending_withdrawal, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*staking_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
     ];
-    let data = Instruction::StartUnstake { lucra };
+    let data = Instruction::SetMinDropAmount { min_drop_amount };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn end_unstake(
+pub fn set_reward_redemption_deadline(
     system_state: &Pubkey,
-    pending_withdrawal: &Pubkey,
-    stake_balance: &Pubkey,
-    pending_vault: &Pubkey,
-    deposit_vault: &Pubkey,
-    owner: &Pubkey,
-    transfer_authority: &Pubkey,
-    user_sol_account: &Pubkey,
+    deadline: i64,
+    grace_period: i64,
+    grace_boost: Bps,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*pending_withdrawal, false),
-        AccountMeta::new_readonly(*stake_balance, false),
-        AccountMeta::new(*pending_vault, false),
-        AccountMeta::new(*deposit_vault, false),
-        AccountMeta::new_readonly(*owner, true),
-        AccountMeta::new_readonly(*transfer_authority, false),
-        AccountMeta::new(*user_sol_account, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
     ];
-    let data = Instruction::EndUnstake { };
+    let data = Instruction::SetRewardRedemptionDeadline { deadline, grace_period, grace_boost };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn withdraw_stake(
+pub fn close_reward_program(system_state: &Pubkey) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+    ];
+    let data = Instruction::CloseRewardProgram {};
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn migrate_account_layout(account: &Pubkey, payer: &Pubkey, signed_by_dao: bool) -> SolInstruction {
+    let mut accounts = vec![
+        AccountMeta::new(*account, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+    if signed_by_dao {
+        accounts.push(AccountMeta::new_readonly(DAO_AUTHORITY, true));
+    }
+    let data = Instruction::MigrateAccountLayout {};
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn migrate_arb_state_layout(arb_state: &Pubkey, system_state: &Pubkey, payer: &Pubkey) -> SolInstruction {
+    let mut instruction = migrate_account_layout(arb_state, payer, true);
+    instruction.accounts.push(AccountMeta::new_readonly(*system_state, false));
+    instruction
+}
+
+pub fn correct_price_history(
     system_state: &Pubkey,
-    stake_balance: &Pubkey,
-    to_account: &Pubkey,
-    deposit_vault: &Pubkey,
-    stake_vault: &Pubkey,
-    pending_vault: &Pubkey,
-    owner: &Pubkey,
-    transfer_authority: &Pubkey,
-    lucra: u64,
+    price_history: &Pubkey,
+    date: i64,
+    corrected_sol_price: u64,
+    corrected_lucra_price: u64,
 ) -> SolInstruction {
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*stake_balance, false),
-        AccountMeta::new(*deposit_vault, false),
-        AccountMeta::new_readonly(*stake_vault, false),
-        AccountMeta::new_readonly(*pending_vault, false),
-        AccountMeta::new(*to_account, false),
-        AccountMeta::new(*owner, true),
-        AccountMeta::new_readonly(*transfer_authority, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*price_history, false),
+        AccountMeta::new(Pda::price_correction(system_state, date).0, false),
+        AccountMeta::new(DAO_AUTHORITY, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
     ];
-    let data = Instruction::WithdrawStake { lucra };
+    let data = Instruction::CorrectPriceHistory { date, corrected_sol_price, corrected_lucra_price };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn claim_reward(
+/// `correct_price_history` for a paged price history, `page_index` is the page the day is archived on
+pub fn correct_paged_price_history(
     system_state: &Pubkey,
-    staking_state: &Pubkey,
-    stake_balance: &Pubkey,
-    reward: &Pubkey,
-    staked_lucra_account: &Pubkey,
-    lucra_vault: &Pubkey,
-    lucra_account: &Pubkey,
-    rewards_vault: &Pubkey,
-    msol_account: &Pubkey,
-    lucra_mint: &Pubkey,
+    price_history: &Pubkey,
+    page_index: u64,
+    date: i64,
+    corrected_sol_price: u64,
+    corrected_lucra_price: u64,
 ) -> SolInstruction {
-    let rewards_vault_authority = SystemState::find_rewards_vault_authority(system_state).0;
-    let lucra_mint_authority = SystemState::find_lucra_mint_authority(system_state).0;
+    let mut instruction = correct_price_history(system_state, price_history, date, corrected_sol_price, corrected_lucra_price);
+    instruction.accounts.push(AccountMeta::new(Pda::price_history_page(price_history, page_index).0, false));
+    instruction
+}
+
+pub fn recompute_penalty(system_state: &Pubkey, loan: &Pubkey, price_history: &Pubkey) -> SolInstruction {
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new_readonly(*staking_state, false),
-        AccountMeta::new(*stake_balance, false),
-        AccountMeta::new_readonly(*reward, false),
-        AccountMeta::new(*staked_lucra_account, false),
-        AccountMeta::new(*lucra_vault, false),
-        AccountMeta::new(*lucra_account, false),
-        AccountMeta::new(*rewards_vault, false),
-        AccountMeta::new(*msol_account, false),
-        AccountMeta::new_readonly(rewards_vault_authority, false),
-        AccountMeta::new(*lucra_mint, false),
-        AccountMeta::new_readonly(lucra_mint_authority, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*price_history, false),
     ];
-    let data = Instruction::ClaimReward { };
+    let data = Instruction::RecomputePenalty {};
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn drop_reward(
+pub fn compute_tvl(
     system_state: &Pubkey,
-    staking_state: &Pubkey,
+    arb_state: &Pubkey,
     marinade_state: &Pubkey,
-    reward: &Pubkey,
-    staked_lucra_mint: &Pubkey,
     msol_vault: &Pubkey,
-    rewards_vault: &Pubkey,
     arb_coffer: &Pubkey,
+    rewards_vault: &Pubkey,
+    wsol_holding_vault: &Pubkey,
+    mata_holding_vault: &Pubkey,
+    lucra_holding_vault: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    user_account: &Pubkey,
     user_reward_account: &Pubkey,
     reward_mint: &Pubkey,
-    reward_mint_authority: &Pubkey,
 ) -> SolInstruction {
-    let msol_vault_transfer_authority = SystemState::find_msol_vault_authority(system_state).0;
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*staking_state, false),
+        AccountMeta::new_readonly(*arb_state, false),
+        AccountMeta::new(Pda::tvl_snapshot(system_state).0, false),
         AccountMeta::new_readonly(*marinade_state, false),
-        AccountMeta::new(*reward, false),
-        AccountMeta::new_readonly(*staked_lucra_mint, false),
         AccountMeta::new_readonly(*msol_vault, false),
-        AccountMeta::new(*rewards_vault, false),
-        AccountMeta::new(*arb_coffer, false),
-        AccountMeta::new_readonly(msol_vault_transfer_authority, false),
+        AccountMeta::new_readonly(*arb_coffer, false),
+        AccountMeta::new_readonly(*rewards_vault, false),
+        AccountMeta::new_readonly(*wsol_holding_vault, false),
+        AccountMeta::new_readonly(*mata_holding_vault, false),
+        AccountMeta::new_readonly(*lucra_holding_vault, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*lucra_sol_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*user_account, true),
         AccountMeta::new(*user_reward_account, false),
         AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(*reward_mint_authority, false),
+        AccountMeta::new_readonly(Pda::reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::ComputeTvl {};
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn rollover_loan(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    user_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    new_lamports: u64,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new_readonly(Pda::msol_vault_authority(system_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(Pda::mata_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
         AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::DropReward { };
+    let data = Instruction::RolloverLoan { new_lamports };
+
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn transfer_funds(
+pub fn repay_loan_partial(
     system_state: &Pubkey,
-    from_account: &Pubkey,
-    to_account: &Pubkey,
-    transfer_authority: &Pubkey,
-    lamports: u64,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    user_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    mata_mint: &Pubkey,
+    user_mata_account: &Pubkey,
+    msol_vault: &Pubkey,
+    mata: u64,
+    release_collateral: bool,
 ) -> SolInstruction {
+    let msol_vault_authority = Pda::msol_vault_authority(system_state).0;
     let accounts = vec![
         AccountMeta::new(*system_state, false),
-        AccountMeta::new_readonly(DAO_AUTHORITY, true),
-        AccountMeta::new(*from_account, false),
-        AccountMeta::new(*to_account, false),
-        AccountMeta::new_readonly(*transfer_authority, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(msol_vault_authority, false),
+        AccountMeta::new(*msol_vault, false),
         AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::TransferFunds {
-        lamports,
+    let data = Instruction::RepayLoanPartial {
+        mata,
+        release_collateral,
     };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn create_price_history(
-    price_history: &Pubkey,
+pub fn repay_loan_partial_with_locked_stake(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    user_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    mata_mint: &Pubkey,
+    user_mata_account: &Pubkey,
+    msol_vault: &Pubkey,
+    user_staking_account: &Pubkey,
+    mata: u64,
+    release_collateral: bool,
 ) -> SolInstruction {
+    let msol_vault_authority = Pda::msol_vault_authority(system_state).0;
     let accounts = vec![
-        AccountMeta::new_readonly(CREATOR_AUTHORITY, true),
-        AccountMeta::new(*price_history, false),
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_msol_account, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*user_mata_account, false),
+        AccountMeta::new_readonly(msol_vault_authority, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new(*user_staking_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::CreatePriceHistory {};
+    let data = Instruction::RepayLoanPartial {
+        mata,
+        release_collateral,
+    };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn update_price_history(
+pub fn borrow_more(
     system_state: &Pubkey,
-    price_history: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    mata_mint: &Pubkey,
+    user_account: &Pubkey,
+    user_mata_account: &Pubkey,
     sol_usdc_oracle: &Pubkey,
     sol_usdt_oracle: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    user_reward_account: &Pubkey,
-    reward_mint: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    mata: u64,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*price_history, false),
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(Pda::mata_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_mata_account, false),
         AccountMeta::new_readonly(*sol_usdc_oracle, false),
         AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
         AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::UpdatePriceHistory {};
+    let data = Instruction::BorrowMore { mata };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn redeem_reward_tokens(
+pub fn set_liquidation_params(
     system_state: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_lucra_reward_account: &Pubkey,
-    user_authority: &Pubkey,
-    reward_mint: &Pubkey,
-    lucra_mint: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    reward_tokens: u64,
+    liquidation_ratio: CollateralRatio,
+    liquidation_bonus: Bps,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*user_lucra_reward_account, false),
-        AccountMeta::new_readonly(*user_authority, true),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new(*lucra_mint, false),
-        AccountMeta::new_readonly(SystemState::find_lucra_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
     ];
-    let data = Instruction::RedeemRewardTokens { reward_tokens };
+    let data = Instruction::SetLiquidationParams { liquidation_ratio, liquidation_bonus };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn add_collateral(
+pub fn liquidate_loan(
     system_state: &Pubkey,
     marinade_state: &Pubkey,
     loan: &Pubkey,
     msol_vault: &Pubkey,
-    owner: &Pubkey,
-    user_msol_account: &Pubkey,
-    msol_mint: &Pubkey,
-    liq_pool_sol_leg_address: &Pubkey,
-    liq_pool_msol_leg: &Pubkey,
-    liq_pool_msol_leg_authority: &Pubkey,
-    reserve_address: &Pubkey,
-    msol_mint_authority: &Pubkey,
-    lamports: u64,
+    arb_coffer: &Pubkey,
+    mata_mint: &Pubkey,
+    liquidator: &Pubkey,
+    liquidator_mata_account: &Pubkey,
+    liquidator_msol_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
 ) -> SolInstruction {
+    let msol_vault_authority = Pda::msol_vault_authority(system_state).0;
     let accounts = vec![
         AccountMeta::new(*system_state, false),
-        AccountMeta::new(*marinade_state, false),
-
+        AccountMeta::new_readonly(*marinade_state, false),
         AccountMeta::new(*loan, false),
         AccountMeta::new(*msol_vault, false),
-        AccountMeta::new(*owner, true),
-        AccountMeta::new(*user_msol_account, false),
-    
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_address, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new_readonly(*liq_pool_msol_leg_authority, false),
-        AccountMeta::new(*reserve_address, false),
-        AccountMeta::new_readonly(*msol_mint_authority, false),
-    
-        AccountMeta::new_readonly(solana_program::sysvar::fees::id(), false),
-        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(msol_vault_authority, false),
+        AccountMeta::new(*arb_coffer, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(*liquidator, true),
+        AccountMeta::new(*liquidator_mata_account, false),
+        AccountMeta::new(*liquidator_msol_account, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
         AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
     ];
-    let data = Instruction::AddCollateral { lamports };
+    let data = Instruction::LiquidateLoan { amm_type: AmmTypes::None as u8 };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn add_collateral_with_locked_stake(
+pub fn liquidate_loan_with_locked_stake(
     system_state: &Pubkey,
     marinade_state: &Pubkey,
     loan: &Pubkey,
     msol_vault: &Pubkey,
-    owner: &Pubkey,
-    user_msol_account: &Pubkey,
-    staking_account: &Pubkey,
+    arb_coffer: &Pubkey,
+    mata_mint: &Pubkey,
+    liquidator: &Pubkey,
+    liquidator_mata_account: &Pubkey,
+    liquidator_msol_account: &Pubkey,
     sol_usdc_oracle: &Pubkey,
     sol_usdt_oracle: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    msol_mint: &Pubkey,
-    liq_pool_sol_leg_address: &Pubkey,
-    liq_pool_msol_leg: &Pubkey,
-    liq_pool_msol_leg_authority: &Pubkey,
-    reserve_address: &Pubkey,
-    msol_mint_authority: &Pubkey,
-    lamports: u64,
+    owner_staking_account: &Pubkey,
 ) -> SolInstruction {
+    let msol_vault_authority = Pda::msol_vault_authority(system_state).0;
     let accounts = vec![
         AccountMeta::new(*system_state, false),
-        AccountMeta::new(*marinade_state, false),
-
+        AccountMeta::new_readonly(*marinade_state, false),
         AccountMeta::new(*loan, false),
         AccountMeta::new(*msol_vault, false),
-        AccountMeta::new(*owner, true),
-        AccountMeta::new(*user_msol_account, false),
-        AccountMeta::new(*staking_account, false),
-
+        AccountMeta::new_readonly(msol_vault_authority, false),
+        AccountMeta::new(*arb_coffer, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(*liquidator, true),
+        AccountMeta::new(*liquidator_mata_account, false),
+        AccountMeta::new(*liquidator_msol_account, false),
         AccountMeta::new_readonly(*sol_usdc_oracle, false),
         AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-    
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_address, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new_readonly(*liq_pool_msol_leg_authority, false),
-        AccountMeta::new(*reserve_address, false),
-        AccountMeta::new_readonly(*msol_mint_authority, false),
-    
-        AccountMeta::new_readonly(solana_program::sysvar::fees::id(), false),
-        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new(*owner_staking_account, false),
         AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
     ];
-    let data = Instruction::AddCollateral { lamports };
+    let data = Instruction::LiquidateLoan { amm_type: AmmTypes::None as u8 };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
+/// Liquidates a loan and swaps its pending penalty for mata on the orca pool
 #[allow(clippy::too_many_arguments)]
-pub fn determine_penalty(
+pub fn liquidate_loan_with_orca(
     system_state: &Pubkey,
+    marinade_state: &Pubkey,
     loan: &Pubkey,
+    msol_vault: &Pubkey,
+    arb_coffer: &Pubkey,
+    mata_mint: &Pubkey,
+    liquidator: &Pubkey,
+    liquidator_mata_account: &Pubkey,
+    liquidator_msol_account: &Pubkey,
     sol_usdc_oracle: &Pubkey,
     sol_usdt_oracle: &Pubkey,
     sol_mata_oracle: &Pubkey,
-    price_history: &Pubkey,
-    user_reward_account: &Pubkey,
-    reward_mint: &Pubkey,
+    liquidator_wsol_account: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_pda: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    treasury_msol_account: &Pubkey,
+    sm_amm: &Pubkey,
+    sm_amm_authority: &Pubkey,
+    sm_pool_base_vault: &Pubkey,
+    sm_pool_quote_vault: &Pubkey,
+    sm_pool_mint: &Pubkey,
+    sm_pool_fees: &Pubkey,
 ) -> SolInstruction {
-    let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*loan, false),
-
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+    let mut instruction = liquidate_loan(
+        system_state,
+        marinade_state,
+        loan,
+        msol_vault,
+        arb_coffer,
+        mata_mint,
+        liquidator,
+        liquidator_mata_account,
+        liquidator_msol_account,
+        sol_usdc_oracle,
+        sol_usdt_oracle,
+    );
+    // The unstake writes the marinade state and pays the liquidator the reward fee
+    instruction.accounts[1].is_writable = true;
+    instruction.accounts[7].is_writable = true;
+    instruction.accounts.extend([
         AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new_readonly(*price_history, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-    ];
-    let data = Instruction::DeterminePenalty { };
-
-    SolInstruction {
-        program_id: id(),
-        accounts,
-        data: serialize(&data).unwrap(),
-    }
+        AccountMeta::new(*liquidator_wsol_account, false),
+        AccountMeta::new(*msol_mint, false),
+        AccountMeta::new(*liq_pool_sol_leg_pda, false),
+        AccountMeta::new(*liq_pool_msol_leg, false),
+        AccountMeta::new(*treasury_msol_account, false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new(*sm_amm, false),
+        AccountMeta::new_readonly(*sm_amm_authority, false),
+        AccountMeta::new(*sm_pool_base_vault, false),
+        AccountMeta::new(*sm_pool_quote_vault, false),
+        AccountMeta::new(*sm_pool_mint, false),
+        AccountMeta::new(*sm_pool_fees, false),
+        AccountMeta::new_readonly(orca_swap::id(), false),
+    ]);
+    instruction.data = Instruction::LiquidateLoan { amm_type: AmmTypes::Orca as u8 }.pack();
+    instruction
 }
 
+/// Liquidates a lucra backed loan and swaps its pending penalty for mata on the orca pool
 #[allow(clippy::too_many_arguments)]
-pub fn harvest_penalty_with_orca(
+pub fn liquidate_loan_with_locked_stake_with_orca(
     system_state: &Pubkey,
     marinade_state: &Pubkey,
+    loan: &Pubkey,
     msol_vault: &Pubkey,
+    arb_coffer: &Pubkey,
     mata_mint: &Pubkey,
-    loan: &Pubkey,
-    user_account: &Pubkey,
-    user_wsol_account: &Pubkey,
-    user_mata_account: &Pubkey,
-    user_msol_account: &Pubkey,
+    liquidator: &Pubkey,
+    liquidator_mata_account: &Pubkey,
+    liquidator_msol_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    owner_staking_account: &Pubkey,
     sol_mata_oracle: &Pubkey,
+    liquidator_wsol_account: &Pubkey,
     msol_mint: &Pubkey,
     liq_pool_sol_leg_pda: &Pubkey,
     liq_pool_msol_leg: &Pubkey,
@@ -1881,1142 +5876,978 @@ pub fn harvest_penalty_with_orca(
     sm_pool_mint: &Pubkey,
     sm_pool_fees: &Pubkey,
 ) -> SolInstruction {
-    let accounts = vec![
-        AccountMeta::new(*system_state, false),
-        AccountMeta::new(*marinade_state, false),
-        AccountMeta::new(*msol_vault, false),
-        AccountMeta::new_readonly(SystemState::find_msol_vault_authority(system_state).0, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*loan, false),
+    let mut instruction = liquidate_loan_with_locked_stake(
+        system_state,
+        marinade_state,
+        loan,
+        msol_vault,
+        arb_coffer,
+        mata_mint,
+        liquidator,
+        liquidator_mata_account,
+        liquidator_msol_account,
+        sol_usdc_oracle,
+        sol_usdt_oracle,
+        owner_staking_account,
+    );
+    // The unstake writes the marinade state and pays the liquidator the reward fee
+    instruction.accounts[1].is_writable = true;
+    instruction.accounts[7].is_writable = true;
+    instruction.accounts.extend([
         AccountMeta::new_readonly(*sol_mata_oracle, false),
-
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new(*user_mata_account, false),
-        AccountMeta::new(*user_msol_account, false),
-        
+        AccountMeta::new(*liquidator_wsol_account, false),
         AccountMeta::new(*msol_mint, false),
         AccountMeta::new(*liq_pool_sol_leg_pda, false),
         AccountMeta::new(*liq_pool_msol_leg, false),
         AccountMeta::new(*treasury_msol_account, false),
         AccountMeta::new_readonly(solana_program::system_program::id(), false),
         AccountMeta::new_readonly(marinade_finance::id(), false),
-        
         AccountMeta::new(*sm_amm, false),
         AccountMeta::new_readonly(*sm_amm_authority, false),
         AccountMeta::new(*sm_pool_base_vault, false),
         AccountMeta::new(*sm_pool_quote_vault, false),
         AccountMeta::new(*sm_pool_mint, false),
         AccountMeta::new(*sm_pool_fees, false),
-        
         AccountMeta::new_readonly(orca_swap::id(), false),
+    ]);
+    instruction.data = Instruction::LiquidateLoan { amm_type: AmmTypes::Orca as u8 }.pack();
+    instruction
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn liquidate_lp_backed_loan(
+    system_state: &Pubkey,
+    loan: &Pubkey,
+    lp_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    liquidator: &Pubkey,
+    liquidator_mata_account: &Pubkey,
+    liquidator_lp_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    pool_sol_vault: &Pubkey,
+    pool_mata_vault: &Pubkey,
+    lp_mint: &Pubkey,
+    liquidator_wsol_account: &Pubkey,
+    pool_authority: &Pubkey,
+    pool_fees: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(SOL_MATA_ORCA_AMM, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*lp_vault, false),
+        AccountMeta::new_readonly(Pda::lp_vault_authority(system_state).0, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new_readonly(*liquidator, true),
+        AccountMeta::new(*liquidator_mata_account, false),
+        AccountMeta::new(*liquidator_lp_account, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
+        AccountMeta::new_readonly(*sol_mata_oracle, false),
+        AccountMeta::new(*pool_sol_vault, false),
+        AccountMeta::new(*pool_mata_vault, false),
+        AccountMeta::new(*lp_mint, false),
         AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*liquidator_wsol_account, false),
+        AccountMeta::new_readonly(*pool_authority, false),
+        AccountMeta::new(*pool_fees, false),
+        AccountMeta::new_readonly(orca_swap::id(), false),
     ];
-
-    let data = Instruction::HarvestPenalty { amm_type: AmmTypes::Orca as u8 };
+    let data = Instruction::LiquidateLoan { amm_type: AmmTypes::Orca as u8 };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn harvest_penalty_with_raydium(
+pub fn liquidate_native_sol_loan(
     system_state: &Pubkey,
-    marinade_state: &Pubkey,
-    msol_vault: &Pubkey,
-    mata_mint: &Pubkey,
     loan: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-    msol_mint: &Pubkey,
-    liq_pool_sol_leg_pda: &Pubkey,
-    liq_pool_msol_leg: &Pubkey,
-    treasury_msol_account: &Pubkey,
-    user_msol_account: &Pubkey,
-    user_account: &Pubkey,
-    user_lucra_account: &Pubkey,
-    user_wsol_account: &Pubkey,
-    pool_lucra_account: &Pubkey,
-    pool_wsol_account: &Pubkey,
-    amm_program: &Pubkey,
-    amm_authority: &Pubkey,
-    amm_open_orders: &Pubkey,
-    amm_target: &Pubkey,
-    serum_lucra_sol_market: &Pubkey,
-    serum_bids: &Pubkey,
-    serum_asks: &Pubkey,
-    serum_event_queue: &Pubkey,
-    serum_base_vault: &Pubkey,
-    serum_quote_vault: &Pubkey,
-    serum_vault_signer: &Pubkey,
+    mata_mint: &Pubkey,
+    liquidator: &Pubkey,
+    liquidator_mata_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
 ) -> SolInstruction {
     let accounts = vec![
         AccountMeta::new(*system_state, false),
-        AccountMeta::new(*marinade_state, false),
-        AccountMeta::new(*msol_vault, false),
-        AccountMeta::new_readonly(SystemState::find_msol_vault_authority(system_state).0, false),
-        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(Pda::sol_vault(system_state).0, false),
         AccountMeta::new(*loan, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-
-        AccountMeta::new(*user_msol_account, false),
-
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_pda, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new(*treasury_msol_account, false),
-        AccountMeta::new_readonly(solana_program::system_program::id(), false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
-
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_lucra_account, false),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new_readonly(raydium_v4::id(), false),
-        AccountMeta::new(*pool_lucra_account, false),
-        AccountMeta::new(*pool_wsol_account, false),
+        AccountMeta::new(*mata_mint, false),
+        AccountMeta::new(*liquidator, true),
+        AccountMeta::new(*liquidator_mata_account, false),
+        AccountMeta::new_readonly(*sol_usdc_oracle, false),
+        AccountMeta::new_readonly(*sol_usdt_oracle, false),
         AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new(*amm_program, false),
-        AccountMeta::new_readonly(*amm_authority, false),
-        AccountMeta::new(*amm_open_orders, false),
-        AccountMeta::new(*amm_target, false),
-        AccountMeta::new(*serum_lucra_sol_market, false),
-        AccountMeta::new_readonly(serum_v3::id(), false),
-        AccountMeta::new(*serum_bids, false),
-        AccountMeta::new(*serum_asks, false),
-        AccountMeta::new(*serum_event_queue, false),
-        AccountMeta::new(*serum_base_vault, false),
-        AccountMeta::new(*serum_quote_vault, false),
-        AccountMeta::new_readonly(*serum_vault_signer, false),
     ];
+    let data = Instruction::LiquidateLoan { amm_type: AmmTypes::None as u8 };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
 
-    let data = Instruction::HarvestPenalty { amm_type: AmmTypes::Raydium as u8 };
+pub fn transfer_loan_ownership(
+    loan: &Pubkey,
+    current_owner: &Pubkey,
+    new_owner: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*current_owner, true),
+        AccountMeta::new_readonly(*new_owner, true),
+    ];
+    let data = Instruction::TransferLoanOwnership { };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn sell_lucra_for_arb_funds_using_raydium(
+pub fn transfer_loan_ownership_with_locked_stake(
+    loan: &Pubkey,
+    current_owner: &Pubkey,
+    new_owner: &Pubkey,
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    lucra_holding_vault: &Pubkey,
-    reward_mint: &Pubkey,
-
+    current_staking_account: &Pubkey,
+    new_staking_account: &Pubkey,
     sol_usdc_oracle: &Pubkey,
     sol_usdt_oracle: &Pubkey,
     lucra_sol_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_lucra_account: &Pubkey,
-    user_wsol_account: &Pubkey,
-    pool_lucra_account: &Pubkey,
-    pool_wsol_account: &Pubkey,
-    amm_program: &Pubkey,
-    amm_authority: &Pubkey,
-    amm_open_orders: &Pubkey,
-    amm_target: &Pubkey,
-    serum_lucra_sol_market: &Pubkey,
-    serum_bids: &Pubkey,
-    serum_asks: &Pubkey,
-    serum_event_queue: &Pubkey,
-    serum_base_vault: &Pubkey,
-    serum_quote_vault: &Pubkey,
-    serum_vault_signer: &Pubkey,
 ) -> SolInstruction {
     let accounts = vec![
+        AccountMeta::new(*loan, false),
+        AccountMeta::new_readonly(*current_owner, true),
+        AccountMeta::new_readonly(*new_owner, true),
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new(*lucra_holding_vault, false),
-        AccountMeta::new_readonly(ArbState::find_lucra_holding_vault_authority(arb_state).0, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
+        AccountMeta::new(*current_staking_account, false),
+        AccountMeta::new(*new_staking_account, false),
         AccountMeta::new_readonly(*sol_usdc_oracle, false),
         AccountMeta::new_readonly(*sol_usdt_oracle, false),
         AccountMeta::new_readonly(*lucra_sol_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_lucra_account, false),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new_readonly(raydium_v4::id(), false),
-        AccountMeta::new(*pool_lucra_account, false),
-        AccountMeta::new(*pool_wsol_account, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new(*amm_program, false),
-        AccountMeta::new_readonly(*amm_authority, false),
-        AccountMeta::new(*amm_open_orders, false),
-        AccountMeta::new(*amm_target, false),
-        AccountMeta::new(*serum_lucra_sol_market, false),
-        AccountMeta::new_readonly(serum_v3::id(), false),
-        AccountMeta::new(*serum_bids, false),
-        AccountMeta::new(*serum_asks, false),
-        AccountMeta::new(*serum_event_queue, false),
-        AccountMeta::new(*serum_base_vault, false),
-        AccountMeta::new(*serum_quote_vault, false),
-        AccountMeta::new_readonly(*serum_vault_signer, false),
     ];
-    let data = Instruction::SellFundsForArb { 
-        fund_source: CurrencyTypes::Lucra as u8, 
-        amm_type: AmmTypes::Raydium as u8,
-        lamports: 0,
-    };
+    let data = Instruction::TransferLoanOwnership { };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn sell_mata_for_arb_funds_using_raydium(
+pub fn close_stake_balance(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    mata_holding_vault: &Pubkey,
-    mata_mint: &Pubkey,
-    wsol_mint: &Pubkey,
-    reward_mint: &Pubkey,
+    stake_balance: &Pubkey,
+    deposit_vault: &Pubkey,
+    stake_vault: &Pubkey,
+    pending_vault: &Pubkey,
+    owner: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(*stake_balance, false),
+        AccountMeta::new(*deposit_vault, false),
+        AccountMeta::new(*stake_vault, false),
+        AccountMeta::new(*pending_vault, false),
+        AccountMeta::new(*owner, true),
+        AccountMeta::new_readonly(Pda::stake_balance_authority(owner, system_state).0, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let data = Instruction::CloseStakeBalance { };
 
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
 
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_wsol_account: &Pubkey,
-    user_mata_account: &Pubkey,
-    pool_wsol_account: &Pubkey,
-    pool_mata_account: &Pubkey,
-    amm_program: &Pubkey,
-    amm_authority: &Pubkey,
-    amm_open_orders: &Pubkey,
-    amm_target: &Pubkey,
-    serum_sol_mata_market: &Pubkey,
-    serum_bids: &Pubkey,
-    serum_asks: &Pubkey,
-    serum_event_queue: &Pubkey,
-    serum_base_vault: &Pubkey,
-    serum_quote_vault: &Pubkey,
-    serum_vault_signer: &Pubkey,
+pub fn close_staking_account(
+    staking_account: &Pubkey,
+    owner: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*staking_account, false),
+        AccountMeta::new(*owner, true),
+    ];
+    let data = Instruction::CloseStakingAccount { };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn cancel_unstake(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    staking_account: &Pubkey,
+    stake_balance: &Pubkey,
+    pending_withdrawal: &Pubkey,
+    pending_vault: &Pubkey,
+    stake_vault: &Pubkey,
+    owner: &Pubkey,
+    staked_lucra_account: &Pubkey,
+    staked_lucra_mint: &Pubkey,
 ) -> SolInstruction {
+    let staked_lucra_mint_authority = Pda::stake_mint_authority(staking_state).0;
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new(*mata_holding_vault, false),
-        AccountMeta::new_readonly(ArbState::find_mata_holding_vault_authority(arb_state).0, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*wsol_mint, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new(*user_mata_account, false),
-        AccountMeta::new_readonly(raydium_v4::id(), false),
-        AccountMeta::new(*pool_wsol_account, false),
-        AccountMeta::new(*pool_mata_account, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new(*amm_program, false),
-        AccountMeta::new_readonly(*amm_authority, false),
-        AccountMeta::new(*amm_open_orders, false),
-        AccountMeta::new(*amm_target, false),
-        AccountMeta::new(*serum_sol_mata_market, false),
-        AccountMeta::new_readonly(serum_v3::id(), false),
-        AccountMeta::new(*serum_bids, false),
-        AccountMeta::new(*serum_asks, false),
-        AccountMeta::new(*serum_event_queue, false),
-        AccountMeta::new(*serum_base_vault, false),
-        AccountMeta::new(*serum_quote_vault, false),
-        AccountMeta::new_readonly(*serum_vault_signer, false),
+        AccountMeta::new(*staking_state, false),
+        AccountMeta::new(*staking_account, false),
+        AccountMeta::new(*stake_balance, false),
+        AccountMeta::new(*pending_withdrawal, false),
+        AccountMeta::new(*pending_vault, false),
+        AccountMeta::new(*stake_vault, false),
+        AccountMeta::new(*owner, true),
+        AccountMeta::new_readonly(Pda::stake_balance_authority(owner, system_state).0, false),
+        AccountMeta::new(*staked_lucra_mint, false),
+        AccountMeta::new(*staked_lucra_account, false),
+        AccountMeta::new_readonly(staked_lucra_mint_authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::SellFundsForArb { 
-        fund_source: CurrencyTypes::Mata as u8, 
-        amm_type: AmmTypes::Raydium as u8,
-        lamports: 0,
-    };
+    let data = Instruction::CancelUnstake { };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn sell_lucra_for_arb_funds_using_orca(
+pub fn propose_authority(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    lucra_holding_vault: &Pubkey,
-    reward_mint: &Pubkey,
+    dao_authority: &Pubkey,
+    new_authority: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*dao_authority, true),
+    ];
+    let data = Instruction::ProposeAuthority { new_authority: *new_authority };
 
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
 
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_lucra_account: &Pubkey,
-    user_wsol_account: &Pubkey,
-    ls_amm: &Pubkey,
-    ls_amm_authority: &Pubkey,
-    ls_pool_base_vault: &Pubkey,
-    ls_pool_quote_vault: &Pubkey,
-    ls_pool_mint: &Pubkey,
-    ls_pool_fees: &Pubkey,
-    sm_amm: &Pubkey,
-    sm_pool_base_vault: &Pubkey,
-    sm_pool_quote_vault: &Pubkey,
-    lamports: u64,
+pub fn accept_authority(
+    system_state: &Pubkey,
+    new_authority: &Pubkey,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new(*lucra_holding_vault, false),
-        AccountMeta::new_readonly(ArbState::find_lucra_holding_vault_authority(arb_state).0, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new_readonly(*user_account, true),
-        AccountMeta::new(*user_lucra_account, false),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new_readonly(*ls_amm, false),
-        AccountMeta::new_readonly(*ls_amm_authority, false),
-        AccountMeta::new(*ls_pool_base_vault, false),
-        AccountMeta::new(*ls_pool_quote_vault, false),
-        AccountMeta::new(*ls_pool_mint, false),
-        AccountMeta::new(*ls_pool_fees, false),
-        AccountMeta::new_readonly(*sm_amm, false),
-        AccountMeta::new_readonly(*sm_pool_base_vault, false),
-        AccountMeta::new_readonly(*sm_pool_quote_vault, false),
-        AccountMeta::new_readonly(orca_swap::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*new_authority, true),
     ];
-    let data = Instruction::SellFundsForArb { 
-        fund_source: CurrencyTypes::Lucra as u8, 
-        amm_type: AmmTypes::Orca as u8,
-        lamports,
-    };
+    let data = Instruction::AcceptAuthority { };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn sell_mata_for_arb_funds_using_orca(
+/// `authority` is the DAO authority or the guardian
+pub fn set_pause(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    mata_holding_vault: &Pubkey,
-    mata_mint: &Pubkey,
-    wsol_mint: &Pubkey,
-    reward_mint: &Pubkey,
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_wsol_account: &Pubkey,
-    user_mata_account: &Pubkey,
-    sm_amm: &Pubkey,
-    sm_amm_authority: &Pubkey,
-    sm_pool_base_vault: &Pubkey,
-    sm_pool_quote_vault: &Pubkey,
-    sm_pool_mint: &Pubkey,
-    sm_pool_fees: &Pubkey,
-    lamports: u64,
+    authority: &Pubkey,
+    loans: bool,
+    staking: bool,
+    arbitrage: bool,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new(*mata_holding_vault, false),
-        AccountMeta::new_readonly(ArbState::find_mata_holding_vault_authority(arb_state).0, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*wsol_mint, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new_readonly(*user_account, true),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new(*user_mata_account, false),
-        AccountMeta::new_readonly(*sm_amm, false),
-        AccountMeta::new_readonly(*sm_amm_authority, false),
-        AccountMeta::new(*sm_pool_base_vault, false),
-        AccountMeta::new(*sm_pool_quote_vault, false),
-        AccountMeta::new(*sm_pool_mint, false),
-        AccountMeta::new(*sm_pool_fees, false),
-        AccountMeta::new_readonly(orca_swap::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*authority, true),
     ];
-    let data = Instruction::SellFundsForArb { 
-        fund_source: CurrencyTypes::Mata as u8, 
-        amm_type: AmmTypes::Orca as u8,
-        lamports,
-    };
+    let data = Instruction::SetPause { loans, staking, arbitrage };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn mint_mata_for_arb_funds_checking_raydium(
+pub fn withdraw_collateral(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    mata_holding_vault: &Pubkey,
-    mata_mint: &Pubkey,
-    reward_mint: &Pubkey,
-
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    user_account: &Pubkey,
+    user_msol_account: &Pubkey,
     sol_usdc_oracle: &Pubkey,
     sol_usdt_oracle: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
     sol_mata_oracle: &Pubkey,
-
-    sm_base_vault: &Pubkey,
-    sm_base_mint: &Pubkey,
-    sm_quote_vault: &Pubkey,
-    sm_amm_open_orders: &Pubkey,
-    sm_amm_program: &Pubkey,
-
-    user_reward_account: &Pubkey,
-
-    mata: u64,
+    lamports: u64,
 ) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new_readonly(*arb_fund, false),
-        AccountMeta::new(*mata_holding_vault, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new_readonly(SystemState::find_mata_mint_authority(system_state).0, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(*marinade_state, false),
+        AccountMeta::new(*loan, false),
+        AccountMeta::new(*msol_vault, false),
+        AccountMeta::new_readonly(Pda::msol_vault_authority(system_state).0, false),
+        AccountMeta::new_readonly(*user_account, true),
+        AccountMeta::new(*user_msol_account, false),
         AccountMeta::new_readonly(*sol_usdc_oracle, false),
         AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
         AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new_readonly(*sm_base_vault, false),
-        AccountMeta::new_readonly(*sm_base_mint, false),
-        AccountMeta::new_readonly(*sm_quote_vault, false),
-        AccountMeta::new_readonly(*sm_amm_open_orders, false),
-        AccountMeta::new_readonly(*sm_amm_program, false),
-        AccountMeta::new(*user_reward_account, false),
         AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::MintFundsForArb { 
-        fund_source: CurrencyTypes::Mata as u8, 
-        amm_type: AmmTypes::Raydium as u8,
-        lamports: mata,
-    };
+    let data = Instruction::WithdrawCollateral { lamports };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn mint_lucra_for_arb_funds_checking_raydium(
-    system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    arb_coffer: &Pubkey,
-    lucra_holding_vault: &Pubkey,
-    lucra_mint: &Pubkey,
-    reward_mint: &Pubkey,
+pub fn migrate_stake_balance(
+    staking_state: &Pubkey,
+    stake_balance: &Pubkey,
+    stake_vault: &Pubkey,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*staking_state, false),
+        AccountMeta::new(*stake_balance, false),
+        AccountMeta::new_readonly(*stake_vault, false),
+    ];
+    let data = Instruction::MigrateStakeBalance { };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
 
+pub fn quote_mata_loan(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
     sol_usdc_oracle: &Pubkey,
     sol_usdt_oracle: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
     sol_mata_oracle: &Pubkey,
-    
-    sm_raydium_base_vault: &Pubkey,
-    sm_raydium_base_mint: &Pubkey,
-    sm_raydium_quote_vault: &Pubkey,
-    sm_raydium_quote_mint: &Pubkey,
-    sm_raydium_amm_open_orders: &Pubkey,
-    sm_raydium_amm: &Pubkey,
-
-    sm_orca_base_vault: &Pubkey,
-    sm_orca_quote_vault: &Pubkey,
-    sm_orca_amm: &Pubkey,
-
-    user_reward_account: &Pubkey,
-
-    marinade_state: &Pubkey,
-
     lamports: u64,
 ) -> SolInstruction {
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new_readonly(*arb_fund, false),
-        AccountMeta::new_readonly(*arb_coffer, false),
-        AccountMeta::new(*lucra_holding_vault, false),
-        AccountMeta::new(*lucra_mint, false),
-        AccountMeta::new_readonly(SystemState::find_lucra_mint_authority(system_state).0, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
+        AccountMeta::new_readonly(*marinade_state, false),
         AccountMeta::new_readonly(*sol_usdc_oracle, false),
         AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
         AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new_readonly(*sm_raydium_base_vault, false),
-        AccountMeta::new_readonly(*sm_raydium_base_mint, false),
-        AccountMeta::new_readonly(*sm_raydium_quote_vault, false),
-        AccountMeta::new_readonly(*sm_raydium_quote_mint, false),
-        AccountMeta::new_readonly(*sm_raydium_amm_open_orders, false),
-        AccountMeta::new_readonly(*sm_raydium_amm, false),
-        AccountMeta::new_readonly(*sm_orca_base_vault, false),
-        AccountMeta::new_readonly(*sm_orca_quote_vault, false),
-        AccountMeta::new_readonly(*sm_orca_amm, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new_readonly(*marinade_state, false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::MintFundsForArb { 
-        fund_source: CurrencyTypes::Lucra as u8, 
-        amm_type: AmmTypes::Raydium as u8,
-        lamports,
-    };
+    let data = Instruction::QuoteMataLoan { lamports };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn create_penalty_history(loan: &Pubkey, payer: &Pubkey) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*loan, false),
+        AccountMeta::new(Pda::penalty_history(loan).0, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+    let data = Instruction::CreatePenaltyHistory { };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+pub fn create_event_queue(system_state: &Pubkey) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(Pda::event_queue(system_state).0, false),
+        AccountMeta::new(CREATOR_AUTHORITY, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+    let data = Instruction::CreateEventQueue { };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn mint_mata_for_arb_funds_checking_orca(
+pub fn claim_reward_v2(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    mata_holding_vault: &Pubkey,
-    mata_mint: &Pubkey,
-    reward_mint: &Pubkey,
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-    sm_amm: &Pubkey,
-    sm_base_vault: &Pubkey,
-    sm_base_mint: &Pubkey,
-    sm_quote_vault: &Pubkey,
-    user_reward_account: &Pubkey,
-    mata: u64,
+    staking_state: &Pubkey,
+    staking_account: &Pubkey,
+    stake_balance: &Pubkey,
+    reward: &Pubkey,
+    staked_lucra_account: &Pubkey,
+    lucra_vault: &Pubkey,
+    lucra_account: &Pubkey,
+    rewards_vault: &Pubkey,
+    msol_account: &Pubkey,
+    lucra_mint: &Pubkey,
+    staked_lucra_mint: &Pubkey,
+    compound: bool,
 ) -> SolInstruction {
+    let rewards_vault_authority = Pda::rewards_vault_authority(system_state).0;
+    let lucra_mint_authority = Pda::lucra_mint_authority(system_state).0;
+    let stake_mint_authority = Pda::stake_mint_authority(staking_state).0;
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new_readonly(*arb_fund, false),
-        AccountMeta::new(*mata_holding_vault, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new_readonly(SystemState::find_mata_mint_authority(system_state).0, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new_readonly(*sm_amm, false),
-        AccountMeta::new_readonly(*sm_base_vault, false),
-        AccountMeta::new_readonly(*sm_base_mint, false),
-        AccountMeta::new_readonly(*sm_quote_vault, false),
-        AccountMeta::new(*user_reward_account, false),
+        AccountMeta::new(*staking_state, false),
+        AccountMeta::new(*staking_account, false),
+        AccountMeta::new(*stake_balance, false),
+        AccountMeta::new(*reward, false),
+        AccountMeta::new(*staked_lucra_account, false),
+        AccountMeta::new(*lucra_vault, false),
+        AccountMeta::new(*lucra_account, false),
+        AccountMeta::new(*rewards_vault, false),
+        AccountMeta::new(*msol_account, false),
+        AccountMeta::new_readonly(rewards_vault_authority, false),
+        AccountMeta::new(*lucra_mint, false),
+        AccountMeta::new_readonly(lucra_mint_authority, false),
         AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*staked_lucra_mint, false),
+        AccountMeta::new_readonly(stake_mint_authority, false),
     ];
-    let data = Instruction::MintFundsForArb { 
-        fund_source: CurrencyTypes::Mata as u8, 
-        amm_type: AmmTypes::Orca as u8,
-        lamports: mata,
-    };
+    let data = Instruction::ClaimRewardV2 { compound };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn mint_lucra_for_arb_funds_checking_orca(
+pub fn update_harvest_params(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    arb_coffer: &Pubkey,
-    lucra_holding_vault: &Pubkey,
-    lucra_mint: &Pubkey,
-    reward_mint: &Pubkey,
+    minimum_harvest_amount: Option<Lamports>,
+    reward_fee: Option<RewardFee>,
+) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+    ];
+    let data = Instruction::UpdateHarvestParams { minimum_harvest_amount, reward_fee };
 
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-    
-    sm_orca_amm: &Pubkey,
-    sm_orca_base_vault: &Pubkey,
-    sm_orca_base_mint: &Pubkey,
-    sm_orca_quote_vault: &Pubkey,
-    sm_orca_quote_mint: &Pubkey,
-    
-    sm_raydium_open_orders: &Pubkey,
-    sm_raydium_base_vault: &Pubkey,
-    sm_raydium_quote_vault: &Pubkey,
-    sm_raydium_amm: &Pubkey,
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
 
-    user_reward_account: &Pubkey,
+pub fn create_sol_vault(system_state: &Pubkey) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new(*system_state, false),
+        AccountMeta::new(Pda::sol_vault(system_state).0, false),
+        AccountMeta::new(CREATOR_AUTHORITY, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+    ];
+    let data = Instruction::CreateSolVault { };
 
-    marinade_state: &Pubkey,
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
 
-    lamports: u64
-) -> SolInstruction {
+/// `page_index` has to be the header's page count, 0 for the first page
+pub fn create_price_history_page(price_history: &Pubkey, page_index: u64, payer: &Pubkey) -> SolInstruction {
     let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new_readonly(*arb_fund, false),
-        AccountMeta::new_readonly(*arb_coffer, false),
-        AccountMeta::new(*lucra_holding_vault, false),
-        AccountMeta::new(*lucra_mint, false),
-        AccountMeta::new_readonly(SystemState::find_lucra_mint_authority(system_state).0, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new_readonly(*sm_orca_amm, false),
-        AccountMeta::new_readonly(*sm_orca_base_vault, false),
-        AccountMeta::new_readonly(*sm_orca_base_mint, false),
-        AccountMeta::new_readonly(*sm_orca_quote_vault, false),
-        AccountMeta::new_readonly(*sm_orca_quote_mint, false),
-        AccountMeta::new_readonly(*sm_raydium_open_orders, false),
-        AccountMeta::new_readonly(*sm_raydium_base_vault, false),
-        AccountMeta::new_readonly(*sm_raydium_quote_vault, false),
-        AccountMeta::new_readonly(*sm_raydium_amm, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new_readonly(*marinade_state, false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-
+        AccountMeta::new(*price_history, false),
+        AccountMeta::new(Pda::price_history_header(price_history).0, false),
+        AccountMeta::new(Pda::price_history_page(price_history, page_index).0, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
     ];
-    let data = Instruction::MintFundsForArb { 
-        fund_source: CurrencyTypes::Lucra as u8, 
-        amm_type: AmmTypes::Orca as u8,
-        lamports,
-    };
+    let data = Instruction::CreatePriceHistoryPage { };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn transfer_msol_for_arb_funds(
-    system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_coffer: &Pubkey,
-    arb_fund: &Pubkey,
-    mata_mint: &Pubkey,
-    wsol_mint: &Pubkey,
-    reward_mint: &Pubkey,
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-    sm_raydium_base_vault: &Pubkey,
-    sm_raydium_quote_vault: &Pubkey,
-    sm_raydium_amm_open_orders: &Pubkey,
-    sm_raydium_amm: &Pubkey,
-    sm_orca_base_vault: &Pubkey,
-    sm_orca_quote_vault: &Pubkey,
-    sm_orca_amm: &Pubkey,
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_msol_account: &Pubkey,
-    msol_mint: &Pubkey,
-    liq_pool_sol_leg_pda: &Pubkey,
-    liq_pool_msol_leg: &Pubkey,
-    treasury_msol_account: &Pubkey,
-    marinade_state: &Pubkey,
-    lamports: u64,
-) -> SolInstruction {
+/// `payer` funds the registry when it's the first keeper
+pub fn register_keeper(system_state: &Pubkey, keeper: &Pubkey, multiplier: Bps, payer: &Pubkey) -> SolInstruction {
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_coffer, false),
-        AccountMeta::new_readonly(SystemState::find_arb_coffer_authority(system_state).0, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*wsol_mint, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new_readonly(*sm_raydium_base_vault, false),
-        AccountMeta::new_readonly(*sm_raydium_quote_vault, false),
-        AccountMeta::new_readonly(*sm_raydium_amm_open_orders, false),
-        AccountMeta::new_readonly(*sm_raydium_amm, false),
-        AccountMeta::new_readonly(*sm_orca_base_vault, false),
-        AccountMeta::new_readonly(*sm_orca_quote_vault, false),
-        AccountMeta::new_readonly(*sm_orca_amm, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_msol_account, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_pda, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new(*treasury_msol_account, false),
-        AccountMeta::new(*marinade_state, false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new(Pda::keeper_registry(system_state).0, false),
+        AccountMeta::new_readonly(*keeper, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+        AccountMeta::new(*payer, true),
         AccountMeta::new_readonly(solana_program::system_program::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::MintFundsForArb { 
-        fund_source: CurrencyTypes::Msol as u8, 
-        amm_type: AmmTypes::None as u8,
-        lamports,
-    };
+    let data = Instruction::RegisterKeeper { multiplier };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn transfer_msol_for_arb_funds_checking_orca(
+pub fn remove_keeper(system_state: &Pubkey, keeper: &Pubkey) -> SolInstruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_state, false),
+        AccountMeta::new(Pda::keeper_registry(system_state).0, false),
+        AccountMeta::new_readonly(*keeper, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+    ];
+    let data = Instruction::RemoveKeeper { };
+
+    SolInstruction {
+        program_id: id(),
+        accounts,
+        data: data.pack(),
+    }
+}
+
+/// `payer` funds the registry when it's the first collateral type
+pub fn add_collateral_type(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_coffer: &Pubkey,
-    arb_fund: &Pubkey,
-    mata_mint: &Pubkey,
-    reward_mint: &Pubkey,
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-    sm_base_vault: &Pubkey,
-    sm_base_mint: &Pubkey,
-    sm_quote_vault: &Pubkey,
-    sm_amm: &Pubkey,
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_msol_account: &Pubkey,
-    msol_mint: &Pubkey,
-    liq_pool_sol_leg_pda: &Pubkey,
-    liq_pool_msol_leg: &Pubkey,
-    treasury_msol_account: &Pubkey,
-    marinade_state: &Pubkey,
-    lamports: u64,
+    mint: &Pubkey,
+    oracle: &Pubkey,
+    vault: &Pubkey,
+    haircut_bps: Bps,
+    payer: &Pubkey,
 ) -> SolInstruction {
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_coffer, false),
-        AccountMeta::new_readonly(SystemState::find_arb_coffer_authority(system_state).0, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new_readonly(*sm_base_vault, false),
-        AccountMeta::new_readonly(*sm_base_mint, false),
-        AccountMeta::new_readonly(*sm_quote_vault, false),
-        AccountMeta::new_readonly(*sm_amm, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_msol_account, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_pda, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new(*treasury_msol_account, false),
-        AccountMeta::new(*marinade_state, false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
+        AccountMeta::new(Pda::collateral_registry(system_state).0, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(*oracle, false),
+        AccountMeta::new_readonly(*vault, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+        AccountMeta::new(*payer, true),
         AccountMeta::new_readonly(solana_program::system_program::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
     ];
-    let data = Instruction::MintFundsForArb { 
-        fund_source: CurrencyTypes::Msol as u8, 
-        amm_type: AmmTypes::Orca as u8,
-        lamports,
-    };
+    let data = Instruction::AddCollateralType { haircut_bps };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn transfer_msol_for_arb_funds_checking_raydium(
-    system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_coffer: &Pubkey,
-    arb_fund: &Pubkey,
-    mata_mint: &Pubkey,
-    reward_mint: &Pubkey,
-    sol_usdc_oracle: &Pubkey,
-    sol_usdt_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-    sm_base_vault: &Pubkey,
-    sm_base_mint: &Pubkey,
-    sm_quote_vault: &Pubkey,
-    sm_amm_open_orders: &Pubkey,
-    sm_amm: &Pubkey,
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_msol_account: &Pubkey,
-    msol_mint: &Pubkey,
-    liq_pool_sol_leg_pda: &Pubkey,
-    liq_pool_msol_leg: &Pubkey,
-    treasury_msol_account: &Pubkey,
-    marinade_state: &Pubkey,
-    lamports: u64,
-) -> SolInstruction {
+pub fn set_collateral_type(system_state: &Pubkey, collateral_type: u8, oracle: &Pubkey, haircut_bps: Bps, enabled: bool) -> SolInstruction {
     let accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_coffer, false),
-        AccountMeta::new_readonly(SystemState::find_arb_coffer_authority(system_state).0, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new_readonly(*sm_base_vault, false),
-        AccountMeta::new_readonly(*sm_base_mint, false),
-        AccountMeta::new_readonly(*sm_quote_vault, false),
-        AccountMeta::new_readonly(*sm_amm_open_orders, false),
-        AccountMeta::new_readonly(*sm_amm, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_msol_account, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_pda, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new(*treasury_msol_account, false),
-        AccountMeta::new(*marinade_state, false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
-        AccountMeta::new_readonly(solana_program::system_program::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(Pda::collateral_registry(system_state).0, false),
+        AccountMeta::new_readonly(*oracle, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
     ];
-    let data = Instruction::MintFundsForArb { 
-        fund_source: CurrencyTypes::Msol as u8, 
-        amm_type: AmmTypes::Raydium as u8,
-        lamports,
-    };
+    let data = Instruction::SetCollateralType { collateral_type, haircut_bps, enabled };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn spend_arb_funds_for_lucra_using_raydium(
+/// `unswept_rewards` is every reward of the staking state that hasn't been swept, newest first
+pub fn rebalance_rewards_vault(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    lucra_mint: &Pubkey,
-    reward_mint: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    wsol_holding_vault: &Pubkey,
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_lucra_account: &Pubkey,
-    user_wsol_account: &Pubkey,
-    pool_lucra_account: &Pubkey,
-    pool_wsol_account: &Pubkey,
-    amm_program: &Pubkey,
-    amm_authority: &Pubkey,
-    amm_open_orders: &Pubkey,
-    amm_target: &Pubkey,
-    serum_lucra_sol_market: &Pubkey,
-    serum_bids: &Pubkey,
-    serum_asks: &Pubkey,
-    serum_event_queue: &Pubkey,
-    serum_base_vault: &Pubkey,
-    serum_quote_vault: &Pubkey,
-    serum_vault_signer: &Pubkey,
+    staking_state: &Pubkey,
+    rewards_vault: &Pubkey,
+    arb_coffer: &Pubkey,
+    unswept_rewards: &[Pubkey],
+    lamports: u64,
 ) -> SolInstruction {
-    let accounts = vec![
+    let mut accounts = vec![
         AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new_readonly(ArbState::find_arb_fund_authority(arb_state).0, false),
-        AccountMeta::new(*lucra_mint, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-        AccountMeta::new(*wsol_holding_vault, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_lucra_account, false),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new_readonly(raydium_v4::id(), false),
-        AccountMeta::new(*pool_lucra_account, false),
-        AccountMeta::new(*pool_wsol_account, false),
+        AccountMeta::new_readonly(*staking_state, false),
+        AccountMeta::new_readonly(DAO_AUTHORITY, true),
+        AccountMeta::new(*rewards_vault, false),
+        AccountMeta::new(*arb_coffer, false),
+        AccountMeta::new_readonly(Pda::rewards_vault_authority(system_state).0, false),
         AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new(*amm_program, false),
-        AccountMeta::new_readonly(*amm_authority, false),
-        AccountMeta::new(*amm_open_orders, false),
-        AccountMeta::new(*amm_target, false),
-        AccountMeta::new(*serum_lucra_sol_market, false),
-        AccountMeta::new_readonly(serum_v3::id(), false),
-        AccountMeta::new(*serum_bids, false),
-        AccountMeta::new(*serum_asks, false),
-        AccountMeta::new(*serum_event_queue, false),
-        AccountMeta::new(*serum_base_vault, false),
-        AccountMeta::new(*serum_quote_vault, false),
-        AccountMeta::new_readonly(*serum_vault_signer, false),
     ];
-    let data = Instruction::BuyBurnForArb {
-        fund_source: CurrencyTypes::Lucra as u8, 
-        amm_type: AmmTypes::Raydium as u8,
-        lamports: 0,
-    };
+    accounts.extend(unswept_rewards.iter().map(|reward| AccountMeta::new_readonly(*reward, false)));
+    let data = Instruction::RebalanceRewardsVault { lamports };
 
     SolInstruction {
         program_id: id(),
         accounts,
-        data: serialize(&data).unwrap(),
+        data: data.pack(),
+    }
+}
+
+/// Appends the keeper registry of `system_state` and `keeper` as its signer to a crank, after any of its
+/// optional accounts
+pub fn with_keeper(mut instruction: SolInstruction, system_state: &Pubkey, keeper: &Pubkey) -> SolInstruction {
+    instruction.accounts.push(AccountMeta::new_readonly(Pda::keeper_registry(system_state).0, false));
+    instruction.accounts.push(AccountMeta::new_readonly(*keeper, true));
+    instruction
+}
+
+/// Appends the event queue of `system_state` to an instruction that emits events
+pub fn with_event_queue(mut instruction: SolInstruction, system_state: &Pubkey) -> SolInstruction {
+    instruction.accounts.push(AccountMeta::new(Pda::event_queue(system_state).0, false));
+    instruction
+}
+
+/// Appends the header of `price_history` and its `pages` to a `determine_penalty` instruction, the pages
+/// have to hold every day the loan hasn't been charged for
+pub fn with_price_history_pages(mut instruction: SolInstruction, price_history: &Pubkey, pages: std::ops::RangeInclusive<u64>) -> SolInstruction {
+    instruction.accounts.push(AccountMeta::new_readonly(Pda::price_history_header(price_history).0, false));
+    for page_index in pages {
+        instruction.accounts.push(AccountMeta::new_readonly(Pda::price_history_page(price_history, page_index).0, false));
     }
+    instruction
 }
 
+// The builders under their old signatures, which still take the authorities the `_derived` builders
+// derive. The passed authorities are ignored. Remove after the next release.
+
+#[deprecated(note = "use `create_mata_loan_derived`, it derives the authorities")]
 #[allow(clippy::too_many_arguments)]
-pub fn spend_arb_funds_for_mata_using_raydium(
+pub fn create_mata_loan(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
     mata_mint: &Pubkey,
-    wsol_mint: &Pubkey,
-    reward_mint: &Pubkey,
+    _mata_mint_authority: &Pubkey,
+    transfer_from: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_msol_account: &Pubkey,
     sol_usdc_oracle: &Pubkey,
     sol_usdt_oracle: &Pubkey,
     sol_mata_oracle: &Pubkey,
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_wsol_account: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    _liq_pool_msol_leg_authority: &Pubkey,
+    reserve_address: &Pubkey,
+    _msol_mint_authority: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    create_mata_loan_derived(
+        system_state,
+        marinade_state,
+        loan,
+        msol_vault,
+        mata_mint,
+        transfer_from,
+        user_mata_account,
+        user_msol_account,
+        sol_usdc_oracle,
+        sol_usdt_oracle,
+        sol_mata_oracle,
+        msol_mint,
+        liq_pool_sol_leg_address,
+        liq_pool_msol_leg,
+        reserve_address,
+        lamports,
+    )
+}
+
+#[deprecated(note = "use `create_mata_loan_with_locked_stake_derived`, it derives the authorities")]
+#[allow(clippy::too_many_arguments)]
+pub fn create_mata_loan_with_locked_stake(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    mata_mint: &Pubkey,
+    _mata_mint_authority: &Pubkey,
+    transfer_from: &Pubkey,
     user_mata_account: &Pubkey,
-    pool_wsol_account: &Pubkey,
-    pool_mata_account: &Pubkey,
-    amm_program: &Pubkey,
-    amm_authority: &Pubkey,
-    amm_open_orders: &Pubkey,
-    amm_target: &Pubkey,
-    serum_sol_mata_market: &Pubkey,
-    serum_bids: &Pubkey,
-    serum_asks: &Pubkey,
-    serum_event_queue: &Pubkey,
-    serum_base_vault: &Pubkey,
-    serum_quote_vault: &Pubkey,
-    serum_vault_signer: &Pubkey,
+    user_msol_account: &Pubkey,
+    staking_account: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    sol_mata_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    _liq_pool_msol_leg_authority: &Pubkey,
+    reserve_address: &Pubkey,
+    _msol_mint_authority: &Pubkey,
     lamports: u64,
 ) -> SolInstruction {
-    let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new_readonly(ArbState::find_arb_fund_authority(arb_state).0, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*wsol_mint, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new(*user_mata_account, false),
-        AccountMeta::new_readonly(raydium_v4::id(), false),
-        AccountMeta::new(*pool_wsol_account, false),
-        AccountMeta::new(*pool_mata_account, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new(*amm_program, false),
-        AccountMeta::new_readonly(*amm_authority, false),
-        AccountMeta::new(*amm_open_orders, false),
-        AccountMeta::new(*amm_target, false),
-        AccountMeta::new(*serum_sol_mata_market, false),
-        AccountMeta::new_readonly(serum_v3::id(), false),
-        AccountMeta::new(*serum_bids, false),
-        AccountMeta::new(*serum_asks, false),
-        AccountMeta::new(*serum_event_queue, false),
-        AccountMeta::new(*serum_base_vault, false),
-        AccountMeta::new(*serum_quote_vault, false),
-        AccountMeta::new_readonly(*serum_vault_signer, false),
-    ];
-    let data = Instruction::BuyBurnForArb { 
-        fund_source: CurrencyTypes::Mata as u8, 
-        amm_type: AmmTypes::Raydium as u8,
-        lamports
-    };
+    create_mata_loan_with_locked_stake_derived(
+        system_state,
+        marinade_state,
+        loan,
+        msol_vault,
+        mata_mint,
+        transfer_from,
+        user_mata_account,
+        user_msol_account,
+        staking_account,
+        sol_usdc_oracle,
+        sol_usdt_oracle,
+        sol_mata_oracle,
+        lucra_sol_oracle,
+        msol_mint,
+        liq_pool_sol_leg_address,
+        liq_pool_msol_leg,
+        reserve_address,
+        lamports,
+    )
+}
+
+#[deprecated(note = "use `stake_derived`, it derives the authorities")]
+#[allow(clippy::too_many_arguments)]
+pub fn stake(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    staking_account: &Pubkey,
+    stake_balance: &Pubkey,
+    deposit_vault: &Pubkey,
+    stake_vault: &Pubkey,
+    owner: &Pubkey,
+    staked_lucra_account: &Pubkey,
+    _transfer_authority: &Pubkey,
+    staked_lucra_mint: &Pubkey,
+    lucra: u64,
+) -> SolInstruction {
+    stake_derived(
+        system_state,
+        staking_state,
+        staking_account,
+        stake_balance,
+        deposit_vault,
+        stake_vault,
+        owner,
+        staked_lucra_account,
+        staked_lucra_mint,
+        lucra,
+    )
+}
+
+#[deprecated(note = "use `start_unstake_derived`, it derives the authorities")]
+#[allow(clippy::too_many_arguments)]
+pub fn start_unstake(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    staking_account: &Pubkey,
+    stake_balance: &Pubkey,
+    stake_vault: &Pubkey,
+    pending_vault: &Pubkey,
+    pending_withdrawal: &Pubkey,
+    owner: &Pubkey,
+    staked_lucra_account: &Pubkey,
+    _transfer_authority: &Pubkey,
+    staked_lucra_mint: &Pubkey,
+    sol_usdc_oracle: &Pubkey,
+    sol_usdt_oracle: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    lucra: u64,
+) -> SolInstruction {
+    start_unstake_derived(
+        system_state,
+        staking_state,
+        staking_account,
+        stake_balance,
+        stake_vault,
+        pending_vault,
+        pending_withdrawal,
+        owner,
+        staked_lucra_account,
+        staked_lucra_mint,
+        sol_usdc_oracle,
+        sol_usdt_oracle,
+        lucra_sol_oracle,
+        lucra,
+    )
+}
 
-    SolInstruction {
-        program_id: id(),
-        accounts,
-        data: serialize(&data).unwrap(),
-    }
+#[deprecated(note = "use `end_unstake_derived`, it derives the authorities")]
+#[allow(clippy::too_many_arguments)]
+pub fn end_unstake(
+    system_state: &Pubkey,
+    pending_withdrawal: &Pubkey,
+    stake_balance: &Pubkey,
+    pending_vault: &Pubkey,
+    deposit_vault: &Pubkey,
+    owner: &Pubkey,
+    _transfer_authority: &Pubkey,
+    _user_sol_account: &Pubkey,
+) -> SolInstruction {
+    end_unstake_derived(
+        system_state,
+        pending_withdrawal,
+        stake_balance,
+        pending_vault,
+        deposit_vault,
+        owner,
+    )
 }
 
+#[deprecated(note = "use `withdraw_stake_derived`, it derives the authorities")]
 #[allow(clippy::too_many_arguments)]
-pub fn spend_arb_funds_for_lucra_using_orca(
+pub fn withdraw_stake(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    wsol_holding_vault: &Pubkey,
-    lucra_mint: &Pubkey,
-    reward_mint: &Pubkey,
-    lucra_sol_oracle: &Pubkey,
-    user_account: &Pubkey,
+    staking_account: &Pubkey,
+    stake_balance: &Pubkey,
+    to_account: &Pubkey,
+    deposit_vault: &Pubkey,
+    stake_vault: &Pubkey,
+    pending_vault: &Pubkey,
+    owner: &Pubkey,
+    _transfer_authority: &Pubkey,
+    lucra: u64,
+) -> SolInstruction {
+    withdraw_stake_derived(
+        system_state,
+        staking_account,
+        stake_balance,
+        to_account,
+        deposit_vault,
+        stake_vault,
+        pending_vault,
+        owner,
+        lucra,
+    )
+}
+
+#[deprecated(note = "use `drop_reward_derived`, it derives the authorities")]
+#[allow(clippy::too_many_arguments)]
+pub fn drop_reward(
+    system_state: &Pubkey,
+    staking_state: &Pubkey,
+    marinade_state: &Pubkey,
+    reward: &Pubkey,
+    staked_lucra_mint: &Pubkey,
+    msol_vault: &Pubkey,
+    rewards_vault: &Pubkey,
+    arb_coffer: &Pubkey,
     user_reward_account: &Pubkey,
-    user_lucra_account: &Pubkey,
-    user_wsol_account: &Pubkey,
-    ls_amm: &Pubkey,
-    ls_amm_authority: &Pubkey,
-    ls_pool_base_vault: &Pubkey,
-    ls_pool_quote_vault: &Pubkey,
-    ls_pool_mint: &Pubkey,
-    ls_pool_fees: &Pubkey,
+    reward_mint: &Pubkey,
+    _reward_mint_authority: &Pubkey,
 ) -> SolInstruction {
-    let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new_readonly(ArbState::find_arb_fund_authority(arb_state).0, false),
-        AccountMeta::new(*wsol_holding_vault, false),
-        AccountMeta::new(*lucra_mint, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*lucra_sol_oracle, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new_readonly(*user_account, true),
-        AccountMeta::new(*user_lucra_account, false),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new_readonly(*ls_amm, false),
-        AccountMeta::new_readonly(*ls_amm_authority, false),
-        AccountMeta::new(*ls_pool_base_vault, false),
-        AccountMeta::new(*ls_pool_quote_vault, false),
-        AccountMeta::new(*ls_pool_mint, false),
-        AccountMeta::new(*ls_pool_fees, false),
-        AccountMeta::new_readonly(orca_swap::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-    ];
-    let data = Instruction::BuyBurnForArb { 
-        fund_source: CurrencyTypes::Lucra as u8, 
-        amm_type: AmmTypes::Orca as u8,
-        lamports: 0,
-    };
+    drop_reward_derived(
+        system_state,
+        staking_state,
+        marinade_state,
+        reward,
+        staked_lucra_mint,
+        msol_vault,
+        rewards_vault,
+        arb_coffer,
+        user_reward_account,
+        reward_mint,
+    )
+}
 
-    SolInstruction {
-        program_id: id(),
-        accounts,
-        data: serialize(&data).unwrap(),
-    }
+#[deprecated(note = "use `transfer_funds_derived`, it derives the authorities")]
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_funds(
+    system_state: &Pubkey,
+    from_account: &Pubkey,
+    to_account: &Pubkey,
+    _transfer_authority: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    transfer_funds_derived(
+        system_state,
+        from_account,
+        to_account,
+        lamports,
+    )
 }
 
+#[deprecated(note = "use `add_collateral_derived`, it derives the authorities")]
 #[allow(clippy::too_many_arguments)]
-pub fn spend_arb_funds_for_mata_using_orca(
+pub fn add_collateral(
     system_state: &Pubkey,
-    arb_state: &Pubkey,
-    arb_fund: &Pubkey,
-    mata_mint: &Pubkey,
-    wsol_mint: &Pubkey,
-    reward_mint: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    owner: &Pubkey,
+    user_msol_account: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    _liq_pool_msol_leg_authority: &Pubkey,
+    reserve_address: &Pubkey,
+    _msol_mint_authority: &Pubkey,
+    lamports: u64,
+) -> SolInstruction {
+    add_collateral_derived(
+        system_state,
+        marinade_state,
+        loan,
+        msol_vault,
+        owner,
+        user_msol_account,
+        msol_mint,
+        liq_pool_sol_leg_address,
+        liq_pool_msol_leg,
+        reserve_address,
+        lamports,
+    )
+}
+
+#[deprecated(note = "use `add_collateral_with_locked_stake_derived`, it derives the authorities")]
+#[allow(clippy::too_many_arguments)]
+pub fn add_collateral_with_locked_stake(
+    system_state: &Pubkey,
+    marinade_state: &Pubkey,
+    loan: &Pubkey,
+    msol_vault: &Pubkey,
+    owner: &Pubkey,
+    user_msol_account: &Pubkey,
+    staking_account: &Pubkey,
     sol_usdc_oracle: &Pubkey,
     sol_usdt_oracle: &Pubkey,
-    sol_mata_oracle: &Pubkey,
-    user_account: &Pubkey,
-    user_reward_account: &Pubkey,
-    user_wsol_account: &Pubkey,
-    user_mata_account: &Pubkey, 
-    sm_amm: &Pubkey,
-    sm_amm_authority: &Pubkey,
-    sm_pool_base_vault: &Pubkey,
-    sm_pool_quote_vault: &Pubkey,
-    sm_pool_mint: &Pubkey,
-    sm_pool_fees: &Pubkey,
+    lucra_sol_oracle: &Pubkey,
+    msol_mint: &Pubkey,
+    liq_pool_sol_leg_address: &Pubkey,
+    liq_pool_msol_leg: &Pubkey,
+    _liq_pool_msol_leg_authority: &Pubkey,
+    reserve_address: &Pubkey,
+    _msol_mint_authority: &Pubkey,
     lamports: u64,
 ) -> SolInstruction {
-    let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_fund, false),
-        AccountMeta::new_readonly(ArbState::find_arb_fund_authority(arb_state).0, false),
-        AccountMeta::new(*mata_mint, false),
-        AccountMeta::new(*wsol_mint, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new_readonly(*sol_usdc_oracle, false),
-        AccountMeta::new_readonly(*sol_usdt_oracle, false),
-        AccountMeta::new_readonly(*sol_mata_oracle, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new_readonly(*user_account, true),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new(*user_mata_account, false),
-        AccountMeta::new_readonly(*sm_amm, false),
-        AccountMeta::new_readonly(*sm_amm_authority, false),
-        AccountMeta::new(*sm_pool_base_vault, false),
-        AccountMeta::new(*sm_pool_quote_vault, false),
-        AccountMeta::new(*sm_pool_mint, false),
-        AccountMeta::new(*sm_pool_fees, false),
-        AccountMeta::new_readonly(orca_swap::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-    ];
-    let data = Instruction::BuyBurnForArb { 
-        fund_source: CurrencyTypes::Mata as u8, 
-        amm_type: AmmTypes::Orca as u8,
+    add_collateral_with_locked_stake_derived(
+        system_state,
+        marinade_state,
+        loan,
+        msol_vault,
+        owner,
+        user_msol_account,
+        staking_account,
+        sol_usdc_oracle,
+        sol_usdt_oracle,
+        lucra_sol_oracle,
+        msol_mint,
+        liq_pool_sol_leg_address,
+        liq_pool_msol_leg,
+        reserve_address,
         lamports,
-    };
-
-    SolInstruction {
-        program_id: id(),
-        accounts,
-        data: serialize(&data).unwrap(),
-    }
-} 
+    )
+}
 
+#[deprecated(note = "use `clean_up_arb_derived`, it derives the authorities")]
 #[allow(clippy::too_many_arguments)]
 pub fn clean_up_arb(
     system_state: &Pubkey,
@@ -3033,40 +6864,554 @@ pub fn clean_up_arb(
     msol_mint: &Pubkey,
     liq_pool_sol_leg_pda: &Pubkey,
     liq_pool_msol_leg: &Pubkey,
-    liq_pool_msol_leg_authority: &Pubkey,
+    _liq_pool_msol_leg_authority: &Pubkey,
     reserve_pda: &Pubkey,
-    msol_mint_authority: &Pubkey,
+    _msol_mint_authority: &Pubkey,
 ) -> SolInstruction {
-    let accounts = vec![
-        AccountMeta::new_readonly(*system_state, false),
-        AccountMeta::new(*arb_state, false),
-        AccountMeta::new(*arb_coffer, false),
-        AccountMeta::new(*wsol_holding_vault, false),
-        AccountMeta::new_readonly(ArbState::find_wsol_holding_vault_authority(arb_state).0, false),
-        AccountMeta::new(*reward_mint, false),
-        AccountMeta::new_readonly(SystemState::find_reward_mint_authority(system_state).0, false),
-        AccountMeta::new(*user_account, true),
-        AccountMeta::new(*user_wsol_account, false),
-        AccountMeta::new(*user_msol_account, false),
-        AccountMeta::new(*user_reward_account, false),
-        AccountMeta::new(*temp_wsol_account, false),
-        AccountMeta::new(*marinade_state, false),
-        AccountMeta::new(*msol_mint, false),
-        AccountMeta::new(*liq_pool_sol_leg_pda, false),
-        AccountMeta::new(*liq_pool_msol_leg, false),
-        AccountMeta::new_readonly(*liq_pool_msol_leg_authority, false),
-        AccountMeta::new(*reserve_pda, false),
-        AccountMeta::new_readonly(*msol_mint_authority, false),
-        AccountMeta::new_readonly(solana_program::sysvar::fees::id(), false),
-        AccountMeta::new_readonly(solana_program::system_program::id(), false),
-        AccountMeta::new_readonly(marinade_finance::id(), false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-    ];
-    let data = Instruction::CleanUpArb {};
+    clean_up_arb_derived(
+        system_state,
+        arb_state,
+        arb_coffer,
+        wsol_holding_vault,
+        reward_mint,
+        temp_wsol_account,
+        user_account,
+        user_reward_account,
+        user_wsol_account,
+        user_msol_account,
+        marinade_state,
+        msol_mint,
+        liq_pool_sol_leg_pda,
+        liq_pool_msol_leg,
+        reserve_pda,
+    )
+}
 
-    SolInstruction {
-        program_id: id(),
-        accounts,
-        data: serialize(&data).unwrap(),
+#[deprecated(note = "use `reclaim_excess_derived`, it derives the authorities")]
+pub fn reclaim_excess(
+    system_state: &Pubkey,
+    msol_vault: &Pubkey,
+    to_account: &Pubkey,
+    _transfer_authority: &Pubkey,
+) -> SolInstruction {
+    reclaim_excess_derived(
+        system_state,
+        msol_vault,
+        to_account,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lucra_core::pda::{
+            find_collateral_registry,
+            find_keeper_registry,
+            find_loan_registry,
+            find_lp_vault_authority,
+            find_price_history_header,
+            find_price_history_page,
+            find_sol_vault,
+            find_stake_balance_authority,
+            find_transfer_allowlist,
+        },
+        state::{staking::StakingState, ArbState, SystemState},
+    };
+
+    struct Fixture {
+        system_state: Pubkey,
+        staking_state: Pubkey,
+        arb_state: Pubkey,
+        marinade_state: Pubkey,
+        owner: Pubkey,
+    }
+
+    fn key(seed: u8) -> Pubkey {
+        Pubkey::new_from_array([seed; 32])
+    }
+
+    fn fixture() -> Fixture {
+        Fixture {
+            system_state: key(1),
+            staking_state: key(2),
+            arb_state: key(3),
+            marinade_state: key(4),
+            owner: key(5),
+        }
+    }
+
+    // Same derivation process_create_stake_balance checks the vault owners against
+    fn stake_balance_vault_owner(f: &Fixture) -> Pubkey {
+        let (_, nonce) = find_stake_balance_authority(&f.owner, &f.system_state);
+        Pubkey::create_program_address(&[f.owner.as_ref(), f.system_state.as_ref(), &[nonce]], &id()).unwrap()
+    }
+
+    fn assert_accounts(instruction: &SolInstruction, expected: &[(usize, Pubkey)]) {
+        for (index, pubkey) in expected {
+            assert_eq!(&instruction.accounts[*index].pubkey, pubkey, "account {}", index);
+        }
+    }
+
+    #[test]
+    fn test_loan_builders_derive_authorities() {
+        let f = fixture();
+        let mata_mint_authority = SystemState::find_mata_mint_authority(&f.system_state).0;
+        let msol_vault_authority = SystemState::find_msol_vault_authority(&f.system_state).0;
+        let msol_mint_authority = marinade_finance::state::State::find_msol_mint_authority(&f.marinade_state).0;
+        let msol_leg_authority = marinade_finance::liq_pool::LiqPool::find_msol_leg_authority(&f.marinade_state).0;
+
+        let instruction = create_mata_loan_derived(
+            &f.system_state, &f.marinade_state, &key(10), &key(11), &key(12), &f.owner, &key(13), &key(14),
+            &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), 1,
+        );
+        assert_accounts(&instruction, &[(5, mata_mint_authority), (15, msol_leg_authority), (17, msol_mint_authority)]);
+
+        let instruction = create_mata_loan_with_locked_stake_derived(
+            &f.system_state, &f.marinade_state, &key(10), &key(11), &key(12), &f.owner, &key(13), &key(14), &key(22),
+            &key(15), &key(16), &key(17), &key(23), &key(18), &key(19), &key(20), &key(21), 1,
+        );
+        assert_accounts(&instruction, &[(5, mata_mint_authority), (17, msol_leg_authority), (19, msol_mint_authority)]);
+
+        let instruction = add_collateral_derived(
+            &f.system_state, &f.marinade_state, &key(10), &key(11), &f.owner, &key(14), &key(18), &key(19), &key(20), &key(21), 1,
+        );
+        assert_accounts(&instruction, &[(9, msol_leg_authority), (11, msol_mint_authority)]);
+
+        let instruction = add_collateral_with_locked_stake_derived(
+            &f.system_state, &f.marinade_state, &key(10), &key(11), &f.owner, &key(14), &key(22),
+            &key(15), &key(16), &key(23), &key(18), &key(19), &key(20), &key(21), 1,
+        );
+        assert_accounts(&instruction, &[(13, msol_leg_authority), (15, msol_mint_authority)]);
+
+        let instruction = close_mata_loan(
+            &f.system_state, &f.marinade_state, &key(10), &f.owner, &key(14), &key(12), &key(13), &key(11),
+            &key(18), &key(19), &key(20), &key(24), false,
+        );
+        assert_accounts(&instruction, &[(7, msol_vault_authority)]);
+    }
+
+    #[test]
+    fn test_lp_backed_builders_match_their_layouts() {
+        let f = fixture();
+        let mata_mint_authority = SystemState::find_mata_mint_authority(&f.system_state).0;
+        let lp_vault_authority = find_lp_vault_authority(&f.system_state).0;
+
+        // The create processor picks the layout from the pool at index 1, the close one from the account count
+        let instruction = create_lp_backed_mata_loan(
+            &f.system_state, &key(10), &key(11), &key(12), &f.owner, &key(13), &key(14),
+            &key(15), &key(16), &key(17), &key(18), &key(19), &key(20), 1,
+        );
+        assert_eq!(instruction.accounts.len(), 18);
+        assert_accounts(&instruction, &[(1, SOL_MATA_ORCA_AMM), (5, mata_mint_authority), (17, find_loan_registry(&f.owner).0)]);
+
+        let instruction = close_lp_backed_mata_loan(&f.system_state, &key(10), &f.owner, &key(14), &key(12), &key(13), &key(11));
+        assert_eq!(instruction.accounts.len(), 9);
+        assert_accounts(&instruction, &[(6, lp_vault_authority), (7, key(11))]);
+    }
+
+    #[test]
+    fn test_native_sol_builders_match_their_layouts() {
+        let f = fixture();
+        let sol_vault = find_sol_vault(&f.system_state).0;
+
+        // The create and harvest processors pick the native layouts from the program owned vault at index 1,
+        // the close and add collateral ones from the account count
+        let instruction = create_native_sol_mata_loan(&f.system_state, &key(10), &key(11), &f.owner, &key(12), &key(15), &key(16), &key(17), 1);
+        assert_eq!(instruction.accounts.len(), 13);
+        assert_accounts(&instruction, &[(1, sol_vault), (4, SystemState::find_mata_mint_authority(&f.system_state).0), (12, find_loan_registry(&f.owner).0)]);
+
+        let instruction = close_native_sol_mata_loan(&f.system_state, &key(10), &f.owner, &key(11), &key(12));
+        assert_eq!(instruction.accounts.len(), 7);
+        assert_accounts(&instruction, &[(1, key(10)), (5, sol_vault)]);
+
+        let instruction = add_native_sol_collateral(&f.system_state, &key(10), &f.owner, 1);
+        assert_eq!(instruction.accounts.len(), 5);
+        assert_accounts(&instruction, &[(1, sol_vault), (3, f.owner)]);
+
+        let instruction = harvest_native_sol_penalty_with_orca(
+            &f.system_state, &key(11), &key(10), &f.owner, &key(12), &key(13), &key(17), &key(15), &key(16),
+            &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), 1,
+        );
+        assert_eq!(instruction.accounts.len(), 18);
+        // The event is emitted for the sixth account, as in the msol layouts
+        assert_accounts(&instruction, &[(1, sol_vault), (5, key(10)), (16, orca_swap::id())]);
+
+        let instruction = create_sol_vault(&f.system_state);
+        assert_accounts(&instruction, &[(1, sol_vault), (2, CREATOR_AUTHORITY)]);
+    }
+
+    #[test]
+    fn test_price_history_page_builders_match_their_layouts() {
+        let f = fixture();
+        let price_history = key(10);
+        let header = find_price_history_header(&price_history).0;
+        let page = |page_index| find_price_history_page(&price_history, page_index).0;
+
+        let instruction = create_price_history_page(&price_history, 2, &f.owner);
+        assert_eq!(instruction.accounts.len(), 5);
+        assert_accounts(&instruction, &[(0, price_history), (1, header), (2, page(2)), (3, f.owner)]);
+
+        // The header and pages go after the arb state
+        let instruction = update_price_history_with_pages(
+            &f.system_state, &price_history, &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), 4,
+        );
+        assert_eq!(instruction.accounts.len(), 14);
+        assert_accounts(&instruction, &[(10, key(17)), (11, header), (12, page(4)), (13, page(5))]);
+        assert!(instruction.accounts[11..].iter().all(|meta| meta.is_writable));
+
+        // DeterminePenalty finds the header by its address, the pages follow it
+        let instruction = with_price_history_pages(
+            determine_penalty_with_history(&f.system_state, &key(11), &key(12), &key(13), &key(14), &price_history, &key(15), &key(16)),
+            &price_history,
+            1..=2,
+        );
+        assert_eq!(instruction.accounts.len(), 14);
+        assert_accounts(&instruction, &[(5, price_history), (11, header), (12, page(1)), (13, page(2))]);
+
+        let instruction = correct_paged_price_history(&f.system_state, &price_history, 3, 86_400, 1, 1);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_accounts(&instruction, &[(1, price_history), (5, page(3))]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_keeper_builders_match_their_layouts() {
+        let f = fixture();
+        let keeper = key(10);
+        let registry = find_keeper_registry(&f.system_state).0;
+
+        let instruction = register_keeper(&f.system_state, &keeper, Bps(15_000), &f.owner);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_accounts(&instruction, &[(1, registry), (2, keeper), (3, DAO_AUTHORITY), (4, f.owner)]);
+        let instruction = remove_keeper(&f.system_state, &keeper);
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_accounts(&instruction, &[(1, registry), (2, keeper), (3, DAO_AUTHORITY)]);
+
+        // The keeper goes after the crank's fixed accounts
+        let instruction = with_keeper(
+            update_price_history(&f.system_state, &key(11), &key(12), &key(13), &key(14), &key(15), &key(16), &key(17), &key(18)),
+            &f.system_state,
+            &keeper,
+        );
+        assert_eq!(instruction.accounts.len(), 13);
+        assert_accounts(&instruction, &[(10, key(18)), (11, registry), (12, keeper)]);
+        assert!(!instruction.accounts[11].is_signer);
+        assert!(instruction.accounts[12].is_signer);
+    }
+
+    #[test]
+    fn test_collateral_type_builders_match_their_layouts() {
+        let f = fixture();
+        let registry = find_collateral_registry(&f.system_state).0;
+
+        let instruction = add_collateral_type(&f.system_state, &key(10), &key(11), &key(12), Bps(200), &f.owner);
+        assert_eq!(instruction.accounts.len(), 8);
+        assert_accounts(&instruction, &[(1, registry), (2, key(10)), (3, key(11)), (4, key(12)), (5, DAO_AUTHORITY), (6, f.owner)]);
+        let instruction = set_collateral_type(&f.system_state, 1, &key(11), Bps(300), true);
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_accounts(&instruction, &[(1, registry), (2, key(11)), (3, DAO_AUTHORITY)]);
+        assert_eq!(
+            Instruction::try_from_slice_versioned(&instruction.data).unwrap(),
+            Instruction::SetCollateralType { collateral_type: 1, haircut_bps: Bps(300), enabled: true },
+        );
+    }
+
+    #[test]
+    fn test_transfer_builders_pass_the_allowlist() {
+        let f = fixture();
+        let allowlist = find_transfer_allowlist(&f.system_state).0;
+
+        let instruction = transfer_funds_derived(&f.system_state, &key(10), &key(11), 5);
+        assert_eq!(instruction.accounts.len(), 7);
+        assert_accounts(&instruction, &[(2, key(10)), (3, key(11)), (6, allowlist)]);
+        assert_eq!(
+            Instruction::try_from_slice_versioned(&instruction.data).unwrap(),
+            Instruction::TransferFunds { lamports: 5, native: false },
+        );
+
+        // Native transfers come out of the sol vault on the same layout
+        let native = transfer_native_funds(&f.system_state, &key(11), 5);
+        assert_accounts(&native, &[(2, find_sol_vault(&f.system_state).0), (3, key(11)), (6, allowlist)]);
+        assert!(native.accounts[2].is_writable && native.accounts[3].is_writable);
+        assert_eq!(
+            Instruction::try_from_slice_versioned(&native.data).unwrap(),
+            Instruction::TransferFunds { lamports: 5, native: true },
+        );
+
+        let instruction = add_transfer_destination(&f.system_state, &key(11), &f.owner);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_accounts(&instruction, &[(1, allowlist), (2, key(11)), (3, DAO_AUTHORITY), (4, f.owner)]);
+        assert!(instruction.accounts[1].is_writable);
+        assert!(instruction.accounts[3].is_signer && instruction.accounts[4].is_signer);
+
+        let instruction = remove_transfer_destination(&f.system_state, &key(11));
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_accounts(&instruction, &[(1, allowlist), (2, key(11)), (3, DAO_AUTHORITY)]);
+        assert!(instruction.accounts[1].is_writable && instruction.accounts[3].is_signer);
+    }
+
+    #[test]
+    fn test_rebalance_rewards_vault_appends_the_unswept_rewards() {
+        let f = fixture();
+        let rewards_vault_authority = SystemState::find_rewards_vault_authority(&f.system_state).0;
+
+        let instruction = rebalance_rewards_vault(&f.system_state, &f.staking_state, &key(10), &key(11), &[key(13), key(12)], 5);
+        assert_eq!(instruction.accounts.len(), 9);
+        assert_accounts(&instruction, &[(1, f.staking_state), (2, DAO_AUTHORITY), (5, rewards_vault_authority), (7, key(13)), (8, key(12))]);
+        assert!(instruction.accounts[2].is_signer);
+        assert!(instruction.accounts[7..].iter().all(|account| !account.is_writable && !account.is_signer));
+        assert_eq!(
+            Instruction::try_from_slice_versioned(&instruction.data).unwrap(),
+            Instruction::RebalanceRewardsVault { lamports: 5 },
+        );
+
+        // Once every reward is swept there's nothing to append
+        assert_eq!(rebalance_rewards_vault(&f.system_state, &f.staking_state, &key(10), &key(11), &[], 5).accounts.len(), 7);
+    }
+
+    #[test]
+    fn test_msol_top_up_builders_match_their_layouts() {
+        let f = fixture();
+
+        // The add collateral processor picks the msol layouts from the account count
+        let instruction = add_collateral_msol(&f.system_state, &f.marinade_state, &key(10), &key(11), &f.owner, &key(12), 5);
+        assert_eq!(instruction.accounts.len(), 7);
+        assert_accounts(&instruction, &[(1, f.marinade_state), (4, f.owner), (6, spl_token::id())]);
+
+        let instruction = add_collateral_msol_with_locked_stake(
+            &f.system_state, &f.marinade_state, &key(10), &key(11), &f.owner, &key(12), &key(13),
+            &key(14), &key(15), &key(16), 5,
+        );
+        assert_eq!(instruction.accounts.len(), 11);
+        assert_accounts(&instruction, &[(6, key(13)), (9, key(16)), (10, spl_token::id())]);
+    }
+
+    #[test]
+    fn test_staking_builders_derive_authorities() {
+        let f = fixture();
+        let vault_owner = stake_balance_vault_owner(&f);
+        let stake_mint_authority = StakingState::find_stake_mint_authority(&f.staking_state).0;
+        let msol_vault_authority = SystemState::find_msol_vault_authority(&f.system_state).0;
+        let reward_mint_authority = SystemState::find_reward_mint_authority(&f.system_state).0;
+
+        let instruction = stake_derived(
+            &f.system_state, &f.staking_state, &key(10), &key(11), &key(12), &key(13), &f.owner, &key(14), &key(15), 1,
+        );
+        assert_accounts(&instruction, &[(7, vault_owner), (10, stake_mint_authority)]);
+
+        let instruction = start_unstake_derived(
+            &f.system_state, &f.staking_state, &key(10), &key(11), &key(13), &key(16), &key(17), &f.owner,
+            &key(14), &key(15), &key(18), &key(19), &key(20), 1,
+        );
+        assert_accounts(&instruction, &[(9, vault_owner)]);
+
+        let instruction = end_unstake_derived(&f.system_state, &key(17), &key(11), &key(16), &key(12), &f.owner);
+        assert_accounts(&instruction, &[(6, vault_owner)]);
+
+        let instruction = withdraw_stake_derived(&f.system_state, &key(10), &key(11), &key(21), &key(12), &key(13), &key(16), &f.owner, 1);
+        assert_accounts(&instruction, &[(1, key(10)), (8, vault_owner)]);
+
+        let instruction = claim_reward(
+            &f.system_state, &f.staking_state, &key(10), &key(11), &key(22), &key(15), &key(13), &key(23), &key(24), &key(25), &key(26),
+        );
+        for compound in [false, true] {
+            // The V2 layout is ClaimReward's with the staked lucra mint and its authority appended
+            let instruction_v2 = claim_reward_v2(
+                &f.system_state, &f.staking_state, &key(10), &key(11), &key(22), &key(15), &key(13), &key(23), &key(24),
+                &key(25), &key(26), &key(14), compound,
+            );
+            let claim_keys: Vec<(usize, Pubkey)> = instruction.accounts.iter().map(|meta| meta.pubkey).enumerate().collect();
+            assert_eq!(instruction_v2.accounts.len(), 16);
+            assert_accounts(&instruction_v2, &claim_keys);
+            assert_accounts(&instruction_v2, &[(14, key(14)), (15, stake_mint_authority)]);
+            assert!(instruction_v2.accounts[2].is_writable);
+            assert_eq!(instruction_v2.data, Instruction::ClaimRewardV2 { compound }.pack());
+        }
+
+        let instruction = drop_reward_derived(
+            &f.system_state, &f.staking_state, &f.marinade_state, &key(22), &key(15), &key(23), &key(24), &key(25), &key(26), &key(27),
+        );
+        assert_accounts(&instruction, &[(8, msol_vault_authority), (11, reward_mint_authority)]);
+    }
+
+    #[test]
+    fn test_vault_builders_derive_authorities() {
+        let f = fixture();
+        let msol_vault_authority = SystemState::find_msol_vault_authority(&f.system_state).0;
+
+        let instruction = transfer_funds_derived(&f.system_state, &key(10), &key(11), 1);
+        assert_accounts(&instruction, &[(4, msol_vault_authority)]);
+
+        let instruction = reclaim_excess_derived(&f.system_state, &key(10), &key(11));
+        assert_accounts(&instruction, &[(4, msol_vault_authority)]);
+
+        let instruction = redeem_reward_tokens_for_msol(&f.system_state, &key(10), &key(11), &f.owner, &key(12), &key(13), &f.marinade_state, &key(14), 1);
+        assert_eq!(instruction.accounts.len(), 10);
+        assert_accounts(&instruction, &[
+            (2, key(11)),
+            (5, key(13)),
+            (6, SystemState::find_rewards_vault_authority(&f.system_state).0),
+            (7, f.marinade_state),
+        ]);
+        assert!(instruction.accounts[3].is_signer);
+
+        let instruction = harvest_penalty_direct(
+            &f.system_state, &f.marinade_state, &key(10), &key(11), &key(12), &key(13), &key(14), &key(15), &key(16),
+            &f.owner, &key(17), &key(18), 1,
+        );
+        assert_eq!(instruction.accounts.len(), 15);
+        // The event is emitted for the sixth account, as in the swap layouts
+        assert_accounts(&instruction, &[(3, msol_vault_authority), (5, key(12)), (13, marinade_finance::id())]);
+        assert!(instruction.accounts[10].is_signer);
+        assert_eq!(Instruction::try_from_slice_versioned(&instruction.data).unwrap(), Instruction::HarvestPenalty {
+            amm_type: AmmTypes::None as u8,
+            min_mata_out: 1,
+        });
+
+        let tick_arrays = [key(40), key(41), key(42)];
+        let instruction = harvest_penalty_with_whirlpool(
+            &f.system_state, &f.marinade_state, &key(10), &key(11), &key(12), &f.owner, &key(13), &key(14), &key(15),
+            &key(16), &key(17), &key(18), &key(19), &key(20), &key(21), &key(22), &key(23), &key(24), &key(25), &key(26),
+            &tick_arrays, 1,
+        );
+        assert_eq!(instruction.accounts.len(), 29);
+        assert_accounts(&instruction, &[
+            (3, msol_vault_authority),
+            (5, key(12)),
+            (20, key(24)),
+            (25, key(42)),
+            (26, find_whirlpool_oracle(&key(24))),
+            (27, orca_whirlpool::id()),
+        ]);
+        assert!(instruction.accounts[10].is_signer);
+        assert_eq!(Instruction::try_from_slice_versioned(&instruction.data).unwrap(), Instruction::HarvestPenalty {
+            amm_type: AmmTypes::Whirlpool as u8,
+            min_mata_out: 1,
+        });
+
+        let instruction = clean_up_arb_derived(
+            &f.system_state, &f.arb_state, &key(10), &key(11), &key(12), &key(13), &f.owner, &key(14), &key(15),
+            &key(16), &f.marinade_state, &key(17), &key(18), &key(19), &key(20),
+        );
+        assert_accounts(&instruction, &[
+            (4, ArbState::find_wsol_holding_vault_authority(&f.arb_state).0),
+            (6, SystemState::find_reward_mint_authority(&f.system_state).0),
+            (16, marinade_finance::liq_pool::LiqPool::find_msol_leg_authority(&f.marinade_state).0),
+            (18, marinade_finance::state::State::find_msol_mint_authority(&f.marinade_state).0),
+        ]);
+    }
+
+    #[test]
+    fn test_recovery_key_accounts_are_optional() {
+        let f = fixture();
+        let recovery_key = key(30);
+
+        let instruction = create_staking_account(&f.system_state, &f.staking_state, &key(10), &f.owner, None);
+        assert_eq!(instruction.accounts.len(), 4);
+        let instruction = create_staking_account(&f.system_state, &f.staking_state, &key(10), &f.owner, Some(&recovery_key));
+        assert_accounts(&instruction, &[(4, recovery_key)]);
+
+        let instruction = set_staking_recovery_key(&key(10), &f.owner, None, recovery_key);
+        assert_eq!(instruction.accounts.len(), 2);
+        let instruction = set_staking_recovery_key(&key(10), &f.owner, Some(&recovery_key), key(31));
+        assert_accounts(&instruction, &[(2, recovery_key)]);
+        assert!(instruction.accounts[2].is_signer);
+    }
+
+    #[test]
+    fn test_update_state_only_serializes_set_fields() {
+        let f = fixture();
+
+        // Instruction tag, then a tag byte per field
+        let empty = update_state_fields(&f.system_state, &f.arb_state, &UpdateStateParams::default());
+        assert_eq!(empty.data.len(), 1 + 33);
+
+        let reward_fee_only = UpdateStateParams { reward_fee: Some(RewardFee(750)), ..UpdateStateParams::default() };
+        let instruction = update_state_fields(&f.system_state, &f.arb_state, &reward_fee_only);
+        assert_eq!(instruction.data.len(), 1 + 33 + 4);
+
+        // Every field set is the old layout plus the 23 tags, then the guardian, the oracle limits, the
+        // MSOL/SOL pool, the arb dust threshold, the Pyth feed, the interest rate, restricted cranking, the
+        // SOL/MATA whirlpool and native loans with theirs
+        let full = update_state(
+            &f.system_state, &f.arb_state, Lamports(1), CollateralRatio(1), false, false, false, false, 1, 1, Mata(1),
+            Lamports(1), RewardFee(1), Percent(1), Mata(1), Bps(1), key(17), false, 1, Lamports(1), Bps(1), Bps(1), Bps(1),
+            1, Bps(1), key(18), 1, Bps(1), key(19), 1, key(20), Bps(1), false, key(21), false,
+        );
+        assert_eq!(full.data.len(), 135 + 23 + 33 + 9 + 5 + 33 + 9 + 33 + 5 + 2 + 33 + 2);
+        assert_eq!(full.accounts, empty.accounts);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_shims_build_the_same_instruction() {
+        let f = fixture();
+        let vault_owner = stake_balance_vault_owner(&f);
+
+        assert_eq!(
+            withdraw_stake(&f.system_state, &key(10), &key(11), &key(21), &key(12), &key(13), &key(16), &f.owner, &vault_owner, 1),
+            withdraw_stake_derived(&f.system_state, &key(10), &key(11), &key(21), &key(12), &key(13), &key(16), &f.owner, 1),
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_shims_derive_the_authorities_they_are_passed() {
+        let f = fixture();
+
+        // A wrong authority is replaced by the derived one instead of building a transaction that fails
+        let instruction = reclaim_excess(&f.system_state, &key(10), &key(11), &f.owner);
+        assert_eq!(instruction, reclaim_excess_derived(&f.system_state, &key(10), &key(11)));
+        assert!(instruction.accounts.iter().all(|meta| meta.pubkey != f.owner));
+    }
+
+    #[test]
+    fn test_tagged_layout_replaces_the_variant_index() {
+        let instruction = Instruction::WithdrawCollateral { lamports: 7 };
+        let legacy = serialize(&instruction).unwrap();
+        let tagged = instruction.pack();
+        assert_eq!(tagged[0], 0xb6);
+        assert_eq!(tagged[1..], legacy[4..]);
+
+        assert_eq!(Instruction::try_from_slice_versioned(&tagged).unwrap(), instruction);
+        assert_eq!(Instruction::try_from_slice_versioned(&legacy).unwrap(), instruction);
+    }
+
+    #[test]
+    fn test_malformed_data_is_invalid_instruction_data() {
+        let tagged = Instruction::RolloverLoan { new_lamports: 1 }.pack();
+        for data in [
+            &[][..],
+            &tagged[..tagged.len() - 1],
+            // Past the last tag
+            &[0xff, 0, 0, 0, 0, 0, 0, 0, 0][..],
+            // A legacy variant index past the last variant
+            &[0x7f, 0, 0, 0][..],
+            &[0, 0, 0, 1][..],
+        ] {
+            assert_eq!(Instruction::try_from_slice_versioned(data), Err(ProgramError::InvalidInstructionData));
+        }
+    }
+
+    #[test]
+    fn test_random_data_never_panics() {
+        // xorshift, so a failing case can be reproduced from its seed
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..20_000 {
+            let len = (next() % 160) as usize;
+            let mut data: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            // Half of the cases start with a real tag so the payloads are fuzzed too, not only the tag
+            if !data.is_empty() && next() % 2 == 0 {
+                data[0] = INSTRUCTION_TAGS[(next() % INSTRUCTION_TAGS.len() as u64) as usize];
+            }
+            let _ = Instruction::try_from_slice_versioned(&data);
+        }
+    }
+}
@@ -1,13 +1,33 @@
 #[macro_use]
 pub mod error;
 
-pub mod instruction;
-pub mod processor;
+pub mod lucra_core;
 pub mod helpers;
 pub mod state;
+pub mod units;
 
+// Everything that builds, sends or processes instructions. Turning off default features leaves only the
+// core for programs that embed the loan math.
+#[cfg(feature = "program")]
+pub mod idl;
+#[cfg(feature = "program")]
+pub mod instruction;
+#[cfg(feature = "program")]
+pub mod processor;
+
+#[cfg(feature = "client")]
+pub mod bootstrap;
+#[cfg(feature = "client")]
+pub mod decode;
+#[cfg(feature = "client")]
+pub mod filters;
+
+#[cfg(feature = "program")]
 pub mod entrypoint;
 
 pub use solana_program;
 
+// Every address the program derives, for clients building transactions off chain
+pub use lucra_core::pda;
+
 solana_program::declare_id!("LCu6pNvyoBkwCHYL6PbMLintScmZFrkDdbq1D7KZ4ay");
\ No newline at end of file
@@ -0,0 +1,173 @@
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use solana_program::native_token::LAMPORTS_PER_SOL;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::LAMPORTS_PER_MATA,
+    helpers::loan_history::LOAN_HISTORY_VERSION,
+    state::MataLoan,
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::CoreInterest);
+
+// Interest is the DAO's base borrow rate, charged whether or not the peg holds. It accrues in mata on the
+// loan's debt over the same days DeterminePenalty charges penalty for, and is owed on top of the debt.
+// interest_accrued is only part of the layout from LOAN_INTEREST_VERSION on. Loans opened before it were
+// opened without a rate, they never accrue and owe their debt alone until a rollover opens them again.
+
+pub const LOAN_INTEREST_VERSION: u8 = LOAN_HISTORY_VERSION + 1;
+
+pub const DAYS_PER_YEAR: u64 = 365;
+
+pub fn accrues_interest(loan: &MataLoan) -> bool {
+    loan.meta_data.version >= LOAN_INTEREST_VERSION
+}
+
+pub fn interest_accrued(loan: &MataLoan) -> u64 {
+    if !accrues_interest(loan) {
+        return 0;
+    }
+
+    loan.interest_accrued
+}
+
+/// 0 turns interest off, a rate over 100% a year is rejected
+pub fn verify_annual_interest(annual_interest_bps: Bps) -> LucraResult {
+    check!(annual_interest_bps <= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)
+}
+
+/// Simple interest on `loan_amount` for `days`, `loan_amount * rate * days / 365` rounded down
+pub fn calc_interest(loan_amount: u64, annual_interest_bps: Bps, days: u64) -> LucraResult<u64> {
+    let interest = u128::from(loan_amount)
+        .checked_mul(u128::from(annual_interest_bps.get()))
+        .ok_or(math_err!())?
+        .checked_mul(u128::from(days))
+        .ok_or(math_err!())?
+        / (u128::from(Bps::ONE_HUNDRED_PERCENT.get()) * u128::from(DAYS_PER_YEAR));
+
+    u64::try_from(interest).map_err(|_| math_err!())
+}
+
+/// Mata a close has to burn, the debt and the interest accrued on it
+pub fn calc_amount_owed(loan: &MataLoan) -> LucraResult<u64> {
+    loan.loan_amount
+        .checked_add(interest_accrued(loan))
+        .ok_or(math_err!())
+}
+
+/// Lamports of collateral that pay for `interest` mata, the mata counted at the peg
+pub fn calc_interest_lamports(interest: u64, sol_usd_price: Decimal) -> LucraResult<u64> {
+    Decimal::from(interest)
+        .checked_div(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .checked_mul(Decimal::from(LAMPORTS_PER_SOL))
+        .ok_or(math_err!())?
+        .checked_div(sol_usd_price)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+/// Interest the borrower hasn't paid is harvested out of the collateral with the penalty. Moves it into the
+/// penalty to harvest at today's SOL price and returns the lamports it added.
+pub fn move_interest_to_harvest(loan: &mut MataLoan, sol_usd_price: Decimal) -> LucraResult<u64> {
+    let interest_lamports = calc_interest_lamports(interest_accrued(loan), sol_usd_price)?;
+    loan.penalty_to_harvest = loan.penalty_to_harvest
+        .checked_add(interest_lamports)
+        .ok_or(math_err!())?;
+    loan.interest_accrued = 0;
+
+    Ok(interest_lamports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use crate::state::{DataType, MetaData};
+
+    fn loan_on(version: u8) -> MataLoan {
+        MataLoan { meta_data: MetaData::new(DataType::Loan, version, true), ..MataLoan::default() }
+    }
+
+    #[test]
+    fn test_interest_accrues_per_day() {
+        // 1,000 mata at 5% a year
+        let loan_amount = 1_000_000_000;
+        assert_eq!(calc_interest(loan_amount, Bps(500), 0).unwrap(), 0);
+        assert_eq!(calc_interest(loan_amount, Bps(500), 1).unwrap(), 136_986);
+        assert_eq!(calc_interest(loan_amount, Bps(500), 73).unwrap(), 10_000_000);
+        assert_eq!(calc_interest(loan_amount, Bps(500), DAYS_PER_YEAR).unwrap(), 50_000_000);
+
+        // Turned off
+        assert_eq!(calc_interest(loan_amount, Bps(0), DAYS_PER_YEAR).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_interest_rounds_down_and_doesnt_overflow() {
+        // A day of 1% on 36.5 mata is a thousandth of a mata, a millionth less rounds down
+        assert_eq!(calc_interest(36_500_000, Bps(100), 1).unwrap(), 1_000);
+        assert_eq!(calc_interest(36_499_999, Bps(100), 1).unwrap(), 999);
+        assert_eq!(calc_interest(1, Bps::ONE_HUNDRED_PERCENT, 364).unwrap(), 0);
+
+        assert!(calc_interest(u64::MAX, Bps::ONE_HUNDRED_PERCENT, DAYS_PER_YEAR).is_ok());
+        assert!(calc_interest(u64::MAX, Bps::ONE_HUNDRED_PERCENT, DAYS_PER_YEAR * 2).is_err());
+    }
+
+    #[test]
+    fn test_interest_rate_validation() {
+        assert!(verify_annual_interest(Bps(0)).is_ok());
+        assert!(verify_annual_interest(Bps::ONE_HUNDRED_PERCENT).is_ok());
+        assert!(verify_annual_interest(Bps(10_001)).is_err());
+    }
+
+    #[test]
+    fn test_close_owes_the_interest_on_top_of_the_debt() {
+        let loan = MataLoan {
+            loan_amount: 100_000_000,
+            interest_accrued: 1_250_000,
+            ..loan_on(LOAN_INTEREST_VERSION)
+        };
+        assert_eq!(calc_amount_owed(&loan).unwrap(), 101_250_000);
+        assert_eq!(calc_amount_owed(&MataLoan { interest_accrued: 0, ..loan }).unwrap(), 100_000_000);
+        assert!(calc_amount_owed(&MataLoan { interest_accrued: u64::MAX, ..loan }).is_err());
+    }
+
+    #[test]
+    fn test_harvest_takes_the_interest_out_of_the_collateral() {
+        // 20 mata of interest at $40 a SOL is half a SOL
+        let mut loan = MataLoan {
+            penalty_to_harvest: 1_000_000,
+            interest_accrued: 20_000_000,
+            ..loan_on(LOAN_INTEREST_VERSION)
+        };
+        assert_eq!(move_interest_to_harvest(&mut loan, dec!(40)).unwrap(), LAMPORTS_PER_SOL / 2);
+        assert_eq!(loan.penalty_to_harvest, 1_000_000 + LAMPORTS_PER_SOL / 2);
+        assert_eq!(loan.interest_accrued, 0);
+
+        // Nothing left to move
+        assert_eq!(move_interest_to_harvest(&mut loan, dec!(40)).unwrap(), 0);
+        assert_eq!(loan.penalty_to_harvest, 1_000_000 + LAMPORTS_PER_SOL / 2);
+    }
+
+    #[test]
+    fn test_loans_from_before_interest_owe_none() {
+        // The bytes are carved out of the reserved region, whatever an older loan has there isn't interest
+        let loan = MataLoan { loan_amount: 100_000_000, interest_accrued: 1_250_000, ..loan_on(LOAN_HISTORY_VERSION) };
+        assert!(!accrues_interest(&loan));
+        assert_eq!(calc_amount_owed(&loan).unwrap(), 100_000_000);
+
+        let mut loan = MataLoan { penalty_to_harvest: 1_000_000, ..loan };
+        assert_eq!(move_interest_to_harvest(&mut loan, dec!(40)).unwrap(), 0);
+        assert_eq!(loan.penalty_to_harvest, 1_000_000);
+
+        assert!(accrues_interest(&loan_on(LOAN_INTEREST_VERSION)));
+    }
+}
@@ -0,0 +1,68 @@
+// The parts of Lucra other programs can embed: loan and penalty math, PDA derivations, and the unit and error
+// types. Nothing in here makes a CPI, so it builds with default-features = false and the program's swap,
+// serum and marinade dependencies left out. The program re-exports everything from where it used to live.
+
+pub mod interest;
+pub mod pda;
+pub mod penalty;
+pub mod quote;
+
+pub use crate::{
+    error::{LucraError, LucraErrorCode, LucraResult},
+    helpers::math,
+    units,
+};
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+    use bytemuck::Zeroable;
+    use solana_program::pubkey::Pubkey;
+    use crate::{
+        helpers::account_layout::RESERVED_LAYOUT_VERSION,
+        state::{DataType, MataLoan, MataSupply, MetaData, RevenueEntry, RevenueLedger, SystemState, REVENUE_LEDGER_LENGTH},
+        units::{Bps, CollateralRatio, Lamports, Lucra, Mata, Percent, RewardFee},
+    };
+
+    // Account layouts are read by existing accounts on chain, moving code into the core can't change them
+    #[test]
+    fn test_layout_snapshot() {
+        assert_eq!(crate::id().to_string(), "LCu6pNvyoBkwCHYL6PbMLintScmZFrkDdbq1D7KZ4ay");
+
+        assert_eq!(size_of::<MataSupply>(), 32);
+        assert_eq!(size_of::<RevenueEntry>(), 40);
+        assert_eq!(REVENUE_LEDGER_LENGTH, 32);
+        assert_eq!(
+            size_of::<RevenueLedger>(),
+            size_of::<MetaData>() + size_of::<Pubkey>() + 8 + REVENUE_LEDGER_LENGTH * size_of::<RevenueEntry>()
+        );
+
+        assert_eq!(size_of::<Bps>(), 4);
+        assert_eq!(size_of::<CollateralRatio>(), 4);
+        assert_eq!(size_of::<Lamports>(), 8);
+        assert_eq!(size_of::<Lucra>(), 8);
+        assert_eq!(size_of::<Mata>(), 8);
+        assert_eq!(size_of::<Percent>(), 1);
+        assert_eq!(size_of::<RewardFee>(), 4);
+
+        let supply = MataSupply {
+            loan_minted_mata: 1,
+            arb_minted_mata: 2,
+            deferred_burn_mata: 3,
+            bought_back_mata: 4,
+        };
+        assert_eq!(
+            bytemuck::bytes_of(&supply),
+            &[1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        // Loans and the system state are told apart and migrated by their leading meta data
+        let mut loan = MataLoan::zeroed();
+        loan.meta_data = MetaData::new(DataType::Loan, RESERVED_LAYOUT_VERSION, true);
+        assert_eq!(&bytemuck::bytes_of(&loan)[..size_of::<MetaData>()], bytemuck::bytes_of(&loan.meta_data));
+
+        let mut system_state = SystemState::zeroed();
+        system_state.meta_data = MetaData::new(DataType::SystemState, RESERVED_LAYOUT_VERSION, true);
+        assert_eq!(&bytemuck::bytes_of(&system_state)[..size_of::<MetaData>()], bytemuck::bytes_of(&system_state.meta_data));
+    }
+}
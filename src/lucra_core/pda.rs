@@ -0,0 +1,530 @@
+use solana_program::pubkey::Pubkey;
+use crate::{
+    helpers::constants::{
+        token_metadata,
+        COLLATERAL_REGISTRY_SEED,
+        COLLATERAL_VAULT_AUTHORITY_SEED,
+        EVENT_QUEUE_SEED,
+        KEEPER_REGISTRY_SEED,
+        LOAN_REGISTRY_SEED,
+        LOAN_SEED,
+        LP_VAULT_AUTHORITY_SEED,
+        PENALTY_HISTORY_SEED,
+        PRICE_CORRECTION_SEED,
+        PRICE_HISTORY_HEADER_SEED,
+        PRICE_HISTORY_PAGE_SEED,
+        REWARD_MINT_AUTHORITY_SEED,
+        SOL_VAULT_SEED,
+        TRANSFER_ALLOWLIST_SEED,
+        TVL_SNAPSHOT_SEED,
+    },
+    id,
+    state::{staking::StakingState, ArbState, SystemState},
+};
+
+// Addresses the program signs for or expects. All of them are derived under the Lucra program id, except the
+// metaplex metadata account. `Pda` names every one of them in one place for clients, the `*_seeds` functions
+// are what the processors sign with so a signature can't drift from the address the builders derive.
+
+pub fn find_program_address(state: &Pubkey, seed: &[u8]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[&state.to_bytes()[..32], seed],
+        &id(),
+    )
+}
+
+/// Owner of a stake balance's deposit, stake and pending vaults
+pub fn find_stake_balance_authority(owner: &Pubkey, system_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), system_state.as_ref()],
+        &id(),
+    )
+}
+
+pub fn find_lp_vault_authority(system_state: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(system_state, LP_VAULT_AUTHORITY_SEED)
+}
+
+/// Holds native SOL loans' collateral as lamports. It's owned by the program, which moves lamports out of it directly
+pub fn find_sol_vault(system_state: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(system_state, SOL_VAULT_SEED)
+}
+
+/// Record of the DAO correcting the price history for `date`, one per day
+pub fn find_price_correction(system_state: &Pubkey, date: i64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[system_state.as_ref(), PRICE_CORRECTION_SEED, &date.to_le_bytes()],
+        &id(),
+    )
+}
+
+/// Tracks the pages `price_history` archives its days to, one per price history
+pub fn find_price_history_header(price_history: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(price_history, PRICE_HISTORY_HEADER_SEED)
+}
+
+/// The `page_index`th page of 30 days archived from `price_history`, the first page is 0
+pub fn find_price_history_page(price_history: &Pubkey, page_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[price_history.as_ref(), PRICE_HISTORY_PAGE_SEED, &page_index.to_le_bytes()],
+        &id(),
+    )
+}
+
+/// Audit record of the days DeterminePenalty charged `loan` for, one per loan
+pub fn find_penalty_history(loan: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[loan.as_ref(), PENALTY_HISTORY_SEED],
+        &id(),
+    )
+}
+
+/// The `loan_index`th loan `owner` opened, the index comes from their loan registry and starts at 1
+pub fn find_loan_address(owner: &Pubkey, loan_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[LOAN_SEED, owner.as_ref(), &loan_index.to_le_bytes()],
+        &id(),
+    )
+}
+
+/// Counter of the loans `owner` opened, one per user
+pub fn find_loan_registry(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[LOAN_REGISTRY_SEED, owner.as_ref()],
+        &id(),
+    )
+}
+
+/// The one TVL snapshot ComputeTvl writes to
+pub fn find_tvl_snapshot(system_state: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(system_state, TVL_SNAPSHOT_SEED)
+}
+
+/// Ring of the events the program emitted, one per system state
+pub fn find_event_queue(system_state: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(system_state, EVENT_QUEUE_SEED)
+}
+
+/// Keepers the DAO registered to run cranks, one per system state
+pub fn find_keeper_registry(system_state: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(system_state, KEEPER_REGISTRY_SEED)
+}
+
+/// Collateral types the DAO whitelisted besides msol, one per system state
+pub fn find_collateral_registry(system_state: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(system_state, COLLATERAL_REGISTRY_SEED)
+}
+
+/// Destinations TransferFunds can send to, one per system state
+pub fn find_transfer_allowlist(system_state: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(system_state, TRANSFER_ALLOWLIST_SEED)
+}
+
+/// Owner of the vault holding collateral of `mint`, every collateral type has its own
+pub fn find_collateral_vault_authority(system_state: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[system_state.as_ref(), COLLATERAL_VAULT_AUTHORITY_SEED, mint.as_ref()],
+        &id(),
+    )
+}
+
+/// Metaplex metadata account of `mint`, derived under the metadata program
+pub fn find_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"metadata", token_metadata::id().as_ref(), mint.as_ref()],
+        &token_metadata::id(),
+    )
+}
+
+pub fn stake_balance_authority_seeds<'a>(owner: &'a Pubkey, system_state: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [owner.as_ref(), system_state.as_ref(), bump_seed]
+}
+
+pub fn reward_mint_authority_seeds<'a>(system_state: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [system_state.as_ref(), REWARD_MINT_AUTHORITY_SEED, bump_seed]
+}
+
+pub fn lp_vault_authority_seeds<'a>(system_state: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [system_state.as_ref(), LP_VAULT_AUTHORITY_SEED, bump_seed]
+}
+
+pub fn collateral_vault_authority_seeds<'a>(system_state: &'a Pubkey, mint: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 4] {
+    [system_state.as_ref(), COLLATERAL_VAULT_AUTHORITY_SEED, mint.as_ref(), bump_seed]
+}
+
+pub fn sol_vault_seeds<'a>(system_state: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [system_state.as_ref(), SOL_VAULT_SEED, bump_seed]
+}
+
+pub fn tvl_snapshot_seeds<'a>(system_state: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [system_state.as_ref(), TVL_SNAPSHOT_SEED, bump_seed]
+}
+
+pub fn event_queue_seeds<'a>(system_state: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [system_state.as_ref(), EVENT_QUEUE_SEED, bump_seed]
+}
+
+pub fn keeper_registry_seeds<'a>(system_state: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [system_state.as_ref(), KEEPER_REGISTRY_SEED, bump_seed]
+}
+
+pub fn collateral_registry_seeds<'a>(system_state: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [system_state.as_ref(), COLLATERAL_REGISTRY_SEED, bump_seed]
+}
+
+pub fn transfer_allowlist_seeds<'a>(system_state: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [system_state.as_ref(), TRANSFER_ALLOWLIST_SEED, bump_seed]
+}
+
+/// `date` is the correction's date as little endian bytes
+pub fn price_correction_seeds<'a>(system_state: &'a Pubkey, date: &'a [u8; 8], bump_seed: &'a [u8; 1]) -> [&'a [u8]; 4] {
+    [system_state.as_ref(), PRICE_CORRECTION_SEED, date, bump_seed]
+}
+
+pub fn price_history_header_seeds<'a>(price_history: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [price_history.as_ref(), PRICE_HISTORY_HEADER_SEED, bump_seed]
+}
+
+/// `page_index` as little endian bytes
+pub fn price_history_page_seeds<'a>(price_history: &'a Pubkey, page_index: &'a [u8; 8], bump_seed: &'a [u8; 1]) -> [&'a [u8]; 4] {
+    [price_history.as_ref(), PRICE_HISTORY_PAGE_SEED, page_index, bump_seed]
+}
+
+pub fn penalty_history_seeds<'a>(loan: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [loan.as_ref(), PENALTY_HISTORY_SEED, bump_seed]
+}
+
+/// `loan_index` as little endian bytes
+pub fn loan_seeds<'a>(owner: &'a Pubkey, loan_index: &'a [u8; 8], bump_seed: &'a [u8; 1]) -> [&'a [u8]; 4] {
+    [LOAN_SEED, owner.as_ref(), loan_index, bump_seed]
+}
+
+pub fn loan_registry_seeds<'a>(owner: &'a Pubkey, bump_seed: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [LOAN_REGISTRY_SEED, owner.as_ref(), bump_seed]
+}
+
+/// Every address the program derives, by what it is. The system state, arb state and staking state
+/// authorities are the ones their state types have always derived.
+pub struct Pda;
+
+impl Pda {
+    pub fn msol_vault_authority(system_state: &Pubkey) -> (Pubkey, u8) {
+        SystemState::find_msol_vault_authority(system_state)
+    }
+
+    pub fn arb_coffer_authority(system_state: &Pubkey) -> (Pubkey, u8) {
+        SystemState::find_arb_coffer_authority(system_state)
+    }
+
+    pub fn rewards_vault_authority(system_state: &Pubkey) -> (Pubkey, u8) {
+        SystemState::find_rewards_vault_authority(system_state)
+    }
+
+    pub fn mata_mint_authority(system_state: &Pubkey) -> (Pubkey, u8) {
+        SystemState::find_mata_mint_authority(system_state)
+    }
+
+    pub fn lucra_mint_authority(system_state: &Pubkey) -> (Pubkey, u8) {
+        SystemState::find_lucra_mint_authority(system_state)
+    }
+
+    pub fn reward_mint_authority(system_state: &Pubkey) -> (Pubkey, u8) {
+        SystemState::find_reward_mint_authority(system_state)
+    }
+
+    pub fn lp_vault_authority(system_state: &Pubkey) -> (Pubkey, u8) {
+        find_lp_vault_authority(system_state)
+    }
+
+    pub fn sol_vault(system_state: &Pubkey) -> (Pubkey, u8) {
+        find_sol_vault(system_state)
+    }
+
+    pub fn tvl_snapshot(system_state: &Pubkey) -> (Pubkey, u8) {
+        find_tvl_snapshot(system_state)
+    }
+
+    pub fn event_queue(system_state: &Pubkey) -> (Pubkey, u8) {
+        find_event_queue(system_state)
+    }
+
+    pub fn keeper_registry(system_state: &Pubkey) -> (Pubkey, u8) {
+        find_keeper_registry(system_state)
+    }
+
+    pub fn collateral_registry(system_state: &Pubkey) -> (Pubkey, u8) {
+        find_collateral_registry(system_state)
+    }
+
+    pub fn transfer_allowlist(system_state: &Pubkey) -> (Pubkey, u8) {
+        find_transfer_allowlist(system_state)
+    }
+
+    pub fn collateral_vault_authority(system_state: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+        find_collateral_vault_authority(system_state, mint)
+    }
+
+    pub fn price_correction(system_state: &Pubkey, date: i64) -> (Pubkey, u8) {
+        find_price_correction(system_state, date)
+    }
+
+    pub fn arb_fund_authority(arb_state: &Pubkey) -> (Pubkey, u8) {
+        ArbState::find_arb_fund_authority(arb_state)
+    }
+
+    pub fn wsol_holding_vault_authority(arb_state: &Pubkey) -> (Pubkey, u8) {
+        ArbState::find_wsol_holding_vault_authority(arb_state)
+    }
+
+    pub fn mata_holding_vault_authority(arb_state: &Pubkey) -> (Pubkey, u8) {
+        ArbState::find_mata_holding_vault_authority(arb_state)
+    }
+
+    pub fn lucra_holding_vault_authority(arb_state: &Pubkey) -> (Pubkey, u8) {
+        ArbState::find_lucra_holding_vault_authority(arb_state)
+    }
+
+    pub fn stake_mint_authority(staking_state: &Pubkey) -> (Pubkey, u8) {
+        StakingState::find_stake_mint_authority(staking_state)
+    }
+
+    /// Owner of the deposit, stake and pending vaults of `owner`'s stake balances. Pending withdrawals aren't
+    /// derived, they're accounts the owner creates.
+    pub fn stake_balance_authority(owner: &Pubkey, system_state: &Pubkey) -> (Pubkey, u8) {
+        find_stake_balance_authority(owner, system_state)
+    }
+
+    pub fn loan(owner: &Pubkey, loan_index: u64) -> (Pubkey, u8) {
+        find_loan_address(owner, loan_index)
+    }
+
+    pub fn loan_registry(owner: &Pubkey) -> (Pubkey, u8) {
+        find_loan_registry(owner)
+    }
+
+    pub fn penalty_history(loan: &Pubkey) -> (Pubkey, u8) {
+        find_penalty_history(loan)
+    }
+
+    pub fn price_history_header(price_history: &Pubkey) -> (Pubkey, u8) {
+        find_price_history_header(price_history)
+    }
+
+    pub fn price_history_page(price_history: &Pubkey, page_index: u64) -> (Pubkey, u8) {
+        find_price_history_page(price_history, page_index)
+    }
+
+    pub fn metadata(mint: &Pubkey) -> (Pubkey, u8) {
+        find_metadata_address(mint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorities_are_lucra_pdas() {
+        let system_state = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+
+        let (authority, bump_seed) = find_lp_vault_authority(&system_state);
+        let derived = Pubkey::create_program_address(&[system_state.as_ref(), LP_VAULT_AUTHORITY_SEED, &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, authority);
+
+        let (authority, bump_seed) = find_stake_balance_authority(&owner, &system_state);
+        let derived = Pubkey::create_program_address(&[owner.as_ref(), system_state.as_ref(), &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, authority);
+
+        let (snapshot, bump_seed) = find_tvl_snapshot(&system_state);
+        let derived = Pubkey::create_program_address(&[system_state.as_ref(), TVL_SNAPSHOT_SEED, &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, snapshot);
+
+        let (event_queue, bump_seed) = find_event_queue(&system_state);
+        let derived = Pubkey::create_program_address(&[system_state.as_ref(), EVENT_QUEUE_SEED, &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, event_queue);
+
+        let (sol_vault, bump_seed) = find_sol_vault(&system_state);
+        let derived = Pubkey::create_program_address(&[system_state.as_ref(), SOL_VAULT_SEED, &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, sol_vault);
+
+        let (keeper_registry, bump_seed) = find_keeper_registry(&system_state);
+        let derived = Pubkey::create_program_address(&[system_state.as_ref(), KEEPER_REGISTRY_SEED, &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, keeper_registry);
+
+        let (collateral_registry, bump_seed) = find_collateral_registry(&system_state);
+        let derived = Pubkey::create_program_address(&[system_state.as_ref(), COLLATERAL_REGISTRY_SEED, &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, collateral_registry);
+
+        let (transfer_allowlist, bump_seed) = find_transfer_allowlist(&system_state);
+        let derived = Pubkey::create_program_address(&[system_state.as_ref(), TRANSFER_ALLOWLIST_SEED, &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, transfer_allowlist);
+
+        let mint = Pubkey::new_unique();
+        let (authority, bump_seed) = find_collateral_vault_authority(&system_state, &mint);
+        let derived = Pubkey::create_program_address(&[system_state.as_ref(), COLLATERAL_VAULT_AUTHORITY_SEED, mint.as_ref(), &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, authority);
+    }
+
+    #[test]
+    fn test_price_corrections_are_per_day() {
+        let system_state = Pubkey::new_unique();
+        let date = 19_000 * 86_400;
+
+        let (correction, bump_seed) = find_price_correction(&system_state, date);
+        let derived = Pubkey::create_program_address(
+            &[system_state.as_ref(), PRICE_CORRECTION_SEED, &date.to_le_bytes(), &[bump_seed]],
+            &id(),
+        )
+        .unwrap();
+        assert_eq!(derived, correction);
+        assert_ne!(find_price_correction(&system_state, date + 86_400).0, correction);
+    }
+
+    #[test]
+    fn test_price_history_pages_are_per_index() {
+        let price_history = Pubkey::new_unique();
+
+        let (header, bump_seed) = find_price_history_header(&price_history);
+        let derived = Pubkey::create_program_address(&[price_history.as_ref(), PRICE_HISTORY_HEADER_SEED, &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, header);
+
+        let (page, bump_seed) = find_price_history_page(&price_history, 0);
+        let derived = Pubkey::create_program_address(
+            &[price_history.as_ref(), PRICE_HISTORY_PAGE_SEED, &0_u64.to_le_bytes(), &[bump_seed]],
+            &id(),
+        )
+        .unwrap();
+        assert_eq!(derived, page);
+        assert_ne!(find_price_history_page(&price_history, 1).0, page);
+        assert_ne!(page, header);
+    }
+
+    #[test]
+    fn test_penalty_histories_are_per_loan() {
+        let loan = Pubkey::new_unique();
+
+        let (history, bump_seed) = find_penalty_history(&loan);
+        let derived = Pubkey::create_program_address(&[loan.as_ref(), PENALTY_HISTORY_SEED, &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, history);
+        assert_ne!(find_penalty_history(&Pubkey::new_unique()).0, history);
+    }
+
+    #[test]
+    fn test_loans_are_per_owner_and_index() {
+        let owner = Pubkey::new_unique();
+
+        let (loan, bump_seed) = find_loan_address(&owner, 1);
+        let derived = Pubkey::create_program_address(&[LOAN_SEED, owner.as_ref(), &1_u64.to_le_bytes(), &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, loan);
+        assert_ne!(find_loan_address(&owner, 2).0, loan);
+        assert_ne!(find_loan_address(&Pubkey::new_unique(), 1).0, loan);
+
+        let (registry, bump_seed) = find_loan_registry(&owner);
+        let derived = Pubkey::create_program_address(&[LOAN_REGISTRY_SEED, owner.as_ref(), &[bump_seed]], &id()).unwrap();
+        assert_eq!(derived, registry);
+        assert_ne!(registry, loan);
+    }
+
+    #[test]
+    fn test_pda_helpers_keep_their_historical_addresses() {
+        let derive = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &id());
+        let system_state = Pubkey::new_unique();
+        let arb_state = Pubkey::new_unique();
+        let staking_state = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let loan = Pubkey::new_unique();
+        let price_history = Pubkey::new_unique();
+        let ss = system_state.as_ref();
+
+        // Seeds spelled out as they were deployed, not through the constants, so renaming a constant can't move an account
+        assert_eq!(Pda::reward_mint_authority(&system_state), derive(&[ss, b"reward_mint_authority"]));
+        assert_eq!(Pda::lp_vault_authority(&system_state), derive(&[ss, b"lp_vault_authority"]));
+        assert_eq!(Pda::sol_vault(&system_state), derive(&[ss, b"sol_vault"]));
+        assert_eq!(Pda::tvl_snapshot(&system_state), derive(&[ss, b"tvl_snapshot"]));
+        assert_eq!(Pda::event_queue(&system_state), derive(&[ss, b"event_queue"]));
+        assert_eq!(Pda::keeper_registry(&system_state), derive(&[ss, b"keeper_registry"]));
+        assert_eq!(Pda::collateral_registry(&system_state), derive(&[ss, b"collateral_registry"]));
+        assert_eq!(Pda::transfer_allowlist(&system_state), derive(&[ss, b"transfer_allowlist"]));
+        assert_eq!(Pda::collateral_vault_authority(&system_state, &mint), derive(&[ss, b"collateral_vault_authority", mint.as_ref()]));
+        assert_eq!(Pda::price_correction(&system_state, 86_400), derive(&[ss, b"price_correction", &86_400_i64.to_le_bytes()]));
+        assert_eq!(Pda::stake_balance_authority(&owner, &system_state), derive(&[owner.as_ref(), ss]));
+        assert_eq!(Pda::loan(&owner, 3), derive(&[b"loan", owner.as_ref(), &3_u64.to_le_bytes()]));
+        assert_eq!(Pda::loan_registry(&owner), derive(&[b"loan_registry", owner.as_ref()]));
+        assert_eq!(Pda::penalty_history(&loan), derive(&[loan.as_ref(), b"penalty_history"]));
+        assert_eq!(Pda::price_history_header(&price_history), derive(&[price_history.as_ref(), b"price_history_header"]));
+        assert_eq!(Pda::price_history_page(&price_history, 2), derive(&[price_history.as_ref(), b"price_history_page", &2_u64.to_le_bytes()]));
+        assert_eq!(Pda::metadata(&mint), find_metadata_address(&mint));
+
+        // The state accounts' own authorities forward to the state modules' find_* functions, which own their seeds.
+        // These only pin that Pda keeps forwarding, the seeds themselves drift with the state modules.
+        assert_eq!(Pda::msol_vault_authority(&system_state), SystemState::find_msol_vault_authority(&system_state));
+        assert_eq!(Pda::arb_coffer_authority(&system_state), SystemState::find_arb_coffer_authority(&system_state));
+        assert_eq!(Pda::rewards_vault_authority(&system_state), SystemState::find_rewards_vault_authority(&system_state));
+        assert_eq!(Pda::mata_mint_authority(&system_state), SystemState::find_mata_mint_authority(&system_state));
+        assert_eq!(Pda::lucra_mint_authority(&system_state), SystemState::find_lucra_mint_authority(&system_state));
+        assert_eq!(Pda::arb_fund_authority(&arb_state), ArbState::find_arb_fund_authority(&arb_state));
+        assert_eq!(Pda::wsol_holding_vault_authority(&arb_state), ArbState::find_wsol_holding_vault_authority(&arb_state));
+        assert_eq!(Pda::mata_holding_vault_authority(&arb_state), ArbState::find_mata_holding_vault_authority(&arb_state));
+        assert_eq!(Pda::lucra_holding_vault_authority(&arb_state), ArbState::find_lucra_holding_vault_authority(&arb_state));
+        assert_eq!(Pda::stake_mint_authority(&staking_state), StakingState::find_stake_mint_authority(&staking_state));
+    }
+
+    #[test]
+    fn test_signer_seeds_sign_for_the_derived_addresses() {
+        let signs_for = |seeds: &[&[u8]], (address, _): (Pubkey, u8)| assert_eq!(Pubkey::create_program_address(seeds, &id()).unwrap(), address);
+        let system_state = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let loan = Pubkey::new_unique();
+        let price_history = Pubkey::new_unique();
+        let (date, index) = (86_400_i64.to_le_bytes(), 2_u64.to_le_bytes());
+
+        let pda = Pda::stake_balance_authority(&owner, &system_state);
+        signs_for(&stake_balance_authority_seeds(&owner, &system_state, &[pda.1]), pda);
+        let pda = Pda::reward_mint_authority(&system_state);
+        signs_for(&reward_mint_authority_seeds(&system_state, &[pda.1]), pda);
+        let pda = Pda::lp_vault_authority(&system_state);
+        signs_for(&lp_vault_authority_seeds(&system_state, &[pda.1]), pda);
+        let pda = Pda::collateral_vault_authority(&system_state, &mint);
+        signs_for(&collateral_vault_authority_seeds(&system_state, &mint, &[pda.1]), pda);
+        let pda = Pda::sol_vault(&system_state);
+        signs_for(&sol_vault_seeds(&system_state, &[pda.1]), pda);
+        let pda = Pda::tvl_snapshot(&system_state);
+        signs_for(&tvl_snapshot_seeds(&system_state, &[pda.1]), pda);
+        let pda = Pda::event_queue(&system_state);
+        signs_for(&event_queue_seeds(&system_state, &[pda.1]), pda);
+        let pda = Pda::keeper_registry(&system_state);
+        signs_for(&keeper_registry_seeds(&system_state, &[pda.1]), pda);
+        let pda = Pda::collateral_registry(&system_state);
+        signs_for(&collateral_registry_seeds(&system_state, &[pda.1]), pda);
+        let pda = Pda::transfer_allowlist(&system_state);
+        signs_for(&transfer_allowlist_seeds(&system_state, &[pda.1]), pda);
+        let pda = Pda::price_correction(&system_state, 86_400);
+        signs_for(&price_correction_seeds(&system_state, &date, &[pda.1]), pda);
+        let pda = Pda::price_history_header(&price_history);
+        signs_for(&price_history_header_seeds(&price_history, &[pda.1]), pda);
+        let pda = Pda::price_history_page(&price_history, 2);
+        signs_for(&price_history_page_seeds(&price_history, &index, &[pda.1]), pda);
+        let pda = Pda::penalty_history(&loan);
+        signs_for(&penalty_history_seeds(&loan, &[pda.1]), pda);
+        let pda = Pda::loan(&owner, 2);
+        signs_for(&loan_seeds(&owner, &index, &[pda.1]), pda);
+        let pda = Pda::loan_registry(&owner);
+        signs_for(&loan_registry_seeds(&owner, &[pda.1]), pda);
+    }
+
+    #[test]
+    fn test_metadata_address_is_a_metaplex_pda() {
+        let mint = Pubkey::new_unique();
+        let (address, bump_seed) = find_metadata_address(&mint);
+
+        let derived = Pubkey::create_program_address(
+            &[b"metadata", token_metadata::id().as_ref(), mint.as_ref(), &[bump_seed]],
+            &token_metadata::id(),
+        )
+        .unwrap();
+        assert_eq!(derived, address);
+    }
+}
@@ -0,0 +1,366 @@
+use std::convert::TryFrom;
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use solana_program::{
+    clock::UnixTimestamp,
+    native_token::LAMPORTS_PER_SOL,
+};
+use time::{OffsetDateTime, Time};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::{LAMPORTS_PER_LUCRA, LAMPORTS_PER_MATA},
+    helpers::math::calculate_annual_interest_rate,
+    lucra_core::interest::calc_amount_owed,
+    lucra_core::quote::{calc_msol_lamport_value, get_price},
+    state::{HistoricPrice, LoanType, MataLoan},
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::CorePenalty);
+
+// Penalty is charged per day on a loan's SOL collateral once it falls under its collateral requirement,
+// and never more than the collateral the loan has left.
+
+/// The DAO sets the band the peg is held in and how fast the penalty grows under it. Bounds and band width
+/// are bps of $1.
+pub fn verify_peg_band(
+    peg_lower_bound: Bps,
+    peg_upper_bound: Bps,
+    penalty_band_width: Bps,
+    penalty_multiplier_per_band: u32,
+) -> LucraResult {
+    check!(peg_lower_bound > Bps(0), LucraErrorCode::InvalidAmount)?;
+    check!(peg_lower_bound <= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)?;
+    check!(peg_upper_bound >= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)?;
+    check!(penalty_band_width > Bps(0), LucraErrorCode::InvalidAmount)?;
+    check!(penalty_band_width <= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)?;
+    check!(penalty_multiplier_per_band > 0, LucraErrorCode::InvalidAmount)
+}
+
+/// What `helpers::peg_band::update_peg` sets `peg_broken` to
+pub fn is_peg_broken(mata_price: Decimal, peg_lower_bound: Bps, peg_upper_bound: Bps) -> bool {
+    mata_price < peg_lower_bound.to_decimal() || mata_price > peg_upper_bound.to_decimal()
+}
+
+#[inline(never)]
+pub fn calculate_penalty_multiplier(mata_deviation_bps: u16, penalty_band_width: Bps, penalty_multiplier_per_band: u32) -> u64 {
+    // penalty multiplier is based off of how far the day's average mata price was under the peg,
+    // every band started under it adds another step
+
+    // if mata held the peg then there is no penalty multiplier
+    if mata_deviation_bps == 0 {
+        return 1;
+    }
+
+    // A zero width never gets past verify_peg_band, treating it as 1 bps keeps this from dividing by it
+    let band_width = u64::from(penalty_band_width.get()).max(1);
+    let bands = (u64::from(mata_deviation_bps) + band_width - 1) / band_width;
+
+    bands.saturating_mul(penalty_multiplier_per_band.into())
+}
+
+/// Midnight UTC of the day `timestamp` falls in, the dates the price history is keyed by
+pub fn start_of_day(timestamp: UnixTimestamp) -> UnixTimestamp {
+    OffsetDateTime::from_unix_timestamp(timestamp)
+        .unwrap()
+        .replace_time(Time::from_hms(0, 0, 0).unwrap())
+        .unix_timestamp()
+}
+
+/// Dollar value of a loan's collateral at one day's prices
+#[inline(never)]
+pub fn calculate_collateral_value(sol_price: u64, sol_decimals: u8, sol_collateral_amount: u64, collateral_factor: Decimal, lucra_price: u64, lucra_decimals: u8, staking_collateral_amount: u64) -> LucraResult<Decimal> {
+    // Calculate the value of the collateral
+    let sol_market_price = get_price(sol_price, sol_decimals)?;
+    let lucra_market_price = get_price(lucra_price, lucra_decimals)?;
+    let sol_side = Decimal::from(sol_collateral_amount)
+        .checked_mul(sol_market_price)
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())?
+        // Only the sol side is held as msol
+        .checked_mul(collateral_factor)
+        .ok_or(math_err!())?;
+    let lucra_side = Decimal::from(staking_collateral_amount)
+        .checked_mul(lucra_market_price)
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_LUCRA)
+        .ok_or(math_err!())?;
+
+    sol_side
+        .checked_add(lucra_side)
+        .ok_or(math_err!())
+}
+
+/// Penalty for one day at that day's prices, multiplied by how far under the peg mata was that day.
+/// Which days get charged and the cap on the loan's collateral are up to the caller.
+pub fn calc_day_penalty(
+    history: &HistoricPrice,
+    loan: &MataLoan,
+    collateral_factor: Decimal,
+    penalty_band_width: Bps,
+    penalty_multiplier_per_band: u32,
+) -> LucraResult<u64> {
+    let one_day = dec!(1).checked_div(356.into()).unwrap();
+
+    // Find value of collateral for given day
+    let collateral_value = calculate_collateral_value(history.sol_price, history.sol_decimals, loan.sol_collateral_amount, collateral_factor, history.lucra_price, history.lucra_decimals, loan.staking_collateral_amount).unwrap();
+    // Find the penalty rate for the collateral
+    let annual_penalty_rate = loan.calc_penalty_rate_percentage(collateral_value)?;
+    let penalty_multiplier = calculate_penalty_multiplier(history.mata_deviation_bps, penalty_band_width, penalty_multiplier_per_band);
+    Ok(calculate_annual_interest_rate(annual_penalty_rate, loan.sol_collateral_amount, one_day)? * penalty_multiplier)
+}
+
+/// Lamports of collateral a loan can still be penalized for, net of penalty already harvested or waiting to be
+pub fn calc_remaining_penalty_collateral(loan: &MataLoan) -> u64 {
+    loan.sol_collateral_amount
+        .saturating_sub(loan.penalty_harvested)
+        .saturating_sub(loan.penalty_to_harvest)
+}
+
+/// The loan DeterminePenalty and RecomputePenalty charge. LP backed loans have no SOL collateral, they're
+/// charged as if they held the SOL their LP tokens were worth when they were opened, `lp_sol_equivalent`. Their
+/// penalty is in those lamports like every other loan's, `calc_lp_penalty_tokens` gives the LP tokens it takes.
+pub fn penalized_loan(loan: &MataLoan) -> MataLoan {
+    let mut penalized = *loan;
+    if loan.loan_type == LoanType::LpBacked {
+        penalized.sol_collateral_amount = loan.lp_sol_equivalent;
+    }
+
+    penalized
+}
+
+/// LP tokens worth `lamports` at the rate an LP backed loan was opened at, rounded down and never more than
+/// the loan holds
+pub fn calc_lp_tokens_for_lamports(loan: &MataLoan, lamports: u64) -> LucraResult<u64> {
+    if loan.lp_sol_equivalent == 0 {
+        return Ok(0);
+    }
+
+    let lp_tokens = u128::from(lamports)
+        .checked_mul(u128::from(loan.lp_collateral_amount))
+        .ok_or(math_err!())?
+        / u128::from(loan.lp_sol_equivalent);
+
+    Ok(u64::try_from(lp_tokens).map_err(|_| math_err!())?.min(loan.lp_collateral_amount))
+}
+
+/// LP tokens an LP backed loan's penalty waiting to be harvested takes out of its collateral. Nothing harvests
+/// LP tokens, a close or a liquidation keeps them in the LP vault.
+pub fn calc_lp_penalty_tokens(loan: &MataLoan) -> LucraResult<u64> {
+    calc_lp_tokens_for_lamports(loan, loan.penalty_to_harvest)
+}
+
+/// What the loan owes, interest included and counted at the peg, over what's left of its collateral at market
+/// prices. A loan with nothing left backing it has no ltv.
+pub fn calc_effective_ltv(loan: &MataLoan, sol_price: Decimal, lucra_price: Decimal) -> LucraResult<Decimal> {
+    let sol_side = Decimal::from(calc_remaining_penalty_collateral(loan))
+        .checked_mul(sol_price)
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())?;
+    let lucra_side = Decimal::from(loan.staking_collateral_amount)
+        .checked_mul(lucra_price)
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_LUCRA)
+        .ok_or(math_err!())?;
+    let collateral_value = sol_side
+        .checked_add(lucra_side)
+        .ok_or(math_err!())?;
+    check!(collateral_value > Decimal::ZERO, LucraErrorCode::InvalidAmount)?;
+
+    Decimal::from(calc_amount_owed(loan)?)
+        .checked_div(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .checked_div(collateral_value)
+        .ok_or(math_err!())
+}
+
+/// Penalty a harvest can actually take. Penalty accrued before the cap counted pending penalty can exceed the
+/// loan's remaining collateral, and the msol the loan put in can be worth less than the books say, so anything
+/// over either is dropped rather than failing the harvest forever.
+pub fn calc_harvestable_penalty(loan: &MataLoan, marinade_rate: Decimal) -> LucraResult<u64> {
+    let remaining_collateral = loan.sol_collateral_amount.saturating_sub(loan.penalty_harvested);
+    let msol_collateral_lamports = calc_msol_lamport_value(loan.msol_collateral_amount, marinade_rate)?;
+
+    Ok(loan.penalty_to_harvest.min(remaining_collateral).min(msol_collateral_lamports))
+}
+
+/// Native SOL collateral is held as the lamports themselves, so only the remaining collateral caps the harvest
+pub fn calc_harvestable_native_penalty(loan: &MataLoan) -> u64 {
+    loan.penalty_to_harvest.min(loan.sol_collateral_amount.saturating_sub(loan.penalty_harvested))
+}
+
+/// Msol a harvest takes out of the vault. The vault is shared, so a loan never takes more than it put in
+/// even if the marinade rate moved since the penalty was accrued.
+pub fn calc_harvest_msol(msol_for_penalty: u64, loan: &MataLoan) -> u64 {
+    msol_for_penalty.min(loan.msol_collateral_amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use crate::{
+        lucra_core::interest::LOAN_INTEREST_VERSION,
+        state::{DataType, MetaData},
+    };
+
+    #[test]
+    fn test_penalty_multiplier_doubles_every_five_cents() {
+        let multiplier = |deviation| calculate_penalty_multiplier(deviation, Bps(500), 2);
+
+        assert_eq!(multiplier(0), 1);
+        assert_eq!(multiplier(1), 2);
+        assert_eq!(multiplier(500), 2);
+        assert_eq!(multiplier(1_000), 4);
+        assert_eq!(multiplier(10_000), 40);
+    }
+
+    #[test]
+    fn test_penalty_multiplier_follows_the_band() {
+        // Every cent triples it
+        let multiplier = |deviation| calculate_penalty_multiplier(deviation, Bps(100), 3);
+        assert_eq!(multiplier(0), 1);
+        assert_eq!(multiplier(100), 3);
+        assert_eq!(multiplier(101), 6);
+        assert_eq!(multiplier(10_000), 300);
+
+        // One band for the whole dollar
+        let multiplier = |deviation| calculate_penalty_multiplier(deviation, Bps::ONE_HUNDRED_PERCENT, 5);
+        assert_eq!(multiplier(1), 5);
+        assert_eq!(multiplier(10_000), 5);
+
+        // Bands that don't divide a dollar evenly still count a started band
+        let multiplier = |deviation| calculate_penalty_multiplier(deviation, Bps(300), 2);
+        assert_eq!(multiplier(300), 2);
+        assert_eq!(multiplier(301), 4);
+        assert_eq!(multiplier(10_000), 68);
+
+        // A multiplier of one only flags that the peg was missed
+        assert_eq!(calculate_penalty_multiplier(10_000, Bps(500), 1), 20);
+    }
+
+    #[test]
+    fn test_peg_band_validation() {
+        assert!(verify_peg_band(Bps(9_800), Bps(10_200), Bps(500), 2).is_ok());
+        assert!(verify_peg_band(Bps::ONE_HUNDRED_PERCENT, Bps::ONE_HUNDRED_PERCENT, Bps::ONE_HUNDRED_PERCENT, 1).is_ok());
+
+        assert!(verify_peg_band(Bps(9_800), Bps(10_200), Bps(0), 2).is_err());
+        assert!(verify_peg_band(Bps(9_800), Bps(10_200), Bps(10_001), 2).is_err());
+        assert!(verify_peg_band(Bps(9_800), Bps(10_200), Bps(500), 0).is_err());
+        assert!(verify_peg_band(Bps(0), Bps(10_200), Bps(500), 2).is_err());
+        assert!(verify_peg_band(Bps(10_100), Bps(10_200), Bps(500), 2).is_err());
+        assert!(verify_peg_band(Bps(9_800), Bps(9_900), Bps(500), 2).is_err());
+    }
+
+    #[test]
+    fn test_peg_breaks_outside_the_band() {
+        let broken = |price| is_peg_broken(price, Bps(9_800), Bps(10_200));
+
+        assert!(!broken(dec!(0.98)));
+        assert!(!broken(Decimal::ONE));
+        assert!(!broken(dec!(1.02)));
+        assert!(broken(dec!(0.9799)));
+        assert!(broken(dec!(1.0201)));
+    }
+
+    #[test]
+    fn test_start_of_day() {
+        assert_eq!(start_of_day(0), 0);
+        assert_eq!(start_of_day(86_399), 0);
+        assert_eq!(start_of_day(86_400), 86_400);
+        assert_eq!(start_of_day(86_400 * 3 + 12), 86_400 * 3);
+    }
+
+    #[test]
+    fn test_collateral_value_adds_both_sides() {
+        // 10 SOL at $20 and 200 LUCRA at $1
+        let actual = calculate_collateral_value(20_000_000, 6, 10 * LAMPORTS_PER_SOL, Decimal::ONE, 1_000_000, 6, 200 * 1_000_000_000).unwrap();
+
+        assert_eq!(actual, dec!(400));
+    }
+
+    #[test]
+    fn test_lp_loans_are_penalized_on_their_sol_equivalent() {
+        let loan = MataLoan {
+            loan_type: LoanType::LpBacked,
+            lp_collateral_amount: 4_000_000,
+            lp_sol_equivalent: 10 * LAMPORTS_PER_SOL,
+            ..MataLoan::default()
+        };
+        assert_eq!(penalized_loan(&loan).sol_collateral_amount, 10 * LAMPORTS_PER_SOL);
+        assert_eq!(penalized_loan(&loan).lp_collateral_amount, 4_000_000);
+
+        // Other loans are charged on their own collateral
+        let msol_loan = MataLoan { sol_collateral_amount: LAMPORTS_PER_SOL, lp_sol_equivalent: 10 * LAMPORTS_PER_SOL, ..MataLoan::default() };
+        assert_eq!(penalized_loan(&msol_loan).sol_collateral_amount, LAMPORTS_PER_SOL);
+    }
+
+    #[test]
+    fn test_lp_penalty_is_taken_at_the_opening_rate() {
+        // 4 LP tokens were worth 10 SOL, 1 SOL of penalty takes 0.4 of them
+        let mut loan = MataLoan {
+            loan_type: LoanType::LpBacked,
+            lp_collateral_amount: 4_000_000,
+            lp_sol_equivalent: 10 * LAMPORTS_PER_SOL,
+            penalty_to_harvest: LAMPORTS_PER_SOL,
+            ..MataLoan::default()
+        };
+        assert_eq!(calc_lp_penalty_tokens(&loan).unwrap(), 400_000);
+
+        // Rounded down, and never more than the loan holds
+        loan.penalty_to_harvest = 2_499;
+        assert_eq!(calc_lp_penalty_tokens(&loan).unwrap(), 0);
+        loan.penalty_to_harvest = 20 * LAMPORTS_PER_SOL;
+        assert_eq!(calc_lp_penalty_tokens(&loan).unwrap(), 4_000_000);
+
+        // Loans opened before the SOL equivalent was recorded were never charged
+        loan.lp_sol_equivalent = 0;
+        assert_eq!(calc_lp_penalty_tokens(&loan).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_effective_ltv() {
+        // $200 owed against 8 SOL at $25 and 100 LUCRA at $0.50
+        let loan = MataLoan {
+            loan_amount: 190_000_000,
+            interest_accrued: 10_000_000,
+            sol_collateral_amount: 8 * LAMPORTS_PER_SOL,
+            staking_collateral_amount: 100 * 1_000_000_000,
+            meta_data: MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true),
+            ..MataLoan::default()
+        };
+        assert_eq!(calc_effective_ltv(&loan, dec!(25), dec!(0.5)).unwrap(), dec!(0.8));
+
+        // Harvested and pending penalty no longer back the loan
+        let loan = MataLoan { penalty_harvested: LAMPORTS_PER_SOL, penalty_to_harvest: LAMPORTS_PER_SOL, ..loan };
+        assert_eq!(calc_effective_ltv(&loan, dec!(25), dec!(0.5)).unwrap(), dec!(1));
+
+        let loan = MataLoan { sol_collateral_amount: 2 * LAMPORTS_PER_SOL, staking_collateral_amount: 0, ..loan };
+        assert!(calc_effective_ltv(&loan, dec!(25), dec!(0.5)).is_err());
+    }
+
+    #[test]
+    fn test_native_harvest_is_capped_at_the_remaining_collateral() {
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            penalty_harvested: 9 * LAMPORTS_PER_SOL,
+            penalty_to_harvest: LAMPORTS_PER_SOL / 2,
+            ..MataLoan::default()
+        };
+        assert_eq!(calc_harvestable_native_penalty(&loan), LAMPORTS_PER_SOL / 2);
+
+        // Penalty accrued past the collateral is dropped, the msol side of the loan doesn't matter
+        let loan = MataLoan { penalty_to_harvest: 2 * LAMPORTS_PER_SOL, msol_collateral_amount: 0, ..loan };
+        assert_eq!(calc_harvestable_native_penalty(&loan), LAMPORTS_PER_SOL);
+        assert_eq!(calc_harvestable_native_penalty(&MataLoan { penalty_harvested: 11 * LAMPORTS_PER_SOL, ..loan }), 0);
+    }
+}
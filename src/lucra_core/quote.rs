@@ -0,0 +1,451 @@
+use std::convert::TryFrom;
+
+use rust_decimal::{Decimal, MathematicalOps, prelude::ToPrimitive};
+use serde::{Deserialize, Serialize};
+use solana_program::native_token::LAMPORTS_PER_SOL;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::{LAMPORTS_PER_MATA, MAX_LCP, MAX_REWARD_DECIMALS, MAX_REWARD_FEE, MIN_HARVEST_REWARD_FEES},
+    units::{Bps, CollateralRatio, Lamports, Mata, Percent, RewardFee},
+};
+
+declare_check_assert_macros!(SourceFileId::CoreQuote);
+
+// Prices and conversions loans and rewards are sized with. Everything rounds down, in the program's favour.
+
+/// Oracle price stored as an integer with `expo` decimals
+pub fn get_price(price: u64, expo: u8) -> LucraResult<Decimal> {
+    Ok(
+        Decimal::from(price)
+            .checked_div(
+                Decimal::TEN.powi(expo as i64)
+            )
+            .unwrap()
+    )
+}
+
+/// Share of the collateral's SOL value that loans are valued against
+pub fn calc_collateral_valuation_factor(haircut: Bps, marinade_rate: Decimal, market_rate: Option<Decimal>) -> LucraResult<Decimal> {
+    // UpdateState and Initialize already refuse a haircut over 100%
+    invariant!(haircut <= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)?;
+    let haircut = Decimal::ONE
+        .checked_sub(haircut.to_decimal())
+        .ok_or(math_err!())?;
+
+    // Value at min(marinade_rate, market_rate), a market trading above redemption doesn't add anything
+    let depeg = match market_rate {
+        Some(market_rate) if market_rate < marinade_rate => market_rate
+            .checked_div(marinade_rate)
+            .ok_or(math_err!())?,
+        _ => Decimal::ONE,
+    };
+
+    haircut
+        .checked_mul(depeg)
+        .ok_or(math_err!())
+}
+
+/// Lamports `msol_amount` redeems for at the marinade rate, rounded down
+pub fn calc_msol_lamport_value(msol_amount: u64, marinade_rate: Decimal) -> LucraResult<u64> {
+    Decimal::from(msol_amount)
+        .checked_mul(marinade_rate)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+/// Checked by Initialize and UpdateState. A loan never mints more mata than its collateral is worth.
+pub fn verify_loan_terms(collateral_requirement: CollateralRatio, lcp: Percent) -> LucraResult {
+    check!(collateral_requirement >= CollateralRatio(100), LucraErrorCode::InvalidAmount)?;
+    check!(lcp <= MAX_LCP, LucraErrorCode::InvalidAmount)
+}
+
+/// Redemptions are paid out of the rewards vault, a runaway fee would empty it
+pub fn verify_reward_fee(reward_fee: RewardFee) -> LucraResult {
+    check!(reward_fee <= MAX_REWARD_FEE, LucraErrorCode::InvalidAmount)
+}
+
+/// Checked by UpdateState and UpdateHarvestParams with the values they end up with. The fee stays well under
+/// 0.1 SOL, and a harvest too small to pay for it ten times over would mostly go to the fee.
+pub fn verify_harvest_params(minimum_harvest_amount: Lamports, reward_fee: RewardFee) -> LucraResult {
+    verify_reward_fee(reward_fee)?;
+    let min_harvest = reward_fee
+        .to_lamports()
+        .get()
+        .checked_mul(MIN_HARVEST_REWARD_FEES)
+        .ok_or(math_err!())?;
+    check!(minimum_harvest_amount >= Lamports(min_harvest), LucraErrorCode::InvalidAmount)
+}
+
+/// Mata a loan mints against `supplied_collateral` dollars of collateral
+pub fn calc_loan_amount(supplied_collateral: Decimal, collateral_requirement: CollateralRatio) -> LucraResult<Mata> {
+    let loan_amount = supplied_collateral
+        .checked_div(collateral_requirement.to_decimal())
+        .ok_or(math_err!())?
+        .checked_mul(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())?;
+
+    Ok(Mata(loan_amount))
+}
+
+/// Dollar value a loan counts `lamports` of SOL collateral at
+pub fn calc_sol_collateral_value(lamports: u64, sol_price: Decimal, collateral_factor: Decimal) -> LucraResult<Decimal> {
+    sol_price
+        .checked_mul(lamports.into())
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())?
+        .checked_mul(collateral_factor)
+        .ok_or(math_err!())
+}
+
+/// Lamports of SOL worth `value` dollars, rounded down
+pub fn calc_lamports_for_value(value: Decimal, sol_price: Decimal) -> LucraResult<u64> {
+    value
+        .checked_div(sol_price)
+        .ok_or(math_err!())?
+        .checked_mul(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+/// Dollar value of lucra stake a locked stake loan of `lamports` has to lock
+pub fn calc_required_stake_value(lcp: Percent, lamports: Lamports, sol_price: Decimal) -> LucraResult<Decimal> {
+    Decimal::from(lamports)
+        .checked_mul(lcp.to_decimal())
+        .ok_or(math_err!())?
+        .checked_mul(sol_price)
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())
+}
+
+/// What a new loan is opened with
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoanQuote {
+    pub loan_amount: Mata,
+    /// Dollars of lucra stake the loan locks, zero without locked stake
+    pub staking_value_required: u64,
+    /// The SOL collateral's value over the mata minted, locked stake makes up the rest of the requirement.
+    /// Zero when nothing would be minted.
+    pub collateral_ratio: Bps,
+}
+
+/// Sizes a loan of `lamports` the way CreateMataLoan opens it. `lcp` is the system state's for a loan with
+/// locked stake and None without.
+pub fn quote_mata_loan(
+    lamports: u64,
+    sol_price: Decimal,
+    collateral_factor: Decimal,
+    collateral_requirement: CollateralRatio,
+    lcp: Option<Percent>,
+) -> LucraResult<LoanQuote> {
+    let sol_collateral_value = calc_sol_collateral_value(lamports, sol_price, collateral_factor)?;
+    let staking_value_required = match lcp {
+        Some(lcp) => calc_required_stake_value(lcp, Lamports(lamports), sol_price)?,
+        None => Decimal::ZERO,
+    };
+    let total_value_supplied = sol_collateral_value
+        .checked_add(staking_value_required)
+        .ok_or(math_err!())?;
+    let loan_amount = calc_loan_amount(total_value_supplied, collateral_requirement)?;
+
+    let collateral_ratio = if loan_amount == Mata(0) {
+        Bps(0)
+    } else {
+        let bps = sol_collateral_value
+            .checked_div(loan_amount.to_decimal())
+            .ok_or(math_err!())?
+            .checked_mul(Bps::ONE_HUNDRED_PERCENT.get().into())
+            .ok_or(math_err!())?
+            .floor()
+            .to_u32()
+            .ok_or(math_err!())?;
+        Bps(bps)
+    };
+
+    Ok(LoanQuote {
+        loan_amount,
+        staking_value_required: staking_value_required.floor().to_u64().ok_or(math_err!())?,
+        collateral_ratio,
+    })
+}
+
+/// Return data of QuoteMataLoan. Both loan types are quoted for the same lamports, a loan with locked stake
+/// also needs staking enabled and that much stake left to lock.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MataLoanQuote {
+    pub loan: LoanQuote,
+    pub loan_with_locked_stake: LoanQuote,
+    /// CreateMataLoan fails with BrokenPeg while the peg is broken
+    pub peg_broken: bool,
+}
+
+/// Mata a loan owing `loan_amount` can still mint before its collateral falls under the requirement
+pub fn calc_borrowable_mata(collateral_value: Decimal, collateral_requirement: CollateralRatio, loan_amount: u64) -> LucraResult<u64> {
+    Ok(calc_loan_amount(collateral_value, collateral_requirement)?.get().saturating_sub(loan_amount))
+}
+
+/// Share of `amount` that repaying `repaid_mata` of a `loan_amount` debt frees
+pub fn calc_repaid_share(amount: u64, repaid_mata: u64, loan_amount: u64) -> LucraResult<u64> {
+    check!(repaid_mata <= loan_amount, LucraErrorCode::InvalidAmount)?;
+    if repaid_mata == loan_amount {
+        return Ok(amount);
+    }
+
+    let share = (amount as u128)
+        .checked_mul(repaid_mata as u128)
+        .ok_or(math_err!())?
+        / loan_amount as u128;
+
+    Ok(share as u64)
+}
+
+pub fn verify_reward_decimals(decimals: u8) -> LucraResult {
+    check!(decimals <= MAX_REWARD_DECIMALS, LucraErrorCode::InvalidAccountInput)
+}
+
+/// Native amount of `whole_tokens` reward tokens
+pub fn calc_reward_base_units(whole_tokens: u64, decimals: u8) -> LucraResult<u64> {
+    verify_reward_decimals(decimals)?;
+
+    whole_tokens
+        .checked_mul(10_u64.pow(decimals.into()))
+        .ok_or(math_err!())
+}
+
+/// Native amount of `share` out of `of` parts of `whole_tokens` reward tokens, never more than all of them
+pub fn calc_partial_reward_base_units(whole_tokens: u64, decimals: u8, share: u64, of: u64) -> LucraResult<u64> {
+    check!(of > 0, LucraErrorCode::InvalidAmount)?;
+    let base_units = calc_reward_base_units(whole_tokens, decimals)?;
+
+    Ok((base_units as u128 * std::cmp::min(share, of) as u128 / of as u128) as u64)
+}
+
+/// `base_units` of a crank's reward scaled by the multiplier of the keeper that ran it
+pub fn calc_keeper_reward_base_units(base_units: u64, multiplier: Bps) -> LucraResult<u64> {
+    let scaled = u128::from(base_units)
+        .checked_mul(u128::from(multiplier.get()))
+        .ok_or(math_err!())?
+        / u128::from(Bps::ONE_HUNDRED_PERCENT.get());
+
+    u64::try_from(scaled).map_err(|_| math_err!())
+}
+
+/// Lamports a native amount of reward tokens redeems for
+pub fn calc_reward_lamports(reward_fee: RewardFee, base_units: u64, decimals: u8) -> LucraResult<u64> {
+    calc_reward_base_units(1, decimals)?;
+
+    Decimal::from(reward_fee.to_lamports().get())
+        .checked_mul(base_units.into())
+        .ok_or(math_err!())?
+        .checked_div(Decimal::from(10_u64.pow(decimals.into())))
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+/// Lucra `reward_lamports` redeem for at the LUCRA/SOL oracle price
+pub fn calc_reward_lucra(reward_lamports: u64, lucra_price: Decimal) -> LucraResult<u64> {
+    Decimal::from(reward_lamports)
+        .checked_div(lucra_price)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+/// msol paid out in place of the lucra `reward_lamports` redeem for, that lucra's SOL value at marinade's msol
+/// price. Redeeming for msol never pays more than redeeming for lucra would be worth.
+pub fn calc_reward_msol(reward_lamports: u64, lucra_price: Decimal, marinade_rate: Decimal) -> LucraResult<u64> {
+    let lucra = calc_reward_lucra(reward_lamports, lucra_price)?;
+
+    Decimal::from(lucra)
+        .checked_mul(lucra_price)
+        .ok_or(math_err!())?
+        .floor()
+        .checked_div(marinade_rate)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_get_price() {
+        assert_eq!(get_price(20_000_000, 6).unwrap(), dec!(20));
+        assert_eq!(get_price(1_500, 3).unwrap(), dec!(1.5));
+        assert_eq!(get_price(7, 0).unwrap(), dec!(7));
+    }
+
+    #[test]
+    fn test_lamports_for_value_round_down() {
+        assert_eq!(calc_lamports_for_value(dec!(200), dec!(20)).unwrap(), 10 * LAMPORTS_PER_SOL);
+        assert_eq!(calc_lamports_for_value(dec!(1), dec!(3)).unwrap(), 333_333_333);
+        assert!(calc_lamports_for_value(dec!(1), Decimal::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_valuation_factor_never_exceeds_one() {
+        for market_rate in [None, Some(dec!(1.2)), Some(dec!(1.08))] {
+            assert_eq!(calc_collateral_valuation_factor(Bps(0), dec!(1.08), market_rate).unwrap(), Decimal::ONE);
+        }
+    }
+
+    #[test]
+    fn test_reward_msol_is_the_lucra_value_at_the_msol_price() {
+        // 0.005 SOL of reward at 0.25 SOL a lucra is 0.02 lucra
+        assert_eq!(calc_reward_lucra(5_000_000, dec!(0.25)).unwrap(), 20_000_000);
+        // worth the same 0.005 SOL, which is 0.004 msol at 1.25 SOL an msol
+        assert_eq!(calc_reward_msol(5_000_000, dec!(0.25), dec!(1.25)).unwrap(), 4_000_000);
+        // An msol worth exactly a SOL pays the lamports out as they are
+        assert_eq!(calc_reward_msol(5_000_000, dec!(0.25), Decimal::ONE).unwrap(), 5_000_000);
+
+        assert!(calc_reward_lucra(5_000_000, Decimal::ZERO).is_err());
+        assert!(calc_reward_msol(5_000_000, dec!(0.25), Decimal::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_conversions_round_down() {
+        assert_eq!(calc_msol_lamport_value(2, dec!(1.99)).unwrap(), 3);
+        assert_eq!(calc_reward_lamports(RewardFee(3), 1, 1).unwrap(), 0);
+        assert_eq!(calc_reward_lucra(10, dec!(3)).unwrap(), 3);
+        assert_eq!(calc_reward_msol(10, dec!(3), dec!(1.1)).unwrap(), 8);
+        assert_eq!(calc_partial_reward_base_units(1, 0, 11, 12).unwrap(), 0);
+        assert_eq!(calc_keeper_reward_base_units(3, Bps(15_000)).unwrap(), 4);
+        // $15 at 150% is ten mata, a fraction of a base unit is dropped
+        assert_eq!(calc_loan_amount(dec!(15), CollateralRatio(150)).unwrap(), Mata(10_000_000));
+        assert_eq!(calc_loan_amount(dec!(0.0000014), CollateralRatio(150)).unwrap(), Mata(0));
+    }
+
+    #[test]
+    fn test_harvest_covers_ten_reward_fees() {
+        // The Initialize defaults
+        assert!(verify_harvest_params(Lamports(550_000), RewardFee(5_500)).is_ok());
+        assert!(verify_harvest_params(Lamports(55_000), RewardFee(5_500)).is_ok());
+        assert!(verify_harvest_params(Lamports(54_999), RewardFee(5_500)).is_err());
+
+        assert!(verify_harvest_params(Lamports(500_000), MAX_REWARD_FEE).is_ok());
+        assert!(verify_harvest_params(Lamports(u64::MAX), RewardFee(MAX_REWARD_FEE.get() + 1)).is_err());
+        assert!(verify_harvest_params(Lamports(0), RewardFee(0)).is_ok());
+    }
+
+    #[test]
+    fn test_repaid_share_rounds_down() {
+        assert_eq!(calc_repaid_share(10_000, 250, 1_000).unwrap(), 2_500);
+        assert_eq!(calc_repaid_share(10, 1, 3).unwrap(), 3);
+        assert_eq!(calc_repaid_share(10, 0, 3).unwrap(), 0);
+        // Paying it all off frees all of it, even when nothing is owed
+        assert_eq!(calc_repaid_share(10, 3, 3).unwrap(), 10);
+        assert_eq!(calc_repaid_share(10, 0, 0).unwrap(), 10);
+        assert_eq!(calc_repaid_share(u64::MAX, u64::MAX - 1, u64::MAX).unwrap(), u64::MAX - 1);
+
+        assert!(calc_repaid_share(10, 4, 3).is_err());
+    }
+
+    #[test]
+    fn test_get_required_stake() {
+        let lcp = Percent(100);
+        let lamports = Lamports(10 * LAMPORTS_PER_SOL); // 10 sol
+        let sol_price = Decimal::new(10, 0);
+
+        let expected = sol_price.checked_mul(lamports.into()).unwrap().checked_div(LAMPORTS_PER_SOL.into()).unwrap();
+        let actual = calc_required_stake_value(lcp, lamports, sol_price).unwrap();
+
+        assert_eq!(actual, expected);
+
+        let lcp = Percent(50);
+        let expected = Decimal::from(50);
+        let actual = calc_required_stake_value(lcp, lamports, sol_price).unwrap();
+
+        assert_eq!(actual, expected);
+
+        let lcp = Percent(150);
+        let expected = Decimal::from(150);
+        let actual = calc_required_stake_value(lcp, lamports, sol_price).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_quote_without_locked_stake() {
+        // 10 SOL at $15 is $150, at 150% that's a hundred mata
+        let quote = quote_mata_loan(10 * LAMPORTS_PER_SOL, dec!(15), Decimal::ONE, CollateralRatio(150), None).unwrap();
+
+        assert_eq!(quote, LoanQuote {
+            loan_amount: Mata(100_000_000),
+            staking_value_required: 0,
+            collateral_ratio: Bps(15_000),
+        });
+
+        // The haircut halves what the SOL counts for
+        let quote = quote_mata_loan(10 * LAMPORTS_PER_SOL, dec!(15), dec!(0.5), CollateralRatio(150), None).unwrap();
+        assert_eq!(quote.loan_amount, Mata(50_000_000));
+        assert_eq!(quote.collateral_ratio, Bps(15_000));
+    }
+
+    #[test]
+    fn test_quote_with_locked_stake() {
+        // 10 SOL at $15 with half its value locked as stake backs $225
+        let quote = quote_mata_loan(10 * LAMPORTS_PER_SOL, dec!(15), Decimal::ONE, CollateralRatio(150), Some(Percent(50))).unwrap();
+
+        assert_eq!(quote, LoanQuote {
+            loan_amount: Mata(150_000_000),
+            staking_value_required: 75,
+            collateral_ratio: Bps(10_000),
+        });
+    }
+
+    #[test]
+    fn test_quote_matches_the_loan_math() {
+        // Odd amounts so every step rounds
+        let lamports = 1_234_567_891;
+        let sol_price = dec!(17.123456);
+        let factor = dec!(0.97);
+
+        let sol_value = sol_price * Decimal::from(lamports) / Decimal::from(LAMPORTS_PER_SOL) * factor;
+        let stake_value = calc_required_stake_value(Percent(30), Lamports(lamports), sol_price).unwrap();
+
+        let quote = quote_mata_loan(lamports, sol_price, factor, CollateralRatio(175), None).unwrap();
+        assert_eq!(quote.loan_amount, calc_loan_amount(sol_value, CollateralRatio(175)).unwrap());
+
+        // The unrounded stake value counts towards the loan, the lock is rounded down
+        let quote = quote_mata_loan(lamports, sol_price, factor, CollateralRatio(175), Some(Percent(30))).unwrap();
+        assert_eq!(quote.loan_amount, calc_loan_amount(sol_value + stake_value, CollateralRatio(175)).unwrap());
+        assert_eq!(quote.staking_value_required, stake_value.floor().to_u64().unwrap());
+        assert!(quote.collateral_ratio < Bps(17_500));
+    }
+
+    #[test]
+    fn test_quote_of_nothing_mints_nothing() {
+        let quote = quote_mata_loan(0, dec!(15), Decimal::ONE, CollateralRatio(150), Some(Percent(50))).unwrap();
+
+        assert_eq!(quote, LoanQuote::default());
+    }
+
+    #[test]
+    fn test_borrowable_mata_tops_the_loan_up_to_the_requirement() {
+        // $15 at 150% backs ten mata
+        assert_eq!(calc_borrowable_mata(dec!(15), CollateralRatio(150), 4_000_000).unwrap(), 6_000_000);
+        assert_eq!(calc_borrowable_mata(dec!(15), CollateralRatio(150), 10_000_000).unwrap(), 0);
+        assert_eq!(calc_borrowable_mata(dec!(15), CollateralRatio(150), 12_000_000).unwrap(), 0);
+    }
+}
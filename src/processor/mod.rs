@@ -22,41 +22,66 @@ mod process_sell_funds_for_arb;
 mod process_buy_burn_for_arb;
 mod process_clean_up_arb;
 mod process_mint_funds_for_arb;
+mod process_create_revenue_ledger;
+mod process_global_settlement;
+mod process_reclaim_excess;
+mod process_roll_arb_window;
+mod process_set_reward_metadata;
+mod process_freeze_staking_account;
+mod process_unfreeze_staking_account;
+mod process_set_staking_recovery_key;
+mod process_set_lp_collateral;
+mod process_sweep_expired_reward;
+mod process_set_min_drop_amount;
+mod process_set_reward_redemption_deadline;
+mod process_close_reward_program;
+mod process_migrate_account_layout;
+mod process_correct_price_history;
+mod process_recompute_penalty;
+mod process_compute_tvl;
+mod process_rollover_loan;
+mod process_repay_loan_partial;
+mod process_borrow_more;
+mod process_set_liquidation_params;
+mod process_liquidate_loan;
+mod process_transfer_loan_ownership;
+mod process_close_stake_balance;
+mod process_close_staking_account;
+mod process_cancel_unstake;
+mod process_propose_authority;
+mod process_accept_authority;
+mod process_set_pause;
+mod process_withdraw_collateral;
+mod process_migrate_stake_balance;
+mod process_quote_mata_loan;
+mod process_create_penalty_history;
+mod process_create_event_queue;
+mod process_update_harvest_params;
+mod process_create_sol_vault;
+mod process_create_price_history_page;
+mod process_register_keeper;
+mod process_remove_keeper;
+mod process_redeem_reward_tokens_for_msol;
+mod process_add_collateral_type;
+mod process_set_collateral_type;
+mod process_rebalance_rewards_vault;
+mod process_add_transfer_destination;
+mod process_remove_transfer_destination;
 
 use crate::instruction::Instruction;
 
-use process_create_mata_loan::*;
-use process_close_out_mata_loan::*;
-use process_initialize::*;
-use process_create_staking_account::*;
-use process_create_stake_balance::*;
-use process_deposit_stake::*;
-use process_stake::*;
-use process_start_unstake::*;
-use process_end_unstake::*;
-use process_withdraw_stake::*;
-use process_claim_reward::*;
-use process_drop_reward::*;
-use process_update_state::*;
-use process_transfer_funds::*;
-use process_redeem_reward_tokens::*;
-use process_add_collateral::*;
-use process_determine_penalty::*;
-use process_harvest_penalty::*;
-use process_create_price_history::*;
-use process_update_price_history::*;
 use process_sell_funds_for_arb::*;
 use process_buy_burn_for_arb::*;
 use process_clean_up_arb::*;
 use process_mint_funds_for_arb::*;
 
-use bincode::deserialize;
 use std::{
     convert::TryFrom,
 };
 use solana_program::{
     account_info::AccountInfo,
     msg,
+    program_error::ProgramError,
     pubkey::Pubkey,
 };
 use crate::{
@@ -65,219 +90,133 @@ use crate::{
     },
     state::{
         AmmTypes,
-        staking::StakingTimeframe,
-        StateParams,
-        UpdateStateParams,
         CurrencyTypes,
     },
 };
 
+// Each processor unpacks its own variant in an #[inline(never)] dispatch, so this match only routes
+// and none of the per instruction locals end up in its stack frame
 pub fn process(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> LucraResult {
-    let instruction = deserialize::<Instruction>(instruction_data).unwrap();
+    let instruction = Instruction::try_from_slice_versioned(instruction_data)?;
 
     match instruction {
-        Instruction::CreateMataLoan {
-            lamports,
-        } => {
-            msg!("Instruction: Create Loan");
-            process_create_mata_loan(
-                program_id,
-                lamports,
-                accounts,
-            )
-        }
-        Instruction::CloseOutMataLoan {
-            unstake_msol,
-        } => {
-            msg!("Instruction: Close Loan");
-            process_close_out_mata_loan(
-                program_id,
-                unstake_msol,
-                accounts,
-            )
-        }
-        Instruction::Initialize {
-            min_deposit,
-            collateral_requirement,
-            epoch,
-            loans_enabled,
-            staking_enabled,
-            arbitrage_enabled,
-            peg_check_enabled,
-            max_amount_of_lucra_to_mint,
-            daily_arb_limit,
-            maximum_outstanding_mata,
-            lcp,
-        } => {
-            msg!("Instruction: Initialize");
-            let state_params = StateParams {
-                min_deposit,
-                collateral_requirement,
-                epoch,
-                loans_enabled,
-                staking_enabled,
-                arbitrage_enabled,
-                peg_check_enabled,
-                max_amount_of_lucra_to_mint,
-                daily_arb_limit,
-                maximum_outstanding_mata,
-                lcp,
-            };
-            process_initialize(program_id, &state_params, accounts)
-        }
-        Instruction::CreateStakingAccount { } => {
-            msg!("Instruction: Create Staking Account");
-            process_create_staking_account(program_id, accounts)
-        }
-        Instruction::CreateStakeBalance {
-            nonce,
-            staking_timeframe,
-        } => {
-            msg!("Instruction: Create Stake Balance");
-            let staking_timeframe = StakingTimeframe::try_from(staking_timeframe).unwrap();
-            process_create_stake_balance(program_id, nonce, staking_timeframe, accounts)
-        }
-        Instruction::DepositStake {
-            lucra,
-        } => {
-            msg!("Instruction: Deposit Stake");
-            process_deposit_stake(program_id, lucra, accounts)
-        }
-        Instruction::Stake {
-            lucra,
-        } => {
-            msg!("Instruction: Stake");
-            process_stake(program_id, lucra, accounts)
-        }
-        Instruction::StartUnstake {
-            lucra,
-        } => {
-            msg!("Instruction: Start Unstake");
-            process_start_unstake(program_id, lucra, accounts)
-        }
-        Instruction::EndUnstake { } => {
-            msg!("Instruction: End Unstake");
-            process_end_unstake(program_id, accounts)
-        }
-        Instruction::WithdrawStake {
-            lucra,
-        } => {
-            msg!("Instruction: Withdraw Stake");
-            process_withdraw_stake(program_id, lucra, accounts)
-        }
-        Instruction::ClaimReward { } => {
-            msg!("Instruction: Claim Reward");
-            process_claim_reward(program_id, accounts)
-        }
-        Instruction::DropReward { } => {
-            msg!("Instruction: Drop Reward");
-            process_drop_reward(program_id, accounts)
-        }
-        Instruction::UpdateState {
-            min_deposit,
-            collateral_requirement,
-            loans_enabled,
-            staking_enabled,
-            arbitrage_enabled,
-            peg_check_enabled,
-            max_amount_of_lucra_to_mint,
-            daily_arb_limit,
-            maximum_outstanding_mata,
-            minimum_harvest_amount,
-            reward_fee,
-            lcp,
-        } => {
-            msg!("Instruction: Update State");
-            let state_params = UpdateStateParams {
-                min_deposit,
-                collateral_requirement,
-                loans_enabled,
-                staking_enabled,
-                arbitrage_enabled,
-                peg_check_enabled,
-                max_amount_of_lucra_to_mint,
-                daily_arb_limit,
-                maximum_outstanding_mata,
-                minimum_harvest_amount,
-                reward_fee,
-                lcp,
-            };
-            process_update_state(program_id, &state_params, accounts)
-        }
-        Instruction::TransferFunds {
-            lamports,
-        } => {
-            msg!("Instruction: Transfer Funds");
-            process_transfer_funds(program_id, lamports, accounts)
-        }
-        Instruction::CreatePriceHistory { } => {
-            msg!("Instruction: Create Price History");
-            process_create_price_history(program_id, accounts)
-        }
-        Instruction::UpdatePriceHistory { } => {
-            msg!("Instruction: Update Price History");
-            process_update_price_history(program_id, accounts)
-        }
-        Instruction::RedeemRewardTokens {
-            reward_tokens,
-        } => {
-            msg!("Instruction: Redeem Oracle Reward");
-            process_redeem_reward_tokens(program_id, reward_tokens, accounts)
-        }
-        Instruction::AddCollateral {
-            lamports,
-        } => {
-            msg!("Instruction: Add Collateral");
-            process_add_collateral(program_id, lamports, accounts)
-        }
-        Instruction::DeterminePenalty { } => {
-            msg!("Instruction: Determine Penalty");
-            process_determine_penalty(program_id, accounts)
-        }
-        Instruction::HarvestPenalty { 
-            amm_type
-        } => {
-            msg!("Instruction: Harvest Penalty");
-            let amm_type = AmmTypes::try_from(amm_type).unwrap();
-            process_harvest_penalty(program_id, amm_type, accounts)
-        }
-        Instruction::SellFundsForArb { 
+        Instruction::CreateMataLoan { .. } => process_create_mata_loan::dispatch(program_id, instruction, accounts),
+        Instruction::CloseOutMataLoan { .. } => process_close_out_mata_loan::dispatch(program_id, instruction, accounts),
+        Instruction::Initialize { .. } => process_initialize::dispatch(program_id, instruction, accounts),
+        Instruction::CreateStakingAccount { .. } => process_create_staking_account::dispatch(program_id, instruction, accounts),
+        Instruction::CreateStakeBalance { .. } => process_create_stake_balance::dispatch(program_id, instruction, accounts),
+        Instruction::DepositStake { .. } => process_deposit_stake::dispatch(program_id, instruction, accounts),
+        Instruction::Stake { .. } => process_stake::dispatch(program_id, instruction, accounts),
+        Instruction::StartUnstake { .. } => process_start_unstake::dispatch(program_id, instruction, accounts),
+        Instruction::EndUnstake { .. } => process_end_unstake::dispatch(program_id, instruction, accounts),
+        Instruction::WithdrawStake { .. } => process_withdraw_stake::dispatch(program_id, instruction, accounts),
+        Instruction::ClaimReward { .. } => process_claim_reward::dispatch(program_id, instruction, accounts),
+        Instruction::ClaimRewardV2 { .. } => process_claim_reward::dispatch(program_id, instruction, accounts),
+        Instruction::DropReward { .. } => process_drop_reward::dispatch(program_id, instruction, accounts),
+        Instruction::UpdateState { .. } => process_update_state::dispatch(program_id, instruction, accounts),
+        Instruction::TransferFunds { .. } => process_transfer_funds::dispatch(program_id, instruction, accounts),
+        Instruction::CreatePriceHistory { .. } => process_create_price_history::dispatch(program_id, instruction, accounts),
+        Instruction::UpdatePriceHistory { .. } => process_update_price_history::dispatch(program_id, instruction, accounts),
+        Instruction::RedeemRewardTokens { .. } => process_redeem_reward_tokens::dispatch(program_id, instruction, accounts),
+        Instruction::AddCollateral { .. } => process_add_collateral::dispatch(program_id, instruction, accounts),
+        Instruction::DeterminePenalty { .. } => process_determine_penalty::dispatch(program_id, instruction, accounts),
+        Instruction::HarvestPenalty { .. } => process_harvest_penalty::dispatch(program_id, instruction, accounts),
+        Instruction::SellFundsForArb { .. }
+        | Instruction::BuyBurnForArb { .. }
+        | Instruction::CleanUpArb { .. }
+        | Instruction::MintFundsForArb { .. } => dispatch_arb(program_id, instruction, accounts),
+        Instruction::CreateRevenueLedger { .. } => process_create_revenue_ledger::dispatch(program_id, instruction, accounts),
+        Instruction::GlobalSettlement { .. } => process_global_settlement::dispatch(program_id, instruction, accounts),
+        Instruction::ReclaimExcess { .. } => process_reclaim_excess::dispatch(program_id, instruction, accounts),
+        Instruction::RollArbWindow { .. } => process_roll_arb_window::dispatch(program_id, instruction, accounts),
+        Instruction::SetRewardMetadata { .. } => process_set_reward_metadata::dispatch(program_id, instruction, accounts),
+        Instruction::FreezeStakingAccount { .. } => process_freeze_staking_account::dispatch(program_id, instruction, accounts),
+        Instruction::UnfreezeStakingAccount { .. } => process_unfreeze_staking_account::dispatch(program_id, instruction, accounts),
+        Instruction::SetStakingRecoveryKey { .. } => process_set_staking_recovery_key::dispatch(program_id, instruction, accounts),
+        Instruction::SetLpCollateral { .. } => process_set_lp_collateral::dispatch(program_id, instruction, accounts),
+        Instruction::SweepExpiredReward { .. } => process_sweep_expired_reward::dispatch(program_id, instruction, accounts),
+        Instruction::SetMinDropAmount { .. } => process_set_min_drop_amount::dispatch(program_id, instruction, accounts),
+        Instruction::SetRewardRedemptionDeadline { .. } => process_set_reward_redemption_deadline::dispatch(program_id, instruction, accounts),
+        Instruction::CloseRewardProgram { .. } => process_close_reward_program::dispatch(program_id, instruction, accounts),
+        Instruction::MigrateAccountLayout { .. } => process_migrate_account_layout::dispatch(program_id, instruction, accounts),
+        Instruction::CorrectPriceHistory { .. } => process_correct_price_history::dispatch(program_id, instruction, accounts),
+        Instruction::RecomputePenalty { .. } => process_recompute_penalty::dispatch(program_id, instruction, accounts),
+        Instruction::ComputeTvl { .. } => process_compute_tvl::dispatch(program_id, instruction, accounts),
+        Instruction::RolloverLoan { .. } => process_rollover_loan::dispatch(program_id, instruction, accounts),
+        Instruction::RepayLoanPartial { .. } => process_repay_loan_partial::dispatch(program_id, instruction, accounts),
+        Instruction::BorrowMore { .. } => process_borrow_more::dispatch(program_id, instruction, accounts),
+        Instruction::SetLiquidationParams { .. } => process_set_liquidation_params::dispatch(program_id, instruction, accounts),
+        Instruction::LiquidateLoan { .. } => process_liquidate_loan::dispatch(program_id, instruction, accounts),
+        Instruction::TransferLoanOwnership { .. } => process_transfer_loan_ownership::dispatch(program_id, instruction, accounts),
+        Instruction::CloseStakeBalance { .. } => process_close_stake_balance::dispatch(program_id, instruction, accounts),
+        Instruction::CloseStakingAccount { .. } => process_close_staking_account::dispatch(program_id, instruction, accounts),
+        Instruction::CancelUnstake { .. } => process_cancel_unstake::dispatch(program_id, instruction, accounts),
+        Instruction::ProposeAuthority { .. } => process_propose_authority::dispatch(program_id, instruction, accounts),
+        Instruction::AcceptAuthority { .. } => process_accept_authority::dispatch(program_id, instruction, accounts),
+        Instruction::SetPause { .. } => process_set_pause::dispatch(program_id, instruction, accounts),
+        Instruction::WithdrawCollateral { .. } => process_withdraw_collateral::dispatch(program_id, instruction, accounts),
+        Instruction::MigrateStakeBalance { .. } => process_migrate_stake_balance::dispatch(program_id, instruction, accounts),
+        Instruction::QuoteMataLoan { .. } => process_quote_mata_loan::dispatch(program_id, instruction, accounts),
+        Instruction::CreatePenaltyHistory { .. } => process_create_penalty_history::dispatch(program_id, instruction, accounts),
+        Instruction::CreateEventQueue { .. } => process_create_event_queue::dispatch(program_id, instruction, accounts),
+        Instruction::UpdateHarvestParams { .. } => process_update_harvest_params::dispatch(program_id, instruction, accounts),
+        Instruction::CreateSolVault { .. } => process_create_sol_vault::dispatch(program_id, instruction, accounts),
+        Instruction::CreatePriceHistoryPage { .. } => process_create_price_history_page::dispatch(program_id, instruction, accounts),
+        Instruction::RegisterKeeper { .. } => process_register_keeper::dispatch(program_id, instruction, accounts),
+        Instruction::RemoveKeeper { .. } => process_remove_keeper::dispatch(program_id, instruction, accounts),
+        Instruction::RedeemRewardTokensForMsol { .. } => process_redeem_reward_tokens_for_msol::dispatch(program_id, instruction, accounts),
+        Instruction::AddCollateralType { .. } => process_add_collateral_type::dispatch(program_id, instruction, accounts),
+        Instruction::SetCollateralType { .. } => process_set_collateral_type::dispatch(program_id, instruction, accounts),
+        Instruction::RebalanceRewardsVault { .. } => process_rebalance_rewards_vault::dispatch(program_id, instruction, accounts),
+        Instruction::AddTransferDestination { .. } => process_add_transfer_destination::dispatch(program_id, instruction, accounts),
+        Instruction::RemoveTransferDestination { .. } => process_remove_transfer_destination::dispatch(program_id, instruction, accounts),
+    }
+}
+
+// The arb processors don't have a dispatch of their own yet, their unpacking stays out of process()'s frame here
+#[inline(never)]
+fn dispatch_arb(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SellFundsForArb {
             fund_source,
             amm_type,
             lamports,
+            min_amount_out,
         } => {
             msg!("Instruction: Sell Funds for Arb");
-            let fund_source = CurrencyTypes::try_from(fund_source).unwrap();
-            let amm_type = AmmTypes::try_from(amm_type).unwrap();
-            process_sell_funds_for_arb(program_id, fund_source, amm_type, lamports, accounts)
+            let fund_source = CurrencyTypes::try_from(fund_source).map_err(|_| ProgramError::InvalidInstructionData)?;
+            let amm_type = AmmTypes::try_from(amm_type).map_err(|_| ProgramError::InvalidInstructionData)?;
+            process_sell_funds_for_arb(program_id, fund_source, amm_type, lamports, min_amount_out, accounts)
         }
-        Instruction::BuyBurnForArb { 
+        Instruction::BuyBurnForArb {
             fund_source,
             amm_type,
             lamports,
+            min_amount_out,
         } => {
             msg!("Instruction: Buy Burn for Arb");
-            let fund_source = CurrencyTypes::try_from(fund_source).unwrap();
-            let amm_type = AmmTypes::try_from(amm_type).unwrap();
-            process_buy_burn_for_arb(program_id, fund_source, amm_type, lamports, accounts)
+            let fund_source = CurrencyTypes::try_from(fund_source).map_err(|_| ProgramError::InvalidInstructionData)?;
+            let amm_type = AmmTypes::try_from(amm_type).map_err(|_| ProgramError::InvalidInstructionData)?;
+            process_buy_burn_for_arb(program_id, fund_source, amm_type, lamports, min_amount_out, accounts)
         }
         Instruction::CleanUpArb {} => {
             msg!("Instruction: Clean Up Arb");
             process_clean_up_arb(program_id, accounts)
         }
-        Instruction::MintFundsForArb { 
+        Instruction::MintFundsForArb {
             fund_source,
             amm_type,
             lamports,
         } => {
             msg!("Instruction: Mint Funds for Arb");
-            let fund_source = CurrencyTypes::try_from(fund_source).unwrap();
-            let amm_type = AmmTypes::try_from(amm_type).unwrap();
+            let fund_source = CurrencyTypes::try_from(fund_source).map_err(|_| ProgramError::InvalidInstructionData)?;
+            let amm_type = AmmTypes::try_from(amm_type).map_err(|_| ProgramError::InvalidInstructionData)?;
             process_mint_funds_for_arb(program_id, fund_source, amm_type, lamports, accounts)
         }
+        _ => unreachable!(),
     }
-}
\ No newline at end of file
+}
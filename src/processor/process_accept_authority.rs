@@ -0,0 +1,52 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::accept_dao_authority,
+    idl,
+    instruction::Instruction,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::AcceptAuthority);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::AcceptAuthority { } => {
+            msg!("Instruction: Accept Authority");
+            process_accept_authority(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const ACCEPT_AUTHORITY_SIZE: usize = idl::ACCEPT_AUTHORITY.len();
+
+// Completes the handoff ProposeAuthority started, signed by the proposed key
+#[inline(never)]
+pub fn process_accept_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = ACCEPT_AUTHORITY_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // write
+        new_authority_ai,   // read
+    ] = accounts;
+
+    check_eq!(new_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    accept_dao_authority(&mut system_state, new_authority_ai.key)
+}
@@ -5,6 +5,7 @@ use std::{
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     native_token::LAMPORTS_PER_SOL,
     pubkey::Pubkey,
     sysvar::{clock::Clock, Sysvar},
@@ -19,12 +20,20 @@ use crate::{
         SourceFileId,
     },
     helpers::{
-        account::{verify_account_will_still_have_lamports},
-        constants::{SOL_USDC_ORACLE, SOL_USDT_ORACLE, LUCRA_SOL_ORACLE },
+        account::{verify_account_will_still_have_lamports, verify_program_account, without_fees_sysvar},
+        collateral::{calc_msol_lamport_value, get_marinade_msol_rate},
+        constants::{FEE_BUFFER_LAMPORTS, SOL_USDC_ORACLE, SOL_USDT_ORACLE, LUCRA_SOL_ORACLE },
+        loan_history::record_collateral_added,
         spl::*,
         oracle::*,
         marinade::deposit,
+        math::calc_stake_value_left_to_lock,
+        settlement::check_not_settled,
+        sol_vault::{check_native_loans_enabled, deposit_to_sol_vault},
+        vaults::verify_msol_vault,
     },
+    idl,
+    instruction::Instruction,
     state::{
         MataLoan,
         LoanType,
@@ -35,20 +44,42 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::AddCollateral);
 
-const ADD_COLLATERAL_SIZE: usize = 16;
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::AddCollateral {
+            lamports,
+        } => {
+            msg!("Instruction: Add Collateral");
+            process_add_collateral(program_id, lamports, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
 
+const ADD_COLLATERAL_SIZE: usize = idl::ADD_COLLATERAL.len();
+const ADD_COLLATERAL_WITH_LOCKED_STAKE_SIZE: usize = idl::ADD_COLLATERAL_WITH_LOCKED_STAKE.len();
+const ADD_COLLATERAL_MSOL_SIZE: usize = idl::ADD_COLLATERAL_MSOL.len();
+const ADD_COLLATERAL_MSOL_WITH_LOCKED_STAKE_SIZE: usize = idl::ADD_COLLATERAL_MSOL_WITH_LOCKED_STAKE.len();
+const ADD_NATIVE_SOL_COLLATERAL_SIZE: usize = idl::ADD_NATIVE_SOL_COLLATERAL.len();
+
+// The msol layouts take an msol amount instead of lamports and skip the marinade deposit.
+// The native SOL layout moves the lamports into the sol vault as they are
 #[inline(never)]
 pub fn process_add_collateral(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
-    if accounts.len() == ADD_COLLATERAL_SIZE {
-        add_collateral(program_id, lamports, accounts)
-    } else {
-        add_collateral_with_locked_stake(program_id, lamports, accounts)
+    let accounts = &*without_fees_sysvar(accounts);
+    match accounts.len() {
+        ADD_NATIVE_SOL_COLLATERAL_SIZE => add_native_sol_collateral(program_id, lamports, accounts),
+        ADD_COLLATERAL_MSOL_SIZE => add_collateral_msol(program_id, lamports, accounts),
+        ADD_COLLATERAL_MSOL_WITH_LOCKED_STAKE_SIZE => add_collateral_msol_with_locked_stake(program_id, lamports, accounts),
+        ADD_COLLATERAL_SIZE => add_collateral(program_id, lamports, accounts),
+        _ => add_collateral_with_locked_stake(program_id, lamports, accounts),
     }
 }
 
 #[inline(never)]
 pub fn add_collateral(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
-    const NUM_FIXED: usize = 16;
+    const NUM_FIXED: usize = ADD_COLLATERAL_SIZE;
     let accounts = array_ref!(accounts, 0, NUM_FIXED);
     let [
         system_state_ai,                // write
@@ -64,7 +95,6 @@ pub fn add_collateral(program_id: &Pubkey, lamports: u64, accounts: &[AccountInf
         liq_pool_msol_leg_authority_ai, // read
         reserve_pda_ai,                 // write
         msol_mint_authority_ai,         // read
-        fees_ai,                        // read
         system_program_ai,              // read
         token_program_ai,               // read
         marinade_program_ai,            // read
@@ -78,9 +108,11 @@ pub fn add_collateral(program_id: &Pubkey, lamports: u64, accounts: &[AccountInf
     check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
 
     check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(marinade_program_ai.key, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
 
     let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
     check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
 
     let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
@@ -100,7 +132,6 @@ pub fn add_collateral(program_id: &Pubkey, lamports: u64, accounts: &[AccountInf
         liq_pool_msol_leg_authority_ai,
         reserve_pda_ai,
         msol_mint_authority_ai,
-        fees_ai,
         system_program_ai,
         token_program_ai,
         lamports,
@@ -110,7 +141,7 @@ pub fn add_collateral(program_id: &Pubkey, lamports: u64, accounts: &[AccountInf
 
 #[inline(never)]
 pub fn add_collateral_with_locked_stake(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
-    const NUM_FIXED: usize = 20;
+    const NUM_FIXED: usize = ADD_COLLATERAL_WITH_LOCKED_STAKE_SIZE;
     let accounts = array_ref!(accounts, 0, NUM_FIXED);
     let [
         system_state_ai,                // write
@@ -131,7 +162,6 @@ pub fn add_collateral_with_locked_stake(program_id: &Pubkey, lamports: u64, acco
         liq_pool_msol_leg_authority_ai, // read
         reserve_pda_ai,                 // write
         msol_mint_authority_ai,         // read
-        fees_ai,                        // read
         system_program_ai,              // read
         token_program_ai,               // read
         marinade_program_ai,            // read
@@ -144,45 +174,35 @@ pub fn add_collateral_with_locked_stake(program_id: &Pubkey, lamports: u64, acco
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(user_staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(sol_usdt_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(sol_usdc_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(lucra_sol_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
     check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
 
     check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(marinade_program_ai.key, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
 
-    check_eq!(sol_usdc_oracle_ai.key, &SOL_USDC_ORACLE, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(sol_usdt_oracle_ai.key, &SOL_USDT_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
     check_eq!(lucra_sol_oracle_ai.key, &LUCRA_SOL_ORACLE, LucraErrorCode::InvalidAccountInput)?;
 
     let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
     check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
     check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
 
     let mut user_staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(user_staking_account_ai, program_id)?;
     check_eq!(&user_staking_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
 
-    let sol_market_price = get_sol_price(sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-    let lamport_dollar_value = sol_market_price
-        .checked_mul(lamports.into())
-        .ok_or(math_err!())?
-        .checked_div(LAMPORTS_PER_SOL.into())
-        .ok_or(math_err!())?;
-    let staking_value_required = lamport_dollar_value.floor().to_u64().unwrap();
-    
-    let lucra_market_price = get_lucra_price(lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-    let value_left_to_lock: u64 = Decimal::from(user_staking_account.total)
-        .checked_mul(lucra_market_price)
-        .ok_or(math_err!())?
-        .checked_sub(user_staking_account.locked_total.into())
-        .ok_or(math_err!())?
-        .floor()
-        .to_u64()
-        .ok_or(math_err!())?;
-
-    check!(staking_value_required <= value_left_to_lock, LucraErrorCode::InvalidAmount)?;
+    let staking_value_required = get_staking_value_required(
+        &system_state,
+        &user_staking_account,
+        sol_usdc_oracle_ai,
+        sol_usdt_oracle_ai,
+        lucra_sol_oracle_ai,
+        lamports,
+        clock,
+    )?;
 
     let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
     check_eq!(loan.loan_type, LoanType::LucraBacked, LucraErrorCode::InvalidLoanType)?;
@@ -201,7 +221,6 @@ pub fn add_collateral_with_locked_stake(program_id: &Pubkey, lamports: u64, acco
         liq_pool_msol_leg_authority_ai,
         reserve_pda_ai,
         msol_mint_authority_ai,
-        fees_ai,
         system_program_ai,
         token_program_ai,
         lamports,
@@ -231,7 +250,6 @@ pub fn add_additional_collateral<'a>(
     reserve_pda_ai: &AccountInfo<'a>,
     msol_mint_authority_ai: &AccountInfo<'a>,
 
-    fees_ai: &AccountInfo<'a>,
     system_program_ai: &AccountInfo<'a>,
     token_program_ai: &AccountInfo<'a>,
 
@@ -241,45 +259,309 @@ pub fn add_additional_collateral<'a>(
     check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&loan.owner, owner_ai.key, LucraErrorCode::InvalidAccountOwner)?;
 
-    check_eq!(&system_state.msol_vault.address, msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
 
-    verify_account_will_still_have_lamports(fees_ai, owner_ai.lamports(), lamports)?;
+    verify_account_will_still_have_lamports(owner_ai.lamports(), lamports, FEE_BUFFER_LAMPORTS)?;
     check!(user_msol_account_ai.key != msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
 
-    let msol_balance_before = get_token_balance(user_msol_account_ai)?;
-    deposit(
-        marinade_state_ai,
-        msol_mint_ai,
-        liq_pool_sol_leg_pda_ai,
-        liq_pool_msol_leg_ai,
-        liq_pool_msol_leg_authority_ai,
-        reserve_pda_ai,
-        owner_ai,
+    let msol_received = measure_balance_change(user_msol_account_ai, || {
+        deposit(
+            marinade_state_ai,
+            msol_mint_ai,
+            liq_pool_sol_leg_pda_ai,
+            liq_pool_msol_leg_ai,
+            liq_pool_msol_leg_authority_ai,
+            reserve_pda_ai,
+            owner_ai,
+            user_msol_account_ai,
+            msol_mint_authority_ai,
+            &[],
+            system_program_ai,
+            token_program_ai,
+            marinade_program_ai,
+            lamports,
+        )?;
+        Ok(())
+    })?;
+    // The lamports were paid in, collateral has to come back for them
+    check!(msol_received > 0, LucraErrorCode::NothingReceived)?;
+
+    // Transfer the msol we recieved to the vault
+    spl_token_transfer(
         user_msol_account_ai,
-        msol_mint_authority_ai,
+        msol_vault_ai,
+        msol_received,
+        owner_ai,
         &[],
-        system_program_ai,
+        token_program_ai
+    )?;
+
+    record_additional_collateral(&mut system_state, &mut loan, msol_received, lamports, staking_collateral_amount)
+}
+
+#[inline(never)]
+pub fn add_collateral_msol(program_id: &Pubkey, msol_amount: u64, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = ADD_COLLATERAL_MSOL_SIZE;
+    let accounts = array_ref!(accounts, 0, NUM_FIXED);
+    let [
+        system_state_ai,                // write
+        marinade_state_ai,              // read
+        loan_ai,                        // write
+        msol_vault_ai,                  // write
+        owner_ai,                       // write
+        user_msol_account_ai,           // write
+        token_program_ai,               // read
+    ] = accounts;
+
+    check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    check_eq!(loan.loan_type, LoanType::Default, LucraErrorCode::InvalidLoanType)?;
+
+    let lamports = calc_msol_lamport_value(msol_amount, get_marinade_msol_rate(marinade_state_ai)?)?;
+
+    add_additional_msol_collateral(
+        &mut system_state,
+        &mut loan,
+        msol_vault_ai,
+        owner_ai,
+        user_msol_account_ai,
         token_program_ai,
-        marinade_program_ai,
+        msol_amount,
+        lamports,
+        0,
+    )
+}
+
+#[inline(never)]
+pub fn add_collateral_msol_with_locked_stake(program_id: &Pubkey, msol_amount: u64, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = ADD_COLLATERAL_MSOL_WITH_LOCKED_STAKE_SIZE;
+    let accounts = array_ref!(accounts, 0, NUM_FIXED);
+    let [
+        system_state_ai,                // write
+        marinade_state_ai,              // read
+        loan_ai,                        // write
+        msol_vault_ai,                  // write
+        owner_ai,                       // write
+        user_msol_account_ai,           // write
+        user_staking_account_ai,        // write
+
+        sol_usdc_oracle_ai,             // read
+        sol_usdt_oracle_ai,             // read
+        lucra_sol_oracle_ai,            // read
+
+        token_program_ai,               // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(user_staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(lucra_sol_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    check_eq!(lucra_sol_oracle_ai.key, &LUCRA_SOL_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+
+    let mut user_staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(user_staking_account_ai, program_id)?;
+    check_eq!(&user_staking_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let lamports = calc_msol_lamport_value(msol_amount, get_marinade_msol_rate(marinade_state_ai)?)?;
+    let staking_value_required = get_staking_value_required(
+        &system_state,
+        &user_staking_account,
+        sol_usdc_oracle_ai,
+        sol_usdt_oracle_ai,
+        lucra_sol_oracle_ai,
         lamports,
+        clock,
     )?;
-    let msol_balance_after = get_token_balance(user_msol_account_ai)?;
-    let msol_received = msol_balance_after - msol_balance_before;
 
-    // Transfer the msol we recieved to the vault
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    check_eq!(loan.loan_type, LoanType::LucraBacked, LucraErrorCode::InvalidLoanType)?;
+
+    add_additional_msol_collateral(
+        &mut system_state,
+        &mut loan,
+        msol_vault_ai,
+        owner_ai,
+        user_msol_account_ai,
+        token_program_ai,
+        msol_amount,
+        lamports,
+        staking_value_required,
+    )?;
+
+    user_staking_account.add_locked_total(staking_value_required);
+
+    Ok(())
+}
+
+#[inline(never)]
+pub fn add_native_sol_collateral(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = ADD_NATIVE_SOL_COLLATERAL_SIZE;
+    let accounts = array_ref!(accounts, 0, NUM_FIXED);
+    let [
+        system_state_ai,                // write
+        sol_vault_ai,                   // write
+        loan_ai,                        // write
+        owner_ai,                       // write
+        system_program_ai,              // read
+    ] = accounts;
+
+    check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(sol_vault_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    check_native_loans_enabled(&system_state)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    check_eq!(loan.loan_type, LoanType::NativeSol, LucraErrorCode::InvalidLoanType)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.owner, owner_ai.key, LucraErrorCode::InvalidAccountOwner)?;
+    check!(lamports > 0, LucraErrorCode::InvalidAmount)?;
+    verify_account_will_still_have_lamports(owner_ai.lamports(), lamports, FEE_BUFFER_LAMPORTS)?;
+
+    deposit_to_sol_vault(&system_state, owner_ai, sol_vault_ai, lamports, system_program_ai)?;
+
+    // Native collateral stays out of total_sol_collateral, only the loan books it
+    loan.add_sol_collateral(lamports);
+    record_collateral_added(&mut loan, lamports)
+}
+
+#[inline(never)]
+#[allow(clippy::too_many_arguments)]
+fn add_additional_msol_collateral<'a>(
+    system_state: &mut RefMut<SystemState>,
+    loan: &mut RefMut<MataLoan>,
+    msol_vault_ai: &AccountInfo<'a>,
+    owner_ai: &AccountInfo<'a>,
+    user_msol_account_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+    msol_amount: u64,
+    lamports: u64,
+    staking_collateral_amount: u64,
+) -> LucraResult {
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.owner, owner_ai.key, LucraErrorCode::InvalidAccountOwner)?;
+    check!(lamports > 0, LucraErrorCode::InvalidAmount)?;
+
+    verify_msol_vault(system_state, msol_vault_ai.key)?;
+    check!(user_msol_account_ai.key != msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
     spl_token_transfer(
         user_msol_account_ai,
         msol_vault_ai,
-        msol_received,
+        msol_amount,
         owner_ai,
         &[],
         token_program_ai
     )?;
 
+    record_additional_collateral(&mut system_state, &mut loan, msol_amount, lamports, staking_collateral_amount)
+}
+
+// Dollar value of the lucra that has to be locked alongside `lamports`
+fn get_staking_value_required(
+    system_state: &SystemState,
+    user_staking_account: &StakingAccount,
+    sol_usdc_oracle_ai: &AccountInfo,
+    sol_usdt_oracle_ai: &AccountInfo,
+    lucra_sol_oracle_ai: &AccountInfo,
+    lamports: u64,
+    clock: &Clock,
+) -> LucraResult<u64> {
+    let sol_market_price = get_sol_price(system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let lamport_dollar_value = sol_market_price
+        .checked_mul(lamports.into())
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())?;
+    let staking_value_required = lamport_dollar_value.floor().to_u64().unwrap();
+    
+    let lucra_market_price = get_lucra_price(system_state, lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let value_left_to_lock = calc_stake_value_left_to_lock(user_staking_account.total, lucra_market_price, user_staking_account.locked_total)?;
+
+    check!(staking_value_required <= value_left_to_lock, LucraErrorCode::InvalidAmount)?;
+
+    Ok(staking_value_required)
+}
+
+// Both the SOL and msol paths book the collateral at its SOL value, and the msol as the loan's share of the vault
+fn record_additional_collateral(system_state: &mut SystemState, loan: &mut MataLoan, msol_amount: u64, lamports: u64, staking_collateral_amount: u64) -> LucraResult {
     loan.add_sol_collateral(lamports);
     loan.add_staking_collateral(staking_collateral_amount);
+    loan.msol_collateral_amount = loan.msol_collateral_amount
+        .checked_add(msol_amount)
+        .ok_or(math_err!())?;
+    record_collateral_added(loan, lamports)?;
 
     system_state.add_collateral(lamports);
 
     Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_msol_top_up_books_the_same_collateral_as_sol() {
+        let marinade_rate = dec!(1.08);
+        let lamports = 10 * LAMPORTS_PER_SOL;
+        // The msol marinade would have minted for the same lamports
+        let msol_amount = Decimal::from(lamports)
+            .checked_div(marinade_rate)
+            .unwrap()
+            .ceil()
+            .to_u64()
+            .unwrap();
+
+        let mut sol_state = SystemState::default();
+        let mut sol_loan = MataLoan::default();
+        record_additional_collateral(&mut sol_state, &mut sol_loan, msol_amount, lamports, 50).unwrap();
+
+        let mut msol_state = SystemState::default();
+        let mut msol_loan = MataLoan::default();
+        let msol_lamports = calc_msol_lamport_value(msol_amount, marinade_rate).unwrap();
+        record_additional_collateral(&mut msol_state, &mut msol_loan, msol_amount, msol_lamports, 50).unwrap();
+
+        assert_eq!(msol_lamports, lamports);
+        assert_eq!(msol_loan.sol_collateral_amount, sol_loan.sol_collateral_amount);
+        assert_eq!(msol_loan.staking_collateral_amount, sol_loan.staking_collateral_amount);
+        assert_eq!(msol_loan.msol_collateral_amount, msol_amount);
+        assert_eq!(msol_loan.msol_collateral_amount, sol_loan.msol_collateral_amount);
+        assert_eq!(msol_state.total_sol_collateral, sol_state.total_sol_collateral);
+        // Both count as one top up of the same lamports
+        assert_eq!(msol_loan.total_collateral_added, lamports);
+        assert_eq!(msol_loan.times_collateral_added, 1);
+        assert_eq!(sol_loan.total_collateral_added, msol_loan.total_collateral_added);
+    }
 }
\ No newline at end of file
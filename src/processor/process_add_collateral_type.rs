@@ -0,0 +1,120 @@
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar},
+};
+use spl_token::state::{Account, Mint};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::collateral_types::verify_new_collateral_vault,
+    helpers::dao_authority::check_dao_authority,
+    helpers::oracle::OracleAccount,
+    helpers::settlement::check_not_settled,
+    helpers::solana::create_pda_account,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::{collateral_registry_seeds, find_collateral_registry},
+    state::{
+        CollateralRegistry,
+        CollateralType,
+        SystemState,
+    },
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::AddCollateralType);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::AddCollateralType { haircut_bps } => {
+            msg!("Instruction: Add Collateral Type");
+            process_add_collateral_type(program_id, haircut_bps, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const ADD_COLLATERAL_TYPE_SIZE: usize = idl::ADD_COLLATERAL_TYPE.len();
+
+// DAO instruction. The registry is a PDA of the system state created with the first collateral type. A type is
+// added disabled, SetCollateralType enables it once its vault and oracle check out.
+#[inline(never)]
+pub fn process_add_collateral_type(program_id: &Pubkey, haircut_bps: Bps, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = ADD_COLLATERAL_TYPE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // read
+        collateral_registry_ai, // write
+        mint_ai,                // read
+        oracle_ai,              // read
+        vault_ai,               // read
+        dao_authority_ai,       // read
+        payer_ai,               // write
+        system_program_ai,      // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(payer_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+    check!(vault_ai.key != &system_state.msol_vault.address, LucraErrorCode::InvalidAccountInput)?;
+
+    // Only checks it reads as an oracle, a stale price doesn't stop the type being added disabled
+    OracleAccount::load(&system_state, oracle_ai)?;
+    let mint = Mint::unpack(&mint_ai.data.borrow())?;
+    let vault = Account::unpack(&vault_ai.data.borrow())?;
+    let vault_authority_bump_seed = verify_new_collateral_vault(system_state_ai.key, mint_ai.key, &vault)?;
+
+    let (collateral_registry, bump_seed) = find_collateral_registry(system_state_ai.key);
+    check_eq!(collateral_registry_ai.key, &collateral_registry, LucraErrorCode::InvalidAccountInput)?;
+
+    if collateral_registry_ai.data_is_empty() {
+        let rent = Rent::get()?;
+        create_pda_account(
+            payer_ai,
+            collateral_registry_ai,
+            rent.minimum_balance(size_of::<CollateralRegistry>()),
+            size_of::<CollateralRegistry>() as u64,
+            program_id,
+            &[&collateral_registry_seeds(system_state_ai.key, &[bump_seed])],
+            system_program_ai,
+        )?;
+        CollateralRegistry::load_mut(collateral_registry_ai)?.init(system_state_ai.key);
+    }
+
+    let mut registry: RefMut<CollateralRegistry> = CollateralRegistry::load_mut_checked(collateral_registry_ai, program_id)?;
+    check_eq!(&registry.system_state, system_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let collateral_type = registry.add(CollateralType {
+        mint: *mint_ai.key,
+        oracle: *oracle_ai.key,
+        vault: *vault_ai.key,
+        haircut_bps,
+        decimals: mint.decimals,
+        vault_authority_bump_seed,
+        ..CollateralType::default()
+    })?;
+    msg!("Collateral type {}", collateral_type);
+
+    Ok(())
+}
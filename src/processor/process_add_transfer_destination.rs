@@ -0,0 +1,97 @@
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::settlement::check_not_settled,
+    helpers::solana::create_pda_account,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::{find_transfer_allowlist, transfer_allowlist_seeds},
+    state::{
+        SystemState,
+        TransferAllowlist,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::AddTransferDestination);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::AddTransferDestination {} => {
+            msg!("Instruction: Add Transfer Destination");
+            process_add_transfer_destination(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const ADD_TRANSFER_DESTINATION_SIZE: usize = idl::ADD_TRANSFER_DESTINATION.len();
+
+// DAO instruction. The allowlist is a PDA of the system state created with the first destination. TransferFunds
+// can only send to the destination once TRANSFER_DESTINATION_DELAY has passed.
+#[inline(never)]
+pub fn process_add_transfer_destination(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = ADD_TRANSFER_DESTINATION_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // read
+        transfer_allowlist_ai,  // write
+        destination_ai,         // read
+        dao_authority_ai,       // read
+        payer_ai,               // write
+        system_program_ai,      // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(payer_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+
+    let (transfer_allowlist, bump_seed) = find_transfer_allowlist(system_state_ai.key);
+    check_eq!(transfer_allowlist_ai.key, &transfer_allowlist, LucraErrorCode::InvalidAccountInput)?;
+
+    if transfer_allowlist_ai.data_is_empty() {
+        let rent = Rent::get()?;
+        create_pda_account(
+            payer_ai,
+            transfer_allowlist_ai,
+            rent.minimum_balance(size_of::<TransferAllowlist>()),
+            size_of::<TransferAllowlist>() as u64,
+            program_id,
+            &[&transfer_allowlist_seeds(system_state_ai.key, &[bump_seed])],
+            system_program_ai,
+        )?;
+        TransferAllowlist::load_mut(transfer_allowlist_ai)?.init(system_state_ai.key);
+    }
+
+    let mut allowlist: RefMut<TransferAllowlist> = TransferAllowlist::load_mut_checked(transfer_allowlist_ai, program_id)?;
+    check_eq!(&allowlist.system_state, system_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let activates_at = allowlist.add(destination_ai.key, clock.unix_timestamp)?;
+    msg!("Transfer destination {} activates at {}", destination_ai.key, activates_at);
+
+    Ok(())
+}
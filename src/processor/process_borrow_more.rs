@@ -0,0 +1,227 @@
+use std::cell::RefMut;
+
+use anchor_lang::prelude::*;
+use arrayref::array_ref;
+use rust_decimal::prelude::ToPrimitive;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        collateral::{calc_loan_collateral_value, find_msol_sol_oracle, get_collateral_valuation_factor},
+        constants::{SOL_MATA_ORACLE, SOL_USDC_ORACLE, SOL_USDT_ORACLE},
+        oracle::{get_mata_price, get_sol_price, verify_sol_usd_oracle},
+        peg_band::update_peg,
+        settlement::check_not_settled,
+        vaults::verify_mata_mint,
+    },
+    idl,
+    instruction::Instruction,
+    lucra_core::{
+        interest::calc_amount_owed,
+        quote::calc_borrowable_mata,
+    },
+    state::{
+        LoanType,
+        MataBucket,
+        MataLoan,
+        SystemState,
+    },
+    units::Mata,
+};
+
+declare_check_assert_macros!(SourceFileId::BorrowMore);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::BorrowMore {
+            mata,
+        } => {
+            msg!("Instruction: Borrow More");
+            process_borrow_more(program_id, mata, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const BORROW_MORE_SIZE: usize = idl::BORROW_MORE.len();
+
+#[inline(never)]
+pub fn process_borrow_more(program_id: &Pubkey, mata: u64, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = BORROW_MORE_SIZE;
+    let trailing_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        marinade_state_ai,          // read
+        loan_ai,                    // write
+        mata_mint_ai,               // write
+        mata_mint_authority_ai,     // read
+        user_account_ai,            // read
+        user_mata_account_ai,       // write
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        sol_mata_oracle_ai,         // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    // LP collateral is valued off the pool and against its own requirement, native SOL collateral isn't in the msol vault
+    check!(!matches!(loan.loan_type, LoanType::LpBacked | LoanType::NativeSol), LucraErrorCode::InvalidLoanType)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.loan_mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let user_mata_account = Account::unpack(&user_mata_account_ai.data.borrow())?;
+    check_eq!(&user_mata_account.mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check!(mata > 0, LucraErrorCode::InvalidAmount)?;
+
+    // Minting more is minting, it has to wait for the peg like a new loan
+    if system_state.peg_check_enabled {
+        check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+
+        let mata_market_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+        update_peg(&mut system_state, mata_market_price);
+        check!(!system_state.peg_broken, LucraErrorCode::BrokenPeg)?;
+    }
+
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let msol_sol_oracle_ai = find_msol_sol_oracle(trailing_accounts, &system_state);
+    let collateral_factor = get_collateral_valuation_factor(&system_state, msol_sol_oracle_ai, Some(marinade_state_ai), clock)?;
+    let collateral_value = calc_loan_collateral_value(&loan, sol_market_price, collateral_factor)?;
+    let borrowable = calc_borrowable_mata(collateral_value, system_state.collateral_requirement, calc_amount_owed(&loan)?)?;
+    check!(mata <= borrowable, LucraErrorCode::InvalidAmount)?;
+
+    system_state.mata_supply.mint(MataBucket::Loan, mata)?;
+    check!(Mata(system_state.mata_supply.total()?) <= system_state.maximum_outstanding_mata, LucraErrorCode::InvalidAmount)?;
+
+    system_state.mint_mata(
+        program_id,
+        mata_mint_ai,
+        user_mata_account_ai,
+        mata,
+        mata_mint_authority_ai,
+        token_program_ai,
+    )?;
+
+    loan.loan_amount = loan.loan_amount
+        .checked_add(mata)
+        .ok_or(math_err!())?;
+    loan.market_price = sol_market_price.floor().to_u64().ok_or(math_err!())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use solana_program::native_token::LAMPORTS_PER_SOL;
+    use crate::{
+        lucra_core::interest::LOAN_INTEREST_VERSION,
+        state::{DataType, MetaData},
+        units::CollateralRatio,
+    };
+
+    fn borrowable(loan: &MataLoan, sol_price: Decimal) -> u64 {
+        let collateral_value = calc_loan_collateral_value(loan, sol_price, Decimal::ONE).unwrap();
+
+        calc_borrowable_mata(collateral_value, CollateralRatio(150), calc_amount_owed(loan).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_appreciated_collateral_can_be_borrowed_against() {
+        // 10 SOL opened at $15 for 100 mata, SOL is at $20 now: $200 backs up to 133.333333 mata
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            loan_amount: 100_000_000,
+            ..MataLoan::default()
+        };
+
+        assert_eq!(borrowable(&loan, dec!(20)), 33_333_333);
+        // Borrowing exactly that keeps the loan at the requirement, one more base unit is under it
+        let at_requirement = MataLoan { loan_amount: 133_333_333, ..loan };
+        assert_eq!(borrowable(&at_requirement, dec!(20)), 0);
+        let collateral_value = calc_loan_collateral_value(&loan, dec!(20), Decimal::ONE).unwrap();
+        assert!(Decimal::from(133_333_334_u64) * dec!(1.5) > collateral_value * dec!(1_000_000));
+        assert!(Decimal::from(133_333_333_u64) * dec!(1.5) <= collateral_value * dec!(1_000_000));
+    }
+
+    #[test]
+    fn test_nothing_to_borrow_below_the_requirement() {
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            loan_amount: 100_000_000,
+            ..MataLoan::default()
+        };
+
+        // $150 at 150% backs exactly the 100 mata owed, SOL falling further doesn't go negative
+        assert_eq!(borrowable(&loan, dec!(15)), 0);
+        assert_eq!(borrowable(&loan, dec!(10)), 0);
+    }
+
+    #[test]
+    fn test_penalty_and_locked_stake_count_as_they_stand() {
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            penalty_harvested: LAMPORTS_PER_SOL,
+            penalty_to_harvest: LAMPORTS_PER_SOL,
+            staking_collateral_amount: 30,
+            loan_amount: 100_000_000,
+            ..MataLoan::default()
+        };
+
+        // 8 SOL at $20 and $30 of stake, $190 backs 126.666666 mata
+        assert_eq!(calc_loan_collateral_value(&loan, dec!(20), Decimal::ONE).unwrap(), dec!(190));
+        assert_eq!(borrowable(&loan, dec!(20)), 26_666_666);
+        // The haircut only applies to the SOL side
+        assert_eq!(calc_loan_collateral_value(&loan, dec!(20), dec!(0.5)).unwrap(), dec!(110));
+    }
+
+    #[test]
+    fn test_unpaid_interest_counts_against_what_can_be_borrowed() {
+        let loan = MataLoan {
+            meta_data: MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true),
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            loan_amount: 100_000_000,
+            interest_accrued: 20_000_000,
+            ..MataLoan::default()
+        };
+
+        // $200 backs 133.333333 mata, 120 of it is already owed
+        assert_eq!(borrowable(&loan, dec!(20)), 13_333_333);
+        assert_eq!(borrowable(&MataLoan { interest_accrued: 40_000_000, ..loan }, dec!(20)), 0);
+    }
+}
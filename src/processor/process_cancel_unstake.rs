@@ -0,0 +1,161 @@
+use std::cell::{Ref, RefMut};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        account::{add_lamports, close_account},
+        reward_expiry::skip_swept_rewards,
+        reward_index::add_indexed_stake,
+        settlement::check_not_settled,
+        spl::{get_token_balance, spl_token_transfer},
+        staking_freeze::{bind_staking_account, check_staking_account_not_frozen},
+    },
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::stake_balance_authority_seeds,
+    state::{
+        staking::{
+            PendingWithdrawal,
+            StakeBalance,
+            StakingAccount,
+            StakingState,
+        },
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::CancelUnstake);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CancelUnstake { } => {
+            msg!("Instruction: Cancel Unstake");
+            process_cancel_unstake(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CANCEL_UNSTAKE_SIZE: usize = idl::CANCEL_UNSTAKE.len();
+
+// Undoes a start unstake that hasn't been ended. The lucra goes back from the pending vault to the stake vault
+// and is staked again the way Stake does it, so the lock starts over and the receipt tokens are minted again.
+#[inline(never)]
+pub fn process_cancel_unstake(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = CANCEL_UNSTAKE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,                // read
+        staking_state_ai,               // write
+        staking_account_ai,             // write
+        stake_balance_ai,               // write
+        pending_withdrawal_ai,          // write
+        pending_vault_ai,               // write
+        stake_vault_ai,                 // write
+        owner_ai,                       // write
+        stake_balance_authority_ai,     // read
+        staked_lucra_mint_ai,           // write
+        user_staked_lucra_account_ai,   // write
+        mint_authority_ai,              // read
+        token_program_ai,               // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(staking_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(stake_balance_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(pending_withdrawal_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(pending_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(stake_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_staked_lucra_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    // Staking again is staking, during settlement the withdrawal can only be ended
+    check_not_settled(&system_state)?;
+    check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+    check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let staking_state: RefMut<StakingState> = StakingState::load_mut_checked(staking_state_ai, program_id)?;
+    let mut staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(staking_account_ai, program_id)?;
+    let mut stake_balance: RefMut<StakeBalance> = StakeBalance::load_mut_checked(stake_balance_ai, program_id)?;
+    check!(!stake_balance.closed, LucraErrorCode::InvalidAccountInput)?;
+    let mut pending_withdrawal: RefMut<PendingWithdrawal> = PendingWithdrawal::load_mut_checked(pending_withdrawal_ai, program_id)?;
+    // An ended withdrawal's lucra is already back in the deposit vault
+    check!(!pending_withdrawal.closed(), LucraErrorCode::InvalidAccountInput)?;
+
+    let staked_lucra_account = Account::unpack(&user_staked_lucra_account_ai.data.borrow())?;
+    check_eq!(staked_lucra_account.mint, staking_state.stake_mint.address, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&staked_lucra_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    check_eq!(&staking_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_staking_account_not_frozen(&staking_account, clock.unix_timestamp)?;
+    check_eq!(&stake_balance.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&pending_withdrawal.stake_balance, stake_balance_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    skip_swept_rewards(&mut stake_balance, &staking_state);
+    check!(staking_state.reward_cursor == stake_balance.reward_cursor, LucraErrorCode::RewardsOutstanding)?;
+    check_eq!(&stake_balance.balances.pending_vault, pending_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&stake_balance.balances.stake_vault, stake_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let staked_before = get_token_balance(stake_vault_ai)?;
+    bind_staking_account(&mut stake_balance, staking_account_ai.key, &staking_account, staked_before)?;
+    check_eq!(&staking_state.stake_mint.address, staked_lucra_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let signer_bump_seed = [stake_balance.signer_bump_seed];
+    let signer_seeds: &[&[u8]] = &stake_balance_authority_seeds(owner_ai.key, system_state_ai.key, &signer_bump_seed);
+    let stake_balance_authority = Pubkey::create_program_address(signer_seeds, program_id).map_err(|_| throw_err!(LucraErrorCode::InvalidNonce))?;
+    check_eq!(stake_balance_authority_ai.key, &stake_balance_authority, LucraErrorCode::InvalidAccountInput)?;
+
+    let lucra = pending_withdrawal.lucra;
+    spl_token_transfer(
+        pending_vault_ai,
+        stake_vault_ai,
+        lucra,
+        stake_balance_authority_ai,
+        &[signer_seeds],
+        token_program_ai,
+    )?;
+    stake_balance.stake_checkpoints.record(clock.slot, staked_before, get_token_balance(stake_vault_ai)?);
+
+    let staked_lucra = lucra
+        .checked_mul(stake_balance.staking_timeframe.weight())
+        .ok_or(math_err!())?;
+    staking_state.mint_stake(
+        program_id,
+        staked_lucra_mint_ai,
+        user_staked_lucra_account_ai,
+        staked_lucra,
+        mint_authority_ai,
+        token_program_ai,
+    )?;
+    add_indexed_stake(&mut stake_balance, staked_lucra)?;
+
+    stake_balance.last_stake_timestamp = clock.unix_timestamp;
+    // Cancelling puts the stake back like Stake does, so it sits the next drop out too
+    stake_balance.increment_reward_cursor(staking_state.reward_cursor);
+    staking_account.add_total(lucra);
+
+    pending_withdrawal.close();
+    let lamports = close_account(pending_withdrawal_ai);
+    add_lamports(owner_ai, lamports);
+
+    Ok(())
+}
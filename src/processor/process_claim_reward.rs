@@ -7,8 +7,10 @@ use arrayref::array_ref;
 use rust_decimal_macros::dec;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     program_pack::Pack,
     pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
 };
 use spl_token::{
     state::Account,
@@ -23,10 +25,20 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
+    helpers::account::verify_distinct_accounts,
     helpers::math::calculate_annual_interest_rate,
+    helpers::reward_expiry::{check_reward_not_expired, record_reward_claim, skip_swept_rewards},
+    helpers::reward_index::{add_indexed_stake, claim_indexed_reward, needs_skipped_reward, release_index_liability},
+    helpers::settlement::check_not_settled,
+    helpers::spl::{get_token_balance, verify_user_token_account},
+    helpers::staking_freeze::{bind_staking_account, check_staking_account_not_frozen},
+    helpers::vaults::verify_rewards_vault,
+    idl,
+    instruction::Instruction,
     state::{
         staking::{
             StakeBalance,
+            StakingAccount,
             StakingState,
             Reward,
         },
@@ -36,16 +48,62 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::ClaimReward);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::ClaimReward { } => {
+            msg!("Instruction: Claim Reward");
+            process_claim_reward(program_id, accounts)
+        }
+        Instruction::ClaimRewardV2 { compound } => {
+            msg!("Instruction: Claim Reward V2");
+            process_claim_reward_v2(program_id, compound, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CLAIM_REWARD_SIZE: usize = idl::CLAIM_REWARD.len();
+const CLAIM_REWARD_V2_SIZE: usize = idl::CLAIM_REWARD_V2.len();
+
 #[inline(never)]
 pub fn process_claim_reward(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
-    const NUM_FIXED: usize = 13;
+    claim_reward(program_id, accounts, None)
+}
+
+// The V2 layout is the ClaimReward one with the staked lucra mint and its authority appended. They're only
+// used to compound, the reward lucra is paid to the wallet as before otherwise.
+#[inline(never)]
+pub fn process_claim_reward_v2(program_id: &Pubkey, compound: bool, accounts: &[AccountInfo]) -> LucraResult {
+    let [
+        staked_lucra_mint_ai,                   // write
+        stake_mint_authority_ai,                // read
+    ] = array_ref![accounts, CLAIM_REWARD_SIZE, CLAIM_REWARD_V2_SIZE - CLAIM_REWARD_SIZE];
+
+    let compound_accounts = if compound {
+        Some(CompoundAccounts { staked_lucra_mint_ai, stake_mint_authority_ai })
+    } else {
+        None
+    };
+    claim_reward(program_id, accounts, compound_accounts)
+}
+
+struct CompoundAccounts<'a, 'b> {
+    staked_lucra_mint_ai: &'a AccountInfo<'b>,
+    stake_mint_authority_ai: &'a AccountInfo<'b>,
+}
+
+#[inline(never)]
+fn claim_reward(program_id: &Pubkey, accounts: &[AccountInfo], compound_accounts: Option<CompoundAccounts>) -> LucraResult {
+    const NUM_FIXED: usize = CLAIM_REWARD_SIZE;
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,                        // read
-        staking_state_ai,                       // read
+        staking_state_ai,                       // write
+        staking_account_ai,                     // read, write to compound
         stake_balance_ai,                       // write
-        reward_ai,                              // read
-        user_staked_lucra_account_ai,           // read
+        reward_ai,                              // write, unused by balances on the reward index
+        user_staked_lucra_account_ai,           // read, write to compound
         lucra_vault_ai,                         // write
         lucra_account_ai,                       // write
         rewards_vault_ai,                       // write
@@ -56,71 +114,180 @@ pub fn process_claim_reward(program_id: &Pubkey, accounts: &[AccountInfo]) -> Lu
         token_program_ai,                       // read
     ] = accounts;
 
+    let clock = &Clock::get()?;
+
     check_eq!(stake_balance_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(staking_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(reward_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(user_staked_lucra_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_staked_lucra_account_ai, token_program_ai)?;
     check_eq!(lucra_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(lucra_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(msol_account_ai, token_program_ai)?;
+    verify_user_token_account(lucra_account_ai, token_program_ai)?;
     check_eq!(rewards_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(lucra_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_claim_accounts_distinct(
+        rewards_vault_ai.key,
+        lucra_vault_ai.key,
+        user_staked_lucra_account_ai.key,
+        lucra_account_ai.key,
+        msol_account_ai.key,
+    )?;
     
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
-    check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
-    check_eq!(&system_state.rewards_vault.address, rewards_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    // Rewards already dropped can still be claimed during settlement
+    if !system_state.settlement_active {
+        check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+    }
+    if compound_accounts.is_some() {
+        verify_can_compound(&system_state)?;
+    }
+    verify_rewards_vault(&system_state, rewards_vault_ai.key)?;
     check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    let staking_state: Ref<StakingState> = StakingState::load_checked(staking_state_ai, program_id)?;
+    let mut staking_state: RefMut<StakingState> = StakingState::load_mut_checked(staking_state_ai, program_id)?;
 
     let mut stake_balance: RefMut<StakeBalance> = StakeBalance::load_mut(stake_balance_ai)?;
     check!(!stake_balance.closed, LucraErrorCode::InvalidAccountInput)?;
+    let staking_account: Ref<StakingAccount> = StakingAccount::load_checked(staking_account_ai, program_id)?;
+    check_staking_account_not_frozen(&staking_account, clock.unix_timestamp)?;
     let staked_lucra_account = Account::unpack(&user_staked_lucra_account_ai.data.borrow())?;
     check_eq!(staked_lucra_account.owner, stake_balance.owner, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(staked_lucra_account.mint, staking_state.stake_mint.address, LucraErrorCode::InvalidAccountInput)?;
 
-    let reward: Ref<Reward> = Reward::load(reward_ai)?;
-
     let msol_account = Account::unpack(&msol_account_ai.data.borrow())?;
     let lucra_account = Account::unpack(&lucra_account_ai.data.borrow())?;
 
     check_eq!(&system_state.lucra_mint.address, lucra_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check!(stake_balance.last_stake_timestamp != 0, LucraErrorCode::NotStakedDuringDrop)?;
-    check!(stake_balance.last_stake_timestamp <= reward.start_timestamp, LucraErrorCode::NotStakedDuringDrop)?;
-    check!(stake_balance.reward_cursor <= reward.reward_cursor, LucraErrorCode::AlreadyProcessed)?;
-    check!(stake_balance.reward_cursor == reward.reward_cursor, LucraErrorCode::ClaimOutOfOrder)?;
-
     check_eq!(&stake_balance.balances.stake_vault, lucra_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let staked_before = get_token_balance(lucra_vault_ai)?;
+    bind_staking_account(&mut stake_balance, staking_account_ai.key, &staking_account, staked_before)?;
     check_eq!(&stake_balance.owner, &msol_account.owner, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&stake_balance.owner, &lucra_account.owner, LucraErrorCode::InvalidAccountInput)?;
 
-    transfer_reward(
-        program_id,
-        system_state.deref(),
-        &staked_lucra_account,
-        &reward,
-        rewards_vault_ai,
-        msol_account_ai,
-        rewards_vault_transfer_authority_ai,
-        token_program_ai,
-    )?;
+    // Balances on the index are paid on the staked lucra minted for them, the wallet's account can hold other
+    // balances' stake or have been topped up by a transfer
+    let staked_amount = if stake_balance.on_reward_index {
+        stake_balance.indexed_stake
+    } else {
+        staked_lucra_account.amount
+    };
+    let drops = if stake_balance.on_reward_index {
+        // A balance that sat out a drop it staked into passes that drop's reward to claim from after it
+        let skipped_reward = if needs_skipped_reward(&stake_balance, &staking_state) {
+            Some(Reward::load_checked(reward_ai, program_id)?)
+        } else {
+            None
+        };
+        // Stake changes wait for a claim, so the indexed stake is the one held through every drop since the last
+        let claim = claim_indexed_reward(&mut stake_balance, &staking_state, skipped_reward.as_deref())?;
+        release_index_liability(&mut staking_state, claim.reward);
+        system_state.transfer_from_reward_vault(
+            program_id,
+            rewards_vault_ai,
+            msol_account_ai,
+            rewards_vault_transfer_authority_ai,
+            token_program_ai,
+            claim.reward,
+        )?;
+        claim.drops
+    } else {
+        check_eq!(reward_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let mut reward: RefMut<Reward> = Reward::load_mut(reward_ai)?;
+
+        check!(stake_balance.last_stake_timestamp != 0, LucraErrorCode::NotStakedDuringDrop)?;
+        check!(stake_balance.last_stake_timestamp <= reward.start_timestamp, LucraErrorCode::NotStakedDuringDrop)?;
+        skip_swept_rewards(&mut stake_balance, &staking_state);
+        // Skipped drops never take a cursor, their yield is part of the next reward, so the cursors are contiguous
+        check!(stake_balance.reward_cursor <= reward.reward_cursor, LucraErrorCode::AlreadyProcessed)?;
+        check!(stake_balance.reward_cursor == reward.reward_cursor, LucraErrorCode::ClaimOutOfOrder)?;
+        check_reward_not_expired(&reward, &system_state, clock.unix_timestamp)?;
+
+        let reward_amount = transfer_reward(
+            program_id,
+            system_state.deref(),
+            &staked_lucra_account,
+            &reward,
+            rewards_vault_ai,
+            msol_account_ai,
+            rewards_vault_transfer_authority_ai,
+            token_program_ai,
+        )?;
+        record_reward_claim(&mut reward, reward_amount)?;
+        // The drop is owed through the index too, whoever claims it
+        if reward.in_reward_index {
+            release_index_liability(&mut staking_state, reward_amount);
+        }
+        stake_balance.increment_reward_cursor(reward.reward_cursor);
+        1
+    };
+
+    let staking_timeframe = stake_balance.staking_timeframe;
+    let inflation_amount = calculate_inflation_for_drops(staking_timeframe.annual_inflation_rate(), staked_amount, drops)?;
+
+    let CompoundAccounts { staked_lucra_mint_ai, stake_mint_authority_ai } = match compound_accounts {
+        Some(compound_accounts) => compound_accounts,
+        None => {
+            return system_state.mint_lucra(
+                program_id,
+                lucra_mint_ai,
+                lucra_account_ai,
+                inflation_amount,
+                lucra_mint_authority_ai,
+                token_program_ai,
+            );
+        }
+    };
+
+    // Compounding stakes the inflation the way Stake does, which moves the stake timestamp past any reward
+    // that's still outstanding, so this claim has to be the last one
+    skip_swept_rewards(&mut stake_balance, &staking_state);
+    check!(staking_state.reward_cursor == stake_balance.reward_cursor, LucraErrorCode::RewardsOutstanding)?;
+    check_eq!(&staking_state.stake_mint.address, staked_lucra_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
 
-    let staking_timeframe = stake_balance.staking_timeframe;    
-    let inflation_amount = calculate_inflation(staking_timeframe.annual_inflation_rate(), staked_lucra_account.amount)?;
     system_state.mint_lucra(
         program_id,
         lucra_mint_ai,
-        lucra_account_ai,
+        lucra_vault_ai,
         inflation_amount,
         lucra_mint_authority_ai,
         token_program_ai,
     )?;
+    stake_balance.stake_checkpoints.record(clock.slot, staked_before, get_token_balance(lucra_vault_ai)?);
+
+    // The staked lucra mint's supply is the total DropReward splits the next reward over
+    let compounded_stake = calc_compounded_stake(inflation_amount, staking_timeframe.weight())?;
+    staking_state.mint_stake(
+        program_id,
+        staked_lucra_mint_ai,
+        user_staked_lucra_account_ai,
+        compounded_stake,
+        stake_mint_authority_ai,
+        token_program_ai,
+    )?;
+    add_indexed_stake(&mut stake_balance, compounded_stake)?;
 
-    stake_balance.increment_reward_cursor(reward.reward_cursor);
+    stake_balance.last_stake_timestamp = clock.unix_timestamp;
+    drop(staking_account);
+    let mut staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(staking_account_ai, program_id)?;
+    staking_account.add_total(inflation_amount);
 
     Ok(())
 }
 
+/// Compounding stakes the reward lucra, it can't be done during settlement or while staking is paused even
+/// though the reward itself can still be claimed then
+fn verify_can_compound(system_state: &SystemState) -> LucraResult {
+    check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+    check_not_settled(system_state)
+}
+
+/// Staked lucra minted for the compounded lucra, weighted by the stake balance's timeframe like Stake
+pub fn calc_compounded_stake(lucra: u64, staking_timeframe_weight: u64) -> LucraResult<u64> {
+    lucra
+        .checked_mul(staking_timeframe_weight)
+        .ok_or(math_err!())
+}
+
 #[inline(never)]
 #[allow(clippy::too_many_arguments)]
 pub fn transfer_reward<'a>(
@@ -132,7 +299,7 @@ pub fn transfer_reward<'a>(
     msol_account_ai: &AccountInfo<'a>,
     transfer_authority_ai: &AccountInfo<'a>,
     token_program_ai: &AccountInfo<'a>,
-) -> LucraResult {
+) -> LucraResult<u64> {
     let reward_amount = Decimal::from(stake_vault.amount)
         .checked_mul(reward.total.into())
         .ok_or(math_err!())?
@@ -151,7 +318,24 @@ pub fn transfer_reward<'a>(
         reward_amount,
     )?;
 
-    Ok(())
+    Ok(reward_amount)
+}
+
+/// The reward is sized off the user's staked lucra, so none of the user's accounts can double as a vault
+fn verify_claim_accounts_distinct(
+    rewards_vault: &Pubkey,
+    lucra_vault: &Pubkey,
+    user_staked_lucra_account: &Pubkey,
+    lucra_account: &Pubkey,
+    msol_account: &Pubkey,
+) -> LucraResult {
+    verify_distinct_accounts(&[
+        ("rewards_vault", rewards_vault),
+        ("lucra_vault", lucra_vault),
+        ("user_staked_lucra_account", user_staked_lucra_account),
+        ("lucra_account", lucra_account),
+        ("msol_account", msol_account),
+    ])
 }
 
 /// Inflation is x% annually of whatever lucra you have staked.
@@ -164,9 +348,22 @@ pub fn calculate_inflation(
     calculate_annual_interest_rate(inflation_rate as u32, stake_amount, time)
 }
 
+/// A claim on the reward index can cover several drops, each of them is a week of inflation
+pub fn calculate_inflation_for_drops(
+    inflation_rate: u8,
+    stake_amount: u64,
+    drops: u64,
+) -> LucraResult<u64> {
+    calculate_inflation(inflation_rate, stake_amount)?
+        .checked_mul(drops)
+        .ok_or(math_err!())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bytemuck::Zeroable;
+    use crate::helpers::account::tests::assert_aliases_are_rejected;
 
     #[test]
     fn test_calculate_inflation() {
@@ -178,4 +375,47 @@ mod tests {
         let actual = calculate_inflation(inflation_rate, amount).unwrap();
         assert_eq!(expected, actual);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_inflation_is_paid_per_drop() {
+        assert_eq!(calculate_inflation_for_drops(10, 10_000, 1).unwrap(), 19);
+        assert_eq!(calculate_inflation_for_drops(10, 10_000, 3).unwrap(), 3 * 19);
+        assert_eq!(calculate_inflation_for_drops(10, 10_000, 0).unwrap(), 0);
+    }
+
+    fn assert_claim_error(result: LucraResult, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_compounding_needs_staking_enabled() {
+        let mut system_state = SystemState::zeroed();
+        assert_claim_error(verify_can_compound(&system_state), LucraErrorCode::StakingNotEnabled);
+
+        system_state.staking_enabled = true;
+        assert!(verify_can_compound(&system_state).is_ok());
+
+        // Settlement still lets the reward be claimed to the wallet, not staked
+        system_state.settlement_active = true;
+        assert_claim_error(verify_can_compound(&system_state), LucraErrorCode::SettlementActive);
+    }
+
+    #[test]
+    fn test_compounded_stake_is_weighted() {
+        let inflation = calculate_inflation_for_drops(10, 10_000, 2).unwrap();
+
+        assert_eq!(calc_compounded_stake(inflation, 1).unwrap(), 38);
+        assert_eq!(calc_compounded_stake(inflation, 3).unwrap(), 3 * 38);
+        assert!(calc_compounded_stake(u64::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn test_claim_accounts_cannot_alias() {
+        assert_aliases_are_rejected(5, |keys| {
+            verify_claim_accounts_distinct(&keys[0], &keys[1], &keys[2], &keys[3], &keys[4])
+        });
+    }
+}
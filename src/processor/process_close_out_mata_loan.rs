@@ -5,6 +5,7 @@ use arrayref::array_ref;
 use marinade_finance;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     program_pack::Pack,
     pubkey::Pubkey,
 };
@@ -12,12 +13,28 @@ use spl_token::state::Account;
 use crate::{
     error::{
         check_assert,
+        LucraError,
         LucraErrorCode,
         LucraResult,
         SourceFileId,
     },
-    helpers::marinade::liquid_unstake,
+    helpers::account::verify_program_account,
+    helpers::collateral::{backfill_msol_collateral, calc_msol_lamport_value, get_marinade_msol_rate},
+    helpers::events::{emit_event, split_event_queue},
+    helpers::loan_address::verify_loan_address,
+    helpers::lp_collateral::transfer_from_lp_vault,
+    helpers::marinade::{liquid_unstake, max_instant_unstake, verify_instant_unstake_size, verify_liquid_unstake_liquidity},
+    helpers::penalty_rebate::calc_payable_rebate,
+    helpers::settlement::calc_settlement_mata_to_burn,
+    helpers::sol_vault::transfer_from_sol_vault,
+    helpers::spl::{get_token_balance, verify_token_program, verify_user_token_account},
+    helpers::vaults::{verify_lp_vault, verify_mata_mint, verify_msol_vault},
+    idl,
+    instruction::Instruction,
+    lucra_core::interest::calc_amount_owed,
+    lucra_core::penalty::calc_lp_penalty_tokens,
     state::{
+        EventKind,
         MataLoan,
         LoanType,
         staking::StakingAccount,
@@ -27,19 +44,60 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::CloseMataLoan);
 
-const CLOSE_OUT_MATA_LOAN_SIZE: usize = 16;
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CloseOutMataLoan {
+            unstake_msol,
+        } => {
+            msg!("Instruction: Close Loan");
+            process_close_out_mata_loan(
+                program_id,
+                unstake_msol,
+                accounts,
+            )
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CLOSE_OUT_MATA_LOAN_SIZE: usize = idl::CLOSE_MATA_LOAN.len();
+const CLOSE_OUT_LP_BACKED_MATA_LOAN_SIZE: usize = idl::CLOSE_LP_BACKED_MATA_LOAN.len();
+const CLOSE_OUT_NATIVE_SOL_MATA_LOAN_SIZE: usize = idl::CLOSE_NATIVE_SOL_MATA_LOAN.len();
 
 #[inline(never)]
 pub fn process_close_out_mata_loan(program_id: &Pubkey, unstake_msol: bool, accounts: &[AccountInfo]) -> LucraResult {
-    if accounts.len() == CLOSE_OUT_MATA_LOAN_SIZE {
-        close_out_mata_loan(program_id, unstake_msol, accounts)
+    // The layouts are told apart by their length, so the event queue has to come off first
+    let (accounts, event_queue_ai) = split_event_queue(program_id, accounts);
+    let amounts = if accounts.len() == CLOSE_OUT_LP_BACKED_MATA_LOAN_SIZE {
+        close_out_lp_backed_mata_loan(program_id, unstake_msol, accounts)?
+    } else if accounts.len() == CLOSE_OUT_NATIVE_SOL_MATA_LOAN_SIZE {
+        close_out_native_sol_mata_loan(program_id, unstake_msol, accounts)?
+    } else if accounts.len() == CLOSE_OUT_MATA_LOAN_SIZE {
+        close_out_mata_loan(program_id, unstake_msol, accounts)?
     } else {
-        close_out_mata_loan_with_locked_stake(program_id, unstake_msol, accounts)
-    }
+        close_out_mata_loan_with_locked_stake(program_id, unstake_msol, accounts)?
+    };
+
+    // Every layout starts with the system state, the loan is the second account of the LP backed and native SOL layouts
+    let system_state_ai = &accounts[0];
+    let loan_ai = match accounts.len() {
+        CLOSE_OUT_LP_BACKED_MATA_LOAN_SIZE | CLOSE_OUT_NATIVE_SOL_MATA_LOAN_SIZE => &accounts[1],
+        _ => &accounts[2],
+    };
+    emit_event(
+        program_id,
+        event_queue_ai,
+        system_state_ai.key,
+        EventKind::CloseMataLoan,
+        loan_ai.key,
+        amounts,
+        Clock::get()?.slot,
+    )
 }
 
 #[inline(never)]
-fn close_out_mata_loan(program_id: &Pubkey, unstake_msol: bool, accounts: &[AccountInfo]) -> LucraResult {
+fn close_out_mata_loan(program_id: &Pubkey, unstake_msol: bool, accounts: &[AccountInfo]) -> LucraResult<[u64; 2]> {
     const NUM_FIXED: usize = CLOSE_OUT_MATA_LOAN_SIZE;
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
@@ -66,14 +124,18 @@ fn close_out_mata_loan(program_id: &Pubkey, unstake_msol: bool, accounts: &[Acco
     check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_mata_account_ai, token_program_ai)?;
     check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(marinade_program_ai.key, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
 
     let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
-    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    // Loans can always be closed out during settlement
+    if !system_state.settlement_active {
+        check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    }
 
     let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    verify_loan_address(loan_ai.key, &loan.creator, loan.loan_index)?;
     check_eq!(loan.loan_type, LoanType::Default, LucraErrorCode::InvalidLoanType)?;
 
     close_loan(
@@ -102,7 +164,7 @@ fn close_out_mata_loan(program_id: &Pubkey, unstake_msol: bool, accounts: &[Acco
 }
 
 #[inline(never)]
-fn close_out_mata_loan_with_locked_stake(program_id: &Pubkey, unstake_msol: bool, accounts: &[AccountInfo]) -> LucraResult {
+fn close_out_mata_loan_with_locked_stake(program_id: &Pubkey, unstake_msol: bool, accounts: &[AccountInfo]) -> LucraResult<[u64; 2]> {
     const NUM_FIXED: usize = 17;
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
@@ -131,15 +193,18 @@ fn close_out_mata_loan_with_locked_stake(program_id: &Pubkey, unstake_msol: bool
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_mata_account_ai, token_program_ai)?;
     check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(marinade_program_ai.key, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
 
     let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
-    check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
-    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    if !system_state.settlement_active {
+        check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+        check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    }
 
     let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    verify_loan_address(loan_ai.key, &loan.creator, loan.loan_index)?;
     check_eq!(loan.loan_type, LoanType::LucraBacked, LucraErrorCode::InvalidLoanType)?;
     let mut staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(staking_account_ai, program_id)?;
 
@@ -169,11 +234,171 @@ fn close_out_mata_loan_with_locked_stake(program_id: &Pubkey, unstake_msol: bool
         system_program_ai,
         token_program_ai,
         unstake_msol,
+    )
+}
+
+#[inline(never)]
+fn close_out_lp_backed_mata_loan(program_id: &Pubkey, unstake_msol: bool, accounts: &[AccountInfo]) -> LucraResult<[u64; 2]> {
+    const NUM_FIXED: usize = CLOSE_OUT_LP_BACKED_MATA_LOAN_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        loan_ai,                    // write
+        user_account_ai,            // write
+        user_lp_account_ai,         // write
+        mata_mint_ai,               // write
+        user_mata_account_ai,       // write
+        lp_vault_authority_ai,      // read
+        lp_vault_ai,                // write
+        token_program_ai,           // read
+    ] = accounts;
+
+    // The LP tokens go back as they are, there is no msol to unstake
+    check_eq!(unstake_msol, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(lp_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_lp_account_ai, token_program_ai)?;
+    verify_user_token_account(user_mata_account_ai, token_program_ai)?;
+    verify_token_program(token_program_ai)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    if !system_state.settlement_active {
+        check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    }
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    verify_loan_address(loan_ai.key, &loan.creator, loan.loan_index)?;
+    check_eq!(loan.loan_type, LoanType::LpBacked, LucraErrorCode::InvalidLoanType)?;
+
+    let clock = &Clock::get()?;
+    let user_mata_account = Account::unpack(&user_mata_account_ai.data.borrow())?;
+    let user_lp_account = Account::unpack(&user_lp_account_ai.data.borrow())?;
+
+    if !system_state.settlement_active {
+        check!(loan.loan_creation_date + system_state.epoch < clock.unix_timestamp, LucraErrorCode::Timelock)?;
+    }
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.loan_mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_mata_account.mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_mata_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_lp_account.mint, &system_state.lp_mint, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_lp_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_lp_vault(&system_state, lp_vault_ai.key)?;
+
+    // The settlement price only values SOL collateral, LP backed loans repay the full amount even during settlement
+    let mata_to_burn = calc_amount_owed(&loan)?;
+    check!(user_mata_account.amount >= mata_to_burn, LucraErrorCode::InvalidAmount)?;
+
+    system_state.burn_mata(
+        mata_mint_ai,
+        user_mata_account_ai,
+        mata_to_burn,
+        user_account_ai,
+        token_program_ai,
     )?;
+    system_state.mata_supply.burn_repayment(mata_to_burn, loan.loan_amount)?;
+
+    // The LP tokens the penalty took stay in the LP vault
+    let lp_to_return = loan.lp_collateral_amount
+        .checked_sub(calc_lp_penalty_tokens(&loan)?)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    transfer_from_lp_vault(
+        program_id,
+        &system_state,
+        lp_vault_ai,
+        user_lp_account_ai,
+        lp_vault_authority_ai,
+        token_program_ai,
+        lp_to_return,
+    )?;
+
+    loan.update_harvested_penalty();
+    loan.interest_accrued = 0;
+    loan.repaid();
 
-    Ok(())
+    Ok([mata_to_burn, lp_to_return])
 }
 
+#[inline(never)]
+fn close_out_native_sol_mata_loan(program_id: &Pubkey, unstake_msol: bool, accounts: &[AccountInfo]) -> LucraResult<[u64; 2]> {
+    const NUM_FIXED: usize = CLOSE_OUT_NATIVE_SOL_MATA_LOAN_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        loan_ai,                    // write
+        user_account_ai,            // write
+        mata_mint_ai,               // write
+        user_mata_account_ai,       // write
+        sol_vault_ai,               // write
+        token_program_ai,           // read
+    ] = accounts;
+
+    // The lamports go back as they are, there is no msol to unstake
+    check_eq!(unstake_msol, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_mata_account_ai, token_program_ai)?;
+    verify_token_program(token_program_ai)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    if !system_state.settlement_active {
+        check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    }
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    verify_loan_address(loan_ai.key, &loan.creator, loan.loan_index)?;
+    check_eq!(loan.loan_type, LoanType::NativeSol, LucraErrorCode::InvalidLoanType)?;
+
+    let clock = &Clock::get()?;
+    let rent = &Rent::get()?;
+    let user_mata_account = Account::unpack(&user_mata_account_ai.data.borrow())?;
+
+    if !system_state.settlement_active {
+        check!(loan.loan_creation_date + system_state.epoch < clock.unix_timestamp, LucraErrorCode::Timelock)?;
+    }
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.loan_mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_mata_account.mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_mata_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+
+    let sol_to_return = loan.calc_remaining_sol();
+    let amount_owed = calc_amount_owed(&loan)?;
+    let mata_to_burn = if system_state.settlement_active {
+        calc_settlement_mata_to_burn(amount_owed, sol_to_return, system_state.settlement_sol_price)?
+    } else {
+        amount_owed
+    };
+    check!(user_mata_account.amount >= mata_to_burn, LucraErrorCode::InvalidAmount)?;
+
+    system_state.burn_mata(
+        mata_mint_ai,
+        user_mata_account_ai,
+        mata_to_burn,
+        user_account_ai,
+        token_program_ai,
+    )?;
+    system_state.mata_supply.burn_repayment(mata_to_burn, loan.loan_amount)?;
+
+    transfer_from_sol_vault(program_id, &system_state, sol_vault_ai, user_account_ai, sol_to_return, rent)?;
+
+    // Lamports earn nothing in the vault, so there's no msol yield a rebate could be paid out of. It stays
+    // recorded as owed on the closed loan.
+    loan.interest_accrued = 0;
+    loan.repaid();
+
+    Ok([mata_to_burn, sol_to_return])
+}
+
+/// Returns the mata burned and the lamports returned to the user
 #[inline(never)]
 #[allow(clippy::too_many_arguments)]
 pub fn close_loan<'a>(
@@ -198,36 +423,58 @@ pub fn close_loan<'a>(
     system_program_ai: &AccountInfo<'a>,
     token_program_ai: &AccountInfo<'a>,
     unstake_msol: bool,
-) -> LucraResult {
+) -> LucraResult<[u64; 2]> {
     let clock = &Clock::get()?;
 
     let user_mata_account = Account::unpack(&user_mata_account_ai.data.borrow())?;
     let user_msol_account = Account::unpack(&user_msol_account_ai.data.borrow())?;
     
-    check!(loan.loan_creation_date + system_state.epoch < clock.unix_timestamp, LucraErrorCode::Timelock)?;
+    if !system_state.settlement_active {
+        check!(loan.loan_creation_date + system_state.epoch < clock.unix_timestamp, LucraErrorCode::Timelock)?;
+    }
     check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&loan.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&loan.loan_mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&user_mata_account.mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&user_mata_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&user_msol_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.mata_mint.address, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.msol_vault.address, msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check!(user_mata_account.amount >= loan.loan_amount, LucraErrorCode::InvalidAmount)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+
+    let sol_to_return = loan.calc_remaining_sol();
+    // The interest accrued is owed on top of the debt. During settlement it's all valued at the frozen price instead of the peg
+    let amount_owed = calc_amount_owed(&loan)?;
+    let mata_to_burn = if system_state.settlement_active {
+        calc_settlement_mata_to_burn(amount_owed, sol_to_return, system_state.settlement_sol_price)?
+    } else {
+        amount_owed
+    };
+    check!(user_mata_account.amount >= mata_to_burn, LucraErrorCode::InvalidAmount)?;
+
+    // Penalty refunded after a price correction is paid out of what the loan's msol earned
+    backfill_msol_collateral(loan, &system_state, get_token_balance(msol_vault_ai)?)?;
+    let msol_collateral_lamports = calc_msol_lamport_value(loan.msol_collateral_amount, get_marinade_msol_rate(marinade_state_ai)?)?;
+    let rebate = calc_payable_rebate(loan.penalty_rebate, msol_collateral_lamports, sol_to_return);
+    let lamports_to_user = sol_to_return
+        .checked_add(rebate)
+        .ok_or(math_err!())?;
+    if unstake_msol {
+        verify_instant_unstake_size(lamports_to_user, max_instant_unstake(&system_state))?;
+        verify_liquid_unstake_liquidity(liq_pool_sol_leg_pda_ai, lamports_to_user)?;
+    }
 
     system_state.burn_mata(
         mata_mint_ai,
         user_mata_account_ai,
-        loan.loan_amount,
+        mata_to_burn,
         user_account_ai,
         token_program_ai,
     )?;
-
-    let sol_to_return = loan.calc_remaining_sol();
+    system_state.mata_supply.burn_repayment(mata_to_burn, loan.loan_amount)?;
 
     // Convert the lamports to msol
     let state = ProgramAccount::<marinade_finance::state::State>::try_from(marinade_program_ai.clone().key, &marinade_state_ai.clone()).unwrap();
-    let msol_lamports = state.calc_msol_from_lamports(sol_to_return).unwrap();
+    let msol_lamports = state.calc_msol_from_lamports(lamports_to_user).unwrap();
 
     system_state.transfer_from_msol_vault(
         program_id,
@@ -257,9 +504,14 @@ pub fn close_loan<'a>(
         )?;
     }
 
+    // Whatever the loan's share earned over what it was owed stays in the vault. Only the part of the rebate
+    // its msol could pay is settled, the rest stays recorded as owed.
+    loan.msol_collateral_amount = 0;
+    loan.penalty_rebate -= rebate;
+    loan.interest_accrued = 0;
     loan.repaid();
     
     system_state.remove_collateral(sol_to_return);
 
-    Ok(())
+    Ok([mata_to_burn, lamports_to_user])
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::reward_sunset::close_reward_program,
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::CloseRewardProgram);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CloseRewardProgram {} => {
+            msg!("Instruction: Close Reward Program");
+            process_close_reward_program(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// DAO instruction. Once the redemption deadline has passed this stops reward minting for good
+#[inline(never)]
+pub fn process_close_reward_program(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 2;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // write
+        dao_authority_ai,   // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+    close_reward_program(&mut system_state, clock.unix_timestamp)
+}
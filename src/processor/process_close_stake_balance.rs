@@ -0,0 +1,130 @@
+use std::cell::Ref;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::account::{add_lamports, close_account},
+    helpers::spl::{get_token_balance, spl_close_account},
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::stake_balance_authority_seeds,
+    state::staking::StakeBalance,
+};
+
+declare_check_assert_macros!(SourceFileId::CloseStakeBalance);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CloseStakeBalance { } => {
+            msg!("Instruction: Close Stake Balance");
+            process_close_stake_balance(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CLOSE_STAKE_BALANCE_SIZE: usize = idl::CLOSE_STAKE_BALANCE.len();
+
+/// Every pending withdrawal holds its lucra in the pending vault until it ends, so an empty pending vault
+/// also means nothing still points at the stake balance
+pub fn verify_stake_balance_empty(deposit_vault_balance: u64, stake_vault_balance: u64, pending_vault_balance: u64) -> LucraResult {
+    check!(
+        deposit_vault_balance == 0 && stake_vault_balance == 0 && pending_vault_balance == 0,
+        LucraErrorCode::StakeBalanceNotEmpty
+    )
+}
+
+// Closes an emptied stake balance and its three vaults, the owner gets all of the rent back. Rewards don't
+// have to be checked, unstaking already required them to be claimed.
+#[inline(never)]
+pub fn process_close_stake_balance(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = CLOSE_STAKE_BALANCE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,                // read
+        stake_balance_ai,               // write
+        deposit_vault_ai,               // write
+        stake_vault_ai,                 // write
+        pending_vault_ai,               // write
+        owner_ai,                       // write
+        stake_balance_authority_ai,     // read
+        token_program_ai,               // read
+    ] = accounts;
+
+    check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(stake_balance_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(deposit_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(stake_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(pending_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let stake_balance: Ref<StakeBalance> = StakeBalance::load_checked(stake_balance_ai, program_id)?;
+    check_eq!(&stake_balance.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&stake_balance.balances.deposit_vault, deposit_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&stake_balance.balances.stake_vault, stake_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&stake_balance.balances.pending_vault, pending_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let signer_bump_seed = [stake_balance.signer_bump_seed];
+    drop(stake_balance);
+
+    let signer_seeds: &[&[u8]] = &stake_balance_authority_seeds(owner_ai.key, system_state_ai.key, &signer_bump_seed);
+    let stake_balance_authority = Pubkey::create_program_address(signer_seeds, program_id).map_err(|_| throw_err!(LucraErrorCode::InvalidNonce))?;
+    // The system state is one of the authority's seeds, the vaults of another deployment won't match it
+    check_eq!(stake_balance_authority_ai.key, &stake_balance_authority, LucraErrorCode::InvalidAccountInput)?;
+
+    verify_stake_balance_empty(
+        get_token_balance(deposit_vault_ai)?,
+        get_token_balance(stake_vault_ai)?,
+        get_token_balance(pending_vault_ai)?,
+    )?;
+
+    for vault_ai in [deposit_vault_ai, stake_vault_ai, pending_vault_ai] {
+        spl_close_account(
+            vault_ai,
+            owner_ai,
+            stake_balance_authority_ai,
+            &[signer_seeds],
+            token_program_ai,
+        )?;
+    }
+
+    // Zeroed so the account can't be loaded again before the runtime drops it
+    stake_balance_ai.data.borrow_mut().fill(0);
+    let lamports = close_account(stake_balance_ai);
+    add_lamports(owner_ai, lamports);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_balance_left_keeps_the_stake_balance_open() {
+        assert!(verify_stake_balance_empty(0, 0, 0).is_ok());
+
+        for balances in [(1, 0, 0), (0, 1, 0), (0, 0, 1)] {
+            assert!(matches!(
+                verify_stake_balance_empty(balances.0, balances.1, balances.2).unwrap_err(),
+                LucraError::LucraErrorCode {
+                    lucra_error_code: LucraErrorCode::StakeBalanceNotEmpty,
+                    ..
+                }
+            ));
+        }
+    }
+}
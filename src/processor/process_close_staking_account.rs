@@ -0,0 +1,105 @@
+use std::cell::Ref;
+
+use anchor_lang::prelude::*;
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        account::{add_lamports, close_account},
+        staking_freeze::check_staking_account_not_frozen,
+    },
+    idl,
+    instruction::Instruction,
+    state::staking::StakingAccount,
+};
+
+declare_check_assert_macros!(SourceFileId::CloseStakingAccount);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CloseStakingAccount { } => {
+            msg!("Instruction: Close Staking Account");
+            process_close_staking_account(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CLOSE_STAKING_ACCOUNT_SIZE: usize = idl::CLOSE_STAKING_ACCOUNT.len();
+
+/// Lucra backed loans pledge against `locked_total`, so nothing locked means no loan still depends on the account
+pub fn verify_staking_account_empty(staking_account: &StakingAccount) -> LucraResult {
+    check!(
+        staking_account.total == 0 && staking_account.locked_total == 0,
+        LucraErrorCode::StakingAccountNotEmpty
+    )
+}
+
+// Closes a staking account that has nothing staked or pledged, the owner gets the rent back
+#[inline(never)]
+pub fn process_close_staking_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = CLOSE_STAKING_ACCOUNT_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        staking_account_ai,             // write
+        owner_ai,                       // write
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let staking_account: Ref<StakingAccount> = StakingAccount::load_checked(staking_account_ai, program_id)?;
+    check_eq!(&staking_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    // A frozen account's key may have leaked, it stays open until the owner unfreezes it
+    check_staking_account_not_frozen(&staking_account, clock.unix_timestamp)?;
+    verify_staking_account_empty(&staking_account)?;
+    drop(staking_account);
+
+    // Zeroed so the account can't be loaded again before the runtime drops it
+    staking_account_ai.data.borrow_mut().fill(0);
+    let lamports = close_account(staking_account_ai);
+    add_lamports(owner_ai, lamports);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn staking_account(total: u64, locked_total: u64) -> StakingAccount {
+        let mut staking_account = StakingAccount::default();
+        staking_account.total = total;
+        staking_account.locked_total = locked_total;
+        staking_account
+    }
+
+    #[test]
+    fn test_locked_collateral_keeps_the_staking_account_open() {
+        assert!(verify_staking_account_empty(&staking_account(0, 0)).is_ok());
+
+        for (total, locked_total) in [(1, 0), (0, 1), (1_000, 600)] {
+            assert!(matches!(
+                verify_staking_account_empty(&staking_account(total, locked_total)).unwrap_err(),
+                LucraError::LucraErrorCode {
+                    lucra_error_code: LucraErrorCode::StakingAccountNotEmpty,
+                    ..
+                }
+            ));
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::{
+        SOL_USDC_ORACLE,
+        SOL_USDT_ORACLE,
+        LUCRA_SOL_ORACLE,
+        SOL_MATA_ORACLE,
+        UNIX_HOUR,
+    },
+    helpers::collateral::get_marinade_msol_rate,
+    helpers::oracle::{get_lucra_price, get_mata_price, get_sol_price, verify_sol_usd_oracle},
+    helpers::keepers::{resolve_crank_caller, split_keeper},
+    helpers::reward_tokens::mint_whole_reward,
+    helpers::settlement::check_not_settled,
+    helpers::solana::create_pda_account,
+    helpers::spl::get_token_balance,
+    helpers::valuation::{calc_micro_usd_price, calc_tvl_components, TvlBalances, TvlPrices},
+    helpers::vaults::{
+        verify_arb_coffer,
+        verify_msol_vault,
+        verify_reward_mint,
+        verify_rewards_vault,
+    },
+    instruction::Instruction,
+    lucra_core::pda::{find_tvl_snapshot, tvl_snapshot_seeds},
+    state::{
+        ArbState,
+        SystemState,
+        TvlSnapshot,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::ComputeTvl);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::ComputeTvl { } => {
+            msg!("Instruction: Compute Tvl");
+            process_compute_tvl(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Crank that writes the canonical TVL, see TvlComponents for what's counted. The first snapshot creates the
+// account, the caller pays its rent. Like the price history it can run at most once an hour.
+#[inline(never)]
+pub fn process_compute_tvl(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    let (accounts, keeper) = split_keeper(program_id, accounts);
+    const NUM_FIXED: usize = 20;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // read
+        arb_state_ai,               // read
+        tvl_snapshot_ai,            // write
+        marinade_state_ai,          // read
+
+        msol_vault_ai,              // read
+        arb_coffer_ai,              // read
+        rewards_vault_ai,           // read
+        wsol_holding_vault_ai,      // read
+        mata_holding_vault_ai,      // read
+        lucra_holding_vault_ai,     // read
+
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        lucra_sol_oracle_ai,        // read
+        sol_mata_oracle_ai,         // read
+
+        user_account_ai,            // write
+        user_reward_account_ai,     // write
+        reward_mint_ai,             // write
+        reward_mint_authority_ai,   // read
+        system_program_ai,          // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    // Verify the accounts are owned by the right programs
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(arb_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(lucra_sol_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_reward_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(reward_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+    for vault_ai in [msol_vault_ai, arb_coffer_ai, rewards_vault_ai, wsol_holding_vault_ai, mata_holding_vault_ai, lucra_holding_vault_ai] {
+        check_eq!(vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    }
+
+    // Verify the oracles
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    check_eq!(lucra_sol_oracle_ai.key, &LUCRA_SOL_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    let keeper_multiplier = resolve_crank_caller(program_id, &system_state, keeper)?;
+    check_not_settled(&system_state)?;
+    verify_reward_mint(&system_state, reward_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+    verify_arb_coffer(&system_state, arb_coffer_ai.key)?;
+    verify_rewards_vault(&system_state, rewards_vault_ai.key)?;
+
+    check_eq!(&system_state.arb_state, arb_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let arb_state: Ref<ArbState> = ArbState::load_checked(arb_state_ai, program_id)?;
+    check_eq!(&arb_state.wsol_holding_vault.address, wsol_holding_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&arb_state.mata_holding_vault.address, mata_holding_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&arb_state.lucra_holding_vault.address, lucra_holding_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let (tvl_snapshot, bump_seed) = find_tvl_snapshot(system_state_ai.key);
+    check_eq!(tvl_snapshot_ai.key, &tvl_snapshot, LucraErrorCode::InvalidAccountInput)?;
+
+    let first_snapshot = tvl_snapshot_ai.data_is_empty();
+    if first_snapshot {
+        let rent = Rent::get()?;
+        create_pda_account(
+            user_account_ai,
+            tvl_snapshot_ai,
+            rent.minimum_balance(size_of::<TvlSnapshot>()),
+            size_of::<TvlSnapshot>() as u64,
+            program_id,
+            &[&tvl_snapshot_seeds(system_state_ai.key, &[bump_seed])],
+            system_program_ai,
+        )?;
+    }
+
+    let mut snapshot: RefMut<TvlSnapshot> = if first_snapshot {
+        let mut snapshot: RefMut<TvlSnapshot> = TvlSnapshot::load_mut(tvl_snapshot_ai)?;
+        snapshot.init(system_state_ai.key);
+        snapshot
+    } else {
+        TvlSnapshot::load_mut_checked(tvl_snapshot_ai, program_id)?
+    };
+    check_eq!(&snapshot.system_state, system_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check!(snapshot.timestamp + UNIX_HOUR <= clock.unix_timestamp, LucraErrorCode::InsufficientTimePassed)?;
+
+    let prices = TvlPrices {
+        marinade_rate: get_marinade_msol_rate(marinade_state_ai)?,
+        sol_price: get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?,
+        mata_price: get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?,
+        lucra_price: get_lucra_price(&system_state, lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?,
+    };
+    let balances = TvlBalances {
+        msol_vault_msol: get_token_balance(msol_vault_ai)?,
+        arb_coffer_msol: get_token_balance(arb_coffer_ai)?,
+        rewards_vault_msol: get_token_balance(rewards_vault_ai)?,
+        wsol_holding_vault_lamports: get_token_balance(wsol_holding_vault_ai)?,
+        mata_holding_vault_mata: get_token_balance(mata_holding_vault_ai)?,
+        lucra_holding_vault_lucra: get_token_balance(lucra_holding_vault_ai)?,
+    };
+
+    snapshot.record(
+        calc_tvl_components(&balances, &prices)?,
+        calc_micro_usd_price(prices.sol_price)?,
+        calc_micro_usd_price(prices.mata_price)?,
+        calc_micro_usd_price(prices.lucra_price)?,
+        clock.unix_timestamp,
+    )?;
+
+    // Pay the user for their efforts
+    mint_whole_reward(
+        program_id,
+        &system_state,
+        reward_mint_ai,
+        user_reward_account_ai,
+        1,
+        keeper_multiplier,
+        reward_mint_authority_ai,
+        token_program_ai,
+    )?;
+
+    Ok(())
+}
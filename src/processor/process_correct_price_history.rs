@@ -0,0 +1,141 @@
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::PRICE_HISTORY_ID,
+    helpers::dao_authority::check_dao_authority,
+    helpers::price_history::price_for_date_mut,
+    helpers::settlement::check_not_settled,
+    helpers::solana::create_pda_account,
+    instruction::Instruction,
+    lucra_core::{
+        pda::{find_price_correction, price_correction_seeds},
+        penalty::start_of_day,
+    },
+    state::{
+        PriceCorrection,
+        PriceHistory,
+        PriceHistoryPage,
+        SystemState,
+        PAGED_PRICE_HISTORY_VERSION,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::CorrectPriceHistory);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CorrectPriceHistory {
+            date,
+            corrected_sol_price,
+            corrected_lucra_price,
+        } => {
+            msg!("Instruction: Correct Price History");
+            process_correct_price_history(program_id, date, corrected_sol_price, corrected_lucra_price, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// DAO instruction. Rewrites the prices of a day an oracle misreported. Loans charged penalty for the day
+// are brought in line by RecomputePenalty, the correction account keeps what the day originally said.
+#[inline(never)]
+pub fn process_correct_price_history(
+    program_id: &Pubkey,
+    date: i64,
+    corrected_sol_price: u64,
+    corrected_lucra_price: u64,
+    accounts: &[AccountInfo],
+) -> LucraResult {
+    const NUM_FIXED: usize = 5;
+    let price_history_page_ai = accounts.get(NUM_FIXED);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // read
+        price_history_ai,       // write
+        price_correction_ai,    // write
+        dao_authority_ai,       // write
+        system_program_ai,      // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(price_history_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(price_history_ai.key, &PRICE_HISTORY_ID, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+
+    // Only days that are over can be corrected, today is still being sampled
+    check_eq!(start_of_day(date), date, LucraErrorCode::InvalidAccountInput)?;
+    check!(date < start_of_day(clock.unix_timestamp), LucraErrorCode::InvalidAccountInput)?;
+
+    let (price_correction, bump_seed) = find_price_correction(system_state_ai.key, date);
+    check_eq!(price_correction_ai.key, &price_correction, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut price_history: Box<RefMut<PriceHistory>> = PriceHistory::load_mut_checked(price_history_ai, program_id)?;
+    // A paged price history archived the day too, the page it's on is corrected with it
+    let is_paged = price_history.meta_data.version >= PAGED_PRICE_HISTORY_VERSION;
+    check!(!is_paged || price_history_page_ai.is_some(), LucraErrorCode::InvalidAccountInput)?;
+    let history = price_for_date_mut(&mut price_history, date)
+        .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+
+    // The first correction of a day creates its record, the dao pays the rent
+    let first_correction = price_correction_ai.data_is_empty();
+    if first_correction {
+        let rent = Rent::get()?;
+        create_pda_account(
+            dao_authority_ai,
+            price_correction_ai,
+            rent.minimum_balance(size_of::<PriceCorrection>()),
+            size_of::<PriceCorrection>() as u64,
+            program_id,
+            &[&price_correction_seeds(system_state_ai.key, &date.to_le_bytes(), &[bump_seed])],
+            system_program_ai,
+        )?;
+    }
+
+    let mut correction: RefMut<PriceCorrection> = if first_correction {
+        let mut correction: RefMut<PriceCorrection> = PriceCorrection::load_mut(price_correction_ai)?;
+        correction.init(system_state_ai.key, history);
+        correction
+    } else {
+        PriceCorrection::load_mut_checked(price_correction_ai, program_id)?
+    };
+    check_eq!(&correction.system_state, system_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    correction.correct(history, corrected_sol_price, corrected_lucra_price, clock.unix_timestamp);
+
+    if let (true, Some(price_history_page_ai)) = (is_paged, price_history_page_ai) {
+        let mut page: RefMut<PriceHistoryPage> = PriceHistoryPage::load_mut_checked(price_history_page_ai, program_id)?;
+        let archived = page.price_for_date_mut(date)
+            .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+        archived.sol_price = history.sol_price;
+        archived.lucra_price = history.lucra_price;
+    }
+
+    Ok(())
+}
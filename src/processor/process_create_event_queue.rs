@@ -0,0 +1,86 @@
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar},
+};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::CREATOR_AUTHORITY,
+    helpers::settlement::check_not_settled,
+    helpers::solana::create_pda_account,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::{event_queue_seeds, find_event_queue},
+    state::{
+        EventQueue,
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::CreateEventQueue);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CreateEventQueue { } => {
+            msg!("Instruction: Create Event Queue");
+            process_create_event_queue(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CREATE_EVENT_QUEUE_SIZE: usize = idl::CREATE_EVENT_QUEUE.len();
+
+// One time instruction. The queue is a PDA of the system state, so there's only ever the one to read.
+#[inline(never)]
+pub fn process_create_event_queue(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = CREATE_EVENT_QUEUE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // read
+        event_queue_ai,         // write
+        creator_authority_ai,   // write
+        system_program_ai,      // read
+    ] = accounts;
+
+    check_eq!(creator_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(creator_authority_ai.key, &CREATOR_AUTHORITY, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+
+    let (event_queue, bump_seed) = find_event_queue(system_state_ai.key);
+    check_eq!(event_queue_ai.key, &event_queue, LucraErrorCode::InvalidAccountInput)?;
+    check!(event_queue_ai.data_is_empty(), LucraErrorCode::AlreadyProcessed)?;
+
+    let rent = Rent::get()?;
+    create_pda_account(
+        creator_authority_ai,
+        event_queue_ai,
+        rent.minimum_balance(size_of::<EventQueue>()),
+        size_of::<EventQueue>() as u64,
+        program_id,
+        &[&event_queue_seeds(system_state_ai.key, &[bump_seed])],
+        system_program_ai,
+    )?;
+
+    let mut queue: RefMut<EventQueue> = EventQueue::load_mut(event_queue_ai)?;
+    queue.init(system_state_ai.key);
+
+    Ok(())
+}
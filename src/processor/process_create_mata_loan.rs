@@ -8,12 +8,12 @@ use legends_loadable_trait::Loadable;
 use spl_token::state::Account;
 use solana_program::{
     account_info::AccountInfo,
-    native_token::LAMPORTS_PER_SOL,
+    msg,
     pubkey::Pubkey,
     program_pack::Pack,
     sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
-use rust_decimal::{Decimal, prelude::ToPrimitive};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 use crate::{
     error::{
         check_assert,
@@ -23,38 +23,95 @@ use crate::{
         SourceFileId,
     },
     helpers::{
-        account::{verify_account_will_still_have_lamports, add_lamports, close_account},
-        constants::{LAMPORTS_PER_MATA, SOL_USDC_ORACLE, SOL_USDT_ORACLE, LUCRA_SOL_ORACLE, SOL_MATA_ORACLE },
+        account::{verify_account_will_still_have_lamports, verify_distinct_accounts, verify_program_account, without_fees_sysvar},
+        collateral::{find_msol_sol_oracle, get_collateral_valuation_factor},
+        constants::{FEE_BUFFER_LAMPORTS, SOL_USDC_ORACLE, SOL_USDT_ORACLE, LUCRA_SOL_ORACLE, SOL_MATA_ORACLE, SOL_MATA_ORCA_AMM },
+        events::{emit_event, split_event_queue},
+        loan_address::create_loan_account,
+        loan_history::open_collateral_history,
+        lp_collateral::{check_lp_loans_enabled, get_lp_collateral_value},
         spl::*,
         oracle::*,
         marinade::deposit,
+        math::{calc_stake_value_left_to_lock, verify_minimum_loan_amount},
+        peg_band::update_peg,
+        settlement::check_not_settled,
+        sol_vault::{check_native_loans_enabled, deposit_to_sol_vault},
+        vaults::{verify_lp_vault, verify_mata_mint, verify_msol_vault},
+    },
+    idl,
+    instruction::Instruction,
+    lucra_core::{
+        interest::LOAN_INTEREST_VERSION,
+        quote::{calc_lamports_for_value, calc_loan_amount, quote_mata_loan},
     },
     state::{
         DataType,
+        EventKind,
+        MSOL_COLLATERAL_TYPE,
         MetaData,
         MataLoan,
         LoanType,
+        MataBucket,
+        RevenueLedger,
         SystemState,
         staking::StakingAccount,
     },
+    units::{Lamports, Mata},
 };
 
 declare_check_assert_macros!(SourceFileId::BeginCreateMataLoan);
 
-const CREATE_MATA_LOAN_SIZE: usize = 22;
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CreateMataLoan {
+            lamports,
+        } => {
+            msg!("Instruction: Create Loan");
+            process_create_mata_loan(
+                program_id,
+                lamports,
+                accounts,
+            )
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CREATE_MATA_LOAN_SIZE: usize = idl::CREATE_MATA_LOAN.len();
+const CREATE_MATA_LOAN_WITH_LOCKED_STAKE_SIZE: usize = idl::CREATE_MATA_LOAN_WITH_LOCKED_STAKE.len();
+const CREATE_LP_BACKED_MATA_LOAN_SIZE: usize = idl::CREATE_LP_BACKED_MATA_LOAN.len();
+const CREATE_NATIVE_SOL_MATA_LOAN_SIZE: usize = idl::CREATE_NATIVE_SOL_MATA_LOAN.len();
 
 #[inline(never)]
 pub fn process_create_mata_loan(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
-    if accounts.len() == CREATE_MATA_LOAN_SIZE {
-        create_mata_loan(program_id, lamports, accounts)
+    let accounts = &*without_fees_sysvar(accounts);
+    let (accounts, event_queue_ai) = split_event_queue(program_id, accounts);
+
+    // The LP backed layout has the SOL/MATA pool where the marinade state would be, and the amount is in LP tokens.
+    // The native SOL layout has the program owned sol vault there instead.
+    // The locked stake layout inserts the user's staking account ahead of the oracles
+    if accounts.len() > 1 && accounts[1].key == &SOL_MATA_ORCA_AMM {
+        create_lp_backed_mata_loan(program_id, lamports, accounts, event_queue_ai)
+    } else if accounts.len() > 1 && accounts[1].owner == program_id {
+        create_native_sol_mata_loan(program_id, lamports, accounts, event_queue_ai)
+    } else if accounts.len() > 9 && accounts[9].key == &SOL_USDC_ORACLE {
+        create_mata_loan(program_id, lamports, accounts, event_queue_ai)
     } else {
-        create_mata_loan_with_locked_stake(program_id, lamports, accounts)
+        create_mata_loan_with_locked_stake(program_id, lamports, accounts, event_queue_ai)
     }
 }
 
 #[inline(never)]
-fn create_mata_loan(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
+fn create_mata_loan(
+    program_id: &Pubkey,
+    lamports: u64,
+    accounts: &[AccountInfo],
+    event_queue_ai: Option<&AccountInfo>,
+) -> LucraResult {
     const NUM_FIXED: usize = CREATE_MATA_LOAN_SIZE;
+    let trailing_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,                // write
@@ -78,10 +135,10 @@ fn create_mata_loan(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]
         liq_pool_msol_leg_authority_ai, // read
         reserve_pda_ai,                 // write
         msol_mint_authority_ai,         // read
-        fees_ai,                        // read
         system_program_ai,              // read
         token_program_ai,               // read
         marinade_program_ai,            // read
+        loan_registry_ai,               // write
     ] = accounts;
 
     let clock = &Clock::get()?;
@@ -90,37 +147,38 @@ fn create_mata_loan(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]
     check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
     // Verify accounts are owned by the right programs
-    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(sol_usdt_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(sol_usdc_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_mata_account_ai, token_program_ai)?;
+    verify_user_token_account(user_msol_account_ai, token_program_ai)?;
     check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(marinade_program_ai.key, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
+    verify_token_program(token_program_ai)?;
 
-    check_eq!(sol_usdc_oracle_ai.key, &SOL_USDC_ORACLE, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(sol_usdt_oracle_ai.key, &SOL_USDT_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
 
     let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
     check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    let revenue_ledger_ai = trailing_accounts
+        .first()
+        .filter(|ai| !is_sol_mata_amm(ai.key) && ai.key != &system_state.msol_sol_oracle);
+    let msol_sol_oracle_ai = find_msol_sol_oracle(trailing_accounts, &system_state);
 
     if system_state.peg_check_enabled {
         check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
         check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
 
-        let mata_market_price = get_mata_price(sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-        system_state.update_peg(mata_market_price)?;
+        let mata_market_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+        update_peg(&mut system_state, mata_market_price);
+        check_peg_holds(&system_state)?;
     }
 
-    let sol_market_price = get_sol_price(sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-    let lamport_dollar_value = sol_market_price
-        .checked_mul(lamports.into())
-        .ok_or(math_err!())?
-        .checked_div(LAMPORTS_PER_SOL.into())
-        .ok_or(math_err!())?;
-    let loan_amount = get_loan_amount(lamport_dollar_value, system_state.collateral_requirement)?;
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let collateral_factor = get_collateral_valuation_factor(&system_state, msol_sol_oracle_ai, Some(marinade_state_ai), clock)?;
+    let quote = quote_mata_loan(lamports, sol_market_price, collateral_factor, system_state.collateral_requirement, None)?;
+    verify_minimum_loan_amount(quote.loan_amount, system_state.min_loan_amount_mata)?;
 
     create_loan(
         program_id,
@@ -140,23 +198,42 @@ fn create_mata_loan(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]
         user_mata_account_ai,
         user_msol_account_ai,
         loan_ai,
-        fees_ai,
+        loan_registry_ai,
         system_program_ai,
         token_program_ai,
         lamports,
-        loan_amount,
+        quote.loan_amount.get(),
         0,
         sol_market_price.floor().to_u64().ok_or(math_err!())?,
         LoanType::Default,
     )?;
 
-    Ok(())
+    if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+        // No origination fee is charged yet, recording still rolls the ledger into the current epoch
+        revenue_ledger.record_origination_fees(clock.unix_timestamp, system_state.epoch, 0);
+    }
+
+    emit_event(
+        program_id,
+        event_queue_ai,
+        system_state_ai.key,
+        EventKind::CreateMataLoan,
+        loan_ai.key,
+        [lamports, quote.loan_amount.get()],
+        clock.slot,
+    )
 }
 
 #[inline(never)]
 #[allow(clippy::too_many_arguments)]
-fn create_mata_loan_with_locked_stake(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
-    const NUM_FIXED: usize = 24;
+fn create_mata_loan_with_locked_stake(
+    program_id: &Pubkey,
+    lamports: u64,
+    accounts: &[AccountInfo],
+    event_queue_ai: Option<&AccountInfo>,
+) -> LucraResult {
+    const NUM_FIXED: usize = CREATE_MATA_LOAN_WITH_LOCKED_STAKE_SIZE;
+    let trailing_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,                // write
@@ -181,10 +258,10 @@ fn create_mata_loan_with_locked_stake(program_id: &Pubkey, lamports: u64, accoun
         liq_pool_msol_leg_authority_ai, // read
         reserve_pda_ai,                 // write
         msol_mint_authority_ai,         // read
-        fees_ai,                        // read
         system_program_ai,              // read
         token_program_ai,               // read
         marinade_program_ai,            // read
+        loan_registry_ai,               // write
     ] = accounts;
 
     let clock = &Clock::get()?;
@@ -193,25 +270,28 @@ fn create_mata_loan_with_locked_stake(program_id: &Pubkey, lamports: u64, accoun
     check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
     // Verify accounts are owned by the right programs
-    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(user_staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_mata_account_ai, token_program_ai)?;
+    verify_user_token_account(user_msol_account_ai, token_program_ai)?;
     check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(sol_usdt_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(sol_usdc_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(lucra_sol_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(marinade_program_ai.key, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
+    verify_token_program(token_program_ai)?;
 
-    check_eq!(sol_usdc_oracle_ai.key, &SOL_USDC_ORACLE, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(sol_usdt_oracle_ai.key, &SOL_USDT_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
     check_eq!(lucra_sol_oracle_ai.key, &LUCRA_SOL_ORACLE, LucraErrorCode::InvalidAccountInput)?;
 
     let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
     check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
     check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    let revenue_ledger_ai = trailing_accounts
+        .first()
+        .filter(|ai| !is_sol_mata_amm(ai.key) && ai.key != &system_state.msol_sol_oracle);
+    let msol_sol_oracle_ai = find_msol_sol_oracle(trailing_accounts, &system_state);
 
     let mut user_staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(user_staking_account_ai, program_id)?;
     check_eq!(&user_staking_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
@@ -220,34 +300,20 @@ fn create_mata_loan_with_locked_stake(program_id: &Pubkey, lamports: u64, accoun
         check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
         check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
 
-        let mata_market_price = get_mata_price(sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-        system_state.update_peg(mata_market_price)?;
+        let mata_market_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+        update_peg(&mut system_state, mata_market_price);
+        check_peg_holds(&system_state)?;
     }
 
-    let sol_market_price = get_sol_price(sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-    let lamport_dollar_value = sol_market_price
-        .checked_mul(lamports.into())
-        .ok_or(math_err!())?
-        .checked_div(LAMPORTS_PER_SOL.into())
-        .ok_or(math_err!())?;
-    let staking_value_required = get_required_stake_value(system_state.lcp, lamports, sol_market_price)?;
-    let total_value_supplied = lamport_dollar_value
-        .checked_add(staking_value_required)
-        .ok_or(math_err!())?;
-
-    let loan_amount = get_loan_amount(total_value_supplied, system_state.collateral_requirement)?;
-
-    let lucra_market_price = get_lucra_price(lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-    let value_left_to_lock: u64 = Decimal::from(user_staking_account.total)
-        .checked_mul(lucra_market_price)
-        .ok_or(math_err!())?
-        .checked_sub(user_staking_account.locked_total.into())
-        .ok_or(math_err!())?
-        .floor()
-        .to_u64()
-        .ok_or(math_err!())?;
-
-    check!(staking_value_required.floor().to_u64().unwrap() <= value_left_to_lock, LucraErrorCode::InvalidAmount)?;
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let collateral_factor = get_collateral_valuation_factor(&system_state, msol_sol_oracle_ai, Some(marinade_state_ai), clock)?;
+    let quote = quote_mata_loan(lamports, sol_market_price, collateral_factor, system_state.collateral_requirement, Some(system_state.lcp))?;
+    verify_minimum_loan_amount(quote.loan_amount, system_state.min_loan_amount_mata)?;
+
+    let lucra_market_price = get_lucra_price(&system_state, lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let value_left_to_lock = calc_stake_value_left_to_lock(user_staking_account.total, lucra_market_price, user_staking_account.locked_total)?;
+
+    check!(quote.staking_value_required <= value_left_to_lock, LucraErrorCode::InvalidAmount)?;
 
     create_loan(
         program_id,
@@ -267,19 +333,322 @@ fn create_mata_loan_with_locked_stake(program_id: &Pubkey, lamports: u64, accoun
         user_mata_account_ai,
         user_msol_account_ai,
         loan_ai,
-        fees_ai,
+        loan_registry_ai,
         system_program_ai,
         token_program_ai,
         lamports,
-        loan_amount,
-        staking_value_required.floor().to_u64().ok_or(math_err!())?,
+        quote.loan_amount.get(),
+        quote.staking_value_required,
         sol_market_price.floor().to_u64().ok_or(math_err!())?,
         LoanType::LucraBacked,
     )?;
 
-    user_staking_account.add_locked_total(staking_value_required.floor().to_u64().ok_or(math_err!())?);
+    user_staking_account.add_locked_total(quote.staking_value_required);
 
-    Ok(())
+    if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+        // No origination fee is charged yet, recording still rolls the ledger into the current epoch
+        revenue_ledger.record_origination_fees(clock.unix_timestamp, system_state.epoch, 0);
+    }
+
+    emit_event(
+        program_id,
+        event_queue_ai,
+        system_state_ai.key,
+        EventKind::CreateMataLoan,
+        loan_ai.key,
+        [lamports, quote.loan_amount.get()],
+        clock.slot,
+    )
+}
+
+#[inline(never)]
+fn create_lp_backed_mata_loan(
+    program_id: &Pubkey,
+    lp_amount: u64,
+    accounts: &[AccountInfo],
+    event_queue_ai: Option<&AccountInfo>,
+) -> LucraResult {
+    const NUM_FIXED: usize = CREATE_LP_BACKED_MATA_LOAN_SIZE;
+    let revenue_ledger_ai = accounts.get(NUM_FIXED);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,                // write
+        sol_mata_pool_ai,               // read
+        loan_ai,                        // write
+        lp_vault_ai,                    // write
+        mata_mint_ai,                   // write
+        mata_mint_authority_ai,         // write
+
+        user_account_ai,                // write
+        user_mata_account_ai,           // write
+        user_lp_account_ai,             // write
+
+        sol_usdc_oracle_ai,             // read
+        sol_usdt_oracle_ai,             // read
+        sol_mata_oracle_ai,             // read
+
+        pool_sol_vault_ai,              // read
+        pool_mata_vault_ai,             // read
+        lp_mint_ai,                     // read
+        token_program_ai,               // read
+        system_program_ai,              // read
+        loan_registry_ai,               // write
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+    let rent = &Rent::get()?;
+
+    // Verify Signers
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    // Verify accounts are owned by the right programs
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_mata_account_ai, token_program_ai)?;
+    verify_user_token_account(user_lp_account_ai, token_program_ai)?;
+    check_eq!(lp_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    verify_token_program(token_program_ai)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    check_lp_loans_enabled(&system_state)?;
+    verify_lp_vault(&system_state, lp_vault_ai.key)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    check!(user_lp_account_ai.key != lp_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let mata_market_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    if system_state.peg_check_enabled {
+        update_peg(&mut system_state, mata_market_price);
+        check_peg_holds(&system_state)?;
+    }
+
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let lp_dollar_value = get_lp_collateral_value(
+        &system_state,
+        sol_mata_pool_ai,
+        pool_sol_vault_ai,
+        pool_mata_vault_ai,
+        lp_mint_ai,
+        lp_amount,
+        sol_market_price,
+        mata_market_price,
+    )?;
+    let loan_amount = calc_loan_amount(lp_dollar_value, system_state.lp_collateral_requirement)?;
+    verify_minimum_loan_amount(loan_amount, system_state.min_loan_amount_mata)?;
+
+    system_state.mata_supply.mint(MataBucket::Loan, loan_amount.get())?;
+    check!(Mata(system_state.mata_supply.total()?) <= system_state.maximum_outstanding_mata, LucraErrorCode::InvalidAmount)?;
+
+    let loan_index = create_loan_account(program_id, user_account_ai, loan_ai, loan_registry_ai, system_program_ai)?;
+
+    // Verify Loan account is created but not initialized
+    check!(
+        rent.is_exempt(loan_ai.lamports(), size_of::<MataLoan>()),
+        LucraErrorCode::NotRentExempt
+    )?;
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut(loan_ai)?;
+    check!(!loan.meta_data.is_initialized, LucraErrorCode::Default)?;
+
+    let user_mata_account = Account::unpack(&user_mata_account_ai.data.borrow())?;
+    check_eq!(user_mata_account.mint, system_state.mata_mint.address, LucraErrorCode::InvalidAccountInput)?;
+
+    spl_token_transfer(
+        user_lp_account_ai,
+        lp_vault_ai,
+        lp_amount,
+        user_account_ai,
+        &[],
+        token_program_ai
+    )?;
+
+    system_state.mint_mata(
+        program_id,
+        mata_mint_ai,
+        user_mata_account_ai,
+        loan_amount.get(),
+        mata_mint_authority_ai,
+        token_program_ai,
+    )?;
+
+    loan.meta_data = MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true);
+    loan.repaid = false;
+    loan.loan_type = LoanType::LpBacked;
+    loan.owner = *user_account_ai.key;
+    loan.creator = *user_account_ai.key;
+    loan.loan_index = loan_index;
+    loan.collateral_rate = system_state.lp_collateral_requirement.get();
+    loan.sol_collateral_amount = 0;
+    loan.msol_collateral_amount = 0;
+    loan.staking_collateral_amount = 0;
+    loan.lp_collateral_amount = lp_amount;
+    // What the penalty is charged on, LP backed loans have no SOL collateral
+    loan.lp_sol_equivalent = calc_lamports_for_value(lp_dollar_value, sol_market_price)?;
+    open_collateral_history(&mut loan, lp_amount);
+    loan.market_price = sol_market_price.floor().to_u64().ok_or(math_err!())?;
+    loan.loan_amount = loan_amount.get();
+    loan.penalty_harvested = 0;
+    loan.penalty_to_harvest = 0;
+    loan.loan_mint = *mata_mint_ai.key;
+    loan.loan_creation_date = clock.unix_timestamp;
+    loan.last_day_penalty_was_checked = clock.unix_timestamp;
+
+    if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+        // No origination fee is charged yet, recording still rolls the ledger into the current epoch
+        revenue_ledger.record_origination_fees(clock.unix_timestamp, system_state.epoch, 0);
+    }
+
+    emit_event(
+        program_id,
+        event_queue_ai,
+        system_state_ai.key,
+        EventKind::CreateMataLoan,
+        loan_ai.key,
+        [lp_amount, loan_amount.get()],
+        clock.slot,
+    )
+}
+
+#[inline(never)]
+fn create_native_sol_mata_loan(
+    program_id: &Pubkey,
+    lamports: u64,
+    accounts: &[AccountInfo],
+    event_queue_ai: Option<&AccountInfo>,
+) -> LucraResult {
+    const NUM_FIXED: usize = CREATE_NATIVE_SOL_MATA_LOAN_SIZE;
+    let trailing_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
+    let revenue_ledger_ai = trailing_accounts.first().filter(|ai| !is_sol_mata_amm(ai.key));
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,                // write
+        sol_vault_ai,                   // write
+        loan_ai,                        // write
+        mata_mint_ai,                   // write
+        mata_mint_authority_ai,         // read
+
+        user_account_ai,                // write
+        user_mata_account_ai,           // write
+
+        sol_usdc_oracle_ai,             // read
+        sol_usdt_oracle_ai,             // read
+        sol_mata_oracle_ai,             // read
+
+        system_program_ai,              // read
+        token_program_ai,               // read
+        loan_registry_ai,               // write
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+    let rent = &Rent::get()?;
+
+    // Verify Signers
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    // Verify accounts are owned by the right programs
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_vault_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_mata_account_ai, token_program_ai)?;
+    verify_token_program(token_program_ai)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    check_native_loans_enabled(&system_state)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+
+    if system_state.peg_check_enabled {
+        check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+
+        let mata_market_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+        update_peg(&mut system_state, mata_market_price);
+        check_peg_holds(&system_state)?;
+    }
+
+    // The collateral is SOL itself, there's no msol rate to value it at
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let quote = quote_mata_loan(lamports, sol_market_price, Decimal::ONE, system_state.collateral_requirement, None)?;
+    verify_minimum_loan_amount(quote.loan_amount, system_state.min_loan_amount_mata)?;
+
+    check!(Lamports(lamports) > system_state.min_deposit, LucraErrorCode::InvalidAmount)?;
+    verify_account_will_still_have_lamports(user_account_ai.lamports(), lamports, FEE_BUFFER_LAMPORTS)?;
+
+    system_state.mata_supply.mint(MataBucket::Loan, quote.loan_amount.get())?;
+    check!(Mata(system_state.mata_supply.total()?) <= system_state.maximum_outstanding_mata, LucraErrorCode::InvalidAmount)?;
+
+    let loan_index = create_loan_account(program_id, user_account_ai, loan_ai, loan_registry_ai, system_program_ai)?;
+
+    // Verify Loan account is created but not initialized
+    check!(
+        rent.is_exempt(loan_ai.lamports(), size_of::<MataLoan>()),
+        LucraErrorCode::NotRentExempt
+    )?;
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut(loan_ai)?;
+    check!(!loan.meta_data.is_initialized, LucraErrorCode::Default)?;
+
+    let user_mata_account = Account::unpack(&user_mata_account_ai.data.borrow())?;
+    check_eq!(user_mata_account.mint, system_state.mata_mint.address, LucraErrorCode::InvalidAccountInput)?;
+
+    deposit_to_sol_vault(&system_state, user_account_ai, sol_vault_ai, lamports, system_program_ai)?;
+
+    system_state.mint_mata(
+        program_id,
+        mata_mint_ai,
+        user_mata_account_ai,
+        quote.loan_amount.get(),
+        mata_mint_authority_ai,
+        token_program_ai,
+    )?;
+
+    loan.meta_data = MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true);
+    loan.repaid = false;
+    loan.loan_type = LoanType::NativeSol;
+    loan.owner = *user_account_ai.key;
+    loan.creator = *user_account_ai.key;
+    loan.loan_index = loan_index;
+    loan.collateral_rate = system_state.collateral_requirement.get();
+    // The lamports sit in the sol vault as they are, there's no msol share
+    loan.sol_collateral_amount = lamports;
+    open_collateral_history(&mut loan, lamports);
+    loan.msol_collateral_amount = 0;
+    loan.staking_collateral_amount = 0;
+    loan.lp_collateral_amount = 0;
+    loan.market_price = sol_market_price.floor().to_u64().ok_or(math_err!())?;
+    loan.loan_amount = quote.loan_amount.get();
+    loan.penalty_harvested = 0;
+    loan.penalty_to_harvest = 0;
+    loan.loan_mint = *mata_mint_ai.key;
+    loan.loan_creation_date = clock.unix_timestamp;
+    loan.last_day_penalty_was_checked = clock.unix_timestamp;
+
+    if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+        // No origination fee is charged yet, recording still rolls the ledger into the current epoch
+        revenue_ledger.record_origination_fees(clock.unix_timestamp, system_state.epoch, 0);
+    }
+
+    emit_event(
+        program_id,
+        event_queue_ai,
+        system_state_ai.key,
+        EventKind::CreateMataLoan,
+        loan_ai.key,
+        [lamports, quote.loan_amount.get()],
+        clock.slot,
+    )
+}
+
+/// A broken peg fails the whole instruction rather than opening nothing. Everything the caller passed in is left
+/// as it was: no mata is minted, the collateral stays with them and their stake isn't locked.
+fn check_peg_holds(system_state: &SystemState) -> LucraResult {
+    check!(!(system_state.peg_check_enabled && system_state.peg_broken), LucraErrorCode::BrokenPeg)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -303,8 +672,8 @@ fn create_loan<'a>(
     user_mata_account_ai: &AccountInfo<'a>,
     user_msol_account_ai: &AccountInfo<'a>,
     loan_ai: &AccountInfo<'a>,
+    loan_registry_ai: &AccountInfo<'a>,
 
-    fees_ai: &AccountInfo<'a>,
     system_program_ai: &AccountInfo<'a>,
     token_program_ai: &AccountInfo<'a>,
     
@@ -314,40 +683,34 @@ fn create_loan<'a>(
     sol_market_price: u64,
     loan_type: LoanType,
 ) -> LucraResult {
-    if !(system_state.peg_check_enabled && system_state.peg_broken) {
-        _create_loan(
-            program_id,
-            system_state,
-            msol_vault_ai,
-            mata_mint_ai,
-            mata_mint_authority_ai,
-            marinade_program_ai,
-            marinade_state_ai,
-            msol_mint_ai,
-            liq_pool_sol_leg_pda_ai,
-            liq_pool_msol_leg_ai,
-            liq_pool_msol_leg_authority_ai,
-            reserve_pda_ai,
-            msol_mint_authority_ai,
-            user_account_ai,
-            user_mata_account_ai,
-            user_msol_account_ai,
-            loan_ai,
-            fees_ai,
-            system_program_ai,
-            token_program_ai,
-            lamports,
-            loan_amount,
-            staking_collateral_amount,
-            sol_market_price,
-            loan_type,
-        )?;    
-    } else {
-        // No data was initialized so there is nothing that can be done if an attacker opens the account
-        // back up after we close it.
-        let lamports = close_account(loan_ai);
-        add_lamports(user_account_ai, lamports);
-    }
+    let loan_index = create_loan_account(program_id, user_account_ai, loan_ai, loan_registry_ai, system_program_ai)?;
+    _create_loan(
+        program_id,
+        system_state,
+        msol_vault_ai,
+        mata_mint_ai,
+        mata_mint_authority_ai,
+        marinade_program_ai,
+        marinade_state_ai,
+        msol_mint_ai,
+        liq_pool_sol_leg_pda_ai,
+        liq_pool_msol_leg_ai,
+        liq_pool_msol_leg_authority_ai,
+        reserve_pda_ai,
+        msol_mint_authority_ai,
+        user_account_ai,
+        user_mata_account_ai,
+        user_msol_account_ai,
+        loan_ai,
+        system_program_ai,
+        token_program_ai,
+        lamports,
+        loan_amount,
+        staking_collateral_amount,
+        sol_market_price,
+        loan_type,
+        loan_index,
+    )?;
 
     Ok(())
 }
@@ -375,7 +738,6 @@ pub fn _create_loan<'a>(
     user_msol_account_ai: &AccountInfo<'a>,
     loan_ai: &AccountInfo<'a>,
 
-    fees_ai: &AccountInfo<'a>,
     system_program_ai: &AccountInfo<'a>,
     token_program_ai: &AccountInfo<'a>,
     
@@ -384,11 +746,13 @@ pub fn _create_loan<'a>(
     staking_collateral_amount: u64,
     sol_market_price: u64,
     loan_type: LoanType,
+    loan_index: u64,
 ) -> LucraResult {
     let clock = &Clock::get()?;
     let rent = &Rent::get()?;
 
-    system_state.add_outstanding_mata(loan_amount)?;
+    system_state.mata_supply.mint(MataBucket::Loan, loan_amount)?;
+    check!(Mata(system_state.mata_supply.total()?) <= system_state.maximum_outstanding_mata, LucraErrorCode::InvalidAmount)?;
 
     // Verify Loan account is created but not initialized
     check!(
@@ -401,31 +765,33 @@ pub fn _create_loan<'a>(
     let user_mata_account = Account::unpack(&user_mata_account_ai.data.borrow())?;
     check_eq!(user_mata_account.mint, system_state.mata_mint.address, LucraErrorCode::InvalidAccountInput)?;
 
-    verify_account_will_still_have_lamports(fees_ai, user_account_ai.lamports(), lamports)?;    
-    check!(lamports > system_state.min_deposit, LucraErrorCode::InvalidAmount)?;
-    check!(user_account_ai.key != msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.mata_mint.address, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.msol_vault.address, msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    verify_account_will_still_have_lamports(user_account_ai.lamports(), lamports, FEE_BUFFER_LAMPORTS)?;
+    check!(Lamports(lamports) > system_state.min_deposit, LucraErrorCode::InvalidAmount)?;
+    verify_loan_accounts_distinct(msol_vault_ai.key, user_account_ai.key, user_msol_account_ai.key, user_mata_account_ai.key)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
     
-    let msol_balance_before = get_token_balance(user_msol_account_ai)?;
-    deposit(
-        marinade_state_ai,
-        msol_mint_ai,
-        liq_pool_sol_leg_pda_ai,
-        liq_pool_msol_leg_ai,
-        liq_pool_msol_leg_authority_ai,
-        reserve_pda_ai,
-        user_account_ai,
-        user_msol_account_ai,
-        msol_mint_authority_ai,
-        &[],
-        system_program_ai,
-        token_program_ai,
-        marinade_program_ai,
-        lamports,
-    )?;
-    let msol_balance_after = get_token_balance(user_msol_account_ai)?;
-    let msol_received = msol_balance_after - msol_balance_before;
+    let msol_received = measure_balance_change(user_msol_account_ai, || {
+        deposit(
+            marinade_state_ai,
+            msol_mint_ai,
+            liq_pool_sol_leg_pda_ai,
+            liq_pool_msol_leg_ai,
+            liq_pool_msol_leg_authority_ai,
+            reserve_pda_ai,
+            user_account_ai,
+            user_msol_account_ai,
+            msol_mint_authority_ai,
+            &[],
+            system_program_ai,
+            token_program_ai,
+            marinade_program_ai,
+            lamports,
+        )?;
+        Ok(())
+    })?;
+    // A loan is never minted against a deposit that came back empty
+    check!(msol_received > 0, LucraErrorCode::NothingReceived)?;
 
     spl_token_transfer(
         user_msol_account_ai,
@@ -445,12 +811,18 @@ pub fn _create_loan<'a>(
         token_program_ai,
     )?;
 
-    loan.meta_data = MetaData::new(DataType::Loan, 0, true);
+    loan.meta_data = MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true);
     loan.repaid = false;
     loan.loan_type = loan_type;
     loan.owner = *user_account_ai.key;
-    loan.collateral_rate = system_state.collateral_requirement;
+    loan.creator = *user_account_ai.key;
+    loan.loan_index = loan_index;
+    loan.collateral_rate = system_state.collateral_requirement.get();
     loan.sol_collateral_amount = lamports;
+    open_collateral_history(&mut loan, lamports);
+    loan.collateral_type = MSOL_COLLATERAL_TYPE;
+    // The loan's own share of the shared msol vault, harvests never take more than this
+    loan.msol_collateral_amount = msol_received;
     loan.staking_collateral_amount = staking_collateral_amount;
     loan.market_price = sol_market_price;
     loan.loan_amount = loan_amount;
@@ -465,59 +837,57 @@ pub fn _create_loan<'a>(
     Ok(())
 }
 
-#[inline(never)]
-fn get_required_stake_value(lcp: u8, lamports: u64, sol_price: Decimal) -> LucraResult<Decimal> {
-    let lcp = Decimal::new(lcp.into(), 2);
-    Decimal::from(lamports)
-        .checked_mul(lcp)
-        .ok_or(math_err!())?
-        .checked_mul(sol_price)
-        .ok_or(math_err!())?
-        .checked_div(LAMPORTS_PER_SOL.into())
-        .ok_or(math_err!())
-}
-
-// Supplied_collateral is in dollars
-#[inline(never)]
-fn get_loan_amount(supplied_collateral: Decimal, collateral_requirement: u32) -> LucraResult<u64> {
-    let collateral_requirement = Decimal::new(collateral_requirement as i64, 2);
-    let loan_amount = supplied_collateral
-        .checked_div(collateral_requirement)
-        .ok_or(math_err!())?
-        .checked_mul(LAMPORTS_PER_MATA)
-        .ok_or(math_err!())?
-        .floor()
-        .to_u64()
-        .ok_or(math_err!())?;
-
-    Ok(loan_amount)
+/// The msol received is measured on the user's msol account, the vault can't stand in for it
+fn verify_loan_accounts_distinct(
+    msol_vault: &Pubkey,
+    user_account: &Pubkey,
+    user_msol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+) -> LucraResult {
+    verify_distinct_accounts(&[
+        ("msol_vault", msol_vault),
+        ("user_account", user_account),
+        ("user_msol_account", user_msol_account),
+        ("user_mata_account", user_mata_account),
+    ])
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::helpers::account::tests::assert_aliases_are_rejected;
 
     #[test]
-    fn test_get_required_stake() {
-        let lcp = 100;
-        let lamports = 10 * LAMPORTS_PER_SOL; // 10 sol
-        let sol_price = Decimal::new(10, 0);
-        
-        let expected = sol_price.checked_mul(lamports.into()).unwrap().checked_div(LAMPORTS_PER_SOL.into()).unwrap();
-        let actual = get_required_stake_value(lcp, lamports, sol_price).unwrap();
-
-        assert_eq!(actual, expected);
-
-        let lcp = 50;
-        let expected = Decimal::from(50);
-        let actual = get_required_stake_value(lcp, lamports, sol_price).unwrap();
-
-        assert_eq!(actual, expected);
+    fn test_loan_accounts_cannot_alias() {
+        assert_aliases_are_rejected(4, |keys| verify_loan_accounts_distinct(&keys[0], &keys[1], &keys[2], &keys[3]));
+    }
 
-        let lcp = 150;
-        let expected = Decimal::from(150);
-        let actual = get_required_stake_value(lcp, lamports, sol_price).unwrap();
+    #[test]
+    fn test_broken_peg_fails_instead_of_opening_nothing() {
+        let mut system_state = SystemState {
+            peg_check_enabled: true,
+            peg_broken: true,
+            ..SystemState::default()
+        };
+
+        // An error reverts the whole transaction, the peg update included, so nothing is left half done
+        assert!(matches!(
+            check_peg_holds(&system_state).unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::BrokenPeg, .. }
+        ));
+
+        system_state.peg_broken = false;
+        assert!(check_peg_holds(&system_state).is_ok());
+    }
 
-        assert_eq!(actual, expected);
+    #[test]
+    fn test_peg_is_ignored_while_the_check_is_off() {
+        let system_state = SystemState {
+            peg_check_enabled: false,
+            peg_broken: true,
+            ..SystemState::default()
+        };
+
+        assert!(check_peg_holds(&system_state).is_ok());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,85 @@
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar},
+};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::solana::create_pda_account,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::{find_penalty_history, penalty_history_seeds},
+    state::{
+        MataLoan,
+        PenaltyHistory,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::CreatePenaltyHistory);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CreatePenaltyHistory { } => {
+            msg!("Instruction: Create Penalty History");
+            process_create_penalty_history(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CREATE_PENALTY_HISTORY_SIZE: usize = idl::CREATE_PENALTY_HISTORY.len();
+
+// Anyone can pay for a loan's penalty history, it only ever holds what DeterminePenalty charged. Days charged
+// before it's created aren't in it.
+#[inline(never)]
+pub fn process_create_penalty_history(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = CREATE_PENALTY_HISTORY_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        loan_ai,                // read
+        penalty_history_ai,     // write
+        payer_ai,               // write
+        system_program_ai,      // read
+    ] = accounts;
+
+    check_eq!(payer_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let loan: Ref<MataLoan> = MataLoan::load_checked(loan_ai, program_id)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+
+    let (penalty_history, bump_seed) = find_penalty_history(loan_ai.key);
+    check_eq!(penalty_history_ai.key, &penalty_history, LucraErrorCode::InvalidAccountInput)?;
+    check!(penalty_history_ai.data_is_empty(), LucraErrorCode::AlreadyProcessed)?;
+
+    let rent = Rent::get()?;
+    create_pda_account(
+        payer_ai,
+        penalty_history_ai,
+        rent.minimum_balance(size_of::<PenaltyHistory>()),
+        size_of::<PenaltyHistory>() as u64,
+        program_id,
+        &[&penalty_history_seeds(loan_ai.key, &[bump_seed])],
+        system_program_ai,
+    )?;
+
+    let mut history: RefMut<PenaltyHistory> = PenaltyHistory::load_mut(penalty_history_ai)?;
+    history.init(loan_ai.key);
+
+    Ok(())
+}
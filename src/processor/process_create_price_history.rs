@@ -5,6 +5,7 @@ use arrayref::array_ref;
 use legends_loadable_trait::Loadable;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     pubkey::Pubkey,
     sysvar::{rent::Rent, Sysvar, clock::Clock},
 };
@@ -16,6 +17,7 @@ use crate::{
         SourceFileId,
     },
     helpers::constants::{CREATOR_AUTHORITY, PRICE_HISTORY_ID},
+    instruction::Instruction,
     state::{
         DataType,
         MetaData,
@@ -26,6 +28,17 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::CreatePriceHistory);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CreatePriceHistory { } => {
+            msg!("Instruction: Create Price History");
+            process_create_price_history(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 // We only support tracking the lucra/sol price and sol/usd price.
 #[inline(never)]
 pub fn process_create_price_history(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
@@ -56,10 +69,12 @@ pub fn process_create_price_history(program_id: &Pubkey, accounts: &[AccountInfo
         date: 0,
         sol_price: 0,
         sol_decimals: 0,
-        padding: [0; 6],
+        mata_deviation_bps: 0,
+        mata_deviation_seconds: 0,
     }; 30];
     price_history.last_update_timestamp = 0;
     price_history.update_counter = 0;
+    price_history.samples_today = 0;
     price_history.interval_start = clock.unix_timestamp;
 
     Ok(())
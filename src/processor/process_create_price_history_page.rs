@@ -0,0 +1,122 @@
+use std::{cell::RefMut, mem::size_of};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar},
+};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::PRICE_HISTORY_ID,
+    helpers::price_history::ordered_prices,
+    helpers::solana::create_pda_account,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::{
+        find_price_history_header,
+        find_price_history_page,
+        price_history_header_seeds,
+        price_history_page_seeds,
+    },
+    state::{
+        PriceHistory,
+        PriceHistoryHeader,
+        PriceHistoryPage,
+        PAGED_PRICE_HISTORY_VERSION,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::CreatePriceHistoryPage);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CreatePriceHistoryPage {} => {
+            msg!("Instruction: Create Price History Page");
+            process_create_price_history_page(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CREATE_PRICE_HISTORY_PAGE_SIZE: usize = idl::CREATE_PRICE_HISTORY_PAGE.len();
+
+// Anyone can pay for the next page, it only ever holds days UpdatePriceHistory recorded. The first page also
+// creates the header and migrates the price history: the days it has are archived to the page and its
+// version says UpdatePriceHistory has to archive every day from then on.
+#[inline(never)]
+pub fn process_create_price_history_page(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = CREATE_PRICE_HISTORY_PAGE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        price_history_ai,       // write
+        header_ai,              // write
+        page_ai,                // write
+        payer_ai,               // write
+        system_program_ai,      // read
+    ] = accounts;
+
+    check_eq!(payer_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(price_history_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(price_history_ai.key, &PRICE_HISTORY_ID, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let (header, header_bump_seed) = find_price_history_header(price_history_ai.key);
+    check_eq!(header_ai.key, &header, LucraErrorCode::InvalidAccountInput)?;
+
+    let rent = Rent::get()?;
+    if header_ai.data_is_empty() {
+        create_pda_account(
+            payer_ai,
+            header_ai,
+            rent.minimum_balance(size_of::<PriceHistoryHeader>()),
+            size_of::<PriceHistoryHeader>() as u64,
+            program_id,
+            &[&price_history_header_seeds(price_history_ai.key, &[header_bump_seed])],
+            system_program_ai,
+        )?;
+        PriceHistoryHeader::load_mut(header_ai)?.init(price_history_ai.key);
+    }
+
+    let mut header: RefMut<PriceHistoryHeader> = PriceHistoryHeader::load_mut_checked(header_ai, program_id)?;
+    check_eq!(&header.price_history, price_history_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    // Pages are created in order, the index is the header's page count
+    let page_index = header.page_count;
+    let (page, page_bump_seed) = find_price_history_page(price_history_ai.key, page_index);
+    check_eq!(page_ai.key, &page, LucraErrorCode::InvalidAccountInput)?;
+    check!(page_ai.data_is_empty(), LucraErrorCode::AlreadyProcessed)?;
+
+    create_pda_account(
+        payer_ai,
+        page_ai,
+        rent.minimum_balance(size_of::<PriceHistoryPage>()),
+        size_of::<PriceHistoryPage>() as u64,
+        program_id,
+        &[&price_history_page_seeds(price_history_ai.key, &page_index.to_le_bytes(), &[page_bump_seed])],
+        system_program_ai,
+    )?;
+
+    let mut page: RefMut<PriceHistoryPage> = PriceHistoryPage::load_mut(page_ai)?;
+    page.init(header.record_page()?);
+
+    if page_index == 0 {
+        let mut price_history: Box<RefMut<PriceHistory>> = PriceHistory::load_mut_checked(price_history_ai, program_id)?;
+        check!(price_history.meta_data.version < PAGED_PRICE_HISTORY_VERSION, LucraErrorCode::AlreadyProcessed)?;
+
+        for price in ordered_prices(&price_history) {
+            header.archive(&mut [&mut *page], &price)?;
+        }
+        price_history.meta_data.version = PAGED_PRICE_HISTORY_VERSION;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::cell::RefMut;
+use std::mem::size_of;
+
+use arrayref::array_ref;
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar, clock::Clock},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::CREATOR_AUTHORITY,
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
+    state::{
+        RevenueLedger,
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::CreateRevenueLedger);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CreateRevenueLedger {} => {
+            msg!("Instruction: Create Revenue Ledger");
+            process_create_revenue_ledger(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// One time instruction. The ledger is pinned to the system state so only one can ever be written to.
+#[inline(never)]
+pub fn process_create_revenue_ledger(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 3;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,                    // write
+        revenue_ledger_ai,                  // write
+        creator_authority_ai,               // read
+    ] = accounts;
+
+    let rent = &Rent::get()?;
+    let clock = &Clock::get()?;
+
+    check_eq!(creator_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(creator_authority_ai.key, &CREATOR_AUTHORITY, LucraErrorCode::InvalidAccountInput)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(revenue_ledger_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check_eq!(system_state.revenue_ledger, Pubkey::default(), LucraErrorCode::AlreadyProcessed)?;
+
+    check!(rent.is_exempt(revenue_ledger_ai.lamports(), size_of::<RevenueLedger>()), LucraErrorCode::NotRentExempt)?;
+    let mut revenue_ledger: RefMut<RevenueLedger> = RevenueLedger::load_mut(revenue_ledger_ai)?;
+    check!(!revenue_ledger.meta_data.is_initialized, LucraErrorCode::Default)?;
+
+    revenue_ledger.init(system_state_ai.key, clock.unix_timestamp);
+    system_state.revenue_ledger = *revenue_ledger_ai.key;
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::CREATOR_AUTHORITY,
+    helpers::settlement::check_not_settled,
+    helpers::sol_vault::find_sol_vault,
+    helpers::solana::create_pda_account,
+    idl,
+    instruction::Instruction,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::CreateSolVault);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CreateSolVault {} => {
+            msg!("Instruction: Create Sol Vault");
+            process_create_sol_vault(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const CREATE_SOL_VAULT_SIZE: usize = idl::CREATE_SOL_VAULT.len();
+
+// One time instruction. The vault is a PDA of the system state, until it exists native SOL loans can't be opened.
+#[inline(never)]
+pub fn process_create_sol_vault(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = CREATE_SOL_VAULT_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // write
+        sol_vault_ai,           // write
+        creator_authority_ai,   // write
+        system_program_ai,      // read
+    ] = accounts;
+
+    check_eq!(creator_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(creator_authority_ai.key, &CREATOR_AUTHORITY, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check_eq!(system_state.sol_vault.address, Pubkey::default(), LucraErrorCode::AlreadyProcessed)?;
+
+    let (sol_vault, bump_seed) = find_sol_vault(system_state_ai.key);
+    check_eq!(sol_vault_ai.key, &sol_vault, LucraErrorCode::InvalidAccountInput)?;
+
+    // No data, the rent exempt minimum stays in the vault for good and the collateral is on top of it
+    let rent = Rent::get()?;
+    create_pda_account(
+        creator_authority_ai,
+        sol_vault_ai,
+        rent.minimum_balance(0),
+        0,
+        program_id,
+        &[&sol_vault_seeds(system_state_ai.key, &[bump_seed])],
+        system_program_ai,
+    )?;
+
+    system_state.sol_vault.address = *sol_vault_ai.key;
+    system_state.sol_vault.authority_bump_seed = bump_seed;
+
+    Ok(())
+}
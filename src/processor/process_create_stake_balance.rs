@@ -1,10 +1,13 @@
 use std::cell::{Ref, RefMut};
 use std::mem::size_of;
+use std::convert::TryFrom;
 
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     sysvar::{rent::Rent, Sysvar},
+    program_error::ProgramError,
     program_pack::Pack,
     pubkey::Pubkey,
 };
@@ -18,11 +21,18 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
+    helpers::account::verify_distinct_accounts,
+    helpers::account_layout::RESERVED_LAYOUT_VERSION,
+    helpers::reward_index::start_reward_index,
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
+    lucra_core::pda::stake_balance_authority_seeds,
     state::{
         DataType,
         MetaData,
         staking::{
             StakeBalance,
+            StakingAccount,
             StakingTimeframe,
             StakingState,
         },
@@ -32,9 +42,24 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::CreateStakeBalance);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CreateStakeBalance {
+            nonce,
+            staking_timeframe,
+        } => {
+            msg!("Instruction: Create Stake Balance");
+            let staking_timeframe = StakingTimeframe::try_from(staking_timeframe).map_err(|_| ProgramError::InvalidInstructionData)?;
+            process_create_stake_balance(program_id, nonce, staking_timeframe, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[inline(never)]
 pub fn process_create_stake_balance(program_id: &Pubkey, nonce: u8, staking_timeframe: StakingTimeframe, accounts: &[AccountInfo]) -> LucraResult {    
-    const NUM_FIXED: usize = 7;
+    const NUM_FIXED: usize = 8;
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,                // read
@@ -44,6 +69,7 @@ pub fn process_create_stake_balance(program_id: &Pubkey, nonce: u8, staking_time
         deposit_vault_ai,               // read
         stake_vault_ai,                 // read
         pending_vault_ai,               // read
+        staking_account_ai,             // read
     ] = accounts;
 
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
@@ -53,18 +79,18 @@ pub fn process_create_stake_balance(program_id: &Pubkey, nonce: u8, staking_time
     check_eq!(deposit_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(stake_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
 
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
     check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
     check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
     let staking_state: Ref<StakingState> = StakingState::load_checked(staking_state_ai, program_id)?;
+    let staking_account: Ref<StakingAccount> = StakingAccount::load_checked(staking_account_ai, program_id)?;
+    check_eq!(&staking_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
     let mut stake_balance: RefMut<StakeBalance> = StakeBalance::load_mut(stake_balance_ai)?;
     check!(!stake_balance.meta_data.is_initialized, LucraErrorCode::Default)?;
 
-    let deposit_vault = Account::unpack(&deposit_vault_ai.data.borrow())?;
-    let stake_vault = Account::unpack(&stake_vault_ai.data.borrow())?;
-    let pending_vault = Account::unpack(&pending_vault_ai.data.borrow())?;
-    
     let rent = &Rent::get()?;
 
     check!(
@@ -72,23 +98,21 @@ pub fn process_create_stake_balance(program_id: &Pubkey, nonce: u8, staking_time
         LucraErrorCode::NotRentExempt
     )?;
 
-    let authority_signer_seeds = &[
-        owner_ai.key.as_ref(),
-        system_state_ai.key.as_ref(),
-        &[nonce],
-    ];
-    let vault_owner_pda = Pubkey::create_program_address(authority_signer_seeds, program_id).map_err(|_| throw_err!(LucraErrorCode::InvalidNonce))?;
-    check_eq!(&deposit_vault.owner, &vault_owner_pda, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(&stake_vault.owner, &vault_owner_pda, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(&pending_vault.owner, &vault_owner_pda, LucraErrorCode::InvalidAccountOwner)?;
-
-    check_eq!(&deposit_vault.mint, &system_state.lucra_mint.address, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&stake_vault.mint, &system_state.lucra_mint.address, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&pending_vault.mint, &system_state.lucra_mint.address, LucraErrorCode::InvalidAccountInput)?;
-   
-    stake_balance.meta_data = MetaData::new(DataType::StakeBalance, 0, true);
+    let vault_owner_pda = Pubkey::create_program_address(&stake_balance_authority_seeds(owner_ai.key, system_state_ai.key, &[nonce]), program_id).map_err(|_| throw_err!(LucraErrorCode::InvalidNonce))?;
+    verify_distinct_accounts(&[
+        ("deposit vault", deposit_vault_ai.key),
+        ("stake vault", stake_vault_ai.key),
+        ("pending vault", pending_vault_ai.key),
+    ])?;
+    verify_stake_balance_vault(deposit_vault_ai, &vault_owner_pda, &system_state.lucra_mint.address)?;
+    verify_stake_balance_vault(stake_vault_ai, &vault_owner_pda, &system_state.lucra_mint.address)?;
+    verify_stake_balance_vault(pending_vault_ai, &vault_owner_pda, &system_state.lucra_mint.address)?;
+
+    stake_balance.meta_data = MetaData::new(DataType::StakeBalance, RESERVED_LAYOUT_VERSION, true);
     stake_balance.owner = *owner_ai.key;
+    stake_balance.staking_account = *staking_account_ai.key;
     stake_balance.reward_cursor = staking_state.reward_cursor;
+    start_reward_index(&mut stake_balance, &staking_state);
     stake_balance.staking_timeframe = staking_timeframe;
     stake_balance.balances.deposit_vault = *deposit_vault_ai.key;
     stake_balance.balances.stake_vault = *stake_vault_ai.key;
@@ -97,4 +121,83 @@ pub fn process_create_stake_balance(program_id: &Pubkey, nonce: u8, staking_time
     stake_balance.closed = false;
 
     Ok(())
+}
+
+// The balance only ever moves tokens through vaults its transfer authority owns outright. A delegate or close
+// authority would let someone else take them back out, and a vault that already holds tokens would be counted
+// as the owner's stake.
+fn verify_stake_balance_vault(vault_ai: &AccountInfo, vault_owner_pda: &Pubkey, lucra_mint: &Pubkey) -> LucraResult {
+    let vault = Account::unpack(&vault_ai.data.borrow())?;
+    check_eq!(&vault.mint, lucra_mint, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&vault.owner, vault_owner_pda, LucraErrorCode::InvalidAccountInput)?;
+    check!(vault.delegate.is_none(), LucraErrorCode::InvalidAccountInput)?;
+    check!(vault.close_authority.is_none(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(vault.amount, 0, LucraErrorCode::InvalidAccountInput)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::program_option::COption;
+    use spl_token::state::AccountState;
+    use crate::helpers::vaults::tests::assert_invalid_account_input;
+
+    fn empty_vault(mint: Pubkey, owner: Pubkey) -> Account {
+        Account {
+            mint,
+            owner,
+            state: AccountState::Initialized,
+            ..Account::default()
+        }
+    }
+
+    fn verify(vault: Account, vault_owner_pda: &Pubkey, lucra_mint: &Pubkey) -> LucraResult {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vec![0; Account::LEN];
+        vault.pack_into_slice(&mut data);
+        let vault_ai = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &spl_token::ID, false, 0);
+        verify_stake_balance_vault(&vault_ai, vault_owner_pda, lucra_mint)
+    }
+
+    #[test]
+    fn test_empty_vault_of_the_authority_is_accepted() {
+        let (vault_owner_pda, lucra_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(verify(empty_vault(lucra_mint, vault_owner_pda), &vault_owner_pda, &lucra_mint).is_ok());
+    }
+
+    #[test]
+    fn test_vault_of_another_mint_is_rejected() {
+        let (vault_owner_pda, lucra_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_invalid_account_input(verify(empty_vault(Pubkey::new_unique(), vault_owner_pda), &vault_owner_pda, &lucra_mint));
+    }
+
+    #[test]
+    fn test_vault_the_authority_doesnt_own_is_rejected() {
+        let (vault_owner_pda, lucra_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_invalid_account_input(verify(empty_vault(lucra_mint, Pubkey::new_unique()), &vault_owner_pda, &lucra_mint));
+    }
+
+    #[test]
+    fn test_preloaded_vault_is_rejected() {
+        let (vault_owner_pda, lucra_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let vault = Account { amount: 1, ..empty_vault(lucra_mint, vault_owner_pda) };
+        assert_invalid_account_input(verify(vault, &vault_owner_pda, &lucra_mint));
+    }
+
+    #[test]
+    fn test_vault_someone_else_can_move_is_rejected() {
+        let (vault_owner_pda, lucra_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let vault = Account {
+            delegate: COption::Some(Pubkey::new_unique()),
+            delegated_amount: 1,
+            ..empty_vault(lucra_mint, vault_owner_pda)
+        };
+        assert_invalid_account_input(verify(vault, &vault_owner_pda, &lucra_mint));
+
+        let vault = Account { close_authority: COption::Some(Pubkey::new_unique()), ..empty_vault(lucra_mint, vault_owner_pda) };
+        assert_invalid_account_input(verify(vault, &vault_owner_pda, &lucra_mint));
+    }
 }
\ No newline at end of file
@@ -4,6 +4,7 @@ use std::mem::size_of;
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     sysvar::{rent::Rent, Sysvar},
     pubkey::Pubkey,
 };
@@ -15,6 +16,8 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
     state::{
         DataType,
         MetaData,
@@ -25,16 +28,29 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::CreateStakingAccount);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::CreateStakingAccount { } => {
+            msg!("Instruction: Create Staking Account");
+            process_create_staking_account(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[inline(never)]
 pub fn process_create_staking_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
     const NUM_FIXED: usize = 4;
-    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let fixed_accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,                // read
         staking_state_ai,               // read
         staking_account_ai,             // write
         owner_ai,                       // read
-    ] = accounts;
+    ] = fixed_accounts;
+    // Optionally followed by the key allowed to unfreeze the account without waiting
+    let recovery_key = accounts.get(NUM_FIXED).map(|ai| *ai.key).unwrap_or_default();
         
     let rent = &Rent::get()?;
 
@@ -44,6 +60,7 @@ pub fn process_create_staking_account(program_id: &Pubkey, accounts: &[AccountIn
     check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
     check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
     check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
 
@@ -59,6 +76,9 @@ pub fn process_create_staking_account(program_id: &Pubkey, accounts: &[AccountIn
     staking_account.owner = *owner_ai.key;
     staking_account.total = 0;
     staking_account.locked_total = 0;
+    staking_account.frozen = false;
+    staking_account.unfreeze_at = 0;
+    staking_account.recovery_key = recovery_key;
 
     Ok(())
 }
\ No newline at end of file
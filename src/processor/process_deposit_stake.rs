@@ -3,6 +3,7 @@ use std::cell::Ref;
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     program_pack::Pack,
     pubkey::Pubkey,
 };
@@ -15,6 +16,8 @@ use crate::{
         SourceFileId,
     },
     helpers::spl::*,
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
     state::{
         staking::StakeBalance,
         SystemState,
@@ -23,6 +26,19 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::DepositStake);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::DepositStake {
+            lucra,
+        } => {
+            msg!("Instruction: Deposit Stake");
+            process_deposit_stake(program_id, lucra, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[inline(never)]
 pub fn process_deposit_stake(program_id: &Pubkey, lucra: u64, accounts: &[AccountInfo]) -> LucraResult {
     check!(lucra != 0, LucraErrorCode::InvalidAmount)?;
@@ -40,12 +56,13 @@ pub fn process_deposit_stake(program_id: &Pubkey, lucra: u64, accounts: &[Accoun
 
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(stake_balance_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(from_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(from_account_ai, token_program_ai)?;
     check_eq!(deposit_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
 
     check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
     check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
     
     let stake_balance: Ref<StakeBalance> = StakeBalance::load_checked(stake_balance_ai, program_id)?;
@@ -1,46 +1,85 @@
-use std::cell::{Ref, RefMut};
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+};
 
 use arrayref::array_ref;
-use rust_decimal_macros::dec;
 use solana_program::{
     account_info::AccountInfo,
     clock::UnixTimestamp,
+    msg,
     sysvar::{clock::Clock, Sysvar},
     pubkey::Pubkey,
-    native_token::LAMPORTS_PER_SOL,
 };
-use rust_decimal::{Decimal, prelude::ToPrimitive};
-use time::{OffsetDateTime, Time};
+use rust_decimal::Decimal;
 use crate::{
     error::{
         check_assert,
-        LucraError,
         LucraErrorCode,
         LucraResult,
         SourceFileId,
     },
     helpers::constants::{
-        LAMPORTS_PER_LUCRA,
         PRICE_HISTORY_ID,
         SOL_MATA_ORACLE,
         SOL_USDT_ORACLE,
         SOL_USDC_ORACLE,
+        UNIX_DAY,
+    },
+    helpers::collateral::{find_msol_sol_oracle, get_collateral_valuation_factor},
+    helpers::oracle::verify_sol_usd_oracle,
+    helpers::paged_price_history::{check_price_history_covers, collect_paged_days},
+    helpers::peg_band::{penalty_band_width, penalty_multiplier_per_band},
+    helpers::price_history::{first_index_after, price_at},
+    helpers::keepers::{resolve_crank_caller, split_keeper},
+    helpers::reward_tokens::mint_whole_reward,
+    helpers::settlement::check_not_settled,
+    helpers::vaults::verify_reward_mint,
+    instruction::Instruction,
+    lucra_core::interest::{accrues_interest, calc_interest},
+    lucra_core::pda::{find_penalty_history, find_price_history_header},
+    lucra_core::penalty::{
+        calc_day_penalty,
+        calc_remaining_penalty_collateral,
+        calculate_penalty_multiplier,
+        penalized_loan,
+        start_of_day,
     },
-    helpers::math::*,
-    helpers::oracle::*,
     state::{
+        penalty_history::to_recorded_multiplier,
+        HistoricPrice,
+        LoanType,
         MataLoan,
-        PriceHistory, 
+        PenaltyHistory,
+        PenaltyHistoryEntry,
+        PenaltyJournalEntry,
+        PriceHistory,
+        PriceHistoryHeader,
+        PriceHistoryPage,
         SystemState,
     },
+    units::Bps,
 };
 
 declare_check_assert_macros!(SourceFileId::DeterminePenalty);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::DeterminePenalty { } => {
+            msg!("Instruction: Determine Penalty");
+            process_determine_penalty(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 /// Anyone can run this contract in order to determine penalty that needs to be harvested on a loan
 #[inline(never)]
 pub fn process_determine_penalty(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    let (accounts, keeper) = split_keeper(program_id, accounts);
     const NUM_FIXED: usize = 10;
+    let trailing_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,            // read
@@ -48,7 +87,7 @@ pub fn process_determine_penalty(program_id: &Pubkey, accounts: &[AccountInfo])
 
         sol_usdc_oracle_ai,         // read
         sol_usdt_oracle_ai,         // read
-        sol_mata_oracle_ai,         // read
+        sol_mata_oracle_ai,         // read - no longer used, the layout is kept for existing callers
         price_history_ai,           // read
 
         user_reward_account_ai,     // write
@@ -62,41 +101,112 @@ pub fn process_determine_penalty(program_id: &Pubkey, accounts: &[AccountInfo])
     // Verify the accounts are owned by the right programs
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(sol_usdc_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(sol_usdt_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(price_history_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(user_reward_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(reward_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     
     // Verify accounts against expectations
-    check_eq!(sol_usdc_oracle_ai.key, &SOL_USDC_ORACLE, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(sol_usdt_oracle_ai.key, &SOL_USDT_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
     check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(price_history_ai.key, &PRICE_HISTORY_ID, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
  
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
-    check_eq!(&system_state.reward_mint.address, reward_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let keeper_multiplier = resolve_crank_caller(program_id, &system_state, keeper)?;
+    check_not_settled(&system_state)?;
+    verify_reward_mint(&system_state, reward_mint_ai.key)?;
 
     let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
     check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
-    check!(loan.penalty_harvested < loan.sol_collateral_amount, LucraErrorCode::InvalidAmount)?;
-
-    let mata_market_price = get_mata_price(sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-
-    let penalty_multiplier = calculate_penalty_multiplier(mata_market_price)?;
-    let penalty_to_charge = accumulate_penalty_rate_charge(price_history_ai, &loan, penalty_multiplier, program_id)?;
+    // Penalties are charged against SOL collateral, LP backed loans against the SOL their LP tokens were worth
+    let charged_loan = Box::new(penalized_loan(&loan));
+    check!(charged_loan.penalty_harvested < charged_loan.sol_collateral_amount, LucraErrorCode::InvalidAmount)?;
+
+    // The marinade state follows the MSOL/SOL oracle so the market rate can be compared against redemption
+    let msol_sol_oracle_ai = find_msol_sol_oracle(trailing_accounts, &system_state);
+    let marinade_state_ai = msol_sol_oracle_ai
+        .and_then(|oracle_ai| trailing_accounts.iter().position(|ai| ai.key == oracle_ai.key))
+        .and_then(|position| trailing_accounts.get(position + 1));
+    // Native SOL collateral is worth its lamports and LP collateral was valued in them, there's no msol rate to
+    // discount either by
+    let collateral_factor = if matches!(loan.loan_type, LoanType::NativeSol | LoanType::LpBacked) {
+        Decimal::ONE
+    } else {
+        get_collateral_valuation_factor(&system_state, msol_sol_oracle_ai, marinade_state_ai, clock)?
+    };
+
+    // Days the price history has written over are charged from its pages. Without them every unchecked day
+    // has to still be in the price history, they're never skipped.
+    let paged_days = load_paged_days(trailing_accounts, price_history_ai, &loan, clock.unix_timestamp, program_id)?;
+    if paged_days.is_none() {
+        let price_history: Box<Ref<PriceHistory>> = PriceHistory::load_checked(price_history_ai, program_id)?;
+        check_price_history_covers(&price_history, unchecked_day_range(&loan, clock.unix_timestamp).0)?;
+    }
 
+    let penalty_band = PenaltyBand::of(&system_state);
+    let mut charged_days = vec![];
+    let mut history_days = vec![];
+    let on_charge = |history: &HistoricPrice, penalty: u64| {
+        charged_days.push(PenaltyJournalEntry {
+            date: history.date,
+            penalty,
+            sol_price: history.sol_price,
+            lucra_price: history.lucra_price,
+        });
+        history_days.push(PenaltyHistoryEntry {
+            date: history.date,
+            charge: penalty,
+            multiplier: to_recorded_multiplier(calculate_penalty_multiplier(
+                history.mata_deviation_bps,
+                penalty_band.width,
+                penalty_band.multiplier_per_band,
+            )),
+            ..PenaltyHistoryEntry::default()
+        });
+    };
+    let penalty_to_charge = match &paged_days {
+        Some(days) => charge_penalty_for_days(&charged_loan, collateral_factor, penalty_band, on_charge, |on_day| {
+            days.iter().try_for_each(on_day)
+        })?,
+        None => accumulate_penalty_rate_charge(price_history_ai, &charged_loan, collateral_factor, &system_state, program_id, on_charge)?,
+    };
+
+    // Kept so a later correction of one of these days' prices can be recomputed by RecomputePenalty
+    for entry in charged_days {
+        loan.penalty_journal.record(entry);
+    }
+    // Loans that have a penalty history keep a month of charges for audits
+    if !trailing_accounts.is_empty() {
+        let penalty_history = find_penalty_history(loan_ai.key).0;
+        if let Some(penalty_history_ai) = trailing_accounts.iter().find(|ai| ai.key == &penalty_history) {
+            let mut penalty_history: RefMut<PenaltyHistory> = PenaltyHistory::load_mut_checked(penalty_history_ai, program_id)?;
+            check_eq!(&penalty_history.loan, loan_ai.key, LucraErrorCode::InvalidAccountInput)?;
+            penalty_history.record_charges(&history_days, penalty_to_charge);
+        }
+    }
     loan.add_penalty_to_harvest(penalty_to_charge);
+    // Interest is charged over the same days, so it has to be read before the checked day moves
+    if accrues_interest(&loan) {
+        let interest_to_charge = match &paged_days {
+            Some(days) => calc_interest(loan.loan_amount, system_state.annual_interest_bps, days.len() as u64)?,
+            None => accumulate_interest_charge(price_history_ai, &loan, system_state.annual_interest_bps, program_id)?,
+        };
+        loan.interest_accrued = loan.interest_accrued
+            .checked_add(interest_to_charge)
+            .ok_or(math_err!())?;
+    }
     loan.update_last_day_penalty_was_checked(clock.unix_timestamp);
 
     // Pay the user for running the contract
-    system_state.mint_reward(
+    mint_whole_reward(
         program_id, 
+        &system_state, 
         reward_mint_ai, 
         user_reward_account_ai, 
         1, 
+        keeper_multiplier,
         reward_mint_authority_ai, 
         token_program_ai
     )?;
@@ -104,122 +214,229 @@ pub fn process_determine_penalty(program_id: &Pubkey, accounts: &[AccountInfo])
     Ok(())
 }
 
-#[inline(never)]
-fn calculate_penalty_multiplier(mata_price: Decimal) -> LucraResult<u64> {
-    // penalty multiplier is based off of how much the mata price has deviated from the peg
-    // for every 5 cents = 2x
+/// The DAO's penalty steps, copied out of the system state for the days being charged. Unset ones are the defaults.
+#[derive(Clone, Copy)]
+struct PenaltyBand {
+    width: Bps,
+    multiplier_per_band: u32,
+}
 
-    // if mata is above the peg then there is no penalty multiplier
-    if mata_price >= Decimal::from(1_u64) {
-        return Ok(1)
+impl PenaltyBand {
+    fn of(system_state: &SystemState) -> Self {
+        PenaltyBand {
+            width: penalty_band_width(system_state),
+            multiplier_per_band: penalty_multiplier_per_band(system_state),
+        }
     }
+}
+
+/// First and last day the loan can be charged for: from the day after it was created or last checked, up to
+/// yesterday
+fn unchecked_day_range(loan: &MataLoan, timestamp: UnixTimestamp) -> (UnixTimestamp, UnixTimestamp) {
+    let last_skipped_date = std::cmp::max(start_of_day(loan.loan_creation_date), start_of_day(loan.last_day_penalty_was_checked));
+
+    (last_skipped_date + UNIX_DAY, start_of_day(timestamp) - UNIX_DAY)
+}
 
-    let five_cents = Decimal::new(5, 2);
-    let remainder = mata_price
-        .checked_div(five_cents)
-        .ok_or(math_err!())?
-        .floor()
-        .to_u64()
-        .ok_or(math_err!())?;
-    let expected_remainder = 20;
+// With the price history's header in the trailing accounts the unchecked days are read from the pages that
+// follow it, oldest first. None when it isn't passed.
+#[inline(never)]
+fn load_paged_days(
+    trailing_accounts: &[AccountInfo],
+    price_history_ai: &AccountInfo,
+    loan: &MataLoan,
+    timestamp: UnixTimestamp,
+    program_id: &Pubkey,
+) -> LucraResult<Option<Vec<HistoricPrice>>> {
+    if trailing_accounts.is_empty() {
+        return Ok(None);
+    }
+    let header = find_price_history_header(price_history_ai.key).0;
+    let position = match trailing_accounts.iter().position(|ai| ai.key == &header) {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+
+    let header: Ref<PriceHistoryHeader> = PriceHistoryHeader::load_checked(&trailing_accounts[position], program_id)?;
+    check_eq!(&header.price_history, price_history_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let pages = trailing_accounts[position + 1..]
+        .iter()
+        .take_while(|ai| ai.owner == program_id && ai.data_len() == size_of::<PriceHistoryPage>())
+        .map(|ai| PriceHistoryPage::load_checked(ai, program_id))
+        .collect::<LucraResult<Vec<Ref<PriceHistoryPage>>>>()?;
+    let pages: Vec<&PriceHistoryPage> = pages.iter().map(|page| &**page).collect();
 
-    let multiplier = (expected_remainder - remainder) * 2;
+    let price_history: Box<Ref<PriceHistory>> = PriceHistory::load_checked(price_history_ai, program_id)?;
+    let (first_day, last_day) = unchecked_day_range(loan, timestamp);
 
-    Ok(multiplier)
+    collect_paged_days(&header, &pages, &price_history, first_day, last_day).map(Some)
 }
 
 // Will find the penalty owed for days that have passed.
 // Does not update the penalty_to_harvest field.
 #[inline(never)]
-fn accumulate_penalty_rate_charge<'a>(price_history_ai: &AccountInfo<'a>, loan: &RefMut<MataLoan>, penalty_multiplier: u64, program_id: &Pubkey) -> LucraResult<u64> {
+fn accumulate_penalty_rate_charge<'a>(
+    price_history_ai: &AccountInfo<'a>,
+    loan: &MataLoan,
+    collateral_factor: Decimal,
+    system_state: &SystemState,
+    program_id: &Pubkey,
+    on_charge: impl FnMut(&HistoricPrice, u64),
+) -> LucraResult<u64> {
     let price_history: Box<Ref<PriceHistory>> = PriceHistory::load_checked(price_history_ai, program_id)?;
     let clock = &Clock::get()?;
 
-    _accumulate_penalty_rate_charge(&price_history, loan, penalty_multiplier, clock.unix_timestamp)
+    _accumulate_penalty_rate_charge_with(&price_history, loan, collateral_factor, PenaltyBand::of(system_state), clock.unix_timestamp, on_charge)
+}
+
+#[cfg(test)]
+fn _accumulate_penalty_rate_charge(price_history: &Ref<PriceHistory>, loan: &RefMut<MataLoan>, collateral_factor: Decimal, penalty_band: PenaltyBand, timestamp: UnixTimestamp) -> LucraResult<u64> {
+    _accumulate_penalty_rate_charge_with(price_history, loan, collateral_factor, penalty_band, timestamp, |_, _| {})
 }
 
 #[inline(never)]
-fn _accumulate_penalty_rate_charge(price_history: &Ref<PriceHistory>, loan: &RefMut<MataLoan>, penalty_multiplier: u64, timestamp: UnixTimestamp) -> LucraResult<u64> {
-    let time = Time::from_hms(0, 0, 0).unwrap();
-    let today =  OffsetDateTime::from_unix_timestamp(timestamp)
-        .unwrap()
-        .replace_time(time)
-        .unix_timestamp();
-    let date_last_harvested = OffsetDateTime::from_unix_timestamp(loan.last_day_penalty_was_checked)
-        .unwrap()
-        .replace_time(time)
-        .unix_timestamp();
-
-    let mut collateral_value;
-    let mut annual_penalty_rate;
+fn _accumulate_penalty_rate_charge_with(
+    price_history: &Ref<PriceHistory>,
+    loan: &MataLoan,
+    collateral_factor: Decimal,
+    penalty_band: PenaltyBand,
+    timestamp: UnixTimestamp,
+    on_charge: impl FnMut(&HistoricPrice, u64),
+) -> LucraResult<u64> {
+    charge_penalty_for_days(loan, collateral_factor, penalty_band, on_charge, |on_day| {
+        for_each_unchecked_day(price_history, loan, timestamp, on_day)
+    })
+}
+
+/// for_each_day calls back with every day to charge. on_charge is called with every day that was charged
+/// penalty and what it was charged, after the cap, so what it's called with adds up to the penalty returned.
+fn charge_penalty_for_days(
+    loan: &MataLoan,
+    collateral_factor: Decimal,
+    penalty_band: PenaltyBand,
+    mut on_charge: impl FnMut(&HistoricPrice, u64),
+    for_each_day: impl FnOnce(&mut dyn FnMut(&HistoricPrice) -> LucraResult) -> LucraResult,
+) -> LucraResult<u64> {
+    // cap penalty at the collateral the loan has left, including penalty that hasn't been harvested yet
+    let remaining = calc_remaining_penalty_collateral(loan);
     let mut penalty_rate = 0_u64;
-    let one_day = dec!(1).checked_div(356.into()).unwrap();
-    
-    for history in price_history.prices.iter() {
-        // if the price is 0 then the day was invalid. Skip charging any penalty for that day
-        if history.sol_price == 0 || history.lucra_price == 0 {
-            continue;
+    for_each_day(&mut |history| {
+        let penalty = penalty_rate_for_day(history, loan, collateral_factor, penalty_band)?
+            .min(remaining - penalty_rate);
+        if penalty > 0 {
+            on_charge(history, penalty);
         }
+        penalty_rate += penalty;
+        Ok(())
+    })?;
 
-        // If the loan was created after the date for the price we can filter it out that day
-        if history.date < loan.loan_creation_date {
-            continue;
-        }
+    Ok(penalty_rate)
+}
 
-        // Don't process anything for todays date
-        if history.date == today {
-            continue;
-        }
+// Will find the interest owed for days that have passed at the DAO's base rate.
+// Does not update the interest_accrued field.
+#[inline(never)]
+fn accumulate_interest_charge<'a>(
+    price_history_ai: &AccountInfo<'a>,
+    loan: &MataLoan,
+    annual_interest_bps: Bps,
+    program_id: &Pubkey,
+) -> LucraResult<u64> {
+    if annual_interest_bps == Bps(0) {
+        return Ok(0);
+    }
+
+    let price_history: Box<Ref<PriceHistory>> = PriceHistory::load_checked(price_history_ai, program_id)?;
+    let clock = &Clock::get()?;
+
+    _accumulate_interest_charge(&price_history, loan, annual_interest_bps, clock.unix_timestamp)
+}
+
+/// Interest doesn't depend on the day's prices, so a day that was zeroed out still accrues it
+fn _accumulate_interest_charge(price_history: &PriceHistory, loan: &MataLoan, annual_interest_bps: Bps, timestamp: UnixTimestamp) -> LucraResult<u64> {
+    let mut days = 0_u64;
+    for_each_unchecked_day(price_history, loan, timestamp, |_| {
+        days += 1;
+        Ok(())
+    })?;
 
-        // only run on days that haven't been harvested
-        if history.date > date_last_harvested {
-            // Find value of collateral for given day
-            collateral_value = calculate_collateral_value(history.sol_price, history.sol_decimals, loan.sol_collateral_amount, history.lucra_price, history.lucra_decimals, loan.staking_collateral_amount).unwrap();
-            // Find the penalty rate for the collateral
-            annual_penalty_rate = loan.calc_penalty_rate_percentage(collateral_value)?;
-            // Calculate how much penalty to charge
-            penalty_rate += calculate_annual_interest_rate(annual_penalty_rate, loan.sol_collateral_amount, one_day)? * penalty_multiplier;
+    calc_interest(loan.loan_amount, annual_interest_bps, days)
+}
+
+/// Calls on_day with every day of the price history the loan hasn't been charged for yet
+fn for_each_unchecked_day(
+    price_history: &PriceHistory,
+    loan: &MataLoan,
+    timestamp: UnixTimestamp,
+    mut on_day: impl FnMut(&HistoricPrice) -> LucraResult,
+) -> LucraResult {
+    // Every comparison below is between days, the same midnight normalized dates the price history is keyed by
+    let today = start_of_day(timestamp);
+    let date_last_harvested = start_of_day(loan.last_day_penalty_was_checked);
+    let loan_creation_day = start_of_day(loan.loan_creation_date);
+
+    let mut visit = |history: &HistoricPrice| -> LucraResult {
+        if is_unchecked_day(history, today, loan_creation_day, date_last_harvested) {
+            on_day(history)?;
         }
-    }
+        Ok(())
+    };
 
-    // cap penalty at loan sol collateral amount
-    if penalty_rate > loan.sol_collateral_amount {
-        penalty_rate = loan.sol_collateral_amount;
+    if price_history.len == 0 {
+        // Histories that haven't been rewritten as a ring buffer yet have no order to rely on
+        for history in price_history.prices.iter() {
+            visit(history)?;
+        }
+    } else {
+        // Prices are ordered by date so only the days after both the loan creation and the last harvest need checking
+        let last_skipped_date = std::cmp::max(loan_creation_day, date_last_harvested);
+        let first_index = first_index_after(price_history, last_skipped_date);
+        for index in first_index..price_history.len as usize {
+            visit(price_at(price_history, index))?;
+        }
     }
 
-    if penalty_rate + loan.penalty_harvested > loan.sol_collateral_amount {
-        penalty_rate = loan.sol_collateral_amount - loan.penalty_harvested;
-    }
+    Ok(())
+}
 
-    Ok(penalty_rate)
+fn is_unchecked_day(
+    history: &HistoricPrice,
+    today: UnixTimestamp,
+    loan_creation_day: UnixTimestamp,
+    date_last_harvested: UnixTimestamp,
+) -> bool {
+    // The day the loan was created is never charged, however late in the day it was taken out.
+    // Don't process anything for todays date, and only run on days that haven't been harvested.
+    history.date > loan_creation_day && history.date != today && history.date > date_last_harvested
 }
 
-#[inline(never)]
-fn calculate_collateral_value(sol_price: u64, sol_decimals: u8, sol_collateral_amount: u64, lucra_price: u64, lucra_decimals: u8, staking_collateral_amount: u64) -> LucraResult<Decimal> {
-    // Calculate the value of the collateral
-    let sol_market_price = get_price(sol_price, sol_decimals)?;
-    let lucra_market_price = get_price(lucra_price, lucra_decimals)?;
-    let sol_side = Decimal::from(sol_collateral_amount)
-        .checked_mul(sol_market_price)
-        .ok_or(math_err!())?
-        .checked_div(LAMPORTS_PER_SOL.into())
-        .ok_or(math_err!())?;
-    let lucra_side = Decimal::from(staking_collateral_amount)
-        .checked_mul(lucra_market_price)
-        .ok_or(math_err!())?
-        .checked_div(LAMPORTS_PER_LUCRA)
-        .ok_or(math_err!())?;
-
-    sol_side
-        .checked_add(lucra_side)
-        .ok_or(math_err!())
+fn penalty_rate_for_day(
+    history: &HistoricPrice,
+    loan: &MataLoan,
+    collateral_factor: Decimal,
+    penalty_band: PenaltyBand,
+) -> LucraResult<u64> {
+    // if the price is 0 then the day was invalid. Skip charging any penalty for that day
+    if history.sol_price == 0 || history.lucra_price == 0 {
+        return Ok(0);
+    }
+
+    calc_day_penalty(history, loan, collateral_factor, penalty_band.width, penalty_band.multiplier_per_band)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::cell::RefCell;
-    use crate::state::HistoricPrice;
+    use rust_decimal_macros::dec;
+    use rust_decimal::prelude::ToPrimitive;
+    use solana_program::native_token::LAMPORTS_PER_SOL;
+    use crate::helpers::constants::LAMPORTS_PER_LUCRA;
+    use crate::helpers::collateral::calc_collateral_valuation_factor;
+    use crate::helpers::price_history::{accumulate_peg_deviation, calc_peg_deviation_bps, push_price};
+    use crate::lucra_core::penalty::{calculate_collateral_value, calculate_penalty_multiplier};
+
+    const PENALTY_BAND: PenaltyBand = PenaltyBand { width: Bps(500), multiplier_per_band: 2 };
 
     #[test]
     fn test_accumulate_penalty_rate() {
@@ -234,7 +451,8 @@ mod tests {
             lucra_price: 1_000_000, // 1 dollar
             lucra_decimals: 6,
             date: 1,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[1] = HistoricPrice {
             sol_price: 20_000_000, // 20 dollars
@@ -242,7 +460,8 @@ mod tests {
             lucra_price: 1_000_000, // 1 dollar
             lucra_decimals: 6,
             date: 2,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[2] = HistoricPrice {
             sol_price: 20_000_000, // 20 dollars
@@ -250,7 +469,8 @@ mod tests {
             lucra_price: 1_000_000, // 1 dollar
             lucra_decimals: 6,
             date: 3,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[3] = HistoricPrice {
             sol_price: 20_000_000, // 20 dollars
@@ -258,7 +478,8 @@ mod tests {
             lucra_price: 1_000_000, // 1 dollar
             lucra_decimals: 6,
             date: 4,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
 
         let price_history = PriceHistory {
@@ -281,7 +502,7 @@ mod tests {
         let b1 = c.borrow_mut();
         let b2 = RefMut::map(b1, |data| data);
 
-        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, 1, 0).unwrap();
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 0).unwrap();
         let expected = 0;
 
         assert_eq!(actual, expected);
@@ -300,7 +521,8 @@ mod tests {
             lucra_price: 30_000,
             lucra_decimals: 6,
             date: 1,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[1] = HistoricPrice {
             sol_price: 5_000_000,
@@ -308,7 +530,8 @@ mod tests {
             lucra_price: 30_000,
             lucra_decimals: 6,
             date: 2,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[2] = HistoricPrice {
             sol_price: 5_000_000,
@@ -316,7 +539,8 @@ mod tests {
             lucra_price: 30_000,
             lucra_decimals: 6,
             date: 3,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[3] = HistoricPrice {
             sol_price: 5_000_000,
@@ -324,7 +548,8 @@ mod tests {
             lucra_price: 30_000,
             lucra_decimals: 6,
             date: 4,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
 
         let price_history = PriceHistory {
@@ -350,7 +575,7 @@ mod tests {
 
         // mata and lucra price have tanked hard enough that there is less than a 25% of the collateral left.
 
-        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, 1, 0).unwrap();
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 0).unwrap();
         let expected = 4_044_943_820;
 
         assert_eq!(actual, expected);
@@ -369,7 +594,8 @@ mod tests {
             lucra_price: 1_000_000,
             lucra_decimals: 6,
             date: 1,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[1] = HistoricPrice {
             sol_price: 500_000,
@@ -377,7 +603,8 @@ mod tests {
             lucra_price: 500_000,
             lucra_decimals: 6,
             date: 2,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[2] = HistoricPrice {
             sol_price: 55_000_000,
@@ -385,7 +612,8 @@ mod tests {
             lucra_price: 100_000,
             lucra_decimals: 6,
             date: 3,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[3] = HistoricPrice {
             sol_price: 25_000_000,
@@ -393,7 +621,8 @@ mod tests {
             lucra_price: 500_000,
             lucra_decimals: 6,
             date: 4,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
 
         let price_history = PriceHistory {
@@ -417,7 +646,7 @@ mod tests {
         let b1 = c.borrow_mut();
         let b2 = RefMut::map(b1, |data| data);
 
-        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, 1, 0).unwrap();
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 0).unwrap();
         let expected = 460_674_156;
 
         assert_eq!(actual, expected);
@@ -436,7 +665,8 @@ mod tests {
             lucra_price: 100_000,
             lucra_decimals: 6,
             date: 1,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[1] = HistoricPrice {
             sol_price: 10_000_000,
@@ -444,7 +674,8 @@ mod tests {
             lucra_price: 100_000,
             lucra_decimals: 6,
             date: 2,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[2] = HistoricPrice {
             sol_price: 50_000_000,
@@ -452,7 +683,8 @@ mod tests {
             lucra_price: 100_000,
             lucra_decimals: 6,
             date: 3,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[3] = HistoricPrice {
             sol_price: 0,
@@ -460,7 +692,8 @@ mod tests {
             lucra_price: 100_000,
             lucra_decimals: 6,
             date: 3,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[4] = HistoricPrice {
             sol_price: 0,
@@ -468,7 +701,8 @@ mod tests {
             lucra_price: 100_000,
             lucra_decimals: 6,
             date: 3,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[5] = HistoricPrice {
             sol_price: 1_000_000,
@@ -476,7 +710,8 @@ mod tests {
             lucra_price: 500_000,
             lucra_decimals: 6,
             date: 4,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
 
         let price_history = PriceHistory {
@@ -500,7 +735,7 @@ mod tests {
         let b1 = c.borrow_mut();
         let b2 = RefMut::map(b1, |data| data);
 
-        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, 1, 0).unwrap();
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 0).unwrap();
         let expected = 688_202_246;
 
         assert_eq!(actual, expected);
@@ -519,7 +754,8 @@ mod tests {
             lucra_price: 100_000,
             lucra_decimals: 6,
             date: 1,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[1] = HistoricPrice {
             sol_price: 50_000,
@@ -527,7 +763,8 @@ mod tests {
             lucra_price: 50_000,
             lucra_decimals: 6,
             date: 2,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[2] = HistoricPrice {
             sol_price: 200_000_000,
@@ -535,7 +772,8 @@ mod tests {
             lucra_price: 100_000,
             lucra_decimals: 6,
             date: 3,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
         prices[3] = HistoricPrice {
             sol_price: 1_000_000,
@@ -543,7 +781,8 @@ mod tests {
             lucra_price: 500_000,
             lucra_decimals: 6,
             date: 4,
-            padding: [0; 6],
+            mata_deviation_bps: 0,
+            mata_deviation_seconds: 0,
         };
 
         let price_history = PriceHistory {
@@ -567,7 +806,7 @@ mod tests {
         let b1 = c.borrow_mut();
         let b2 = RefMut::map(b1, |data| data);
 
-        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, 1, 0).unwrap();
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 0).unwrap();
         let expected = 1_573_033_707;
 
         assert_eq!(actual, expected);
@@ -587,7 +826,8 @@ mod tests {
                 lucra_price: 50_000,
                 lucra_decimals: 6,
                 date: i as i64,
-                padding: [0; 6],
+                mata_deviation_bps: 0,
+                mata_deviation_seconds: 0,
             };
         }
 
@@ -612,7 +852,7 @@ mod tests {
         let b1 = c.borrow_mut();
         let b2 = RefMut::map(b1, |data| data);
 
-        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, 1, 0).unwrap();
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 0).unwrap();
         let expected = 10_000_000_000;
 
         assert_eq!(actual, expected);
@@ -632,7 +872,8 @@ mod tests {
                 lucra_price: 50_000,
                 lucra_decimals: 6,
                 date: i as i64,
-                padding: [0; 6],
+                mata_deviation_bps: 0,
+                mata_deviation_seconds: 0,
             };
         }
 
@@ -658,12 +899,61 @@ mod tests {
         let b1 = c.borrow_mut();
         let b2 = RefMut::map(b1, |data| data);
 
-        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, 1, 0).unwrap();
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 0).unwrap();
         let expected = 5_000_000_000;
 
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_accumulate_penalty_rate_counts_penalty_waiting_to_be_harvested() {
+        let mut prices = [
+            HistoricPrice {
+                ..HistoricPrice::default()
+            }; 30
+        ];
+        for i in 0..6 {
+            prices[i] = HistoricPrice {
+                sol_price: 50_000,
+                sol_decimals: 6,
+                lucra_price: 50_000,
+                lucra_decimals: 6,
+                date: i as i64,
+                mata_deviation_bps: 0,
+                mata_deviation_seconds: 0,
+            };
+        }
+
+        let price_history = PriceHistory {
+            prices,
+            ..PriceHistory::default()
+        };
+        let c = RefCell::new(price_history);
+        let b1 = c.borrow();
+        let b2 = Ref::map(b1, |data| data);
+        let price_history = Box::from(b2);
+
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            staking_collateral_amount: 200 * LAMPORTS_PER_LUCRA.to_u64().unwrap(),
+            market_price: 20_000_000,
+            loan_amount: 133_333_333,
+            penalty_harvested: 5_000_000_000,
+            // Accrued on an earlier run and not harvested yet
+            penalty_to_harvest: 3_000_000_000,
+            collateral_rate: 300,
+            ..MataLoan::default()
+        };
+        let c = RefCell::new(loan);
+        let b1 = c.borrow_mut();
+        let b2 = RefMut::map(b1, |data| data);
+
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 0).unwrap();
+        let expected = 2_000_000_000;
+
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_accumulate_penalty_rate_doesnt_process_todays_date() {
         let mut prices = [
@@ -678,7 +968,8 @@ mod tests {
                 lucra_price: 50_000,
                 lucra_decimals: 6,
                 date: i as i64,
-                padding: [0; 6],
+                mata_deviation_bps: 0,
+                mata_deviation_seconds: 0,
             };
         }
 
@@ -703,7 +994,7 @@ mod tests {
         let b1 = c.borrow_mut();
         let b2 = RefMut::map(b1, |data| data);
 
-        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, 1, 7).unwrap();
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 7).unwrap();
         let expected = 6_067_415_730;
 
         assert_eq!(actual, expected);
@@ -723,7 +1014,9 @@ mod tests {
                 lucra_price: 35_000,
                 lucra_decimals: 6,
                 date: i as i64,
-                padding: [0; 6],
+                // a day averaging 5 cents under the peg
+                mata_deviation_bps: 500,
+                mata_deviation_seconds: 0,
             };
         }
 
@@ -748,7 +1041,7 @@ mod tests {
         let b1 = c.borrow_mut();
         let b2 = RefMut::map(b1, |data| data);
 
-        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, 2, 7).unwrap();
+        let actual = _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 7).unwrap();
         let expected = 10_000_000_000;
 
         assert_eq!(actual, expected);
@@ -764,43 +1057,421 @@ mod tests {
         let sol_collateral_amount = 10 * LAMPORTS_PER_SOL; // 200 dollars of sol
         let staking_collateral_amount = 0;
 
-        let actual = calculate_collateral_value(sol_price, sol_decimals, sol_collateral_amount, lucra_price, lucra_decimals, staking_collateral_amount).unwrap();
+        let actual = calculate_collateral_value(sol_price, sol_decimals, sol_collateral_amount, Decimal::ONE, lucra_price, lucra_decimals, staking_collateral_amount).unwrap();
         let expected = Decimal::from(200_u64);
         
         assert_eq!(actual, expected);
         
         let staking_collateral_amount = 200 * LAMPORTS_PER_LUCRA.to_u64().unwrap(); // 200 dollars of lucra
-        let actual = calculate_collateral_value(sol_price, sol_decimals, sol_collateral_amount, lucra_price, lucra_decimals, staking_collateral_amount).unwrap();
+        let actual = calculate_collateral_value(sol_price, sol_decimals, sol_collateral_amount, Decimal::ONE, lucra_price, lucra_decimals, staking_collateral_amount).unwrap();
         let expected = Decimal::from(400_u64);
 
         assert_eq!(actual, expected);
+
+        // A 1% haircut only applies to the sol side
+        let actual = calculate_collateral_value(sol_price, sol_decimals, sol_collateral_amount, dec!(0.99), lucra_price, lucra_decimals, staking_collateral_amount).unwrap();
+        let expected = Decimal::from(398_u64);
+
+        assert_eq!(actual, expected);
+    }
+
+    // Walks the sol price down in 5 cent steps until the loan starts getting charged
+    fn penalty_onset_price(collateral_factor: Decimal) -> Option<u64> {
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            market_price: 20_000_000,
+            loan_amount: 133_333_333,
+            collateral_rate: 300,
+            ..MataLoan::default()
+        };
+        let c = RefCell::new(loan);
+        let loan = RefMut::map(c.borrow_mut(), |data| data);
+
+        (1..=4_000).rev().map(|step| step * 50_000).find(|sol_price| {
+            let mut price_history = PriceHistory::default();
+            push_price(&mut price_history, 1, *sol_price, 6, 1_000_000, 6);
+            let c = RefCell::new(price_history);
+            let price_history = Ref::map(c.borrow(), |data| data);
+
+            _accumulate_penalty_rate_charge(&price_history, &loan, collateral_factor, PENALTY_BAND, 0).unwrap() > 0
+        })
+    }
+
+    #[test]
+    fn test_msol_depeg_brings_penalty_onset_forward() {
+        let marinade_rate = dec!(1.08);
+        let depegged_market_rate = marinade_rate * dec!(0.95);
+        let pegged = calc_collateral_valuation_factor(Bps(0), marinade_rate, None).unwrap();
+        let depegged = calc_collateral_valuation_factor(Bps(100), marinade_rate, Some(depegged_market_rate)).unwrap();
+
+        let pegged_onset = penalty_onset_price(pegged).unwrap();
+        let depegged_onset = penalty_onset_price(depegged).unwrap();
+
+        // The discounted collateral starts paying penalty while sol is still trading higher
+        assert!(depegged_onset > pegged_onset);
     }
 
     #[test]
     fn test_calculate_penalty_multiplier() {
-        let mata_price = Decimal::from(1_u64);
+        assert_eq!(calculate_penalty_multiplier(0, Bps(500), 2), 1);
+        assert_eq!(calculate_penalty_multiplier(calc_peg_deviation_bps(Decimal::from(1_u64)), Bps(500), 2), 1);
+        assert_eq!(calculate_penalty_multiplier(calc_peg_deviation_bps(Decimal::new(95, 2)), Bps(500), 2), 2);
+        assert_eq!(calculate_penalty_multiplier(calc_peg_deviation_bps(Decimal::new(90, 2)), Bps(500), 2), 4);
+        assert_eq!(calculate_penalty_multiplier(calc_peg_deviation_bps(Decimal::new(60, 2)), Bps(500), 2), 16);
+    }
 
-        let actual = calculate_penalty_multiplier(mata_price).unwrap();
-        let expected = 1;
+    #[test]
+    fn test_a_short_dip_is_charged_for_how_long_it_lasted() {
+        let day = |date: i64, mata_deviation_bps: u16| HistoricPrice {
+            sol_price: 5_000_000,
+            sol_decimals: 6,
+            lucra_price: 30_000,
+            lucra_decimals: 6,
+            date,
+            mata_deviation_bps,
+            mata_deviation_seconds: 0,
+        };
 
-        assert_eq!(actual, expected);
+        // An hour at 60 cents in an otherwise calm day
+        let mut dipped_day = day(1, 0);
+        for hour in 0..24 {
+            let price = if hour == 12 { Decimal::new(60, 2) } else { Decimal::ONE };
+            accumulate_peg_deviation(&mut dipped_day, calc_peg_deviation_bps(price), 3_600);
+        }
 
-        let mata_price = Decimal::new(95, 2);
-        let actual = calculate_penalty_multiplier(mata_price).unwrap();
-        let expected = 2;
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            staking_collateral_amount: 200 * LAMPORTS_PER_LUCRA.to_u64().unwrap(),
+            market_price: 50_000_000_000,
+            loan_amount: 233_333_333,
+            collateral_rate: 300,
+            ..MataLoan::default()
+        };
+        let c = RefCell::new(loan);
+        let b1 = c.borrow_mut();
+        let b2 = RefMut::map(b1, |data| data);
 
-        assert_eq!(actual, expected);
+        let charge_for = |history: HistoricPrice| {
+            let mut prices = [HistoricPrice::default(); 30];
+            prices[0] = history;
+            let c = RefCell::new(PriceHistory { prices, ..PriceHistory::default() });
+            let b1 = c.borrow();
+            let price_history = Ref::map(b1, |data| data);
+            _accumulate_penalty_rate_charge(&price_history, &b2, Decimal::ONE, PENALTY_BAND, 0).unwrap()
+        };
 
-        let mata_price = Decimal::new(90, 2);
-        let actual = calculate_penalty_multiplier(mata_price).unwrap();
-        let expected = 4;
+        let calm = charge_for(day(1, 0));
+        let dipped = charge_for(dipped_day);
 
-        assert_eq!(actual, expected);
+        assert!(calm > 0);
+        assert_eq!(dipped, calm * 2);
+        // Pricing the whole day at the dip would have charged 16x
+        assert!(dipped < calm * 16);
+    }
 
-        let mata_price = Decimal::new(60, 2);
-        let actual = calculate_penalty_multiplier(mata_price).unwrap();
-        let expected = 16;
+    #[test]
+    fn test_creation_day_is_never_charged() {
+        const DAY: i64 = 86_400;
+        let creation_day = 19_000 * DAY;
+
+        let charge_for = |loan_creation_date: i64, days: &[i64]| {
+            let mut price_history = PriceHistory::default();
+            for day in days {
+                push_price(&mut price_history, *day, 5_000_000, 6, 30_000, 6);
+            }
+            let loan = MataLoan {
+                sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+                staking_collateral_amount: 200 * LAMPORTS_PER_LUCRA.to_u64().unwrap(),
+                market_price: 50_000_000_000,
+                loan_amount: 233_333_333,
+                collateral_rate: 300,
+                loan_creation_date,
+                last_day_penalty_was_checked: loan_creation_date,
+                ..MataLoan::default()
+            };
 
-        assert_eq!(actual, expected);
+            let c = RefCell::new(loan);
+            let loan = RefMut::map(c.borrow_mut(), |data| data);
+            let c = RefCell::new(price_history);
+            let price_history = Ref::map(c.borrow(), |data| data);
+            _accumulate_penalty_rate_charge(&price_history, &loan, Decimal::ONE, PENALTY_BAND, creation_day + 2 * DAY + DAY / 2).unwrap()
+        };
+
+        for seconds_into_the_day in [1, DAY / 2, DAY - 1] {
+            let loan_creation_date = creation_day + seconds_into_the_day;
+
+            // The entry for the creation day is skipped, only the day after it is charged
+            let day_after_only = charge_for(loan_creation_date, &[creation_day + DAY]);
+            assert!(day_after_only > 0);
+            assert_eq!(charge_for(loan_creation_date, &[creation_day, creation_day + DAY]), day_after_only);
+            assert_eq!(charge_for(loan_creation_date, &[creation_day]), 0);
+        }
+    }
+
+    // Small deterministic generator so the randomized histories are reproducible
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            (self.0 >> 33) % bound
+        }
+    }
+
+    #[test]
+    fn test_accumulate_penalty_rate_suffix_matches_full_scan() {
+        const DAY: i64 = 86_400;
+        const FIRST_DAY: i64 = 19_000;
+        let mut rng = Lcg(7);
+
+        for _ in 0..200 {
+            let mut ring = PriceHistory::default();
+            let mut legacy = PriceHistory::default();
+
+            let mut day = FIRST_DAY;
+            for _ in 0..rng.next(70) + 1 {
+                day += rng.next(3) as i64 + 1;
+                // Some days didn't get enough updates and were zeroed out
+                let (sol_price, lucra_price) = if rng.next(6) == 0 {
+                    (0, 0)
+                } else {
+                    (1_000_000 + rng.next(30_000_000), 10_000 + rng.next(2_000_000))
+                };
+
+                push_price(&mut ring, day * DAY, sol_price, 6, lucra_price, 6);
+                push_price(&mut legacy, day * DAY, sol_price, 6, lucra_price, 6);
+            }
+            // The legacy layout is scanned in full
+            legacy.head = 0;
+            legacy.len = 0;
+
+            let span = (day - FIRST_DAY) as u64 + 2;
+            let loan = MataLoan {
+                sol_collateral_amount: (rng.next(20) + 1) * LAMPORTS_PER_SOL,
+                staking_collateral_amount: rng.next(200) * LAMPORTS_PER_LUCRA.to_u64().unwrap(),
+                market_price: 20_000_000,
+                loan_amount: 133_333_333,
+                loan_creation_date: (FIRST_DAY + rng.next(span) as i64) * DAY + rng.next(DAY as u64) as i64,
+                last_day_penalty_was_checked: (FIRST_DAY + rng.next(span) as i64) * DAY + rng.next(DAY as u64) as i64,
+                ..MataLoan::default()
+            };
+            let timestamp = (day - rng.next(2) as i64) * DAY + rng.next(DAY as u64) as i64;
+
+            let c = RefCell::new(loan);
+            let b1 = c.borrow_mut();
+            let loan = RefMut::map(b1, |data| data);
+
+            let c = RefCell::new(ring);
+            let ring = Ref::map(c.borrow(), |data| data);
+            let c = RefCell::new(legacy);
+            let legacy = Ref::map(c.borrow(), |data| data);
+
+            let expected = _accumulate_penalty_rate_charge(&legacy, &loan, Decimal::ONE, PENALTY_BAND, timestamp).unwrap();
+            let actual = _accumulate_penalty_rate_charge(&ring, &loan, Decimal::ONE, PENALTY_BAND, timestamp).unwrap();
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_charged_days_are_reported_with_their_prices() {
+        const DAY: i64 = 86_400;
+        let mut price_history = PriceHistory::default();
+        push_price(&mut price_history, DAY, 10_000_000, 6, 100_000, 6);
+        // Zeroed out days aren't charged so they aren't reported either
+        push_price(&mut price_history, 2 * DAY, 0, 6, 0, 6);
+        push_price(&mut price_history, 3 * DAY, 12_000_000, 6, 100_000, 6);
+
+        let c = RefCell::new(price_history);
+        let price_history = Ref::map(c.borrow(), |data| data);
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            market_price: 50_000_000,
+            loan_amount: 233_333_333,
+            collateral_rate: 300,
+            ..MataLoan::default()
+        };
+        let c = RefCell::new(loan);
+        let loan = RefMut::map(c.borrow_mut(), |data| data);
+
+        let mut charged_days = vec![];
+        let total = _accumulate_penalty_rate_charge_with(&price_history, &loan, Decimal::ONE, PENALTY_BAND, 5 * DAY, |history, penalty| {
+            charged_days.push((history.date, penalty, history.sol_price));
+        }).unwrap();
+
+        assert_eq!(charged_days.len(), 2);
+        assert_eq!((charged_days[0].0, charged_days[0].2), (DAY, 10_000_000));
+        assert_eq!((charged_days[1].0, charged_days[1].2), (3 * DAY, 12_000_000));
+        assert_eq!(charged_days.iter().map(|(_, penalty, _)| penalty).sum::<u64>(), total);
+    }
+
+    #[test]
+    fn test_charged_days_are_reported_after_the_cap() {
+        const DAY: i64 = 86_400;
+        let mut price_history = PriceHistory::default();
+        for day in 1..=20 {
+            push_price(&mut price_history, day * DAY, 50_000, 6, 50_000, 6);
+        }
+
+        let c = RefCell::new(price_history);
+        let price_history = Ref::map(c.borrow(), |data| data);
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            staking_collateral_amount: 200 * LAMPORTS_PER_LUCRA.to_u64().unwrap(),
+            market_price: 20_000_000,
+            loan_amount: 133_333_333,
+            collateral_rate: 300,
+            ..MataLoan::default()
+        };
+        let c = RefCell::new(loan);
+        let loan = RefMut::map(c.borrow_mut(), |data| data);
+
+        let mut charged_days = vec![];
+        let total = _accumulate_penalty_rate_charge_with(&price_history, &loan, Decimal::ONE, PENALTY_BAND, 21 * DAY, |history, penalty| {
+            charged_days.push((history.date, penalty));
+        }).unwrap();
+
+        // More is owed than the loan has, what's journaled stops where the collateral runs out
+        assert_eq!(total, 10 * LAMPORTS_PER_SOL);
+        assert!(!charged_days.is_empty());
+        assert_eq!(charged_days.iter().map(|(_, penalty)| penalty).sum::<u64>(), total);
+    }
+
+    #[test]
+    fn test_lp_loans_are_charged_like_the_sol_they_were_worth() {
+        const DAY: i64 = 86_400;
+        let mut price_history = PriceHistory::default();
+        for day in 1..=5 {
+            push_price(&mut price_history, day * DAY, 15_000_000, 6, 50_000, 6);
+        }
+
+        let c = RefCell::new(price_history);
+        let price_history = Ref::map(c.borrow(), |data| data);
+        let sol_loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            market_price: 20_000_000,
+            loan_amount: 66_666_666,
+            collateral_rate: 300,
+            ..MataLoan::default()
+        };
+        // The same loan opened with LP tokens that were worth 10 SOL
+        let lp_loan = MataLoan {
+            loan_type: LoanType::LpBacked,
+            sol_collateral_amount: 0,
+            lp_collateral_amount: 4_000_000,
+            lp_sol_equivalent: 10 * LAMPORTS_PER_SOL,
+            ..sol_loan
+        };
+
+        let sol_penalty = _accumulate_penalty_rate_charge_with(&price_history, &penalized_loan(&sol_loan), Decimal::ONE, PENALTY_BAND, 6 * DAY, |_, _| {}).unwrap();
+        let lp_penalty = _accumulate_penalty_rate_charge_with(&price_history, &penalized_loan(&lp_loan), Decimal::ONE, PENALTY_BAND, 6 * DAY, |_, _| {}).unwrap();
+        assert!(sol_penalty > 0);
+        assert_eq!(lp_penalty, sol_penalty);
+
+        // Charged on its own collateral it would never pay anything
+        assert_eq!(_accumulate_penalty_rate_charge_with(&price_history, &lp_loan, Decimal::ONE, PENALTY_BAND, 6 * DAY, |_, _| {}).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_loan_unchecked_past_the_price_history_is_charged_from_its_pages() {
+        use bytemuck::Zeroable;
+        use crate::helpers::paged_price_history::archive_recent_prices;
+
+        const DAY: i64 = 86_400;
+        let mut header = PriceHistoryHeader::zeroed();
+        header.init(&Pubkey::new_unique());
+        let mut pages: Vec<PriceHistoryPage> = (0..3)
+            .map(|_| {
+                let mut page = PriceHistoryPage::zeroed();
+                page.init(header.record_page().unwrap());
+                page
+            })
+            .collect();
+        let mut price_history = PriceHistory::default();
+        for day in 1..=70 {
+            push_price(&mut price_history, day * DAY, 10_000_000, 6, 100_000, 6);
+            let mut page_refs: Vec<&mut PriceHistoryPage> = pages.iter_mut().collect();
+            archive_recent_prices(&mut header, &mut page_refs, &price_history).unwrap();
+        }
+        assert_eq!(header.active_page, 2);
+
+        let loan = MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            market_price: 50_000_000,
+            loan_amount: 233_333_333,
+            collateral_rate: 300,
+            ..MataLoan::default()
+        };
+        let today = 70 * DAY + DAY / 2;
+        let (first_day, last_day) = unchecked_day_range(&loan, today);
+        assert_eq!((first_day, last_day), (DAY, 69 * DAY));
+
+        // The price history only has the last 30 days left, the loan can't be charged from it alone
+        assert!(check_price_history_covers(&price_history, first_day).is_err());
+
+        let page_refs: Vec<&PriceHistoryPage> = pages.iter().collect();
+        let days = collect_paged_days(&header, &page_refs, &price_history, first_day, last_day).unwrap();
+        assert_eq!(days.len(), 69);
+
+        let per_day = penalty_rate_for_day(&days[0], &loan, Decimal::ONE, PENALTY_BAND).unwrap();
+        assert!(per_day > 0);
+        let mut charged_days = 0;
+        let total = charge_penalty_for_days(&loan, Decimal::ONE, PENALTY_BAND, |_, _| charged_days += 1, |on_day| {
+            days.iter().try_for_each(on_day)
+        })
+        .unwrap();
+        assert_eq!(charged_days, 69);
+        assert_eq!(total, (69 * per_day).min(calc_remaining_penalty_collateral(&loan)));
+
+        // A loan checked within the last 30 days is charged the same either way
+        let recent = MataLoan { last_day_penalty_was_checked: 60 * DAY + 10, ..loan };
+        let (first_day, last_day) = unchecked_day_range(&recent, today);
+        assert!(check_price_history_covers(&price_history, first_day).is_ok());
+        let days = collect_paged_days(&header, &page_refs, &price_history, first_day, last_day).unwrap();
+        let paged = charge_penalty_for_days(&recent, Decimal::ONE, PENALTY_BAND, |_, _| {}, |on_day| {
+            days.iter().try_for_each(on_day)
+        })
+        .unwrap();
+
+        let c = RefCell::new(price_history);
+        let price_history = Ref::map(c.borrow(), |data| data);
+        let c = RefCell::new(recent);
+        let recent = RefMut::map(c.borrow_mut(), |data| data);
+        assert_eq!(_accumulate_penalty_rate_charge(&price_history, &recent, Decimal::ONE, PENALTY_BAND, today).unwrap(), paged);
+        assert_eq!(days.len(), 9);
+    }
+
+    #[test]
+    fn test_interest_accrues_over_the_unchecked_days() {
+        const DAY: i64 = 86_400;
+        let mut price_history = PriceHistory::default();
+        push_price(&mut price_history, DAY, 10_000_000, 6, 100_000, 6);
+        // Zeroed out days aren't charged penalty but the debt is still outstanding
+        push_price(&mut price_history, 2 * DAY, 0, 6, 0, 6);
+        push_price(&mut price_history, 3 * DAY, 12_000_000, 6, 100_000, 6);
+        push_price(&mut price_history, 4 * DAY, 12_000_000, 6, 100_000, 6);
+
+        // 365 mata at 10% a year is 0.1 mata a day
+        let loan = MataLoan {
+            loan_amount: 365_000_000,
+            ..MataLoan::default()
+        };
+        let today = 4 * DAY + DAY / 2;
+
+        assert_eq!(_accumulate_interest_charge(&price_history, &loan, Bps(1_000), today).unwrap(), 300_000);
+        assert_eq!(_accumulate_interest_charge(&price_history, &loan, Bps(0), today).unwrap(), 0);
+
+        // Days already checked and the creation day aren't charged again
+        let checked = MataLoan { last_day_penalty_was_checked: DAY + 10, ..loan };
+        assert_eq!(_accumulate_interest_charge(&price_history, &checked, Bps(1_000), today).unwrap(), 200_000);
+        let created = MataLoan { loan_creation_date: 3 * DAY + 10, ..loan };
+        assert_eq!(_accumulate_interest_charge(&price_history, &created, Bps(1_000), today).unwrap(), 0);
+
+        // The legacy layout accrues the same
+        let mut legacy = price_history;
+        legacy.head = 0;
+        legacy.len = 0;
+        assert_eq!(_accumulate_interest_charge(&legacy, &loan, Bps(1_000), today).unwrap(), 300_000);
+        assert_eq!(_accumulate_interest_charge(&legacy, &checked, Bps(1_000), today).unwrap(), 200_000);
     }
 }
\ No newline at end of file
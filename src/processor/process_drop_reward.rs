@@ -6,6 +6,7 @@ use arrayref::array_ref;
 use marinade_finance::state::State as MarinadeState;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     sysvar::{clock::Clock, rent::Rent, Sysvar},
     program_pack::Pack,
     pubkey::Pubkey,
@@ -22,7 +23,19 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
+    helpers::reward_expiry::{calc_reward_expiry, reward_expiry_epochs},
+    helpers::reward_index::record_reward_index,
+    helpers::keepers::{resolve_crank_caller, split_keeper},
+    helpers::reward_tokens::mint_whole_reward,
     helpers::spl::get_token_account_mint,
+    helpers::settlement::check_not_settled,
+    helpers::vaults::{
+        verify_arb_coffer,
+        verify_msol_vault,
+        verify_reward_mint,
+        verify_rewards_vault,
+    },
+    instruction::Instruction,
     state::{
         DataType,
         MetaData,
@@ -33,8 +46,20 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::DropReward);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::DropReward { } => {
+            msg!("Instruction: Drop Reward");
+            process_drop_reward(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[inline(never)]
 pub fn process_drop_reward(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    let (accounts, keeper) = split_keeper(program_id, accounts);
     const NUM_FIXED: usize = 13;
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
@@ -73,15 +98,19 @@ pub fn process_drop_reward(program_id: &Pubkey, accounts: &[AccountInfo]) -> Luc
     let marinade_state = MarinadeState::try_deserialize(&mut marinade_data).unwrap();
 
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    let keeper_multiplier = resolve_crank_caller(program_id, &system_state, keeper)?;
+    check_not_settled(&system_state)?;
     check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
-    check_eq!(&system_state.arb_coffer.address, arb_coffer_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.reward_mint.address, reward_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.rewards_vault.address, rewards_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    verify_drop_reward_accounts(
+        &system_state,
+        staking_state_ai.key,
+        msol_vault_ai.key,
+        rewards_vault_ai.key,
+        arb_coffer_ai.key,
+        reward_mint_ai.key,
+    )?;
     let mut staking_state: RefMut<StakingState> = StakingState::load_mut_checked(staking_state_ai, program_id)?;
-    if staking_state.last_drop_timestamp != 0 {
-        check!(staking_state.last_drop_timestamp + system_state.epoch <= clock.unix_timestamp, LucraErrorCode::EarlyRewardDrop)?;
-    }
+    check_drop_due(&staking_state, system_state.epoch, clock.unix_timestamp)?;
 
     // Check that the last reward is not the new one
     if staking_state.current_reward_pubkey != Pubkey::default() {
@@ -101,57 +130,236 @@ pub fn process_drop_reward(program_id: &Pubkey, accounts: &[AccountInfo]) -> Luc
 
     check_eq!(&user_token_account_mint, reward_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&staking_state.stake_mint.address, stake_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.msol_vault.address, msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
 
     let msol_lamport_value = marinade_state.calc_msol_from_lamports(system_state.total_sol_collateral).unwrap();
     let total = msol_vault.amount
         .checked_sub(msol_lamport_value)
-        .ok_or(math_err!())?;
+        .ok_or_else(|| invariant_err!(LucraErrorCode::MsolVaultShortfall))?;
     let half_of_total = total
         .checked_div(2)
         .ok_or(math_err!())?;
 
-    reward.meta_data = MetaData::new(DataType::Reward, 0, true);
-    reward.previous_reward = staking_state.current_reward_pubkey;
-    reward.pool_token_supply = stake_mint.supply;
-    reward.reward_cursor = staking_state.reward_cursor;
-    reward.total = half_of_total;
-    reward.start_timestamp = clock.unix_timestamp;
+    if stake_mint.supply == 0 {
+        // Nobody could ever claim a reward on no stake, the coffer takes the whole yield instead
+        msg!("Nothing staked, sending the {} dropped to the arb coffer", total);
+        record_unstaked_drop(&mut staking_state, clock.unix_timestamp);
 
-    staking_state.last_reward = half_of_total;
-    staking_state.current_reward_pubkey = *reward_ai.key;
-    staking_state.last_drop_timestamp = clock.unix_timestamp;
-    staking_state.increment_reward_cursor();
+        system_state.transfer_from_msol_vault(
+            program_id,
+            msol_vault_ai,
+            arb_coffer_ai,
+            msol_vault_transfer_authority_ai,
+            token_program_ai,
+            total,
+        )?;
+    } else if is_below_min_drop(&staking_state, half_of_total) {
+        msg!("Skipping drop of {} under the minimum of {}", half_of_total, staking_state.min_drop_amount);
+        record_skipped_drop(&mut staking_state, clock.unix_timestamp)?;
+    } else {
+        create_reward(&mut reward, &staking_state, &system_state, stake_mint.supply, half_of_total, clock.unix_timestamp)?;
+        record_drop(&mut staking_state, reward_ai.key, stake_mint.supply, half_of_total, clock.unix_timestamp)?;
+        // Balances that staked into this drop sit it out and claim from the index it leaves
+        reward.reward_index = staking_state.reward_index;
 
-    // Transfer half to the arb_coffer
-    system_state.transfer_from_msol_vault(
-        program_id, 
-        msol_vault_ai, 
-        arb_coffer_ai, 
-        msol_vault_transfer_authority_ai, 
-        token_program_ai, 
-        half_of_total
-    )?;
+        // Transfer half to the arb_coffer
+        system_state.transfer_from_msol_vault(
+            program_id, 
+            msol_vault_ai, 
+            arb_coffer_ai, 
+            msol_vault_transfer_authority_ai, 
+            token_program_ai, 
+            half_of_total
+        )?;
 
-    // Transfer other half to the rewards vault
-    system_state.transfer_from_msol_vault(
-        program_id,
-        msol_vault_ai,
-        rewards_vault_ai,
-        msol_vault_transfer_authority_ai,
-        token_program_ai,
-        half_of_total,
-    )?;
+        // Transfer other half to the rewards vault
+        system_state.transfer_from_msol_vault(
+            program_id,
+            msol_vault_ai,
+            rewards_vault_ai,
+            msol_vault_transfer_authority_ai,
+            token_program_ai,
+            half_of_total,
+        )?;
+    }
 
-    // Pay the user for their efforts
-    system_state.mint_reward(
+    // Pay the user for their efforts, a skip still keeps the drops on their epoch
+    mint_whole_reward(
         program_id,
+        &system_state,
         reward_mint_ai,
         user_reward_account_ai,
         1,
+        keeper_multiplier,
         reward_mint_authority_ai,
         token_program_ai,
     )?;
 
     Ok(())
 }
+
+/// Drops are an epoch apart, the first one can run whenever
+fn check_drop_due(staking_state: &StakingState, epoch: i64, now: i64) -> LucraResult {
+    if staking_state.last_drop_timestamp == 0 {
+        return Ok(());
+    }
+
+    let next_drop = staking_state.last_drop_timestamp
+        .checked_add(epoch)
+        .ok_or(math_err!())?;
+    check!(next_drop <= now, LucraErrorCode::EarlyRewardDrop)
+}
+
+/// With no stake the drop creates no reward and takes no reward cursor, only the timestamp moves so the epoch
+/// isn't dropped again
+fn record_unstaked_drop(staking_state: &mut StakingState, timestamp: i64) {
+    staking_state.last_drop_timestamp = timestamp;
+}
+
+/// A drop under the minimum costs stakers more to claim than it pays
+fn is_below_min_drop(staking_state: &StakingState, half_of_total: u64) -> bool {
+    half_of_total < staking_state.min_drop_amount
+}
+
+/// A skip moves the drop timestamp but takes no reward cursor, so balances never have a gap to claim across.
+/// Nothing leaves the msol vault and the yield is counted again by the next drop.
+fn record_skipped_drop(staking_state: &mut StakingState, timestamp: i64) -> LucraResult {
+    staking_state.last_drop_timestamp = timestamp;
+    staking_state.skipped_drops = staking_state.skipped_drops
+        .checked_add(1)
+        .ok_or(math_err!())?;
+
+    Ok(())
+}
+
+fn create_reward(
+    reward: &mut Reward,
+    staking_state: &StakingState,
+    system_state: &SystemState,
+    pool_token_supply: u64,
+    half_of_total: u64,
+    timestamp: i64,
+) -> LucraResult {
+    reward.meta_data = MetaData::new(DataType::Reward, 0, true);
+    reward.previous_reward = staking_state.current_reward_pubkey;
+    reward.pool_token_supply = pool_token_supply;
+    reward.reward_cursor = staking_state.reward_cursor;
+    reward.total = half_of_total;
+    reward.start_timestamp = timestamp;
+    reward.expires_at = calc_reward_expiry(timestamp, system_state.epoch, reward_expiry_epochs(system_state))?;
+    reward.claimed = 0;
+    // Balances on the index claim this drop through it, the account stays for the ones still on the cursor
+    reward.in_reward_index = true;
+
+    Ok(())
+}
+
+fn record_drop(staking_state: &mut StakingState, reward: &Pubkey, pool_token_supply: u64, half_of_total: u64, timestamp: i64) -> LucraResult {
+    record_reward_index(staking_state, half_of_total, pool_token_supply)?;
+    staking_state.last_reward = half_of_total;
+    staking_state.current_reward_pubkey = *reward;
+    staking_state.last_drop_timestamp = timestamp;
+    staking_state.skipped_drops = 0;
+    staking_state.increment_reward_cursor();
+
+    Ok(())
+}
+
+// The msol yield is split between the coffer and the rewards vault so both must be the protocol's own
+#[inline(never)]
+fn verify_drop_reward_accounts(
+    system_state: &SystemState,
+    staking_state: &Pubkey,
+    msol_vault: &Pubkey,
+    rewards_vault: &Pubkey,
+    arb_coffer: &Pubkey,
+    reward_mint: &Pubkey,
+) -> LucraResult {
+    check_eq!(&system_state.staking_state, staking_state, LucraErrorCode::InvalidAccountInput)?;
+    verify_msol_vault(system_state, msol_vault)?;
+    verify_rewards_vault(system_state, rewards_vault)?;
+    verify_arb_coffer(system_state, arb_coffer)?;
+    verify_reward_mint(system_state, reward_mint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::vaults::tests::{assert_invalid_account_input, system_state_with_vaults};
+
+    fn drop_reward_accounts(system_state: &SystemState) -> [Pubkey; 5] {
+        [
+            system_state.staking_state,
+            system_state.msol_vault.address,
+            system_state.rewards_vault.address,
+            system_state.arb_coffer.address,
+            system_state.reward_mint.address,
+        ]
+    }
+
+    fn verify(system_state: &SystemState, accounts: &[Pubkey; 5]) -> LucraResult {
+        verify_drop_reward_accounts(system_state, &accounts[0], &accounts[1], &accounts[2], &accounts[3], &accounts[4])
+    }
+
+    #[test]
+    fn test_protocol_accounts_are_accepted() {
+        let mut system_state = system_state_with_vaults();
+        system_state.staking_state = Pubkey::new_unique();
+
+        assert!(verify(&system_state, &drop_reward_accounts(&system_state)).is_ok());
+    }
+
+    #[test]
+    fn test_attacker_account_in_each_slot_is_rejected() {
+        let mut system_state = system_state_with_vaults();
+        system_state.staking_state = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+
+        for slot in 0..5 {
+            let mut accounts = drop_reward_accounts(&system_state);
+            accounts[slot] = attacker;
+
+            assert_invalid_account_input(verify(&system_state, &accounts));
+        }
+    }
+
+    const EPOCH: i64 = 604_800;
+    const FIRST_DROP: i64 = 1_650_000_000;
+
+    #[test]
+    fn test_no_minimum_drops_every_epoch() {
+        let mut staking_state = StakingState::default();
+        assert!(!is_below_min_drop(&staking_state, 0));
+
+        staking_state.min_drop_amount = 500;
+        assert!(is_below_min_drop(&staking_state, 499));
+        assert!(!is_below_min_drop(&staking_state, 500));
+    }
+
+    #[test]
+    fn test_early_drop_is_rejected() {
+        let mut staking_state = StakingState::default();
+        // The first drop can run at any time
+        assert!(check_drop_due(&staking_state, EPOCH, FIRST_DROP).is_ok());
+
+        staking_state.last_drop_timestamp = FIRST_DROP;
+        assert!(matches!(
+            check_drop_due(&staking_state, EPOCH, FIRST_DROP + EPOCH - 1).unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::EarlyRewardDrop, .. }
+        ));
+        assert!(check_drop_due(&staking_state, EPOCH, FIRST_DROP + EPOCH).is_ok());
+
+        staking_state.last_drop_timestamp = i64::MAX;
+        assert!(check_drop_due(&staking_state, EPOCH, FIRST_DROP).is_err());
+    }
+
+    #[test]
+    fn test_swapped_coffer_and_rewards_vault_are_rejected() {
+        let mut system_state = system_state_with_vaults();
+        system_state.staking_state = Pubkey::new_unique();
+
+        let mut accounts = drop_reward_accounts(&system_state);
+        accounts.swap(2, 3);
+
+        assert_invalid_account_input(verify(&system_state, &accounts));
+    }
+}
\ No newline at end of file
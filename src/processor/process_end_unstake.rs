@@ -3,6 +3,7 @@ use std::cell::{Ref, RefMut};
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     sysvar::{clock::Clock, Sysvar},
     pubkey::Pubkey,
 };
@@ -14,6 +15,9 @@ use crate::{
         SourceFileId,
     },
     helpers::account::*,
+    helpers::unstake_timelock::check_claimable,
+    idl,
+    instruction::Instruction,
     state::staking::{
         PendingWithdrawal,
         StakeBalance,
@@ -23,21 +27,41 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::EndUnstake);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::EndUnstake { } => {
+            msg!("Instruction: End Unstake");
+            process_end_unstake(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const END_UNSTAKE_SIZE: usize = idl::END_UNSTAKE.len();
+
 #[inline(never)]
 pub fn process_end_unstake(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
-    const NUM_FIXED: usize = 9;
-    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    const NUM_FIXED: usize = END_UNSTAKE_SIZE;
+    let fixed_accounts = array_ref![accounts, 0, NUM_FIXED - 1];
     let [
         system_state_ai,        // read
         pending_withdrawal_ai,  // write
         stake_balance_ai,       // read
         pending_vault_ai,       // write
         deposit_vault_ai,       // write
-        owner_ai,               // read
+        owner_ai,               // write
         transfer_authority_ai,  // read
-        sol_account_ai,         // write
-        token_program_ai,       // read
-    ] = accounts;
+    ] = fixed_accounts;
+    // The old layout had a separate account for the pending withdrawal's rent ahead of the token program.
+    // It's still accepted as long as it's the owner, who gets the rent in the current layout.
+    let (rent_receiver_ai, token_program_ai) = if accounts.len() > NUM_FIXED {
+        (&accounts[NUM_FIXED - 1], &accounts[NUM_FIXED])
+    } else {
+        (owner_ai, &accounts[NUM_FIXED - 1])
+    };
+    check_eq!(rent_receiver_ai.key, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
 
     let clock = &Clock::get()?;
 
@@ -50,16 +74,20 @@ pub fn process_end_unstake(program_id: &Pubkey, accounts: &[AccountInfo]) -> Luc
     check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
-    check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+    // Stakers can always leave during settlement
+    if !system_state.settlement_active {
+        check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+    }
 
     let mut pending_withdrawal: RefMut<PendingWithdrawal> = PendingWithdrawal::load_mut_checked(pending_withdrawal_ai, program_id)?;
     check!(!pending_withdrawal.closed(), LucraErrorCode::InvalidAccountInput)?;
     let stake_balance: Ref<StakeBalance> = StakeBalance::load_checked(stake_balance_ai, program_id)?;
 
     check_eq!(&stake_balance.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&stake_balance.owner, sol_account_ai.key, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(&pending_withdrawal.stake_balance, stake_balance_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check!(pending_withdrawal.end_timestamp <= clock.unix_timestamp, LucraErrorCode::Timelock)?;
+    if !system_state.settlement_active {
+        check_claimable(&pending_withdrawal, clock.unix_timestamp)?;
+    }
 
     check_eq!(&stake_balance.balances.deposit_vault, deposit_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&stake_balance.balances.pending_vault, pending_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
@@ -76,7 +104,7 @@ pub fn process_end_unstake(program_id: &Pubkey, accounts: &[AccountInfo]) -> Luc
 
     pending_withdrawal.close();
     let lamports = close_account(pending_withdrawal_ai);
-    add_lamports(sol_account_ai, lamports);
+    add_lamports(owner_ai, lamports);
 
     Ok(())
 }
\ No newline at end of file
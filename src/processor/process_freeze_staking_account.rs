@@ -0,0 +1,56 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::staking_freeze::{freeze_staking_account, is_recovery_key},
+    instruction::Instruction,
+    state::staking::StakingAccount,
+};
+
+declare_check_assert_macros!(SourceFileId::FreezeStakingAccount);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::FreezeStakingAccount { } => {
+            msg!("Instruction: Freeze Staking Account");
+            process_freeze_staking_account(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Freezing only stops the account's own stake, unstake, withdraw and claim so it stays open during settlement
+#[inline(never)]
+pub fn process_freeze_staking_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 2;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        staking_account_ai,             // write
+        authority_ai,                   // read
+    ] = accounts;
+
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    let mut staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(staking_account_ai, program_id)?;
+    check!(
+        &staking_account.owner == authority_ai.key || is_recovery_key(&staking_account, authority_ai.key),
+        LucraErrorCode::InvalidAccountInput
+    )?;
+
+    freeze_staking_account(&mut staking_account);
+
+    Ok(())
+}
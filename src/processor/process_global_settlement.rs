@@ -0,0 +1,67 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        dao_authority::check_dao_authority,
+        settlement::check_not_settled,
+    },
+    instruction::Instruction,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::GlobalSettlement);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::GlobalSettlement {
+            settlement_sol_price,
+        } => {
+            msg!("Instruction: Global Settlement");
+            process_global_settlement(program_id, settlement_sol_price, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// DAO instruction that winds the protocol down. Once set it can't be undone.
+#[inline(never)]
+pub fn process_global_settlement(program_id: &Pubkey, settlement_sol_price: u64, accounts: &[AccountInfo]) -> LucraResult {
+    check!(settlement_sol_price > 0, LucraErrorCode::InvalidAmount)?;
+
+    const NUM_FIXED: usize = 2;
+    let accounts = array_ref!(accounts, 0, NUM_FIXED);
+    let [
+        system_state_ai,    // write
+        dao_authority_ai,   // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+
+    system_state.settlement_active = true;
+    system_state.settlement_sol_price = settlement_sol_price;
+    system_state.settlement_timestamp = clock.unix_timestamp;
+
+    Ok(())
+}
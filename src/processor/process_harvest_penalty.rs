@@ -1,12 +1,17 @@
 use std::cell::RefMut;
+use std::convert::TryFrom;
 
 use anchor_lang::prelude::ProgramAccount;
 use arrayref::array_ref;
 use marinade_finance;
-use oracles::state::Oracle;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 use solana_program::{
     account_info::AccountInfo,
+    msg,
+    native_token::LAMPORTS_PER_SOL,
+    program_error::ProgramError,
     pubkey::Pubkey,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
 use crate::{
     error::{
@@ -17,42 +22,104 @@ use crate::{
         SourceFileId,
     },
     helpers::{
+        account::{verify_distinct_accounts, verify_program_account},
+        events::{emit_event, split_event_queue},
+        loan_address::verify_loan_address,
         constants::{
+            ABOVE_PEG_HARVEST_TOLERANCE,
+            LAMPORTS_PER_MATA,
+            MAX_HARVEST_SLIPPAGE,
             SOL_MATA_ORCA_AMM,
             orca_swap,
+            orca_whirlpool,
             SOL_MATA_ORACLE,
+            SOL_USDC_ORACLE,
+            SOL_USDT_ORACLE,
             serum_v3,
             raydium_v4,
             SOL_MATA_RAYDIUM_AMM,
+            wsol,
         },
-        raydium::swap as raydium_swap,
+        raydium::{swap as raydium_swap, verify_open_orders},
         spltokenswap::swap as orca_swap,
         spl::*,
-        oracle::{verify_orca_has_more_volume, verify_raydium_has_more_volume},
+        oracle::{get_mata_price, get_sol_price, verify_orca_has_more_volume, verify_raydium_has_more_volume, verify_sol_usd_oracle, verify_whirlpool_has_more_volume, OracleAccount},
         solana::transfer,
-        marinade::liquid_unstake,
+        collateral::{backfill_msol_collateral, calc_harvest_msol, calc_harvestable_penalty, get_marinade_msol_rate},
+        marinade::{liquid_unstake, verify_liquid_unstake_liquidity},
+        settlement::check_not_settled,
+        sol_vault::transfer_from_sol_vault,
+        vaults::{verify_arb_coffer, verify_mata_mint, verify_msol_vault, verify_msol_vault_covers},
+        whirlpool::{load_whirlpool, swap as whirlpool_swap, verify_whirlpool_has_proper_mints},
     },
+    idl,
+    instruction::Instruction,
+    lucra_core::{interest::move_interest_to_harvest, penalty::calc_harvestable_native_penalty},
     state::{
         AmmTypes,
+        EventKind,
+        LoanType,
         MataLoan,
+        RevenueLedger,
         SystemState,
     },
+    units::{Bps, Lamports},
 };
 
 declare_check_assert_macros!(SourceFileId::HarvestPenalty);
 
 #[inline(never)]
-pub fn process_harvest_penalty(program_id: &Pubkey, amm_type: AmmTypes, accounts: &[AccountInfo]) -> LucraResult {
-    match amm_type {
-        AmmTypes::None => Err(throw_err!(LucraErrorCode::NotImplemented)),
-        AmmTypes::Orca => process_harvest_penalty_orca(program_id, accounts),
-        AmmTypes::Raydium => process_harvest_penalty_raydium(program_id, accounts),
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::HarvestPenalty { 
+            amm_type,
+            min_mata_out,
+        } => {
+            msg!("Instruction: Harvest Penalty");
+            let amm_type = AmmTypes::try_from(amm_type).map_err(|_| ProgramError::InvalidInstructionData)?;
+            process_harvest_penalty(program_id, amm_type, min_mata_out, accounts)
+        }
+        _ => unreachable!(),
     }
 }
 
+const HARVEST_PENALTY_NATIVE_SOL_WITH_ORCA_SIZE: usize = idl::HARVEST_PENALTY_NATIVE_SOL_WITH_ORCA.len();
+const HARVEST_PENALTY_DIRECT_SIZE: usize = idl::HARVEST_PENALTY_DIRECT.len();
+const HARVEST_PENALTY_WITH_WHIRLPOOL_SIZE: usize = idl::HARVEST_PENALTY_WITH_WHIRLPOOL.len();
+
 #[inline(never)]
-pub fn process_harvest_penalty_orca(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
-    const NUM_FIXED: usize = 25;
+pub fn process_harvest_penalty(program_id: &Pubkey, amm_type: AmmTypes, min_mata_out: u64, accounts: &[AccountInfo]) -> LucraResult {
+    // The revenue ledger is read by position, so the event queue has to come off first
+    let (accounts, event_queue_ai) = split_event_queue(program_id, accounts);
+    // The native SOL layout has the program owned sol vault where the marinade state would be
+    let native_sol = accounts.len() > 1 && accounts[1].owner == program_id;
+    let amounts = match amm_type {
+        AmmTypes::None if native_sol => return Err(throw_err!(LucraErrorCode::NotImplemented)),
+        AmmTypes::None => process_harvest_penalty_direct(program_id, min_mata_out, accounts)?,
+        AmmTypes::Orca if native_sol => process_harvest_native_sol_penalty_orca(program_id, min_mata_out, accounts)?,
+        AmmTypes::Raydium if native_sol => return Err(throw_err!(LucraErrorCode::NotImplemented)),
+        AmmTypes::Orca => process_harvest_penalty_orca(program_id, min_mata_out, accounts)?,
+        AmmTypes::Raydium => process_harvest_penalty_raydium(program_id, min_mata_out, accounts)?,
+        AmmTypes::Whirlpool if native_sol => return Err(throw_err!(LucraErrorCode::NotImplemented)),
+        AmmTypes::Whirlpool => process_harvest_penalty_whirlpool(program_id, min_mata_out, accounts)?,
+    };
+
+    // Every layout starts with the system state and has the loan sixth
+    emit_event(
+        program_id,
+        event_queue_ai,
+        accounts[0].key,
+        EventKind::HarvestPenalty,
+        accounts[5].key,
+        amounts,
+        Clock::get()?.slot,
+    )
+}
+
+#[inline(never)]
+pub fn process_harvest_penalty_orca(program_id: &Pubkey, min_mata_out: u64, accounts: &[AccountInfo]) -> LucraResult<[u64; 2]> {
+    const NUM_FIXED: usize = 28;
+    let revenue_ledger_ai = accounts.get(NUM_FIXED);
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,            // write
@@ -63,6 +130,9 @@ pub fn process_harvest_penalty_orca(program_id: &Pubkey, accounts: &[AccountInfo
         
         loan_ai,                    // write
         sol_mata_oracle_ai,         // read
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        arb_coffer_ai,              // write
 
         user_account_ai,            // write
         user_wsol_account_ai,       // write
@@ -96,38 +166,85 @@ pub fn process_harvest_penalty_orca(program_id: &Pubkey, accounts: &[AccountInfo
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(arb_coffer_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
 
     check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(marinade_program_ai.key, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
 
     check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(mata_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(user_wsol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_wsol_account(user_wsol_account_ai)?;
     check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
 
     // Verify accounts against expectations
     check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
     check_eq!(sm_amm_ai.key, &SOL_MATA_ORCA_AMM, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(token_swap_program_ai.key, &orca_swap::id(), LucraErrorCode::InvalidAccountInput)?;
-
-    let sol_mata_oracle = Oracle::load_checked(sol_mata_oracle_ai, &oracles::id()).unwrap();
-    verify_orca_has_more_volume(&sol_mata_oracle, &SOL_MATA_RAYDIUM_AMM, &SOL_MATA_ORCA_AMM)?;
+    verify_program_account(token_swap_program_ai, &orca_swap::id())?;
+    verify_harvest_accounts_distinct(msol_vault_ai.key, arb_coffer_ai.key, user_account_ai.key, user_wsol_account_ai.key, user_mata_account_ai.key, user_msol_account_ai.key)?;
 
     let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
-    check_eq!(&system_state.mata_mint.address, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.msol_vault.address, msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_not_settled(&system_state)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+
+    let sol_mata_oracle = OracleAccount::load(&system_state, sol_mata_oracle_ai)?;
+    verify_orca_has_more_volume(&sol_mata_oracle, &SOL_MATA_RAYDIUM_AMM, &SOL_MATA_ORCA_AMM)?;
 
     let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    verify_loan_address(loan_ai.key, &loan.creator, loan.loan_index)?;
+    // LP backed loans keep their penalty as LP tokens in the LP vault, there's no msol to harvest
+    check!(!matches!(loan.loan_type, LoanType::NativeSol | LoanType::LpBacked), LucraErrorCode::InvalidLoanType)?;
     check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
 
-    if loan.penalty_to_harvest >= system_state.minimum_harvest_amount {
+    let clock = &Clock::get()?;
+    let sol_usd_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+
+    // The interest accrued is harvested with the penalty. Only harvest what the loan can still cover, whatever is over that is dropped
+    let interest_lamports = move_interest_to_harvest(&mut loan, sol_usd_price)?;
+    backfill_msol_collateral(&mut loan, &system_state, get_token_balance(msol_vault_ai)?)?;
+    let marinade_rate = get_marinade_msol_rate(marinade_state_ai)?;
+    loan.penalty_to_harvest = calc_harvestable_penalty(&loan, marinade_rate)?;
+
+    check_harvest_minimum(loan.penalty_to_harvest, system_state.minimum_harvest_amount)?;
+    let penalty_harvested = loan.penalty_to_harvest;
+
+    let mata_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    if is_above_harvest_peg(mata_price) {
+        // Buying mata above the peg would only push it further up, the coffer sells the collateral later instead
+        let sol_to_coffer = harvest_into_coffer(
+            program_id,
+            &mut system_state,
+            &mut loan,
+            msol_vault_authority_ai,
+            msol_vault_ai,
+            arb_coffer_ai,
+            user_msol_account_ai,
+            marinade_state_ai,
+            marinade_program_ai,
+            token_program_ai,
+            sol_usd_price,
+            mata_price,
+            interest_lamports,
+        )?;
+
+        if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+            record_harvest_revenue(&mut revenue_ledger, &system_state, sol_to_coffer)?;
+        }
+
+        return Ok([penalty_harvested, 0]);
+    }
+
+    {
         // There is enough penalty to harvest
         let sol_received = liquid_unstake_for_harvest(
             program_id,
             &system_state,
-            &loan,
+            &mut loan,
             msol_vault_authority_ai,
             msol_vault_ai,
             user_account_ai,
@@ -144,7 +261,157 @@ pub fn process_harvest_penalty_orca(program_id: &Pubkey, accounts: &[AccountInfo
         )?;
 
         // Swap the wrapped sol for mata
-        let user_mata_balance_before = get_token_balance(user_mata_account_ai)?;
+        let min_mata_out = calc_harvest_min_mata_out(sol_received, sol_usd_price, mata_price, min_mata_out)?;
+        let mata_to_burn = measure_swap_output(user_mata_account_ai, min_mata_out, || {
+            orca_swap(
+                token_swap_program_ai,
+                token_program_ai,
+                sm_amm_ai,
+                sm_amm_authority_ai,
+                user_account_ai,
+                user_wsol_account_ai,
+                user_mata_account_ai,
+                sm_pool_base_vault_ai,
+                sm_pool_quote_vault_ai,
+                sm_pool_mint_ai,
+                sm_pool_fees_ai,
+                &[&[&[]]],
+                sol_received,               // sol in
+                0,                          // mata in
+                min_mata_out,
+            )?;
+            Ok(())
+        })?;
+
+        // Burn the mata
+        system_state.burn_mata(
+            mata_mint_ai,
+            user_mata_account_ai,
+            mata_to_burn,
+            user_account_ai,
+            token_program_ai,
+        )?;
+
+        // Update system state values
+        system_state.mata_supply.burn_bought_back(mata_to_burn)?;
+        system_state.remove_collateral(loan.penalty_to_harvest);
+
+        // Update loan values
+        loan.update_harvested_penalty();
+
+        if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+            record_harvest_revenue(&mut revenue_ledger, &system_state, sol_received)?;
+        }
+
+        Ok([penalty_harvested, mata_to_burn])
+    }
+}
+
+// Native SOL loans have their collateral in the sol vault already, so the penalty is wrapped straight into the
+// user's wsol account with no marinade unstake. Only the orca pool is supported for now.
+#[inline(never)]
+pub fn process_harvest_native_sol_penalty_orca(program_id: &Pubkey, min_mata_out: u64, accounts: &[AccountInfo]) -> LucraResult<[u64; 2]> {
+    const NUM_FIXED: usize = HARVEST_PENALTY_NATIVE_SOL_WITH_ORCA_SIZE;
+    let revenue_ledger_ai = accounts.get(NUM_FIXED);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        sol_vault_ai,               // write
+        mata_mint_ai,               // write
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+
+        loan_ai,                    // write
+        sol_mata_oracle_ai,         // read
+
+        user_account_ai,            // write
+        user_wsol_account_ai,       // write
+        user_mata_account_ai,       // write
+
+        // Accounts required for orca swap
+        sm_amm_ai,                  // write
+        sm_amm_authority_ai,        // read
+        sm_pool_base_vault_ai,      // write
+        sm_pool_quote_vault_ai,     // write
+        sm_pool_mint_ai,            // write
+        sm_pool_fees_ai,            // write
+
+        token_swap_program_ai,      // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    // Verify the user signed the transaction
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    // Verify the accounts are owned by the right programs
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_vault_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(mata_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_wsol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_wsol_account(user_wsol_account_ai)?;
+    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+
+    // Verify accounts against expectations
+    check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    check_eq!(sm_amm_ai.key, &SOL_MATA_ORCA_AMM, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    verify_program_account(token_swap_program_ai, &orca_swap::id())?;
+    verify_distinct_accounts(&[
+        ("sol_vault", sol_vault_ai.key),
+        ("user_account", user_account_ai.key),
+        ("user_wsol_account", user_wsol_account_ai.key),
+        ("user_mata_account", user_mata_account_ai.key),
+    ])?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+
+    let sol_mata_oracle = OracleAccount::load(&system_state, sol_mata_oracle_ai)?;
+    verify_orca_has_more_volume(&sol_mata_oracle, &SOL_MATA_RAYDIUM_AMM, &SOL_MATA_ORCA_AMM)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    verify_loan_address(loan_ai.key, &loan.creator, loan.loan_index)?;
+    check_eq!(loan.loan_type, LoanType::NativeSol, LucraErrorCode::InvalidLoanType)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+
+    let clock = &Clock::get()?;
+    let rent = &Rent::get()?;
+    let sol_usd_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+
+    // The lamports are the collateral, so the loan can cover as much as is left of them
+    move_interest_to_harvest(&mut loan, sol_usd_price)?;
+    loan.penalty_to_harvest = calc_harvestable_native_penalty(&loan);
+
+    check_harvest_minimum(loan.penalty_to_harvest, system_state.minimum_harvest_amount)?;
+    let penalty_harvested = loan.penalty_to_harvest;
+
+    let mata_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    // The coffer holds msol, there's nothing to sell native collateral into it with
+    check!(!is_above_harvest_peg(mata_price), LucraErrorCode::NotImplemented)?;
+
+    // Leave a fee of the lamports as payment to the user for running the contract, the rest is wrapped
+    let reward_fee = system_state.reward_fee.to_lamports().get();
+    let sol_to_swap = loan.penalty_to_harvest
+        .checked_sub(reward_fee)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    transfer_from_sol_vault(program_id, &system_state, sol_vault_ai, user_account_ai, reward_fee, rent)?;
+    transfer_from_sol_vault(program_id, &system_state, sol_vault_ai, user_wsol_account_ai, sol_to_swap, rent)?;
+
+    // Sync native the sol to get wrapped sol
+    sync_native(
+        user_wsol_account_ai,
+        &[],
+        token_program_ai,
+    )?;
+
+    // Swap the wrapped sol for mata
+    let min_mata_out = calc_harvest_min_mata_out(sol_to_swap, sol_usd_price, mata_price, min_mata_out)?;
+    let mata_to_burn = measure_swap_output(user_mata_account_ai, min_mata_out, || {
         orca_swap(
             token_swap_program_ai,
             token_program_ai,
@@ -158,38 +425,37 @@ pub fn process_harvest_penalty_orca(program_id: &Pubkey, accounts: &[AccountInfo
             sm_pool_mint_ai,
             sm_pool_fees_ai,
             &[&[&[]]],
-            sol_received,               // sol in
+            sol_to_swap,                // sol in
             0,                          // mata in
+            min_mata_out,
         )?;
-        let user_mata_balance_after = get_token_balance(user_mata_account_ai)?;
-        let mata_to_burn = user_mata_balance_after - user_mata_balance_before;
+        Ok(())
+    })?;
 
-        // Burn the mata
-        system_state.burn_mata(
-            mata_mint_ai,
-            user_mata_account_ai,
-            mata_to_burn,
-            user_account_ai,
-            token_program_ai,
-        )?;
+    // Burn the mata
+    system_state.burn_mata(
+        mata_mint_ai,
+        user_mata_account_ai,
+        mata_to_burn,
+        user_account_ai,
+        token_program_ai,
+    )?;
 
-        // Update system state values
-        system_state.remove_outstanding_mata(mata_to_burn);
-        system_state.remove_collateral(loan.penalty_to_harvest);
+    // Update supply and loan values, native collateral was never counted in total_sol_collateral
+    system_state.mata_supply.burn_bought_back(mata_to_burn)?;
+    loan.update_harvested_penalty();
 
-        // Update loan values
-        loan.update_harvested_penalty();
-    } else {
-        // Not enough penalty to harvest
-        return Err(throw_err!(LucraErrorCode::NoPenaltyToHarvest));
+    if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+        record_harvest_revenue(&mut revenue_ledger, &system_state, sol_to_swap)?;
     }
 
-    Ok(())
+    Ok([penalty_harvested, mata_to_burn])
 }
 
 #[inline(never)]
-pub fn process_harvest_penalty_raydium(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
-    const NUM_FIXED: usize = 33;
+pub fn process_harvest_penalty_raydium(program_id: &Pubkey, min_mata_out: u64, accounts: &[AccountInfo]) -> LucraResult<[u64; 2]> {
+    const NUM_FIXED: usize = 36;
+    let revenue_ledger_ai = accounts.get(NUM_FIXED);
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,                // write
@@ -199,6 +465,9 @@ pub fn process_harvest_penalty_raydium(program_id: &Pubkey, accounts: &[AccountI
         mata_mint_ai,                   // write
         loan_ai,                        // write
         sol_mata_oracle_ai,             // read
+        sol_usdc_oracle_ai,             // read
+        sol_usdt_oracle_ai,             // read
+        arb_coffer_ai,                  // write
 
         user_msol_account_ai,           // write
 
@@ -218,10 +487,10 @@ pub fn process_harvest_penalty_raydium(program_id: &Pubkey, accounts: &[AccountI
         _pool_mata_account_ai,          // write
         token_program_ai,               // read
         amm_program_ai,                 // write
-        _amm_authority_ai,              // read
-        _amm_open_orders_ai,            // write
+        amm_authority_ai,               // read
+        amm_open_orders_ai,             // write
         _amm_target_ai,                 // read
-        _serum_sol_mata_market_ai,      // write
+        serum_sol_mata_market_ai,       // write
         serum_program_ai,               // read
         _serum_bids_ai,                 // write
         _serum_asks_ai,                 // write
@@ -238,40 +507,88 @@ pub fn process_harvest_penalty_raydium(program_id: &Pubkey, accounts: &[AccountI
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(arb_coffer_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
 
     check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(marinade_program_ai.key, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
 
     check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(mata_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(user_wsol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_wsol_account(user_wsol_account_ai)?;
     check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
 
     // Verify accounts against expectations
     check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
     check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(serum_program_ai.key, &serum_v3::id(), LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(pool_program_ai.key, &raydium_v4::id(), LucraErrorCode::InvalidAccountInput)?;
+    verify_program_account(serum_program_ai, &serum_v3::id())?;
+    verify_program_account(pool_program_ai, &raydium_v4::id())?;
     check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
     check_eq!(amm_program_ai.key, &SOL_MATA_RAYDIUM_AMM, LucraErrorCode::InvalidAccountInput)?;
-
-    let sol_mata_oracle = Oracle::load_checked(sol_mata_oracle_ai, &oracles::id()).unwrap();
-    verify_raydium_has_more_volume(&sol_mata_oracle, &SOL_MATA_RAYDIUM_AMM, &SOL_MATA_ORCA_AMM)?;
+    verify_open_orders(amm_open_orders_ai, serum_sol_mata_market_ai, amm_authority_ai.key)?;
+    verify_harvest_accounts_distinct(msol_vault_ai.key, arb_coffer_ai.key, user_account_ai.key, user_wsol_account_ai.key, user_mata_account_ai.key, user_msol_account_ai.key)?;
 
     let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
-    check_eq!(&system_state.mata_mint.address, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&system_state.msol_vault.address, msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_not_settled(&system_state)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+
+    let sol_mata_oracle = OracleAccount::load(&system_state, sol_mata_oracle_ai)?;
+    verify_raydium_has_more_volume(&sol_mata_oracle, &SOL_MATA_RAYDIUM_AMM, &SOL_MATA_ORCA_AMM)?;
 
     let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    verify_loan_address(loan_ai.key, &loan.creator, loan.loan_index)?;
+    // LP backed loans keep their penalty as LP tokens in the LP vault, there's no msol to harvest
+    check!(!matches!(loan.loan_type, LoanType::NativeSol | LoanType::LpBacked), LucraErrorCode::InvalidLoanType)?;
     check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
 
-    if loan.penalty_to_harvest >= system_state.minimum_harvest_amount {
+    let clock = &Clock::get()?;
+    let sol_usd_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+
+    // The interest accrued is harvested with the penalty. Only harvest what the loan can still cover, whatever is over that is dropped
+    let interest_lamports = move_interest_to_harvest(&mut loan, sol_usd_price)?;
+    backfill_msol_collateral(&mut loan, &system_state, get_token_balance(msol_vault_ai)?)?;
+    let marinade_rate = get_marinade_msol_rate(marinade_state_ai)?;
+    loan.penalty_to_harvest = calc_harvestable_penalty(&loan, marinade_rate)?;
+
+    check_harvest_minimum(loan.penalty_to_harvest, system_state.minimum_harvest_amount)?;
+    let penalty_harvested = loan.penalty_to_harvest;
+
+    let mata_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    if is_above_harvest_peg(mata_price) {
+        // Buying mata above the peg would only push it further up, the coffer sells the collateral later instead
+        let sol_to_coffer = harvest_into_coffer(
+            program_id,
+            &mut system_state,
+            &mut loan,
+            msol_vault_authority_ai,
+            msol_vault_ai,
+            arb_coffer_ai,
+            user_msol_account_ai,
+            marinade_state_ai,
+            marinade_program_ai,
+            token_program_ai,
+            sol_usd_price,
+            mata_price,
+            interest_lamports,
+        )?;
+
+        if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+            record_harvest_revenue(&mut revenue_ledger, &system_state, sol_to_coffer)?;
+        }
+
+        return Ok([penalty_harvested, 0]);
+    }
+
+    {
         // There is enough penalty to harvest
         let sol_received = liquid_unstake_for_harvest(
             program_id,
             &system_state,
-            &loan,
+            &mut loan,
             msol_vault_authority_ai,
             msol_vault_ai,
             user_account_ai,
@@ -288,15 +605,17 @@ pub fn process_harvest_penalty_raydium(program_id: &Pubkey, accounts: &[AccountI
         )?;
 
         // Swap the wrapped sol for mata
-        let user_mata_balance_before = get_token_balance(user_mata_account_ai)?;
+        let min_mata_out = calc_harvest_min_mata_out(sol_received, sol_usd_price, mata_price, min_mata_out)?;
         let accounts = array_ref![accounts, NUM_FIXED - 19, 19];
-        raydium_swap(
-            accounts,
-            sol_received,       // sol in
-            0,                  // mata in
-        )?;
-        let user_mata_balance_after = get_token_balance(user_mata_account_ai)?;
-        let mata_to_burn = user_mata_balance_after - user_mata_balance_before;
+        let mata_to_burn = measure_swap_output(user_mata_account_ai, min_mata_out, || {
+            raydium_swap(
+                accounts,
+                sol_received,       // sol in
+                0,                  // mata in
+                min_mata_out,
+            )?;
+            Ok(())
+        })?;
 
         // Burn the mata
         system_state.burn_mata(
@@ -308,16 +627,528 @@ pub fn process_harvest_penalty_raydium(program_id: &Pubkey, accounts: &[AccountI
         )?;
 
         // Update system state values
-        system_state.remove_outstanding_mata(mata_to_burn);
+        system_state.mata_supply.burn_bought_back(mata_to_burn)?;
         system_state.remove_collateral(loan.penalty_to_harvest);
 
         // Update loan values
         loan.update_harvested_penalty();
+
+        if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+            record_harvest_revenue(&mut revenue_ledger, &system_state, sol_received)?;
+        }
+
+        Ok([penalty_harvested, mata_to_burn])
+    }
+}
+
+// Whirlpools are permissionless, so the pool is the one the DAO registered rather than any SOL/MATA whirlpool. The
+// user's wsol and mata accounts are passed to the swap in the pool's mint order.
+#[inline(never)]
+pub fn process_harvest_penalty_whirlpool(program_id: &Pubkey, min_mata_out: u64, accounts: &[AccountInfo]) -> LucraResult<[u64; 2]> {
+    const NUM_FIXED: usize = HARVEST_PENALTY_WITH_WHIRLPOOL_SIZE;
+    let revenue_ledger_ai = accounts.get(NUM_FIXED);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        marinade_state_ai,          // write
+        msol_vault_ai,              // write
+        msol_vault_authority_ai,    // read
+        mata_mint_ai,               // write
+
+        loan_ai,                    // write
+        sol_mata_oracle_ai,         // read
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        arb_coffer_ai,              // write
+
+        user_account_ai,            // write
+        user_wsol_account_ai,       // write
+        user_mata_account_ai,       // write
+        user_msol_account_ai,       // write
+
+        // Accounts required for msol unstake
+        msol_mint_ai,               // write
+        liq_pool_sol_leg_pda_ai,    // write
+        liq_pool_msol_leg_ai,       // write
+        treasury_msol_account_ai,   // write
+        system_program_ai,          // read
+        marinade_program_ai,        // read
+
+        // Accounts required for the whirlpool swap
+        whirlpool_ai,               // write
+        whirlpool_vault_a_ai,       // write
+        whirlpool_vault_b_ai,       // write
+        tick_array_0_ai,            // write
+        tick_array_1_ai,            // write
+        tick_array_2_ai,            // write
+        whirlpool_oracle_ai,        // read
+
+        whirlpool_program_ai,       // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    // Verify the user signed the transaction
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    // Verify the accounts are owned by the right programs
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(arb_coffer_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+
+    check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
+
+    check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(mata_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_wsol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_wsol_account(user_wsol_account_ai)?;
+    check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+
+    // Verify accounts against expectations
+    check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    verify_program_account(whirlpool_program_ai, &orca_whirlpool::id())?;
+    verify_harvest_accounts_distinct(msol_vault_ai.key, arb_coffer_ai.key, user_account_ai.key, user_wsol_account_ai.key, user_mata_account_ai.key, user_msol_account_ai.key)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+    check!(system_state.sol_mata_whirlpool != Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(whirlpool_ai.key, &system_state.sol_mata_whirlpool, LucraErrorCode::InvalidAccountInput)?;
+
+    let whirlpool = load_whirlpool(whirlpool_ai)?;
+    let sol_is_a = verify_whirlpool_has_proper_mints(&whirlpool, &wsol::id(), mata_mint_ai.key)?;
+
+    let sol_mata_oracle = OracleAccount::load(&system_state, sol_mata_oracle_ai)?;
+    verify_whirlpool_has_more_volume(&sol_mata_oracle, whirlpool_ai.key, &SOL_MATA_RAYDIUM_AMM, &SOL_MATA_ORCA_AMM)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    verify_loan_address(loan_ai.key, &loan.creator, loan.loan_index)?;
+    // LP backed loans keep their penalty as LP tokens in the LP vault, there's no msol to harvest
+    check!(!matches!(loan.loan_type, LoanType::NativeSol | LoanType::LpBacked), LucraErrorCode::InvalidLoanType)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+
+    let clock = &Clock::get()?;
+    let sol_usd_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+
+    // The interest accrued is harvested with the penalty. Only harvest what the loan can still cover, whatever is over that is dropped
+    let interest_lamports = move_interest_to_harvest(&mut loan, sol_usd_price)?;
+    backfill_msol_collateral(&mut loan, &system_state, get_token_balance(msol_vault_ai)?)?;
+    let marinade_rate = get_marinade_msol_rate(marinade_state_ai)?;
+    loan.penalty_to_harvest = calc_harvestable_penalty(&loan, marinade_rate)?;
+
+    check_harvest_minimum(loan.penalty_to_harvest, system_state.minimum_harvest_amount)?;
+    let penalty_harvested = loan.penalty_to_harvest;
+
+    let mata_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    if is_above_harvest_peg(mata_price) {
+        // Buying mata above the peg would only push it further up, the coffer sells the collateral later instead
+        let sol_to_coffer = harvest_into_coffer(
+            program_id,
+            &mut system_state,
+            &mut loan,
+            msol_vault_authority_ai,
+            msol_vault_ai,
+            arb_coffer_ai,
+            user_msol_account_ai,
+            marinade_state_ai,
+            marinade_program_ai,
+            token_program_ai,
+            sol_usd_price,
+            mata_price,
+            interest_lamports,
+        )?;
+
+        if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+            record_harvest_revenue(&mut revenue_ledger, &system_state, sol_to_coffer)?;
+        }
+
+        return Ok([penalty_harvested, 0]);
+    }
+
+    let sol_received = liquid_unstake_for_harvest(
+        program_id,
+        &system_state,
+        &mut loan,
+        msol_vault_authority_ai,
+        msol_vault_ai,
+        user_account_ai,
+        user_msol_account_ai,
+        user_wsol_account_ai,
+        marinade_state_ai,
+        msol_mint_ai,
+        liq_pool_sol_leg_pda_ai,
+        liq_pool_msol_leg_ai,
+        treasury_msol_account_ai,
+        marinade_program_ai,
+        system_program_ai,
+        token_program_ai,
+    )?;
+
+    // Swap the wrapped sol for mata
+    let (user_account_a_ai, user_account_b_ai) = if sol_is_a {
+        (user_wsol_account_ai, user_mata_account_ai)
     } else {
-        // Not enough penalty to harvest
-        return Err(throw_err!(LucraErrorCode::NoPenaltyToHarvest));
+        (user_mata_account_ai, user_wsol_account_ai)
+    };
+    let min_mata_out = calc_harvest_min_mata_out(sol_received, sol_usd_price, mata_price, min_mata_out)?;
+    let mata_to_burn = measure_swap_output(user_mata_account_ai, min_mata_out, || {
+        whirlpool_swap(
+            whirlpool_program_ai,
+            token_program_ai,
+            user_account_ai,
+            whirlpool_ai,
+            user_account_a_ai,
+            whirlpool_vault_a_ai,
+            user_account_b_ai,
+            whirlpool_vault_b_ai,
+            tick_array_0_ai,
+            tick_array_1_ai,
+            tick_array_2_ai,
+            whirlpool_oracle_ai,
+            sol_received,               // sol in
+            sol_is_a,                   // a to b when sol is a
+            min_mata_out,
+        )
+    })?;
+
+    // Burn the mata
+    system_state.burn_mata(
+        mata_mint_ai,
+        user_mata_account_ai,
+        mata_to_burn,
+        user_account_ai,
+        token_program_ai,
+    )?;
+
+    // Update system state values
+    system_state.mata_supply.burn_bought_back(mata_to_burn)?;
+    system_state.remove_collateral(loan.penalty_to_harvest);
+
+    // Update loan values
+    loan.update_harvested_penalty();
+
+    if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+        record_harvest_revenue(&mut revenue_ledger, &system_state, sol_received)?;
+    }
+
+    Ok([penalty_harvested, mata_to_burn])
+}
+
+// With no AMM the keeper pays the penalty off with their own mata. The mata is burned and the keeper takes the
+// penalty's msol, so they're paid the reward fee as the difference between the two.
+#[inline(never)]
+pub fn process_harvest_penalty_direct(program_id: &Pubkey, max_mata_in: u64, accounts: &[AccountInfo]) -> LucraResult<[u64; 2]> {
+    const NUM_FIXED: usize = HARVEST_PENALTY_DIRECT_SIZE;
+    let revenue_ledger_ai = accounts.get(NUM_FIXED);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        marinade_state_ai,          // read
+        msol_vault_ai,              // write
+        msol_vault_authority_ai,    // read
+        mata_mint_ai,               // write
+
+        loan_ai,                    // write
+        sol_mata_oracle_ai,         // read
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        arb_coffer_ai,              // write
+
+        user_account_ai,            // write
+        user_mata_account_ai,       // write
+        user_msol_account_ai,       // write
+
+        marinade_program_ai,        // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    // Verify the user signed the transaction
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    // Verify the accounts are owned by the right programs
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(arb_coffer_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+
+    check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
+
+    check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(mata_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+
+    // Verify accounts against expectations
+    check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    verify_distinct_accounts(&[
+        ("msol_vault", msol_vault_ai.key),
+        ("arb_coffer", arb_coffer_ai.key),
+        ("user_account", user_account_ai.key),
+        ("user_mata_account", user_mata_account_ai.key),
+        ("user_msol_account", user_msol_account_ai.key),
+    ])?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    verify_loan_address(loan_ai.key, &loan.creator, loan.loan_index)?;
+    // LP backed loans keep their penalty as LP tokens in the LP vault, there's no msol to harvest
+    check!(!matches!(loan.loan_type, LoanType::NativeSol | LoanType::LpBacked), LucraErrorCode::InvalidLoanType)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+
+    let clock = &Clock::get()?;
+    let sol_usd_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+
+    // The interest accrued is harvested with the penalty. Only harvest what the loan can still cover, whatever is over that is dropped
+    let interest_lamports = move_interest_to_harvest(&mut loan, sol_usd_price)?;
+    backfill_msol_collateral(&mut loan, &system_state, get_token_balance(msol_vault_ai)?)?;
+    let marinade_rate = get_marinade_msol_rate(marinade_state_ai)?;
+    loan.penalty_to_harvest = calc_harvestable_penalty(&loan, marinade_rate)?;
+
+    check_harvest_minimum(loan.penalty_to_harvest, system_state.minimum_harvest_amount)?;
+    let penalty_harvested = loan.penalty_to_harvest;
+
+    let mata_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    if is_above_harvest_peg(mata_price) {
+        // Burning the keeper's mata would push it further over the peg, the coffer sells the collateral later instead
+        let sol_to_coffer = harvest_into_coffer(
+            program_id,
+            &mut system_state,
+            &mut loan,
+            msol_vault_authority_ai,
+            msol_vault_ai,
+            arb_coffer_ai,
+            user_msol_account_ai,
+            marinade_state_ai,
+            marinade_program_ai,
+            token_program_ai,
+            sol_usd_price,
+            mata_price,
+            interest_lamports,
+        )?;
+
+        if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+            record_harvest_revenue(&mut revenue_ledger, &system_state, sol_to_coffer)?;
+        }
+
+        return Ok([penalty_harvested, 0]);
     }
 
+    // The keeper pays for everything but the reward fee they keep
+    let sol_paid_for = loan.penalty_to_harvest
+        .checked_sub(system_state.reward_fee.to_lamports().get())
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    let mata_to_burn = calc_direct_harvest_mata(sol_paid_for, sol_usd_price, mata_price)?;
+    check!(max_mata_in == 0 || mata_to_burn <= max_mata_in, LucraErrorCode::SlippageExceeded)?;
+
+    system_state.burn_mata(
+        mata_mint_ai,
+        user_mata_account_ai,
+        mata_to_burn,
+        user_account_ai,
+        token_program_ai,
+    )?;
+
+    let state = ProgramAccount::<marinade_finance::state::State>::try_from(marinade_program_ai.clone().key, &marinade_state_ai.clone())
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    let msol_to_take = calc_harvest_msol(state.calc_msol_from_lamports(loan.penalty_to_harvest).map_err(|_| math_err!())?, &loan);
+    verify_msol_vault_covers(get_token_balance(msol_vault_ai)?, msol_to_take)?;
+    system_state.transfer_from_msol_vault(
+        program_id,
+        msol_vault_ai,
+        user_msol_account_ai,
+        msol_vault_authority_ai,
+        token_program_ai,
+        msol_to_take,
+    )?;
+
+    // Update system state values
+    system_state.mata_supply.burn_bought_back(mata_to_burn)?;
+    system_state.remove_collateral(loan.penalty_to_harvest);
+
+    // Update loan values
+    loan.msol_collateral_amount = loan.msol_collateral_amount
+        .checked_sub(msol_to_take)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    loan.update_harvested_penalty();
+
+    if let Some(mut revenue_ledger) = RevenueLedger::load_optional_mut(revenue_ledger_ai, &system_state, program_id)? {
+        record_harvest_revenue(&mut revenue_ledger, &system_state, sol_paid_for)?;
+    }
+
+    Ok([penalty_harvested, mata_to_burn])
+}
+
+/// The swap output is measured on the user's mata account and the unstake on the user's own lamports, neither
+/// can double as a vault or another of the user's accounts
+fn verify_harvest_accounts_distinct(
+    msol_vault: &Pubkey,
+    arb_coffer: &Pubkey,
+    user_account: &Pubkey,
+    user_wsol_account: &Pubkey,
+    user_mata_account: &Pubkey,
+    user_msol_account: &Pubkey,
+) -> LucraResult {
+    verify_distinct_accounts(&[
+        ("msol_vault", msol_vault),
+        ("arb_coffer", arb_coffer),
+        ("user_account", user_account),
+        ("user_wsol_account", user_wsol_account),
+        ("user_mata_account", user_mata_account),
+        ("user_msol_account", user_msol_account),
+    ])
+}
+
+/// Mata trading over the peg means the market wants more of it, so harvested collateral is held back instead of buying mata
+pub fn is_above_harvest_peg(mata_price: Decimal) -> bool {
+    mata_price >= Decimal::ONE + ABOVE_PEG_HARVEST_TOLERANCE
+}
+
+/// Mata the loan is paid down by when its penalty goes to the coffer, valued at what the coffer can buy back at today's price
+pub fn calc_coffer_harvest_loan_reduction(lamports: u64, sol_usd_price: Decimal, mata_price: Decimal, loan_amount: u64) -> LucraResult<u64> {
+    let mata = calc_oracle_harvest_mata(lamports, sol_usd_price, mata_price)?;
+
+    Ok(mata.min(loan_amount))
+}
+
+/// Least mata a harvest swap of `lamports` can pay. The keeper's `min_mata_out` can only raise the floor set
+/// from the oracle prices, the keeper is whoever cranks and a 0 would let the swap be sandwiched for free.
+pub fn calc_harvest_min_mata_out(lamports: u64, sol_usd_price: Decimal, mata_price: Decimal, min_mata_out: u64) -> LucraResult<u64> {
+    let oracle_mata = calc_oracle_harvest_mata(lamports, sol_usd_price, mata_price)?;
+    let floor = u128::from(oracle_mata)
+        .checked_mul(u128::from(Bps::ONE_HUNDRED_PERCENT.get() - MAX_HARVEST_SLIPPAGE.get()))
+        .ok_or(math_err!())?
+        / u128::from(Bps::ONE_HUNDRED_PERCENT.get());
+    let floor = u64::try_from(floor).map_err(|_| math_err!())?;
+
+    Ok(floor.max(min_mata_out))
+}
+
+/// Mata `lamports` buys at the oracle prices, rounded down
+fn calc_oracle_harvest_mata(lamports: u64, sol_usd_price: Decimal, mata_price: Decimal) -> LucraResult<u64> {
+    Decimal::from(lamports)
+        .checked_mul(sol_usd_price)
+        .ok_or(math_err!())?
+        .checked_div(Decimal::from(LAMPORTS_PER_SOL))
+        .ok_or(math_err!())?
+        .checked_div(mata_price)
+        .ok_or(math_err!())?
+        .checked_mul(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+/// A harvest under the system state's minimum isn't worth the fee it pays the caller
+pub fn check_harvest_minimum(penalty_to_harvest: u64, minimum_harvest_amount: Lamports) -> LucraResult {
+    check!(Lamports(penalty_to_harvest) >= minimum_harvest_amount, LucraErrorCode::NoPenaltyToHarvest)
+}
+
+/// Mata a keeper burns to take `lamports` of collateral in a direct harvest. Rounded up so the keeper never
+/// pays less than the collateral is worth at the oracle's mata price.
+pub fn calc_direct_harvest_mata(lamports: u64, sol_usd_price: Decimal, mata_price: Decimal) -> LucraResult<u64> {
+    Decimal::from(lamports)
+        .checked_mul(sol_usd_price)
+        .ok_or(math_err!())?
+        .checked_div(Decimal::from(LAMPORTS_PER_SOL))
+        .ok_or(math_err!())?
+        .checked_div(mata_price)
+        .ok_or(math_err!())?
+        .checked_mul(LAMPORTS_PER_MATA)
+        .ok_or(math_err!())?
+        .ceil()
+        .to_u64()
+        .ok_or(math_err!())
+}
+
+/// Moves the penalty to the arb coffer as msol. The loan is paid down now and the mata it was backed by is burned later
+/// when the coffer sells the collateral for mata.
+#[allow(clippy::too_many_arguments)]
+#[inline(never)]
+pub fn harvest_into_coffer<'a, 'b>(
+    program_id: &'a Pubkey,
+    system_state: &'a mut RefMut<SystemState>,
+    loan: &'a mut RefMut<MataLoan>,
+    msol_vault_authority_ai: &'a AccountInfo<'b>,
+    msol_vault_ai: &'a AccountInfo<'b>,
+    arb_coffer_ai: &'a AccountInfo<'b>,
+    user_msol_account_ai: &'a AccountInfo<'b>,
+    marinade_state_ai: &'a AccountInfo<'b>,
+    marinade_program_ai: &'a AccountInfo<'b>,
+    token_program_ai: &'a AccountInfo<'b>,
+    sol_usd_price: Decimal,
+    mata_price: Decimal,
+    interest_lamports: u64,
+) -> LucraResult<u64> {
+    verify_arb_coffer(system_state, arb_coffer_ai.key)?;
+
+    // Leave a fee of the msol as payment to the user for running the contract
+    let sol_to_coffer = loan.penalty_to_harvest
+        .checked_sub(system_state.reward_fee.to_lamports().get())
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+
+    let state = ProgramAccount::<marinade_finance::state::State>::try_from(marinade_program_ai.clone().key, &marinade_state_ai.clone())
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    let msol_to_take = calc_harvest_msol(state.calc_msol_from_lamports(loan.penalty_to_harvest).map_err(|_| math_err!())?, loan);
+    verify_msol_vault_covers(get_token_balance(msol_vault_ai)?, msol_to_take)?;
+    let msol_to_coffer = state.calc_msol_from_lamports(sol_to_coffer).map_err(|_| math_err!())?.min(msol_to_take);
+    let msol_fee = msol_to_take
+        .checked_sub(msol_to_coffer)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+
+    system_state.transfer_from_msol_vault(
+        program_id,
+        msol_vault_ai,
+        arb_coffer_ai,
+        msol_vault_authority_ai,
+        token_program_ai,
+        msol_to_coffer,
+    )?;
+    system_state.transfer_from_msol_vault(
+        program_id,
+        msol_vault_ai,
+        user_msol_account_ai,
+        msol_vault_authority_ai,
+        token_program_ai,
+        msol_fee,
+    )?;
+
+    // Update loan and supply values, the mata stays in circulation until the coffer buys it back.
+    // The interest in the harvest was owed on top of the debt, so it doesn't pay the loan down.
+    let loan_reduction = calc_coffer_harvest_loan_reduction(sol_to_coffer.saturating_sub(interest_lamports), sol_usd_price, mata_price, loan.loan_amount)?;
+    loan.loan_amount = loan.loan_amount
+        .checked_sub(loan_reduction)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    system_state.mata_supply.defer_burn(loan_reduction)?;
+    system_state.remove_collateral(loan.penalty_to_harvest);
+    loan.msol_collateral_amount = loan.msol_collateral_amount
+        .checked_sub(msol_to_take)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    loan.update_harvested_penalty();
+
+    Ok(sol_to_coffer)
+}
+
+fn record_harvest_revenue(revenue_ledger: &mut RevenueLedger, system_state: &SystemState, sol_received: u64) -> LucraResult {
+    let clock = &Clock::get()?;
+
+    revenue_ledger.record_penalty_captured(clock.unix_timestamp, system_state.epoch, sol_received);
+    revenue_ledger.record_keeper_fee(clock.unix_timestamp, system_state.epoch, system_state.reward_fee.to_lamports().get());
+
     Ok(())
 }
 
@@ -326,7 +1157,7 @@ pub fn process_harvest_penalty_raydium(program_id: &Pubkey, accounts: &[AccountI
 pub fn liquid_unstake_for_harvest<'a, 'b>(
     program_id: &'a Pubkey,
     system_state: &'a RefMut<SystemState>,
-    loan: &'a RefMut<MataLoan>,
+    loan: &'a mut RefMut<MataLoan>,
     msol_vault_authority_ai: &'a AccountInfo<'b>,
     msol_vault_ai: &'a AccountInfo<'b>,
     user_account_ai: &'a AccountInfo<'b>,
@@ -341,9 +1172,13 @@ pub fn liquid_unstake_for_harvest<'a, 'b>(
     system_program_ai: &'a AccountInfo<'b>,
     token_program_ai: &'a AccountInfo<'b>,
 ) -> LucraResult<u64> {
+    verify_liquid_unstake_liquidity(liq_pool_sol_leg_pda_ai, loan.penalty_to_harvest)?;
+
     // Convert the lamports to msol
-    let state = ProgramAccount::<marinade_finance::state::State>::try_from(marinade_program_ai.clone().key, &marinade_state_ai.clone()).unwrap();
-    let msol_lamports = state.calc_msol_from_lamports(loan.penalty_to_harvest).unwrap();
+    let state = ProgramAccount::<marinade_finance::state::State>::try_from(marinade_program_ai.clone().key, &marinade_state_ai.clone())
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    let msol_lamports = calc_harvest_msol(state.calc_msol_from_lamports(loan.penalty_to_harvest).map_err(|_| math_err!())?, loan);
+    verify_msol_vault_covers(get_token_balance(msol_vault_ai)?, msol_lamports)?;
     // Transfer out the msol that corresponds to that lamport value
     system_state.transfer_from_msol_vault(
         program_id,
@@ -353,30 +1188,33 @@ pub fn liquid_unstake_for_harvest<'a, 'b>(
         token_program_ai,
         msol_lamports
     )?;
+    loan.msol_collateral_amount = loan.msol_collateral_amount
+        .checked_sub(msol_lamports)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
 
     // Unstake collateral to user's sol account
-    let user_sol_balance_before = user_account_ai.lamports();
-    liquid_unstake(
-        marinade_state_ai,
-        msol_mint_ai,
-        liq_pool_sol_leg_pda_ai,
-        liq_pool_msol_leg_ai,
-        treasury_msol_account_ai,
-        user_msol_account_ai,
-        user_account_ai,
-        user_account_ai,
-        &[&[&[]]],
-        token_program_ai,
-        system_program_ai,
-        marinade_program_ai,
-        msol_lamports,
-    )?;
-    let user_sol_balance_after = user_account_ai.lamports();
-    let sol_received = user_sol_balance_after - user_sol_balance_before;
+    let sol_received = measure_lamports_change(user_account_ai, || {
+        liquid_unstake(
+            marinade_state_ai,
+            msol_mint_ai,
+            liq_pool_sol_leg_pda_ai,
+            liq_pool_msol_leg_ai,
+            treasury_msol_account_ai,
+            user_msol_account_ai,
+            user_account_ai,
+            user_account_ai,
+            &[&[&[]]],
+            token_program_ai,
+            system_program_ai,
+            marinade_program_ai,
+            msol_lamports,
+        )?;
+        Ok(())
+    })?;
 
     // Leave a fee of the wsol as payment to the user for running the contract
     let sol_received = sol_received
-        .checked_sub(system_state.reward_fee as u64)
+        .checked_sub(system_state.reward_fee.to_lamports().get())
         .ok_or(math_err!())?;
 
     // Transfer the sol to the wrapped sol account
@@ -396,4 +1234,90 @@ pub fn liquid_unstake_for_harvest<'a, 'b>(
     )?;
 
     Ok(sol_received)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::account::tests::assert_aliases_are_rejected;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_an_unknown_amm_type_is_invalid_instruction_data() {
+        let instruction = Instruction::HarvestPenalty { amm_type: u8::MAX, min_mata_out: 0 };
+        assert!(matches!(
+            dispatch(&Pubkey::new_unique(), instruction, &[]).unwrap_err(),
+            LucraError::ProgramError(ProgramError::InvalidInstructionData)
+        ));
+    }
+
+    #[test]
+    fn test_harvest_peg() {
+        assert!(!is_above_harvest_peg(dec!(0.98)));
+        assert!(!is_above_harvest_peg(dec!(1.004)));
+        assert!(is_above_harvest_peg(dec!(1.005)));
+        assert!(is_above_harvest_peg(dec!(1.02)));
+    }
+
+    #[test]
+    fn test_coffer_harvest_pays_the_loan_down_at_the_mata_price() {
+        // 2 SOL at $150 is $300, which buys back 294.117647 mata at $1.02
+        let actual = calc_coffer_harvest_loan_reduction(2 * LAMPORTS_PER_SOL, dec!(150), dec!(1.02), u64::MAX).unwrap();
+
+        assert_eq!(actual, 294_117_647);
+    }
+
+    #[test]
+    fn test_coffer_harvest_never_pays_down_more_than_the_loan() {
+        let actual = calc_coffer_harvest_loan_reduction(2 * LAMPORTS_PER_SOL, dec!(150), dec!(1.02), 100_000_000).unwrap();
+
+        assert_eq!(actual, 100_000_000);
+    }
+
+    #[test]
+    fn test_harvest_swaps_have_an_oracle_floor() {
+        // 2 SOL at $150 is 300 mata at the peg, the swap has to pay at least 98% of that
+        assert_eq!(calc_harvest_min_mata_out(2 * LAMPORTS_PER_SOL, dec!(150), dec!(1), 0).unwrap(), 294_000_000);
+        assert_eq!(calc_harvest_min_mata_out(2 * LAMPORTS_PER_SOL, dec!(150), dec!(0.98), 0).unwrap(), 299_999_999);
+
+        // The keeper can only ask for more
+        assert_eq!(calc_harvest_min_mata_out(2 * LAMPORTS_PER_SOL, dec!(150), dec!(1), 1).unwrap(), 294_000_000);
+        assert_eq!(calc_harvest_min_mata_out(2 * LAMPORTS_PER_SOL, dec!(150), dec!(1), 299_000_000).unwrap(), 299_000_000);
+        assert!(calc_harvest_min_mata_out(LAMPORTS_PER_SOL, dec!(150), Decimal::ZERO, 0).is_err());
+    }
+
+    #[test]
+    fn test_direct_harvest_is_paid_at_the_mata_price() {
+        // 2 SOL at $150 is $300, 300 mata at the peg and 306.122449 at $0.98
+        assert_eq!(calc_direct_harvest_mata(2 * LAMPORTS_PER_SOL, dec!(150), dec!(1)).unwrap(), 300_000_000);
+        assert_eq!(calc_direct_harvest_mata(2 * LAMPORTS_PER_SOL, dec!(150), dec!(0.98)).unwrap(), 306_122_449);
+    }
+
+    #[test]
+    fn test_direct_harvest_rounds_against_the_keeper() {
+        // 1 lamport at $150 is 0.00015 of a mata unit, the keeper still pays a whole one
+        assert_eq!(calc_direct_harvest_mata(1, dec!(150), dec!(1)).unwrap(), 1);
+        assert_eq!(calc_direct_harvest_mata(0, dec!(150), dec!(1)).unwrap(), 0);
+        assert!(calc_direct_harvest_mata(LAMPORTS_PER_SOL, dec!(150), Decimal::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_harvest_under_the_minimum_is_rejected() {
+        let minimum = Lamports(10_000_000);
+        assert!(check_harvest_minimum(10_000_000, minimum).is_ok());
+        assert!(check_harvest_minimum(10_000_001, minimum).is_ok());
+
+        let err = check_harvest_minimum(9_999_999, minimum).unwrap_err();
+        assert!(matches!(
+            err,
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == LucraErrorCode::NoPenaltyToHarvest
+        ));
+    }
+
+    #[test]
+    fn test_harvest_accounts_cannot_alias() {
+        assert_aliases_are_rejected(6, |keys| {
+            verify_harvest_accounts_distinct(&keys[0], &keys[1], &keys[2], &keys[3], &keys[4], &keys[5])
+        });
+    }
+}
@@ -18,10 +18,19 @@ use crate::{
         SourceFileId,
     },
     helpers::spl::*,
-    helpers::constants::{CREATOR_AUTHORITY, SOL_FEE_PLUS_INTEREST},
+    helpers::account_layout::RESERVED_LAYOUT_VERSION,
+    helpers::arb_window::refresh_arb_capacity,
+    helpers::constants::{CREATOR_AUTHORITY, DEFAULT_MAX_INSTANT_UNSTAKE, DEFAULT_REWARD_EXPIRY_EPOCHS, SOL_FEE_PLUS_INTEREST},
+    helpers::oracle::verify_oracle_limits,
+    helpers::reward_tokens::verify_reward_decimals,
+    instruction::Instruction,
+    lucra_core::pda::Pda,
+    lucra_core::penalty::verify_peg_band,
+    lucra_core::quote::verify_loan_terms,
     state::{
         ArbState, 
         Limit, 
+        MataSupply,
         StateEnum,
         DataType,
         MetaData,
@@ -29,12 +38,66 @@ use crate::{
         StateParams,
         staking::StakingState,
     },
+    units::{Bps, Lamports, RewardFee},
 };
 
 declare_check_assert_macros!(SourceFileId::Initialize);
 
 #[inline(never)]
-pub fn process_initialize(program_id: &Pubkey, state_params: &StateParams, accounts: &[AccountInfo]) -> LucraResult {
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::Initialize {
+            min_deposit,
+            collateral_requirement,
+            epoch,
+            loans_enabled,
+            staking_enabled,
+            arbitrage_enabled,
+            peg_check_enabled,
+            max_amount_of_lucra_to_mint,
+            daily_arb_limit,
+            maximum_outstanding_mata,
+            lcp,
+            min_loan_amount_mata,
+            msol_haircut_bps,
+            peg_lower_bound,
+            peg_upper_bound,
+            penalty_band_width,
+            penalty_multiplier_per_band,
+            max_oracle_staleness_slots,
+            max_oracle_deviation_bps,
+            guardian,
+        } => {
+            msg!("Instruction: Initialize");
+            let state_params = StateParams {
+                min_deposit,
+                collateral_requirement,
+                epoch,
+                loans_enabled,
+                staking_enabled,
+                arbitrage_enabled,
+                peg_check_enabled,
+                max_amount_of_lucra_to_mint,
+                daily_arb_limit,
+                maximum_outstanding_mata,
+                lcp,
+                min_loan_amount_mata,
+                msol_haircut_bps,
+                peg_lower_bound,
+                peg_upper_bound,
+                penalty_band_width,
+                penalty_multiplier_per_band,
+                max_oracle_staleness_slots,
+                max_oracle_deviation_bps,
+            };
+            process_initialize(program_id, &state_params, &guardian, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[inline(never)]
+pub fn process_initialize(program_id: &Pubkey, state_params: &StateParams, guardian: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
     const NUM_FIXED: usize = 16;
     let accounts = array_ref!(accounts, 0, NUM_FIXED);
     let [
@@ -88,7 +151,7 @@ pub fn process_initialize(program_id: &Pubkey, state_params: &StateParams, accou
     msg!("4");
     let arb_coffer_authority_bump_seed = verify_arb_coffer(system_state_ai, arb_coffer_ai)?;
     msg!("5");
-    let reward_mint_authority_bump_seed = verify_reward_mint(system_state_ai, reward_mint_ai)?;
+    let (reward_mint_authority_bump_seed, reward_mint_decimals) = verify_reward_mint(system_state_ai, reward_mint_ai)?;
     msg!("6");
     let staked_lucra_mint_authority_bump_seed = verify_staked_lucra_mint(staking_state_ai, staked_lucra_mint_ai)?;
     msg!("7");
@@ -103,23 +166,51 @@ pub fn process_initialize(program_id: &Pubkey, state_params: &StateParams, accou
     let lucra_holding_vault_authority_bump_seed = verify_lucra_holding_vault(arb_state_ai, lucra_holding_vault_ai, lucra_mint_ai.key)?;
 
     // Initialize System State
-    state.meta_data = MetaData::new(DataType::SystemState, 0, true);
+    state.meta_data = MetaData::new(DataType::SystemState, RESERVED_LAYOUT_VERSION, true);
     state.key = *system_state_ai.key;
     state.staking_state = *staking_state_ai.key;
     state.arb_state = *arb_state_ai.key;
+    // The creator runs the DAO until it proposes the multisig with ProposeAuthority
+    state.dao_authority = CREATOR_AUTHORITY;
+    state.pending_dao_authority = Pubkey::default();
+    // The default key means no guardian until the DAO appoints one with UpdateState
+    state.guardian = *guardian;
     state.min_deposit = state_params.min_deposit;
+    verify_loan_terms(state_params.collateral_requirement, state_params.lcp)?;
     state.collateral_requirement = state_params.collateral_requirement;
     state.maximum_outstanding_mata = state_params.maximum_outstanding_mata;
-    state.minimum_harvest_amount = SOL_FEE_PLUS_INTEREST as u64 * 100;
+    check!(state_params.min_loan_amount_mata <= state_params.maximum_outstanding_mata, LucraErrorCode::InvalidAmount)?;
+    state.min_loan_amount_mata = state_params.min_loan_amount_mata;
+    check!(state_params.msol_haircut_bps <= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)?;
+    state.msol_haircut_bps = state_params.msol_haircut_bps;
+    state.msol_sol_oracle = Pubkey::default();
+    state.msol_sol_pool = Pubkey::default();
+    state.sol_mata_whirlpool = Pubkey::default();
+    state.pyth_sol_usd_feed = Pubkey::default();
+    state.annual_interest_bps = Bps(0);
+    state.restricted_cranking = false;
+    state.native_loans_enabled = false;
+    state.require_oracle_v2 = false;
+    verify_oracle_limits(state_params.max_oracle_staleness_slots, state_params.max_oracle_deviation_bps)?;
+    state.max_oracle_staleness_slots = state_params.max_oracle_staleness_slots;
+    state.max_oracle_deviation_bps = state_params.max_oracle_deviation_bps;
+    state.minimum_harvest_amount = Lamports(SOL_FEE_PLUS_INTEREST as u64 * 100);
     state.total_outstanding_mata = 0;
-    state.reward_fee = SOL_FEE_PLUS_INTEREST;                // Reward fee = 1 sol fee plus 10%
+    state.mata_supply = MataSupply::default();
+    state.settlement_active = false;
+    state.settlement_sol_price = 0;
+    state.settlement_timestamp = 0;
+    state.reward_fee = RewardFee(SOL_FEE_PLUS_INTEREST);                // Reward fee = 1 sol fee plus 10%
     state.epoch = state_params.epoch;
+    state.reward_expiry_epochs = DEFAULT_REWARD_EXPIRY_EPOCHS;
+    state.max_instant_unstake = DEFAULT_MAX_INSTANT_UNSTAKE;
     state.lucra_mint.address = *lucra_mint_ai.key;
     state.lucra_mint.authority_bump_seed = lucra_mint_authority_bump_seed;
     state.mata_mint.address = *mata_mint_ai.key;
     state.mata_mint.authority_bump_seed = mata_mint_authority_bump_seed;
     state.reward_mint.address = *reward_mint_ai.key;
     state.reward_mint.authority_bump_seed = reward_mint_authority_bump_seed;
+    state.reward_mint_decimals = reward_mint_decimals;
     state.msol_vault.address = *msol_vault_ai.key;
     state.msol_vault.authority_bump_seed = msol_vault_authority_bump_seed;
     state.arb_coffer.address = *arb_coffer_ai.key;
@@ -132,6 +223,16 @@ pub fn process_initialize(program_id: &Pubkey, state_params: &StateParams, accou
     state.arbitrage_enabled = state_params.arbitrage_enabled;
     state.peg_check_enabled = state_params.peg_check_enabled;
     state.peg_broken = false;
+    verify_peg_band(
+        state_params.peg_lower_bound,
+        state_params.peg_upper_bound,
+        state_params.penalty_band_width,
+        state_params.penalty_multiplier_per_band,
+    )?;
+    state.peg_lower_bound = state_params.peg_lower_bound;
+    state.peg_upper_bound = state_params.peg_upper_bound;
+    state.penalty_band_width = state_params.penalty_band_width;
+    state.penalty_multiplier_per_band = state_params.penalty_multiplier_per_band;
     state.lcp = state_params.lcp;
     
     // Initialize Staking State
@@ -143,9 +244,10 @@ pub fn process_initialize(program_id: &Pubkey, state_params: &StateParams, accou
     staking_state.last_reward = 0;
     staking_state.last_drop_timestamp = 0;
     staking_state.reward_cursor = 0;
+    staking_state.swept_reward_cursor = 0;
     
     // Initialize Arb State
-    arb_state.meta_data = MetaData::new(DataType::ArbState, 0, true);
+    arb_state.meta_data = MetaData::new(DataType::ArbState, RESERVED_LAYOUT_VERSION, true);
     arb_state.key = *arb_state_ai.key;
     arb_state.daily_limit = state_params.daily_arb_limit;
     arb_state.max_amount_of_lucra_to_mint = state_params.max_amount_of_lucra_to_mint;
@@ -162,6 +264,7 @@ pub fn process_initialize(program_id: &Pubkey, state_params: &StateParams, accou
         }; 30];
     arb_state.rolling_limits[0].date = start_of_day;
     arb_state.rolling_limits[0].limit = state_params.daily_arb_limit;
+    refresh_arb_capacity(&mut arb_state);
     arb_state.arb_fund.address = *arb_fund_ai.key;
     arb_state.arb_fund.authority_bump_seed = arb_fund_authority_bump_seed;
     arb_state.wsol_holding_vault.address = *wsol_holding_vault_ai.key;
@@ -170,6 +273,8 @@ pub fn process_initialize(program_id: &Pubkey, state_params: &StateParams, accou
     arb_state.mata_holding_vault.authority_bump_seed = mata_holding_vault_authority_bump_seed;
     arb_state.lucra_holding_vault.address = *lucra_holding_vault_ai.key;
     arb_state.lucra_holding_vault.authority_bump_seed = lucra_holding_vault_authority_bump_seed;
+    // No dust threshold, CleanUpArb burns the leftover mata and keeps every lucra until the DAO sets one
+    arb_state.dust_threshold = 0;
     arb_state.state = StateEnum::Minting;
 
     Ok(())
@@ -179,7 +284,7 @@ fn verify_mata_mint(
     system_state_ai: &AccountInfo,
     mata_mint_ai: &AccountInfo,
 ) -> LucraResult<u8> {
-    let (mata_mint_authority_address, mata_mint_authority_bump_seed) = SystemState::find_mata_mint_authority(system_state_ai.key);
+    let (mata_mint_authority_address, mata_mint_authority_bump_seed) = Pda::mata_mint_authority(system_state_ai.key);
     verify_mint(&mata_mint_authority_address, mata_mint_ai)?;
 
     Ok(mata_mint_authority_bump_seed)
@@ -189,7 +294,7 @@ fn verify_lucra_mint(
     system_state_ai: &AccountInfo,
     lucra_mint_ai: &AccountInfo,
 ) -> LucraResult<u8> {
-    let (lucra_mint_authority_address, lucra_mint_authority_bump_seed) = SystemState::find_lucra_mint_authority(system_state_ai.key);
+    let (lucra_mint_authority_address, lucra_mint_authority_bump_seed) = Pda::lucra_mint_authority(system_state_ai.key);
     verify_mint(&lucra_mint_authority_address, lucra_mint_ai)?;
 
     Ok(lucra_mint_authority_bump_seed)
@@ -198,21 +303,22 @@ fn verify_lucra_mint(
 fn verify_reward_mint(
     system_state_ai: &AccountInfo,
     reward_mint_ai: &AccountInfo,
-) -> LucraResult<u8> {
-    let (reward_mint_authority_address, reward_mint_authority_bump_seed) = SystemState::find_reward_mint_authority(system_state_ai.key);
+) -> LucraResult<(u8, u8)> {
+    let (reward_mint_authority_address, reward_mint_authority_bump_seed) = Pda::reward_mint_authority(system_state_ai.key);
     verify_mint(&reward_mint_authority_address, reward_mint_ai)?;
 
     let reward_mint = Mint::unpack(&reward_mint_ai.data.borrow())?;
     check!(reward_mint.supply == 0, LucraErrorCode::InvalidAccountInput)?;
+    verify_reward_decimals(reward_mint.decimals)?;
 
-    Ok(reward_mint_authority_bump_seed)
+    Ok((reward_mint_authority_bump_seed, reward_mint.decimals))
 }
 
 fn verify_staked_lucra_mint(
     staking_state_ai: &AccountInfo,
     staked_lucra_mint_ai: &AccountInfo,
 ) -> LucraResult<u8> {
-    let (staked_lucra_mint_authority_address, staked_lucra_mint_authority_bump_seed) = StakingState::find_stake_mint_authority(staking_state_ai.key);
+    let (staked_lucra_mint_authority_address, staked_lucra_mint_authority_bump_seed) = Pda::stake_mint_authority(staking_state_ai.key);
     verify_mint(&staked_lucra_mint_authority_address, staked_lucra_mint_ai)?;
 
     let staked_lucra_mint = Mint::unpack(&staked_lucra_mint_ai.data.borrow())?;
@@ -238,7 +344,7 @@ fn verify_msol_vault(
     marinade_state_ai: &AccountInfo,
     msol_vault_ai: &AccountInfo,
 ) -> LucraResult<u8> {
-    let (msol_vault_authority_address, msol_vault_authority_bump_seed) = SystemState::find_msol_vault_authority(system_state_ai.key);
+    let (msol_vault_authority_address, msol_vault_authority_bump_seed) = Pda::msol_vault_authority(system_state_ai.key);
     verify_vault(&msol_vault_authority_address, msol_vault_ai)?;
     
     let msol_vault = Account::unpack(&msol_vault_ai.data.borrow())?;
@@ -255,7 +361,7 @@ fn verify_arb_coffer(
     system_state_ai: &AccountInfo,
     arb_coffer_ai: &AccountInfo,
 ) -> LucraResult<u8> {
-    let (arb_coffer_authority_address, arb_coffer_authority_bump_seed) = SystemState::find_arb_coffer_authority(system_state_ai.key);
+    let (arb_coffer_authority_address, arb_coffer_authority_bump_seed) = Pda::arb_coffer_authority(system_state_ai.key);
     verify_vault(&arb_coffer_authority_address, arb_coffer_ai)?;
 
     Ok(arb_coffer_authority_bump_seed)
@@ -265,7 +371,7 @@ fn verify_rewards_vault(
     system_state_ai: &AccountInfo,
     rewards_vault_ai: &AccountInfo,
 ) -> LucraResult<u8> {
-    let (rewards_vault_authority_address, rewards_vault_authority_bump_seed) = SystemState::find_rewards_vault_authority(system_state_ai.key);
+    let (rewards_vault_authority_address, rewards_vault_authority_bump_seed) = Pda::rewards_vault_authority(system_state_ai.key);
     verify_vault(&rewards_vault_authority_address, rewards_vault_ai)?;
 
     let rewards_vault_balance = get_token_balance(rewards_vault_ai)?;
@@ -278,7 +384,7 @@ fn verify_arb_fund(
     arb_state_ai: &AccountInfo,
     arb_fund_ai: &AccountInfo,
 ) -> LucraResult<u8> {
-    let (arb_fund_authority_address, arb_fund_authority_bump_seed) = ArbState::find_arb_fund_authority(arb_state_ai.key);
+    let (arb_fund_authority_address, arb_fund_authority_bump_seed) = Pda::arb_fund_authority(arb_state_ai.key);
     msg!("arb_state_ai: {:?}", arb_state_ai.key.to_string());
     msg!("arb_fund_authority_address: {:?}", arb_fund_authority_address);
     msg!("arb_fund_ai: {:?}", arb_fund_ai.key.to_string());
@@ -294,8 +400,9 @@ fn verify_wsol_holding_vault(
     arb_state_ai: &AccountInfo,
     wsol_holding_vault_ai: &AccountInfo,
 ) -> LucraResult<u8> {
-    let (wsol_holding_vault_authority_address, wsol_holding_vault_authority_bump_seed) = ArbState::find_wsol_holding_vault_authority(arb_state_ai.key);
+    let (wsol_holding_vault_authority_address, wsol_holding_vault_authority_bump_seed) = Pda::wsol_holding_vault_authority(arb_state_ai.key);
     verify_vault(&wsol_holding_vault_authority_address, wsol_holding_vault_ai)?;
+    verify_wsol_account(wsol_holding_vault_ai)?;
 
     let wsol_holding_vault_balance = get_token_balance(wsol_holding_vault_ai)?;
     check!(wsol_holding_vault_balance == 0, LucraErrorCode::InvalidAmount)?;
@@ -308,7 +415,7 @@ fn verify_mata_holding_vault(
     mata_holding_vault_ai: &AccountInfo,
     mata_mint: &Pubkey,
 ) -> LucraResult<u8> {
-    let (mata_holding_vault_authority_address, mata_holding_vault_authority_bump_seed) = ArbState::find_mata_holding_vault_authority(arb_state_ai.key);
+    let (mata_holding_vault_authority_address, mata_holding_vault_authority_bump_seed) = Pda::mata_holding_vault_authority(arb_state_ai.key);
     verify_vault_and_mint(&mata_holding_vault_authority_address, mata_holding_vault_ai, mata_mint)?;
 
     let mata_holding_vault_balance = get_token_balance(mata_holding_vault_ai)?;
@@ -322,7 +429,7 @@ fn verify_lucra_holding_vault(
     lucra_holding_vault_ai: &AccountInfo,
     lucra_mint: &Pubkey,
 ) -> LucraResult<u8> {
-    let (lucra_holding_vault_authority_address, lucra_holding_vault_authority_bump_seed) = ArbState::find_lucra_holding_vault_authority(arb_state_ai.key);
+    let (lucra_holding_vault_authority_address, lucra_holding_vault_authority_bump_seed) = Pda::lucra_holding_vault_authority(arb_state_ai.key);
     verify_vault_and_mint(&lucra_holding_vault_authority_address, lucra_holding_vault_ai, lucra_mint)?;
 
     let lucra_holding_vault_balance = get_token_balance(lucra_holding_vault_ai)?;
@@ -353,4 +460,40 @@ fn verify_vault_and_mint(
     check_eq!(&vault.mint, mint, LucraErrorCode::InvalidAccountOwner)?;
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault_data(mint: Pubkey, owner: Pubkey) -> Vec<u8> {
+        let mut data = vec![0; Account::LEN];
+        Account {
+            mint,
+            owner,
+            state: spl_token::state::AccountState::Initialized,
+            ..Account::default()
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    #[test]
+    fn test_wsol_holding_vault_must_hold_wsol() {
+        let arb_state = Pubkey::new_unique();
+        let mut arb_state_lamports = 0;
+        let mut arb_state_data = vec![];
+        let arb_state_ai = AccountInfo::new(&arb_state, false, false, &mut arb_state_lamports, &mut arb_state_data, &crate::id(), false, 0);
+        let (vault_authority, bump_seed) = ArbState::find_wsol_holding_vault_authority(&arb_state);
+
+        let vault = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vault_data(crate::helpers::constants::wsol::id(), vault_authority);
+        let vault_ai = AccountInfo::new(&vault, false, true, &mut lamports, &mut data, &spl_token::ID, false, 0);
+        assert_eq!(verify_wsol_holding_vault(&arb_state_ai, &vault_ai).unwrap(), bump_seed);
+
+        let mut lamports = 0;
+        let mut data = vault_data(Pubkey::new_unique(), vault_authority);
+        let vault_ai = AccountInfo::new(&vault, false, true, &mut lamports, &mut data, &spl_token::ID, false, 0);
+        assert!(verify_wsol_holding_vault(&arb_state_ai, &vault_ai).is_err());
+    }
+}
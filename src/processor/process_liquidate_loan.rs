@@ -0,0 +1,821 @@
+use std::cell::RefMut;
+use std::convert::TryFrom;
+
+use anchor_lang::prelude::*;
+use arrayref::array_ref;
+use rust_decimal::Decimal;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        account::{verify_distinct_accounts, verify_program_account},
+        collateral::{
+            backfill_msol_collateral,
+            calc_harvest_msol,
+            calc_harvestable_penalty,
+            calc_loan_collateral_value,
+            find_msol_sol_oracle,
+            get_collateral_valuation_factor,
+            get_marinade_msol_rate,
+        },
+        constants::{orca_swap, SOL_MATA_ORACLE, SOL_MATA_ORCA_AMM, SOL_MATA_RAYDIUM_AMM, SOL_USDC_ORACLE, SOL_USDT_ORACLE},
+        liquidation::{calc_liquidation_split, calc_lp_liquidation_split, is_liquidatable},
+        lp_collateral::{get_lp_collateral_value, transfer_from_lp_vault},
+        oracle::{get_mata_price, get_sol_price, verify_orca_has_more_volume, verify_sol_usd_oracle, OracleAccount},
+        settlement::check_not_settled,
+        sol_vault::transfer_from_sol_vault,
+        spl::{get_token_balance, measure_swap_output, verify_wsol_account},
+        spltokenswap::{swap as orca_swap, withdraw_all_token_types},
+        vaults::{verify_arb_coffer, verify_lp_vault, verify_mata_mint, verify_msol_vault, verify_msol_vault_covers},
+    },
+    idl,
+    instruction::Instruction,
+    lucra_core::interest::calc_amount_owed,
+    lucra_core::penalty::{calc_harvestable_native_penalty, calc_lp_penalty_tokens},
+    state::{
+        AmmTypes,
+        LoanType,
+        MataLoan,
+        staking::StakingAccount,
+        SystemState,
+    },
+    units::Lamports,
+};
+use super::process_harvest_penalty::{calc_harvest_min_mata_out, liquid_unstake_for_harvest};
+
+declare_check_assert_macros!(SourceFileId::LiquidateLoan);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::LiquidateLoan { amm_type } => {
+            msg!("Instruction: Liquidate Loan");
+            let amm_type = AmmTypes::try_from(amm_type).map_err(|_| ProgramError::InvalidInstructionData)?;
+            process_liquidate_loan(program_id, amm_type, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const LIQUIDATE_LOAN_SIZE: usize = idl::LIQUIDATE_LOAN.len();
+const LIQUIDATE_LOAN_WITH_LOCKED_STAKE_SIZE: usize = idl::LIQUIDATE_LOAN_WITH_LOCKED_STAKE.len();
+const LIQUIDATE_LP_BACKED_LOAN_SIZE: usize = idl::LIQUIDATE_LP_BACKED_LOAN.len();
+const LIQUIDATE_NATIVE_SOL_LOAN_SIZE: usize = idl::LIQUIDATE_NATIVE_SOL_LOAN.len();
+const ORCA_PENALTY_SWAP_SIZE: usize = idl::LIQUIDATE_LOAN_WITH_ORCA.len() - LIQUIDATE_LOAN_SIZE;
+const LOAN_INDEX: usize = 2;
+
+// Anyone can repay a loan that fell under the liquidation ratio and take its collateral as msol, lamports for a
+// native SOL loan or the mata its LP tokens come out to for an LP backed loan, at the value of the debt and its
+// interest plus the DAO's bonus. A lucra backed loan also needs the owner's staking account to unlock. The
+// penalty the loan still owes is settled first, with an AMM it's unstaked and swapped for mata that's burned the
+// way HarvestPenalty does, otherwise its msol goes to the arb coffer.
+#[inline(never)]
+pub fn process_liquidate_loan(program_id: &Pubkey, amm_type: AmmTypes, accounts: &[AccountInfo]) -> LucraResult {
+    let loan_ai = accounts.get(LOAN_INDEX).ok_or(throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    let loan_type = MataLoan::load_checked(loan_ai, program_id)?.loan_type;
+
+    match loan_type {
+        LoanType::Default => liquidate_loan(program_id, amm_type, accounts),
+        LoanType::LucraBacked => liquidate_loan_with_locked_stake(program_id, amm_type, accounts),
+        // The penalty of an LP backed loan is LP tokens that stay in the LP vault, but the liquidator's share is
+        // withdrawn from the orca pool and its SOL sold there
+        LoanType::LpBacked if matches!(amm_type, AmmTypes::Orca) => liquidate_lp_backed_loan(program_id, accounts),
+        LoanType::LpBacked => Err(throw_err!(LucraErrorCode::NotImplemented)),
+        // The penalty of a native SOL loan is lamports that stay in the sol vault, there's nothing to swap
+        LoanType::NativeSol if matches!(amm_type, AmmTypes::None) => liquidate_native_sol_loan(program_id, accounts),
+        LoanType::NativeSol => Err(throw_err!(LucraErrorCode::NotImplemented)),
+    }
+}
+
+/// Splits off the accounts the penalty swap needs after the fixed layout, the rest trail after them. Only the
+/// orca pool is supported for now.
+fn split_penalty_swap_accounts<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+    num_fixed: usize,
+    amm_type: AmmTypes,
+) -> LucraResult<(&'a [AccountInfo<'b>], &'a [AccountInfo<'b>])> {
+    let num_swap = match amm_type {
+        AmmTypes::None => 0,
+        AmmTypes::Orca => ORCA_PENALTY_SWAP_SIZE,
+        AmmTypes::Raydium | AmmTypes::Whirlpool => return Err(throw_err!(LucraErrorCode::NotImplemented)),
+    };
+    check!(accounts.len() >= num_fixed + num_swap, LucraErrorCode::InvalidAccountInput)?;
+
+    Ok(accounts[num_fixed..].split_at(num_swap))
+}
+
+#[inline(never)]
+fn liquidate_loan(program_id: &Pubkey, amm_type: AmmTypes, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = LIQUIDATE_LOAN_SIZE;
+    let (penalty_swap_accounts, trailing_accounts) = split_penalty_swap_accounts(accounts, NUM_FIXED, amm_type)?;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        marinade_state_ai,          // read, write with an AMM
+        loan_ai,                    // write
+        msol_vault_ai,              // write
+        msol_vault_authority_ai,    // read
+        arb_coffer_ai,              // write
+        mata_mint_ai,               // write
+        liquidator_ai,              // read, write with an AMM
+        liquidator_mata_account_ai, // write
+        liquidator_msol_account_ai, // write
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+
+    liquidate(
+        program_id,
+        &mut loan,
+        penalty_swap_accounts,
+        trailing_accounts,
+        system_state_ai,
+        marinade_state_ai,
+        msol_vault_ai,
+        msol_vault_authority_ai,
+        arb_coffer_ai,
+        mata_mint_ai,
+        liquidator_ai,
+        liquidator_mata_account_ai,
+        liquidator_msol_account_ai,
+        sol_usdc_oracle_ai,
+        sol_usdt_oracle_ai,
+        token_program_ai,
+    )?;
+
+    Ok(())
+}
+
+#[inline(never)]
+fn liquidate_loan_with_locked_stake(program_id: &Pubkey, amm_type: AmmTypes, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = LIQUIDATE_LOAN_WITH_LOCKED_STAKE_SIZE;
+    let (penalty_swap_accounts, trailing_accounts) = split_penalty_swap_accounts(accounts, NUM_FIXED, amm_type)?;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        marinade_state_ai,          // read, write with an AMM
+        loan_ai,                    // write
+        msol_vault_ai,              // write
+        msol_vault_authority_ai,    // read
+        arb_coffer_ai,              // write
+        mata_mint_ai,               // write
+        liquidator_ai,              // read, write with an AMM
+        liquidator_mata_account_ai, // write
+        liquidator_msol_account_ai, // write
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        staking_account_ai,         // write
+        token_program_ai,           // read
+    ] = accounts;
+
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    let mut staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(staking_account_ai, program_id)?;
+    check_eq!(&staking_account.owner, &loan.owner, LucraErrorCode::InvalidAccountInput)?;
+
+    let staking_collateral = loan.staking_collateral_amount;
+    liquidate(
+        program_id,
+        &mut loan,
+        penalty_swap_accounts,
+        trailing_accounts,
+        system_state_ai,
+        marinade_state_ai,
+        msol_vault_ai,
+        msol_vault_authority_ai,
+        arb_coffer_ai,
+        mata_mint_ai,
+        liquidator_ai,
+        liquidator_mata_account_ai,
+        liquidator_msol_account_ai,
+        sol_usdc_oracle_ai,
+        sol_usdt_oracle_ai,
+        token_program_ai,
+    )?;
+    // The stake backed the debt the liquidator just repaid, the owner gets it back unlocked
+    staking_account.remove_locked_total(staking_collateral);
+
+    Ok(())
+}
+
+// LP collateral is valued off the pool like it was when the loan was opened. The liquidator's share of the LP
+// tokens is withdrawn from the pool and the SOL leg sold back into it, so the liquidator is paid in mata. What
+// the penalty took and whatever is left over after the liquidator's share stays in the LP vault.
+#[inline(never)]
+fn liquidate_lp_backed_loan(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = LIQUIDATE_LP_BACKED_LOAN_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        sol_mata_pool_ai,           // read
+        loan_ai,                    // write
+        lp_vault_ai,                // write
+        lp_vault_authority_ai,      // read
+        mata_mint_ai,               // write
+        liquidator_ai,              // read
+        liquidator_mata_account_ai, // write
+        liquidator_lp_account_ai,   // write
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        sol_mata_oracle_ai,         // read
+        pool_sol_vault_ai,          // write
+        pool_mata_vault_ai,         // write
+        lp_mint_ai,                 // write
+        token_program_ai,           // read
+        liquidator_wsol_account_ai, // write
+        pool_authority_ai,          // read
+        pool_fees_ai,               // write
+        token_swap_program_ai,      // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(liquidator_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(lp_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(liquidator_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(liquidator_lp_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(liquidator_wsol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_wsol_account(liquidator_wsol_account_ai)?;
+    verify_program_account(token_swap_program_ai, &orca_swap::id())?;
+    verify_distinct_accounts(&[
+        ("lp_vault", lp_vault_ai.key),
+        ("liquidator_lp_account", liquidator_lp_account_ai.key),
+        ("liquidator_wsol_account", liquidator_wsol_account_ai.key),
+        ("liquidator_mata_account", liquidator_mata_account_ai.key),
+    ])?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_lp_vault(&system_state, lp_vault_ai.key)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    check_eq!(loan.loan_type, LoanType::LpBacked, LucraErrorCode::InvalidLoanType)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.loan_mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let liquidator_mata_account = Account::unpack(&liquidator_mata_account_ai.data.borrow())?;
+    check_eq!(&liquidator_mata_account.mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&liquidator_mata_account.owner, liquidator_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let liquidator_lp_account = Account::unpack(&liquidator_lp_account_ai.data.borrow())?;
+    check_eq!(&liquidator_lp_account.mint, &system_state.lp_mint, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&liquidator_lp_account.owner, liquidator_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let amount_owed = calc_amount_owed(&loan)?;
+    check!(liquidator_mata_account.amount >= amount_owed, LucraErrorCode::InvalidAmount)?;
+
+    // Pending penalty has already taken its LP tokens, only the rest backs the debt
+    let remaining_lp = loan.lp_collateral_amount
+        .checked_sub(calc_lp_penalty_tokens(&loan)?)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let mata_market_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let collateral_value = get_lp_collateral_value(
+        &system_state,
+        sol_mata_pool_ai,
+        pool_sol_vault_ai,
+        pool_mata_vault_ai,
+        lp_mint_ai,
+        remaining_lp,
+        sol_market_price,
+        mata_market_price,
+    )?;
+    check!(
+        is_liquidatable(collateral_value, amount_owed, system_state.liquidation_ratio)?,
+        LucraErrorCode::LoanNotLiquidatable
+    )?;
+    let split = calc_lp_liquidation_split(remaining_lp, collateral_value, amount_owed, system_state.liquidation_bonus)?;
+
+    system_state.burn_mata(
+        mata_mint_ai,
+        liquidator_mata_account_ai,
+        amount_owed,
+        liquidator_ai,
+        token_program_ai,
+    )?;
+    system_state.mata_supply.burn_repayment(amount_owed, loan.loan_amount)?;
+
+    transfer_from_lp_vault(
+        program_id,
+        &system_state,
+        lp_vault_ai,
+        liquidator_lp_account_ai,
+        lp_vault_authority_ai,
+        token_program_ai,
+        split.to_liquidator,
+    )?;
+
+    // Nothing bounds the withdrawal itself, the oracle bound on the swap keeps the SOL leg from being sold cheap
+    let sol_withdrawn = measure_swap_output(liquidator_wsol_account_ai, 0, || {
+        withdraw_all_token_types(
+            token_swap_program_ai,
+            token_program_ai,
+            sol_mata_pool_ai,
+            pool_authority_ai,
+            liquidator_ai,
+            lp_mint_ai,
+            pool_fees_ai,
+            liquidator_lp_account_ai,
+            pool_sol_vault_ai,
+            pool_mata_vault_ai,
+            liquidator_wsol_account_ai,
+            liquidator_mata_account_ai,
+            split.to_liquidator,
+            0,                          // min sol out
+            0,                          // min mata out
+        )
+    })?;
+
+    let min_mata_out = calc_harvest_min_mata_out(sol_withdrawn, sol_market_price, mata_market_price, 0)?;
+    measure_swap_output(liquidator_mata_account_ai, min_mata_out, || {
+        orca_swap(
+            token_swap_program_ai,
+            token_program_ai,
+            sol_mata_pool_ai,
+            pool_authority_ai,
+            liquidator_ai,
+            liquidator_wsol_account_ai,
+            liquidator_mata_account_ai,
+            pool_sol_vault_ai,
+            pool_mata_vault_ai,
+            lp_mint_ai,
+            pool_fees_ai,
+            &[&[&[]]],
+            sol_withdrawn,              // sol in
+            0,                          // mata in
+            min_mata_out,
+        )
+    })?;
+
+    loan.update_harvested_penalty();
+    loan.lp_collateral_amount = 0;
+    loan.interest_accrued = 0;
+    loan.repaid();
+
+    Ok(())
+}
+
+// Native SOL collateral is valued at the SOL price with nothing to discount, and paid out as lamports from the
+// sol vault. The penalty and whatever is left over after the liquidator's share stay in the vault for the DAO.
+// Turning native loans off only stops new ones, the open ones can still be liquidated.
+#[inline(never)]
+fn liquidate_native_sol_loan(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = LIQUIDATE_NATIVE_SOL_LOAN_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        sol_vault_ai,               // write
+        loan_ai,                    // write
+        mata_mint_ai,               // write
+        liquidator_ai,              // write
+        liquidator_mata_account_ai, // write
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+    let rent = &Rent::get()?;
+
+    check_eq!(liquidator_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_vault_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(liquidator_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    verify_distinct_accounts(&[
+        ("sol_vault", sol_vault_ai.key),
+        ("liquidator", liquidator_ai.key),
+    ])?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    check_eq!(loan.loan_type, LoanType::NativeSol, LucraErrorCode::InvalidLoanType)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.loan_mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let liquidator_mata_account = Account::unpack(&liquidator_mata_account_ai.data.borrow())?;
+    check_eq!(&liquidator_mata_account.mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&liquidator_mata_account.owner, liquidator_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let amount_owed = calc_amount_owed(&loan)?;
+    check!(liquidator_mata_account.amount >= amount_owed, LucraErrorCode::InvalidAmount)?;
+
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let collateral_value = calc_loan_collateral_value(&loan, sol_market_price, Decimal::ONE)?;
+    check!(
+        is_liquidatable(collateral_value, amount_owed, system_state.liquidation_ratio)?,
+        LucraErrorCode::LoanNotLiquidatable
+    )?;
+
+    system_state.burn_mata(
+        mata_mint_ai,
+        liquidator_mata_account_ai,
+        amount_owed,
+        liquidator_ai,
+        token_program_ai,
+    )?;
+    system_state.mata_supply.burn_repayment(amount_owed, loan.loan_amount)?;
+
+    // The penalty's lamports are the protocol's, the liquidator only buys what's left after it
+    loan.penalty_to_harvest = calc_harvestable_native_penalty(&loan);
+    loan.update_harvested_penalty();
+
+    let remaining_sol = loan.calc_remaining_sol();
+    let split = calc_liquidation_split(remaining_sol, amount_owed, sol_market_price, system_state.liquidation_bonus)?;
+    transfer_from_sol_vault(program_id, &system_state, sol_vault_ai, liquidator_ai, split.to_liquidator, rent)?;
+
+    // Native collateral was never counted in total_sol_collateral, there's nothing to remove
+    loan.interest_accrued = 0;
+    loan.repaid();
+
+    Ok(())
+}
+
+#[inline(never)]
+#[allow(clippy::too_many_arguments)]
+fn liquidate<'a>(
+    program_id: &Pubkey,
+    loan: &mut RefMut<MataLoan>,
+    penalty_swap_accounts: &[AccountInfo<'a>],
+    trailing_accounts: &[AccountInfo<'a>],
+    system_state_ai: &AccountInfo<'a>,
+    marinade_state_ai: &AccountInfo<'a>,
+    msol_vault_ai: &AccountInfo<'a>,
+    msol_vault_authority_ai: &AccountInfo<'a>,
+    arb_coffer_ai: &AccountInfo<'a>,
+    mata_mint_ai: &AccountInfo<'a>,
+    liquidator_ai: &AccountInfo<'a>,
+    liquidator_mata_account_ai: &AccountInfo<'a>,
+    liquidator_msol_account_ai: &AccountInfo<'a>,
+    sol_usdc_oracle_ai: &AccountInfo<'a>,
+    sol_usdt_oracle_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+) -> LucraResult {
+    let clock = &Clock::get()?;
+
+    check_eq!(liquidator_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(arb_coffer_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(liquidator_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(liquidator_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    verify_distinct_accounts(&[
+        ("msol_vault", msol_vault_ai.key),
+        ("arb_coffer", arb_coffer_ai.key),
+        ("liquidator_msol_account", liquidator_msol_account_ai.key),
+    ])?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    // Settlement already prices every loan at the frozen SOL price
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+    verify_arb_coffer(&system_state, arb_coffer_ai.key)?;
+
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.loan_mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let liquidator_mata_account = Account::unpack(&liquidator_mata_account_ai.data.borrow())?;
+    check_eq!(&liquidator_mata_account.mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&liquidator_mata_account.owner, liquidator_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    // The liquidator repays the interest with the debt, it isn't forgiven by letting the loan go under
+    let amount_owed = calc_amount_owed(loan)?;
+    check!(liquidator_mata_account.amount >= amount_owed, LucraErrorCode::InvalidAmount)?;
+
+    // Valued the way borrowing more is, the discount during a depeg makes a loan liquidatable sooner. A
+    // liquidation ratio of 0 means liquidation is off and no loan passes.
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let msol_sol_oracle_ai = find_msol_sol_oracle(trailing_accounts, &system_state);
+    let collateral_factor = get_collateral_valuation_factor(&system_state, msol_sol_oracle_ai, Some(marinade_state_ai), clock)?;
+    let collateral_value = calc_loan_collateral_value(loan, sol_market_price, collateral_factor)?;
+    check!(
+        is_liquidatable(collateral_value, amount_owed, system_state.liquidation_ratio)?,
+        LucraErrorCode::LoanNotLiquidatable
+    )?;
+
+    system_state.burn_mata(
+        mata_mint_ai,
+        liquidator_mata_account_ai,
+        amount_owed,
+        liquidator_ai,
+        token_program_ai,
+    )?;
+    system_state.mata_supply.burn_repayment(amount_owed, loan.loan_amount)?;
+
+    // Converted the way a close converts what it returns, capped at the loan's own msol
+    backfill_msol_collateral(loan, &system_state, get_token_balance(msol_vault_ai)?)?;
+
+    // The penalty is the protocol's, the liquidator only buys what's left after it
+    loan.penalty_to_harvest = calc_harvestable_penalty(loan, get_marinade_msol_rate(marinade_state_ai)?)?;
+    let penalty = loan.penalty_to_harvest;
+    if penalty_swap_accounts.is_empty() || Lamports(penalty) < system_state.minimum_harvest_amount {
+        settle_penalty_into_coffer(
+            program_id,
+            &system_state,
+            loan,
+            marinade_state_ai,
+            msol_vault_ai,
+            msol_vault_authority_ai,
+            arb_coffer_ai,
+            token_program_ai,
+        )?;
+    } else {
+        let penalty_swap_accounts = array_ref![penalty_swap_accounts, 0, ORCA_PENALTY_SWAP_SIZE];
+        settle_penalty_with_orca(
+            program_id,
+            &mut system_state,
+            loan,
+            penalty_swap_accounts,
+            marinade_state_ai,
+            msol_vault_ai,
+            msol_vault_authority_ai,
+            mata_mint_ai,
+            liquidator_ai,
+            liquidator_mata_account_ai,
+            liquidator_msol_account_ai,
+            sol_usdc_oracle_ai,
+            sol_usdt_oracle_ai,
+            token_program_ai,
+            sol_market_price,
+        )?;
+    }
+    system_state.remove_collateral(penalty);
+    loan.update_harvested_penalty();
+
+    let remaining_sol = loan.calc_remaining_sol();
+    let split = calc_liquidation_split(remaining_sol, amount_owed, sol_market_price, system_state.liquidation_bonus)?;
+
+    let marinade_state = ProgramAccount::<marinade_finance::state::State>::try_from(&marinade_finance::id(), marinade_state_ai)
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    let msol_to_liquidator = marinade_state
+        .calc_msol_from_lamports(split.to_liquidator)
+        .map_err(|_| math_err!())?
+        .min(loan.msol_collateral_amount);
+    let msol_to_coffer = marinade_state
+        .calc_msol_from_lamports(split.to_coffer)
+        .map_err(|_| math_err!())?
+        .min(loan.msol_collateral_amount - msol_to_liquidator);
+
+    system_state.transfer_from_msol_vault(
+        program_id,
+        msol_vault_ai,
+        liquidator_msol_account_ai,
+        msol_vault_authority_ai,
+        token_program_ai,
+        msol_to_liquidator,
+    )?;
+    if msol_to_coffer > 0 {
+        system_state.transfer_from_msol_vault(
+            program_id,
+            msol_vault_ai,
+            arb_coffer_ai,
+            msol_vault_authority_ai,
+            token_program_ai,
+            msol_to_coffer,
+        )?;
+    }
+
+    // Nothing of the rebate is paid out, it stays recorded as owed to the owner
+    loan.msol_collateral_amount = 0;
+    loan.interest_accrued = 0;
+    loan.repaid();
+
+    system_state.remove_collateral(remaining_sol);
+
+    Ok(())
+}
+
+/// Sends the penalty's msol to the arb coffer, which sells it later like a harvest above the peg would
+#[inline(never)]
+#[allow(clippy::too_many_arguments)]
+fn settle_penalty_into_coffer<'a>(
+    program_id: &Pubkey,
+    system_state: &SystemState,
+    loan: &mut RefMut<MataLoan>,
+    marinade_state_ai: &AccountInfo<'a>,
+    msol_vault_ai: &AccountInfo<'a>,
+    msol_vault_authority_ai: &AccountInfo<'a>,
+    arb_coffer_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+) -> LucraResult {
+    if loan.penalty_to_harvest == 0 {
+        return Ok(());
+    }
+
+    let marinade_state = ProgramAccount::<marinade_finance::state::State>::try_from(&marinade_finance::id(), marinade_state_ai)
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    let msol_for_penalty = marinade_state
+        .calc_msol_from_lamports(loan.penalty_to_harvest)
+        .map_err(|_| math_err!())?;
+    let penalty_msol = calc_harvest_msol(msol_for_penalty, loan);
+    verify_msol_vault_covers(get_token_balance(msol_vault_ai)?, penalty_msol)?;
+
+    system_state.transfer_from_msol_vault(
+        program_id,
+        msol_vault_ai,
+        arb_coffer_ai,
+        msol_vault_authority_ai,
+        token_program_ai,
+        penalty_msol,
+    )?;
+    loan.msol_collateral_amount = loan.msol_collateral_amount
+        .checked_sub(penalty_msol)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+
+    Ok(())
+}
+
+/// Unstakes the penalty's msol and swaps it for mata on the orca pool, the mata is burned. The liquidator is
+/// paid the reward fee for running it, like a keeper harvesting the penalty would be.
+#[inline(never)]
+#[allow(clippy::too_many_arguments)]
+fn settle_penalty_with_orca<'a>(
+    program_id: &Pubkey,
+    system_state: &mut RefMut<SystemState>,
+    loan: &mut RefMut<MataLoan>,
+    penalty_swap_accounts: &[AccountInfo<'a>; ORCA_PENALTY_SWAP_SIZE],
+    marinade_state_ai: &AccountInfo<'a>,
+    msol_vault_ai: &AccountInfo<'a>,
+    msol_vault_authority_ai: &AccountInfo<'a>,
+    mata_mint_ai: &AccountInfo<'a>,
+    liquidator_ai: &AccountInfo<'a>,
+    liquidator_mata_account_ai: &AccountInfo<'a>,
+    liquidator_msol_account_ai: &AccountInfo<'a>,
+    sol_usdc_oracle_ai: &AccountInfo<'a>,
+    sol_usdt_oracle_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+    sol_usd_price: Decimal,
+) -> LucraResult {
+    let [
+        sol_mata_oracle_ai,         // read
+        liquidator_wsol_account_ai, // write
+        msol_mint_ai,               // write
+        liq_pool_sol_leg_pda_ai,    // write
+        liq_pool_msol_leg_ai,       // write
+        treasury_msol_account_ai,   // write
+        system_program_ai,          // read
+        marinade_program_ai,        // read
+        sm_amm_ai,                  // write
+        sm_amm_authority_ai,        // read
+        sm_pool_base_vault_ai,      // write
+        sm_pool_quote_vault_ai,     // write
+        sm_pool_mint_ai,            // write
+        sm_pool_fees_ai,            // write
+        token_swap_program_ai,      // read
+    ] = penalty_swap_accounts;
+
+    check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(liquidator_wsol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_wsol_account(liquidator_wsol_account_ai)?;
+    check_eq!(sm_amm_ai.key, &SOL_MATA_ORCA_AMM, LucraErrorCode::InvalidAccountInput)?;
+    verify_program_account(marinade_program_ai, &marinade_finance::id())?;
+    verify_program_account(token_swap_program_ai, &orca_swap::id())?;
+    verify_distinct_accounts(&[
+        ("liquidator_wsol_account", liquidator_wsol_account_ai.key),
+        ("liquidator_mata_account", liquidator_mata_account_ai.key),
+        ("liquidator_msol_account", liquidator_msol_account_ai.key),
+    ])?;
+
+    let sol_mata_oracle = OracleAccount::load(system_state, sol_mata_oracle_ai)?;
+    verify_orca_has_more_volume(&sol_mata_oracle, &SOL_MATA_RAYDIUM_AMM, &SOL_MATA_ORCA_AMM)?;
+    let clock = &Clock::get()?;
+    let mata_price = get_mata_price(system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+
+    let sol_received = liquid_unstake_for_harvest(
+        program_id,
+        system_state,
+        loan,
+        msol_vault_authority_ai,
+        msol_vault_ai,
+        liquidator_ai,
+        liquidator_msol_account_ai,
+        liquidator_wsol_account_ai,
+        marinade_state_ai,
+        msol_mint_ai,
+        liq_pool_sol_leg_pda_ai,
+        liq_pool_msol_leg_ai,
+        treasury_msol_account_ai,
+        marinade_program_ai,
+        system_program_ai,
+        token_program_ai,
+    )?;
+
+    let min_mata_out = calc_harvest_min_mata_out(sol_received, sol_usd_price, mata_price, 0)?;
+    let mata_to_burn = measure_swap_output(liquidator_mata_account_ai, min_mata_out, || {
+        orca_swap(
+            token_swap_program_ai,
+            token_program_ai,
+            sm_amm_ai,
+            sm_amm_authority_ai,
+            liquidator_ai,
+            liquidator_wsol_account_ai,
+            liquidator_mata_account_ai,
+            sm_pool_base_vault_ai,
+            sm_pool_quote_vault_ai,
+            sm_pool_mint_ai,
+            sm_pool_fees_ai,
+            &[&[&[]]],
+            sol_received,               // sol in
+            0,                          // mata in
+            min_mata_out,
+        )
+    })?;
+
+    system_state.burn_mata(
+        mata_mint_ai,
+        liquidator_mata_account_ai,
+        mata_to_burn,
+        liquidator_ai,
+        token_program_ai,
+    )?;
+    system_state.mata_supply.burn_bought_back(mata_to_burn)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{account::tests::FakeAccounts, vaults::tests::assert_invalid_account_input};
+
+    #[test]
+    fn test_penalty_swap_accounts_sit_between_the_layout_and_the_trailing_accounts() {
+        let mut accounts = FakeAccounts::new(LIQUIDATE_LOAN_SIZE + ORCA_PENALTY_SWAP_SIZE + 1);
+        let msol_sol_oracle = accounts.key(LIQUIDATE_LOAN_SIZE + ORCA_PENALTY_SWAP_SIZE);
+        let infos = accounts.infos();
+
+        let (penalty_swap, trailing) = split_penalty_swap_accounts(&infos, LIQUIDATE_LOAN_SIZE, AmmTypes::Orca).unwrap();
+        assert_eq!(penalty_swap.len(), ORCA_PENALTY_SWAP_SIZE);
+        assert_eq!(penalty_swap[0].key, infos[LIQUIDATE_LOAN_SIZE].key);
+        assert_eq!(trailing.len(), 1);
+        assert_eq!(trailing[0].key, &msol_sol_oracle);
+
+        // Without an AMM everything after the layout trails
+        let (penalty_swap, trailing) = split_penalty_swap_accounts(&infos, LIQUIDATE_LOAN_SIZE, AmmTypes::None).unwrap();
+        assert!(penalty_swap.is_empty());
+        assert_eq!(trailing.len(), ORCA_PENALTY_SWAP_SIZE + 1);
+
+        assert_invalid_account_input(split_penalty_swap_accounts(&infos[..LIQUIDATE_LOAN_SIZE], LIQUIDATE_LOAN_SIZE, AmmTypes::Orca).map(|_| ()));
+    }
+
+    #[test]
+    fn test_only_the_orca_pool_swaps_the_penalty() {
+        let mut accounts = FakeAccounts::new(LIQUIDATE_LOAN_SIZE + ORCA_PENALTY_SWAP_SIZE);
+        let infos = accounts.infos();
+
+        for amm_type in [AmmTypes::Raydium, AmmTypes::Whirlpool] {
+            assert!(matches!(
+                split_penalty_swap_accounts(&infos, LIQUIDATE_LOAN_SIZE, amm_type).unwrap_err(),
+                LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::NotImplemented, .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_an_unknown_amm_type_is_invalid_instruction_data() {
+        assert!(matches!(
+            dispatch(&Pubkey::new_unique(), Instruction::LiquidateLoan { amm_type: u8::MAX }, &[]).unwrap_err(),
+            LucraError::ProgramError(ProgramError::InvalidInstructionData)
+        ));
+    }
+}
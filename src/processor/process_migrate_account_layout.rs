@@ -0,0 +1,99 @@
+use std::cell::Ref;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    system_program,
+    sysvar::{rent::Rent, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::account_layout::{
+        calc_rent_top_up,
+        is_dao_migrated,
+        plan_layout_migration,
+        read_meta_data,
+        write_meta_data,
+        RESERVED_LAYOUT_VERSION,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::solana::transfer,
+    instruction::Instruction,
+    state::{DataType, SystemState},
+};
+
+declare_check_assert_macros!(SourceFileId::MigrateAccountLayout);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::MigrateAccountLayout {} => {
+            msg!("Instruction: Migrate Account Layout");
+            process_migrate_account_layout(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Reallocs an account from before the reserved region to the reserved layout, the payer covers the extra
+// rent. Loans and stake balances can be migrated by anyone, the system and arb state need the DAO the system
+// state names. The account is read through its meta data only since the typed load expects the new size, so
+// the system state is checked once it has been reallocated and the arb state needs it migrated first.
+#[inline(never)]
+pub fn process_migrate_account_layout(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 3;
+    let fixed_accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        account_ai,             // write
+        payer_ai,               // write
+        system_program_ai,      // read
+    ] = fixed_accounts;
+
+    let rent = &Rent::get()?;
+
+    check_eq!(account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(account_ai.is_writable, true, LucraErrorCode::AccountNotMutable)?;
+    check_eq!(payer_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_program_ai.key, &system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let mut meta_data = read_meta_data(&account_ai.try_borrow_data()?)?;
+    let new_len = plan_layout_migration(&meta_data, account_ai.data_len())?;
+
+    if is_dao_migrated(meta_data.data_type) {
+        let dao_authority_ai = accounts.get(NUM_FIXED).ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+        check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    }
+
+    if meta_data.data_type == DataType::ArbState as u8 {
+        let system_state_ai = accounts.get(NUM_FIXED + 1).ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+        let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+        check_eq!(&system_state.arb_state, account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+        check_dao_authority(&system_state, accounts[NUM_FIXED].key)?;
+    }
+
+    let top_up = calc_rent_top_up(account_ai.lamports(), rent.minimum_balance(new_len));
+    if top_up > 0 {
+        transfer(payer_ai, account_ai, top_up, &[], system_program_ai)?;
+    }
+
+    // The new region is zeroed so reserved fields read as unset
+    account_ai.realloc(new_len, true)?;
+    meta_data.version = RESERVED_LAYOUT_VERSION;
+    write_meta_data(&mut account_ai.try_borrow_mut_data()?, &meta_data)?;
+
+    // Undone with the rest of the transaction if the signer isn't the DAO
+    if meta_data.data_type == DataType::SystemState as u8 {
+        let system_state: Ref<SystemState> = SystemState::load_checked(account_ai, program_id)?;
+        check_dao_authority(&system_state, accounts[NUM_FIXED].key)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::cell::{Ref, RefMut};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::reward_index::migrate_to_reward_index,
+    helpers::spl::get_token_balance,
+    idl,
+    instruction::Instruction,
+    state::staking::{StakeBalance, StakingState},
+};
+
+declare_check_assert_macros!(SourceFileId::MigrateStakeBalance);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::MigrateStakeBalance { } => {
+            msg!("Instruction: Migrate Stake Balance");
+            process_migrate_stake_balance(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const MIGRATE_STAKE_BALANCE_SIZE: usize = idl::MIGRATE_STAKE_BALANCE.len();
+
+// Nothing is paid out and the balance can only end up on the index once it has claimed everything it's owed,
+// so no signer is needed and a crank can move old balances over. The balance's stake vault holds the lucra its
+// staked lucra was minted for, which is the stake the index pays it on from then on.
+#[inline(never)]
+pub fn process_migrate_stake_balance(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = MIGRATE_STAKE_BALANCE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        staking_state_ai,   // read
+        stake_balance_ai,   // write
+        stake_vault_ai,     // read
+    ] = accounts;
+
+    check_eq!(staking_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(stake_balance_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(stake_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+
+    let staking_state: Ref<StakingState> = StakingState::load_checked(staking_state_ai, program_id)?;
+    let mut stake_balance: RefMut<StakeBalance> = StakeBalance::load_mut(stake_balance_ai)?;
+    check!(stake_balance.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&stake_balance.balances.stake_vault, stake_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    migrate_to_reward_index(&mut stake_balance, &staking_state, get_token_balance(stake_vault_ai)?)
+}
@@ -0,0 +1,58 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::{check_dao_authority, propose_dao_authority},
+    idl,
+    instruction::Instruction,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::ProposeAuthority);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::ProposeAuthority {
+            new_authority,
+        } => {
+            msg!("Instruction: Propose Authority");
+            process_propose_authority(program_id, new_authority, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const PROPOSE_AUTHORITY_SIZE: usize = idl::PROPOSE_AUTHORITY.len();
+
+// DAO instruction. Names the next DAO authority, which takes over once it accepts, see helpers::dao_authority.
+// Still allowed during settlement, the DAO has to be able to reclaim what's left afterwards.
+#[inline(never)]
+pub fn process_propose_authority(program_id: &Pubkey, new_authority: Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = PROPOSE_AUTHORITY_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // write
+        dao_authority_ai,   // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    propose_dao_authority(&mut system_state, new_authority);
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::cell::Ref;
+
+use arrayref::array_ref;
+use bincode::serialize;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program::set_return_data,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        collateral::{find_msol_sol_oracle, get_collateral_valuation_factor},
+        constants::{SOL_MATA_ORACLE, SOL_USDC_ORACLE, SOL_USDT_ORACLE},
+        oracle::{get_mata_price, get_sol_price, verify_sol_usd_oracle},
+        peg_band::{peg_lower_bound, peg_upper_bound},
+    },
+    idl,
+    instruction::Instruction,
+    lucra_core::{
+        penalty::is_peg_broken,
+        quote::{quote_mata_loan, MataLoanQuote},
+    },
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::QuoteMataLoan);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::QuoteMataLoan {
+            lamports,
+        } => {
+            msg!("Instruction: Quote Mata Loan");
+            process_quote_mata_loan(program_id, lamports, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const QUOTE_MATA_LOAN_SIZE: usize = idl::QUOTE_MATA_LOAN.len();
+
+// Prices the loan off the same oracles and valuation CreateMataLoan reads and sizes it with the same
+// quote_mata_loan, then hands the result back as return data for a simulated transaction to read. Nothing is
+// written, the peg is checked against the bounds without recording the price.
+#[inline(never)]
+pub fn process_quote_mata_loan(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = QUOTE_MATA_LOAN_SIZE;
+    let trailing_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // read
+        marinade_state_ai,      // read
+        sol_usdc_oracle_ai,     // read
+        sol_usdt_oracle_ai,     // read
+        sol_mata_oracle_ai,     // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+
+    let peg_broken = if system_state.peg_check_enabled {
+        check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+
+        let mata_market_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+        is_peg_broken(mata_market_price, peg_lower_bound(&system_state), peg_upper_bound(&system_state))
+    } else {
+        false
+    };
+
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let msol_sol_oracle_ai = find_msol_sol_oracle(trailing_accounts, &system_state);
+    let collateral_factor = get_collateral_valuation_factor(&system_state, msol_sol_oracle_ai, Some(marinade_state_ai), clock)?;
+
+    let quote = MataLoanQuote {
+        loan: quote_mata_loan(lamports, sol_market_price, collateral_factor, system_state.collateral_requirement, None)?,
+        loan_with_locked_stake: quote_mata_loan(
+            lamports,
+            sol_market_price,
+            collateral_factor,
+            system_state.collateral_requirement,
+            Some(system_state.lcp),
+        )?,
+        peg_broken,
+    };
+    set_return_data(&serialize(&quote).map_err(|_| throw_err!(LucraErrorCode::Default))?);
+
+    Ok(())
+}
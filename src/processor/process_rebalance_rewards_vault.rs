@@ -0,0 +1,265 @@
+use std::cell::Ref;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::settlement::check_not_settled,
+    helpers::spl::get_token_balance,
+    helpers::vaults::{verify_arb_coffer, verify_rewards_vault},
+    idl,
+    instruction::Instruction,
+    state::{
+        staking::{Reward, StakingState},
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::RebalanceRewardsVault);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RebalanceRewardsVault { lamports } => {
+            msg!("Instruction: Rebalance Rewards Vault");
+            process_rebalance_rewards_vault(program_id, lamports, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const REBALANCE_REWARDS_VAULT_SIZE: usize = idl::REBALANCE_REWARDS_VAULT.len();
+
+// DAO instruction. Moves msol the rewards vault holds beyond what stakers can still claim to the arb coffer.
+// Every reward that hasn't been swept is passed after the fixed accounts, newest first, and is counted at its
+// total less what was claimed through its account. Drops in the reward index are owed until they're claimed
+// through it, swept or not, so those are counted at no less than the staking state's reward_index_liability.
+#[inline(never)]
+pub fn process_rebalance_rewards_vault(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = REBALANCE_REWARDS_VAULT_SIZE;
+    let (fixed_accounts, reward_ais) = accounts.split_at(NUM_FIXED.min(accounts.len()));
+    let fixed_accounts = array_ref![fixed_accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,                        // read
+        staking_state_ai,                       // read
+        dao_authority_ai,                       // read
+        rewards_vault_ai,                       // write
+        arb_coffer_ai,                          // write
+        rewards_vault_transfer_authority_ai,    // read
+        token_program_ai,                       // read
+    ] = fixed_accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(staking_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(rewards_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(arb_coffer_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check!(lamports > 0, LucraErrorCode::InvalidAmount)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+    check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    verify_rewards_vault(&system_state, rewards_vault_ai.key)?;
+    verify_arb_coffer(&system_state, arb_coffer_ai.key)?;
+    let staking_state: Ref<StakingState> = StakingState::load_checked(staking_state_ai, program_id)?;
+
+    for reward_ai in reward_ais {
+        check_eq!(reward_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    }
+    let rewards = reward_ais
+        .iter()
+        .map(Reward::load)
+        .collect::<Result<Vec<Ref<Reward>>, _>>()?;
+    let rewards: Vec<(&Pubkey, &Reward)> = reward_ais
+        .iter()
+        .zip(rewards.iter())
+        .map(|(reward_ai, reward)| (reward_ai.key, &**reward))
+        .collect();
+
+    let obligations = calc_reward_obligations(&staking_state, &rewards)?;
+    let excess = calc_rewards_vault_excess(get_token_balance(rewards_vault_ai)?, obligations);
+    check!(lamports <= excess, LucraErrorCode::RewardsOutstanding)?;
+    msg!("Moving {} of {} over the {} stakers can still claim", lamports, excess, obligations);
+
+    system_state.transfer_from_reward_vault(
+        program_id,
+        rewards_vault_ai,
+        arb_coffer_ai,
+        rewards_vault_transfer_authority_ai,
+        token_program_ai,
+        lamports,
+    )
+}
+
+/// What the rewards vault still owes. `rewards` has to be every reward that hasn't been swept, newest first, the
+/// staking state's current reward leading and each reward followed by its previous one. The index liability
+/// covers drops made before it was kept only through their accounts, so the larger of the two is owed.
+pub fn calc_reward_obligations(staking_state: &StakingState, rewards: &[(&Pubkey, &Reward)]) -> LucraResult<u64> {
+    let unswept = staking_state.reward_cursor
+        .checked_sub(staking_state.swept_reward_cursor)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    check_eq!(rewards.len() as u64, unswept, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut expected = staking_state.current_reward_pubkey;
+    let mut cursor = staking_state.reward_cursor;
+    let mut indexed: u64 = 0;
+    let mut unindexed: u64 = 0;
+    for (key, reward) in rewards {
+        cursor -= 1;
+        check_eq!(*key, &expected, LucraErrorCode::InvalidAccountInput)?;
+        check!(reward.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check_eq!(reward.reward_cursor, cursor, LucraErrorCode::InvalidAccountInput)?;
+
+        let owed = if reward.in_reward_index { &mut indexed } else { &mut unindexed };
+        *owed = owed
+            .checked_add(reward.total.saturating_sub(reward.claimed))
+            .ok_or(math_err!())?;
+        expected = reward.previous_reward;
+    }
+
+    indexed
+        .max(staking_state.reward_index_liability)
+        .checked_add(unindexed)
+        .ok_or(math_err!())
+}
+
+pub fn calc_rewards_vault_excess(vault_msol: u64, obligations: u64) -> u64 {
+    vault_msol.saturating_sub(obligations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_error<T: std::fmt::Debug>(result: LucraResult<T>, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    // Drops `totals` in order, the way DropReward links each reward to the one before it
+    fn drop_rewards(totals: &[u64]) -> (StakingState, Vec<(Pubkey, Reward)>) {
+        let mut staking_state = StakingState::default();
+        let mut rewards = vec![];
+        for total in totals {
+            let mut reward = Reward::default();
+            reward.meta_data.is_initialized = true;
+            reward.previous_reward = staking_state.current_reward_pubkey;
+            reward.reward_cursor = staking_state.reward_cursor;
+            reward.total = *total;
+            reward.in_reward_index = true;
+
+            let key = Pubkey::new_unique();
+            staking_state.current_reward_pubkey = key;
+            staking_state.reward_cursor += 1;
+            rewards.push((key, reward));
+        }
+        rewards.reverse();
+
+        (staking_state, rewards)
+    }
+
+    fn newest_first(rewards: &[(Pubkey, Reward)]) -> Vec<(&Pubkey, &Reward)> {
+        rewards.iter().map(|(key, reward)| (key, reward)).collect()
+    }
+
+    #[test]
+    fn test_every_unswept_reward_is_owed_less_its_claims() {
+        let (staking_state, mut rewards) = drop_rewards(&[1_000, 2_000, 3_000]);
+        assert_eq!(calc_reward_obligations(&staking_state, &newest_first(&rewards)).unwrap(), 6_000);
+
+        // Claims through the account come off
+        rewards[1].1.claimed = 600;
+        assert_eq!(calc_reward_obligations(&staking_state, &newest_first(&rewards)).unwrap(), 5_400);
+    }
+
+    #[test]
+    fn test_the_index_liability_outlasts_the_sweep() {
+        let (mut staking_state, rewards) = drop_rewards(&[1_000, 2_000, 3_000]);
+        staking_state.reward_index_liability = 6_000;
+        assert_eq!(calc_reward_obligations(&staking_state, &newest_first(&rewards)).unwrap(), 6_000);
+
+        // Sweeping moves nothing out of an indexed reward, what it owes stays owed through the index
+        staking_state.swept_reward_cursor = 2;
+        assert_eq!(calc_reward_obligations(&staking_state, &newest_first(&rewards[..1])).unwrap(), 6_000);
+        staking_state.swept_reward_cursor = 3;
+        assert_eq!(calc_reward_obligations(&staking_state, &[]).unwrap(), 6_000);
+
+        // Drops from before the liability was kept still count through their accounts
+        staking_state.swept_reward_cursor = 0;
+        staking_state.reward_index_liability = 2_500;
+        assert_eq!(calc_reward_obligations(&staking_state, &newest_first(&rewards)).unwrap(), 6_000);
+    }
+
+    #[test]
+    fn test_rewards_outside_the_index_add_to_its_liability() {
+        let (mut staking_state, mut rewards) = drop_rewards(&[1_000, 2_000, 3_000]);
+        rewards[0].1.in_reward_index = false;
+        rewards[0].1.claimed = 1_000;
+        staking_state.reward_index_liability = 4_000;
+
+        assert_eq!(calc_reward_obligations(&staking_state, &newest_first(&rewards)).unwrap(), 6_000);
+    }
+
+    #[test]
+    fn test_swept_rewards_are_no_longer_owed() {
+        let (mut staking_state, rewards) = drop_rewards(&[1_000, 2_000, 3_000]);
+        staking_state.swept_reward_cursor = 1;
+
+        assert_eq!(calc_reward_obligations(&staking_state, &newest_first(&rewards[..2])).unwrap(), 5_000);
+        // The swept reward can't be passed to raise the floor either
+        assert_error(calc_reward_obligations(&staking_state, &newest_first(&rewards)), LucraErrorCode::InvalidAccountInput);
+
+        staking_state.swept_reward_cursor = 3;
+        assert_eq!(calc_reward_obligations(&staking_state, &[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_an_unswept_reward_cant_be_left_out() {
+        let (staking_state, rewards) = drop_rewards(&[1_000, 2_000, 3_000]);
+        let rewards = newest_first(&rewards);
+
+        assert_error(calc_reward_obligations(&staking_state, &rewards[..2]), LucraErrorCode::InvalidAccountInput);
+        assert_error(calc_reward_obligations(&staking_state, &[]), LucraErrorCode::InvalidAccountInput);
+
+        // Swapping an owed reward for one of any other chain, or out of order, is refused
+        let (_, other) = drop_rewards(&[1]);
+        assert_error(calc_reward_obligations(&staking_state, &[rewards[0], rewards[1], (other[0].0, other[0].1)]), LucraErrorCode::InvalidAccountInput);
+        assert_error(calc_reward_obligations(&staking_state, &[rewards[1], rewards[0], rewards[2]]), LucraErrorCode::InvalidAccountInput);
+    }
+
+    #[test]
+    fn test_a_closed_reward_in_the_chain_is_refused() {
+        let (staking_state, mut rewards) = drop_rewards(&[1_000, 2_000]);
+        rewards[1].1.meta_data.is_initialized = false;
+
+        assert_error(calc_reward_obligations(&staking_state, &newest_first(&rewards)), LucraErrorCode::InvalidAccountInput);
+    }
+
+    #[test]
+    fn test_only_the_excess_over_the_obligations_can_move() {
+        let (staking_state, rewards) = drop_rewards(&[1_000, 2_000]);
+        let obligations = calc_reward_obligations(&staking_state, &newest_first(&rewards)).unwrap();
+
+        assert_eq!(calc_rewards_vault_excess(3_500, obligations), 500);
+        assert_eq!(calc_rewards_vault_excess(3_000, obligations), 0);
+        // A vault already under its obligations has nothing to give
+        assert_eq!(calc_rewards_vault_excess(2_000, obligations), 0);
+    }
+}
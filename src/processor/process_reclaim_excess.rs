@@ -0,0 +1,80 @@
+use std::cell::Ref;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        dao_authority::check_dao_authority,
+        settlement::{check_reclaim_deadline_passed, check_settled},
+        spl::get_token_balance,
+        vaults::verify_msol_vault,
+    },
+    instruction::Instruction,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::ReclaimExcess);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::ReclaimExcess {} => {
+            msg!("Instruction: Reclaim Excess");
+            process_reclaim_excess(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Final DAO instruction of a settlement. Sweeps whatever is left in the msol vault once the deadline passed.
+#[inline(never)]
+pub fn process_reclaim_excess(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 6;
+    let accounts = array_ref!(accounts, 0, NUM_FIXED);
+    let [
+        system_state_ai,        // read
+        dao_authority_ai,       // read
+        msol_vault_ai,          // write
+        to_account_ai,          // write
+        transfer_authority_ai,  // read
+        token_program_ai,       // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(to_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_settled(&system_state)?;
+    check_reclaim_deadline_passed(system_state.settlement_timestamp, clock.unix_timestamp)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+
+    let leftover = get_token_balance(msol_vault_ai)?;
+    check!(leftover > 0, LucraErrorCode::InvalidAmount)?;
+
+    system_state.transfer_from_msol_vault(
+        program_id,
+        msol_vault_ai,
+        to_account_ai,
+        transfer_authority_ai,
+        token_program_ai,
+        leftover,
+    )
+}
@@ -0,0 +1,101 @@
+use std::cell::{Ref, RefMut};
+
+use arrayref::array_ref;
+use rust_decimal::Decimal;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::PRICE_HISTORY_ID,
+    helpers::collateral::{find_msol_sol_oracle, get_collateral_valuation_factor},
+    helpers::peg_band::{penalty_band_width, penalty_multiplier_per_band},
+    helpers::penalty_rebate::{apply_penalty_reduction, recompute_journal},
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
+    lucra_core::penalty::{calc_day_penalty, penalized_loan},
+    state::{
+        LoanType,
+        MataLoan,
+        PriceHistory,
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::RecomputePenalty);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RecomputePenalty { } => {
+            msg!("Instruction: Recompute Penalty");
+            process_recompute_penalty(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Anyone can run this once the DAO corrected the price history, it lowers penalty the loan was charged
+/// at the misreported prices and credits the loan owner for penalty that was already harvested
+#[inline(never)]
+pub fn process_recompute_penalty(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 3;
+    let trailing_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // read
+        loan_ai,            // write
+        price_history_ai,   // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(price_history_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(price_history_ai.key, &PRICE_HISTORY_ID, LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+
+    // Days are recomputed with the same collateral valuation DeterminePenalty would use now
+    let msol_sol_oracle_ai = find_msol_sol_oracle(trailing_accounts, &system_state);
+    let marinade_state_ai = msol_sol_oracle_ai
+        .and_then(|oracle_ai| trailing_accounts.iter().position(|ai| ai.key == oracle_ai.key))
+        .and_then(|position| trailing_accounts.get(position + 1));
+    let collateral_factor = if matches!(loan.loan_type, LoanType::NativeSol | LoanType::LpBacked) {
+        Decimal::ONE
+    } else {
+        get_collateral_valuation_factor(&system_state, msol_sol_oracle_ai, marinade_state_ai, clock)?
+    };
+
+    let price_history: Box<Ref<PriceHistory>> = PriceHistory::load_checked(price_history_ai, program_id)?;
+    let charged_loan = penalized_loan(&loan);
+    let mut journal = loan.penalty_journal;
+    let reduction = recompute_journal(&mut journal, &price_history, |history| {
+        calc_day_penalty(
+            history,
+            &charged_loan,
+            collateral_factor,
+            penalty_band_width(&system_state),
+            penalty_multiplier_per_band(&system_state),
+        )
+    })?;
+    check!(reduction > 0, LucraErrorCode::AlreadyProcessed)?;
+
+    loan.penalty_journal = journal;
+    apply_penalty_reduction(&mut loan, reduction)?;
+
+    Ok(())
+}
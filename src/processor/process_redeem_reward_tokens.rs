@@ -2,27 +2,45 @@ use std::cell::Ref;
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     sysvar::{clock::Clock, Sysvar},
     pubkey::Pubkey,
     program_pack::Pack,
 };
 use spl_token::state::Account;
-use rust_decimal::{Decimal, prelude::ToPrimitive};
 use crate::{
     error::{
         check_assert,
-        LucraError,
         LucraErrorCode,
         LucraResult,
         SourceFileId,
     },
     helpers::constants::LUCRA_SOL_ORACLE,
     helpers::oracle::*,
+    helpers::reward_sunset::{apply_grace_boost, calc_grace_boost, check_reward_redemption_open},
+    helpers::reward_tokens::{calc_reward_lamports, calc_reward_lucra},
+    helpers::settlement::check_not_settled,
+    helpers::spl::{verify_token_program, verify_user_token_account},
+    helpers::vaults::verify_reward_mint,
+    instruction::Instruction,
     state::SystemState,
 };
 
 declare_check_assert_macros!(SourceFileId::RedeemRewardTokens);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RedeemRewardTokens {
+            reward_tokens,
+        } => {
+            msg!("Instruction: Redeem Oracle Reward");
+            process_redeem_reward_tokens(program_id, reward_tokens, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[inline(never)]
 pub fn process_redeem_reward_tokens(program_id: &Pubkey, reward_tokens: u64, accounts: &[AccountInfo]) -> LucraResult {
     check!(reward_tokens != 0, LucraErrorCode::InvalidAmount)?;
@@ -50,17 +68,20 @@ pub fn process_redeem_reward_tokens(program_id: &Pubkey, reward_tokens: u64, acc
 
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(lucra_sol_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(user_reward_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(user_lucra_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_reward_account_ai, token_program_ai)?;
+    verify_user_token_account(user_lucra_account_ai, token_program_ai)?;
     check_eq!(lucra_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(reward_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    verify_token_program(token_program_ai)?;
 
     check_eq!(lucra_sol_oracle_ai.key, &LUCRA_SOL_ORACLE, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(user_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check_reward_redemption_open(&system_state, clock.unix_timestamp)?;
     check_eq!(&system_state.lucra_mint.address, lucra_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    verify_reward_mint(&system_state, reward_mint_ai.key)?;
     let reward_account = Account::unpack(&user_reward_account_ai.data.borrow())?;
     let lucra_account = Account::unpack(&user_lucra_account_ai.data.borrow())?;
     check_eq!(reward_mint_ai.key, &reward_account.mint, LucraErrorCode::InvalidAccountInput)?;
@@ -68,17 +89,13 @@ pub fn process_redeem_reward_tokens(program_id: &Pubkey, reward_tokens: u64, acc
     check_eq!(reward_account.owner, lucra_account.owner, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(lucra_mint_ai.key, &lucra_account.mint, LucraErrorCode::InvalidAccountInput)?;
 
-    let reward = system_state.reward_fee as u64;
-    let total_reward_lamports = reward.checked_mul(reward_tokens)
-        .ok_or(math_err!())?;
+    let total_reward_lamports = apply_grace_boost(
+        calc_reward_lamports(system_state.reward_fee, reward_tokens, system_state.reward_mint_decimals)?,
+        calc_grace_boost(&system_state, clock.unix_timestamp),
+    )?;
 
-    let lucra_price = get_oracle_price(lucra_sol_oracle_ai, clock)?;
-    let reward_to_mint = Decimal::from(total_reward_lamports)
-        .checked_div(lucra_price)
-        .ok_or(math_err!())?
-        .floor()
-        .to_u64()
-        .ok_or(math_err!())?;
+    let lucra_price = get_oracle_price(&system_state, lucra_sol_oracle_ai, clock)?;
+    let reward_to_mint = calc_reward_lucra(total_reward_lamports, lucra_price)?;
 
     system_state.mint_lucra(
         program_id,
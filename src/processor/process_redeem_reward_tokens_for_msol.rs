@@ -0,0 +1,127 @@
+use std::cell::Ref;
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    sysvar::{clock::Clock, Sysvar},
+    pubkey::Pubkey,
+    program_pack::Pack,
+};
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::collateral::get_marinade_msol_rate,
+    helpers::constants::LUCRA_SOL_ORACLE,
+    helpers::oracle::*,
+    helpers::reward_sunset::{apply_grace_boost, calc_grace_boost, check_reward_redemption_open},
+    helpers::reward_tokens::{calc_reward_lamports, calc_reward_msol},
+    helpers::settlement::check_not_settled,
+    helpers::spl::{get_token_balance, verify_token_program, verify_user_token_account},
+    helpers::vaults::{verify_reward_mint, verify_rewards_vault, verify_rewards_vault_covers},
+    idl,
+    instruction::Instruction,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::RedeemRewardTokensForMsol);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RedeemRewardTokensForMsol {
+            reward_tokens,
+        } => {
+            msg!("Instruction: Redeem Reward Tokens For Msol");
+            process_redeem_reward_tokens_for_msol(program_id, reward_tokens, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const REDEEM_REWARD_TOKENS_FOR_MSOL_SIZE: usize = idl::REDEEM_REWARD_TOKENS_FOR_MSOL.len();
+
+// Pays the same value RedeemRewardTokens mints in lucra as msol out of the rewards vault, so a redemption
+// doesn't have to inflate the lucra supply. Both share the deadline and the grace period boost.
+#[inline(never)]
+pub fn process_redeem_reward_tokens_for_msol(program_id: &Pubkey, reward_tokens: u64, accounts: &[AccountInfo]) -> LucraResult {
+    check!(reward_tokens != 0, LucraErrorCode::InvalidAmount)?;
+
+    const NUM_FIXED: usize = REDEEM_REWARD_TOKENS_FOR_MSOL_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,                        // read
+
+        user_reward_account_ai,                 // write
+        user_msol_account_ai,                   // write
+        user_authority_ai,                      // read
+
+        reward_mint_ai,                         // write
+
+        rewards_vault_ai,                       // write
+        rewards_vault_transfer_authority_ai,    // read
+
+        marinade_state_ai,                      // read
+        lucra_sol_oracle_ai,                    // read
+
+        token_program_ai,                       // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(lucra_sol_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(user_reward_account_ai, token_program_ai)?;
+    verify_user_token_account(user_msol_account_ai, token_program_ai)?;
+    check_eq!(reward_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(rewards_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_token_program(token_program_ai)?;
+
+    check_eq!(lucra_sol_oracle_ai.key, &LUCRA_SOL_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(user_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check!(user_msol_account_ai.key != rewards_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check_reward_redemption_open(&system_state, clock.unix_timestamp)?;
+    verify_reward_mint(&system_state, reward_mint_ai.key)?;
+    verify_rewards_vault(&system_state, rewards_vault_ai.key)?;
+    let reward_account = Account::unpack(&user_reward_account_ai.data.borrow())?;
+    let msol_account = Account::unpack(&user_msol_account_ai.data.borrow())?;
+    check_eq!(reward_mint_ai.key, &reward_account.mint, LucraErrorCode::InvalidAccountInput)?;
+    check!(reward_account.amount >= reward_tokens, LucraErrorCode::InvalidAmount)?;
+    check_eq!(reward_account.owner, msol_account.owner, LucraErrorCode::InvalidAccountInput)?;
+
+    let total_reward_lamports = apply_grace_boost(
+        calc_reward_lamports(system_state.reward_fee, reward_tokens, system_state.reward_mint_decimals)?,
+        calc_grace_boost(&system_state, clock.unix_timestamp),
+    )?;
+
+    let lucra_price = get_oracle_price(&system_state, lucra_sol_oracle_ai, clock)?;
+    let msol_owed = calc_reward_msol(total_reward_lamports, lucra_price, get_marinade_msol_rate(marinade_state_ai)?)?;
+    check!(msol_owed > 0, LucraErrorCode::InvalidAmount)?;
+    verify_rewards_vault_covers(get_token_balance(rewards_vault_ai)?, msol_owed)?;
+
+    system_state.burn_reward(
+        reward_mint_ai,
+        user_reward_account_ai,
+        reward_tokens,
+        user_authority_ai,
+        token_program_ai,
+    )?;
+
+    system_state.transfer_from_reward_vault(
+        program_id,
+        rewards_vault_ai,
+        user_msol_account_ai,
+        rewards_vault_transfer_authority_ai,
+        token_program_ai,
+        msol_owed,
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar},
+};
+use legends_loadable_trait::Loadable;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::settlement::check_not_settled,
+    helpers::solana::create_pda_account,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::{find_keeper_registry, keeper_registry_seeds},
+    state::{
+        KeeperRegistry,
+        SystemState,
+    },
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::RegisterKeeper);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RegisterKeeper { multiplier } => {
+            msg!("Instruction: Register Keeper");
+            process_register_keeper(program_id, multiplier, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const REGISTER_KEEPER_SIZE: usize = idl::REGISTER_KEEPER.len();
+
+// DAO instruction. The registry is a PDA of the system state created with the first keeper, registering a keeper
+// again sets its multiplier.
+#[inline(never)]
+pub fn process_register_keeper(program_id: &Pubkey, multiplier: Bps, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = REGISTER_KEEPER_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // read
+        keeper_registry_ai,     // write
+        keeper_ai,              // read
+        dao_authority_ai,       // read
+        payer_ai,               // write
+        system_program_ai,      // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(payer_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+
+    let (keeper_registry, bump_seed) = find_keeper_registry(system_state_ai.key);
+    check_eq!(keeper_registry_ai.key, &keeper_registry, LucraErrorCode::InvalidAccountInput)?;
+
+    if keeper_registry_ai.data_is_empty() {
+        let rent = Rent::get()?;
+        create_pda_account(
+            payer_ai,
+            keeper_registry_ai,
+            rent.minimum_balance(size_of::<KeeperRegistry>()),
+            size_of::<KeeperRegistry>() as u64,
+            program_id,
+            &[&keeper_registry_seeds(system_state_ai.key, &[bump_seed])],
+            system_program_ai,
+        )?;
+        KeeperRegistry::load_mut(keeper_registry_ai)?.init(system_state_ai.key);
+    }
+
+    let mut registry: RefMut<KeeperRegistry> = KeeperRegistry::load_mut_checked(keeper_registry_ai, program_id)?;
+    check_eq!(&registry.system_state, system_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    registry.register(keeper_ai.key, multiplier)
+}
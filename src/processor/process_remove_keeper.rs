@@ -0,0 +1,64 @@
+use std::cell::{Ref, RefMut};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::find_keeper_registry,
+    state::{
+        KeeperRegistry,
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::RemoveKeeper);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RemoveKeeper {} => {
+            msg!("Instruction: Remove Keeper");
+            process_remove_keeper(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const REMOVE_KEEPER_SIZE: usize = idl::REMOVE_KEEPER.len();
+
+// DAO instruction. Still allowed during settlement, a keeper that's removed only loses its boost and its pass
+// through restricted cranking.
+#[inline(never)]
+pub fn process_remove_keeper(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = REMOVE_KEEPER_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // read
+        keeper_registry_ai,     // write
+        keeper_ai,              // read
+        dao_authority_ai,       // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_eq!(keeper_registry_ai.key, &find_keeper_registry(system_state_ai.key).0, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut registry: RefMut<KeeperRegistry> = KeeperRegistry::load_mut_checked(keeper_registry_ai, program_id)?;
+    check_eq!(&registry.system_state, system_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    registry.remove(keeper_ai.key)
+}
@@ -0,0 +1,67 @@
+use std::cell::{Ref, RefMut};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::find_transfer_allowlist,
+    state::{
+        SystemState,
+        TransferAllowlist,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::RemoveTransferDestination);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RemoveTransferDestination {} => {
+            msg!("Instruction: Remove Transfer Destination");
+            process_remove_transfer_destination(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const REMOVE_TRANSFER_DESTINATION_SIZE: usize = idl::REMOVE_TRANSFER_DESTINATION.len();
+
+// DAO instruction. Works on a destination that's still waiting out its delay, which is what the delay is for.
+// Still allowed during settlement, a removed destination can only receive less.
+#[inline(never)]
+pub fn process_remove_transfer_destination(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = REMOVE_TRANSFER_DESTINATION_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // read
+        transfer_allowlist_ai,  // write
+        destination_ai,         // read
+        dao_authority_ai,       // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_eq!(transfer_allowlist_ai.key, &find_transfer_allowlist(system_state_ai.key).0, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut allowlist: RefMut<TransferAllowlist> = TransferAllowlist::load_mut_checked(transfer_allowlist_ai, program_id)?;
+    check_eq!(&allowlist.system_state, system_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    allowlist.remove(destination_ai.key)?;
+    msg!("Transfer destination {} removed", destination_ai.key);
+
+    Ok(())
+}
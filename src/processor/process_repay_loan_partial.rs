@@ -0,0 +1,421 @@
+use std::cell::RefMut;
+
+use anchor_lang::prelude::*;
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        collateral::backfill_msol_collateral,
+        math::verify_remaining_loan_amount,
+        settlement::check_not_settled,
+        spl::get_token_balance,
+        vaults::{verify_mata_mint, verify_msol_vault},
+    },
+    idl,
+    instruction::Instruction,
+    lucra_core::{
+        interest::{calc_amount_owed, interest_accrued},
+        quote::calc_repaid_share,
+    },
+    state::{
+        LoanType,
+        MataBucket,
+        MataLoan,
+        staking::StakingAccount,
+        SystemState,
+    },
+    units::Mata,
+};
+
+declare_check_assert_macros!(SourceFileId::RepayLoanPartial);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RepayLoanPartial {
+            mata,
+            release_collateral,
+        } => {
+            msg!("Instruction: Repay Loan Partial");
+            process_repay_loan_partial(program_id, mata, release_collateral, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const REPAY_LOAN_PARTIAL_SIZE: usize = idl::REPAY_LOAN_PARTIAL.len();
+const REPAY_LOAN_PARTIAL_WITH_LOCKED_STAKE_SIZE: usize = idl::REPAY_LOAN_PARTIAL_WITH_LOCKED_STAKE.len();
+
+/// Collateral the repaid share of the debt frees
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PartialRelease {
+    pub sol_collateral: u64,
+    pub staking_collateral: u64,
+}
+
+/// How a repayment splits between the interest accrued and the debt
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepaymentSplit {
+    pub interest: u64,
+    pub principal: u64,
+}
+
+/// Repaying `mata` pays the interest accrued first and the debt with the rest, up to the amount owed
+pub fn calc_repayment_split(loan: &MataLoan, mata: u64) -> LucraResult<RepaymentSplit> {
+    check!(mata <= calc_amount_owed(loan)?, LucraErrorCode::InvalidAmount)?;
+    let interest = mata.min(interest_accrued(loan));
+
+    Ok(RepaymentSplit {
+        interest,
+        principal: mata - interest,
+    })
+}
+
+/// Repaying `mata` frees the same share of the collateral the loan has left as it is of the amount owed,
+/// rounded down
+pub fn calc_partial_release(loan: &MataLoan, mata: u64) -> LucraResult<PartialRelease> {
+    let amount_owed = calc_amount_owed(loan)?;
+    Ok(PartialRelease {
+        sol_collateral: calc_repaid_share(loan.calc_remaining_sol(), mata, amount_owed)?,
+        staking_collateral: calc_repaid_share(loan.staking_collateral_amount, mata, amount_owed)?,
+    })
+}
+
+#[inline(never)]
+pub fn process_repay_loan_partial(program_id: &Pubkey, mata: u64, release_collateral: bool, accounts: &[AccountInfo]) -> LucraResult {
+    if accounts.len() >= REPAY_LOAN_PARTIAL_WITH_LOCKED_STAKE_SIZE {
+        repay_loan_partial_with_locked_stake(program_id, mata, release_collateral, accounts)
+    } else {
+        check!(accounts.len() >= REPAY_LOAN_PARTIAL_SIZE, LucraErrorCode::InvalidAccountInput)?;
+        repay_loan_partial(program_id, mata, release_collateral, accounts)
+    }
+}
+
+#[inline(never)]
+fn repay_loan_partial(program_id: &Pubkey, mata: u64, release_collateral: bool, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = REPAY_LOAN_PARTIAL_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        marinade_state_ai,          // read
+        loan_ai,                    // write
+        user_account_ai,            // read
+        user_msol_account_ai,       // write
+        mata_mint_ai,               // write
+        user_mata_account_ai,       // write
+        msol_vault_authority_ai,    // read
+        msol_vault_ai,              // write
+        token_program_ai,           // read
+    ] = accounts;
+
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    check_eq!(loan.loan_type, LoanType::Default, LucraErrorCode::InvalidLoanType)?;
+
+    repay_loan(
+        program_id,
+        &mut loan,
+        mata,
+        release_collateral,
+        system_state_ai,
+        marinade_state_ai,
+        user_account_ai,
+        user_msol_account_ai,
+        mata_mint_ai,
+        user_mata_account_ai,
+        msol_vault_authority_ai,
+        msol_vault_ai,
+        token_program_ai,
+    )?;
+
+    Ok(())
+}
+
+#[inline(never)]
+fn repay_loan_partial_with_locked_stake(program_id: &Pubkey, mata: u64, release_collateral: bool, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = REPAY_LOAN_PARTIAL_WITH_LOCKED_STAKE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        marinade_state_ai,          // read
+        loan_ai,                    // write
+        user_account_ai,            // read
+        user_msol_account_ai,       // write
+        mata_mint_ai,               // write
+        user_mata_account_ai,       // write
+        msol_vault_authority_ai,    // read
+        msol_vault_ai,              // write
+        staking_account_ai,         // write
+        token_program_ai,           // read
+    ] = accounts;
+
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    check_eq!(loan.loan_type, LoanType::LucraBacked, LucraErrorCode::InvalidLoanType)?;
+    let mut staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(staking_account_ai, program_id)?;
+    check_eq!(&staking_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let release = repay_loan(
+        program_id,
+        &mut loan,
+        mata,
+        release_collateral,
+        system_state_ai,
+        marinade_state_ai,
+        user_account_ai,
+        user_msol_account_ai,
+        mata_mint_ai,
+        user_mata_account_ai,
+        msol_vault_authority_ai,
+        msol_vault_ai,
+        token_program_ai,
+    )?;
+    staking_account.remove_locked_total(release.staking_collateral);
+
+    Ok(())
+}
+
+// Burns `mata` of what the loan owes, interest first, and, when asked, sends back the share of the collateral it paid off. The
+// rebate and whatever the loan's msol earned stay with the loan until it's closed.
+#[inline(never)]
+#[allow(clippy::too_many_arguments)]
+fn repay_loan<'a>(
+    program_id: &Pubkey,
+    loan: &mut RefMut<MataLoan>,
+    mata: u64,
+    release_collateral: bool,
+    system_state_ai: &AccountInfo<'a>,
+    marinade_state_ai: &AccountInfo<'a>,
+    user_account_ai: &AccountInfo<'a>,
+    user_msol_account_ai: &AccountInfo<'a>,
+    mata_mint_ai: &AccountInfo<'a>,
+    user_mata_account_ai: &AccountInfo<'a>,
+    msol_vault_authority_ai: &AccountInfo<'a>,
+    msol_vault_ai: &AccountInfo<'a>,
+    token_program_ai: &AccountInfo<'a>,
+) -> LucraResult<PartialRelease> {
+    let clock = &Clock::get()?;
+
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    // Settlement values the debt at the frozen price, a close is the only way out then
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+    check!(user_msol_account_ai.key != msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.loan_mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    // The collateral a share is taken of isn't settled until the pending penalty is harvested
+    check_eq!(loan.penalty_to_harvest, 0, LucraErrorCode::PenaltyNotHarvested)?;
+    check!(mata > 0, LucraErrorCode::InvalidAmount)?;
+    let split = calc_repayment_split(loan, mata)?;
+    let remaining_loan_amount = loan.loan_amount
+        .checked_sub(split.principal)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    verify_remaining_loan_amount(Mata(remaining_loan_amount), system_state.min_loan_amount_mata)?;
+
+    let user_mata_account = Account::unpack(&user_mata_account_ai.data.borrow())?;
+    let user_msol_account = Account::unpack(&user_msol_account_ai.data.borrow())?;
+    check_eq!(&user_mata_account.mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_mata_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_msol_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check!(user_mata_account.amount >= mata, LucraErrorCode::InvalidAmount)?;
+
+    let release = if release_collateral {
+        // Taking collateral back is a partial close, it waits out the same timelock
+        check!(loan.loan_creation_date + system_state.epoch < clock.unix_timestamp, LucraErrorCode::Timelock)?;
+        calc_partial_release(loan, mata)?
+    } else {
+        PartialRelease::default()
+    };
+
+    system_state.burn_mata(
+        mata_mint_ai,
+        user_mata_account_ai,
+        mata,
+        user_account_ai,
+        token_program_ai,
+    )?;
+    // The interest was never minted for the loan, it comes off the total the way a close burns it
+    system_state.mata_supply.burn_bought_back(split.interest)?;
+    system_state.mata_supply.burn(MataBucket::Loan, split.principal)?;
+    if split.interest > 0 {
+        loan.interest_accrued -= split.interest;
+    }
+    loan.loan_amount = remaining_loan_amount;
+
+    if release.sol_collateral > 0 {
+        // Converted the way a close converts what it returns, capped at the loan's own msol
+        backfill_msol_collateral(loan, &system_state, get_token_balance(msol_vault_ai)?)?;
+        let marinade_state = ProgramAccount::<marinade_finance::state::State>::try_from(&marinade_finance::id(), marinade_state_ai)
+            .map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+        let msol_to_user = marinade_state
+            .calc_msol_from_lamports(release.sol_collateral)
+            .map_err(|_| math_err!())?
+            .min(loan.msol_collateral_amount);
+
+        system_state.transfer_from_msol_vault(
+            program_id,
+            msol_vault_ai,
+            user_msol_account_ai,
+            msol_vault_authority_ai,
+            token_program_ai,
+            msol_to_user,
+        )?;
+
+        loan.msol_collateral_amount = loan.msol_collateral_amount
+            .checked_sub(msol_to_user)
+            .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+        loan.sol_collateral_amount = loan.sol_collateral_amount
+            .checked_sub(release.sol_collateral)
+            .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+        system_state.remove_collateral(release.sol_collateral);
+    }
+    loan.staking_collateral_amount = loan.staking_collateral_amount
+        .checked_sub(release.staking_collateral)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+
+    Ok(release)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::native_token::LAMPORTS_PER_SOL;
+    use crate::{
+        lucra_core::interest::LOAN_INTEREST_VERSION,
+        state::{DataType, MetaData},
+    };
+
+    fn loan(loan_amount: u64, sol_collateral_amount: u64, staking_collateral_amount: u64) -> MataLoan {
+        MataLoan {
+            loan_amount,
+            sol_collateral_amount,
+            staking_collateral_amount,
+            ..MataLoan::default()
+        }
+    }
+
+    #[test]
+    fn test_release_is_the_repaid_share_of_the_collateral() {
+        // 10 SOL and 300 of staked value against 1,000 mata
+        let loan = loan(1_000_000_000, 10 * LAMPORTS_PER_SOL, 300_000_000);
+
+        assert_eq!(
+            calc_partial_release(&loan, 250_000_000).unwrap(),
+            PartialRelease { sol_collateral: 2_500_000_000, staking_collateral: 75_000_000 }
+        );
+        assert_eq!(
+            calc_partial_release(&loan, 1_000_000_000).unwrap(),
+            PartialRelease { sol_collateral: 10 * LAMPORTS_PER_SOL, staking_collateral: 300_000_000 }
+        );
+    }
+
+    #[test]
+    fn test_harvested_penalty_is_not_released_again() {
+        let loan = MataLoan {
+            penalty_harvested: 2 * LAMPORTS_PER_SOL,
+            ..loan(1_000_000_000, 10 * LAMPORTS_PER_SOL, 0)
+        };
+
+        // Half of the 8 SOL the loan has left
+        let release = calc_partial_release(&loan, 500_000_000).unwrap();
+        assert_eq!(release.sol_collateral, 4 * LAMPORTS_PER_SOL);
+    }
+
+    #[test]
+    fn test_release_rounds_down() {
+        let loan = loan(3, 10, 5);
+
+        assert_eq!(calc_partial_release(&loan, 1).unwrap(), PartialRelease { sol_collateral: 3, staking_collateral: 1 });
+        assert_eq!(calc_partial_release(&loan, 2).unwrap(), PartialRelease { sol_collateral: 6, staking_collateral: 3 });
+    }
+
+    #[test]
+    fn test_release_sums_to_no_more_than_the_collateral() {
+        let collateral = 10 * LAMPORTS_PER_SOL + 7;
+        let mut loan = loan(999_999_937, collateral, 0);
+        let mut released = 0;
+        for repayment in [123_456_789, 400_000_000, 1, 476_543_147] {
+            let release = calc_partial_release(&loan, repayment).unwrap();
+            released += release.sol_collateral;
+            loan.sol_collateral_amount -= release.sol_collateral;
+            loan.loan_amount -= repayment;
+        }
+
+        assert_eq!(loan.loan_amount, 0);
+        assert_eq!(released, collateral);
+    }
+
+    #[test]
+    fn test_overpayment_is_rejected() {
+        let loan = loan(1_000, 10 * LAMPORTS_PER_SOL, 0);
+
+        assert!(matches!(
+            calc_partial_release(&loan, 1_001).unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::InvalidAmount,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_interest_is_paid_before_the_debt() {
+        // 1,000 mata of debt and 50 of interest on a loan that accrues it
+        let loan = MataLoan {
+            meta_data: MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true),
+            interest_accrued: 50_000_000,
+            ..loan(1_000_000_000, 10 * LAMPORTS_PER_SOL, 0)
+        };
+
+        assert_eq!(
+            calc_repayment_split(&loan, 30_000_000).unwrap(),
+            RepaymentSplit { interest: 30_000_000, principal: 0 }
+        );
+        assert_eq!(
+            calc_repayment_split(&loan, 1_050_000_000).unwrap(),
+            RepaymentSplit { interest: 50_000_000, principal: 1_000_000_000 }
+        );
+        assert!(calc_repayment_split(&loan, 1_050_000_001).is_err());
+    }
+
+    #[test]
+    fn test_repaying_the_debt_with_interest_pending_keeps_collateral_back() {
+        let loan = MataLoan {
+            meta_data: MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true),
+            interest_accrued: 50_000_000,
+            ..loan(1_000_000_000, 10_500_000_000, 0)
+        };
+
+        // 1,000 of the 1,050 mata owed frees 1,000 / 1,050 of the collateral, the interest's share stays
+        let release = calc_partial_release(&loan, 1_000_000_000).unwrap();
+        assert_eq!(release.sol_collateral, 10 * LAMPORTS_PER_SOL);
+        assert_eq!(calc_partial_release(&loan, 1_050_000_000).unwrap().sol_collateral, 10_500_000_000);
+    }
+}
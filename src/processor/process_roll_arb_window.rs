@@ -0,0 +1,93 @@
+use std::cell::{Ref, RefMut};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        arb_window::roll_arb_window,
+        keepers::{resolve_crank_caller, split_keeper},
+        reward_tokens::mint_whole_reward,
+        settlement::check_not_settled,
+        vaults::verify_reward_mint,
+    },
+    instruction::Instruction,
+    state::{
+        ArbState,
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::RollArbWindow);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RollArbWindow {} => {
+            msg!("Instruction: Roll Arb Window");
+            process_roll_arb_window(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Anyone can run this contract to move the arb limits window up to today so the first arb of the day doesn't have to
+#[inline(never)]
+pub fn process_roll_arb_window(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    let (accounts, keeper) = split_keeper(program_id, accounts);
+    const NUM_FIXED: usize = 6;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // read
+        arb_state_ai,               // write
+
+        user_reward_account_ai,     // write
+        reward_mint_ai,             // write
+        reward_mint_authority_ai,   // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(arb_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_reward_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(reward_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    let keeper_multiplier = resolve_crank_caller(program_id, &system_state, keeper)?;
+    check_not_settled(&system_state)?;
+    check_eq!(&system_state.arb_state, arb_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    verify_reward_mint(&system_state, reward_mint_ai.key)?;
+
+    let mut arb_state: RefMut<ArbState> = ArbState::load_mut_checked(arb_state_ai, program_id)?;
+
+    // Rolling a window that is already current is a no-op, and only a roll that did something gets paid
+    if !roll_arb_window(&mut arb_state, clock.unix_timestamp) {
+        return Ok(());
+    }
+
+    mint_whole_reward(
+        program_id,
+        &system_state,
+        reward_mint_ai,
+        user_reward_account_ai,
+        1,
+        keeper_multiplier,
+        reward_mint_authority_ai,
+        token_program_ai
+    )?;
+
+    Ok(())
+}
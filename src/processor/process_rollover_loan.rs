@@ -0,0 +1,376 @@
+use std::cell::RefMut;
+
+use anchor_lang::prelude::*;
+use arrayref::array_ref;
+use bytemuck::Zeroable;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    native_token::LAMPORTS_PER_SOL,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use rust_decimal::prelude::ToPrimitive;
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        collateral::{
+            backfill_msol_collateral,
+            calc_msol_lamport_value,
+            find_msol_sol_oracle,
+            get_collateral_valuation_factor,
+            get_marinade_msol_rate,
+        },
+        constants::{SOL_MATA_ORACLE, SOL_USDC_ORACLE, SOL_USDT_ORACLE},
+        loan_history::open_collateral_history,
+        math::verify_minimum_loan_amount,
+        oracle::{get_mata_price, get_sol_price, verify_sol_usd_oracle},
+        peg_band::update_peg,
+        penalty_rebate::calc_payable_rebate,
+        settlement::check_not_settled,
+        spl::{get_token_balance, spl_token_transfer},
+        vaults::{verify_mata_mint, verify_msol_vault},
+    },
+    idl,
+    instruction::Instruction,
+    lucra_core::{
+        interest::{calc_amount_owed, interest_accrued, LOAN_INTEREST_VERSION},
+        quote::calc_loan_amount,
+    },
+    state::{
+        DataType,
+        LoanType,
+        MataBucket,
+        MataLoan,
+        MetaData,
+        SystemState,
+    },
+    units::{Lamports, Mata},
+};
+
+declare_check_assert_macros!(SourceFileId::RolloverLoan);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::RolloverLoan {
+            new_lamports,
+        } => {
+            msg!("Instruction: Rollover Loan");
+            process_rollover_loan(program_id, new_lamports, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// What closing the loan settles and what the new loan needs, both at the same prices
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RolloverTerms {
+    /// Mata a close would burn
+    pub old_debt: u64,
+    /// msol a close would send back, the remaining collateral and any payable rebate
+    pub old_msol_returned: u64,
+    /// Mata the new loan mints
+    pub new_debt: u64,
+    /// msol the new loan keeps in the vault
+    pub new_msol_collateral: u64,
+}
+
+/// The differences the user settles. At most one side of each pair is non zero.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RolloverPlan {
+    pub mata_from_user: u64,
+    pub mata_to_user: u64,
+    pub msol_from_user: u64,
+    pub msol_to_user: u64,
+}
+
+pub fn plan_rollover(terms: &RolloverTerms) -> RolloverPlan {
+    RolloverPlan {
+        mata_from_user: terms.old_debt.saturating_sub(terms.new_debt),
+        mata_to_user: terms.new_debt.saturating_sub(terms.old_debt),
+        msol_from_user: terms.new_msol_collateral.saturating_sub(terms.old_msol_returned),
+        msol_to_user: terms.old_msol_returned.saturating_sub(terms.new_msol_collateral),
+    }
+}
+
+const ROLLOVER_LOAN_SIZE: usize = idl::ROLLOVER_LOAN.len();
+
+// Closes a default loan and opens it again with `new_lamports` of collateral on the same account, without the
+// user holding the whole debt in between. The user only settles the difference in mata and in msol. Every
+// check a close and a create would make applies, the supply cap and the collateral checks against the end
+// state. Everything the user owes is taken before anything is paid out.
+#[inline(never)]
+pub fn process_rollover_loan(program_id: &Pubkey, new_lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = ROLLOVER_LOAN_SIZE;
+    let trailing_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        marinade_state_ai,          // read
+        loan_ai,                    // write
+        msol_vault_ai,              // write
+        msol_vault_authority_ai,    // read
+        mata_mint_ai,               // write
+        mata_mint_authority_ai,     // read
+        user_account_ai,            // read
+        user_mata_account_ai,       // write
+        user_msol_account_ai,       // write
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        sol_mata_oracle_ai,         // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_mata_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    verify_mata_mint(&system_state, mata_mint_ai.key)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+    check!(user_msol_account_ai.key != msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    check_eq!(loan.loan_type, LoanType::Default, LucraErrorCode::InvalidLoanType)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.loan_mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    // The same timelock a close has, a rollover can't be used to get out of a loan early
+    check!(loan.loan_creation_date + system_state.epoch < clock.unix_timestamp, LucraErrorCode::Timelock)?;
+
+    let user_mata_account = Account::unpack(&user_mata_account_ai.data.borrow())?;
+    let user_msol_account = Account::unpack(&user_msol_account_ai.data.borrow())?;
+    check_eq!(&user_mata_account.mint, mata_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_mata_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&user_msol_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    // The new loan is opened at the peg and prices a create would see
+    if system_state.peg_check_enabled {
+        check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+
+        let mata_market_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+        update_peg(&mut system_state, mata_market_price);
+        check!(!system_state.peg_broken, LucraErrorCode::BrokenPeg)?;
+    }
+
+    check!(Lamports(new_lamports) > system_state.min_deposit, LucraErrorCode::InvalidAmount)?;
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let msol_sol_oracle_ai = find_msol_sol_oracle(trailing_accounts, &system_state);
+    let collateral_factor = get_collateral_valuation_factor(&system_state, msol_sol_oracle_ai, Some(marinade_state_ai), clock)?;
+    let lamport_dollar_value = sol_market_price
+        .checked_mul(new_lamports.into())
+        .ok_or(math_err!())?
+        .checked_div(LAMPORTS_PER_SOL.into())
+        .ok_or(math_err!())?
+        .checked_mul(collateral_factor)
+        .ok_or(math_err!())?;
+    let new_loan_amount = calc_loan_amount(lamport_dollar_value, system_state.collateral_requirement)?;
+    verify_minimum_loan_amount(new_loan_amount, system_state.min_loan_amount_mata)?;
+
+    // What a close would return, converted to msol the way the close and the marinade deposit do
+    let sol_to_return = loan.calc_remaining_sol();
+    backfill_msol_collateral(&mut loan, &system_state, get_token_balance(msol_vault_ai)?)?;
+    let msol_collateral_lamports = calc_msol_lamport_value(loan.msol_collateral_amount, get_marinade_msol_rate(marinade_state_ai)?)?;
+    let rebate = calc_payable_rebate(loan.penalty_rebate, msol_collateral_lamports, sol_to_return);
+    let lamports_returned = sol_to_return
+        .checked_add(rebate)
+        .ok_or(math_err!())?;
+    let marinade_state = ProgramAccount::<marinade_finance::state::State>::try_from(&marinade_finance::id(), marinade_state_ai)
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    // The interest is settled with the old debt, a rollover doesn't forgive it
+    let terms = RolloverTerms {
+        old_debt: calc_amount_owed(&loan)?,
+        old_msol_returned: marinade_state.calc_msol_from_lamports(lamports_returned).map_err(|_| math_err!())?,
+        new_debt: new_loan_amount.get(),
+        new_msol_collateral: marinade_state.calc_msol_from_lamports(new_lamports).map_err(|_| math_err!())?,
+    };
+    let plan = plan_rollover(&terms);
+
+    // The loan bucket follows the debt, the interest comes off the total the way a close burns it
+    if terms.new_debt >= loan.loan_amount {
+        system_state.mata_supply.mint(MataBucket::Loan, terms.new_debt - loan.loan_amount)?;
+    } else {
+        system_state.mata_supply.burn(MataBucket::Loan, loan.loan_amount - terms.new_debt)?;
+    }
+    system_state.mata_supply.burn_bought_back(interest_accrued(&loan))?;
+    check!(Mata(system_state.mata_supply.total()?) <= system_state.maximum_outstanding_mata, LucraErrorCode::InvalidAmount)?;
+    check!(user_mata_account.amount >= plan.mata_from_user, LucraErrorCode::InvalidAmount)?;
+    check!(user_msol_account.amount >= plan.msol_from_user, LucraErrorCode::InvalidAmount)?;
+
+    // The user pays in first
+    if plan.mata_from_user > 0 {
+        system_state.burn_mata(
+            mata_mint_ai,
+            user_mata_account_ai,
+            plan.mata_from_user,
+            user_account_ai,
+            token_program_ai,
+        )?;
+    }
+    if plan.msol_from_user > 0 {
+        spl_token_transfer(
+            user_msol_account_ai,
+            msol_vault_ai,
+            plan.msol_from_user,
+            user_account_ai,
+            &[],
+            token_program_ai,
+        )?;
+    }
+
+    if plan.mata_to_user > 0 {
+        system_state.mint_mata(
+            program_id,
+            mata_mint_ai,
+            user_mata_account_ai,
+            plan.mata_to_user,
+            mata_mint_authority_ai,
+            token_program_ai,
+        )?;
+    }
+    if plan.msol_to_user > 0 {
+        system_state.transfer_from_msol_vault(
+            program_id,
+            msol_vault_ai,
+            user_msol_account_ai,
+            msol_vault_authority_ai,
+            token_program_ai,
+            plan.msol_to_user,
+        )?;
+    }
+
+    system_state.remove_collateral(sol_to_return);
+    system_state.add_collateral(new_lamports);
+
+    // The old terms and its penalty journal are settled and start over like a new loan. The part of the
+    // rebate the old msol couldn't pay is still owed and carries over.
+    let unpaid_rebate = loan.penalty_rebate - rebate;
+    restart_loan(&mut loan);
+    loan.meta_data = MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true);
+    loan.loan_type = LoanType::Default;
+    loan.owner = *user_account_ai.key;
+    loan.collateral_rate = system_state.collateral_requirement.get();
+    loan.sol_collateral_amount = new_lamports;
+    open_collateral_history(&mut loan, new_lamports);
+    loan.msol_collateral_amount = terms.new_msol_collateral;
+    loan.market_price = sol_market_price.floor().to_u64().ok_or(math_err!())?;
+    loan.loan_amount = terms.new_debt;
+    loan.loan_mint = *mata_mint_ai.key;
+    loan.loan_creation_date = clock.unix_timestamp;
+    loan.last_day_penalty_was_checked = clock.unix_timestamp;
+    loan.penalty_rebate = unpaid_rebate;
+
+    Ok(())
+}
+
+/// Zeroes the loan for its new terms. The creator and loan index it was created with are kept, they derive
+/// the address the loan is still at and HarvestPenalty checks it against them.
+fn restart_loan(loan: &mut MataLoan) {
+    let (creator, loan_index) = (loan.creator, loan.loan_index);
+    *loan = MataLoan::zeroed();
+    loan.creator = creator;
+    loan.loan_index = loan_index;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What the user ends up with after closing the loan and opening the new one in two transactions
+    fn close_then_open(terms: &RolloverTerms, mata: u64, msol: u64) -> Option<(u64, u64)> {
+        let mata = mata.checked_sub(terms.old_debt)?.checked_add(terms.new_debt)?;
+        let msol = msol.checked_add(terms.old_msol_returned)?.checked_sub(terms.new_msol_collateral)?;
+
+        Some((mata, msol))
+    }
+
+    fn rollover(terms: &RolloverTerms, mata: u64, msol: u64) -> Option<(u64, u64)> {
+        let plan = plan_rollover(terms);
+        let mata = mata.checked_sub(plan.mata_from_user)?.checked_add(plan.mata_to_user)?;
+        let msol = msol.checked_sub(plan.msol_from_user)?.checked_add(plan.msol_to_user)?;
+
+        Some((mata, msol))
+    }
+
+    fn terms(old_debt: u64, old_msol_returned: u64, new_debt: u64, new_msol_collateral: u64) -> RolloverTerms {
+        RolloverTerms { old_debt, old_msol_returned, new_debt, new_msol_collateral }
+    }
+
+    #[test]
+    fn test_only_one_side_of_each_leg_moves() {
+        // More collateral, more debt
+        assert_eq!(plan_rollover(&terms(100, 50, 160, 80)), RolloverPlan { mata_to_user: 60, msol_from_user: 30, ..RolloverPlan::default() });
+        // Less collateral, less debt
+        assert_eq!(plan_rollover(&terms(160, 80, 100, 50)), RolloverPlan { mata_from_user: 60, msol_to_user: 30, ..RolloverPlan::default() });
+        // Same collateral after the price moved up
+        assert_eq!(plan_rollover(&terms(100, 50, 120, 50)), RolloverPlan { mata_to_user: 20, ..RolloverPlan::default() });
+        // Nothing changed
+        assert_eq!(plan_rollover(&terms(100, 50, 100, 50)), RolloverPlan::default());
+    }
+
+    #[test]
+    fn test_end_state_matches_close_then_open() {
+        for terms in [terms(100, 50, 160, 80), terms(160, 80, 100, 50), terms(100, 50, 120, 45), terms(100, 50, 100, 52)] {
+            assert_eq!(rollover(&terms, 1_000, 1_000), close_then_open(&terms, 1_000, 1_000));
+        }
+    }
+
+    #[test]
+    fn test_debt_is_not_needed_up_front() {
+        // The user holds none of the mata a close would burn and the new loan mints more than the old one
+        let terms = terms(100, 50, 140, 70);
+
+        assert_eq!(close_then_open(&terms, 0, 20), None);
+        assert_eq!(rollover(&terms, 0, 20), Some((40, 0)));
+    }
+
+    #[test]
+    fn test_shortfall_is_caught_before_anything_moves() {
+        // Shrinking the loan still needs the mata the new loan no longer covers
+        let terms = terms(160, 80, 100, 50);
+
+        assert_eq!(plan_rollover(&terms).mata_from_user, 60);
+        assert_eq!(rollover(&terms, 59, 0), None);
+        assert_eq!(close_then_open(&terms, 59, 0), None);
+    }
+
+    #[test]
+    fn test_restarted_loan_keeps_its_address() {
+        use crate::{helpers::loan_address::verify_loan_address, lucra_core::pda::find_loan_address};
+
+        let creator = Pubkey::new_unique();
+        let mut loan = MataLoan::zeroed();
+        loan.creator = creator;
+        loan.loan_index = 3;
+        loan.loan_amount = 100;
+        loan.penalty_harvested = 10;
+
+        restart_loan(&mut loan);
+
+        assert_eq!((loan.creator, loan.loan_index), (creator, 3));
+        assert_eq!((loan.loan_amount, loan.penalty_harvested), (0, 0));
+        assert!(verify_loan_address(&find_loan_address(&creator, 3).0, &loan.creator, loan.loan_index).is_ok());
+    }
+}
@@ -0,0 +1,81 @@
+use std::cell::{Ref, RefMut};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::oracle::OracleAccount,
+    helpers::settlement::check_not_settled,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::find_collateral_registry,
+    state::{
+        CollateralRegistry,
+        SystemState,
+    },
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::SetCollateralType);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SetCollateralType {
+            collateral_type,
+            haircut_bps,
+            enabled,
+        } => {
+            msg!("Instruction: Set Collateral Type");
+            process_set_collateral_type(program_id, collateral_type, haircut_bps, enabled, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const SET_COLLATERAL_TYPE_SIZE: usize = idl::SET_COLLATERAL_TYPE.len();
+
+// DAO instruction. Sets a registered collateral type's oracle and haircut and turns loans against it on or off.
+// Turning it off is still allowed during settlement, loans already open against it are left as they are.
+#[inline(never)]
+pub fn process_set_collateral_type(
+    program_id: &Pubkey,
+    collateral_type: u8,
+    haircut_bps: Bps,
+    enabled: bool,
+    accounts: &[AccountInfo],
+) -> LucraResult {
+    const NUM_FIXED: usize = SET_COLLATERAL_TYPE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,        // read
+        collateral_registry_ai, // write
+        oracle_ai,              // read
+        dao_authority_ai,       // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    if enabled {
+        check_not_settled(&system_state)?;
+    }
+    check_eq!(collateral_registry_ai.key, &find_collateral_registry(system_state_ai.key).0, LucraErrorCode::InvalidAccountInput)?;
+    OracleAccount::load(&system_state, oracle_ai)?;
+
+    let mut registry: RefMut<CollateralRegistry> = CollateralRegistry::load_mut_checked(collateral_registry_ai, program_id)?;
+    check_eq!(&registry.system_state, system_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    registry.set(collateral_type, oracle_ai.key, haircut_bps, enabled)
+}
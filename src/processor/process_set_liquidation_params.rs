@@ -0,0 +1,62 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::liquidation::set_liquidation_params,
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
+    state::SystemState,
+    units::{Bps, CollateralRatio},
+};
+
+declare_check_assert_macros!(SourceFileId::SetLiquidationParams);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SetLiquidationParams {
+            liquidation_ratio,
+            liquidation_bonus,
+        } => {
+            msg!("Instruction: Set Liquidation Params");
+            process_set_liquidation_params(program_id, liquidation_ratio, liquidation_bonus, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// DAO instruction. Sets when loans can be liquidated and the liquidator's bonus, see helpers::liquidation
+#[inline(never)]
+pub fn process_set_liquidation_params(
+    program_id: &Pubkey,
+    liquidation_ratio: CollateralRatio,
+    liquidation_bonus: Bps,
+    accounts: &[AccountInfo],
+) -> LucraResult {
+    const NUM_FIXED: usize = 2;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // write
+        dao_authority_ai,   // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+    set_liquidation_params(&mut system_state, liquidation_ratio, liquidation_bonus)
+}
@@ -0,0 +1,86 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::{orca_swap, SOL_MATA_ORCA_AMM},
+    helpers::dao_authority::check_dao_authority,
+    helpers::lp_collateral::{find_lp_vault_authority, verify_lp_collateral_requirement},
+    helpers::settlement::check_not_settled,
+    helpers::spltokenswap::get_orca_pool_mint,
+    instruction::Instruction,
+    state::SystemState,
+    units::CollateralRatio,
+};
+
+declare_check_assert_macros!(SourceFileId::SetLpCollateral);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SetLpCollateral {
+            lp_collateral_requirement,
+        } => {
+            msg!("Instruction: Set Lp Collateral");
+            process_set_lp_collateral(program_id, lp_collateral_requirement, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// DAO instruction. Registers the vault LP backed loans deposit into and the requirement they're sized at,
+// a requirement of 0 stops new LP backed loans. The vault can't be swapped out once collateral is in it.
+#[inline(never)]
+pub fn process_set_lp_collateral(program_id: &Pubkey, lp_collateral_requirement: CollateralRatio, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 4;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // write
+        dao_authority_ai,   // read
+        sol_mata_pool_ai,   // read
+        lp_vault_ai,        // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_mata_pool_ai.owner, &orca_swap::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(lp_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(sol_mata_pool_ai.key, &SOL_MATA_ORCA_AMM, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+    verify_lp_collateral_requirement(&system_state, lp_collateral_requirement)?;
+
+    if system_state.lp_vault.address != Pubkey::default() {
+        check_eq!(&system_state.lp_vault.address, lp_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    }
+
+    let lp_mint = get_orca_pool_mint(sol_mata_pool_ai)?;
+    let (lp_vault_authority, lp_vault_authority_bump_seed) = find_lp_vault_authority(system_state_ai.key);
+    let lp_vault = Account::unpack(&lp_vault_ai.data.borrow())?;
+    check_eq!(&lp_vault.owner, &lp_vault_authority, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(&lp_vault.mint, &lp_mint, LucraErrorCode::InvalidAccountInput)?;
+    check!(lp_vault.delegate.is_none(), LucraErrorCode::InvalidAccountInput)?;
+    check!(lp_vault.close_authority.is_none(), LucraErrorCode::InvalidAccountInput)?;
+
+    system_state.lp_vault.address = *lp_vault_ai.key;
+    system_state.lp_vault.authority_bump_seed = lp_vault_authority_bump_seed;
+    system_state.lp_mint = lp_mint;
+    system_state.lp_collateral_requirement = lp_collateral_requirement;
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use std::cell::{Ref, RefMut};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
+    state::{
+        staking::StakingState,
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::SetMinDropAmount);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SetMinDropAmount {
+            min_drop_amount,
+        } => {
+            msg!("Instruction: Set Min Drop Amount");
+            process_set_min_drop_amount(program_id, min_drop_amount, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// DAO instruction. Drops under the minimum are skipped so stakers aren't paying more to claim than they get,
+// the yield stays in the msol vault and goes out with the next drop that clears it.
+#[inline(never)]
+pub fn process_set_min_drop_amount(program_id: &Pubkey, min_drop_amount: u64, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 3;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // read
+        staking_state_ai,   // write
+        dao_authority_ai,   // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(staking_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+    check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut staking_state: RefMut<StakingState> = StakingState::load_mut_checked(staking_state_ai, program_id)?;
+    staking_state.min_drop_amount = min_drop_amount;
+
+    Ok(())
+}
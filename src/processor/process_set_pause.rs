@@ -0,0 +1,145 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::get_dao_authority,
+    helpers::settlement::check_not_settled,
+    idl,
+    instruction::Instruction,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::SetPause);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SetPause {
+            loans,
+            staking,
+            arbitrage,
+        } => {
+            msg!("Instruction: Set Pause");
+            process_set_pause(program_id, loans, staking, arbitrage, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const SET_PAUSE_SIZE: usize = idl::SET_PAUSE.len();
+
+/// Sets which features are paused, true pauses one. The guardian can only pause, a call from it that would
+/// turn a paused feature back on fails as a whole.
+pub fn apply_pause(system_state: &mut SystemState, is_dao: bool, loans: bool, staking: bool, arbitrage: bool) -> LucraResult {
+    let unpauses = (!loans && !system_state.loans_enabled)
+        || (!staking && !system_state.staking_enabled)
+        || (!arbitrage && !system_state.arbitrage_enabled);
+    if unpauses {
+        check!(is_dao, LucraErrorCode::GuardianCannotUnpause)?;
+        // Same as UpdateState, nothing comes back on once the program is settled
+        check_not_settled(system_state)?;
+    }
+
+    system_state.loans_enabled = !loans;
+    system_state.staking_enabled = !staking;
+    system_state.arbitrage_enabled = !arbitrage;
+
+    Ok(())
+}
+
+// Signed by the DAO authority or the guardian in the system state. Pausing doesn't need the full UpdateState
+// proposal so it can be done as soon as something goes wrong, and it's still allowed during settlement.
+#[inline(never)]
+pub fn process_set_pause(program_id: &Pubkey, loans: bool, staking: bool, arbitrage: bool, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = SET_PAUSE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // write
+        authority_ai,       // read
+    ] = accounts;
+
+    check_eq!(authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    let is_dao = authority_ai.key == &get_dao_authority(&system_state);
+    if !is_dao {
+        check!(system_state.guardian != Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+        check_eq!(authority_ai.key, &system_state.guardian, LucraErrorCode::InvalidAccountInput)?;
+    }
+
+    apply_pause(&mut system_state, is_dao, loans, staking, arbitrage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    fn running_state() -> SystemState {
+        let mut system_state = SystemState::zeroed();
+        system_state.loans_enabled = true;
+        system_state.staking_enabled = true;
+        system_state.arbitrage_enabled = true;
+        system_state
+    }
+
+    fn assert_error(result: LucraResult, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_guardian_can_only_pause() {
+        let mut system_state = running_state();
+
+        apply_pause(&mut system_state, false, true, false, false).unwrap();
+        assert!(!system_state.loans_enabled);
+        assert!(system_state.staking_enabled);
+        assert!(system_state.arbitrage_enabled);
+
+        // Loans are already paused so they have to stay paused in the next call
+        apply_pause(&mut system_state, false, true, false, true).unwrap();
+        assert!(!system_state.arbitrage_enabled);
+
+        assert_error(apply_pause(&mut system_state, false, false, true, true), LucraErrorCode::GuardianCannotUnpause);
+        // Nothing is written when the call fails
+        assert!(!system_state.loans_enabled);
+        assert!(system_state.staking_enabled);
+    }
+
+    #[test]
+    fn test_dao_can_unpause() {
+        let mut system_state = running_state();
+        apply_pause(&mut system_state, false, true, true, true).unwrap();
+
+        apply_pause(&mut system_state, true, false, true, false).unwrap();
+        assert!(system_state.loans_enabled);
+        assert!(!system_state.staking_enabled);
+        assert!(system_state.arbitrage_enabled);
+    }
+
+    #[test]
+    fn test_nothing_is_unpaused_after_settlement() {
+        let mut system_state = running_state();
+        system_state.settlement_active = true;
+
+        apply_pause(&mut system_state, false, true, true, true).unwrap();
+        assert_error(apply_pause(&mut system_state, true, false, true, true), LucraErrorCode::SettlementActive);
+        assert!(!system_state.loans_enabled);
+    }
+}
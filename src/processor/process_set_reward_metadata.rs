@@ -0,0 +1,118 @@
+use std::cell::Ref;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::account::verify_program_account,
+    helpers::constants::{token_metadata, CREATOR_AUTHORITY},
+    helpers::settlement::check_not_settled,
+    helpers::token_metadata::{
+        create_metadata_accounts_v3,
+        find_metadata_address,
+        CreateMetadataAccountV3,
+        MAX_NAME_LENGTH,
+        MAX_SYMBOL_LENGTH,
+        MAX_URI_LENGTH,
+    },
+    helpers::reward_tokens::verify_reward_mint_accounts,
+    instruction::Instruction,
+    lucra_core::pda::reward_mint_authority_seeds,
+    state::SystemState,
+};
+
+declare_check_assert_macros!(SourceFileId::SetRewardMetadata);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SetRewardMetadata {
+            name,
+            symbol,
+            uri,
+        } => {
+            msg!("Instruction: Set Reward Metadata");
+            process_set_reward_metadata(program_id, name, symbol, uri, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Creator instruction. The reward mint authority is a program PDA so only the program can sign for its metadata.
+#[inline(never)]
+pub fn process_set_reward_metadata(program_id: &Pubkey, name: String, symbol: String, uri: String, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 7;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,                // read
+        creator_authority_ai,           // write
+        reward_mint_ai,                 // read
+        reward_mint_authority_ai,       // read
+        metadata_ai,                    // write
+        metadata_program_ai,            // read
+        system_program_ai,              // read
+    ] = accounts;
+
+    check_eq!(creator_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(creator_authority_ai.key, &CREATOR_AUTHORITY, LucraErrorCode::InvalidAccountInput)?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(reward_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_program_account(metadata_program_ai, &token_metadata::id())?;
+    check_eq!(system_program_ai.key, &solana_program::system_program::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    check!(name.len() <= MAX_NAME_LENGTH, LucraErrorCode::InvalidAccountInput)?;
+    check!(symbol.len() <= MAX_SYMBOL_LENGTH, LucraErrorCode::InvalidAccountInput)?;
+    check!(uri.len() <= MAX_URI_LENGTH, LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+    check_eq!(metadata_ai.key, &find_metadata_address(reward_mint_ai.key).0, LucraErrorCode::InvalidAccountInput)?;
+    verify_reward_mint_accounts(program_id, &system_state, reward_mint_ai.key, reward_mint_authority_ai.key)?;
+
+    let bump_seed = [system_state.reward_mint.authority_bump_seed];
+    let authority_signer_seeds: &[&[u8]] = &reward_mint_authority_seeds(system_state_ai.key, &bump_seed);
+
+    create_metadata_accounts_v3(
+        metadata_ai,
+        reward_mint_ai,
+        reward_mint_authority_ai,
+        creator_authority_ai,
+        creator_authority_ai,
+        system_program_ai,
+        metadata_program_ai,
+        &[authority_signer_seeds],
+        CreateMetadataAccountV3 { name, symbol, uri },
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::constants::REWARD_MINT_AUTHORITY_SEED;
+
+    #[test]
+    fn test_signer_seeds_derive_the_reward_mint_authority() {
+        let system_state = Pubkey::new_unique();
+        let (authority, bump_seed) = SystemState::find_reward_mint_authority(&system_state);
+
+        let derived = Pubkey::create_program_address(
+            &[system_state.as_ref(), REWARD_MINT_AUTHORITY_SEED, &[bump_seed]],
+            &crate::id(),
+        )
+        .unwrap();
+
+        assert_eq!(derived, authority);
+    }
+}
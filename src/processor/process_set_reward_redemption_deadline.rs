@@ -0,0 +1,67 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::reward_sunset::set_reward_redemption_deadline,
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
+    state::SystemState,
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::SetRewardRedemptionDeadline);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SetRewardRedemptionDeadline {
+            deadline,
+            grace_period,
+            grace_boost,
+        } => {
+            msg!("Instruction: Set Reward Redemption Deadline");
+            process_set_reward_redemption_deadline(program_id, deadline, grace_period, grace_boost, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// DAO instruction. Starts, moves or cancels the sunset of the reward token, see helpers::reward_sunset
+#[inline(never)]
+pub fn process_set_reward_redemption_deadline(
+    program_id: &Pubkey,
+    deadline: i64,
+    grace_period: i64,
+    grace_boost: Bps,
+    accounts: &[AccountInfo],
+) -> LucraResult {
+    const NUM_FIXED: usize = 2;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // write
+        dao_authority_ai,   // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+    set_reward_redemption_deadline(&mut system_state, deadline, grace_period, grace_boost, clock.unix_timestamp)
+}
@@ -0,0 +1,63 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    instruction::Instruction,
+    state::staking::StakingAccount,
+};
+
+declare_check_assert_macros!(SourceFileId::SetStakingRecoveryKey);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SetStakingRecoveryKey {
+            recovery_key,
+        } => {
+            msg!("Instruction: Set Staking Recovery Key");
+            process_set_staking_recovery_key(program_id, recovery_key, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// A frozen account can't change its recovery key, otherwise a thief could name their own key and skip
+// the unfreeze delay. Once a recovery key is set it has to sign off on its replacement.
+#[inline(never)]
+pub fn process_set_staking_recovery_key(program_id: &Pubkey, recovery_key: Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 2;
+    let fixed_accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        staking_account_ai,             // write
+        owner_ai,                       // read
+    ] = fixed_accounts;
+
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    let mut staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(staking_account_ai, program_id)?;
+    check_eq!(&staking_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check!(!staking_account.frozen, LucraErrorCode::StakingAccountFrozen)?;
+
+    if staking_account.recovery_key != Pubkey::default() {
+        let current_recovery_key_ai = accounts.get(NUM_FIXED).ok_or(throw_err!(LucraErrorCode::InvalidAccountInput))?;
+        check_eq!(current_recovery_key_ai.key, &staking_account.recovery_key, LucraErrorCode::InvalidAccountInput)?;
+        check_eq!(current_recovery_key_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    }
+
+    staking_account.recovery_key = recovery_key;
+
+    Ok(())
+}
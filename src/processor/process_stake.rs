@@ -3,6 +3,7 @@ use std::cell::{Ref, RefMut};
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     sysvar::{clock::Clock, Sysvar},
     pubkey::Pubkey,
     program_pack::Pack,
@@ -16,7 +17,15 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
+    helpers::events::{emit_event, split_event_queue},
+    helpers::reward_expiry::skip_swept_rewards,
+    helpers::reward_index::add_indexed_stake,
+    helpers::settlement::check_not_settled,
+    helpers::spl::get_token_balance,
+    helpers::staking_freeze::{bind_staking_account, check_staking_account_not_frozen},
+    instruction::Instruction,
     state::{
+        EventKind,
         staking::{
             StakeBalance,
             StakingAccount,
@@ -28,11 +37,25 @@ use crate::{
 
 declare_check_assert_macros!(SourceFileId::Stake);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::Stake {
+            lucra,
+        } => {
+            msg!("Instruction: Stake");
+            process_stake(program_id, lucra, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[inline(never)]
 pub fn process_stake(program_id: &Pubkey, lucra: u64, accounts: &[AccountInfo]) -> LucraResult {
     check!(lucra > 0, LucraErrorCode::InvalidAmount)?;
 
     const NUM_FIXED: usize = 12;
+    let (accounts, event_queue_ai) = split_event_queue(program_id, accounts);
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,                // read
@@ -62,6 +85,7 @@ pub fn process_stake(program_id: &Pubkey, lucra: u64, accounts: &[AccountInfo])
     check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
     check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
     check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
     let staking_state: RefMut<StakingState> = StakingState::load_mut_checked(staking_state_ai, program_id)?;
@@ -73,10 +97,13 @@ pub fn process_stake(program_id: &Pubkey, lucra: u64, accounts: &[AccountInfo])
     check_eq!(staked_lucra_account.mint, staking_state.stake_mint.address, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&staked_lucra_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&staking_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(staking_account.owner, stake_balance.owner, LucraErrorCode::InvalidAccountInput)?;
+    check_staking_account_not_frozen(&staking_account, clock.unix_timestamp)?;
+    skip_swept_rewards(&mut stake_balance, &staking_state);
     check!(staking_state.reward_cursor == stake_balance.reward_cursor, LucraErrorCode::RewardsOutstanding)?;
     check_eq!(&stake_balance.balances.deposit_vault, deposit_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&stake_balance.balances.stake_vault, stake_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let staked_before = get_token_balance(stake_vault_ai)?;
+    bind_staking_account(&mut stake_balance, staking_account_ai.key, &staking_account, staked_before)?;
     check_eq!(&staking_state.stake_mint.address, staked_lucra_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
 
     stake_balance.transfer_from_deposit_to_stake(
@@ -88,6 +115,7 @@ pub fn process_stake(program_id: &Pubkey, lucra: u64, accounts: &[AccountInfo])
         token_program_ai,
         lucra,
     )?;
+    stake_balance.stake_checkpoints.record(clock.slot, staked_before, get_token_balance(stake_vault_ai)?);
 
     let staking_timeframe_weight = stake_balance.staking_timeframe.weight();
     let weighted_spl_token_amount = lucra
@@ -101,10 +129,20 @@ pub fn process_stake(program_id: &Pubkey, lucra: u64, accounts: &[AccountInfo])
         mint_authority_ai,
         token_program_ai,
     )?;
+    add_indexed_stake(&mut stake_balance, weighted_spl_token_amount)?;
 
     stake_balance.last_stake_timestamp = clock.unix_timestamp;
+    // Stake put in just before a drop could be taken out right after it, the balance sits the next drop out
     stake_balance.increment_reward_cursor(staking_state.reward_cursor);
     staking_account.add_total(lucra);
 
-    Ok(())
+    emit_event(
+        program_id,
+        event_queue_ai,
+        system_state_ai.key,
+        EventKind::Stake,
+        stake_balance_ai.key,
+        [lucra, weighted_spl_token_amount],
+        clock.slot,
+    )
 }
\ No newline at end of file
@@ -6,13 +6,14 @@ use std::{
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
+    program::set_return_data,
     sysvar::{clock::Clock, rent::Rent, Sysvar},
     pubkey::Pubkey,
     program_pack::Pack,
 };
 use spl_token::state::Account;
 use legends_loadable_trait::Loadable;
-use rust_decimal::{Decimal, prelude::ToPrimitive};
 use crate::{
     error::{
         check_assert,
@@ -21,10 +22,13 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
-    helpers::constants::{SOL_USDC_ORACLE, SOL_USDT_ORACLE, LUCRA_SOL_ORACLE},
-    helpers::{oracle::*, spl::spl_token_burn},
+    helpers::constants::{SOL_USDC_ORACLE, SOL_USDT_ORACLE, LUCRA_SOL_ORACLE, UNSTAKE_ALL},
+    helpers::events::{emit_event, split_event_queue},
+    helpers::{math::{calc_unstakeable_lucra, verify_unstake_keeps_pledges}, oracle::*, reward_expiry::skip_swept_rewards, reward_index::remove_indexed_stake, spl::{get_token_balance, spl_token_burn}, staking_freeze::{bind_staking_account, check_staking_account_not_frozen}, unstake_timelock::{calc_claimable_at, UNSTAKE_TIMELOCK_VERSION}},
+    instruction::Instruction,
     state::{
         DataType,
+        EventKind,
         MetaData,
         staking::{
             PendingWithdrawal,
@@ -39,10 +43,36 @@ use crate::{
 declare_check_assert_macros!(SourceFileId::StartUnstake);
 
 #[inline(never)]
-pub fn process_start_unstake(program_id: &Pubkey, lucra: u64, accounts: &[AccountInfo]) -> LucraResult {
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::StartUnstake {
+            lucra,
+        } => {
+            msg!("Instruction: Start Unstake");
+            process_start_unstake(program_id, lucra, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// The lucra a StartUnstake for `requested` unstakes from a stake balance holding `balance_lucra`, UNSTAKE_ALL
+/// being as much of it as the `unstakeable` lucra the pledges leave allows
+fn resolve_unstake_amount(requested: u64, balance_lucra: u64, unstakeable: u64) -> LucraResult<u64> {
+    let lucra = if requested == UNSTAKE_ALL { balance_lucra } else { requested };
     check!(lucra > 0, LucraErrorCode::InvalidAmount)?;
+    check!(lucra <= balance_lucra, LucraErrorCode::InvalidAmount)?;
+
+    let lucra = if requested == UNSTAKE_ALL { lucra.min(unstakeable) } else { lucra };
+    check!(lucra > 0, LucraErrorCode::StakingAccountLocked)?;
+    check!(lucra <= unstakeable, LucraErrorCode::StakingAccountLocked)?;
 
+    Ok(lucra)
+}
+
+#[inline(never)]
+pub fn process_start_unstake(program_id: &Pubkey, lucra: u64, accounts: &[AccountInfo]) -> LucraResult {
     const NUM_FIXED: usize = 15;
+    let (accounts, event_queue_ai) = split_event_queue(program_id, accounts);
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,                // read
@@ -74,18 +104,19 @@ pub fn process_start_unstake(program_id: &Pubkey, lucra: u64, accounts: &[Accoun
     check_eq!(pending_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(stake_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(user_staked_lucra_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(sol_usdt_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(sol_usdc_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(lucra_sol_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
 
-    check_eq!(sol_usdc_oracle_ai.key, &SOL_USDC_ORACLE, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(sol_usdt_oracle_ai.key, &SOL_USDT_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
     check_eq!(lucra_sol_oracle_ai.key, &LUCRA_SOL_ORACLE, LucraErrorCode::InvalidAccountInput)?;
 
     check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
-    check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+    // Stakers can always leave during settlement
+    if !system_state.settlement_active {
+        check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+    }
     check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
     let staking_state: Ref<StakingState> = StakingState::load_checked(staking_state_ai, program_id)?;
     let mut staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(staking_account_ai, program_id)?;
@@ -99,7 +130,10 @@ pub fn process_start_unstake(program_id: &Pubkey, lucra: u64, accounts: &[Accoun
     check_eq!(staked_lucra_account.mint, staking_state.stake_mint.address, LucraErrorCode::InvalidAccountInput)?;
 
     check_eq!(&staking_account.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_staking_account_not_frozen(&staking_account, clock.unix_timestamp)?;
     check_eq!(&stake_balance.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&stake_balance.balances.stake_vault, stake_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    bind_staking_account(&mut stake_balance, staking_account_ai.key, &staking_account, stake_vault.amount)?;
     check!(
         rent.is_exempt(pending_withdrawal_ai.lamports(), size_of::<PendingWithdrawal>()),
         LucraErrorCode::NotRentExempt
@@ -107,26 +141,27 @@ pub fn process_start_unstake(program_id: &Pubkey, lucra: u64, accounts: &[Accoun
     let mut pending_withdrawal: RefMut<PendingWithdrawal> = PendingWithdrawal::load_mut(pending_withdrawal_ai)?;
     check!(!pending_withdrawal.meta_data.is_initialized, LucraErrorCode::Default)?;
 
-    let lucra_market_price = get_lucra_price(lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-    let value_of_lucra_to_be_unlocked = Decimal::from(lucra)
-        .checked_mul(lucra_market_price)
-        .ok_or(math_err!())?
-        .floor()
-        .to_u64()
-        .ok_or(math_err!())?;
-    let value_locked_up = Decimal::from(stake_vault.amount)
-        .checked_mul(lucra_market_price)
-        .ok_or(math_err!())?
-        .floor()
-        .to_u64()
-        .ok_or(math_err!())?;
+    let lucra_market_price = get_lucra_price(&system_state, lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
 
+    skip_swept_rewards(&mut stake_balance, &staking_state);
     check!(staking_state.reward_cursor == stake_balance.reward_cursor, LucraErrorCode::RewardsOutstanding)?;
     check_eq!(&staking_state.stake_mint.address, staked_lucra_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check!(value_locked_up - staking_account.locked_total >= value_of_lucra_to_be_unlocked, LucraErrorCode::OutstandingLoans)?;
-    check!(stake_balance.last_stake_timestamp + (system_state.epoch * staking_timeframe.timeframe_multiplier()) <= clock.unix_timestamp, LucraErrorCode::StakingAccountNotUnlocked)?;
+
+    // Pledges are valued across the staking account, the balance can't give up more than the account can
+    let unstakeable = calc_unstakeable_lucra(staking_account.total, lucra_market_price, staking_account.locked_total)?;
+    let lucra = match resolve_unstake_amount(lucra, stake_vault.amount, unstakeable) {
+        Ok(lucra) => lucra,
+        Err(e) => {
+            // UIs simulating the unstake show the most it can be for from the return data
+            set_return_data(&stake_vault.amount.min(unstakeable).to_le_bytes());
+            return Err(e);
+        }
+    };
+    verify_unstake_keeps_pledges(staking_account.total, lucra, lucra_market_price, staking_account.locked_total)?;
+    if !system_state.settlement_active {
+        check!(stake_balance.last_stake_timestamp + (system_state.epoch * staking_timeframe.timeframe_multiplier()) <= clock.unix_timestamp, LucraErrorCode::StakingAccountNotUnlocked)?;
+    }
     check_eq!(&stake_balance.balances.pending_vault, pending_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(&stake_balance.balances.stake_vault, stake_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
 
     let staked_lucra = lucra
         .checked_mul(staking_timeframe.weight())
@@ -140,7 +175,9 @@ pub fn process_start_unstake(program_id: &Pubkey, lucra: u64, accounts: &[Accoun
         &[],
         token_program_ai,
     )?;
+    remove_indexed_stake(&mut stake_balance, staked_lucra)?;
 
+    let staked_before = get_token_balance(stake_vault_ai)?;
     stake_balance.transfer_from_stake_to_pending(
         program_id,
         system_state_ai.key,
@@ -150,16 +187,16 @@ pub fn process_start_unstake(program_id: &Pubkey, lucra: u64, accounts: &[Accoun
         token_program_ai,
         lucra,
     )?;
+    stake_balance.stake_checkpoints.record(clock.slot, staked_before, get_token_balance(stake_vault_ai)?);
 
-    let end_timestamp = clock
-        .unix_timestamp
-        .checked_add(system_state.epoch)
-        .ok_or(math_err!())?;
+    // The pending withdrawal can be ended straight away during settlement
+    let claimable_at = calc_claimable_at(staking_timeframe, clock.unix_timestamp, system_state.settlement_active)?;
 
-    pending_withdrawal.meta_data = MetaData::new(DataType::PendingWithdrawal, 0, true);
+    pending_withdrawal.meta_data = MetaData::new(DataType::PendingWithdrawal, UNSTAKE_TIMELOCK_VERSION, true);
     pending_withdrawal.stake_balance = *stake_balance_ai.key;
     pending_withdrawal.start_timestamp = clock.unix_timestamp;
-    pending_withdrawal.end_timestamp = end_timestamp;
+    pending_withdrawal.end_timestamp = claimable_at;
+    pending_withdrawal.claimable_at = claimable_at;
     pending_withdrawal.lucra = lucra;
     pending_withdrawal.open();
 
@@ -167,5 +204,47 @@ pub fn process_start_unstake(program_id: &Pubkey, lucra: u64, accounts: &[Accoun
 
     staking_account.remove_total(lucra);
 
-    Ok(())
-}
\ No newline at end of file
+    emit_event(
+        program_id,
+        event_queue_ai,
+        system_state_ai.key,
+        EventKind::StartUnstake,
+        stake_balance_ai.key,
+        [lucra, staked_lucra],
+        clock.slot,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_error(result: LucraResult<u64>, expected: LucraErrorCode) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code, .. } if lucra_error_code == expected
+        ));
+    }
+
+    #[test]
+    fn test_unstake_all_takes_what_the_pledges_leave() {
+        assert_eq!(resolve_unstake_amount(UNSTAKE_ALL, 1_000, u64::MAX).unwrap(), 1_000);
+        // The account's other balances cover part of the pledges, so more is unstakeable than this balance has
+        assert_eq!(resolve_unstake_amount(UNSTAKE_ALL, 1_000, 5_000).unwrap(), 1_000);
+        assert_eq!(resolve_unstake_amount(UNSTAKE_ALL, 1_000, 400).unwrap(), 400);
+
+        assert_error(resolve_unstake_amount(UNSTAKE_ALL, 1_000, 0), LucraErrorCode::StakingAccountLocked);
+        assert_error(resolve_unstake_amount(UNSTAKE_ALL, 0, 400), LucraErrorCode::InvalidAmount);
+    }
+
+    #[test]
+    fn test_partially_locked_stake_rejects_more_than_is_unstakeable() {
+        assert_eq!(resolve_unstake_amount(400, 1_000, 400).unwrap(), 400);
+        assert_error(resolve_unstake_amount(401, 1_000, 400), LucraErrorCode::StakingAccountLocked);
+        assert_error(resolve_unstake_amount(1_000, 1_000, 0), LucraErrorCode::StakingAccountLocked);
+
+        // More than the balance holds is still a bad amount, locked or not
+        assert_error(resolve_unstake_amount(1_001, 1_000, 400), LucraErrorCode::InvalidAmount);
+        assert_error(resolve_unstake_amount(0, 1_000, 400), LucraErrorCode::InvalidAmount);
+    }
+}
@@ -0,0 +1,126 @@
+use std::cell::{Ref, RefMut};
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        account::{add_lamports, close_account},
+        reward_expiry::{calc_unclaimed_reward, check_reward_expired, record_reward_sweep},
+        keepers::{resolve_crank_caller, split_keeper},
+        reward_tokens::mint_whole_reward,
+        settlement::check_not_settled,
+        spl::get_token_balance,
+        vaults::{verify_arb_coffer, verify_reward_mint, verify_rewards_vault},
+    },
+    instruction::Instruction,
+    state::{
+        staking::{Reward, StakingState},
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::SweepExpiredReward);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::SweepExpiredReward {} => {
+            msg!("Instruction: Sweep Expired Reward");
+            process_sweep_expired_reward(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Anyone can run this contract once a reward's claim window has closed. The unclaimed msol goes from the
+/// rewards vault to the arb coffer and the reward account is closed.
+#[inline(never)]
+pub fn process_sweep_expired_reward(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    let (accounts, keeper) = split_keeper(program_id, accounts);
+    const NUM_FIXED: usize = 11;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,                        // read
+        staking_state_ai,                       // write
+        reward_ai,                              // write
+        rewards_vault_ai,                       // write
+        arb_coffer_ai,                          // write
+        rewards_vault_transfer_authority_ai,    // read
+        receiver_ai,                            // write
+
+        user_reward_account_ai,                 // write
+        reward_mint_ai,                         // write
+        reward_mint_authority_ai,               // read
+        token_program_ai,                       // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(staking_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(reward_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(rewards_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(arb_coffer_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_reward_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(reward_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+    check!(receiver_ai.key != reward_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    let keeper_multiplier = resolve_crank_caller(program_id, &system_state, keeper)?;
+    check_not_settled(&system_state)?;
+    check_eq!(&system_state.staking_state, staking_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    verify_rewards_vault(&system_state, rewards_vault_ai.key)?;
+    verify_arb_coffer(&system_state, arb_coffer_ai.key)?;
+    verify_reward_mint(&system_state, reward_mint_ai.key)?;
+
+    let mut staking_state: RefMut<StakingState> = StakingState::load_mut_checked(staking_state_ai, program_id)?;
+    let mut reward: RefMut<Reward> = Reward::load_mut_checked(reward_ai, program_id)?;
+    check_reward_expired(&reward, &system_state, clock.unix_timestamp)?;
+    record_reward_sweep(&mut staking_state, &reward)?;
+
+    let unclaimed = calc_unclaimed_reward(&reward).min(get_token_balance(rewards_vault_ai)?);
+    if unclaimed > 0 {
+        system_state.transfer_from_reward_vault(
+            program_id,
+            rewards_vault_ai,
+            arb_coffer_ai,
+            rewards_vault_transfer_authority_ai,
+            token_program_ai,
+            unclaimed,
+        )?;
+    }
+    msg!("Swept {} unclaimed from reward {}", unclaimed, reward_ai.key);
+
+    // Nothing can be claimed against a reward under the swept cursor, so it doesn't matter if the
+    // account is opened back up after it is closed
+    reward.meta_data.is_initialized = false;
+    drop(reward);
+    let lamports = close_account(reward_ai);
+    add_lamports(receiver_ai, lamports);
+
+    // Pay the user for their efforts
+    mint_whole_reward(
+        program_id,
+        &system_state,
+        reward_mint_ai,
+        user_reward_account_ai,
+        1,
+        keeper_multiplier,
+        reward_mint_authority_ai,
+        token_program_ai,
+    )?;
+
+    Ok(())
+}
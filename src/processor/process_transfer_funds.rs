@@ -3,7 +3,9 @@ use std::cell::Ref;
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     pubkey::Pubkey,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
 use crate::{
     error::{
@@ -12,15 +14,44 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
-    helpers::constants::DAO_AUTHORITY,
-    state::SystemState,
+    helpers::dao_authority::check_dao_authority,
+    helpers::lp_collateral::transfer_from_lp_vault,
+    helpers::settlement::check_not_settled,
+    helpers::sol_vault::transfer_from_sol_vault,
+    helpers::vaults::verify_msol_vault,
+    idl,
+    instruction::Instruction,
+    lucra_core::pda::find_transfer_allowlist,
+    state::{
+        SystemState,
+        TransferAllowlist,
+    },
 };
 
 declare_check_assert_macros!(SourceFileId::TransferFunds);
 
 #[inline(never)]
-pub fn process_transfer_funds(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
-    const NUM_FIXED: usize = 6;
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::TransferFunds {
+            lamports,
+            native,
+        } => {
+            msg!("Instruction: Transfer Funds");
+            process_transfer_funds(program_id, lamports, native, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const TRANSFER_FUNDS_SIZE: usize = idl::TRANSFER_FUNDS.len();
+
+// DAO instruction. Sends msol out of the msol vault, LP tokens out of the LP vault, or with `native` lamports
+// out of the sol vault, to a destination that has been on the transfer allowlist for at least
+// TRANSFER_DESTINATION_DELAY.
+#[inline(never)]
+pub fn process_transfer_funds(program_id: &Pubkey, lamports: u64, native: bool, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = TRANSFER_FUNDS_SIZE;
     let accounts = array_ref!(accounts, 0, NUM_FIXED);
     let [
         system_state_ai,        // write
@@ -29,17 +60,45 @@ pub fn process_transfer_funds(program_id: &Pubkey, lamports: u64, accounts: &[Ac
         to_account_ai,          // write
         transfer_authority_ai,  // read
         token_program_ai,       // read
+        transfer_allowlist_ai,  // read
     ] = accounts;
 
+    let clock = &Clock::get()?;
+
     check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+
+    check_eq!(transfer_allowlist_ai.key, &find_transfer_allowlist(system_state_ai.key).0, LucraErrorCode::InvalidAccountInput)?;
+    let allowlist: Ref<TransferAllowlist> = TransferAllowlist::load_checked(transfer_allowlist_ai, program_id)?;
+    check_eq!(&allowlist.system_state, system_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    allowlist.check_active(to_account_ai.key, clock.unix_timestamp)?;
+
+    if native {
+        // The sol vault is owned by the program, its lamports move without the msol vault authority or a CPI
+        let rent = &Rent::get()?;
+        return transfer_from_sol_vault(program_id, &system_state, from_vault_ai, to_account_ai, lamports, rent);
+    }
 
-    check_eq!(dao_authority_ai.key, &DAO_AUTHORITY, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(from_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(to_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-
-    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    // Origination fees, penalty and forfeited collateral of LP backed loans are left in the LP vault as LP tokens
+    if from_vault_ai.key == &system_state.lp_vault.address {
+        return transfer_from_lp_vault(
+            program_id,
+            &system_state,
+            from_vault_ai,
+            to_account_ai,
+            transfer_authority_ai,
+            token_program_ai,
+            lamports,
+        );
+    }
+    verify_msol_vault(&system_state, from_vault_ai.key)?;
 
     system_state.transfer_from_msol_vault(
         program_id,
@@ -49,4 +108,4 @@ pub fn process_transfer_funds(program_id: &Pubkey, lamports: u64, accounts: &[Ac
         token_program_ai,
         lamports,
     )
-}
\ No newline at end of file
+}
@@ -0,0 +1,232 @@
+use std::cell::{Ref, RefMut};
+
+use anchor_lang::prelude::*;
+use arrayref::array_ref;
+use rust_decimal::Decimal;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        constants::{LUCRA_SOL_ORACLE, SOL_USDC_ORACLE, SOL_USDT_ORACLE},
+        math::calc_stake_value_left_to_lock,
+        oracle::{get_lucra_price, verify_sol_usd_oracle},
+        settlement::check_not_settled,
+        staking_freeze::check_staking_account_not_frozen,
+    },
+    idl,
+    instruction::Instruction,
+    state::{
+        LoanType,
+        MataLoan,
+        staking::StakingAccount,
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::TransferLoanOwnership);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::TransferLoanOwnership { } => {
+            msg!("Instruction: Transfer Loan Ownership");
+            process_transfer_loan_ownership(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const TRANSFER_LOAN_OWNERSHIP_SIZE: usize = idl::TRANSFER_LOAN_OWNERSHIP.len();
+const TRANSFER_LOAN_OWNERSHIP_WITH_LOCKED_STAKE_SIZE: usize = idl::TRANSFER_LOAN_OWNERSHIP_WITH_LOCKED_STAKE.len();
+
+// Hands a loan to another wallet, after a key rotation or when the position is sold. The loan itself
+// doesn't change. A lucra backed loan's pledge moves too, so the new owner's stake has to be able to hold it.
+#[inline(never)]
+pub fn process_transfer_loan_ownership(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    let loan_ai = accounts.first().ok_or(throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    let loan_type = MataLoan::load_checked(loan_ai, program_id)?.loan_type;
+
+    if loan_type == LoanType::LucraBacked {
+        transfer_loan_ownership_with_locked_stake(program_id, accounts)
+    } else {
+        transfer_loan_ownership(program_id, accounts)
+    }
+}
+
+#[inline(never)]
+fn transfer_loan_ownership(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = TRANSFER_LOAN_OWNERSHIP_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        loan_ai,                    // write
+        current_owner_ai,           // read
+        new_owner_ai,               // read
+    ] = accounts;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    set_loan_owner(&mut loan, current_owner_ai, new_owner_ai)
+}
+
+#[inline(never)]
+fn transfer_loan_ownership_with_locked_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = TRANSFER_LOAN_OWNERSHIP_WITH_LOCKED_STAKE_SIZE;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        loan_ai,                        // write
+        current_owner_ai,               // read
+        new_owner_ai,                   // read
+        system_state_ai,                // read
+        current_staking_account_ai,     // write
+        new_staking_account_ai,         // write
+        sol_usdc_oracle_ai,             // read
+        sol_usdt_oracle_ai,             // read
+        lucra_sol_oracle_ai,            // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(current_staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(new_staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(lucra_sol_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+    check_eq!(lucra_sol_oracle_ai.key, &LUCRA_SOL_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+
+    let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
+    check_not_settled(&system_state)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    set_loan_owner(&mut loan, current_owner_ai, new_owner_ai)?;
+
+    let mut current_staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(current_staking_account_ai, program_id)?;
+    let mut new_staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(new_staking_account_ai, program_id)?;
+    check_eq!(&current_staking_account.owner, current_owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&new_staking_account.owner, new_owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    // A frozen account's key may have leaked, its pledges stay where they are until the owner unfreezes it
+    check_staking_account_not_frozen(&current_staking_account, clock.unix_timestamp)?;
+
+    let lucra_market_price = get_lucra_price(&system_state, lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    move_locked_stake(&mut current_staking_account, &mut new_staking_account, loan.staking_collateral_amount, lucra_market_price)
+}
+
+/// Only the owner can give the loan away, and only to a wallet that signs for it. A loan with an unharvested
+/// penalty or no debt left can't change hands.
+fn set_loan_owner(loan: &mut MataLoan, current_owner_ai: &AccountInfo, new_owner_ai: &AccountInfo) -> LucraResult {
+    check_eq!(current_owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    // A lucra backed loan locks the new owner's stake, nobody else can pledge it for them
+    check_eq!(new_owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(&loan.owner, current_owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check!(new_owner_ai.key != current_owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    // Harvesting settles the penalty against the loan as it is, it shouldn't change hands halfway
+    check_eq!(loan.penalty_to_harvest, 0, LucraErrorCode::PenaltyNotHarvested)?;
+
+    loan.owner = *new_owner_ai.key;
+
+    Ok(())
+}
+
+/// The pledge moves at the value it was locked at. The new owner's stake has to cover it at today's
+/// lucra price on top of whatever it already backs.
+pub fn move_locked_stake(
+    from: &mut StakingAccount,
+    to: &mut StakingAccount,
+    staking_collateral_amount: u64,
+    lucra_price: Decimal,
+) -> LucraResult {
+    let value_left_to_lock = calc_stake_value_left_to_lock(to.total, lucra_price, to.locked_total)?;
+    check!(staking_collateral_amount <= value_left_to_lock, LucraErrorCode::InvalidAmount)?;
+
+    from.remove_locked_total(staking_collateral_amount);
+    to.add_locked_total(staking_collateral_amount);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn staking_account(total: u64, locked_total: u64) -> StakingAccount {
+        let mut staking_account = StakingAccount::default();
+        staking_account.total = total;
+        staking_account.locked_total = locked_total;
+        staking_account
+    }
+
+    fn set_owner(loan: &mut MataLoan, current_owner: &Pubkey, new_owner: &Pubkey, new_owner_signs: bool) -> LucraResult {
+        let (mut current_lamports, mut new_lamports) = (0, 0);
+        let (mut current_data, mut new_data) = (vec![], vec![]);
+        let current_owner_ai = AccountInfo::new(current_owner, true, false, &mut current_lamports, &mut current_data, current_owner, false, 0);
+        let new_owner_ai = AccountInfo::new(new_owner, new_owner_signs, false, &mut new_lamports, &mut new_data, new_owner, false, 0);
+        set_loan_owner(loan, &current_owner_ai, &new_owner_ai)
+    }
+
+    #[test]
+    fn test_new_owner_has_to_sign() {
+        let (current_owner, new_owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut loan = MataLoan::default();
+        loan.owner = current_owner;
+
+        assert!(matches!(
+            set_owner(&mut loan, &current_owner, &new_owner, false).unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::AccountNotSigner, .. }
+        ));
+        assert_eq!(loan.owner, current_owner);
+
+        set_owner(&mut loan, &current_owner, &new_owner, true).unwrap();
+        assert_eq!(loan.owner, new_owner);
+    }
+
+    #[test]
+    fn test_pledge_moves_to_the_new_owner() {
+        let mut from = staking_account(1_000, 600);
+        // 1,000 lucra at $0.50 backs 500, 200 of it is already pledged
+        let mut to = staking_account(1_000, 200);
+
+        move_locked_stake(&mut from, &mut to, 300, dec!(0.5)).unwrap();
+
+        assert_eq!(from.locked_total, 300);
+        assert_eq!(to.locked_total, 500);
+    }
+
+    #[test]
+    fn test_new_owner_without_enough_unlocked_stake_is_rejected() {
+        let mut from = staking_account(1_000, 600);
+        let mut to = staking_account(1_000, 200);
+
+        assert!(matches!(
+            move_locked_stake(&mut from, &mut to, 301, dec!(0.5)).unwrap_err(),
+            LucraError::LucraErrorCode {
+                lucra_error_code: LucraErrorCode::InvalidAmount,
+                ..
+            }
+        ));
+        assert_eq!(from.locked_total, 600);
+        assert_eq!(to.locked_total, 200);
+    }
+
+    #[test]
+    fn test_overpledged_new_owner_has_nothing_left_to_lock() {
+        let mut from = staking_account(1_000, 600);
+        // The lucra price fell, the 200 already pledged is worth more than the stake
+        let mut to = staking_account(1_000, 200);
+
+        assert!(move_locked_stake(&mut from, &mut to, 1, dec!(0.1)).is_err());
+        assert!(move_locked_stake(&mut from, &mut to, 0, dec!(0.1)).is_ok());
+    }
+}
@@ -0,0 +1,54 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::staking_freeze::unfreeze_staking_account,
+    instruction::Instruction,
+    state::staking::StakingAccount,
+};
+
+declare_check_assert_macros!(SourceFileId::UnfreezeStakingAccount);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::UnfreezeStakingAccount { } => {
+            msg!("Instruction: Unfreeze Staking Account");
+            process_unfreeze_staking_account(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// The owner calls this twice, once to start the delay and once it has passed. The recovery key only needs one call.
+#[inline(never)]
+pub fn process_unfreeze_staking_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = 2;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        staking_account_ai,             // write
+        authority_ai,                   // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    let mut staking_account: RefMut<StakingAccount> = StakingAccount::load_mut_checked(staking_account_ai, program_id)?;
+    unfreeze_staking_account(&mut staking_account, authority_ai.key, clock.unix_timestamp)?;
+
+    Ok(())
+}
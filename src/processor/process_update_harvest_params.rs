@@ -0,0 +1,121 @@
+use std::cell::RefMut;
+
+use arrayref::array_ref;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::dao_authority::check_dao_authority,
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
+    lucra_core::quote::verify_harvest_params,
+    state::SystemState,
+    units::{Lamports, RewardFee},
+};
+
+declare_check_assert_macros!(SourceFileId::UpdateHarvestParams);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::UpdateHarvestParams {
+            minimum_harvest_amount,
+            reward_fee,
+        } => {
+            msg!("Instruction: Update Harvest Params");
+            process_update_harvest_params(program_id, minimum_harvest_amount, reward_fee, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// DAO instruction. Tunes the harvest without resubmitting every UpdateState field, a None leaves it as it is
+#[inline(never)]
+pub fn process_update_harvest_params(
+    program_id: &Pubkey,
+    minimum_harvest_amount: Option<Lamports>,
+    reward_fee: Option<RewardFee>,
+    accounts: &[AccountInfo],
+) -> LucraResult {
+    const NUM_FIXED: usize = 2;
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,    // write
+        dao_authority_ai,   // read
+    ] = accounts;
+
+    check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
+    apply_harvest_params(&mut system_state, minimum_harvest_amount, reward_fee)
+}
+
+/// The kept value of a field left as None is checked against the other one
+fn apply_harvest_params(system_state: &mut SystemState, minimum_harvest_amount: Option<Lamports>, reward_fee: Option<RewardFee>) -> LucraResult {
+    let minimum_harvest_amount = minimum_harvest_amount.unwrap_or(system_state.minimum_harvest_amount);
+    let reward_fee = reward_fee.unwrap_or(system_state.reward_fee);
+    verify_harvest_params(minimum_harvest_amount, reward_fee)?;
+
+    system_state.minimum_harvest_amount = minimum_harvest_amount;
+    system_state.reward_fee = reward_fee;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    fn configured_state() -> SystemState {
+        let mut system_state = SystemState::zeroed();
+        system_state.minimum_harvest_amount = Lamports(550_000);
+        system_state.reward_fee = RewardFee(5_500);
+        system_state
+    }
+
+    #[test]
+    fn test_only_the_set_field_changes() {
+        let mut system_state = configured_state();
+        apply_harvest_params(&mut system_state, Some(Lamports(100_000)), None).unwrap();
+        assert_eq!(system_state.minimum_harvest_amount, Lamports(100_000));
+        assert_eq!(system_state.reward_fee, RewardFee(5_500));
+
+        apply_harvest_params(&mut system_state, None, Some(RewardFee(10_000))).unwrap();
+        assert_eq!(system_state.minimum_harvest_amount, Lamports(100_000));
+        assert_eq!(system_state.reward_fee, RewardFee(10_000));
+
+        apply_harvest_params(&mut system_state, None, None).unwrap();
+        assert_eq!(system_state.minimum_harvest_amount, Lamports(100_000));
+        assert_eq!(system_state.reward_fee, RewardFee(10_000));
+    }
+
+    #[test]
+    fn test_set_field_is_checked_against_the_kept_one() {
+        // 55,000 covers ten of the kept 5,500 fees, 54,999 doesn't
+        let mut system_state = configured_state();
+        assert!(apply_harvest_params(&mut system_state, Some(Lamports(54_999)), None).is_err());
+        assert_eq!(system_state.minimum_harvest_amount, Lamports(550_000));
+        apply_harvest_params(&mut system_state, Some(Lamports(55_000)), None).unwrap();
+
+        // A fee the kept minimum can't cover ten times
+        let mut system_state = configured_state();
+        assert!(apply_harvest_params(&mut system_state, None, Some(RewardFee(55_001))).is_err());
+        assert_eq!(system_state.reward_fee, RewardFee(5_500));
+
+        // Raising both at once is fine as long as they fit together
+        apply_harvest_params(&mut system_state, Some(Lamports(500_000)), Some(RewardFee(50_000))).unwrap();
+        assert!(apply_harvest_params(&mut system_state, Some(Lamports(u64::MAX)), Some(RewardFee(50_001))).is_err());
+    }
+}
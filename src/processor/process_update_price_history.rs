@@ -1,9 +1,9 @@
 use std::cell::{Ref, RefMut};
 
 use arrayref::array_ref;
-use rust_decimal_macros::dec;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     pubkey::Pubkey,
     sysvar::{clock::Clock, Sysvar},
 };
@@ -21,20 +21,56 @@ use crate::{
         SOL_USDC_ORACLE,
         SOL_USDT_ORACLE,
         LUCRA_SOL_ORACLE,
-        UNIX_HOUR,
+        SOL_MATA_ORACLE,
+        MIN_DAILY_PRICE_SAMPLES,
     },
-    helpers::oracle::{get_lucra_price, get_sol_price},
+    helpers::arb_trigger::{calc_abs_peg_deviation_bps, record_arb_trigger},
+    helpers::paged_price_history::archive_recent_prices,
+    helpers::oracle::{get_lucra_price, get_mata_price, get_sol_price, verify_sol_usd_oracle},
+    helpers::price_history::{
+        accumulate_peg_deviation,
+        calc_deviation_sample_seconds,
+        calc_peg_deviation_bps,
+        is_update_too_frequent,
+        last_price_mut,
+        migrate_to_ring_buffer,
+        price_for_date,
+        record_price_sample,
+    },
+    helpers::keepers::{resolve_crank_caller, split_keeper},
+    helpers::reward_tokens::mint_partial_reward,
+    helpers::settlement::check_not_settled,
+    helpers::vaults::verify_reward_mint,
+    instruction::Instruction,
+    lucra_core::pda::find_price_history_header,
     state::{
+        ArbState,
         PriceHistory,
+        PriceHistoryHeader,
+        PriceHistoryPage,
         SystemState,
+        PAGED_PRICE_HISTORY_VERSION,
     },
 };
 
 declare_check_assert_macros!(SourceFileId::UpdatePriceHistory);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::UpdatePriceHistory { } => {
+            msg!("Instruction: Update Price History");
+            process_update_price_history(program_id, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[inline(never)]
 pub fn process_update_price_history(program_id: &Pubkey, accounts: &[AccountInfo]) -> LucraResult {
-    const NUM_FIXED: usize = 9;
+    let (accounts, keeper) = split_keeper(program_id, accounts);
+    const NUM_FIXED: usize = 11;
+    let paged_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,            // read
@@ -44,11 +80,14 @@ pub fn process_update_price_history(program_id: &Pubkey, accounts: &[AccountInfo
         sol_usdc_oracle_ai,         // read
         sol_usdt_oracle_ai,         // read
         lucra_sol_oracle_ai,        // read
+        sol_mata_oracle_ai,         // read
 
         user_reward_account_ai,     // write
         reward_mint_ai,             // write
         reward_mint_authority_ai,   // read
         token_program_ai,           // read
+
+        arb_state_ai,               // write
     ] = accounts;
 
     let clock = &Clock::get()?;
@@ -56,92 +95,72 @@ pub fn process_update_price_history(program_id: &Pubkey, accounts: &[AccountInfo
     // Verify the accounts are owned by the right programs
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(price_history_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(sol_usdc_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(sol_usdt_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(lucra_sol_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(user_reward_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(reward_mint_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
 
     // Verify the oracles
-    check_eq!(sol_usdc_oracle_ai.key, &SOL_USDC_ORACLE, LucraErrorCode::InvalidAccountInput)?;
-    check_eq!(sol_usdt_oracle_ai.key, &SOL_USDT_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
     check_eq!(lucra_sol_oracle_ai.key, &LUCRA_SOL_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
 
     check_eq!(price_history_ai.key, &PRICE_HISTORY_ID, LucraErrorCode::InvalidAccountInput)?;
     
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
-    check_eq!(&system_state.reward_mint.address, reward_mint_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let keeper_multiplier = resolve_crank_caller(program_id, &system_state, keeper)?;
+    check_not_settled(&system_state)?;
+    verify_reward_mint(&system_state, reward_mint_ai.key)?;
     
-    // Check to see if the price can be updated (should be atleast 1 hour difference)
+    // Check to see if the price can be updated (should be about an hour since the last update)
     let mut price_history: Box<RefMut<PriceHistory>> = PriceHistory::load_mut_checked(price_history_ai, program_id)?;
+    migrate_to_ring_buffer(&mut price_history);
     let price_history_update_counter_before = price_history.update_counter;
-    check!(price_history.last_update_timestamp + UNIX_HOUR <= clock.unix_timestamp, LucraErrorCode::InsufficientTimePassed)?;
+    check!(!is_update_too_frequent(&price_history, clock.unix_timestamp), LucraErrorCode::TooFrequent)?;
     
-    let sol_price = get_sol_price(sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
-    let lucra_price = get_lucra_price(lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let sol_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let lucra_price = get_lucra_price(&system_state, lucra_sol_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    // A stale mata oracle leaves the time out of the day's deviation instead of blocking the update
+    let mata_price = match get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock) {
+        Ok(mata_price) => Some(mata_price),
+        Err(LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::OracleStale, .. }) => None,
+        Err(e) => return Err(e),
+    };
+    let peg_deviation_bps = mata_price.map(calc_peg_deviation_bps);
+
+    // The measurement also raises or drops the arb request, a stale price leaves it alone
+    check_eq!(arb_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(&system_state.arb_state, arb_state_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let mut arb_state: RefMut<ArbState> = ArbState::load_mut_checked(arb_state_ai, program_id)?;
+    if let Some(mata_price) = mata_price {
+        record_arb_trigger(&mut arb_state, calc_abs_peg_deviation_bps(mata_price), clock.unix_timestamp);
+    }
+    let last_update_timestamp = price_history.last_update_timestamp;
+
+    let decimals = 6_u8;
+    let exponent = Decimal::from(10_u64.pow(decimals.into()));
+    let sol_price = sol_price
+        .checked_mul(exponent)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())?;
+    let lucra_price = lucra_price
+        .checked_mul(exponent)
+        .ok_or(math_err!())?
+        .floor()
+        .to_u64()
+        .ok_or(math_err!())?;
 
     // where does the current unix timestamp sit between the intervals?
     let interval_start = price_history.interval_start;
     if clock.unix_timestamp >= interval_start 
         && clock.unix_timestamp <= price_history.interval_end() {
             // We are between the intervals and should record a price
-            let current_interval_historic_price = price_history.find_price_by_timestamp(interval_start);
-            
-            if let Some(historic_price) = current_interval_historic_price {
-                // We have a price for the interval
-                // Update the price by averaging the new price in
-                let decimals = 6_u8;
-                let exponent = Decimal::from(10_i64.pow(decimals.into()));
-                historic_price.sol_price = sol_price
-                    .checked_mul(exponent)
-                    .ok_or(math_err!())?
-                    .checked_add(historic_price.sol_price.into())
-                    .ok_or(math_err!())?
-                    .checked_div(dec!(2))
-                    .ok_or(math_err!())?
-                    .floor()
-                    .to_u64()
-                    .ok_or(math_err!())?;
-
-                historic_price.lucra_price = lucra_price
-                    .checked_mul(exponent)
-                    .ok_or(math_err!())?
-                    .checked_add(historic_price.lucra_price.into())
-                    .ok_or(math_err!())?
-                    .checked_div(dec!(2))
-                    .ok_or(math_err!())?
-                    .floor()
-                    .to_u64()
-                    .ok_or(math_err!())?;          
-            } else {
-                // We didn't have a price to update for that interval. 
-                // Create a new one for the current interval
-                // make sure the counter is reset
+            if price_for_date(&price_history, interval_start).is_none() {
+                // We didn't have a price for that interval, make sure the counter is reset for the new one
                 price_history.reset_counter();
-
-                // find a price for the new day
-                let decimals = 6_u8;
-                let exponent = Decimal::from(10_u64.pow(decimals.into()));
-                let sol_price = sol_price
-                    .checked_mul(exponent)
-                    .ok_or(math_err!())?
-                    .floor()
-                    .to_u64()
-                    .ok_or(math_err!())?;
-                let lucra_price = lucra_price
-                    .checked_mul(exponent)
-                    .ok_or(math_err!())?
-                    .floor()
-                    .to_u64()
-                    .ok_or(math_err!())?;
-                
-                price_history.replace_oldest_price(
-                    interval_start,
-                    sol_price,
-                    decimals,
-                    lucra_price,
-                    decimals,
-                );
             }
     } else if clock.unix_timestamp > price_history.interval_end() {
         // we are on a new interval
@@ -158,48 +177,61 @@ pub fn process_update_price_history(program_id: &Pubkey, accounts: &[AccountInfo
 
         price_history.reset_counter();
         price_history.update_interval(clock.unix_timestamp);
-
-        let interval_start = price_history.interval_start;
-        let decimals = 6_u8;
-        let exponent = Decimal::from(10_i64.pow(decimals.into()));
-        let sol_price = sol_price
-            .checked_mul(exponent)
-            .ok_or(math_err!())?
-            .floor()
-            .to_u64()
-            .ok_or(math_err!())?;
-        let lucra_price = lucra_price
-            .checked_mul(exponent)
-            .ok_or(math_err!())?
-            .floor()
-            .to_u64()
-            .ok_or(math_err!())?;
-
-        price_history.replace_oldest_price(
-            interval_start,
-            sol_price,
-            decimals,
-            lucra_price,
-            decimals,
-        );
     } else {
         // we are before the start of the interval. This is a bad state
         return Err(throw_err!(LucraErrorCode::Default));
     }
 
+    // Averaged into the interval's price, or the first sample of a new one
+    let interval_start = price_history.interval_start;
+    record_price_sample(&mut price_history, interval_start, sol_price, lucra_price, decimals);
+    record_peg_deviation(&mut price_history, peg_deviation_bps, last_update_timestamp, clock.unix_timestamp);
+
     // increment the counter and update the timestamp
     price_history.increment_counter();
     price_history.last_update_timestamp = clock.unix_timestamp;
 
-    // Pay the user for their efforts
-    system_state.mint_reward(
+    // Once the price history is paged the days it just wrote are archived, DeterminePenalty charges them from
+    // the pages after they're written over here
+    if price_history.meta_data.version >= PAGED_PRICE_HISTORY_VERSION {
+        let (header_ai, page_ais) = paged_accounts
+            .split_first()
+            .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+        check_eq!(header_ai.key, &find_price_history_header(price_history_ai.key).0, LucraErrorCode::InvalidAccountInput)?;
+        let mut header: RefMut<PriceHistoryHeader> = PriceHistoryHeader::load_mut_checked(header_ai, program_id)?;
+
+        // The page after the active one is passed before it's created, it's only needed once it exists
+        let mut pages = page_ais
+            .iter()
+            .filter(|page_ai| !page_ai.data_is_empty())
+            .map(|page_ai| PriceHistoryPage::load_mut_checked(page_ai, program_id))
+            .collect::<LucraResult<Vec<RefMut<PriceHistoryPage>>>>()?;
+        let mut pages: Vec<&mut PriceHistoryPage> = pages.iter_mut().map(|page| &mut **page).collect();
+        archive_recent_prices(&mut header, &mut pages, &price_history)?;
+    }
+
+    // Pay the user for their efforts, in part until the day has enough samples to keep its prices
+    mint_partial_reward(
         program_id,
+        &system_state,
         reward_mint_ai,
         user_reward_account_ai,
         1,
+        price_history.samples_today.into(),
+        MIN_DAILY_PRICE_SAMPLES.into(),
+        keeper_multiplier,
         reward_mint_authority_ai,
         token_program_ai,
     )?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Starts the deviation average for the price that was just pushed
+fn record_peg_deviation(price_history: &mut PriceHistory, peg_deviation_bps: Option<u16>, last_update_timestamp: i64, now: i64) {
+    let interval_start = price_history.interval_start;
+    if let (Some(deviation_bps), Some(price)) = (peg_deviation_bps, last_price_mut(price_history)) {
+        let seconds = calc_deviation_sample_seconds(last_update_timestamp, interval_start, now);
+        accumulate_peg_deviation(price, deviation_bps, seconds);
+    }
+}
@@ -3,6 +3,7 @@ use std::cell::RefMut;
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     pubkey::Pubkey,
 };
 use crate::{
@@ -12,16 +13,106 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
-    helpers::constants::DAO_AUTHORITY,
+    helpers::arb_trigger::verify_arb_trigger,
+    helpers::dao_authority::check_dao_authority,
+    helpers::oracle::{get_max_oracle_deviation_bps, get_max_oracle_staleness_slots, verify_oracle_limits},
+    helpers::peg_band,
+    helpers::settlement::check_not_settled,
+    instruction::Instruction,
+    lucra_core::interest::verify_annual_interest,
+    lucra_core::penalty::verify_peg_band,
+    lucra_core::quote::{verify_harvest_params, verify_loan_terms},
     state::{
         ArbState,
+        MataBucket,
         UpdateStateParams,
         SystemState,
     },
+    units::{Bps, CollateralRatio, Lamports},
 };
 
 declare_check_assert_macros!(SourceFileId::UpdateState);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::UpdateState {
+            min_deposit,
+            collateral_requirement,
+            loans_enabled,
+            staking_enabled,
+            arbitrage_enabled,
+            peg_check_enabled,
+            max_amount_of_lucra_to_mint,
+            daily_arb_limit,
+            maximum_outstanding_mata,
+            minimum_harvest_amount,
+            reward_fee,
+            lcp,
+            min_loan_amount_mata,
+            msol_haircut_bps,
+            msol_sol_oracle,
+            require_oracle_v2,
+            reward_expiry_epochs,
+            max_instant_unstake,
+            peg_lower_bound,
+            peg_upper_bound,
+            penalty_band_width,
+            penalty_multiplier_per_band,
+            arb_trigger_deviation_bps,
+            guardian,
+            max_oracle_staleness_slots,
+            max_oracle_deviation_bps,
+            msol_sol_pool,
+            arb_dust_threshold,
+            pyth_sol_usd_feed,
+            annual_interest_bps,
+            restricted_cranking,
+            sol_mata_whirlpool,
+            native_loans_enabled,
+        } => {
+            msg!("Instruction: Update State");
+            let state_params = UpdateStateParams {
+                min_deposit,
+                collateral_requirement,
+                loans_enabled,
+                staking_enabled,
+                arbitrage_enabled,
+                peg_check_enabled,
+                max_amount_of_lucra_to_mint,
+                daily_arb_limit,
+                maximum_outstanding_mata,
+                minimum_harvest_amount,
+                reward_fee,
+                lcp,
+                min_loan_amount_mata,
+                msol_haircut_bps,
+                msol_sol_oracle,
+                require_oracle_v2,
+                reward_expiry_epochs,
+                max_instant_unstake,
+                peg_lower_bound,
+                peg_upper_bound,
+                penalty_band_width,
+                penalty_multiplier_per_band,
+                arb_trigger_deviation_bps,
+                guardian,
+                max_oracle_staleness_slots,
+                max_oracle_deviation_bps,
+                msol_sol_pool,
+                arb_dust_threshold,
+                pyth_sol_usd_feed,
+                annual_interest_bps,
+                restricted_cranking,
+                sol_mata_whirlpool,
+                native_loans_enabled,
+            };
+            process_update_state(program_id, &state_params, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[inline(never)]
 pub fn process_update_state(program_id: &Pubkey, state_params: &UpdateStateParams, accounts: &[AccountInfo]) -> LucraResult {
     const NUM_FIXED: usize = 3;
@@ -34,26 +125,358 @@ pub fn process_update_state(program_id: &Pubkey, state_params: &UpdateStateParam
 
     check_eq!(dao_authority_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
-    check_eq!(dao_authority_ai.key, &DAO_AUTHORITY, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(arb_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
 
     let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    check_dao_authority(&system_state, dao_authority_ai.key)?;
+    check_not_settled(&system_state)?;
     let mut arb_state: RefMut<ArbState> = ArbState::load_mut_checked(arb_state_ai, program_id)?;
-    
-    system_state.min_deposit = state_params.min_deposit;
-    system_state.collateral_requirement = state_params.collateral_requirement;
-    system_state.loans_enabled = state_params.loans_enabled;
-    system_state.staking_enabled = state_params.staking_enabled;
-    system_state.arbitrage_enabled = state_params.arbitrage_enabled;
-    system_state.peg_check_enabled = state_params.peg_check_enabled;
-    system_state.maximum_outstanding_mata = state_params.maximum_outstanding_mata;
-    system_state.minimum_harvest_amount = state_params.minimum_harvest_amount;
-    system_state.reward_fee = state_params.reward_fee;
-    system_state.lcp = state_params.lcp;
-    
-    arb_state.daily_limit = state_params.daily_arb_limit;
-    arb_state.max_amount_of_lucra_to_mint = state_params.max_amount_of_lucra_to_mint;
+
+    apply_state_params(&mut system_state, &mut arb_state, state_params)?;
+
+    // Outstanding mata used to be tracked in a single counter, everything in it was minted by loans
+    if system_state.total_outstanding_mata != 0 {
+        let legacy_total = system_state.total_outstanding_mata;
+        system_state.mata_supply.mint(MataBucket::Loan, legacy_total)?;
+        system_state.total_outstanding_mata = 0;
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Applies the fields that are set, a None leaves the field as it is. Each set field is checked against
+/// its bounds, fields that are bounded by each other are checked with the values they end up with.
+fn apply_state_params(system_state: &mut SystemState, arb_state: &mut ArbState, state_params: &UpdateStateParams) -> LucraResult {
+    system_state.min_deposit = state_params.min_deposit.unwrap_or(system_state.min_deposit);
+    if state_params.collateral_requirement.is_some() || state_params.lcp.is_some() {
+        let collateral_requirement = state_params.collateral_requirement.unwrap_or(system_state.collateral_requirement);
+        let lcp = state_params.lcp.unwrap_or(system_state.lcp);
+        verify_loan_terms(collateral_requirement, lcp)?;
+        // SetLiquidationParams and SetLpCollateral hold their ratios on either side of the requirement
+        if system_state.liquidation_ratio != CollateralRatio(0) {
+            check!(system_state.liquidation_ratio < collateral_requirement, LucraErrorCode::InvalidAmount)?;
+        }
+        if system_state.lp_collateral_requirement != CollateralRatio(0) {
+            check!(system_state.lp_collateral_requirement >= collateral_requirement, LucraErrorCode::InvalidAmount)?;
+        }
+        system_state.collateral_requirement = collateral_requirement;
+        system_state.lcp = lcp;
+    }
+    system_state.loans_enabled = state_params.loans_enabled.unwrap_or(system_state.loans_enabled);
+    system_state.staking_enabled = state_params.staking_enabled.unwrap_or(system_state.staking_enabled);
+    system_state.arbitrage_enabled = state_params.arbitrage_enabled.unwrap_or(system_state.arbitrage_enabled);
+    system_state.peg_check_enabled = state_params.peg_check_enabled.unwrap_or(system_state.peg_check_enabled);
+
+    if state_params.maximum_outstanding_mata.is_some() || state_params.min_loan_amount_mata.is_some() {
+        let maximum_outstanding_mata = state_params.maximum_outstanding_mata.unwrap_or(system_state.maximum_outstanding_mata);
+        let min_loan_amount_mata = state_params.min_loan_amount_mata.unwrap_or(system_state.min_loan_amount_mata);
+        check!(min_loan_amount_mata <= maximum_outstanding_mata, LucraErrorCode::InvalidAmount)?;
+        system_state.maximum_outstanding_mata = maximum_outstanding_mata;
+        system_state.min_loan_amount_mata = min_loan_amount_mata;
+    }
+    if let Some(msol_haircut_bps) = state_params.msol_haircut_bps {
+        check!(msol_haircut_bps <= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)?;
+        system_state.msol_haircut_bps = msol_haircut_bps;
+    }
+    // Setting the oracle back to the default key turns the market price check off
+    system_state.msol_sol_oracle = state_params.msol_sol_oracle.unwrap_or(system_state.msol_sol_oracle);
+    // Setting the pool back to the default key sends the arb's mSOL through the marinade unstake again
+    system_state.msol_sol_pool = state_params.msol_sol_pool.unwrap_or(system_state.msol_sol_pool);
+    // Setting the whirlpool back to the default key leaves harvests on the constant product pools
+    system_state.sol_mata_whirlpool = state_params.sol_mata_whirlpool.unwrap_or(system_state.sol_mata_whirlpool);
+    // Setting the feed back to the default key stops Pyth standing in for a SOL/USD oracle
+    system_state.pyth_sol_usd_feed = state_params.pyth_sol_usd_feed.unwrap_or(system_state.pyth_sol_usd_feed);
+    // Only flip once every oracle the program reads has migrated to the v2 layout
+    system_state.require_oracle_v2 = state_params.require_oracle_v2.unwrap_or(system_state.require_oracle_v2);
+    if state_params.max_oracle_staleness_slots.is_some() || state_params.max_oracle_deviation_bps.is_some() {
+        let max_oracle_staleness_slots = state_params.max_oracle_staleness_slots.unwrap_or(get_max_oracle_staleness_slots(system_state));
+        let max_oracle_deviation_bps = state_params.max_oracle_deviation_bps.unwrap_or(get_max_oracle_deviation_bps(system_state));
+        verify_oracle_limits(max_oracle_staleness_slots, max_oracle_deviation_bps)?;
+        system_state.max_oracle_staleness_slots = max_oracle_staleness_slots;
+        system_state.max_oracle_deviation_bps = max_oracle_deviation_bps;
+    }
+    // Rewards keep the expiry they dropped with, a change only applies to later drops
+    if let Some(reward_expiry_epochs) = state_params.reward_expiry_epochs {
+        check!(reward_expiry_epochs > 0, LucraErrorCode::InvalidAmount)?;
+        system_state.reward_expiry_epochs = reward_expiry_epochs;
+    }
+    // Zero would stop every close from unstaking, even the ones the liq pool handles easily
+    if let Some(max_instant_unstake) = state_params.max_instant_unstake {
+        check!(max_instant_unstake > Lamports(0), LucraErrorCode::InvalidAmount)?;
+        system_state.max_instant_unstake = max_instant_unstake;
+    }
+    // The peg status is only recomputed on the next price update, so a tighter band applies from then on
+    if state_params.peg_lower_bound.is_some()
+        || state_params.peg_upper_bound.is_some()
+        || state_params.penalty_band_width.is_some()
+        || state_params.penalty_multiplier_per_band.is_some()
+    {
+        // Settings never set keep their defaults when only some are changed
+        let peg_lower_bound = state_params.peg_lower_bound.unwrap_or_else(|| peg_band::peg_lower_bound(system_state));
+        let peg_upper_bound = state_params.peg_upper_bound.unwrap_or_else(|| peg_band::peg_upper_bound(system_state));
+        let penalty_band_width = state_params.penalty_band_width.unwrap_or_else(|| peg_band::penalty_band_width(system_state));
+        let penalty_multiplier_per_band = state_params
+            .penalty_multiplier_per_band
+            .unwrap_or_else(|| peg_band::penalty_multiplier_per_band(system_state));
+        verify_peg_band(peg_lower_bound, peg_upper_bound, penalty_band_width, penalty_multiplier_per_band)?;
+        system_state.peg_lower_bound = peg_lower_bound;
+        system_state.peg_upper_bound = peg_upper_bound;
+        system_state.penalty_band_width = penalty_band_width;
+        system_state.penalty_multiplier_per_band = penalty_multiplier_per_band;
+    }
+    if state_params.minimum_harvest_amount.is_some() || state_params.reward_fee.is_some() {
+        let minimum_harvest_amount = state_params.minimum_harvest_amount.unwrap_or(system_state.minimum_harvest_amount);
+        let reward_fee = state_params.reward_fee.unwrap_or(system_state.reward_fee);
+        verify_harvest_params(minimum_harvest_amount, reward_fee)?;
+        system_state.minimum_harvest_amount = minimum_harvest_amount;
+        system_state.reward_fee = reward_fee;
+    }
+    // Loans accrue at the new rate from the next DeterminePenalty, including for the days it hasn't charged yet
+    if let Some(annual_interest_bps) = state_params.annual_interest_bps {
+        verify_annual_interest(annual_interest_bps)?;
+        system_state.annual_interest_bps = annual_interest_bps;
+    }
+    // Only registered keepers can run the cranks until the DAO lifts it
+    system_state.restricted_cranking = state_params.restricted_cranking.unwrap_or(system_state.restricted_cranking);
+    // Native SOL collateral stays closed until the DAO opens it, on top of the sol vault existing
+    system_state.native_loans_enabled = state_params.native_loans_enabled.unwrap_or(system_state.native_loans_enabled);
+
+    arb_state.daily_limit = state_params.daily_arb_limit.unwrap_or(arb_state.daily_limit);
+    arb_state.max_amount_of_lucra_to_mint = state_params.max_amount_of_lucra_to_mint.unwrap_or(arb_state.max_amount_of_lucra_to_mint);
+    if let Some(arb_trigger_deviation_bps) = state_params.arb_trigger_deviation_bps {
+        verify_arb_trigger(arb_trigger_deviation_bps)?;
+        arb_state.arb_trigger_deviation_bps = arb_trigger_deviation_bps;
+    }
+    arb_state.dust_threshold = state_params.arb_dust_threshold.unwrap_or(arb_state.dust_threshold);
+    // The default key leaves SetPause to the DAO alone
+    system_state.guardian = state_params.guardian.unwrap_or(system_state.guardian);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use crate::units::{Mata, Percent, RewardFee};
+    use crate::helpers::constants::{DEFAULT_PEG_LOWER_BOUND, DEFAULT_PEG_UPPER_BOUND, DEFAULT_PENALTY_BAND_WIDTH};
+
+    fn configured_state() -> (SystemState, ArbState) {
+        let mut system_state = SystemState::zeroed();
+        system_state.min_deposit = Lamports(1_000);
+        system_state.collateral_requirement = CollateralRatio(150);
+        system_state.lcp = Percent(100);
+        system_state.loans_enabled = true;
+        system_state.maximum_outstanding_mata = Mata(1_000_000);
+        system_state.min_loan_amount_mata = Mata(100);
+        system_state.reward_expiry_epochs = 4;
+        system_state.max_instant_unstake = Lamports(5_000);
+        system_state.peg_lower_bound = Bps(9_800);
+        system_state.peg_upper_bound = Bps(10_200);
+        system_state.penalty_band_width = Bps(100);
+        system_state.penalty_multiplier_per_band = 2;
+        system_state.reward_fee = RewardFee(500);
+        system_state.minimum_harvest_amount = Lamports(550_000);
+
+        let mut arb_state = ArbState::zeroed();
+        arb_state.daily_limit = 7;
+        arb_state.arb_trigger_deviation_bps = Bps(300);
+
+        (system_state, arb_state)
+    }
+
+    fn assert_invalid_amount(result: LucraResult) {
+        assert!(matches!(
+            result.unwrap_err(),
+            crate::error::LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::InvalidAmount, .. }
+        ));
+    }
+
+    #[test]
+    fn test_only_set_fields_change() {
+        let (mut system_state, mut arb_state) = configured_state();
+        let state_params = UpdateStateParams {
+            reward_fee: Some(RewardFee(750)),
+            peg_upper_bound: Some(Bps(10_500)),
+            daily_arb_limit: Some(9),
+            ..UpdateStateParams::default()
+        };
+
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+
+        assert_eq!(system_state.reward_fee, RewardFee(750));
+        assert_eq!(system_state.peg_upper_bound, Bps(10_500));
+        assert_eq!(arb_state.daily_limit, 9);
+
+        // Everything else is what it was
+        assert_eq!(system_state.min_deposit, Lamports(1_000));
+        assert_eq!(system_state.collateral_requirement, CollateralRatio(150));
+        assert_eq!(system_state.lcp, Percent(100));
+        assert!(system_state.loans_enabled);
+        assert_eq!(system_state.maximum_outstanding_mata, Mata(1_000_000));
+        assert_eq!(system_state.min_loan_amount_mata, Mata(100));
+        assert_eq!(system_state.reward_expiry_epochs, 4);
+        assert_eq!(system_state.max_instant_unstake, Lamports(5_000));
+        assert_eq!(system_state.peg_lower_bound, Bps(9_800));
+        assert_eq!(system_state.penalty_band_width, Bps(100));
+        assert_eq!(system_state.penalty_multiplier_per_band, 2);
+        assert_eq!(arb_state.arb_trigger_deviation_bps, Bps(300));
+    }
+
+    #[test]
+    fn test_unset_peg_settings_fill_in_with_the_defaults() {
+        let (mut system_state, mut arb_state) = configured_state();
+        system_state.peg_lower_bound = Bps(0);
+        system_state.peg_upper_bound = Bps(0);
+        system_state.penalty_band_width = Bps(0);
+        system_state.penalty_multiplier_per_band = 0;
+        let state_params = UpdateStateParams { penalty_multiplier_per_band: Some(3), ..UpdateStateParams::default() };
+
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+
+        assert_eq!(system_state.peg_lower_bound, DEFAULT_PEG_LOWER_BOUND);
+        assert_eq!(system_state.peg_upper_bound, DEFAULT_PEG_UPPER_BOUND);
+        assert_eq!(system_state.penalty_band_width, DEFAULT_PENALTY_BAND_WIDTH);
+        assert_eq!(system_state.penalty_multiplier_per_band, 3);
+    }
+
+    #[test]
+    fn test_empty_update_changes_nothing() {
+        let (mut system_state, mut arb_state) = configured_state();
+        let (expected_system_state, expected_arb_state) = configured_state();
+
+        apply_state_params(&mut system_state, &mut arb_state, &UpdateStateParams::default()).unwrap();
+
+        assert_eq!(bytemuck::bytes_of(&system_state), bytemuck::bytes_of(&expected_system_state));
+        assert_eq!(bytemuck::bytes_of(&arb_state), bytemuck::bytes_of(&expected_arb_state));
+    }
+
+    #[test]
+    fn test_set_fields_are_checked_against_the_kept_ones() {
+        // A minimum loan above the outstanding cap already in place
+        let (mut system_state, mut arb_state) = configured_state();
+        let state_params = UpdateStateParams { min_loan_amount_mata: Some(Mata(2_000_000)), ..UpdateStateParams::default() };
+        assert_invalid_amount(apply_state_params(&mut system_state, &mut arb_state, &state_params));
+
+        // A lower bound above the peg with the rest of the band unchanged
+        let (mut system_state, mut arb_state) = configured_state();
+        let state_params = UpdateStateParams { peg_lower_bound: Some(Bps(10_001)), ..UpdateStateParams::default() };
+        assert_invalid_amount(apply_state_params(&mut system_state, &mut arb_state, &state_params));
+
+        let (mut system_state, mut arb_state) = configured_state();
+        let state_params = UpdateStateParams { reward_expiry_epochs: Some(0), ..UpdateStateParams::default() };
+        assert_invalid_amount(apply_state_params(&mut system_state, &mut arb_state, &state_params));
+    }
+
+    #[test]
+    fn test_interest_rate_is_capped_at_one_hundred_percent() {
+        let (mut system_state, mut arb_state) = configured_state();
+        let state_params = UpdateStateParams { annual_interest_bps: Some(Bps(10_001)), ..UpdateStateParams::default() };
+        assert_invalid_amount(apply_state_params(&mut system_state, &mut arb_state, &state_params));
+        assert_eq!(system_state.annual_interest_bps, Bps(0));
+
+        let state_params = UpdateStateParams { annual_interest_bps: Some(Bps(500)), ..UpdateStateParams::default() };
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+        assert_eq!(system_state.annual_interest_bps, Bps(500));
+
+        // Turning it back off
+        let state_params = UpdateStateParams { annual_interest_bps: Some(Bps(0)), ..UpdateStateParams::default() };
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+        assert_eq!(system_state.annual_interest_bps, Bps(0));
+    }
+
+    #[test]
+    fn test_native_loans_are_kept_unless_set() {
+        let (mut system_state, mut arb_state) = configured_state();
+        assert!(!system_state.native_loans_enabled);
+
+        let state_params = UpdateStateParams { native_loans_enabled: Some(true), ..UpdateStateParams::default() };
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+        assert!(system_state.native_loans_enabled);
+
+        apply_state_params(&mut system_state, &mut arb_state, &UpdateStateParams::default()).unwrap();
+        assert!(system_state.native_loans_enabled);
+
+        let state_params = UpdateStateParams { native_loans_enabled: Some(false), ..UpdateStateParams::default() };
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+        assert!(!system_state.native_loans_enabled);
+    }
+
+    #[test]
+    fn test_restricted_cranking_is_kept_unless_set() {
+        let (mut system_state, mut arb_state) = configured_state();
+        let state_params = UpdateStateParams { restricted_cranking: Some(true), ..UpdateStateParams::default() };
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+        assert!(system_state.restricted_cranking);
+
+        apply_state_params(&mut system_state, &mut arb_state, &UpdateStateParams::default()).unwrap();
+        assert!(system_state.restricted_cranking);
+
+        let state_params = UpdateStateParams { restricted_cranking: Some(false), ..UpdateStateParams::default() };
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+        assert!(!system_state.restricted_cranking);
+    }
+
+    #[test]
+    fn test_out_of_range_loan_terms_are_rejected() {
+        for state_params in [
+            UpdateStateParams { collateral_requirement: Some(CollateralRatio(99)), ..UpdateStateParams::default() },
+            UpdateStateParams { lcp: Some(Percent(201)), ..UpdateStateParams::default() },
+            UpdateStateParams { reward_fee: Some(RewardFee(50_001)), ..UpdateStateParams::default() },
+        ] {
+            let (mut system_state, mut arb_state) = configured_state();
+            assert_invalid_amount(apply_state_params(&mut system_state, &mut arb_state, &state_params));
+        }
+
+        // The bounds themselves are fine
+        let (mut system_state, mut arb_state) = configured_state();
+        let state_params = UpdateStateParams {
+            collateral_requirement: Some(CollateralRatio(100)),
+            lcp: Some(Percent(200)),
+            reward_fee: Some(RewardFee(50_000)),
+            ..UpdateStateParams::default()
+        };
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+        assert_eq!(system_state.collateral_requirement, CollateralRatio(100));
+        assert_eq!(system_state.lcp, Percent(200));
+        assert_eq!(system_state.reward_fee, RewardFee(50_000));
+    }
+
+    #[test]
+    fn test_collateral_requirement_stays_between_the_liquidation_and_lp_ratios() {
+        let (mut system_state, mut arb_state) = configured_state();
+        system_state.liquidation_ratio = CollateralRatio(120);
+        system_state.lp_collateral_requirement = CollateralRatio(200);
+
+        let state_params = UpdateStateParams { collateral_requirement: Some(CollateralRatio(120)), ..UpdateStateParams::default() };
+        assert_invalid_amount(apply_state_params(&mut system_state, &mut arb_state, &state_params));
+        let state_params = UpdateStateParams { collateral_requirement: Some(CollateralRatio(201)), ..UpdateStateParams::default() };
+        assert_invalid_amount(apply_state_params(&mut system_state, &mut arb_state, &state_params));
+        assert_eq!(system_state.collateral_requirement, CollateralRatio(150));
+
+        let state_params = UpdateStateParams { collateral_requirement: Some(CollateralRatio(200)), ..UpdateStateParams::default() };
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+        assert_eq!(system_state.collateral_requirement, CollateralRatio(200));
+    }
+
+    #[test]
+    fn test_oracle_limits_keep_the_default_they_replace() {
+        // Never set, so the state reads the compiled in limits
+        let (mut system_state, mut arb_state) = configured_state();
+        let state_params = UpdateStateParams { max_oracle_deviation_bps: Some(Bps(50)), ..UpdateStateParams::default() };
+        apply_state_params(&mut system_state, &mut arb_state, &state_params).unwrap();
+        assert_eq!(system_state.max_oracle_deviation_bps, Bps(50));
+        assert_eq!(system_state.max_oracle_staleness_slots, crate::helpers::constants::ORACLE_PRICE_MAX_SLOTS);
+
+        for state_params in [
+            UpdateStateParams { max_oracle_staleness_slots: Some(0), ..UpdateStateParams::default() },
+            UpdateStateParams { max_oracle_staleness_slots: Some(751), ..UpdateStateParams::default() },
+            UpdateStateParams { max_oracle_deviation_bps: Some(Bps(0)), ..UpdateStateParams::default() },
+            UpdateStateParams { max_oracle_deviation_bps: Some(Bps(1_001)), ..UpdateStateParams::default() },
+        ] {
+            let (mut system_state, mut arb_state) = configured_state();
+            assert_invalid_amount(apply_state_params(&mut system_state, &mut arb_state, &state_params));
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::cell::RefMut;
+
+use anchor_lang::prelude::*;
+use arrayref::array_ref;
+use rust_decimal::Decimal;
+use solana_program::{
+    account_info::AccountInfo,
+    msg,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_token::state::Account;
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::{
+        collateral::{
+            backfill_msol_collateral,
+            calc_loan_collateral_value,
+            calc_remaining_penalty_collateral,
+            find_msol_sol_oracle,
+            get_collateral_valuation_factor,
+        },
+        constants::{SOL_MATA_ORACLE, SOL_USDC_ORACLE, SOL_USDT_ORACLE},
+        oracle::{get_mata_price, get_sol_price, verify_sol_usd_oracle},
+        peg_band::update_peg,
+        settlement::check_not_settled,
+        spl::get_token_balance,
+        vaults::{verify_msol_vault, verify_msol_vault_covers},
+    },
+    idl,
+    instruction::Instruction,
+    lucra_core::{
+        interest::calc_amount_owed,
+        quote::calc_loan_amount,
+    },
+    state::{
+        LoanType,
+        MataLoan,
+        SystemState,
+    },
+    units::CollateralRatio,
+};
+
+declare_check_assert_macros!(SourceFileId::WithdrawCollateral);
+
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::WithdrawCollateral {
+            lamports,
+        } => {
+            msg!("Instruction: Withdraw Collateral");
+            process_withdraw_collateral(program_id, lamports, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
+const WITHDRAW_COLLATERAL_SIZE: usize = idl::WITHDRAW_COLLATERAL.len();
+
+/// Taking `lamports` of SOL collateral out has to leave the `amount_owed` at the loan's own collateral rate.
+/// Pending penalty is already gone from the loan's point of view, only what's left after it can be taken.
+pub fn verify_collateral_withdrawal(
+    loan: &MataLoan,
+    amount_owed: u64,
+    lamports: u64,
+    sol_price: Decimal,
+    collateral_factor: Decimal,
+) -> LucraResult {
+    check!(lamports > 0, LucraErrorCode::InvalidAmount)?;
+    check!(lamports <= calc_remaining_penalty_collateral(loan), LucraErrorCode::InvalidAmount)?;
+
+    let remaining = MataLoan {
+        sol_collateral_amount: loan.sol_collateral_amount - lamports,
+        ..*loan
+    };
+    let collateral_value = calc_loan_collateral_value(&remaining, sol_price, collateral_factor)?;
+    let backed_mata = calc_loan_amount(collateral_value, CollateralRatio(loan.collateral_rate))?;
+
+    check!(backed_mata.get() >= amount_owed, LucraErrorCode::InvalidAmount)
+}
+
+// Sends SOL collateral the loan doesn't need back to the owner as msol, converted at the marinade rate the way
+// RepayLoanPartial releases it. The loan is valued at today's SOL price like BorrowMore, locked stake counts at
+// the value it was locked at.
+#[inline(never)]
+pub fn process_withdraw_collateral(program_id: &Pubkey, lamports: u64, accounts: &[AccountInfo]) -> LucraResult {
+    const NUM_FIXED: usize = WITHDRAW_COLLATERAL_SIZE;
+    let trailing_accounts = accounts.get(NUM_FIXED..).unwrap_or(&[]);
+    let accounts = array_ref![accounts, 0, NUM_FIXED];
+    let [
+        system_state_ai,            // write
+        marinade_state_ai,          // read
+        loan_ai,                    // write
+        msol_vault_ai,              // write
+        msol_vault_authority_ai,    // read
+        user_account_ai,            // read
+        user_msol_account_ai,       // write
+        sol_usdc_oracle_ai,         // read
+        sol_usdt_oracle_ai,         // read
+        sol_mata_oracle_ai,         // read
+        token_program_ai,           // read
+    ] = accounts;
+
+    let clock = &Clock::get()?;
+
+    check_eq!(user_account_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
+
+    check_eq!(loan_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(msol_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(user_msol_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(marinade_state_ai.owner, &marinade_finance::id(), LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(token_program_ai.key, &spl_token::id(), LucraErrorCode::InvalidAccountInput)?;
+
+    verify_sol_usd_oracle(sol_usdc_oracle_ai, &SOL_USDC_ORACLE, program_id)?;
+    verify_sol_usd_oracle(sol_usdt_oracle_ai, &SOL_USDT_ORACLE, program_id)?;
+
+    let mut system_state: RefMut<SystemState> = SystemState::load_mut_checked(system_state_ai, program_id)?;
+    // Settlement values the debt at the frozen price, a close is the only way out then
+    check_not_settled(&system_state)?;
+    check!(system_state.loans_enabled, LucraErrorCode::LoansNotEnabled)?;
+    verify_msol_vault(&system_state, msol_vault_ai.key)?;
+    check!(user_msol_account_ai.key != msol_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    let mut loan: RefMut<MataLoan> = MataLoan::load_mut_checked(loan_ai, program_id)?;
+    // LP collateral is valued off the pool and against its own requirement, native SOL collateral isn't in the msol vault
+    check!(!matches!(loan.loan_type, LoanType::LpBacked | LoanType::NativeSol), LucraErrorCode::InvalidLoanType)?;
+    check_eq!(loan.repaid, false, LucraErrorCode::InvalidAccountInput)?;
+    check_eq!(&loan.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    // Taking collateral back is a partial close, it waits out the same timelock
+    check!(loan.loan_creation_date + system_state.epoch < clock.unix_timestamp, LucraErrorCode::Timelock)?;
+
+    let user_msol_account = Account::unpack(&user_msol_account_ai.data.borrow())?;
+    check_eq!(&user_msol_account.owner, user_account_ai.key, LucraErrorCode::InvalidAccountInput)?;
+
+    // A broken peg means loans are under water as a whole, nothing comes out until it holds again
+    if system_state.peg_check_enabled {
+        check_eq!(sol_mata_oracle_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        check_eq!(sol_mata_oracle_ai.key, &SOL_MATA_ORACLE, LucraErrorCode::InvalidAccountInput)?;
+
+        let mata_market_price = get_mata_price(&system_state, sol_mata_oracle_ai, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+        update_peg(&mut system_state, mata_market_price);
+        check!(!system_state.peg_broken, LucraErrorCode::BrokenPeg)?;
+    }
+
+    let sol_market_price = get_sol_price(&system_state, sol_usdc_oracle_ai, sol_usdt_oracle_ai, clock)?;
+    let msol_sol_oracle_ai = find_msol_sol_oracle(trailing_accounts, &system_state);
+    let collateral_factor = get_collateral_valuation_factor(&system_state, msol_sol_oracle_ai, Some(marinade_state_ai), clock)?;
+    verify_collateral_withdrawal(&loan, calc_amount_owed(&loan)?, lamports, sol_market_price, collateral_factor)?;
+
+    let msol_vault_balance = get_token_balance(msol_vault_ai)?;
+    backfill_msol_collateral(&mut loan, &system_state, msol_vault_balance)?;
+    let marinade_state = ProgramAccount::<marinade_finance::state::State>::try_from(&marinade_finance::id(), marinade_state_ai)
+        .map_err(|_| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+    let msol_to_user = marinade_state
+        .calc_msol_from_lamports(lamports)
+        .map_err(|_| math_err!())?
+        .min(loan.msol_collateral_amount);
+    verify_msol_vault_covers(msol_vault_balance, msol_to_user)?;
+
+    system_state.transfer_from_msol_vault(
+        program_id,
+        msol_vault_ai,
+        user_msol_account_ai,
+        msol_vault_authority_ai,
+        token_program_ai,
+        msol_to_user,
+    )?;
+
+    loan.msol_collateral_amount = loan.msol_collateral_amount
+        .checked_sub(msol_to_user)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    loan.sol_collateral_amount = loan.sol_collateral_amount
+        .checked_sub(lamports)
+        .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+    system_state.remove_collateral(lamports);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use solana_program::native_token::LAMPORTS_PER_SOL;
+    use crate::{
+        lucra_core::interest::LOAN_INTEREST_VERSION,
+        state::{DataType, MetaData},
+    };
+
+    fn loan() -> MataLoan {
+        // 10 SOL against 100 mata at 150%
+        MataLoan {
+            sol_collateral_amount: 10 * LAMPORTS_PER_SOL,
+            loan_amount: 100_000_000,
+            collateral_rate: 150,
+            ..MataLoan::default()
+        }
+    }
+
+    fn assert_invalid_amount(result: LucraResult) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::InvalidAmount, .. }
+        ));
+    }
+
+    #[test]
+    fn test_withdrawal_can_go_down_to_the_collateral_rate() {
+        // $200 of collateral at $20, the debt needs $150 of it so 2.5 SOL can come out
+        let loan = loan();
+
+        assert!(verify_collateral_withdrawal(&loan, loan.loan_amount, 2_500_000_000, dec!(20), Decimal::ONE).is_ok());
+        assert_invalid_amount(verify_collateral_withdrawal(&loan, loan.loan_amount, 2_500_000_001, dec!(20), Decimal::ONE));
+        // At the price the loan opened at there's nothing spare
+        assert_invalid_amount(verify_collateral_withdrawal(&loan, loan.loan_amount, 1, dec!(15), Decimal::ONE));
+    }
+
+    #[test]
+    fn test_pending_penalty_counts_as_already_lost() {
+        let loan = MataLoan { penalty_to_harvest: LAMPORTS_PER_SOL, ..loan() };
+
+        // 9 SOL left at $20 is $180, 1.5 SOL of it is spare
+        assert!(verify_collateral_withdrawal(&loan, loan.loan_amount, 1_500_000_000, dec!(20), Decimal::ONE).is_ok());
+        assert_invalid_amount(verify_collateral_withdrawal(&loan, loan.loan_amount, 1_500_000_001, dec!(20), Decimal::ONE));
+    }
+
+    #[test]
+    fn test_withdrawal_never_takes_more_than_the_remaining_collateral() {
+        let loan = MataLoan { loan_amount: 0, penalty_harvested: LAMPORTS_PER_SOL, ..loan() };
+
+        assert!(verify_collateral_withdrawal(&loan, loan.loan_amount, 9 * LAMPORTS_PER_SOL, dec!(20), Decimal::ONE).is_ok());
+        assert_invalid_amount(verify_collateral_withdrawal(&loan, loan.loan_amount, 9 * LAMPORTS_PER_SOL + 1, dec!(20), Decimal::ONE));
+        assert_invalid_amount(verify_collateral_withdrawal(&loan, loan.loan_amount, 0, dec!(20), Decimal::ONE));
+    }
+
+    #[test]
+    fn test_locked_stake_and_haircut_are_valued_like_borrow_more() {
+        let loan = MataLoan { staking_collateral_amount: 50, ..loan() };
+
+        // Halved by the haircut the SOL is worth $100, the $50 of locked stake makes up the rest of $150
+        assert_invalid_amount(verify_collateral_withdrawal(&loan, loan.loan_amount, 1, dec!(20), dec!(0.5)));
+        // Without it the stake covers a third of the debt and half the SOL is spare
+        assert!(verify_collateral_withdrawal(&loan, loan.loan_amount, 5 * LAMPORTS_PER_SOL, dec!(20), Decimal::ONE).is_ok());
+        assert_invalid_amount(verify_collateral_withdrawal(&loan, loan.loan_amount, 5 * LAMPORTS_PER_SOL + 1, dec!(20), Decimal::ONE));
+    }
+
+    #[test]
+    fn test_unpaid_interest_needs_backing_too() {
+        let loan = MataLoan {
+            meta_data: MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true),
+            interest_accrued: 10_000_000,
+            ..loan()
+        };
+        let amount_owed = calc_amount_owed(&loan).unwrap();
+
+        // 110 mata owed needs $165 of the $200, 1.75 SOL is spare instead of 2.5
+        assert!(verify_collateral_withdrawal(&loan, amount_owed, 1_750_000_000, dec!(20), Decimal::ONE).is_ok());
+        assert_invalid_amount(verify_collateral_withdrawal(&loan, amount_owed, 1_750_000_001, dec!(20), Decimal::ONE));
+    }
+}
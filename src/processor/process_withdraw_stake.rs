@@ -3,8 +3,10 @@ use std::cell::{Ref, RefMut};
 use arrayref::array_ref;
 use solana_program::{
     account_info::AccountInfo,
+    msg,
     program_pack::Pack,
     pubkey::Pubkey,
+    sysvar::{clock::Clock, Sysvar},
 };
 use spl_token::{
     state::{Account},
@@ -16,24 +18,39 @@ use crate::{
         LucraResult,
         SourceFileId,
     },
-    helpers::account::{close_account, add_lamports},
     helpers::spl::*,
+    helpers::staking_freeze::{bind_staking_account, check_staking_account_not_frozen},
+    instruction::Instruction,
     state::{
-        staking::StakeBalance,
+        staking::{StakeBalance, StakingAccount},
         SystemState,
     },
 };
 
 declare_check_assert_macros!(SourceFileId::WithdrawStake);
 
+#[inline(never)]
+pub fn dispatch(program_id: &Pubkey, instruction: Instruction, accounts: &[AccountInfo]) -> LucraResult {
+    match instruction {
+        Instruction::WithdrawStake {
+            lucra,
+        } => {
+            msg!("Instruction: Withdraw Stake");
+            process_withdraw_stake(program_id, lucra, accounts)
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[inline(never)]
 pub fn process_withdraw_stake(program_id: &Pubkey, lucra: u64, accounts: &[AccountInfo]) -> LucraResult {
     check!(lucra > 0, LucraErrorCode::InvalidAmount)?;
 
-    const NUM_FIXED: usize = 9;
+    const NUM_FIXED: usize = 10;
     let accounts = array_ref![accounts, 0, NUM_FIXED];
     let [
         system_state_ai,        // read
+        staking_account_ai,     // read
         stake_balance_ai,       // write
         deposit_vault_ai,       // write
         stake_vault_ai,         // read
@@ -44,27 +61,36 @@ pub fn process_withdraw_stake(program_id: &Pubkey, lucra: u64, accounts: &[Accou
         token_program_ai,       // read
     ] = accounts;
 
+    let clock = &Clock::get()?;
+
     check_eq!(system_state_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+    check_eq!(staking_account_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(stake_balance_ai.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(deposit_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(stake_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
     check_eq!(pending_vault_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
-    check_eq!(to_account_ai.owner, &spl_token::id(), LucraErrorCode::InvalidAccountOwner)?;
+    verify_user_token_account(to_account_ai, token_program_ai)?;
 
     check_eq!(owner_ai.is_signer, true, LucraErrorCode::AccountNotSigner)?;
 
     let system_state: Ref<SystemState> = SystemState::load_checked(system_state_ai, program_id)?;
-    check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+    // Stakers can always leave during settlement
+    if !system_state.settlement_active {
+        check!(system_state.staking_enabled, LucraErrorCode::StakingNotEnabled)?;
+    }
     let mut stake_balance: RefMut<StakeBalance> = StakeBalance::load_mut_checked(stake_balance_ai, program_id)?;
     check!(!stake_balance.closed, LucraErrorCode::InvalidAccountInput)?;
     let to_account = Account::unpack(&to_account_ai.data.borrow())?;
 
     check_eq!(&to_account.mint, &system_state.lucra_mint.address, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&stake_balance.owner, owner_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    let staking_account: Ref<StakingAccount> = StakingAccount::load_checked(staking_account_ai, program_id)?;
+    check_staking_account_not_frozen(&staking_account, clock.unix_timestamp)?;
     check_eq!(&to_account.owner, &stake_balance.owner, LucraErrorCode::InvalidAccountInput)?;
 
     check_eq!(&stake_balance.balances.deposit_vault, deposit_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
     check_eq!(&stake_balance.balances.stake_vault, stake_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
+    bind_staking_account(&mut stake_balance, staking_account_ai.key, &staking_account, get_token_balance(stake_vault_ai)?)?;
     check_eq!(&stake_balance.balances.pending_vault, pending_vault_ai.key, LucraErrorCode::InvalidAccountInput)?;
 
     stake_balance.transfer_from_deposit(
@@ -77,19 +103,6 @@ pub fn process_withdraw_stake(program_id: &Pubkey, lucra: u64, accounts: &[Accou
         lucra,
     )?;
 
-    let deposit_vault_balance = get_token_balance(deposit_vault_ai)?;
-    let stake_vault_balance = get_token_balance(stake_vault_ai)?;
-    let pending_vault_balance = get_token_balance(pending_vault_ai)?;
-
-    if deposit_vault_balance == 0
-        && stake_vault_balance == 0
-        && pending_vault_balance == 0
-    {
-        stake_balance.closed = true;
-        // Close the stake balance account
-        let lamports = close_account(stake_balance_ai);
-        add_lamports(owner_ai, lamports);
-    }
-
+    // An emptied stake balance stays open, CloseStakeBalance closes it along with its vaults
     Ok(())
 }
\ No newline at end of file
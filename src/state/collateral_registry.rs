@@ -0,0 +1,238 @@
+use std::cell::{Ref, RefMut};
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    state::{
+        DataType,
+        MetaData,
+    },
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::CollateralRegistry);
+
+// Collateral types the DAO whitelisted besides msol. msol is collateral type 0 and keeps its vault, oracle and
+// haircut on the system state, the registry's first entry is collateral type 1. Types are added disabled and
+// loans can't be opened against one until the DAO enables it. A msol backed loan records the type it was opened
+// against in MataLoan::collateral_type, LP backed and native SOL loans leave it at 0 and never read it.
+
+pub const MSOL_COLLATERAL_TYPE: u8 = 0;
+pub const MAX_COLLATERAL_TYPES: usize = 16;
+
+#[derive(Copy, Clone, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct CollateralType {
+    pub mint: Pubkey,
+    /// Dollar price of one whole token
+    pub oracle: Pubkey,
+    /// Holds the type's collateral, owned by its own vault authority
+    pub vault: Pubkey,
+    /// Taken off the oracle price when the collateral is valued
+    pub haircut_bps: Bps,
+    pub decimals: u8,
+    pub enabled: u8,
+    pub vault_authority_bump_seed: u8,
+    pub padding: [u8; 1],
+}
+
+impl CollateralType {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled != 0
+    }
+}
+
+#[derive(Copy, Clone, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct CollateralRegistry {
+    pub meta_data: MetaData,
+    pub system_state: Pubkey,
+    /// Types written to the front of `collateral_types`
+    pub collateral_type_count: u64,
+    pub collateral_types: [CollateralType; MAX_COLLATERAL_TYPES],
+}
+
+impl CollateralRegistry {
+    pub fn load_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<Ref<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let registry = Self::load(account)?;
+        check!(registry.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(registry.meta_data.data_type == DataType::CollateralRegistry as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(registry)
+    }
+
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let registry = Self::load_mut(account)?;
+        check!(registry.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(registry.meta_data.data_type == DataType::CollateralRegistry as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(registry)
+    }
+
+    pub fn init(&mut self, system_state: &Pubkey) {
+        self.meta_data = MetaData::new(DataType::CollateralRegistry, 0, true);
+        self.system_state = *system_state;
+        self.collateral_type_count = 0;
+        self.collateral_types = [CollateralType::default(); MAX_COLLATERAL_TYPES];
+    }
+
+    pub fn collateral_types(&self) -> &[CollateralType] {
+        &self.collateral_types[..self.collateral_type_count as usize]
+    }
+
+    /// Index of the registry slot holding `collateral_type`, msol isn't in the registry
+    fn slot(&self, collateral_type: u8) -> LucraResult<usize> {
+        check!(collateral_type != MSOL_COLLATERAL_TYPE, LucraErrorCode::InvalidAccountInput)?;
+        let slot = collateral_type as usize - 1;
+        check!(slot < self.collateral_type_count as usize, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(slot)
+    }
+
+    pub fn get(&self, collateral_type: u8) -> LucraResult<&CollateralType> {
+        let slot = self.slot(collateral_type)?;
+        Ok(&self.collateral_types[slot])
+    }
+
+    /// The collateral type a new loan is opened against, it has to be enabled
+    pub fn get_enabled(&self, collateral_type: u8) -> LucraResult<&CollateralType> {
+        let entry = self.get(collateral_type)?;
+        check!(entry.is_enabled(), LucraErrorCode::LoansNotEnabled)?;
+
+        Ok(entry)
+    }
+
+    /// Adds a disabled collateral type and returns its index. A mint can only be registered once.
+    pub fn add(&mut self, collateral_type: CollateralType) -> LucraResult<u8> {
+        check!(collateral_type.mint != Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+        check!(collateral_type.oracle != Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+        check!(collateral_type.vault != Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+        check!(collateral_type.haircut_bps <= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)?;
+        check!(
+            self.collateral_types().iter().all(|entry| entry.mint != collateral_type.mint && entry.vault != collateral_type.vault),
+            LucraErrorCode::InvalidAccountInput
+        )?;
+
+        let count = self.collateral_type_count as usize;
+        check!(count < MAX_COLLATERAL_TYPES, LucraErrorCode::CollateralRegistryFull)?;
+        self.collateral_types[count] = CollateralType { enabled: 0, ..collateral_type };
+        self.collateral_type_count = self.collateral_type_count.checked_add(1).ok_or(math_err!())?;
+
+        Ok((count + 1) as u8)
+    }
+
+    /// The mint and vault stay what they were registered with, collateral already in the vault depends on them
+    pub fn set(&mut self, collateral_type: u8, oracle: &Pubkey, haircut_bps: Bps, enabled: bool) -> LucraResult {
+        check!(oracle != &Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+        check!(haircut_bps <= Bps::ONE_HUNDRED_PERCENT, LucraErrorCode::InvalidAmount)?;
+
+        let slot = self.slot(collateral_type)?;
+        let entry = &mut self.collateral_types[slot];
+        entry.oracle = *oracle;
+        entry.haircut_bps = haircut_bps;
+        entry.enabled = enabled as u8;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_registry() -> CollateralRegistry {
+        let mut registry = CollateralRegistry::zeroed();
+        registry.init(&Pubkey::new_unique());
+        registry
+    }
+
+    fn new_type(haircut_bps: Bps) -> CollateralType {
+        CollateralType {
+            mint: Pubkey::new_unique(),
+            oracle: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            haircut_bps,
+            decimals: 9,
+            enabled: 1,
+            ..CollateralType::default()
+        }
+    }
+
+    #[test]
+    fn test_types_are_added_disabled_after_msol() {
+        let mut registry = new_registry();
+        assert_eq!(registry.meta_data.data_type, DataType::CollateralRegistry as u8);
+
+        let jito_sol = new_type(Bps(200));
+        assert_eq!(registry.add(jito_sol).unwrap(), 1);
+        assert_eq!(registry.add(new_type(Bps(300))).unwrap(), 2);
+
+        let entry = registry.get(1).unwrap();
+        assert_eq!(entry.mint, jito_sol.mint);
+        assert!(!entry.is_enabled());
+        assert!(registry.get_enabled(1).is_err());
+
+        // msol and types that were never added aren't in the registry
+        assert!(registry.get(MSOL_COLLATERAL_TYPE).is_err());
+        assert!(registry.get(3).is_err());
+    }
+
+    #[test]
+    fn test_mints_and_vaults_are_registered_once() {
+        let mut registry = new_registry();
+        let jito_sol = new_type(Bps(200));
+        registry.add(jito_sol).unwrap();
+
+        assert!(registry.add(CollateralType { vault: Pubkey::new_unique(), ..jito_sol }).is_err());
+        assert!(registry.add(CollateralType { mint: Pubkey::new_unique(), ..jito_sol }).is_err());
+        assert!(registry.add(CollateralType { oracle: Pubkey::default(), ..new_type(Bps(0)) }).is_err());
+        assert!(registry.add(new_type(Bps(10_001))).is_err());
+        assert_eq!(registry.collateral_types().len(), 1);
+    }
+
+    #[test]
+    fn test_registry_holds_max_collateral_types() {
+        let mut registry = new_registry();
+        for _ in 0..MAX_COLLATERAL_TYPES {
+            registry.add(new_type(Bps(100))).unwrap();
+        }
+
+        assert!(registry.add(new_type(Bps(100))).is_err());
+        assert!(registry.get(MAX_COLLATERAL_TYPES as u8).is_ok());
+    }
+
+    #[test]
+    fn test_set_enables_and_reprices_a_type() {
+        let mut registry = new_registry();
+        let jito_sol = new_type(Bps(200));
+        registry.add(jito_sol).unwrap();
+
+        let oracle = Pubkey::new_unique();
+        registry.set(1, &oracle, Bps(500), true).unwrap();
+        let entry = registry.get_enabled(1).unwrap();
+        assert_eq!(entry.oracle, oracle);
+        assert_eq!(entry.haircut_bps, Bps(500));
+        assert_eq!((entry.mint, entry.vault), (jito_sol.mint, jito_sol.vault));
+
+        registry.set(1, &oracle, Bps(500), false).unwrap();
+        assert!(registry.get_enabled(1).is_err());
+
+        assert!(registry.set(1, &oracle, Bps(10_001), true).is_err());
+        assert!(registry.set(1, &Pubkey::default(), Bps(500), true).is_err());
+        assert!(registry.set(MSOL_COLLATERAL_TYPE, &oracle, Bps(500), true).is_err());
+        assert!(registry.set(2, &oracle, Bps(500), true).is_err());
+    }
+}
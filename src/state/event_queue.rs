@@ -0,0 +1,190 @@
+use std::cell::RefMut;
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Slot,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    state::{
+        DataType,
+        MetaData,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::EventQueue);
+
+pub const EVENT_QUEUE_LENGTH: usize = 128;
+
+/// What an event is about. Zero is never used, so an unwritten slot can't be read as an event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum EventKind {
+    /// The loan, then the collateral deposited (LP tokens for an LP backed loan) and the mata minted
+    CreateMataLoan = 1,
+    /// The loan, then the mata burned and the lamports returned (LP tokens for an LP backed loan)
+    CloseMataLoan = 2,
+    /// The loan, then the lamports of penalty harvested and the mata burned, none when the penalty went
+    /// to the arb coffer
+    HarvestPenalty = 3,
+    /// The stake balance, then the lucra staked and the staked lucra minted for it
+    Stake = 4,
+    /// The stake balance, then the lucra moved to pending and the staked lucra burned for it
+    StartUnstake = 5,
+    /// The arb state, then the amount processed
+    MintFundsForArb = 6,
+    SellFundsForArb = 7,
+    BuyBurnForArb = 8,
+    CleanUpArb = 9,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct Event {
+    pub sequence: u64,
+    pub slot: Slot,
+    /// The account the event is about, see EventKind
+    pub principal: Pubkey,
+    pub amounts: [u64; 2],
+    pub kind: u8,
+    pub padding: [u8; 7],
+}
+
+impl Event {
+    pub fn kind(&self) -> Option<EventKind> {
+        EventKind::try_from(self.kind).ok()
+    }
+}
+
+/// Ring of the last events the program emitted, one per system state. Indexers read it instead of the
+/// transaction logs, which get truncated. `sequence` only ever goes up, so a reader that keeps the last
+/// sequence it saw can tell what's new and how many events it missed when the ring lapped it.
+#[derive(Copy, Clone, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct EventQueue {
+    pub meta_data: MetaData,
+    pub system_state: Pubkey,
+    /// Sequence of the last event, the first event is 1
+    pub sequence: u64,
+    pub events: [Event; EVENT_QUEUE_LENGTH],
+}
+
+impl EventQueue {
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let queue = Self::load_mut(account)?;
+        check!(queue.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(queue.meta_data.data_type == DataType::EventQueue as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(queue)
+    }
+
+    pub fn init(&mut self, system_state: &Pubkey) {
+        self.meta_data = MetaData::new(DataType::EventQueue, 0, true);
+        self.system_state = *system_state;
+    }
+
+    /// Writes over the oldest event once the ring is full
+    pub fn push(&mut self, kind: EventKind, principal: &Pubkey, amounts: [u64; 2], slot: Slot) {
+        self.sequence = self.sequence.wrapping_add(1);
+        self.events[Self::index(self.sequence)] = Event {
+            sequence: self.sequence,
+            slot,
+            principal: *principal,
+            amounts,
+            kind: kind.into(),
+            ..Event::default()
+        };
+    }
+
+    /// First sequence still in the ring after `last_seen`
+    fn first_after(&self, last_seen: u64) -> u64 {
+        let oldest = self.sequence.saturating_sub(EVENT_QUEUE_LENGTH as u64) + 1;
+        oldest.max(last_seen.saturating_add(1))
+    }
+
+    /// Events after `last_seen` that are still in the ring, oldest first. 0 reads all of them.
+    pub fn events_after(&self, last_seen: u64) -> impl Iterator<Item = &Event> {
+        (self.first_after(last_seen)..=self.sequence).map(move |sequence| &self.events[Self::index(sequence)])
+    }
+
+    /// Events after `last_seen` that were written over before they could be read
+    pub fn missed_after(&self, last_seen: u64) -> u64 {
+        self.first_after(last_seen).saturating_sub(last_seen.saturating_add(1))
+    }
+
+    fn index(sequence: u64) -> usize {
+        (sequence.wrapping_sub(1) % EVENT_QUEUE_LENGTH as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue_with(events: u64) -> Box<EventQueue> {
+        let mut queue = Box::new(EventQueue::zeroed());
+        let principal = Pubkey::new_unique();
+        for slot in 1..=events {
+            queue.push(EventKind::Stake, &principal, [slot, 0], slot);
+        }
+        queue
+    }
+
+    fn sequences(queue: &EventQueue, last_seen: u64) -> Vec<u64> {
+        queue.events_after(last_seen).map(|event| event.sequence).collect()
+    }
+
+    #[test]
+    fn test_events_are_read_in_order() {
+        let queue = queue_with(3);
+
+        assert_eq!(sequences(&queue, 0), vec![1, 2, 3]);
+        assert_eq!(sequences(&queue, 2), vec![3]);
+        assert!(sequences(&queue, 3).is_empty());
+        assert_eq!(queue.missed_after(0), 0);
+
+        let event = queue.events_after(0).next().unwrap();
+        assert_eq!(event.kind(), Some(EventKind::Stake));
+        assert_eq!(event.amounts, [1, 0]);
+        assert_eq!(event.slot, 1);
+    }
+
+    #[test]
+    fn test_ring_wraps_around() {
+        let events = EVENT_QUEUE_LENGTH as u64 + 5;
+        let queue = queue_with(events);
+
+        // The first five were written over
+        let all = sequences(&queue, 0);
+        assert_eq!(all.len(), EVENT_QUEUE_LENGTH);
+        assert_eq!(all[0], 6);
+        assert_eq!(*all.last().unwrap(), events);
+        assert!(all.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        assert_eq!(queue.missed_after(0), 5);
+        assert_eq!(queue.missed_after(3), 2);
+
+        // A reader that kept up misses nothing
+        assert_eq!(sequences(&queue, events - 2), vec![events - 1, events]);
+        assert_eq!(queue.missed_after(events - 2), 0);
+        assert_eq!(queue.events_after(events - 1).next().unwrap().slot, events);
+    }
+
+    #[test]
+    fn test_empty_queue_has_no_events() {
+        let queue = queue_with(0);
+
+        assert!(sequences(&queue, 0).is_empty());
+        assert_eq!(queue.missed_after(0), 0);
+        assert_eq!(Event::default().kind(), None);
+    }
+}
@@ -0,0 +1,192 @@
+use std::cell::{Ref, RefMut};
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::MAX_KEEPER_MULTIPLIER,
+    state::{
+        DataType,
+        MetaData,
+    },
+    units::Bps,
+};
+
+declare_check_assert_macros!(SourceFileId::KeeperRegistry);
+
+// Keepers are the crank callers the DAO registered. Their crank rewards are scaled by their multiplier, and while
+// the system state restricts cranking they're the only ones who can run the cranks at all.
+
+pub const MAX_KEEPERS: usize = 32;
+
+#[derive(Copy, Clone, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct Keeper {
+    pub address: Pubkey,
+    /// Share of a crank's reward the keeper is paid, 10_000 pays the reward as it is
+    pub multiplier: Bps,
+}
+
+#[derive(Copy, Clone, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct KeeperRegistry {
+    pub meta_data: MetaData,
+    pub system_state: Pubkey,
+    /// Keepers written to the front of `keepers`
+    pub keeper_count: u64,
+    pub keepers: [Keeper; MAX_KEEPERS],
+}
+
+impl KeeperRegistry {
+    pub fn load_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<Ref<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let registry = Self::load(account)?;
+        check!(registry.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(registry.meta_data.data_type == DataType::KeeperRegistry as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(registry)
+    }
+
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let registry = Self::load_mut(account)?;
+        check!(registry.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(registry.meta_data.data_type == DataType::KeeperRegistry as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(registry)
+    }
+
+    pub fn init(&mut self, system_state: &Pubkey) {
+        self.meta_data = MetaData::new(DataType::KeeperRegistry, 0, true);
+        self.system_state = *system_state;
+        self.keeper_count = 0;
+        self.keepers = [Keeper::default(); MAX_KEEPERS];
+    }
+
+    pub fn keepers(&self) -> &[Keeper] {
+        &self.keepers[..self.keeper_count as usize]
+    }
+
+    /// Multiplier of `address`, None when it isn't a keeper
+    pub fn multiplier_of(&self, address: &Pubkey) -> Option<Bps> {
+        self.keepers()
+            .iter()
+            .find(|keeper| &keeper.address == address)
+            .map(|keeper| keeper.multiplier)
+    }
+
+    /// Registers `address`, or sets the multiplier of a keeper that already is
+    pub fn register(&mut self, address: &Pubkey, multiplier: Bps) -> LucraResult {
+        check!(address != &Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+        check!(multiplier <= MAX_KEEPER_MULTIPLIER, LucraErrorCode::InvalidAmount)?;
+
+        let count = self.keeper_count as usize;
+        if let Some(keeper) = self.keepers[..count].iter_mut().find(|keeper| &keeper.address == address) {
+            keeper.multiplier = multiplier;
+            return Ok(());
+        }
+
+        check!(count < MAX_KEEPERS, LucraErrorCode::KeeperRegistryFull)?;
+        self.keepers[count] = Keeper { address: *address, multiplier };
+        self.keeper_count = self.keeper_count.checked_add(1).ok_or(math_err!())?;
+
+        Ok(())
+    }
+
+    /// The last keeper takes the removed one's slot, the order keepers are kept in doesn't matter
+    pub fn remove(&mut self, address: &Pubkey) -> LucraResult {
+        let count = self.keeper_count as usize;
+        let index = self.keepers[..count]
+            .iter()
+            .position(|keeper| &keeper.address == address)
+            .ok_or_else(|| throw_err!(LucraErrorCode::KeeperNotRegistered))?;
+
+        self.keepers[index] = self.keepers[count - 1];
+        self.keepers[count - 1] = Keeper::default();
+        self.keeper_count -= 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_registry() -> KeeperRegistry {
+        let mut registry = KeeperRegistry::zeroed();
+        registry.init(&Pubkey::new_unique());
+        registry
+    }
+
+    #[test]
+    fn test_registered_keepers_have_their_multiplier() {
+        let mut registry = new_registry();
+        let (keeper, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(registry.meta_data.data_type, DataType::KeeperRegistry as u8);
+        assert!(registry.multiplier_of(&keeper).is_none());
+
+        registry.register(&keeper, Bps(15_000)).unwrap();
+        registry.register(&other, Bps(5_000)).unwrap();
+        assert_eq!(registry.multiplier_of(&keeper), Some(Bps(15_000)));
+        assert_eq!(registry.multiplier_of(&other), Some(Bps(5_000)));
+
+        // Registering again only changes the multiplier
+        registry.register(&keeper, Bps(20_000)).unwrap();
+        assert_eq!(registry.keeper_count, 2);
+        assert_eq!(registry.multiplier_of(&keeper), Some(Bps(20_000)));
+
+        assert!(registry.register(&keeper, Bps(MAX_KEEPER_MULTIPLIER.get() + 1)).is_err());
+        assert!(registry.register(&Pubkey::default(), Bps(10_000)).is_err());
+        assert_eq!(registry.multiplier_of(&keeper), Some(Bps(20_000)));
+    }
+
+    #[test]
+    fn test_registry_holds_max_keepers() {
+        let mut registry = new_registry();
+        let keepers: Vec<Pubkey> = (0..MAX_KEEPERS).map(|_| Pubkey::new_unique()).collect();
+        for keeper in keepers.iter() {
+            registry.register(keeper, Bps(10_000)).unwrap();
+        }
+
+        assert!(registry.register(&Pubkey::new_unique(), Bps(10_000)).is_err());
+        // A keeper already in it can still be updated
+        registry.register(&keepers[0], Bps(12_000)).unwrap();
+
+        registry.remove(&keepers[3]).unwrap();
+        registry.register(&Pubkey::new_unique(), Bps(10_000)).unwrap();
+        assert_eq!(registry.keepers().len(), MAX_KEEPERS);
+    }
+
+    #[test]
+    fn test_removed_keeper_is_anonymous_again() {
+        let mut registry = new_registry();
+        let keepers: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        for (index, keeper) in keepers.iter().enumerate() {
+            registry.register(keeper, Bps(10_000 + index as u32)).unwrap();
+        }
+
+        registry.remove(&keepers[0]).unwrap();
+        assert_eq!(registry.keeper_count, 2);
+        assert!(registry.multiplier_of(&keepers[0]).is_none());
+        // The others keep their multipliers
+        assert_eq!(registry.multiplier_of(&keepers[1]), Some(Bps(10_001)));
+        assert_eq!(registry.multiplier_of(&keepers[2]), Some(Bps(10_002)));
+
+        assert!(registry.remove(&keepers[0]).is_err());
+        registry.remove(&keepers[1]).unwrap();
+        registry.remove(&keepers[2]).unwrap();
+        assert!(registry.keepers().is_empty());
+        assert!(registry.remove(&keepers[2]).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use std::cell::RefMut;
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    state::{
+        DataType,
+        MetaData,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::LoanRegistry);
+
+/// Index of a loan opened before loans were PDAs, at an address its creator picked
+pub const LEGACY_LOAN_INDEX: u64 = 0;
+
+/// Counter of the loans a user opened, one per user. A loan lives at the PDA of its creator and index, so
+/// the registry is what hands out the address of the next one and no two loans can share an address.
+#[derive(Copy, Clone, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct LoanRegistry {
+    pub meta_data: MetaData,
+    pub owner: Pubkey,
+    /// Loans opened so far, which is also the index of the last one. The first loan is 1.
+    pub loan_count: u64,
+}
+
+impl LoanRegistry {
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let registry = Self::load_mut(account)?;
+        check!(registry.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(registry.meta_data.data_type == DataType::LoanRegistry as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(registry)
+    }
+
+    pub fn init(&mut self, owner: &Pubkey) {
+        self.meta_data = MetaData::new(DataType::LoanRegistry, 0, true);
+        self.owner = *owner;
+        self.loan_count = 0;
+    }
+
+    /// Index the next loan is derived with
+    pub fn next_loan_index(&self) -> LucraResult<u64> {
+        self.loan_count.checked_add(1).ok_or(math_err!())
+    }
+
+    /// Hands out the next index, once the loan's account is created at its address
+    pub fn record_loan(&mut self) -> LucraResult<u64> {
+        let loan_index = self.next_loan_index()?;
+        self.loan_count = loan_index;
+
+        Ok(loan_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_registry_starts_at_the_first_loan() {
+        let owner = Pubkey::new_unique();
+        let mut registry = LoanRegistry::zeroed();
+        registry.init(&owner);
+
+        assert!(registry.meta_data.is_initialized);
+        assert_eq!(registry.meta_data.data_type, DataType::LoanRegistry as u8);
+        assert_eq!(registry.owner, owner);
+        assert_eq!(registry.loan_count, 0);
+        assert_eq!(registry.next_loan_index().unwrap(), 1);
+        assert_ne!(registry.next_loan_index().unwrap(), LEGACY_LOAN_INDEX);
+    }
+
+    #[test]
+    fn test_each_loan_gets_the_next_index() {
+        let mut registry = LoanRegistry::zeroed();
+        registry.init(&Pubkey::new_unique());
+
+        assert_eq!(registry.record_loan().unwrap(), 1);
+        assert_eq!(registry.record_loan().unwrap(), 2);
+        assert_eq!(registry.next_loan_index().unwrap(), 3);
+        assert_eq!(registry.loan_count, 2);
+
+        // Reading the next index doesn't hand it out
+        assert_eq!(registry.next_loan_index().unwrap(), 3);
+        assert_eq!(registry.record_loan().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_index_never_wraps_back_to_a_used_address() {
+        let mut registry = LoanRegistry::zeroed();
+        registry.init(&Pubkey::new_unique());
+        registry.loan_count = u64::MAX;
+
+        assert!(registry.next_loan_index().is_err());
+        assert!(registry.record_loan().is_err());
+        assert_eq!(registry.loan_count, u64::MAX);
+    }
+}
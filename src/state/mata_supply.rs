@@ -0,0 +1,319 @@
+use bytemuck::{Pod, Zeroable};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::MataSupply);
+
+/// Where a given amount of mata was minted from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MataBucket {
+    Loan,
+    Arb,
+}
+
+/// Outstanding mata split by the mint path that created it. Each burn site must burn from the
+/// same bucket its mata was minted into; a burn larger than its bucket is a logic bug and fails loudly.
+/// The loan bucket is what loans still owe, so every loan can be closed out against it. Mata a penalty
+/// harvest buys back and burns pays no loan down, it comes off the total through `bought_back_mata`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct MataSupply {
+    pub loan_minted_mata: u64,
+    pub arb_minted_mata: u64,
+    /// Loan mata whose debt was repaid with collateral sent to the coffer instead of being bought back.
+    /// It stays outstanding until the coffer buys and burns mata below the peg.
+    pub deferred_burn_mata: u64,
+    /// Mata bought off the market with penalties and burned. It was minted by a loan or an arb that is
+    /// still counted in its bucket, so it's taken off the total instead.
+    pub bought_back_mata: u64,
+}
+
+impl MataSupply {
+    /// Everything outstanding before the split was minted by loans
+    pub fn from_legacy_total(total_outstanding_mata: u64) -> Self {
+        MataSupply {
+            loan_minted_mata: total_outstanding_mata,
+            arb_minted_mata: 0,
+            deferred_burn_mata: 0,
+            bought_back_mata: 0,
+        }
+    }
+
+    fn minted(&self) -> LucraResult<u64> {
+        self.loan_minted_mata
+            .checked_add(self.arb_minted_mata)
+            .ok_or(math_err!())
+    }
+
+    pub fn total(&self) -> LucraResult<u64> {
+        self.minted()?
+            .checked_sub(self.bought_back_mata)
+            .ok_or_else(|| invariant_err!(LucraErrorCode::MataBucketUnderflow))
+    }
+
+    pub fn get(&self, bucket: MataBucket) -> u64 {
+        match bucket {
+            MataBucket::Loan => self.loan_minted_mata,
+            MataBucket::Arb => self.arb_minted_mata,
+        }
+    }
+
+    fn get_mut(&mut self, bucket: MataBucket) -> &mut u64 {
+        match bucket {
+            MataBucket::Loan => &mut self.loan_minted_mata,
+            MataBucket::Arb => &mut self.arb_minted_mata,
+        }
+    }
+
+    pub fn mint(&mut self, bucket: MataBucket, amount: u64) -> LucraResult {
+        let mut updated = *self;
+        let counter = updated.get_mut(bucket);
+        *counter = counter
+            .checked_add(amount)
+            .ok_or(math_err!())?;
+
+        // Keep the reported total representable
+        updated.minted()?;
+        *self = updated;
+
+        Ok(())
+    }
+
+    pub fn burn(&mut self, bucket: MataBucket, amount: u64) -> LucraResult {
+        let counter = self.get_mut(bucket);
+        invariant!(amount <= *counter, LucraErrorCode::MataBucketUnderflow)?;
+        *counter -= amount;
+        // Deferred burns can never be more than what is left in the loan bucket, nor buy-backs more than
+        // what is left minted
+        self.deferred_burn_mata = self.deferred_burn_mata.min(self.loan_minted_mata);
+        self.bought_back_mata = self.bought_back_mata.min(self.loan_minted_mata + self.arb_minted_mata);
+
+        Ok(())
+    }
+
+    /// Records mata a penalty harvest bought back and burned. No loan was paid down by it, so the loan
+    /// bucket keeps covering every loan's debt.
+    pub fn burn_bought_back(&mut self, amount: u64) -> LucraResult {
+        let bought_back = self.bought_back_mata
+            .checked_add(amount)
+            .ok_or(math_err!())?;
+        invariant!(bought_back <= self.minted()?, LucraErrorCode::MataBucketUnderflow)?;
+        self.bought_back_mata = bought_back;
+
+        Ok(())
+    }
+
+    /// Burns the mata a loan was repaid with. Its debt comes out of the loan bucket. Interest on top of it was
+    /// never minted for the loan, it's mata minted for something else, so it comes off the total like a
+    /// buy-back does.
+    pub fn burn_repayment(&mut self, mata: u64, loan_amount: u64) -> LucraResult {
+        let debt = mata.min(loan_amount);
+        let mut updated = *self;
+        updated.burn(MataBucket::Loan, debt)?;
+        updated.burn_bought_back(mata - debt)?;
+        *self = updated;
+
+        Ok(())
+    }
+
+    /// Records loan mata that has to be burned later, once the coffer buys it back below the peg
+    pub fn defer_burn(&mut self, amount: u64) -> LucraResult {
+        let deferred = self.deferred_burn_mata
+            .checked_add(amount)
+            .ok_or(math_err!())?;
+        invariant!(deferred <= self.loan_minted_mata, LucraErrorCode::MataBucketUnderflow)?;
+        self.deferred_burn_mata = deferred;
+
+        Ok(())
+    }
+
+    /// Burns mata the coffer bought back. Deferred loan mata is settled first and whatever is left over
+    /// comes out of the arb bucket. Returns the amount that settled deferred loan mata.
+    pub fn burn_from_coffer(&mut self, amount: u64) -> LucraResult<u64> {
+        let settled = amount.min(self.deferred_burn_mata);
+        let mut updated = *self;
+        updated.burn(MataBucket::Loan, settled)?;
+        updated.deferred_burn_mata -= settled;
+        updated.burn(MataBucket::Arb, amount - settled)?;
+        *self = updated;
+
+        Ok(settled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_is_sum_of_buckets() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, 100).unwrap();
+        supply.mint(MataBucket::Arb, 25).unwrap();
+
+        assert_eq!(supply.total().unwrap(), 125);
+        assert_eq!(supply.get(MataBucket::Loan), 100);
+        assert_eq!(supply.get(MataBucket::Arb), 25);
+    }
+
+    #[test]
+    fn test_burn_from_own_bucket() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, 100).unwrap();
+        supply.burn(MataBucket::Loan, 100).unwrap();
+
+        assert_eq!(supply, MataSupply::default());
+    }
+
+    #[test]
+    fn test_cross_bucket_burn_is_rejected() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Arb, 100).unwrap();
+
+        let result = supply.burn(MataBucket::Loan, 1);
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::Invariant {
+                lucra_error_code: LucraErrorCode::MataBucketUnderflow,
+                source_file_id: SourceFileId::MataSupply,
+                ..
+            }
+        ));
+        assert_eq!(supply.get(MataBucket::Arb), 100);
+        assert_eq!(supply.get(MataBucket::Loan), 0);
+    }
+
+    #[test]
+    fn test_burn_more_than_bucket_is_rejected() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, 50).unwrap();
+        supply.mint(MataBucket::Arb, 50).unwrap();
+
+        assert!(supply.burn(MataBucket::Arb, 51).is_err());
+        assert_eq!(supply.total().unwrap(), 100);
+    }
+
+    #[test]
+    fn test_interest_repaid_leaves_other_loans_covered() {
+        // Two loans of 100 and 50, the first pays 10 of interest with mata the second minted
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, 150).unwrap();
+        supply.burn_repayment(110, 100).unwrap();
+
+        assert_eq!(supply.get(MataBucket::Loan), 50);
+        assert_eq!(supply.total().unwrap(), 40);
+
+        // A repayment under the debt only comes off the loan bucket
+        supply.burn_repayment(30, 50).unwrap();
+        assert_eq!(supply.get(MataBucket::Loan), 20);
+        assert_eq!(supply.total().unwrap(), 10);
+    }
+
+    #[test]
+    fn test_repayment_past_the_supply_is_rejected() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, 100).unwrap();
+
+        assert!(supply.burn_repayment(101, 100).is_err());
+        assert_eq!(supply.get(MataBucket::Loan), 100);
+        assert_eq!(supply.total().unwrap(), 100);
+    }
+
+    #[test]
+    fn test_mint_overflow_is_rejected() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, u64::MAX).unwrap();
+
+        assert!(supply.mint(MataBucket::Loan, 1).is_err());
+        assert!(supply.mint(MataBucket::Arb, 1).is_err());
+        assert_eq!(supply.get(MataBucket::Arb), 0);
+    }
+
+    #[test]
+    fn test_deferred_burn_is_settled_by_coffer_burns_first() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, 100).unwrap();
+        supply.mint(MataBucket::Arb, 50).unwrap();
+        supply.defer_burn(30).unwrap();
+
+        // Deferred mata is still outstanding until it's bought back
+        assert_eq!(supply.total().unwrap(), 150);
+
+        assert_eq!(supply.burn_from_coffer(20).unwrap(), 20);
+        assert_eq!(supply.deferred_burn_mata, 10);
+        assert_eq!(supply.get(MataBucket::Loan), 80);
+        assert_eq!(supply.get(MataBucket::Arb), 50);
+
+        assert_eq!(supply.burn_from_coffer(25).unwrap(), 10);
+        assert_eq!(supply.deferred_burn_mata, 0);
+        assert_eq!(supply.get(MataBucket::Loan), 70);
+        assert_eq!(supply.get(MataBucket::Arb), 35);
+    }
+
+    #[test]
+    fn test_defer_more_than_loan_bucket_is_rejected() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, 10).unwrap();
+
+        assert!(supply.defer_burn(11).is_err());
+        supply.defer_burn(10).unwrap();
+        assert!(supply.defer_burn(1).is_err());
+    }
+
+    #[test]
+    fn test_failed_coffer_burn_leaves_supply_untouched() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, 10).unwrap();
+        supply.defer_burn(5).unwrap();
+        let before = supply;
+
+        assert!(supply.burn_from_coffer(6).is_err());
+        assert_eq!(supply, before);
+    }
+
+    #[test]
+    fn test_loan_bucket_covers_every_loan_through_buy_backs() {
+        // Two loans are opened, a harvest buys back and burns mata, then both loans close out
+        let mut loan_amounts = vec![600, 400];
+        let mut supply = MataSupply::default();
+        for loan_amount in &loan_amounts {
+            supply.mint(MataBucket::Loan, *loan_amount).unwrap();
+        }
+
+        supply.burn_bought_back(150).unwrap();
+        assert_eq!(supply.get(MataBucket::Loan), loan_amounts.iter().sum::<u64>());
+        assert_eq!(supply.total().unwrap(), 850);
+
+        while let Some(loan_amount) = loan_amounts.pop() {
+            supply.burn(MataBucket::Loan, loan_amount).unwrap();
+            assert_eq!(supply.get(MataBucket::Loan), loan_amounts.iter().sum::<u64>());
+        }
+        assert_eq!(supply.total().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_buy_backs_cant_exceed_what_was_minted() {
+        let mut supply = MataSupply::default();
+        supply.mint(MataBucket::Loan, 100).unwrap();
+        supply.mint(MataBucket::Arb, 20).unwrap();
+
+        assert!(supply.burn_bought_back(121).is_err());
+        supply.burn_bought_back(120).unwrap();
+        assert_eq!(supply.total().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_migration_assigns_legacy_total_to_loans() {
+        let supply = MataSupply::from_legacy_total(1_000);
+
+        assert_eq!(supply.loan_minted_mata, 1_000);
+        assert_eq!(supply.arb_minted_mata, 0);
+    }
+}
@@ -0,0 +1,169 @@
+use std::cell::RefMut;
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    state::{
+        DataType,
+        MetaData,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::PenaltyHistory);
+
+pub const PENALTY_HISTORY_LENGTH: usize = 30;
+
+/// Penalty a loan was charged for one day and the multiplier the day's mata price put on it
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct PenaltyHistoryEntry {
+    pub date: i64,
+    pub charge: u64,
+    pub multiplier: u16,
+    pub padding: [u8; 6],
+}
+
+/// Audit record of the last days DeterminePenalty charged a loan for, one per loan. The loan's penalty
+/// journal only keeps enough days for RecomputePenalty, this keeps a month so a disputed charge can be
+/// traced back to the day and multiplier that produced it.
+#[derive(Copy, Clone, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct PenaltyHistory {
+    pub meta_data: MetaData,
+    pub loan: Pubkey,
+    pub head: u64,
+    pub entries: [PenaltyHistoryEntry; PENALTY_HISTORY_LENGTH],
+}
+
+impl PenaltyHistory {
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let history = Self::load_mut(account)?;
+        check!(history.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(history.meta_data.data_type == DataType::PenaltyHistory as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(history)
+    }
+
+    pub fn init(&mut self, loan: &Pubkey) {
+        self.meta_data = MetaData::new(DataType::PenaltyHistory, 0, true);
+        self.loan = *loan;
+    }
+
+    /// Writes over the oldest entry once the history is full
+    pub fn record(&mut self, entry: PenaltyHistoryEntry) {
+        let index = self.head as usize % PENALTY_HISTORY_LENGTH;
+        self.entries[index] = entry;
+        self.head = self.head.wrapping_add(1);
+    }
+
+    /// Records the days one DeterminePenalty charged. The days are charged before the loan's cap, so
+    /// each day only keeps what was left of `total_charged` once the days before it were recorded.
+    pub fn record_charges(&mut self, days: &[PenaltyHistoryEntry], total_charged: u64) {
+        let mut uncharged = total_charged;
+        for day in days {
+            let charge = day.charge.min(uncharged);
+            uncharged -= charge;
+            self.record(PenaltyHistoryEntry { charge, ..*day });
+        }
+    }
+
+    /// Penalty charged over the days still in the history
+    pub fn total_charged(&self) -> u64 {
+        self.entries
+            .iter()
+            .fold(0_u64, |total, entry| total.saturating_add(entry.charge))
+    }
+
+    /// The recorded days, oldest first
+    pub fn days(&self) -> impl Iterator<Item = &PenaltyHistoryEntry> {
+        let head = self.head as usize % PENALTY_HISTORY_LENGTH;
+        self.entries[head..]
+            .iter()
+            .chain(self.entries[..head].iter())
+            .filter(|entry| entry.date != 0)
+    }
+}
+
+/// What the penalty multiplier of a day is recorded as, a multiplier that doesn't fit is kept at the maximum
+pub fn to_recorded_multiplier(multiplier: u64) -> u16 {
+    u16::try_from(multiplier).unwrap_or(u16::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+
+    fn day(date: i64, charge: u64) -> PenaltyHistoryEntry {
+        PenaltyHistoryEntry {
+            date,
+            charge,
+            multiplier: 2,
+            ..PenaltyHistoryEntry::default()
+        }
+    }
+
+    #[test]
+    fn test_history_wraps_after_a_month_of_accruals() {
+        let mut history = PenaltyHistory::zeroed();
+        let mut penalty_to_harvest = 0;
+        for date in 1..=PENALTY_HISTORY_LENGTH as i64 {
+            history.record_charges(&[day(date * DAY, 100)], 100);
+            penalty_to_harvest += 100;
+        }
+        assert_eq!(history.total_charged(), penalty_to_harvest);
+        assert_eq!(history.days().next().unwrap().date, DAY);
+
+        // The 31st accrual pushes the first day out
+        history.record_charges(&[day(31 * DAY, 250)], 250);
+        assert_eq!(history.total_charged(), penalty_to_harvest - 100 + 250);
+        let dates: Vec<i64> = history.days().map(|entry| entry.date).collect();
+        assert_eq!(dates.len(), PENALTY_HISTORY_LENGTH);
+        assert_eq!(dates[0], 2 * DAY);
+        assert_eq!(dates[PENALTY_HISTORY_LENGTH - 1], 31 * DAY);
+        assert!(dates.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_history_matches_the_penalty_added_to_the_loan() {
+        let mut history = PenaltyHistory::zeroed();
+
+        // Three days in one run, the loan had 250 of collateral left to charge
+        history.record_charges(&[day(DAY, 100), day(2 * DAY, 100), day(3 * DAY, 100)], 250);
+
+        let charges: Vec<u64> = history.days().map(|entry| entry.charge).collect();
+        assert_eq!(charges, vec![100, 100, 50]);
+        assert_eq!(history.total_charged(), 250);
+
+        // Nothing left to charge still records the days it would have been
+        history.record_charges(&[day(4 * DAY, 100)], 0);
+        assert_eq!(history.days().count(), 4);
+        assert_eq!(history.total_charged(), 250);
+    }
+
+    #[test]
+    fn test_empty_history_has_no_days() {
+        let history = PenaltyHistory::zeroed();
+
+        assert_eq!(history.days().count(), 0);
+        assert_eq!(history.total_charged(), 0);
+    }
+
+    #[test]
+    fn test_multiplier_is_kept_at_the_maximum() {
+        assert_eq!(to_recorded_multiplier(40), 40);
+        assert_eq!(to_recorded_multiplier(u64::from(u16::MAX) + 1), u16::MAX);
+    }
+}
@@ -0,0 +1,72 @@
+use bytemuck::{Pod, Zeroable};
+
+pub const PENALTY_JOURNAL_LENGTH: usize = 7;
+
+/// Penalty a loan was charged for one day and the prices it was charged at
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct PenaltyJournalEntry {
+    pub date: i64,
+    pub penalty: u64,
+    pub sol_price: u64,
+    pub lucra_price: u64,
+}
+
+/// Ring buffer of the last days a loan was charged penalty for. It bounds how far back a
+/// price correction can be recomputed for a loan.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct PenaltyJournal {
+    pub head: u64,
+    pub entries: [PenaltyJournalEntry; PENALTY_JOURNAL_LENGTH],
+}
+
+impl PenaltyJournal {
+    /// Writes over the oldest entry once the journal is full
+    pub fn record(&mut self, entry: PenaltyJournalEntry) {
+        let index = self.head as usize % PENALTY_JOURNAL_LENGTH;
+        self.entries[index] = entry;
+        self.head = self.head.wrapping_add(1);
+    }
+
+    pub fn entry_for_mut(&mut self, date: i64) -> Option<&mut PenaltyJournalEntry> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.date != 0 && entry.date == date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(date: i64) -> PenaltyJournalEntry {
+        PenaltyJournalEntry {
+            date,
+            penalty: 100,
+            ..PenaltyJournalEntry::default()
+        }
+    }
+
+    #[test]
+    fn test_oldest_day_falls_out_of_the_window() {
+        let mut journal = PenaltyJournal::default();
+        for date in 1..=PENALTY_JOURNAL_LENGTH as i64 {
+            journal.record(entry(date));
+        }
+        assert!(journal.entry_for_mut(1).is_some());
+
+        journal.record(entry(PENALTY_JOURNAL_LENGTH as i64 + 1));
+
+        assert!(journal.entry_for_mut(1).is_none());
+        assert!(journal.entry_for_mut(2).is_some());
+        assert!(journal.entry_for_mut(PENALTY_JOURNAL_LENGTH as i64 + 1).is_some());
+    }
+
+    #[test]
+    fn test_unwritten_slots_are_never_found() {
+        let mut journal = PenaltyJournal::default();
+
+        assert!(journal.entry_for_mut(0).is_none());
+    }
+}
@@ -0,0 +1,93 @@
+use std::cell::RefMut;
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    state::{
+        DataType,
+        HistoricPrice,
+        MetaData,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::PriceCorrection);
+
+/// Audit record of a price history day the DAO corrected. The original prices are the ones the day was
+/// first recorded with, a later correction of the same day only moves the corrected prices.
+#[derive(Copy, Clone, Default, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct PriceCorrection {
+    pub meta_data: MetaData,
+    pub system_state: Pubkey,
+    pub date: i64,
+    pub original_sol_price: u64,
+    pub original_lucra_price: u64,
+    pub corrected_sol_price: u64,
+    pub corrected_lucra_price: u64,
+    pub corrected_at: i64,
+}
+
+impl PriceCorrection {
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let correction = Self::load_mut(account)?;
+        check!(correction.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(correction.meta_data.data_type == DataType::PriceCorrection as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(correction)
+    }
+
+    pub fn init(&mut self, system_state: &Pubkey, history: &HistoricPrice) {
+        self.meta_data = MetaData::new(DataType::PriceCorrection, 0, true);
+        self.system_state = *system_state;
+        self.date = history.date;
+        self.original_sol_price = history.sol_price;
+        self.original_lucra_price = history.lucra_price;
+    }
+
+    /// Amends the day in the price history and records what it was changed to
+    pub fn correct(&mut self, history: &mut HistoricPrice, sol_price: u64, lucra_price: u64, timestamp: i64) {
+        self.corrected_sol_price = sol_price;
+        self.corrected_lucra_price = lucra_price;
+        self.corrected_at = timestamp;
+
+        history.sol_price = sol_price;
+        history.lucra_price = lucra_price;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_correction_keeps_the_original() {
+        let mut history = HistoricPrice {
+            sol_price: 20_000_000,
+            lucra_price: 1_000_000,
+            date: 86_400,
+            ..HistoricPrice::default()
+        };
+        let mut correction = PriceCorrection::default();
+        correction.init(&Pubkey::new_unique(), &history);
+
+        correction.correct(&mut history, 25_000_000, 1_100_000, 1);
+        correction.correct(&mut history, 24_000_000, 1_050_000, 2);
+
+        assert_eq!(correction.original_sol_price, 20_000_000);
+        assert_eq!(correction.original_lucra_price, 1_000_000);
+        assert_eq!((correction.corrected_sol_price, correction.corrected_lucra_price), (24_000_000, 1_050_000));
+        assert_eq!((history.sol_price, history.lucra_price), (24_000_000, 1_050_000));
+        assert_eq!(correction.corrected_at, 2);
+    }
+}
@@ -0,0 +1,346 @@
+use std::cell::{Ref, RefMut};
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    state::{
+        DataType,
+        HistoricPrice,
+        MetaData,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::PriceHistoryPage);
+
+// The price history only keeps the last 30 days it recorded. Once it's paged every day is also archived, in
+// order, to pages of 30 days that are never written over. The header tracks the pages and which one days
+// are appended to. Pages are created ahead of time by CreatePriceHistoryPage, the crank rotates onto the
+// next one when the active page fills up.
+
+pub const PRICE_HISTORY_PAGE_LENGTH: usize = 30;
+
+/// Meta data version of a price history whose days are archived to pages, and of its header and pages
+pub const PAGED_PRICE_HISTORY_VERSION: u8 = 1;
+
+#[derive(Copy, Clone, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct PriceHistoryHeader {
+    pub meta_data: MetaData,
+    pub price_history: Pubkey,
+    /// Pages created so far, the next one is created with this index
+    pub page_count: u64,
+    /// Page new days are appended to
+    pub active_page: u64,
+    /// Oldest and newest day archived, both 0 until the first one is
+    pub first_date: i64,
+    pub last_date: i64,
+}
+
+#[derive(Copy, Clone, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct PriceHistoryPage {
+    pub meta_data: MetaData,
+    pub page_index: u64,
+    /// Days written to the front of `prices`, oldest first
+    pub len: u64,
+    pub prices: [HistoricPrice; PRICE_HISTORY_PAGE_LENGTH],
+}
+
+impl PriceHistoryHeader {
+    pub fn load_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<Ref<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let header = Self::load(account)?;
+        check!(header.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(header.meta_data.data_type == DataType::PriceHistoryHeader as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(header)
+    }
+
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let header = Self::load_mut(account)?;
+        check!(header.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(header.meta_data.data_type == DataType::PriceHistoryHeader as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(header)
+    }
+
+    pub fn init(&mut self, price_history: &Pubkey) {
+        self.meta_data = MetaData::new(DataType::PriceHistoryHeader, PAGED_PRICE_HISTORY_VERSION, true);
+        self.price_history = *price_history;
+        self.page_count = 0;
+        self.active_page = 0;
+        self.first_date = 0;
+        self.last_date = 0;
+    }
+
+    /// Counts a newly created page, returns its index
+    pub fn record_page(&mut self) -> LucraResult<u64> {
+        let page_index = self.page_count;
+        self.page_count = page_index.checked_add(1).ok_or(math_err!())?;
+
+        Ok(page_index)
+    }
+
+    /// Writes `price` to the archive. The newest day is rewritten while the crank is still sampling it, or
+    /// when it zeroes it out, a later day is appended after it. Days before the newest are left alone.
+    pub fn archive(&mut self, pages: &mut [&mut PriceHistoryPage], price: &HistoricPrice) -> LucraResult {
+        if price.date == 0 || price.date < self.last_date {
+            return Ok(());
+        }
+
+        let active_page = self.active_page;
+        let is_full = find_page(pages, active_page)
+            .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?
+            .is_full();
+
+        if self.last_date != 0 && price.date == self.last_date {
+            let page = find_page_mut(pages, active_page)
+                .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?;
+            let last = page.last_price_mut()
+                .ok_or_else(|| invariant_err!(LucraErrorCode::InvalidState))?;
+            *last = *price;
+            return Ok(());
+        }
+
+        if is_full {
+            // The next page has to exist before the active one fills up
+            check!(active_page + 1 < self.page_count, LucraErrorCode::PriceHistoryPageFull)?;
+            find_page_mut(pages, active_page + 1)
+                .ok_or_else(|| throw_err!(LucraErrorCode::PriceHistoryPageFull))?
+                .append(price)?;
+            self.active_page = active_page + 1;
+        } else {
+            find_page_mut(pages, active_page)
+                .ok_or_else(|| throw_err!(LucraErrorCode::InvalidAccountInput))?
+                .append(price)?;
+        }
+
+        if self.first_date == 0 {
+            self.first_date = price.date;
+        }
+        self.last_date = price.date;
+
+        Ok(())
+    }
+}
+
+fn find_page<'a>(pages: &'a [&mut PriceHistoryPage], page_index: u64) -> Option<&'a PriceHistoryPage> {
+    pages.iter().find(|page| page.page_index == page_index).map(|page| &**page)
+}
+
+fn find_page_mut<'a>(pages: &'a mut [&mut PriceHistoryPage], page_index: u64) -> Option<&'a mut PriceHistoryPage> {
+    pages.iter_mut().find(|page| page.page_index == page_index).map(|page| &mut **page)
+}
+
+impl PriceHistoryPage {
+    pub fn load_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<Ref<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let page = Self::load(account)?;
+        check!(page.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(page.meta_data.data_type == DataType::PriceHistoryPage as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(page)
+    }
+
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let page = Self::load_mut(account)?;
+        check!(page.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(page.meta_data.data_type == DataType::PriceHistoryPage as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(page)
+    }
+
+    pub fn init(&mut self, page_index: u64) {
+        self.meta_data = MetaData::new(DataType::PriceHistoryPage, PAGED_PRICE_HISTORY_VERSION, true);
+        self.page_index = page_index;
+        self.len = 0;
+        self.prices = [HistoricPrice::default(); PRICE_HISTORY_PAGE_LENGTH];
+    }
+
+    pub fn prices(&self) -> &[HistoricPrice] {
+        &self.prices[..self.len as usize]
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len as usize >= PRICE_HISTORY_PAGE_LENGTH
+    }
+
+    pub fn first_date(&self) -> Option<i64> {
+        self.prices().first().map(|price| price.date)
+    }
+
+    pub fn last_date(&self) -> Option<i64> {
+        self.prices().last().map(|price| price.date)
+    }
+
+    fn last_price_mut(&mut self) -> Option<&mut HistoricPrice> {
+        let len = self.len as usize;
+        self.prices[..len].last_mut()
+    }
+
+    /// Days only go on after the page's last one
+    pub fn append(&mut self, price: &HistoricPrice) -> LucraResult {
+        check!(!self.is_full(), LucraErrorCode::PriceHistoryPageFull)?;
+        if let Some(last_date) = self.last_date() {
+            check!(price.date > last_date, LucraErrorCode::InvalidAccountInput)?;
+        }
+
+        self.prices[self.len as usize] = *price;
+        self.len += 1;
+
+        Ok(())
+    }
+
+    pub fn price_for_date(&self, date: i64) -> Option<&HistoricPrice> {
+        let prices = self.prices();
+        prices
+            .binary_search_by_key(&date, |price| price.date)
+            .ok()
+            .map(|index| &prices[index])
+    }
+
+    pub fn price_for_date_mut(&mut self, date: i64) -> Option<&mut HistoricPrice> {
+        let index = self.prices().binary_search_by_key(&date, |price| price.date).ok()?;
+        Some(&mut self.prices[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+
+    fn day(date: i64) -> HistoricPrice {
+        HistoricPrice {
+            date: date * DAY,
+            sol_price: 20_000_000,
+            lucra_price: 1_000_000,
+            ..HistoricPrice::default()
+        }
+    }
+
+    fn new_pages(count: u64) -> (PriceHistoryHeader, Vec<PriceHistoryPage>) {
+        let mut header = PriceHistoryHeader::zeroed();
+        header.init(&Pubkey::new_unique());
+        let pages = (0..count)
+            .map(|_| {
+                let mut page = PriceHistoryPage::zeroed();
+                page.init(header.record_page().unwrap());
+                page
+            })
+            .collect();
+
+        (header, pages)
+    }
+
+    fn archive(header: &mut PriceHistoryHeader, pages: &mut [PriceHistoryPage], price: &HistoricPrice) -> LucraResult {
+        let mut pages: Vec<&mut PriceHistoryPage> = pages.iter_mut().collect();
+        header.archive(&mut pages, price)
+    }
+
+    #[test]
+    fn test_new_header_and_pages_are_on_the_paged_version() {
+        let (header, pages) = new_pages(2);
+
+        assert_eq!(header.meta_data.data_type, DataType::PriceHistoryHeader as u8);
+        assert_eq!(header.meta_data.version, PAGED_PRICE_HISTORY_VERSION);
+        assert_eq!(header.page_count, 2);
+        assert_eq!((header.first_date, header.last_date), (0, 0));
+
+        assert_eq!(pages[1].meta_data.data_type, DataType::PriceHistoryPage as u8);
+        assert_eq!(pages[1].meta_data.version, PAGED_PRICE_HISTORY_VERSION);
+        assert_eq!(pages[1].page_index, 1);
+        assert!(pages[1].prices().is_empty());
+    }
+
+    #[test]
+    fn test_days_rotate_onto_the_next_page() {
+        let (mut header, mut pages) = new_pages(2);
+        let length = PRICE_HISTORY_PAGE_LENGTH as i64;
+
+        for date in 1..=length + 2 {
+            archive(&mut header, &mut pages, &day(date)).unwrap();
+        }
+
+        assert_eq!(header.active_page, 1);
+        assert_eq!((header.first_date, header.last_date), (DAY, (length + 2) * DAY));
+        assert!(pages[0].is_full());
+        assert_eq!(pages[0].first_date(), Some(DAY));
+        assert_eq!(pages[0].last_date(), Some(length * DAY));
+        assert_eq!(pages[1].prices().len(), 2);
+        assert_eq!(pages[1].first_date(), Some((length + 1) * DAY));
+    }
+
+    #[test]
+    fn test_full_page_needs_the_next_one_created() {
+        let (mut header, mut pages) = new_pages(1);
+        let length = PRICE_HISTORY_PAGE_LENGTH as i64;
+        for date in 1..=length {
+            archive(&mut header, &mut pages, &day(date)).unwrap();
+        }
+
+        assert!(archive(&mut header, &mut pages, &day(length + 1)).is_err());
+        assert_eq!(header.last_date, length * DAY);
+
+        // Created but not passed in
+        let (mut header, mut pages) = new_pages(2);
+        for date in 1..=length {
+            archive(&mut header, &mut pages, &day(date)).unwrap();
+        }
+        assert!(archive(&mut header, &mut pages[..1], &day(length + 1)).is_err());
+        assert_eq!(header.active_page, 0);
+    }
+
+    #[test]
+    fn test_newest_day_is_rewritten_and_older_ones_left_alone() {
+        let (mut header, mut pages) = new_pages(1);
+        archive(&mut header, &mut pages, &day(1)).unwrap();
+        archive(&mut header, &mut pages, &day(2)).unwrap();
+
+        // The crank zeroed out the day
+        let zeroed = HistoricPrice { date: 2 * DAY, ..HistoricPrice::default() };
+        archive(&mut header, &mut pages, &zeroed).unwrap();
+        assert_eq!(pages[0].prices().len(), 2);
+        assert_eq!(pages[0].price_for_date(2 * DAY).unwrap().sol_price, 0);
+
+        // Already archived
+        archive(&mut header, &mut pages, &HistoricPrice { sol_price: 1, ..day(1) }).unwrap();
+        assert_eq!(pages[0].price_for_date(DAY).unwrap().sol_price, 20_000_000);
+
+        // Never recorded
+        archive(&mut header, &mut pages, &HistoricPrice::default()).unwrap();
+        assert_eq!(pages[0].prices().len(), 2);
+    }
+
+    #[test]
+    fn test_page_lookup_by_date() {
+        let (mut header, mut pages) = new_pages(1);
+        for date in [1, 2, 5] {
+            archive(&mut header, &mut pages, &day(date)).unwrap();
+        }
+
+        assert_eq!(pages[0].price_for_date(5 * DAY).unwrap().date, 5 * DAY);
+        assert!(pages[0].price_for_date(3 * DAY).is_none());
+        assert!(pages[0].price_for_date(6 * DAY).is_none());
+        // Only the written days are searched
+        assert!(pages[0].price_for_date(0).is_none());
+
+        pages[0].price_for_date_mut(2 * DAY).unwrap().sol_price = 1;
+        assert_eq!(pages[0].prices()[1].sol_price, 1);
+        assert!(pages[0].append(&day(4)).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use std::cell::RefMut;
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    state::{
+        DataType,
+        MetaData,
+        SystemState,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::RevenueLedger);
+
+pub const REVENUE_LEDGER_LENGTH: usize = 32;
+
+/// Revenue collected by the protocol during a single epoch
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct RevenueEntry {
+    pub epoch_start: i64,
+    pub origination_fees: u64,
+    pub arb_profit_msol: u64,
+    pub penalties_captured_sol: u64,
+    pub keeper_fees_paid: u64,
+}
+
+/// Ring buffer of per-epoch revenue. The entry at `head` is the epoch currently being written.
+#[derive(Copy, Clone, Default, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct RevenueLedger {
+    pub meta_data: MetaData,
+    pub system_state: Pubkey,
+    pub head: u64,
+    pub entries: [RevenueEntry; REVENUE_LEDGER_LENGTH],
+}
+
+impl RevenueLedger {
+    pub fn load_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<std::cell::Ref<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let ledger = Self::load(account)?;
+        check!(ledger.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(ledger.meta_data.data_type == DataType::RevenueLedger as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(ledger)
+    }
+
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let ledger = Self::load_mut(account)?;
+        check!(ledger.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(ledger.meta_data.data_type == DataType::RevenueLedger as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(ledger)
+    }
+
+    /// Loads the ledger when it was passed as an optional trailing account. Returns None when the
+    /// account wasn't supplied so that older clients keep working.
+    pub fn load_optional_mut<'a>(
+        account: Option<&'a AccountInfo>,
+        system_state: &SystemState,
+        program_id: &Pubkey,
+    ) -> LucraResult<Option<RefMut<'a, Self>>> {
+        match account {
+            Some(ledger_ai) => {
+                check_eq!(&system_state.revenue_ledger, ledger_ai.key, LucraErrorCode::InvalidAccountInput)?;
+                let ledger = Self::load_mut_checked(ledger_ai, program_id)?;
+                check_eq!(ledger.system_state, system_state.key, LucraErrorCode::InvalidAccountInput)?;
+
+                Ok(Some(ledger))
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn init(&mut self, system_state: &Pubkey, timestamp: i64) {
+        self.meta_data = MetaData::new(DataType::RevenueLedger, 0, true);
+        self.system_state = *system_state;
+        self.head = 0;
+        self.entries = [RevenueEntry::default(); REVENUE_LEDGER_LENGTH];
+        self.entries[0].epoch_start = timestamp;
+    }
+
+    pub fn current_entry(&self) -> &RevenueEntry {
+        &self.entries[self.head as usize % REVENUE_LEDGER_LENGTH]
+    }
+
+    /// Returns the entry for the epoch containing `timestamp`, rolling the ring buffer forward
+    /// when one or more epoch boundaries have passed. Empty epochs in between are not recorded.
+    pub fn entry_for(&mut self, timestamp: i64, epoch: i64) -> &mut RevenueEntry {
+        let head = self.head as usize % REVENUE_LEDGER_LENGTH;
+        let epoch_start = self.entries[head].epoch_start;
+
+        if epoch > 0 && timestamp >= epoch_start.saturating_add(epoch) {
+            let epochs_passed = (timestamp - epoch_start) / epoch;
+            let next = (head + 1) % REVENUE_LEDGER_LENGTH;
+
+            self.head = next as u64;
+            self.entries[next] = RevenueEntry {
+                epoch_start: epoch_start.saturating_add(epochs_passed.saturating_mul(epoch)),
+                ..RevenueEntry::default()
+            };
+        }
+
+        &mut self.entries[self.head as usize]
+    }
+
+    pub fn record_origination_fees(&mut self, timestamp: i64, epoch: i64, lamports: u64) {
+        let entry = self.entry_for(timestamp, epoch);
+        entry.origination_fees = entry.origination_fees.saturating_add(lamports);
+    }
+
+    pub fn record_arb_profit(&mut self, timestamp: i64, epoch: i64, msol: u64) {
+        let entry = self.entry_for(timestamp, epoch);
+        entry.arb_profit_msol = entry.arb_profit_msol.saturating_add(msol);
+    }
+
+    pub fn record_penalty_captured(&mut self, timestamp: i64, epoch: i64, lamports: u64) {
+        let entry = self.entry_for(timestamp, epoch);
+        entry.penalties_captured_sol = entry.penalties_captured_sol.saturating_add(lamports);
+    }
+
+    pub fn record_keeper_fee(&mut self, timestamp: i64, epoch: i64, lamports: u64) {
+        let entry = self.entry_for(timestamp, epoch);
+        entry.keeper_fees_paid = entry.keeper_fees_paid.saturating_add(lamports);
+    }
+}
+
+#[cfg(feature = "client")]
+impl RevenueLedger {
+    /// Decodes a ledger from raw account data
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != std::mem::size_of::<Self>() {
+            return None;
+        }
+
+        Some(*bytemuck::from_bytes::<Self>(data))
+    }
+
+    /// Entries ordered from oldest to newest, skipping slots that were never written
+    pub fn ordered_entries(&self) -> Vec<RevenueEntry> {
+        let head = self.head as usize % REVENUE_LEDGER_LENGTH;
+        (1..=REVENUE_LEDGER_LENGTH)
+            .map(|offset| self.entries[(head + offset) % REVENUE_LEDGER_LENGTH])
+            .filter(|entry| entry.epoch_start != 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPOCH: i64 = 604_800;
+    const START: i64 = 1_650_000_000;
+
+    fn new_ledger() -> RevenueLedger {
+        let mut ledger = RevenueLedger::default();
+        ledger.init(&Pubkey::new_unique(), START);
+        ledger
+    }
+
+    #[test]
+    fn test_scripted_sequence_produces_expected_row() {
+        let mut ledger = new_ledger();
+
+        ledger.record_origination_fees(START + 10, EPOCH, 1_000);
+        ledger.record_penalty_captured(START + 100, EPOCH, 5_000_000);
+        ledger.record_keeper_fee(START + 100, EPOCH, 5_500);
+        ledger.record_arb_profit(START + 200, EPOCH, 42);
+        ledger.record_penalty_captured(START + EPOCH - 1, EPOCH, 1_000_000);
+
+        let expected = RevenueEntry {
+            epoch_start: START,
+            origination_fees: 1_000,
+            arb_profit_msol: 42,
+            penalties_captured_sol: 6_000_000,
+            keeper_fees_paid: 5_500,
+        };
+        assert_eq!(ledger.head, 0);
+        assert_eq!(*ledger.current_entry(), expected);
+    }
+
+    #[test]
+    fn test_rolls_to_new_entry_on_epoch_boundary() {
+        let mut ledger = new_ledger();
+
+        ledger.record_keeper_fee(START, EPOCH, 5_500);
+        ledger.record_keeper_fee(START + EPOCH, EPOCH, 11_000);
+
+        assert_eq!(ledger.head, 1);
+        assert_eq!(ledger.entries[0].keeper_fees_paid, 5_500);
+        assert_eq!(ledger.entries[1].epoch_start, START + EPOCH);
+        assert_eq!(ledger.entries[1].keeper_fees_paid, 11_000);
+    }
+
+    #[test]
+    fn test_skipped_epochs_align_to_boundary() {
+        let mut ledger = new_ledger();
+
+        ledger.record_arb_profit(START + 3 * EPOCH + 500, EPOCH, 7);
+
+        assert_eq!(ledger.head, 1);
+        assert_eq!(ledger.entries[1].epoch_start, START + 3 * EPOCH);
+        assert_eq!(ledger.entries[1].arb_profit_msol, 7);
+    }
+
+    #[test]
+    fn test_ring_buffer_wraps() {
+        let mut ledger = new_ledger();
+
+        for i in 1..=REVENUE_LEDGER_LENGTH as i64 {
+            ledger.record_origination_fees(START + i * EPOCH, EPOCH, i as u64);
+        }
+
+        assert_eq!(ledger.head, 0);
+        assert_eq!(ledger.entries[0].epoch_start, START + REVENUE_LEDGER_LENGTH as i64 * EPOCH);
+        assert_eq!(ledger.entries[0].origination_fees, REVENUE_LEDGER_LENGTH as u64);
+    }
+
+    #[test]
+    fn test_record_saturates() {
+        let mut ledger = new_ledger();
+
+        ledger.record_penalty_captured(START, EPOCH, u64::MAX);
+        ledger.record_penalty_captured(START, EPOCH, 1);
+
+        assert_eq!(ledger.current_entry().penalties_captured_sol, u64::MAX);
+    }
+
+    #[test]
+    fn test_zero_epoch_never_rolls() {
+        let mut ledger = new_ledger();
+
+        ledger.record_keeper_fee(START + 10 * EPOCH, 0, 1);
+
+        assert_eq!(ledger.head, 0);
+        assert_eq!(ledger.current_entry().keeper_fees_paid, 1);
+    }
+}
@@ -0,0 +1,158 @@
+use bytemuck::{Pod, Zeroable};
+
+#[cfg(feature = "client")]
+use crate::state::staking::StakeBalance;
+
+/// Fits the reserved region of a stake balance
+pub const STAKE_CHECKPOINTS_LENGTH: usize = 10;
+
+/// What the stake vault held once the slot's stake changes were done, and what it held before them
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct StakeCheckpoint {
+    pub slot: u64,
+    pub previous: u64,
+    pub amount: u64,
+}
+
+/// Ring buffer of the last changes to a stake balance's staked lucra. Governance reads it to weigh votes by
+/// the stake held when a proposal was created, so stake bought after seeing the proposal doesn't count.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct StakeCheckpoints {
+    /// Number of checkpoints ever written
+    pub head: u64,
+    pub entries: [StakeCheckpoint; STAKE_CHECKPOINTS_LENGTH],
+}
+
+impl StakeCheckpoints {
+    /// Writes over the oldest checkpoint once the ring is full. A second change in the same slot replaces
+    /// that slot's amount and keeps what it held before the first.
+    pub fn record(&mut self, slot: u64, previous: u64, amount: u64) {
+        if let Some(latest) = self.latest_mut() {
+            if latest.slot == slot {
+                latest.amount = amount;
+                return;
+            }
+        }
+
+        let index = self.head as usize % STAKE_CHECKPOINTS_LENGTH;
+        self.entries[index] = StakeCheckpoint { slot, previous, amount };
+        self.head = self.head.wrapping_add(1);
+    }
+
+    fn latest_mut(&mut self) -> Option<&mut StakeCheckpoint> {
+        if self.head == 0 {
+            return None;
+        }
+        let index = (self.head - 1) as usize % STAKE_CHECKPOINTS_LENGTH;
+
+        Some(&mut self.entries[index])
+    }
+}
+
+#[cfg(feature = "client")]
+impl StakeCheckpoints {
+    /// Checkpoints ordered from oldest to newest, slots that were never written are left out
+    pub fn ordered_entries(&self) -> Vec<StakeCheckpoint> {
+        let written = (self.head as usize).min(STAKE_CHECKPOINTS_LENGTH);
+        let start = self.head as usize - written;
+        (start..self.head as usize)
+            .map(|position| self.entries[position % STAKE_CHECKPOINTS_LENGTH])
+            .collect()
+    }
+
+    /// Amount of the last checkpoint at or before the slot. A slot before the oldest checkpoint the ring still
+    /// has reads as what was held before that checkpoint's changes.
+    pub fn amount_at_slot(&self, slot: u64) -> Option<u64> {
+        let entries = self.ordered_entries();
+        let position = entries.partition_point(|checkpoint| checkpoint.slot <= slot);
+
+        match position {
+            0 => entries.first().map(|checkpoint| checkpoint.previous),
+            _ => Some(entries[position - 1].amount),
+        }
+    }
+}
+
+/// Lucra the stake balance had staked at the end of the slot, for governance snapshots. None when its stake
+/// hasn't changed since checkpoints were added, its current stake vault balance is still the answer then.
+#[cfg(feature = "client")]
+pub fn staked_at_slot(stake_balance: &StakeBalance, slot: u64) -> Option<u64> {
+    stake_balance.stake_checkpoints.amount_at_slot(slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::account_layout::RESERVED_LAYOUT_BYTES;
+
+    #[test]
+    fn test_ring_fits_the_reserved_region() {
+        assert!(std::mem::size_of::<StakeCheckpoints>() <= RESERVED_LAYOUT_BYTES);
+    }
+
+    #[test]
+    fn test_same_slot_changes_share_a_checkpoint() {
+        let mut checkpoints = StakeCheckpoints::default();
+        checkpoints.record(100, 0, 5);
+        checkpoints.record(100, 5, 8);
+        checkpoints.record(101, 8, 3);
+
+        assert_eq!(checkpoints.head, 2);
+        assert_eq!(checkpoints.entries[0], StakeCheckpoint { slot: 100, previous: 0, amount: 8 });
+        assert_eq!(checkpoints.entries[1], StakeCheckpoint { slot: 101, previous: 8, amount: 3 });
+    }
+
+    #[test]
+    fn test_oldest_checkpoint_is_overwritten() {
+        let mut checkpoints = StakeCheckpoints::default();
+        let last = STAKE_CHECKPOINTS_LENGTH as u64 + 1;
+        for slot in 1..=last {
+            checkpoints.record(slot, (slot - 1) * 10, slot * 10);
+        }
+
+        assert_eq!(checkpoints.entries[0], StakeCheckpoint { slot: last, previous: (last - 1) * 10, amount: last * 10 });
+        assert_eq!(checkpoints.entries[1], StakeCheckpoint { slot: 2, previous: 10, amount: 20 });
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_lookup_before_between_and_after_checkpoints() {
+        let mut checkpoints = StakeCheckpoints::default();
+        assert_eq!(checkpoints.amount_at_slot(100), None);
+
+        checkpoints.record(100, 0, 5);
+        checkpoints.record(200, 5, 0);
+        checkpoints.record(300, 0, 9);
+
+        // Before the first checkpoint nothing was staked yet
+        assert_eq!(checkpoints.amount_at_slot(50), Some(0));
+        assert_eq!(checkpoints.amount_at_slot(99), Some(0));
+        assert_eq!(checkpoints.amount_at_slot(100), Some(5));
+        assert_eq!(checkpoints.amount_at_slot(199), Some(5));
+        assert_eq!(checkpoints.amount_at_slot(200), Some(0));
+        assert_eq!(checkpoints.amount_at_slot(299), Some(0));
+        assert_eq!(checkpoints.amount_at_slot(300), Some(9));
+        assert_eq!(checkpoints.amount_at_slot(u64::MAX), Some(9));
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_lookup_after_the_ring_wraps() {
+        let mut checkpoints = StakeCheckpoints::default();
+        let last = STAKE_CHECKPOINTS_LENGTH as u64 + 3;
+        for slot in 1..=last {
+            checkpoints.record(slot * 10, slot - 1, slot);
+        }
+        let ordered = checkpoints.ordered_entries();
+        assert_eq!(ordered.len(), STAKE_CHECKPOINTS_LENGTH);
+        assert_eq!(ordered[0], StakeCheckpoint { slot: 40, previous: 3, amount: 4 });
+
+        // Slots before the oldest checkpoint left read as what it started from
+        assert_eq!(checkpoints.amount_at_slot(35), Some(3));
+        assert_eq!(checkpoints.amount_at_slot(40), Some(4));
+        assert_eq!(checkpoints.amount_at_slot(95), Some(9));
+        assert_eq!(checkpoints.amount_at_slot(last * 10), Some(last));
+    }
+}
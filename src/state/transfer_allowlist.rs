@@ -0,0 +1,222 @@
+use std::cell::{Ref, RefMut};
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    helpers::constants::{MAX_TRANSFER_DESTINATIONS, TRANSFER_DESTINATION_DELAY},
+    state::{
+        DataType,
+        MetaData,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::TransferAllowlist);
+
+// Accounts TransferFunds is allowed to send to. A destination the DAO adds only becomes usable
+// TRANSFER_DESTINATION_DELAY after it was added, so a single proposal can't add an address and drain a vault
+// to it in one go, and stakers have the delay to react to one they don't like.
+
+#[derive(Copy, Clone, Default, Pod, Zeroable)]
+#[repr(C)]
+pub struct TransferDestination {
+    pub pubkey: Pubkey,
+    pub activates_at: i64,
+}
+
+#[derive(Copy, Clone, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct TransferAllowlist {
+    pub meta_data: MetaData,
+    pub system_state: Pubkey,
+    /// Destinations written to the front of `destinations`
+    pub destination_count: u64,
+    pub destinations: [TransferDestination; MAX_TRANSFER_DESTINATIONS],
+}
+
+impl TransferAllowlist {
+    pub fn load_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<Ref<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let allowlist = Self::load(account)?;
+        check!(allowlist.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(allowlist.meta_data.data_type == DataType::TransferAllowlist as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(allowlist)
+    }
+
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let allowlist = Self::load_mut(account)?;
+        check!(allowlist.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(allowlist.meta_data.data_type == DataType::TransferAllowlist as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(allowlist)
+    }
+
+    pub fn init(&mut self, system_state: &Pubkey) {
+        self.meta_data = MetaData::new(DataType::TransferAllowlist, 0, true);
+        self.system_state = *system_state;
+        self.destination_count = 0;
+        self.destinations = [TransferDestination::default(); MAX_TRANSFER_DESTINATIONS];
+    }
+
+    pub fn destinations(&self) -> &[TransferDestination] {
+        &self.destinations[..self.destination_count as usize]
+    }
+
+    /// Adds a destination that activates after the delay and returns when. Adding one again is refused, it
+    /// would either restart its delay or do nothing.
+    pub fn add(&mut self, destination: &Pubkey, timestamp: i64) -> LucraResult<i64> {
+        check!(destination != &Pubkey::default(), LucraErrorCode::InvalidAccountInput)?;
+        check!(
+            self.destinations().iter().all(|entry| &entry.pubkey != destination),
+            LucraErrorCode::AlreadyProcessed
+        )?;
+
+        let count = self.destination_count as usize;
+        check!(count < MAX_TRANSFER_DESTINATIONS, LucraErrorCode::TransferAllowlistFull)?;
+        let activates_at = timestamp
+            .checked_add(TRANSFER_DESTINATION_DELAY)
+            .ok_or(math_err!())?;
+        self.destinations[count] = TransferDestination { pubkey: *destination, activates_at };
+        self.destination_count = self.destination_count.checked_add(1).ok_or(math_err!())?;
+
+        Ok(activates_at)
+    }
+
+    /// The last destination takes the removed one's slot. A destination can be removed during its delay, a
+    /// malicious addition is revoked before it ever activates.
+    pub fn remove(&mut self, destination: &Pubkey) -> LucraResult {
+        let count = self.destination_count as usize;
+        let index = self.destinations()
+            .iter()
+            .position(|entry| &entry.pubkey == destination)
+            .ok_or_else(|| throw_err!(LucraErrorCode::TransferDestinationNotActive))?;
+
+        self.destinations[index] = self.destinations[count - 1];
+        self.destinations[count - 1] = TransferDestination::default();
+        self.destination_count -= 1;
+
+        Ok(())
+    }
+
+    pub fn check_active(&self, destination: &Pubkey, timestamp: i64) -> LucraResult {
+        check!(
+            self.destinations().iter().any(|entry| &entry.pubkey == destination && entry.activates_at <= timestamp),
+            LucraErrorCode::TransferDestinationNotActive
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_650_000_000;
+
+    fn new_allowlist() -> TransferAllowlist {
+        let mut allowlist = TransferAllowlist::zeroed();
+        allowlist.init(&Pubkey::new_unique());
+        allowlist
+    }
+
+    fn assert_not_active(result: LucraResult) {
+        assert!(matches!(
+            result.unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::TransferDestinationNotActive, .. }
+        ));
+    }
+
+    #[test]
+    fn test_destinations_wait_out_the_delay() {
+        let mut allowlist = new_allowlist();
+        assert_eq!(allowlist.meta_data.data_type, DataType::TransferAllowlist as u8);
+
+        let treasury = Pubkey::new_unique();
+        assert_eq!(allowlist.add(&treasury, NOW).unwrap(), NOW + TRANSFER_DESTINATION_DELAY);
+
+        assert_not_active(allowlist.check_active(&treasury, NOW));
+        assert_not_active(allowlist.check_active(&treasury, NOW + TRANSFER_DESTINATION_DELAY - 1));
+        assert!(allowlist.check_active(&treasury, NOW + TRANSFER_DESTINATION_DELAY).is_ok());
+    }
+
+    #[test]
+    fn test_unlisted_destinations_are_refused() {
+        let mut allowlist = new_allowlist();
+        allowlist.add(&Pubkey::new_unique(), NOW).unwrap();
+
+        assert_not_active(allowlist.check_active(&Pubkey::new_unique(), NOW + TRANSFER_DESTINATION_DELAY));
+        assert_not_active(new_allowlist().check_active(&Pubkey::default(), NOW));
+    }
+
+    #[test]
+    fn test_a_destination_is_added_once() {
+        let mut allowlist = new_allowlist();
+        let treasury = Pubkey::new_unique();
+        allowlist.add(&treasury, NOW).unwrap();
+
+        // Adding it again later doesn't restart its delay
+        assert!(allowlist.add(&treasury, NOW + 1).is_err());
+        assert!(allowlist.check_active(&treasury, NOW + TRANSFER_DESTINATION_DELAY).is_ok());
+        assert!(allowlist.add(&Pubkey::default(), NOW).is_err());
+        assert_eq!(allowlist.destinations().len(), 1);
+    }
+
+    #[test]
+    fn test_allowlist_holds_max_transfer_destinations() {
+        let mut allowlist = new_allowlist();
+        for _ in 0..MAX_TRANSFER_DESTINATIONS {
+            allowlist.add(&Pubkey::new_unique(), NOW).unwrap();
+        }
+
+        assert!(matches!(
+            allowlist.add(&Pubkey::new_unique(), NOW).unwrap_err(),
+            LucraError::LucraErrorCode { lucra_error_code: LucraErrorCode::TransferAllowlistFull, .. }
+        ));
+    }
+
+    #[test]
+    fn test_a_pending_destination_can_be_removed() {
+        let mut allowlist = new_allowlist();
+        let attacker = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        allowlist.add(&attacker, NOW).unwrap();
+        allowlist.add(&treasury, NOW).unwrap();
+
+        allowlist.remove(&attacker).unwrap();
+        assert_not_active(allowlist.check_active(&attacker, NOW + TRANSFER_DESTINATION_DELAY));
+        assert!(allowlist.check_active(&treasury, NOW + TRANSFER_DESTINATION_DELAY).is_ok());
+        assert_eq!(allowlist.destinations().len(), 1);
+
+        // Only listed destinations can be removed
+        assert_not_active(allowlist.remove(&attacker));
+        assert_not_active(new_allowlist().remove(&Pubkey::default()));
+    }
+
+    #[test]
+    fn test_removing_frees_a_slot() {
+        let mut allowlist = new_allowlist();
+        let destinations: Vec<Pubkey> = (0..MAX_TRANSFER_DESTINATIONS).map(|_| Pubkey::new_unique()).collect();
+        for destination in &destinations {
+            allowlist.add(destination, NOW).unwrap();
+        }
+
+        allowlist.remove(&destinations[0]).unwrap();
+        allowlist.add(&Pubkey::new_unique(), NOW).unwrap();
+        assert_eq!(allowlist.destinations().len(), MAX_TRANSFER_DESTINATIONS);
+
+        // Adding a removed destination back starts its delay over
+        allowlist.remove(&destinations[1]).unwrap();
+        assert_eq!(allowlist.add(&destinations[1], NOW + 1).unwrap(), NOW + 1 + TRANSFER_DESTINATION_DELAY);
+    }
+}
@@ -0,0 +1,149 @@
+use std::cell::{Ref, RefMut};
+
+use bytemuck::{Pod, Zeroable};
+use legends_loadable_trait::Loadable;
+use solana_program::{
+    account_info::AccountInfo,
+    pubkey::Pubkey,
+};
+use crate::{
+    error::{
+        check_assert,
+        LucraError,
+        LucraErrorCode,
+        LucraResult,
+        SourceFileId,
+    },
+    state::{
+        DataType,
+        MetaData,
+    },
+};
+
+declare_check_assert_macros!(SourceFileId::TvlSnapshot);
+
+/// What each protocol holding was worth when the snapshot was taken, in micro dollars. This is the
+/// official definition of Lucra's TVL: the holdings below at oracle prices, msol at the marinade
+/// redemption rate, and nothing else. Mata and lucra only count while they sit in an arb holding vault.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct TvlComponents {
+    /// Loan collateral, the msol in the shared vault
+    pub msol_vault: u64,
+    /// msol the arbitrage has earned and kept
+    pub arb_coffer: u64,
+    /// msol waiting to be claimed by stakers
+    pub rewards_vault: u64,
+    /// wsol in the arb holding vault that CleanUpArb hasn't staked yet
+    pub wsol_holding_vault: u64,
+    /// Mata in the arb holding vault, at the mata market price rather than the peg
+    pub mata_holding_vault: u64,
+    /// Lucra in the arb holding vault
+    pub lucra_holding_vault: u64,
+}
+
+impl TvlComponents {
+    pub fn total(&self) -> LucraResult<u64> {
+        [
+            self.msol_vault,
+            self.arb_coffer,
+            self.rewards_vault,
+            self.wsol_holding_vault,
+            self.mata_holding_vault,
+            self.lucra_holding_vault,
+        ]
+        .iter()
+        .try_fold(0_u64, |total, value| total.checked_add(*value).ok_or(math_err!()))
+    }
+}
+
+/// Latest TVL written by the ComputeTvl crank. Prices are the oracle prices the snapshot was valued at,
+/// as integers with 6 decimals like the price history keeps them.
+#[derive(Copy, Clone, Default, Pod, Zeroable, Loadable)]
+#[repr(C)]
+pub struct TvlSnapshot {
+    pub meta_data: MetaData,
+    pub system_state: Pubkey,
+    pub timestamp: i64,
+    pub sol_price: u64,
+    pub mata_price: u64,
+    pub lucra_price: u64,
+    pub components: TvlComponents,
+    /// Sum of the components, in micro dollars
+    pub total: u64,
+}
+
+impl TvlSnapshot {
+    pub fn load_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<Ref<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let snapshot = Self::load(account)?;
+        check!(snapshot.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(snapshot.meta_data.data_type == DataType::TvlSnapshot as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(snapshot)
+    }
+
+    pub fn load_mut_checked<'a>(account: &'a AccountInfo, program_id: &Pubkey) -> LucraResult<RefMut<'a, Self>> {
+        check_eq!(account.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        let snapshot = Self::load_mut(account)?;
+        check!(snapshot.meta_data.is_initialized, LucraErrorCode::InvalidAccountInput)?;
+        check!(snapshot.meta_data.data_type == DataType::TvlSnapshot as u8, LucraErrorCode::InvalidAccountInput)?;
+
+        Ok(snapshot)
+    }
+
+    pub fn init(&mut self, system_state: &Pubkey) {
+        self.meta_data = MetaData::new(DataType::TvlSnapshot, 0, true);
+        self.system_state = *system_state;
+    }
+
+    pub fn record(
+        &mut self,
+        components: TvlComponents,
+        sol_price: u64,
+        mata_price: u64,
+        lucra_price: u64,
+        timestamp: i64,
+    ) -> LucraResult {
+        self.total = components.total()?;
+        self.components = components;
+        self.sol_price = sol_price;
+        self.mata_price = mata_price;
+        self.lucra_price = lucra_price;
+        self.timestamp = timestamp;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_overwrites_the_last_snapshot() {
+        let mut snapshot = TvlSnapshot::default();
+        snapshot.init(&Pubkey::new_unique());
+
+        let first = TvlComponents { msol_vault: 5, rewards_vault: 7, ..TvlComponents::default() };
+        snapshot.record(first, 40_000_000, 1_000_000, 250_000, 1).unwrap();
+        assert_eq!(snapshot.total, 12);
+
+        let second = TvlComponents { arb_coffer: 3, lucra_holding_vault: 4, ..TvlComponents::default() };
+        snapshot.record(second, 41_000_000, 990_000, 260_000, 2).unwrap();
+        assert_eq!(snapshot.components, second);
+        assert_eq!(snapshot.total, 7);
+        assert_eq!((snapshot.sol_price, snapshot.mata_price, snapshot.lucra_price), (41_000_000, 990_000, 260_000));
+        assert_eq!(snapshot.timestamp, 2);
+    }
+
+    #[test]
+    fn test_total_overflow_leaves_the_snapshot_alone() {
+        let mut snapshot = TvlSnapshot::default();
+        let overflowing = TvlComponents { msol_vault: u64::MAX, arb_coffer: 1, ..TvlComponents::default() };
+
+        assert!(snapshot.record(overflowing, 1, 1, 1, 1).is_err());
+        assert_eq!(snapshot.components, TvlComponents::default());
+        assert_eq!(snapshot.timestamp, 0);
+    }
+}
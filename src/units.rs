@@ -0,0 +1,205 @@
+use bytemuck::{Pod, Zeroable};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::constants::{LAMPORTS_PER_LUCRA, LAMPORTS_PER_MATA};
+
+// Config values used to be bare integers and the unit was only known from the field name. Each unit
+// gets its own newtype so mixing them up is a compile error. They're repr(transparent) and serde
+// transparent, so accounts and instruction data keep the exact bytes of the integer they wrap.
+
+macro_rules! unit {
+    ($(#[$doc:meta])* $name:ident($inner:ty)) => {
+        $(#[$doc])*
+        #[repr(transparent)]
+        #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Pod, Zeroable, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(pub $inner);
+
+        impl $name {
+            pub const ZERO: Self = Self(0);
+            pub const MAX: Self = Self(<$inner>::MAX);
+
+            pub fn get(self) -> $inner {
+                self.0
+            }
+
+            pub fn checked_add(self, other: Self) -> Option<Self> {
+                self.0.checked_add(other.0).map(Self)
+            }
+
+            pub fn checked_sub(self, other: Self) -> Option<Self> {
+                self.0.checked_sub(other.0).map(Self)
+            }
+        }
+
+        impl From<$name> for Decimal {
+            fn from(value: $name) -> Decimal {
+                Decimal::from(value.0)
+            }
+        }
+    };
+}
+
+unit!(
+    /// Basis points, 10_000 = 100%
+    Bps(u32)
+);
+
+unit!(
+    /// Whole percent, 100 = 100%
+    Percent(u8)
+);
+
+unit!(
+    /// Whole percent that can go past 255%, the collateral requirement is 150 for a 150% requirement
+    CollateralRatio(u32)
+);
+
+unit!(
+    /// Native sol amount
+    Lamports(u64)
+);
+
+unit!(
+    /// Fee paid in lamports that is stored in 4 bytes
+    RewardFee(u32)
+);
+
+unit!(
+    /// Native mata amount, 6 decimals
+    Mata(u64)
+);
+
+unit!(
+    /// Native lucra amount, 9 decimals
+    Lucra(u64)
+);
+
+impl Bps {
+    pub const ONE_HUNDRED_PERCENT: Self = Self(10_000);
+
+    /// 100 bps = 0.01
+    pub fn to_decimal(self) -> Decimal {
+        Decimal::new(self.0.into(), 4)
+    }
+}
+
+impl Percent {
+    /// 5% = 0.05
+    pub fn to_decimal(self) -> Decimal {
+        Decimal::new(self.0.into(), 2)
+    }
+}
+
+impl CollateralRatio {
+    /// 150% = 1.5
+    pub fn to_decimal(self) -> Decimal {
+        Decimal::new(self.0.into(), 2)
+    }
+}
+
+impl RewardFee {
+    pub fn to_lamports(self) -> Lamports {
+        Lamports(self.0.into())
+    }
+}
+
+impl Mata {
+    /// Whole mata, 1_000_000 = 1 mata
+    pub fn to_decimal(self) -> Decimal {
+        Decimal::from(self.0) / LAMPORTS_PER_MATA
+    }
+}
+
+impl Lucra {
+    /// Whole lucra, 1_000_000_000 = 1 lucra
+    pub fn to_decimal(self) -> Decimal {
+        Decimal::from(self.0) / LAMPORTS_PER_LUCRA
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{align_of, size_of};
+    use rust_decimal_macros::dec;
+
+    macro_rules! assert_same_layout {
+        ($name:ident, $inner:ty, $value:expr) => {
+            assert_eq!(size_of::<$name>(), size_of::<$inner>());
+            assert_eq!(align_of::<$name>(), align_of::<$inner>());
+
+            let raw: $inner = $value;
+            assert_eq!(bytemuck::bytes_of(&$name(raw)), bytemuck::bytes_of(&raw));
+            assert_eq!(*bytemuck::from_bytes::<$name>(bytemuck::bytes_of(&raw)), $name(raw));
+            assert_eq!(bincode::serialize(&$name(raw)).unwrap(), bincode::serialize(&raw).unwrap());
+        };
+    }
+
+    #[test]
+    fn test_units_keep_the_layout_of_their_integer() {
+        assert_same_layout!(Bps, u32, 0x0102_0304);
+        assert_same_layout!(Percent, u8, 150);
+        assert_same_layout!(CollateralRatio, u32, 0x0102_0304);
+        assert_same_layout!(Lamports, u64, 0x0102_0304_0506_0708);
+        assert_same_layout!(RewardFee, u32, 0x0102_0304);
+        assert_same_layout!(Mata, u64, 0x0102_0304_0506_0708);
+        assert_same_layout!(Lucra, u64, 0x0102_0304_0506_0708);
+    }
+
+    #[test]
+    fn test_units_inside_a_struct_keep_its_layout() {
+        #[repr(C)]
+        #[derive(Copy, Clone, Pod, Zeroable)]
+        struct Typed {
+            min_deposit: Lamports,
+            collateral_requirement: CollateralRatio,
+            reward_fee: RewardFee,
+            lcp: Percent,
+            padding: [u8; 7],
+        }
+
+        #[repr(C)]
+        #[derive(Copy, Clone, Pod, Zeroable)]
+        struct Raw {
+            min_deposit: u64,
+            collateral_requirement: u32,
+            reward_fee: u32,
+            lcp: u8,
+            padding: [u8; 7],
+        }
+
+        let raw = Raw {
+            min_deposit: 1_000_000,
+            collateral_requirement: 150,
+            reward_fee: 5_500,
+            lcp: 100,
+            padding: [0; 7],
+        };
+        let typed: &Typed = bytemuck::from_bytes(bytemuck::bytes_of(&raw));
+
+        assert_eq!(size_of::<Typed>(), size_of::<Raw>());
+        assert_eq!(typed.min_deposit, Lamports(1_000_000));
+        assert_eq!(typed.collateral_requirement, CollateralRatio(150));
+        assert_eq!(typed.reward_fee, RewardFee(5_500));
+        assert_eq!(typed.lcp, Percent(100));
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Bps(100).to_decimal(), dec!(0.01));
+        assert_eq!(Percent(5).to_decimal(), dec!(0.05));
+        assert_eq!(CollateralRatio(150).to_decimal(), dec!(1.5));
+        assert_eq!(RewardFee(5_500).to_lamports(), Lamports(5_500));
+        assert_eq!(Mata(2_500_000).to_decimal(), dec!(2.5));
+        assert_eq!(Lucra(500_000_000).to_decimal(), dec!(0.5));
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(Mata(1).checked_add(Mata(2)), Some(Mata(3)));
+        assert_eq!(Mata(1).checked_sub(Mata(2)), None);
+        assert_eq!(Lamports::MAX.checked_add(Lamports(1)), None);
+    }
+}
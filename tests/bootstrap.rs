@@ -0,0 +1,182 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use std::collections::HashMap;
+
+use common::{marinade_state_with_msol_mint, mint_with_authority, oracle_account, program_account, uninitialized_account};
+use lucra::{
+    bootstrap::{bootstrap_batches, vault_owners, BootstrapAccounts},
+    entrypoint::process_instruction,
+    helpers::constants::{
+        wsol, CREATOR_AUTHORITY, DEFAULT_MSOL_HAIRCUT_BPS, DEFAULT_ORACLE_MAX_DEVIATION_BPS, DEFAULT_PEG_LOWER_BOUND,
+        DEFAULT_PEG_UPPER_BOUND, DEFAULT_PENALTY_BAND_WIDTH, DEFAULT_PENALTY_MULTIPLIER_PER_BAND, LUCRA_SOL_ORACLE,
+        ORACLE_PRICE_MAX_SLOTS, PRICE_HISTORY_ID, SOL_MATA_ORACLE, SOL_USDC_ORACLE, SOL_USDT_ORACLE,
+    },
+    id,
+    state::{PriceHistory, StateParams, SystemState},
+    units::{CollateralRatio, Lamports, Mata, Percent},
+};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    system_program,
+};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+// $20 sol, a pegged mata and 0.05 sol lucra for the first price update
+const SOL_USD_PRICE: u64 = 20_000_000;
+const LUCRA_SOL_PRICE: u64 = 50_000;
+
+// The creator authority's key is compiled into the program and its keypair can't be generated here. Its
+// instructions are sent with it unsigned and the program is shown it as a signer, the way the creator's
+// wallet signs them on a real deployment.
+fn process_as_creator(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let accounts: Vec<AccountInfo> = accounts
+        .iter()
+        .cloned()
+        .map(|mut account| {
+            account.is_signer |= account.key == &CREATOR_AUTHORITY;
+            account
+        })
+        .collect();
+
+    process_instruction(program_id, &accounts, instruction_data)
+}
+
+#[tokio::test]
+async fn test_bootstrap_batches_initialize_the_program() {
+    let marinade_state = Pubkey::new_unique();
+    let msol_mint = Pubkey::new_unique();
+
+    let mut program_test = ProgramTest::new("lucra", id(), processor!(process_as_creator));
+    program_test.add_account(marinade_state, program_account(marinade_finance::id(), marinade_state_with_msol_mint(&msol_mint)));
+    program_test.add_account(msol_mint, mint_with_authority(COption::Some(Pubkey::new_unique()), 0, 9));
+    program_test.add_account(wsol::id(), mint_with_authority(COption::None, 0, 9));
+    // The price history address is compiled in as well, it's added the way its create_account leaves it
+    program_test.add_account(PRICE_HISTORY_ID, uninitialized_account::<PriceHistory>());
+    let mut context = program_test.start_with_context().await;
+    let payer = Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+
+    let new_keypairs: Vec<Keypair> = (0..15).map(|_| Keypair::new()).collect();
+    let guardian = Pubkey::new_unique();
+    let accounts = BootstrapAccounts {
+        payer: payer.pubkey(),
+        marinade_state,
+        msol_mint,
+        guardian,
+        system_state: new_keypairs[0].pubkey(),
+        staking_state: new_keypairs[1].pubkey(),
+        arb_state: new_keypairs[2].pubkey(),
+        price_history: PRICE_HISTORY_ID,
+        mata_mint: new_keypairs[3].pubkey(),
+        lucra_mint: new_keypairs[4].pubkey(),
+        reward_mint: new_keypairs[5].pubkey(),
+        staked_lucra_mint: new_keypairs[6].pubkey(),
+        msol_vault: new_keypairs[7].pubkey(),
+        arb_coffer: new_keypairs[8].pubkey(),
+        rewards_vault: new_keypairs[9].pubkey(),
+        arb_fund: new_keypairs[10].pubkey(),
+        wsol_holding_vault: new_keypairs[11].pubkey(),
+        mata_holding_vault: new_keypairs[12].pubkey(),
+        lucra_holding_vault: new_keypairs[13].pubkey(),
+        reward_account: new_keypairs[14].pubkey(),
+    };
+    let state_params = StateParams {
+        min_deposit: Lamports(0),
+        collateral_requirement: CollateralRatio(150),
+        epoch: 604_800,
+        loans_enabled: true,
+        staking_enabled: true,
+        arbitrage_enabled: true,
+        peg_check_enabled: true,
+        max_amount_of_lucra_to_mint: 0,
+        daily_arb_limit: 0,
+        maximum_outstanding_mata: Mata::MAX,
+        lcp: Percent(0),
+        min_loan_amount_mata: Mata(0),
+        msol_haircut_bps: DEFAULT_MSOL_HAIRCUT_BPS,
+        peg_lower_bound: DEFAULT_PEG_LOWER_BOUND,
+        peg_upper_bound: DEFAULT_PEG_UPPER_BOUND,
+        penalty_band_width: DEFAULT_PENALTY_BAND_WIDTH,
+        penalty_multiplier_per_band: DEFAULT_PENALTY_MULTIPLIER_PER_BAND,
+        max_oracle_staleness_slots: ORACLE_PRICE_MAX_SLOTS,
+        max_oracle_deviation_bps: DEFAULT_ORACLE_MAX_DEVIATION_BPS,
+    };
+
+    let mut keypairs: HashMap<Pubkey, &Keypair> = new_keypairs.iter().map(|keypair| (keypair.pubkey(), keypair)).collect();
+    keypairs.insert(payer.pubkey(), &payer);
+
+    // Oracles are fresh as of the slot the batches land in
+    let slot = context.banks_client.get_sysvar::<Clock>().await.unwrap().slot;
+    for (oracle, price) in [
+        (SOL_USDC_ORACLE, SOL_USD_PRICE),
+        (SOL_USDT_ORACLE, SOL_USD_PRICE),
+        (SOL_MATA_ORACLE, SOL_USD_PRICE),
+        (LUCRA_SOL_ORACLE, LUCRA_SOL_PRICE),
+    ] {
+        context.set_account(&oracle, &oracle_account(price, slot));
+    }
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let recent_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    for mut batch in bootstrap_batches(&accounts, &state_params, &rent) {
+        // Neither fixed key can sign, the price history already exists and the creator is shown as a signer
+        batch.instructions.retain(|instruction| {
+            !(instruction.program_id == system_program::id() && instruction.accounts[1].pubkey == PRICE_HISTORY_ID)
+        });
+        for account in batch.instructions.iter_mut().flat_map(|instruction| instruction.accounts.iter_mut()) {
+            account.is_signer &= account.pubkey != CREATOR_AUTHORITY;
+        }
+        batch.signers.retain(|key| key != &CREATOR_AUTHORITY && key != &PRICE_HISTORY_ID);
+        if batch.instructions.is_empty() {
+            continue;
+        }
+
+        let signers: Vec<&Keypair> = batch.signers.iter().map(|key| keypairs[key]).collect();
+        let transaction = Transaction::new_signed_with_payer(
+            &batch.instructions,
+            Some(&payer.pubkey()),
+            &signers,
+            recent_blockhash,
+        );
+        context.banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let system_state_account = context.banks_client.get_account(accounts.system_state).await.unwrap().unwrap();
+    let system_state = bytemuck::from_bytes::<SystemState>(&system_state_account.data);
+    assert!(system_state.meta_data.is_initialized);
+    assert_eq!(system_state.mata_mint.address, accounts.mata_mint);
+    assert_eq!(system_state.msol_vault.address, accounts.msol_vault);
+    assert_eq!(system_state.dao_authority, CREATOR_AUTHORITY);
+    assert_eq!(system_state.guardian, guardian);
+    assert_eq!(system_state.max_oracle_staleness_slots, ORACLE_PRICE_MAX_SLOTS);
+
+    // The first price update went through, the crank takes it from here
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let price_history_account = context.banks_client.get_account(PRICE_HISTORY_ID).await.unwrap().unwrap();
+    let price_history = bytemuck::from_bytes::<PriceHistory>(&price_history_account.data);
+    assert!(price_history.meta_data.is_initialized);
+    assert_eq!(price_history.update_counter, 1);
+    assert_eq!(price_history.last_update_timestamp, clock.unix_timestamp);
+
+    // Every vault is owned by the PDA the program derives for it
+    for (vault, mint, owner) in vault_owners(&accounts) {
+        let vault_account = context.banks_client.get_account(vault).await.unwrap().unwrap();
+        let vault = spl_token::state::Account::unpack(&vault_account.data).unwrap();
+        assert_eq!(vault.owner, owner);
+        assert_eq!(vault.mint, mint);
+    }
+    let reward_account = context.banks_client.get_account(accounts.reward_account).await.unwrap().unwrap();
+    let reward_account = spl_token::state::Account::unpack(&reward_account.data).unwrap();
+    assert_eq!(reward_account.owner, payer.pubkey());
+    assert_eq!(reward_account.mint, accounts.reward_mint);
+}
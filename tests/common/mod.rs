@@ -0,0 +1,233 @@
+// Accounts and a context wrapper the program tests share. Every test binary pulls this in with `mod common`
+// and uses a different part of it.
+#![allow(dead_code)]
+
+use std::mem::size_of;
+
+use lucra::{
+    entrypoint::process_instruction,
+    error::LucraErrorCode,
+    helpers::constants::DAO_AUTHORITY,
+    id,
+    state::{staking::StakingState, DataType, MetaData, SystemState},
+};
+use solana_program::{
+    clock::Clock,
+    hash::hash,
+    instruction::{Instruction, InstructionError},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+pub const DAY: i64 = 86_400;
+pub const EPOCH: i64 = 604_800;
+pub const START_TIMESTAMP: i64 = 1_650_000_000;
+
+// The v1 oracle layout, see helpers::oracle. Every test oracle quotes with 6 decimals.
+pub const ORACLE_EXPO: u8 = 6;
+pub const ORACLE_V1_EXPO: usize = 72;
+pub const ORACLE_V1_PRICE: usize = 11_097;
+pub const ORACLE_V1_VALID_SLOT: usize = 11_105;
+pub const ORACLE_V1_STATUS: usize = 11_222;
+pub const ORACLE_V1_LEN: usize = ORACLE_V1_STATUS + 1;
+
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new("lucra", id(), processor!(process_instruction))
+}
+
+pub fn program_account(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// A program owned account holding `value` the way its processor writes it
+pub fn pod_account<T: bytemuck::Pod>(value: &T) -> Account {
+    program_account(id(), bytemuck::bytes_of(value).to_vec())
+}
+
+/// A program owned account of `T`'s size that its create instruction hasn't initialized yet
+pub fn uninitialized_account<T>() -> Account {
+    program_account(id(), vec![0; size_of::<T>()])
+}
+
+pub fn mint(authority: Pubkey, supply: u64, decimals: u8) -> Account {
+    mint_with_authority(COption::Some(authority), supply, decimals)
+}
+
+pub fn mint_with_authority(mint_authority: COption<Pubkey>, supply: u64, decimals: u8) -> Account {
+    let mut data = vec![0; Mint::LEN];
+    let mint = Mint {
+        mint_authority,
+        supply,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    Mint::pack(mint, &mut data).unwrap();
+
+    program_account(spl_token::id(), data)
+}
+
+pub fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; TokenAccount::LEN];
+    let account = TokenAccount {
+        mint,
+        owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    };
+    TokenAccount::pack(account, &mut data).unwrap();
+
+    program_account(spl_token::id(), data)
+}
+
+/// An all zero marinade state behind the anchor discriminator. It converts lamports to msol one to one and
+/// values msol at nothing, so there's no yield for a rebate or the recorded sol collateral to come out of.
+pub fn marinade_state_data() -> Vec<u8> {
+    marinade_state_with_msol_mint(&Pubkey::default())
+}
+
+/// Initialize only reads the msol mint out of marinade's state, the first field behind the discriminator
+pub fn marinade_state_with_msol_mint(msol_mint: &Pubkey) -> Vec<u8> {
+    let mut data = hash(b"account:State").to_bytes()[..8].to_vec();
+    data.extend_from_slice(msol_mint.as_ref());
+    data.resize(2_048, 0);
+    data
+}
+
+/// A trading v1 oracle quoting `price` as of `valid_slot`
+pub fn oracle_account(price: u64, valid_slot: u64) -> AccountSharedData {
+    let mut data = vec![0; ORACLE_V1_LEN];
+    data[ORACLE_V1_EXPO] = ORACLE_EXPO;
+    data[ORACLE_V1_PRICE..ORACLE_V1_PRICE + 8].copy_from_slice(&price.to_le_bytes());
+    data[ORACLE_V1_VALID_SLOT..ORACLE_V1_VALID_SLOT + 8].copy_from_slice(&valid_slot.to_le_bytes());
+    data[ORACLE_V1_STATUS] = 1;
+
+    program_account(id(), data).into()
+}
+
+/// Written the way Initialize leaves it, Initialize itself needs the creator authority's keypair. Each test
+/// fills in the mints, vaults and flags it runs against.
+pub fn system_state(key: Pubkey) -> SystemState {
+    let mut system_state = SystemState::default();
+    system_state.meta_data = MetaData::new(DataType::SystemState, 0, true);
+    system_state.key = key;
+    system_state.epoch = EPOCH;
+    system_state
+}
+
+pub fn staking_state(key: Pubkey, stake_mint: Pubkey) -> StakingState {
+    let mut staking_state = StakingState::default();
+    staking_state.meta_data = MetaData::new(DataType::StakingState, 0, true);
+    staking_state.key = key;
+    staking_state.stake_mint.address = stake_mint;
+    staking_state.stake_mint.authority_bump_seed = StakingState::find_stake_mint_authority(&key).1;
+    staking_state
+}
+
+/// The builders sign with the compiled in DAO authority, the test's system state answers to `dao` instead
+pub fn as_dao(dao: &Pubkey, mut instruction: Instruction) -> Instruction {
+    for account in instruction.accounts.iter_mut().filter(|account| account.pubkey == DAO_AUTHORITY) {
+        account.pubkey = *dao;
+    }
+    instruction
+}
+
+pub fn assert_lucra_error(result: Result<(), BanksClientError>, lucra_error_code: LucraErrorCode) {
+    let expected: u32 = lucra_error_code.into();
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(0, InstructionError::Custom(code)))) => {
+            assert_eq!(code, expected)
+        }
+        other => panic!("expected lucra error {}, got {:?}", expected, other),
+    }
+}
+
+pub struct Harness {
+    pub context: ProgramTestContext,
+}
+
+impl Harness {
+    /// Starts the validator with the clock at START_TIMESTAMP
+    pub async fn start(program_test: ProgramTest) -> Self {
+        let mut context = program_test.start_with_context().await;
+
+        let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp = START_TIMESTAMP;
+        context.set_sysvar(&clock);
+
+        Harness { context }
+    }
+
+    pub async fn clock(&mut self) -> Clock {
+        self.context.banks_client.get_sysvar().await.unwrap()
+    }
+
+    /// Moves to a later slot and pushes the clock forward, a new slot also means a new blockhash
+    pub async fn warp(&mut self, seconds: i64) {
+        let mut clock = self.clock().await;
+        clock.slot += 100;
+        self.context.warp_to_slot(clock.slot).unwrap();
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Quotes every `(oracle, price)` as of the current slot
+    pub async fn refresh_oracles(&mut self, prices: &[(Pubkey, u64)]) {
+        let slot = self.clock().await.slot;
+        for (oracle, price) in prices {
+            self.context.set_account(oracle, &oracle_account(*price, slot));
+        }
+    }
+
+    pub fn set_account(&mut self, key: &Pubkey, account: Account) {
+        self.context.set_account(key, &account.into());
+    }
+
+    /// Sends `instruction` paid for by the payer and signed by `signers` as well
+    pub async fn send(&mut self, instruction: Instruction, signers: &[&Keypair]) -> Result<(), BanksClientError> {
+        let recent_blockhash = self.context.banks_client.get_latest_blockhash().await.unwrap();
+        let payer = &self.context.payer;
+        let mut all_signers = vec![payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(&[instruction], Some(&payer.pubkey()), &all_signers, recent_blockhash);
+
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    pub async fn account(&mut self, key: &Pubkey) -> Option<Account> {
+        self.context.banks_client.get_account(*key).await.unwrap()
+    }
+
+    pub async fn load<T: bytemuck::Pod>(&mut self, key: &Pubkey) -> T {
+        let account = self.account(key).await.unwrap();
+        *bytemuck::from_bytes::<T>(&account.data[..size_of::<T>()])
+    }
+
+    pub async fn token_balance(&mut self, key: &Pubkey) -> u64 {
+        TokenAccount::unpack(&self.account(key).await.unwrap().data).unwrap().amount
+    }
+
+    pub async fn mint_supply(&mut self, key: &Pubkey) -> u64 {
+        Mint::unpack(&self.account(key).await.unwrap().data).unwrap().supply
+    }
+
+    pub async fn lamports(&mut self, key: &Pubkey) -> u64 {
+        self.account(key).await.map_or(0, |account| account.lamports)
+    }
+}
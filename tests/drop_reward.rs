@@ -0,0 +1,252 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{
+    assert_lucra_error, marinade_state_data, mint, pod_account, program_account, program_test, token_account,
+    uninitialized_account, Harness, EPOCH, START_TIMESTAMP,
+};
+use lucra::{
+    error::LucraErrorCode,
+    helpers::{constants::DEFAULT_REWARD_EXPIRY_EPOCHS, reward_index::REWARD_INDEX_SCALE},
+    instruction::drop_reward_derived,
+    state::{
+        staking::{Reward, StakingState},
+        SystemState,
+    },
+};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_program_test::ProgramTest;
+
+const STAKED_LUCRA: u64 = 1_000;
+const MIN_DROP_AMOUNT: u64 = 500;
+
+// Every drop runs through DropReward against a staking state whose stake mint already has a supply. No sol
+// collateral is recorded, so everything in the msol vault is yield and drops in two halves.
+struct Fixture {
+    system_state: Pubkey,
+    staking_state: Pubkey,
+    marinade_state: Pubkey,
+    staked_lucra_mint: Pubkey,
+    reward_mint: Pubkey,
+    msol_mint: Pubkey,
+    msol_vault: Pubkey,
+    rewards_vault: Pubkey,
+    arb_coffer: Pubkey,
+    rewards: [Pubkey; 2],
+    user_reward: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Fixture {
+            system_state: Pubkey::new_unique(),
+            staking_state: Pubkey::new_unique(),
+            marinade_state: Pubkey::new_unique(),
+            staked_lucra_mint: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            msol_mint: Pubkey::new_unique(),
+            msol_vault: Pubkey::new_unique(),
+            rewards_vault: Pubkey::new_unique(),
+            arb_coffer: Pubkey::new_unique(),
+            rewards: [Pubkey::new_unique(), Pubkey::new_unique()],
+            user_reward: Pubkey::new_unique(),
+        }
+    }
+
+    fn system_state(&self) -> SystemState {
+        let mut system_state = common::system_state(self.system_state);
+        system_state.staking_state = self.staking_state;
+        system_state.reward_expiry_epochs = DEFAULT_REWARD_EXPIRY_EPOCHS;
+        system_state.staking_enabled = true;
+        system_state.reward_mint.address = self.reward_mint;
+        system_state.reward_mint.authority_bump_seed = SystemState::find_reward_mint_authority(&self.system_state).1;
+        system_state.msol_vault.address = self.msol_vault;
+        system_state.msol_vault.authority_bump_seed = SystemState::find_msol_vault_authority(&self.system_state).1;
+        system_state.rewards_vault.address = self.rewards_vault;
+        system_state.rewards_vault.authority_bump_seed = SystemState::find_rewards_vault_authority(&self.system_state).1;
+        system_state.arb_coffer.address = self.arb_coffer;
+        system_state.arb_coffer.authority_bump_seed = SystemState::find_arb_coffer_authority(&self.system_state).1;
+        system_state
+    }
+
+    fn staking_state(&self, reward_cursor: u64) -> StakingState {
+        let mut staking_state = common::staking_state(self.staking_state, self.staked_lucra_mint);
+        staking_state.min_drop_amount = MIN_DROP_AMOUNT;
+        staking_state.reward_cursor = reward_cursor;
+        staking_state
+    }
+
+    fn program_test(&self, staked_lucra: u64, reward_cursor: u64) -> ProgramTest {
+        let mut program_test = program_test();
+
+        program_test.add_account(self.system_state, pod_account(&self.system_state()));
+        program_test.add_account(self.staking_state, pod_account(&self.staking_state(reward_cursor)));
+        program_test.add_account(self.marinade_state, program_account(marinade_finance::id(), marinade_state_data()));
+        for reward in self.rewards {
+            program_test.add_account(reward, uninitialized_account::<Reward>());
+        }
+
+        program_test.add_account(
+            self.staked_lucra_mint,
+            mint(StakingState::find_stake_mint_authority(&self.staking_state).0, staked_lucra, 9),
+        );
+        program_test.add_account(self.reward_mint, mint(SystemState::find_reward_mint_authority(&self.system_state).0, 0, 0));
+        program_test.add_account(self.msol_mint, mint(Pubkey::new_unique(), 0, 9));
+
+        program_test.add_account(self.msol_vault, token_account(self.msol_mint, SystemState::find_msol_vault_authority(&self.system_state).0, 0));
+        program_test.add_account(self.rewards_vault, token_account(self.msol_mint, SystemState::find_rewards_vault_authority(&self.system_state).0, 0));
+        program_test.add_account(self.arb_coffer, token_account(self.msol_mint, SystemState::find_arb_coffer_authority(&self.system_state).0, 0));
+        program_test.add_account(self.user_reward, token_account(self.reward_mint, Pubkey::new_unique(), 0));
+
+        program_test
+    }
+
+    fn drop_reward(&self, reward: usize) -> Instruction {
+        drop_reward_derived(
+            &self.system_state, &self.staking_state, &self.marinade_state, &self.rewards[reward], &self.staked_lucra_mint,
+            &self.msol_vault, &self.rewards_vault, &self.arb_coffer, &self.user_reward, &self.reward_mint,
+        )
+    }
+}
+
+/// Puts `msol` in the msol vault, what's still there from a skipped drop included
+fn set_yield(drops: &mut Harness, fixture: &Fixture, msol: u64) {
+    let msol_vault_authority = SystemState::find_msol_vault_authority(&fixture.system_state).0;
+    drops.set_account(&fixture.msol_vault, token_account(fixture.msol_mint, msol_vault_authority, msol));
+}
+
+#[tokio::test]
+async fn test_consecutive_skips_roll_into_a_combined_drop() {
+    let f = Fixture::new();
+    let mut drops = Harness::start(f.program_test(STAKED_LUCRA, 4)).await;
+
+    // The yield stays in the vault across skips so each drop sees everything since the last reward
+    set_yield(&mut drops, &f, 300);
+    drops.send(f.drop_reward(0), &[]).await.unwrap();
+    drops.warp(EPOCH).await;
+    set_yield(&mut drops, &f, 700);
+    drops.send(f.drop_reward(0), &[]).await.unwrap();
+
+    let staking_state: StakingState = drops.load(&f.staking_state).await;
+    assert_eq!(staking_state.skipped_drops, 2);
+    assert_eq!(staking_state.last_drop_timestamp, START_TIMESTAMP + EPOCH);
+    assert_eq!(staking_state.reward_cursor, 4);
+    assert_eq!(staking_state.current_reward_pubkey, Pubkey::default());
+    let reward: Reward = drops.load(&f.rewards[0]).await;
+    assert!(!reward.meta_data.is_initialized);
+    assert_eq!(drops.token_balance(&f.msol_vault).await, 700);
+    assert_eq!(drops.token_balance(&f.rewards_vault).await, 0);
+    assert_eq!(drops.token_balance(&f.arb_coffer).await, 0);
+    // A skip still pays the caller for keeping the drops on their epoch
+    assert_eq!(drops.token_balance(&f.user_reward).await, 2);
+
+    drops.warp(EPOCH).await;
+    set_yield(&mut drops, &f, 1_200);
+    drops.send(f.drop_reward(0), &[]).await.unwrap();
+
+    let reward: Reward = drops.load(&f.rewards[0]).await;
+    assert_eq!(reward.total, 600);
+    assert_eq!(reward.pool_token_supply, STAKED_LUCRA);
+    // A balance still on the cursor from before the skips claims this one next
+    assert_eq!(reward.reward_cursor, 4);
+    assert_eq!(reward.start_timestamp, START_TIMESTAMP + 2 * EPOCH);
+    let staking_state: StakingState = drops.load(&f.staking_state).await;
+    assert_eq!(staking_state.reward_cursor, 5);
+    assert_eq!(staking_state.skipped_drops, 0);
+    assert_eq!(staking_state.last_reward, 600);
+    assert_eq!(staking_state.current_reward_pubkey, f.rewards[0]);
+    assert_eq!(staking_state.last_drop_timestamp, START_TIMESTAMP + 2 * EPOCH);
+    assert_eq!(drops.token_balance(&f.msol_vault).await, 0);
+    assert_eq!(drops.token_balance(&f.rewards_vault).await, 600);
+    assert_eq!(drops.token_balance(&f.arb_coffer).await, 600);
+}
+
+#[tokio::test]
+async fn test_drop_at_the_minimum_is_not_skipped() {
+    let f = Fixture::new();
+    let mut drops = Harness::start(f.program_test(STAKED_LUCRA, 0)).await;
+
+    set_yield(&mut drops, &f, 2 * MIN_DROP_AMOUNT - 1);
+    drops.send(f.drop_reward(0), &[]).await.unwrap();
+    let staking_state: StakingState = drops.load(&f.staking_state).await;
+    assert_eq!(staking_state.skipped_drops, 1);
+
+    drops.warp(EPOCH).await;
+    set_yield(&mut drops, &f, 2 * MIN_DROP_AMOUNT);
+    drops.send(f.drop_reward(0), &[]).await.unwrap();
+    let reward: Reward = drops.load(&f.rewards[0]).await;
+    assert_eq!(reward.total, MIN_DROP_AMOUNT);
+    let staking_state: StakingState = drops.load(&f.staking_state).await;
+    assert_eq!(staking_state.skipped_drops, 0);
+    assert_eq!(staking_state.reward_cursor, 1);
+}
+
+#[tokio::test]
+async fn test_drops_accumulate_in_the_reward_index() {
+    let f = Fixture::new();
+    let mut drops = Harness::start(f.program_test(STAKED_LUCRA, 0)).await;
+
+    set_yield(&mut drops, &f, 1_000);
+    drops.send(f.drop_reward(0), &[]).await.unwrap();
+    let reward: Reward = drops.load(&f.rewards[0]).await;
+    assert!(reward.in_reward_index);
+    let staking_state: StakingState = drops.load(&f.staking_state).await;
+    assert_eq!(staking_state.reward_index, REWARD_INDEX_SCALE / 2);
+    assert_eq!(reward.reward_index, staking_state.reward_index);
+
+    // A skip leaves the index where it was, the next drop carries the yield
+    drops.warp(EPOCH).await;
+    set_yield(&mut drops, &f, 400);
+    drops.send(f.drop_reward(1), &[]).await.unwrap();
+    let staking_state: StakingState = drops.load(&f.staking_state).await;
+    assert_eq!(staking_state.reward_index, REWARD_INDEX_SCALE / 2);
+
+    drops.warp(EPOCH).await;
+    set_yield(&mut drops, &f, 3_000);
+    drops.send(f.drop_reward(1), &[]).await.unwrap();
+    let reward: Reward = drops.load(&f.rewards[1]).await;
+    assert_eq!(reward.previous_reward, f.rewards[0]);
+    let staking_state: StakingState = drops.load(&f.staking_state).await;
+    assert_eq!(staking_state.reward_index, 2 * REWARD_INDEX_SCALE);
+
+    // The last reward can't be dropped into again
+    drops.warp(EPOCH).await;
+    set_yield(&mut drops, &f, 1_000);
+    assert_lucra_error(drops.send(f.drop_reward(1), &[]).await, LucraErrorCode::InvalidAccountInput);
+}
+
+#[tokio::test]
+async fn test_drop_with_nothing_staked_goes_to_the_coffer() {
+    let f = Fixture::new();
+    let mut drops = Harness::start(f.program_test(0, 3)).await;
+
+    set_yield(&mut drops, &f, 1_000);
+    drops.send(f.drop_reward(0), &[]).await.unwrap();
+
+    let staking_state: StakingState = drops.load(&f.staking_state).await;
+    assert_eq!(staking_state.last_drop_timestamp, START_TIMESTAMP);
+    assert_eq!(staking_state.reward_cursor, 3);
+    assert_eq!(staking_state.reward_index, 0);
+    assert_eq!(staking_state.skipped_drops, 0);
+    assert_eq!(staking_state.current_reward_pubkey, Pubkey::default());
+    let reward: Reward = drops.load(&f.rewards[0]).await;
+    assert!(!reward.meta_data.is_initialized);
+    assert_eq!(drops.token_balance(&f.msol_vault).await, 0);
+    assert_eq!(drops.token_balance(&f.arb_coffer).await, 1_000);
+    assert_eq!(drops.token_balance(&f.rewards_vault).await, 0);
+}
+
+#[tokio::test]
+async fn test_early_drop_is_rejected() {
+    let f = Fixture::new();
+    let mut drops = Harness::start(f.program_test(STAKED_LUCRA, 0)).await;
+
+    // The first drop can run at any time, the next waits out the epoch, skipped or not
+    set_yield(&mut drops, &f, 100);
+    drops.send(f.drop_reward(0), &[]).await.unwrap();
+    drops.warp(EPOCH - 1).await;
+    assert_lucra_error(drops.send(f.drop_reward(0), &[]).await, LucraErrorCode::EarlyRewardDrop);
+    drops.warp(1).await;
+    drops.send(f.drop_reward(0), &[]).await.unwrap();
+}
@@ -0,0 +1,410 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use std::convert::TryFrom;
+
+use common::{
+    assert_lucra_error, marinade_state_data, mint, pod_account, program_account, program_test, token_account,
+    uninitialized_account, Harness, DAY, EPOCH, START_TIMESTAMP,
+};
+use lucra::{
+    error::LucraErrorCode,
+    helpers::constants::{
+        DEFAULT_REWARD_EXPIRY_EPOCHS, LUCRA_SOL_ORACLE, SETTLEMENT_RECLAIM_DELAY, SOL_USDC_ORACLE, SOL_USDT_ORACLE,
+    },
+    id,
+    instruction::{
+        add_transfer_destination, close_native_sol_mata_loan, create_stake_balance, create_staking_account,
+        deposit_stake, drop_reward_derived, end_unstake_derived, global_settlement, reclaim_excess_derived,
+        stake_derived, start_unstake_derived, withdraw_stake_derived,
+    },
+    lucra_core::interest::LOAN_INTEREST_VERSION,
+    pda::Pda,
+    state::{
+        staking::{PendingWithdrawal, Reward, StakeBalance, StakingAccount, StakingTimeframe},
+        DataType, LoanType, MataBucket, MataLoan, MetaData, SystemState,
+    },
+};
+use solana_program::{
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+};
+
+const LUCRA: u64 = 1_000_000_000;
+const DEPOSIT: u64 = 1_000 * LUCRA;
+// No sol collateral is recorded against the msol vault, the native loan's lamports sit in the sol vault
+const MSOL_YIELD: u64 = 10_000_000_000;
+
+// $20 sol when the loan is opened, and 0.05 sol lucra. The DAO settles at $8, which leaves the loan underwater.
+const SOL_USD_PRICE: u64 = 20_000_000;
+const SETTLEMENT_SOL_PRICE: u64 = 8_000_000;
+const LUCRA_SOL_PRICE: u64 = 50_000;
+const ORACLE_PRICES: [(Pubkey, u64); 3] = [
+    (SOL_USDC_ORACLE, SOL_USD_PRICE),
+    (SOL_USDT_ORACLE, SOL_USD_PRICE),
+    (LUCRA_SOL_ORACLE, LUCRA_SOL_PRICE),
+];
+
+// 10 sol backing 100 mata, worth 80 mata at the settlement price
+const LOAN_LAMPORTS: u64 = 10 * LAMPORTS_PER_SOL;
+const LOAN_MATA: u64 = 100_000_000;
+const LOAN_INDEX: u64 = 1;
+
+struct Fixture {
+    dao: Keypair,
+    user: Keypair,
+    system_state: Pubkey,
+    staking_state: Pubkey,
+    marinade_state: Pubkey,
+    loan: Pubkey,
+
+    lucra_mint: Pubkey,
+    staked_lucra_mint: Pubkey,
+    reward_mint: Pubkey,
+    mata_mint: Pubkey,
+    msol_mint: Pubkey,
+
+    msol_vault: Pubkey,
+    rewards_vault: Pubkey,
+    arb_coffer: Pubkey,
+    treasury_msol: Pubkey,
+
+    staking_account: Pubkey,
+    stake_balance: Pubkey,
+    deposit_vault: Pubkey,
+    stake_vault: Pubkey,
+    pending_vault: Pubkey,
+    pending_withdrawal: Pubkey,
+    reward: Pubkey,
+    next_reward: Pubkey,
+
+    user_lucra: Pubkey,
+    user_staked_lucra: Pubkey,
+    user_mata: Pubkey,
+    user_reward: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let user = Keypair::new();
+        let loan = Pda::loan(&user.pubkey(), LOAN_INDEX).0;
+        Fixture {
+            dao: Keypair::new(),
+            user,
+            system_state: Pubkey::new_unique(),
+            staking_state: Pubkey::new_unique(),
+            marinade_state: Pubkey::new_unique(),
+            loan,
+            lucra_mint: Pubkey::new_unique(),
+            staked_lucra_mint: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            mata_mint: Pubkey::new_unique(),
+            msol_mint: Pubkey::new_unique(),
+            msol_vault: Pubkey::new_unique(),
+            rewards_vault: Pubkey::new_unique(),
+            arb_coffer: Pubkey::new_unique(),
+            treasury_msol: Pubkey::new_unique(),
+            staking_account: Pubkey::new_unique(),
+            stake_balance: Pubkey::new_unique(),
+            deposit_vault: Pubkey::new_unique(),
+            stake_vault: Pubkey::new_unique(),
+            pending_vault: Pubkey::new_unique(),
+            pending_withdrawal: Pubkey::new_unique(),
+            reward: Pubkey::new_unique(),
+            next_reward: Pubkey::new_unique(),
+            user_lucra: Pubkey::new_unique(),
+            user_staked_lucra: Pubkey::new_unique(),
+            user_mata: Pubkey::new_unique(),
+            user_reward: Pubkey::new_unique(),
+        }
+    }
+
+    // Written the way Initialize and the loan's create leave it, with loans and staking both running
+    fn system_state(&self) -> SystemState {
+        let mut system_state = common::system_state(self.system_state);
+        system_state.dao_authority = self.dao.pubkey();
+        system_state.staking_state = self.staking_state;
+        system_state.reward_expiry_epochs = DEFAULT_REWARD_EXPIRY_EPOCHS;
+        system_state.loans_enabled = true;
+        system_state.native_loans_enabled = true;
+        system_state.staking_enabled = true;
+        system_state.lucra_mint.address = self.lucra_mint;
+        system_state.lucra_mint.authority_bump_seed = Pda::lucra_mint_authority(&self.system_state).1;
+        system_state.reward_mint.address = self.reward_mint;
+        system_state.reward_mint.authority_bump_seed = Pda::reward_mint_authority(&self.system_state).1;
+        system_state.reward_mint_decimals = 0;
+        system_state.mata_mint.address = self.mata_mint;
+        system_state.mata_mint.authority_bump_seed = Pda::mata_mint_authority(&self.system_state).1;
+        system_state.msol_vault.address = self.msol_vault;
+        system_state.msol_vault.authority_bump_seed = Pda::msol_vault_authority(&self.system_state).1;
+        system_state.rewards_vault.address = self.rewards_vault;
+        system_state.rewards_vault.authority_bump_seed = Pda::rewards_vault_authority(&self.system_state).1;
+        system_state.arb_coffer.address = self.arb_coffer;
+        system_state.arb_coffer.authority_bump_seed = Pda::arb_coffer_authority(&self.system_state).1;
+        system_state.sol_vault.address = Pda::sol_vault(&self.system_state).0;
+        system_state.mata_supply.mint(MataBucket::Loan, LOAN_MATA).unwrap();
+        system_state
+    }
+
+    // Opened right at the start, it's still inside the epoch a close normally has to wait out
+    fn loan(&self) -> MataLoan {
+        let mut loan = MataLoan::default();
+        loan.meta_data = MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true);
+        loan.loan_type = LoanType::NativeSol;
+        loan.owner = self.user.pubkey();
+        loan.creator = self.user.pubkey();
+        loan.loan_index = LOAN_INDEX;
+        loan.loan_mint = self.mata_mint;
+        loan.sol_collateral_amount = LOAN_LAMPORTS;
+        loan.loan_amount = LOAN_MATA;
+        loan.market_price = SOL_USD_PRICE;
+        loan.loan_creation_date = START_TIMESTAMP;
+        loan.last_day_penalty_was_checked = START_TIMESTAMP;
+        loan
+    }
+
+    fn program_test(&self) -> ProgramTest {
+        let mut program_test = program_test();
+        let user = self.user.pubkey();
+        let vault_owner = Pda::stake_balance_authority(&user, &self.system_state).0;
+
+        program_test.add_account(self.system_state, pod_account(&self.system_state()));
+        program_test.add_account(self.staking_state, pod_account(&common::staking_state(self.staking_state, self.staked_lucra_mint)));
+        program_test.add_account(self.marinade_state, program_account(marinade_finance::id(), marinade_state_data()));
+        program_test.add_account(self.loan, pod_account(&self.loan()));
+        program_test.add_account(self.staking_account, uninitialized_account::<StakingAccount>());
+        program_test.add_account(self.stake_balance, uninitialized_account::<StakeBalance>());
+        program_test.add_account(self.pending_withdrawal, uninitialized_account::<PendingWithdrawal>());
+        program_test.add_account(self.reward, uninitialized_account::<Reward>());
+        program_test.add_account(self.next_reward, uninitialized_account::<Reward>());
+
+        program_test.add_account(self.lucra_mint, mint(Pda::lucra_mint_authority(&self.system_state).0, DEPOSIT, 9));
+        program_test.add_account(self.staked_lucra_mint, mint(Pda::stake_mint_authority(&self.staking_state).0, 0, 9));
+        program_test.add_account(self.reward_mint, mint(Pda::reward_mint_authority(&self.system_state).0, 0, 0));
+        program_test.add_account(self.mata_mint, mint(Pda::mata_mint_authority(&self.system_state).0, LOAN_MATA, 6));
+        program_test.add_account(self.msol_mint, mint(Pubkey::new_unique(), 2 * MSOL_YIELD, 9));
+
+        program_test.add_account(self.msol_vault, token_account(self.msol_mint, Pda::msol_vault_authority(&self.system_state).0, MSOL_YIELD));
+        program_test.add_account(self.rewards_vault, token_account(self.msol_mint, Pda::rewards_vault_authority(&self.system_state).0, 0));
+        program_test.add_account(self.arb_coffer, token_account(self.msol_mint, Pda::arb_coffer_authority(&self.system_state).0, 0));
+        program_test.add_account(self.treasury_msol, token_account(self.msol_mint, Pubkey::new_unique(), 0));
+        program_test.add_account(
+            Pda::sol_vault(&self.system_state).0,
+            Account::new(Rent::default().minimum_balance(0) + LOAN_LAMPORTS, 0, &id()),
+        );
+
+        program_test.add_account(self.deposit_vault, token_account(self.lucra_mint, vault_owner, 0));
+        program_test.add_account(self.stake_vault, token_account(self.lucra_mint, vault_owner, 0));
+        program_test.add_account(self.pending_vault, token_account(self.lucra_mint, vault_owner, 0));
+
+        program_test.add_account(user, Account::new(LAMPORTS_PER_SOL, 0, &system_program::id()));
+        program_test.add_account(self.user_lucra, token_account(self.lucra_mint, user, DEPOSIT));
+        program_test.add_account(self.user_staked_lucra, token_account(self.staked_lucra_mint, user, 0));
+        program_test.add_account(self.user_mata, token_account(self.mata_mint, user, LOAN_MATA));
+        program_test.add_account(self.user_reward, token_account(self.reward_mint, user, 0));
+
+        program_test
+    }
+
+    fn as_dao(&self, instruction: Instruction) -> Instruction {
+        common::as_dao(&self.dao.pubkey(), instruction)
+    }
+
+    fn drop_reward(&self, reward: &Pubkey) -> Instruction {
+        drop_reward_derived(
+            &self.system_state, &self.staking_state, &self.marinade_state, reward, &self.staked_lucra_mint,
+            &self.msol_vault, &self.rewards_vault, &self.arb_coffer, &self.user_reward, &self.reward_mint,
+        )
+    }
+}
+
+/// Msol that lands in the vault after the last drop, nothing can drop it once settlement started
+fn accrue_yield(settlement: &mut Harness, fixture: &Fixture) {
+    let msol_vault_authority = Pda::msol_vault_authority(&fixture.system_state).0;
+    settlement.set_account(&fixture.msol_vault, token_account(fixture.msol_mint, msol_vault_authority, MSOL_YIELD));
+}
+
+#[tokio::test]
+async fn test_settlement_winds_the_protocol_down() {
+    let staking_timeframe = StakingTimeframe::try_from(0).unwrap();
+    let lock = EPOCH * staking_timeframe.timeframe_multiplier();
+
+    let fixture = Fixture::new();
+    let f = &fixture;
+    let user = f.user.pubkey();
+    let nonce = Pda::stake_balance_authority(&user, &f.system_state).1;
+
+    let mut settlement = Harness::start(f.program_test()).await;
+    let user_lamports = settlement.lamports(&user).await;
+
+    // A staker stakes and sits out the first drop, the way staking works before settlement
+    settlement
+        .send(create_staking_account(&f.system_state, &f.staking_state, &f.staking_account, &user, None), &[&f.user])
+        .await
+        .unwrap();
+    settlement
+        .send(
+            create_stake_balance(
+                &f.system_state, &f.staking_state, &f.stake_balance, &user,
+                &f.deposit_vault, &f.stake_vault, &f.pending_vault, &f.staking_account, nonce, staking_timeframe,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    settlement
+        .send(deposit_stake(&f.system_state, &f.stake_balance, &f.user_lucra, &f.deposit_vault, &user, DEPOSIT), &[&f.user])
+        .await
+        .unwrap();
+    settlement
+        .send(
+            stake_derived(
+                &f.system_state, &f.staking_state, &f.staking_account, &f.stake_balance, &f.deposit_vault,
+                &f.stake_vault, &user, &f.user_staked_lucra, &f.staked_lucra_mint, DEPOSIT,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    settlement.warp(DAY).await;
+    settlement.send(f.drop_reward(&f.reward), &[]).await.unwrap();
+    assert_eq!(settlement.token_balance(&f.msol_vault).await, 0);
+
+    // Only the DAO can settle
+    let not_dao = Keypair::new();
+    let mut instruction = global_settlement(&f.system_state, SETTLEMENT_SOL_PRICE);
+    instruction.accounts[1].pubkey = not_dao.pubkey();
+    assert_lucra_error(settlement.send(instruction, &[&not_dao]).await, LucraErrorCode::InvalidAccountInput);
+    assert_lucra_error(
+        settlement.send(f.as_dao(global_settlement(&f.system_state, 0)), &[&f.dao]).await,
+        LucraErrorCode::InvalidAmount,
+    );
+
+    let settlement_timestamp = settlement.clock().await.unix_timestamp;
+    settlement.send(f.as_dao(global_settlement(&f.system_state, SETTLEMENT_SOL_PRICE)), &[&f.dao]).await.unwrap();
+    let system_state: SystemState = settlement.load(&f.system_state).await;
+    assert!(system_state.settlement_active);
+    assert_eq!(system_state.settlement_sol_price, SETTLEMENT_SOL_PRICE);
+    assert_eq!(system_state.settlement_timestamp, settlement_timestamp);
+
+    // It can't be undone or moved to another price, and everything that isn't a way out is refused
+    settlement.warp(1).await;
+    assert_lucra_error(
+        settlement.send(f.as_dao(global_settlement(&f.system_state, SOL_USD_PRICE)), &[&f.dao]).await,
+        LucraErrorCode::SettlementActive,
+    );
+    assert_lucra_error(
+        settlement
+            .send(f.as_dao(add_transfer_destination(&f.system_state, &f.treasury_msol, &user)), &[&f.dao, &f.user])
+            .await,
+        LucraErrorCode::SettlementActive,
+    );
+    accrue_yield(&mut settlement, f);
+    assert_lucra_error(settlement.send(f.drop_reward(&f.next_reward), &[]).await, LucraErrorCode::SettlementActive);
+    assert_lucra_error(
+        settlement
+            .send(deposit_stake(&f.system_state, &f.stake_balance, &f.user_lucra, &f.deposit_vault, &user, 1), &[&f.user])
+            .await,
+        LucraErrorCode::SettlementActive,
+    );
+
+    // The loan is still inside its epoch and underwater at the settlement price. The borrower burns what the
+    // collateral is worth at that price and gets all of it back.
+    let sol_vault = Pda::sol_vault(&f.system_state).0;
+    settlement
+        .send(close_native_sol_mata_loan(&f.system_state, &f.loan, &user, &f.mata_mint, &f.user_mata), &[&f.user])
+        .await
+        .unwrap();
+    let collateral_value = LOAN_LAMPORTS / LAMPORTS_PER_SOL * SETTLEMENT_SOL_PRICE;
+    assert_eq!(settlement.token_balance(&f.user_mata).await, LOAN_MATA - collateral_value);
+    assert_eq!(settlement.mint_supply(&f.mata_mint).await, LOAN_MATA - collateral_value);
+    assert_eq!(settlement.lamports(&user).await, user_lamports + LOAN_LAMPORTS);
+    assert_eq!(settlement.lamports(&sol_vault).await, Rent::default().minimum_balance(0));
+    let loan: MataLoan = settlement.load(&f.loan).await;
+    assert!(loan.repaid);
+    let system_state: SystemState = settlement.load(&f.system_state).await;
+    assert_eq!(system_state.mata_supply.loan_minted_mata, LOAN_MATA - collateral_value);
+
+    // The staker leaves before the stake's lock and the withdrawal's timelock are up
+    assert!(lock > DAY);
+    settlement.refresh_oracles(&ORACLE_PRICES).await;
+    settlement
+        .send(
+            start_unstake_derived(
+                &f.system_state, &f.staking_state, &f.staking_account, &f.stake_balance, &f.stake_vault,
+                &f.pending_vault, &f.pending_withdrawal, &user, &f.user_staked_lucra, &f.staked_lucra_mint,
+                &SOL_USDC_ORACLE, &SOL_USDT_ORACLE, &LUCRA_SOL_ORACLE, DEPOSIT,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    let unstake_timestamp = settlement.clock().await.unix_timestamp;
+    let pending_withdrawal: PendingWithdrawal = settlement.load(&f.pending_withdrawal).await;
+    assert_eq!(pending_withdrawal.lucra, DEPOSIT);
+    assert_eq!(pending_withdrawal.claimable_at, unstake_timestamp);
+
+    settlement
+        .send(
+            end_unstake_derived(
+                &f.system_state, &f.pending_withdrawal, &f.stake_balance, &f.pending_vault, &f.deposit_vault, &user,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    settlement
+        .send(
+            withdraw_stake_derived(
+                &f.system_state, &f.staking_account, &f.stake_balance, &f.user_lucra, &f.deposit_vault,
+                &f.stake_vault, &f.pending_vault, &user, DEPOSIT,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(settlement.token_balance(&f.user_lucra).await, DEPOSIT);
+    assert_eq!(settlement.token_balance(&f.pending_vault).await, 0);
+    assert_eq!(settlement.token_balance(&f.deposit_vault).await, 0);
+
+    // What's left in the msol vault stays there until everyone had time to close out
+    let reclaim = f.as_dao(reclaim_excess_derived(&f.system_state, &f.msol_vault, &f.treasury_msol));
+    let waited = settlement.clock().await.unix_timestamp - settlement_timestamp;
+    settlement.warp(SETTLEMENT_RECLAIM_DELAY - waited - 1).await;
+    assert_lucra_error(settlement.send(reclaim.clone(), &[&f.dao]).await, LucraErrorCode::Timelock);
+
+    let mut instruction = reclaim_excess_derived(&f.system_state, &f.msol_vault, &f.treasury_msol);
+    instruction.accounts[1].pubkey = not_dao.pubkey();
+    settlement.warp(1).await;
+    assert_lucra_error(settlement.send(instruction, &[&not_dao]).await, LucraErrorCode::InvalidAccountInput);
+
+    settlement.send(reclaim.clone(), &[&f.dao]).await.unwrap();
+    assert_eq!(settlement.token_balance(&f.msol_vault).await, 0);
+    assert_eq!(settlement.token_balance(&f.treasury_msol).await, MSOL_YIELD);
+
+    // Once it's empty there's nothing left to sweep
+    settlement.warp(1).await;
+    assert_lucra_error(settlement.send(reclaim, &[&f.dao]).await, LucraErrorCode::InvalidAmount);
+}
+
+#[tokio::test]
+async fn test_reclaim_needs_a_settlement() {
+    let f = Fixture::new();
+    let mut settlement = Harness::start(f.program_test()).await;
+
+    settlement.warp(SETTLEMENT_RECLAIM_DELAY).await;
+    assert_lucra_error(
+        settlement.send(f.as_dao(reclaim_excess_derived(&f.system_state, &f.msol_vault, &f.treasury_msol)), &[&f.dao]).await,
+        LucraErrorCode::SettlementNotActive,
+    );
+    assert_eq!(settlement.token_balance(&f.msol_vault).await, MSOL_YIELD);
+}
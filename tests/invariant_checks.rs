@@ -0,0 +1,155 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// `check!` and `invariant!` page different people, so one condition has to be one or the other. These read
+// the source rather than run it since the processors need a validator to reach most of their checks.
+
+const CALLER_ERROR_CODES: [&str; 5] = [
+    "LucraErrorCode::InvalidAccountInput",
+    "LucraErrorCode::InvalidAccountOwner",
+    "LucraErrorCode::InvalidProgramAccount",
+    "LucraErrorCode::AccountNotSigner",
+    "LucraErrorCode::AccountNotMutable",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Family {
+    Check,
+    Invariant,
+}
+
+struct Site {
+    family: Family,
+    condition: String,
+    error_code: String,
+}
+
+fn source_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            source_files(&path, files);
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+/// Splits the macro arguments starting right after the opening paren on the top level commas
+fn macro_args(source: &str) -> Vec<String> {
+    let mut args = vec![String::new()];
+    let mut depth = 0;
+    for c in source.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => break,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        args.last_mut().unwrap().push(c);
+    }
+
+    args.into_iter()
+        .map(|arg| arg.split_whitespace().collect::<String>())
+        .filter(|arg| !arg.is_empty())
+        .collect()
+}
+
+fn find_sites(source: &str) -> Vec<Site> {
+    let macros = [
+        ("check!(", Family::Check, false),
+        ("check_eq!(", Family::Check, true),
+        ("invariant!(", Family::Invariant, false),
+        ("invariant_eq!(", Family::Invariant, true),
+    ];
+
+    let mut sites = vec![];
+    for (name, family, is_eq) in macros.iter() {
+        for (start, _) in source.match_indices(name) {
+            let preceded_by_ident = source[..start]
+                .chars()
+                .last()
+                .map_or(false, |c| c.is_alphanumeric() || c == '_');
+            if preceded_by_ident {
+                continue;
+            }
+
+            let args = macro_args(&source[start + name.len()..]);
+            let (condition, error_code) = match (is_eq, args.as_slice()) {
+                (false, [condition, error_code]) => (condition.clone(), error_code.clone()),
+                (true, [x, y, error_code]) => (format!("{}=={}", x, y), error_code.clone()),
+                _ => continue,
+            };
+            sites.push(Site { family: *family, condition, error_code });
+        }
+    }
+
+    sites
+}
+
+fn all_sites() -> Vec<(PathBuf, Vec<Site>)> {
+    let mut files = vec![];
+    source_files(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut files);
+
+    files
+        .into_iter()
+        .map(|path| {
+            let sites = find_sites(&fs::read_to_string(&path).unwrap());
+            (path, sites)
+        })
+        .collect()
+}
+
+#[test]
+fn test_no_condition_is_both_a_check_and_an_invariant() {
+    for (path, sites) in all_sites() {
+        let mut families: HashMap<&str, Family> = HashMap::new();
+        for site in sites.iter() {
+            let family = families.entry(&site.condition).or_insert(site.family);
+            assert_eq!(
+                *family, site.family,
+                "{} uses `{}` as both a check and an invariant",
+                path.display(), site.condition,
+            );
+        }
+    }
+}
+
+#[test]
+fn test_invariants_do_not_report_caller_errors() {
+    for (path, sites) in all_sites() {
+        for site in sites.iter().filter(|site| site.family == Family::Invariant) {
+            assert!(
+                !CALLER_ERROR_CODES.contains(&site.error_code.as_str()),
+                "{} raises {} from an invariant, the caller can trigger that one",
+                path.display(), site.error_code,
+            );
+        }
+    }
+}
+
+#[test]
+fn test_scanner_reads_both_families() {
+    let sites = find_sites("
+        check!(amount > 0, LucraErrorCode::InvalidAmount)?;
+        check_eq!(a.owner, program_id, LucraErrorCode::InvalidAccountOwner)?;
+        invariant!(
+            amount <= min(a, b),
+            LucraErrorCode::MataBucketUnderflow
+        )?;
+    ");
+
+    assert_eq!(sites.len(), 3);
+    assert_eq!(sites[0].family, Family::Check);
+    assert_eq!(sites[1].condition, "a.owner==program_id");
+    assert_eq!(sites[2].family, Family::Invariant);
+    assert_eq!(sites[2].condition, "amount<=min(a,b)");
+    assert_eq!(sites[2].error_code, "LucraErrorCode::MataBucketUnderflow");
+}
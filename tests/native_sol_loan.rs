@@ -0,0 +1,250 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{
+    assert_lucra_error, mint, pod_account, program_account, program_test, token_account, Harness, DAY, EPOCH,
+    ORACLE_EXPO, START_TIMESTAMP,
+};
+use lucra::{
+    error::LucraErrorCode,
+    helpers::{
+        constants::{orca_swap, wsol, SOL_MATA_ORACLE, SOL_MATA_ORCA_AMM, SOL_USDC_ORACLE, SOL_USDT_ORACLE},
+        liquidation::calc_liquidation_split,
+    },
+    id,
+    instruction::{
+        close_native_sol_mata_loan, create_native_sol_mata_loan, harvest_native_sol_penalty_with_orca,
+        liquidate_native_sol_loan,
+    },
+    lucra_core::quote::calc_loan_amount,
+    pda::Pda,
+    state::{LoanType, MataLoan, SystemState},
+    units::{Bps, CollateralRatio, Lamports, Mata},
+};
+use rust_decimal::Decimal;
+use solana_program::{
+    bpf_loader,
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+};
+
+const COLLATERAL_REQUIREMENT: CollateralRatio = CollateralRatio(150);
+const LIQUIDATION_RATIO: CollateralRatio = CollateralRatio(120);
+const LIQUIDATION_BONUS: Bps = Bps(500);
+
+// $20 sol when the loan is opened. At $15 the collateral covers the debt 112.5%, under the liquidation ratio.
+const SOL_USD_PRICE: u64 = 20_000_000;
+const CRASHED_SOL_PRICE: u64 = 15_000_000;
+
+const LOAN_LAMPORTS: u64 = 10 * LAMPORTS_PER_SOL;
+
+struct Fixture {
+    user: Keypair,
+    liquidator: Keypair,
+    system_state: Pubkey,
+    mata_mint: Pubkey,
+    user_mata: Pubkey,
+    user_wsol: Pubkey,
+    liquidator_mata: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Fixture {
+            user: Keypair::new(),
+            liquidator: Keypair::new(),
+            system_state: Pubkey::new_unique(),
+            mata_mint: Pubkey::new_unique(),
+            user_mata: Pubkey::new_unique(),
+            user_wsol: Pubkey::new_unique(),
+            liquidator_mata: Pubkey::new_unique(),
+        }
+    }
+
+    // Written the way Initialize and CreateSolVault leave it with native loans turned on, the peg check is off
+    // so a create doesn't need the sol/mata oracle
+    fn system_state(&self) -> SystemState {
+        let mut system_state = common::system_state(self.system_state);
+        system_state.loans_enabled = true;
+        system_state.native_loans_enabled = true;
+        system_state.collateral_requirement = COLLATERAL_REQUIREMENT;
+        system_state.liquidation_ratio = LIQUIDATION_RATIO;
+        system_state.liquidation_bonus = LIQUIDATION_BONUS;
+        system_state.min_deposit = Lamports(LAMPORTS_PER_SOL / 10);
+        system_state.min_loan_amount_mata = Mata(1_000_000);
+        system_state.minimum_harvest_amount = Lamports(LAMPORTS_PER_SOL / 100);
+        system_state.maximum_outstanding_mata = Mata(1_000_000_000_000);
+        system_state.mata_mint.address = self.mata_mint;
+        system_state.mata_mint.authority_bump_seed = Pda::mata_mint_authority(&self.system_state).1;
+        system_state.sol_vault.address = self.sol_vault();
+        system_state
+    }
+
+    fn sol_vault(&self) -> Pubkey {
+        Pda::sol_vault(&self.system_state).0
+    }
+
+    // The user's first loan
+    fn loan(&self) -> Pubkey {
+        Pda::loan(&self.user.pubkey(), 1).0
+    }
+
+    // The liquidator already holds enough mata to repay the loan. The orca program is only a stand in, a harvest
+    // that gets as far as the swap can't run here.
+    fn program_test(&self) -> ProgramTest {
+        let mut program_test = program_test();
+        let user = self.user.pubkey();
+        let liquidator = self.liquidator.pubkey();
+
+        program_test.add_account(self.system_state, pod_account(&self.system_state()));
+        program_test.add_account(self.sol_vault(), Account::new(Rent::default().minimum_balance(0), 0, &id()));
+        program_test.add_account(self.mata_mint, mint(Pda::mata_mint_authority(&self.system_state).0, loan_amount(), 6));
+        // The v2 fixture has orca out trading raydium, which the harvest's swap needs
+        program_test.add_account(SOL_MATA_ORACLE, program_account(id(), include_bytes!("fixtures/oracle_v2_sol_mata.bin").to_vec()));
+        program_test.add_account(orca_swap::id(), Account { executable: true, ..Account::new(0, 0, &bpf_loader::id()) });
+
+        program_test.add_account(user, Account::new(2 * LOAN_LAMPORTS, 0, &system_program::id()));
+        program_test.add_account(self.user_mata, token_account(self.mata_mint, user, 0));
+        program_test.add_account(self.user_wsol, token_account(wsol::id(), user, 0));
+        program_test.add_account(liquidator, Account::new(LAMPORTS_PER_SOL, 0, &system_program::id()));
+        program_test.add_account(self.liquidator_mata, token_account(self.mata_mint, liquidator, loan_amount()));
+
+        program_test
+    }
+
+    fn create(&self) -> Instruction {
+        create_native_sol_mata_loan(
+            &self.system_state, &self.loan(), &self.mata_mint, &self.user.pubkey(), &self.user_mata,
+            &SOL_USDC_ORACLE, &SOL_USDT_ORACLE, &SOL_MATA_ORACLE, LOAN_LAMPORTS,
+        )
+    }
+
+    fn close(&self) -> Instruction {
+        close_native_sol_mata_loan(&self.system_state, &self.loan(), &self.user.pubkey(), &self.mata_mint, &self.user_mata)
+    }
+
+    fn harvest(&self) -> Instruction {
+        harvest_native_sol_penalty_with_orca(
+            &self.system_state, &self.mata_mint, &self.loan(), &self.user.pubkey(), &self.user_wsol, &self.user_mata,
+            &SOL_MATA_ORACLE, &SOL_USDC_ORACLE, &SOL_USDT_ORACLE, &SOL_MATA_ORCA_AMM, &Pubkey::new_unique(),
+            &Pubkey::new_unique(), &Pubkey::new_unique(), &Pubkey::new_unique(), &Pubkey::new_unique(), 0,
+        )
+    }
+
+    fn liquidate(&self) -> Instruction {
+        liquidate_native_sol_loan(
+            &self.system_state, &self.loan(), &self.mata_mint, &self.liquidator.pubkey(), &self.liquidator_mata,
+            &SOL_USDC_ORACLE, &SOL_USDT_ORACLE,
+        )
+    }
+}
+
+fn sol_price(price: u64) -> Decimal {
+    Decimal::new(price as i64, ORACLE_EXPO.into())
+}
+
+/// What a create mints for the loan's lamports at the opening price, rounded the way the program does
+fn loan_amount() -> u64 {
+    let value = sol_price(SOL_USD_PRICE) * Decimal::from(LOAN_LAMPORTS) / Decimal::from(LAMPORTS_PER_SOL);
+    calc_loan_amount(value, COLLATERAL_REQUIREMENT).unwrap().get()
+}
+
+async fn refresh_oracles(borrower: &mut Harness, price: u64) {
+    borrower.refresh_oracles(&[(SOL_USDC_ORACLE, price), (SOL_USDT_ORACLE, price)]).await;
+}
+
+/// Opens the loan at the opening price through CreateMataLoan
+async fn start(fixture: &Fixture) -> Harness {
+    let mut borrower = Harness::start(fixture.program_test()).await;
+    refresh_oracles(&mut borrower, SOL_USD_PRICE).await;
+    borrower.send(fixture.create(), &[&fixture.user]).await.unwrap();
+    borrower
+}
+
+#[tokio::test]
+async fn test_native_loan_round_trip() {
+    let f = Fixture::new();
+    let rent = Rent::default();
+    let mut borrower = start(&f).await;
+
+    // The lamports sit in the sol vault as they are and the debt is minted against them
+    assert_eq!(borrower.lamports(&f.sol_vault()).await, rent.minimum_balance(0) + LOAN_LAMPORTS);
+    assert_eq!(borrower.token_balance(&f.user_mata).await, loan_amount());
+    let loan: MataLoan = borrower.load(&f.loan()).await;
+    assert_eq!(loan.loan_type, LoanType::NativeSol);
+    assert_eq!(loan.sol_collateral_amount, LOAN_LAMPORTS);
+    assert_eq!(loan.msol_collateral_amount, 0);
+    assert_eq!(loan.loan_amount, loan_amount());
+    assert_eq!(loan.loan_creation_date, START_TIMESTAMP);
+    // Native collateral isn't counted against the msol vault
+    let system_state: SystemState = borrower.load(&f.system_state).await;
+    assert_eq!(system_state.total_sol_collateral, 0);
+    assert_eq!(system_state.mata_supply.total().unwrap(), loan_amount());
+
+    // Neither penalty nor interest has accrued yet, a harvest has nothing to take out of the vault
+    assert_lucra_error(borrower.send(f.harvest(), &[&f.user]).await, LucraErrorCode::NoPenaltyToHarvest);
+    assert_eq!(borrower.lamports(&f.sol_vault()).await, rent.minimum_balance(0) + LOAN_LAMPORTS);
+
+    // A loan can't be closed inside the epoch it was opened in
+    assert_lucra_error(borrower.send(f.close(), &[&f.user]).await, LucraErrorCode::Timelock);
+
+    borrower.warp(EPOCH + DAY).await;
+    let user_lamports = borrower.lamports(&f.user.pubkey()).await;
+    let mata_supply = borrower.mint_supply(&f.mata_mint).await;
+    borrower.send(f.close(), &[&f.user]).await.unwrap();
+
+    // The debt is burned and every lamport comes back, the vault is left with its rent
+    assert_eq!(borrower.token_balance(&f.user_mata).await, 0);
+    assert_eq!(borrower.mint_supply(&f.mata_mint).await, mata_supply - loan_amount());
+    assert_eq!(borrower.lamports(&f.user.pubkey()).await, user_lamports + LOAN_LAMPORTS);
+    assert_eq!(borrower.lamports(&f.sol_vault()).await, rent.minimum_balance(0));
+    let loan: MataLoan = borrower.load(&f.loan()).await;
+    assert!(loan.repaid);
+    let system_state: SystemState = borrower.load(&f.system_state).await;
+    assert_eq!(system_state.mata_supply.total().unwrap(), 0);
+
+    assert_lucra_error(borrower.send(f.close(), &[&f.user]).await, LucraErrorCode::InvalidAccountInput);
+}
+
+#[tokio::test]
+async fn test_underwater_native_loan_is_liquidated_out_of_the_sol_vault() {
+    let f = Fixture::new();
+    let rent = Rent::default();
+    let mut borrower = start(&f).await;
+
+    // Still covered 150% at the opening price
+    assert_lucra_error(borrower.send(f.liquidate(), &[&f.liquidator]).await, LucraErrorCode::LoanNotLiquidatable);
+
+    borrower.warp(DAY).await;
+    refresh_oracles(&mut borrower, CRASHED_SOL_PRICE).await;
+    let liquidator_lamports = borrower.lamports(&f.liquidator.pubkey()).await;
+    let mata_supply = borrower.mint_supply(&f.mata_mint).await;
+    borrower.send(f.liquidate(), &[&f.liquidator]).await.unwrap();
+
+    // The liquidator repays the debt and is paid its worth in lamports plus the bonus, the rest stays in the vault
+    let split = calc_liquidation_split(LOAN_LAMPORTS, loan_amount(), sol_price(CRASHED_SOL_PRICE), LIQUIDATION_BONUS).unwrap();
+    assert!(split.to_coffer > 0);
+    assert_eq!(borrower.token_balance(&f.liquidator_mata).await, 0);
+    assert_eq!(borrower.mint_supply(&f.mata_mint).await, mata_supply - loan_amount());
+    assert_eq!(borrower.lamports(&f.liquidator.pubkey()).await, liquidator_lamports + split.to_liquidator);
+    assert_eq!(borrower.lamports(&f.sol_vault()).await, rent.minimum_balance(0) + split.to_coffer);
+
+    let loan: MataLoan = borrower.load(&f.loan()).await;
+    assert!(loan.repaid);
+    let system_state: SystemState = borrower.load(&f.system_state).await;
+    assert_eq!(system_state.mata_supply.total().unwrap(), 0);
+    assert_eq!(system_state.total_sol_collateral, 0);
+
+    // The owner can't close what the liquidator already repaid
+    borrower.warp(EPOCH).await;
+    assert_lucra_error(borrower.send(f.close(), &[&f.user]).await, LucraErrorCode::InvalidAccountInput);
+}
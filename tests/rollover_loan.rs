@@ -0,0 +1,233 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{
+    assert_lucra_error, marinade_state_data, mint, pod_account, program_account, program_test, token_account, Harness,
+    EPOCH, ORACLE_EXPO, START_TIMESTAMP,
+};
+use lucra::{
+    error::LucraErrorCode,
+    helpers::constants::{SOL_MATA_ORACLE, SOL_USDC_ORACLE, SOL_USDT_ORACLE},
+    instruction::rollover_loan,
+    lucra_core::{interest::LOAN_INTEREST_VERSION, quote::calc_loan_amount},
+    pda::Pda,
+    state::{DataType, LoanType, MataBucket, MataLoan, MetaData, SystemState},
+    units::{CollateralRatio, Lamports, Mata},
+};
+use rust_decimal::Decimal;
+use solana_program::{
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    system_program,
+};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+};
+
+const COLLATERAL_REQUIREMENT: CollateralRatio = CollateralRatio(300);
+
+// $20 sol, the loan is opened and rolled over at the same price
+const SOL_USD_PRICE: u64 = 20_000_000;
+
+const OLD_LAMPORTS: u64 = 10 * LAMPORTS_PER_SOL;
+
+struct Fixture {
+    user: Keypair,
+    system_state: Pubkey,
+    marinade_state: Pubkey,
+    loan: Pubkey,
+    mata_mint: Pubkey,
+    msol_mint: Pubkey,
+    msol_vault: Pubkey,
+    user_mata: Pubkey,
+    user_msol: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Fixture {
+            user: Keypair::new(),
+            system_state: Pubkey::new_unique(),
+            marinade_state: Pubkey::new_unique(),
+            loan: Pubkey::new_unique(),
+            mata_mint: Pubkey::new_unique(),
+            msol_mint: Pubkey::new_unique(),
+            msol_vault: Pubkey::new_unique(),
+            user_mata: Pubkey::new_unique(),
+            user_msol: Pubkey::new_unique(),
+        }
+    }
+
+    // Written the way Initialize and a create leave it, the loan's mata is all in the loan bucket
+    fn system_state(&self) -> SystemState {
+        let mut system_state = common::system_state(self.system_state);
+        system_state.loans_enabled = true;
+        system_state.collateral_requirement = COLLATERAL_REQUIREMENT;
+        system_state.min_deposit = Lamports(LAMPORTS_PER_SOL / 10);
+        system_state.min_loan_amount_mata = Mata(1_000_000);
+        system_state.maximum_outstanding_mata = Mata(1_000_000_000_000);
+        system_state.mata_mint.address = self.mata_mint;
+        system_state.mata_mint.authority_bump_seed = Pda::mata_mint_authority(&self.system_state).1;
+        system_state.msol_vault.address = self.msol_vault;
+        system_state.msol_vault.authority_bump_seed = Pda::msol_vault_authority(&self.system_state).1;
+        system_state.total_sol_collateral = OLD_LAMPORTS;
+        system_state.mata_supply.mint(MataBucket::Loan, loan_amount(OLD_LAMPORTS)).unwrap();
+        system_state
+    }
+
+    // Opened two epochs ago, past the timelock a close has
+    fn loan(&self, loan_creation_date: i64) -> MataLoan {
+        let mut loan = MataLoan::default();
+        loan.meta_data = MetaData::new(DataType::Loan, LOAN_INTEREST_VERSION, true);
+        loan.loan_type = LoanType::Default;
+        loan.owner = self.user.pubkey();
+        loan.loan_mint = self.mata_mint;
+        loan.collateral_rate = COLLATERAL_REQUIREMENT.get();
+        loan.sol_collateral_amount = OLD_LAMPORTS;
+        loan.msol_collateral_amount = OLD_LAMPORTS;
+        loan.loan_amount = loan_amount(OLD_LAMPORTS);
+        loan.market_price = SOL_USD_PRICE;
+        loan.loan_creation_date = loan_creation_date;
+        loan.last_day_penalty_was_checked = loan_creation_date;
+        loan
+    }
+
+    fn program_test(&self, loan: &MataLoan, user_mata: u64, user_msol: u64) -> ProgramTest {
+        let mut program_test = program_test();
+        let user = self.user.pubkey();
+
+        program_test.add_account(self.system_state, pod_account(&self.system_state()));
+        program_test.add_account(self.loan, pod_account(loan));
+        program_test.add_account(self.marinade_state, program_account(marinade_finance::id(), marinade_state_data()));
+
+        // The loan's mata is held somewhere other than the user's account
+        program_test.add_account(self.mata_mint, mint(Pda::mata_mint_authority(&self.system_state).0, loan_amount(OLD_LAMPORTS) + user_mata, 6));
+        program_test.add_account(self.msol_mint, mint(Pubkey::new_unique(), OLD_LAMPORTS + user_msol, 9));
+        program_test.add_account(self.msol_vault, token_account(self.msol_mint, Pda::msol_vault_authority(&self.system_state).0, OLD_LAMPORTS));
+
+        program_test.add_account(user, Account::new(LAMPORTS_PER_SOL, 0, &system_program::id()));
+        program_test.add_account(self.user_mata, token_account(self.mata_mint, user, user_mata));
+        program_test.add_account(self.user_msol, token_account(self.msol_mint, user, user_msol));
+
+        program_test
+    }
+
+    fn rollover(&self, new_lamports: u64) -> Instruction {
+        rollover_loan(
+            &self.system_state, &self.marinade_state, &self.loan, &self.msol_vault, &self.mata_mint, &self.user.pubkey(),
+            &self.user_mata, &self.user_msol, &SOL_USDC_ORACLE, &SOL_USDT_ORACLE, &SOL_MATA_ORACLE, new_lamports,
+        )
+    }
+}
+
+/// What a create would mint for the lamports at the fixture's price, rounded the way the program does
+fn loan_amount(lamports: u64) -> u64 {
+    let value = Decimal::from(SOL_USD_PRICE) / Decimal::from(10_u64.pow(ORACLE_EXPO.into())) * Decimal::from(lamports)
+        / Decimal::from(LAMPORTS_PER_SOL);
+    calc_loan_amount(value, COLLATERAL_REQUIREMENT).unwrap().get()
+}
+
+async fn start(fixture: &Fixture, loan: &MataLoan, user_mata: u64, user_msol: u64) -> Harness {
+    let mut borrower = Harness::start(fixture.program_test(loan, user_mata, user_msol)).await;
+    borrower.refresh_oracles(&[(SOL_USDC_ORACLE, SOL_USD_PRICE), (SOL_USDT_ORACLE, SOL_USD_PRICE)]).await;
+    borrower
+}
+
+/// Every account the rollover writes to
+async fn snapshot(borrower: &mut Harness, fixture: &Fixture) -> Vec<Account> {
+    let mut accounts = vec![];
+    for key in [
+        fixture.system_state, fixture.loan, fixture.msol_vault, fixture.mata_mint, fixture.user_mata, fixture.user_msol,
+    ] {
+        accounts.push(borrower.account(&key).await.unwrap());
+    }
+    accounts
+}
+
+#[tokio::test]
+async fn test_growing_a_loan_needs_none_of_the_debt_up_front() {
+    let f = Fixture::new();
+    let new_lamports = 16 * LAMPORTS_PER_SOL;
+    let (old_debt, new_debt) = (loan_amount(OLD_LAMPORTS), loan_amount(new_lamports));
+    let extra_msol = new_lamports - OLD_LAMPORTS;
+
+    // A close would burn the whole debt first, the user holds none of it
+    let mut borrower = start(&f, &f.loan(START_TIMESTAMP - 2 * EPOCH), 0, extra_msol).await;
+    let mata_supply = borrower.mint_supply(&f.mata_mint).await;
+
+    borrower.send(f.rollover(new_lamports), &[&f.user]).await.unwrap();
+
+    // The same end state as closing and opening again at the same prices, only the differences moved
+    assert_eq!(borrower.token_balance(&f.user_mata).await, new_debt - old_debt);
+    assert_eq!(borrower.mint_supply(&f.mata_mint).await, mata_supply + new_debt - old_debt);
+    assert_eq!(borrower.token_balance(&f.user_msol).await, 0);
+    assert_eq!(borrower.token_balance(&f.msol_vault).await, new_lamports);
+
+    let loan: MataLoan = borrower.load(&f.loan).await;
+    assert_eq!(loan.loan_amount, new_debt);
+    assert_eq!(loan.sol_collateral_amount, new_lamports);
+    assert_eq!(loan.msol_collateral_amount, new_lamports);
+    assert_eq!(loan.initial_collateral, new_lamports);
+    assert_eq!(loan.loan_creation_date, START_TIMESTAMP);
+    assert_eq!(loan.owner, f.user.pubkey());
+    assert!(!loan.repaid);
+
+    let system_state: SystemState = borrower.load(&f.system_state).await;
+    assert_eq!(system_state.mata_supply.total().unwrap(), new_debt);
+    assert_eq!(system_state.total_sol_collateral, new_lamports);
+}
+
+#[tokio::test]
+async fn test_shrinking_a_loan_takes_the_difference_before_paying_out() {
+    let f = Fixture::new();
+    let new_lamports = 7 * LAMPORTS_PER_SOL;
+    let (old_debt, new_debt) = (loan_amount(OLD_LAMPORTS), loan_amount(new_lamports));
+
+    let mut borrower = start(&f, &f.loan(START_TIMESTAMP - 2 * EPOCH), old_debt - new_debt, 0).await;
+    let mata_supply = borrower.mint_supply(&f.mata_mint).await;
+
+    borrower.send(f.rollover(new_lamports), &[&f.user]).await.unwrap();
+
+    assert_eq!(borrower.token_balance(&f.user_mata).await, 0);
+    assert_eq!(borrower.mint_supply(&f.mata_mint).await, mata_supply - (old_debt - new_debt));
+    assert_eq!(borrower.token_balance(&f.user_msol).await, OLD_LAMPORTS - new_lamports);
+    assert_eq!(borrower.token_balance(&f.msol_vault).await, new_lamports);
+
+    let loan: MataLoan = borrower.load(&f.loan).await;
+    assert_eq!((loan.loan_amount, loan.sol_collateral_amount), (new_debt, new_lamports));
+    let system_state: SystemState = borrower.load(&f.system_state).await;
+    assert_eq!(system_state.mata_supply.total().unwrap(), new_debt);
+}
+
+#[tokio::test]
+async fn test_a_shortfall_leaves_every_account_as_it_was() {
+    let f = Fixture::new();
+    let new_lamports = 7 * LAMPORTS_PER_SOL;
+    let owed = loan_amount(OLD_LAMPORTS) - loan_amount(new_lamports);
+
+    // One short of the mata the smaller loan no longer covers, the msol it would get back is never paid out
+    let mut borrower = start(&f, &f.loan(START_TIMESTAMP - 2 * EPOCH), owed - 1, 0).await;
+    let before = snapshot(&mut borrower, &f).await;
+    assert_lucra_error(borrower.send(f.rollover(new_lamports), &[&f.user]).await, LucraErrorCode::InvalidAmount);
+    assert_eq!(snapshot(&mut borrower, &f).await, before);
+
+    // Growing the loan past what the user's msol covers fails the same way
+    let mut borrower = start(&f, &f.loan(START_TIMESTAMP - 2 * EPOCH), 0, LAMPORTS_PER_SOL - 1).await;
+    let before = snapshot(&mut borrower, &f).await;
+    assert_lucra_error(borrower.send(f.rollover(OLD_LAMPORTS + LAMPORTS_PER_SOL), &[&f.user]).await, LucraErrorCode::InvalidAmount);
+    assert_eq!(snapshot(&mut borrower, &f).await, before);
+}
+
+#[tokio::test]
+async fn test_a_rollover_waits_out_the_close_timelock() {
+    let f = Fixture::new();
+    let mut borrower = start(&f, &f.loan(START_TIMESTAMP - EPOCH), 0, LAMPORTS_PER_SOL).await;
+    let before = snapshot(&mut borrower, &f).await;
+
+    assert_lucra_error(borrower.send(f.rollover(OLD_LAMPORTS + LAMPORTS_PER_SOL), &[&f.user]).await, LucraErrorCode::Timelock);
+    assert_eq!(snapshot(&mut borrower, &f).await, before);
+}
@@ -0,0 +1,540 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use std::convert::TryFrom;
+
+use common::{
+    assert_lucra_error, marinade_state_data, mint, pod_account, program_account, program_test, token_account,
+    uninitialized_account, Harness, DAY, EPOCH, START_TIMESTAMP,
+};
+use lucra::{
+    error::LucraErrorCode,
+    helpers::{
+        account::find_stake_balance_authority,
+        constants::{DEFAULT_REWARD_EXPIRY_EPOCHS, LUCRA_SOL_ORACLE, SOL_USDC_ORACLE, SOL_USDT_ORACLE},
+        unstake_timelock::{unstake_timelock, UNSTAKE_TIMELOCK_VERSION},
+    },
+    instruction::{
+        cancel_unstake, claim_reward, close_stake_balance, close_staking_account, create_stake_balance,
+        create_staking_account, deposit_stake, drop_reward_derived, end_unstake_derived, stake_derived,
+        start_unstake_derived, withdraw_stake_derived,
+    },
+    state::{
+        staking::{PendingWithdrawal, Reward, StakeBalance, StakingAccount, StakingState, StakingTimeframe},
+        SystemState,
+    },
+};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use solana_program::{
+    instruction::AccountMeta,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    system_program,
+};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+};
+
+const LUCRA: u64 = 1_000_000_000;
+const DEPOSIT: u64 = 1_000 * LUCRA;
+// No sol collateral is recorded, so the whole vault is yield and drops in two halves
+const MSOL_YIELD: u64 = 10_000_000_000;
+
+// $20 sol and 0.05 sol lucra, start unstake only needs the prices to be fresh
+const SOL_USD_PRICE: u64 = 20_000_000;
+const LUCRA_SOL_PRICE: u64 = 50_000;
+const ORACLE_PRICES: [(Pubkey, u64); 3] = [
+    (SOL_USDC_ORACLE, SOL_USD_PRICE),
+    (SOL_USDT_ORACLE, SOL_USD_PRICE),
+    (LUCRA_SOL_ORACLE, LUCRA_SOL_PRICE),
+];
+
+struct Fixture {
+    user: Keypair,
+    system_state: Pubkey,
+    staking_state: Pubkey,
+    marinade_state: Pubkey,
+
+    lucra_mint: Pubkey,
+    staked_lucra_mint: Pubkey,
+    reward_mint: Pubkey,
+    msol_mint: Pubkey,
+
+    msol_vault: Pubkey,
+    rewards_vault: Pubkey,
+    arb_coffer: Pubkey,
+
+    staking_account: Pubkey,
+    stake_balance: Pubkey,
+    deposit_vault: Pubkey,
+    stake_vault: Pubkey,
+    pending_vault: Pubkey,
+    pending_withdrawal: Pubkey,
+    reward: Pubkey,
+    next_reward: Pubkey,
+
+    user_lucra: Pubkey,
+    user_staked_lucra: Pubkey,
+    user_msol: Pubkey,
+    user_reward: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Fixture {
+            user: Keypair::new(),
+            system_state: Pubkey::new_unique(),
+            staking_state: Pubkey::new_unique(),
+            marinade_state: Pubkey::new_unique(),
+            lucra_mint: Pubkey::new_unique(),
+            staked_lucra_mint: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            msol_mint: Pubkey::new_unique(),
+            msol_vault: Pubkey::new_unique(),
+            rewards_vault: Pubkey::new_unique(),
+            arb_coffer: Pubkey::new_unique(),
+            staking_account: Pubkey::new_unique(),
+            stake_balance: Pubkey::new_unique(),
+            deposit_vault: Pubkey::new_unique(),
+            stake_vault: Pubkey::new_unique(),
+            pending_vault: Pubkey::new_unique(),
+            pending_withdrawal: Pubkey::new_unique(),
+            reward: Pubkey::new_unique(),
+            next_reward: Pubkey::new_unique(),
+            user_lucra: Pubkey::new_unique(),
+            user_staked_lucra: Pubkey::new_unique(),
+            user_msol: Pubkey::new_unique(),
+            user_reward: Pubkey::new_unique(),
+        }
+    }
+
+    fn stake_balance_authority(&self) -> (Pubkey, u8) {
+        find_stake_balance_authority(&self.user.pubkey(), &self.system_state)
+    }
+
+    // Written the way Initialize leaves it, Initialize itself needs the creator authority's keypair
+    fn system_state(&self) -> SystemState {
+        let mut system_state = common::system_state(self.system_state);
+        system_state.staking_state = self.staking_state;
+        system_state.reward_expiry_epochs = DEFAULT_REWARD_EXPIRY_EPOCHS;
+        system_state.staking_enabled = true;
+        system_state.lucra_mint.address = self.lucra_mint;
+        system_state.lucra_mint.authority_bump_seed = SystemState::find_lucra_mint_authority(&self.system_state).1;
+        system_state.reward_mint.address = self.reward_mint;
+        system_state.reward_mint.authority_bump_seed = SystemState::find_reward_mint_authority(&self.system_state).1;
+        system_state.reward_mint_decimals = 0;
+        system_state.msol_vault.address = self.msol_vault;
+        system_state.msol_vault.authority_bump_seed = SystemState::find_msol_vault_authority(&self.system_state).1;
+        system_state.rewards_vault.address = self.rewards_vault;
+        system_state.rewards_vault.authority_bump_seed = SystemState::find_rewards_vault_authority(&self.system_state).1;
+        system_state.arb_coffer.address = self.arb_coffer;
+        system_state.arb_coffer.authority_bump_seed = SystemState::find_arb_coffer_authority(&self.system_state).1;
+        system_state
+    }
+
+    fn program_test(&self) -> ProgramTest {
+        let mut program_test = program_test();
+        let user = self.user.pubkey();
+        let vault_owner = self.stake_balance_authority().0;
+
+        program_test.add_account(self.system_state, pod_account(&self.system_state()));
+        program_test.add_account(self.staking_state, pod_account(&common::staking_state(self.staking_state, self.staked_lucra_mint)));
+        program_test.add_account(self.marinade_state, program_account(marinade_finance::id(), marinade_state_data()));
+        program_test.add_account(self.staking_account, uninitialized_account::<StakingAccount>());
+        program_test.add_account(self.stake_balance, uninitialized_account::<StakeBalance>());
+        program_test.add_account(self.pending_withdrawal, uninitialized_account::<PendingWithdrawal>());
+        program_test.add_account(self.reward, uninitialized_account::<Reward>());
+        program_test.add_account(self.next_reward, uninitialized_account::<Reward>());
+
+        program_test.add_account(self.lucra_mint, mint(SystemState::find_lucra_mint_authority(&self.system_state).0, DEPOSIT, 9));
+        program_test.add_account(self.staked_lucra_mint, mint(StakingState::find_stake_mint_authority(&self.staking_state).0, 0, 9));
+        program_test.add_account(self.reward_mint, mint(SystemState::find_reward_mint_authority(&self.system_state).0, 0, 0));
+        program_test.add_account(self.msol_mint, mint(Pubkey::new_unique(), MSOL_YIELD, 9));
+
+        program_test.add_account(self.msol_vault, token_account(self.msol_mint, SystemState::find_msol_vault_authority(&self.system_state).0, MSOL_YIELD));
+        program_test.add_account(self.rewards_vault, token_account(self.msol_mint, SystemState::find_rewards_vault_authority(&self.system_state).0, 0));
+        program_test.add_account(self.arb_coffer, token_account(self.msol_mint, SystemState::find_arb_coffer_authority(&self.system_state).0, 0));
+
+        program_test.add_account(self.deposit_vault, token_account(self.lucra_mint, vault_owner, 0));
+        program_test.add_account(self.stake_vault, token_account(self.lucra_mint, vault_owner, 0));
+        program_test.add_account(self.pending_vault, token_account(self.lucra_mint, vault_owner, 0));
+
+        program_test.add_account(user, Account::new(10 * LAMPORTS_PER_SOL, 0, &system_program::id()));
+        program_test.add_account(self.user_lucra, token_account(self.lucra_mint, user, DEPOSIT));
+        program_test.add_account(self.user_staked_lucra, token_account(self.staked_lucra_mint, user, 0));
+        program_test.add_account(self.user_msol, token_account(self.msol_mint, user, 0));
+        program_test.add_account(self.user_reward, token_account(self.reward_mint, user, 0));
+
+        program_test
+    }
+}
+
+/// Mirrors calculate_annual_interest_rate so the expectation rounds the same way
+fn weekly_inflation(annual_inflation_rate: u8, staked_lucra: u64) -> u64 {
+    (Decimal::ONE / Decimal::from(52_u64) * Decimal::new(annual_inflation_rate.into(), 2) * Decimal::from(staked_lucra))
+        .floor()
+        .to_u64()
+        .unwrap()
+}
+
+/// Marinade yield building back up in the msol vault for the next drop
+fn accrue_yield(lifecycle: &mut Harness, fixture: &Fixture) {
+    let msol_vault_authority = SystemState::find_msol_vault_authority(&fixture.system_state).0;
+    lifecycle.set_account(&fixture.msol_vault, token_account(fixture.msol_mint, msol_vault_authority, MSOL_YIELD));
+}
+
+async fn run_lifecycle(staking_timeframe: StakingTimeframe) {
+    let weight = staking_timeframe.weight();
+    let lock = EPOCH * staking_timeframe.timeframe_multiplier();
+    let staked_lucra = DEPOSIT * weight;
+
+    let fixture = Fixture::new();
+    let f = &fixture;
+    let user = f.user.pubkey();
+    let (_, nonce) = f.stake_balance_authority();
+
+    let mut lifecycle = Harness::start(f.program_test()).await;
+    let user_lamports = lifecycle.lamports(&user).await;
+    let pending_withdrawal_rent = lifecycle.lamports(&f.pending_withdrawal).await;
+    let stake_balance_rent = lifecycle.lamports(&f.stake_balance).await;
+    let staking_account_rent = lifecycle.lamports(&f.staking_account).await;
+    let vaults_rent = lifecycle.lamports(&f.deposit_vault).await
+        + lifecycle.lamports(&f.stake_vault).await
+        + lifecycle.lamports(&f.pending_vault).await;
+
+    // Create the staking account and a balance for this timeframe
+    lifecycle
+        .send(create_staking_account(&f.system_state, &f.staking_state, &f.staking_account, &user, None), &[&f.user])
+        .await
+        .unwrap();
+    let staking_account: StakingAccount = lifecycle.load(&f.staking_account).await;
+    assert!(staking_account.meta_data.is_initialized);
+    assert_eq!(staking_account.owner, user);
+    assert_eq!(staking_account.total, 0);
+
+    lifecycle
+        .send(
+            create_stake_balance(
+                &f.system_state, &f.staking_state, &f.stake_balance, &user,
+                &f.deposit_vault, &f.stake_vault, &f.pending_vault, &f.staking_account, nonce, staking_timeframe,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    let stake_balance: StakeBalance = lifecycle.load(&f.stake_balance).await;
+    assert_eq!(stake_balance.owner, user);
+    assert_eq!(stake_balance.reward_cursor, 0);
+    assert_eq!(stake_balance.staking_timeframe as u8, staking_timeframe as u8);
+    assert_eq!(stake_balance.balances.deposit_vault, f.deposit_vault);
+    assert_eq!(stake_balance.balances.stake_vault, f.stake_vault);
+    assert_eq!(stake_balance.balances.pending_vault, f.pending_vault);
+    assert_eq!(stake_balance.staking_account, f.staking_account);
+
+    // Deposit and stake all of it
+    lifecycle
+        .send(deposit_stake(&f.system_state, &f.stake_balance, &f.user_lucra, &f.deposit_vault, &user, DEPOSIT), &[&f.user])
+        .await
+        .unwrap();
+    assert_eq!(lifecycle.token_balance(&f.user_lucra).await, 0);
+    assert_eq!(lifecycle.token_balance(&f.deposit_vault).await, DEPOSIT);
+
+    lifecycle
+        .send(
+            stake_derived(
+                &f.system_state, &f.staking_state, &f.staking_account, &f.stake_balance, &f.deposit_vault,
+                &f.stake_vault, &user, &f.user_staked_lucra, &f.staked_lucra_mint, DEPOSIT,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(lifecycle.token_balance(&f.deposit_vault).await, 0);
+    assert_eq!(lifecycle.token_balance(&f.stake_vault).await, DEPOSIT);
+    assert_eq!(lifecycle.token_balance(&f.user_staked_lucra).await, staked_lucra);
+    assert_eq!(lifecycle.mint_supply(&f.staked_lucra_mint).await, staked_lucra);
+    let staking_account: StakingAccount = lifecycle.load(&f.staking_account).await;
+    assert_eq!(staking_account.total, DEPOSIT);
+    assert_lucra_error(
+        lifecycle.send(close_staking_account(&f.staking_account, &user), &[&f.user]).await,
+        LucraErrorCode::StakingAccountNotEmpty,
+    );
+    let stake_balance: StakeBalance = lifecycle.load(&f.stake_balance).await;
+    assert_eq!(stake_balance.last_stake_timestamp, START_TIMESTAMP);
+    // The stake sits the next drop out
+    assert_eq!(stake_balance.reward_cursor, 1);
+
+    // A day later the yield drops, half to the coffer and half to stakers
+    lifecycle.warp(DAY).await;
+    lifecycle
+        .send(
+            drop_reward_derived(
+                &f.system_state, &f.staking_state, &f.marinade_state, &f.reward, &f.staked_lucra_mint,
+                &f.msol_vault, &f.rewards_vault, &f.arb_coffer, &f.user_reward, &f.reward_mint,
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    let reward: Reward = lifecycle.load(&f.reward).await;
+    assert_eq!(reward.total, MSOL_YIELD / 2);
+    assert_eq!(reward.pool_token_supply, staked_lucra);
+    assert_eq!(reward.reward_cursor, 0);
+    assert_eq!(reward.start_timestamp, START_TIMESTAMP + DAY);
+    assert_eq!(reward.claimed, 0);
+    let staking_state: StakingState = lifecycle.load(&f.staking_state).await;
+    assert_eq!(staking_state.reward_cursor, 1);
+    assert_eq!(staking_state.current_reward_pubkey, f.reward);
+    assert_eq!(lifecycle.token_balance(&f.msol_vault).await, 0);
+    assert_eq!(lifecycle.token_balance(&f.arb_coffer).await, MSOL_YIELD / 2);
+    assert_eq!(lifecycle.token_balance(&f.rewards_vault).await, MSOL_YIELD / 2);
+    assert_eq!(lifecycle.token_balance(&f.user_reward).await, 1);
+
+    // Staked right before it, the balance has nothing to claim on this drop
+    let claim = |f: &Fixture| {
+        claim_reward(
+            &f.system_state, &f.staking_state, &f.staking_account, &f.stake_balance, &f.reward,
+            &f.user_staked_lucra, &f.stake_vault, &f.user_lucra, &f.rewards_vault, &f.user_msol, &f.lucra_mint,
+        )
+    };
+    assert_lucra_error(lifecycle.send(claim(f), &[]).await, LucraErrorCode::AlreadyProcessed);
+
+    // An epoch later the next yield drops
+    lifecycle.warp(EPOCH).await;
+    accrue_yield(&mut lifecycle, f);
+    lifecycle
+        .send(
+            drop_reward_derived(
+                &f.system_state, &f.staking_state, &f.marinade_state, &f.next_reward, &f.staked_lucra_mint,
+                &f.msol_vault, &f.rewards_vault, &f.arb_coffer, &f.user_reward, &f.reward_mint,
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    let next_reward: Reward = lifecycle.load(&f.next_reward).await;
+    assert_eq!(next_reward.reward_cursor, 1);
+    assert_eq!(next_reward.previous_reward, f.reward);
+    assert_eq!(lifecycle.token_balance(&f.rewards_vault).await, MSOL_YIELD);
+
+    // The only staker takes the whole second reward plus a week of inflation on its weighted stake. The claim
+    // passes the reward of the drop it sat out.
+    lifecycle.send(claim(f), &[]).await.unwrap();
+    let inflation = weekly_inflation(staking_timeframe.annual_inflation_rate(), staked_lucra);
+    assert_eq!(lifecycle.token_balance(&f.user_msol).await, MSOL_YIELD / 2);
+    assert_eq!(lifecycle.token_balance(&f.rewards_vault).await, MSOL_YIELD / 2);
+    assert_eq!(lifecycle.token_balance(&f.user_lucra).await, inflation);
+    assert_eq!(lifecycle.mint_supply(&f.lucra_mint).await, DEPOSIT + inflation);
+    let stake_balance: StakeBalance = lifecycle.load(&f.stake_balance).await;
+    assert_eq!(stake_balance.reward_cursor, 2);
+    assert_lucra_error(lifecycle.send(claim(f), &[]).await, LucraErrorCode::AlreadyProcessed);
+
+    let unstake = |f: &Fixture| {
+        start_unstake_derived(
+            &f.system_state, &f.staking_state, &f.staking_account, &f.stake_balance, &f.stake_vault,
+            &f.pending_vault, &f.pending_withdrawal, &f.user.pubkey(), &f.user_staked_lucra, &f.staked_lucra_mint,
+            &SOL_USDC_ORACLE, &SOL_USDT_ORACLE, &LUCRA_SOL_ORACLE, DEPOSIT,
+        )
+    };
+
+    // The stake stays locked for its timeframe, counted from when it was staked
+    let staked_for = DAY + EPOCH;
+    if lock > staked_for {
+        lifecycle.warp(lock - staked_for - 1).await;
+        lifecycle.refresh_oracles(&ORACLE_PRICES).await;
+        assert_lucra_error(lifecycle.send(unstake(f), &[&f.user]).await, LucraErrorCode::StakingAccountNotUnlocked);
+        lifecycle.warp(1).await;
+    }
+    lifecycle.refresh_oracles(&ORACLE_PRICES).await;
+    let unstake_timestamp = lifecycle.clock().await.unix_timestamp;
+    assert_eq!(unstake_timestamp, START_TIMESTAMP + lock.max(staked_for));
+
+    lifecycle.send(unstake(f), &[&f.user]).await.unwrap();
+    assert_eq!(lifecycle.token_balance(&f.stake_vault).await, 0);
+    assert_eq!(lifecycle.token_balance(&f.pending_vault).await, DEPOSIT);
+    assert_eq!(lifecycle.token_balance(&f.user_staked_lucra).await, 0);
+    assert_eq!(lifecycle.mint_supply(&f.staked_lucra_mint).await, 0);
+    let staking_account: StakingAccount = lifecycle.load(&f.staking_account).await;
+    assert_eq!(staking_account.total, 0);
+    let pending_withdrawal: PendingWithdrawal = lifecycle.load(&f.pending_withdrawal).await;
+    assert_eq!(pending_withdrawal.stake_balance, f.stake_balance);
+    assert_eq!(pending_withdrawal.lucra, DEPOSIT);
+    assert_eq!(pending_withdrawal.start_timestamp, unstake_timestamp);
+    let timelock = unstake_timelock(staking_timeframe).unwrap();
+    assert_eq!(pending_withdrawal.meta_data.version, UNSTAKE_TIMELOCK_VERSION);
+    assert_eq!(pending_withdrawal.claimable_at, unstake_timestamp + timelock);
+    assert_eq!(pending_withdrawal.end_timestamp, pending_withdrawal.claimable_at);
+
+    let close = |f: &Fixture| {
+        close_stake_balance(
+            &f.system_state, &f.stake_balance, &f.deposit_vault, &f.stake_vault, &f.pending_vault, &f.user.pubkey(),
+        )
+    };
+    // The pending withdrawal's lucra still sits in the pending vault
+    assert_lucra_error(lifecycle.send(close(f), &[&f.user]).await, LucraErrorCode::StakeBalanceNotEmpty);
+
+    // The withdrawal waits out its timeframe's timelock before the lucra is back in the deposit vault
+    let finish = |f: &Fixture| {
+        end_unstake_derived(
+            &f.system_state, &f.pending_withdrawal, &f.stake_balance, &f.pending_vault, &f.deposit_vault,
+            &f.user.pubkey(),
+        )
+    };
+    lifecycle.warp(timelock - 1).await;
+    assert_lucra_error(lifecycle.send(finish(f), &[&f.user]).await, LucraErrorCode::Timelock);
+    lifecycle.warp(1).await;
+    // The old layout's rent account can't send the rent anywhere but the owner
+    let mut old_layout = finish(f);
+    old_layout.accounts.insert(7, AccountMeta::new(Pubkey::new_unique(), false));
+    assert_lucra_error(lifecycle.send(old_layout, &[&f.user]).await, LucraErrorCode::InvalidAccountInput);
+    lifecycle.send(finish(f), &[&f.user]).await.unwrap();
+    assert_eq!(lifecycle.token_balance(&f.pending_vault).await, 0);
+    assert_eq!(lifecycle.token_balance(&f.deposit_vault).await, DEPOSIT);
+    assert!(lifecycle.account(&f.pending_withdrawal).await.is_none());
+    assert_eq!(lifecycle.lamports(&user).await, user_lamports + pending_withdrawal_rent);
+
+    // Emptying the last vault leaves the stake balance open until the owner closes it
+    lifecycle
+        .send(
+            withdraw_stake_derived(
+                &f.system_state, &f.staking_account, &f.stake_balance, &f.user_lucra, &f.deposit_vault,
+                &f.stake_vault, &f.pending_vault, &user, DEPOSIT,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(lifecycle.token_balance(&f.deposit_vault).await, 0);
+    assert_eq!(lifecycle.token_balance(&f.user_lucra).await, DEPOSIT + inflation);
+    assert!(lifecycle.account(&f.stake_balance).await.is_some());
+
+    lifecycle.send(close(f), &[&f.user]).await.unwrap();
+    for closed in [&f.stake_balance, &f.deposit_vault, &f.stake_vault, &f.pending_vault] {
+        assert!(lifecycle.account(closed).await.is_none());
+    }
+    assert_eq!(
+        lifecycle.lamports(&user).await,
+        user_lamports + pending_withdrawal_rent + stake_balance_rent + vaults_rent
+    );
+
+    // Nothing is staked anymore, the staking account can go too
+    lifecycle.send(close_staking_account(&f.staking_account, &user), &[&f.user]).await.unwrap();
+    assert!(lifecycle.account(&f.staking_account).await.is_none());
+    assert_eq!(
+        lifecycle.lamports(&user).await,
+        user_lamports + pending_withdrawal_rent + stake_balance_rent + vaults_rent + staking_account_rent
+    );
+}
+
+#[tokio::test]
+async fn test_staking_lifecycle_for_every_timeframe() {
+    let mut timeframes = 0;
+    while let Ok(staking_timeframe) = StakingTimeframe::try_from(timeframes) {
+        run_lifecycle(staking_timeframe).await;
+        timeframes += 1;
+    }
+
+    assert!(timeframes > 0);
+}
+
+#[tokio::test]
+async fn test_cancel_unstake_restores_the_stake() {
+    let staking_timeframe = StakingTimeframe::try_from(0).unwrap();
+    let lock = EPOCH * staking_timeframe.timeframe_multiplier();
+    let staked_lucra = DEPOSIT * staking_timeframe.weight();
+
+    let fixture = Fixture::new();
+    let f = &fixture;
+    let user = f.user.pubkey();
+    let (_, nonce) = f.stake_balance_authority();
+
+    let mut lifecycle = Harness::start(f.program_test()).await;
+    let user_lamports = lifecycle.lamports(&user).await;
+    let pending_withdrawal_rent = lifecycle.lamports(&f.pending_withdrawal).await;
+
+    lifecycle
+        .send(create_staking_account(&f.system_state, &f.staking_state, &f.staking_account, &user, None), &[&f.user])
+        .await
+        .unwrap();
+    lifecycle
+        .send(
+            create_stake_balance(
+                &f.system_state, &f.staking_state, &f.stake_balance, &user,
+                &f.deposit_vault, &f.stake_vault, &f.pending_vault, &f.staking_account, nonce, staking_timeframe,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    lifecycle
+        .send(deposit_stake(&f.system_state, &f.stake_balance, &f.user_lucra, &f.deposit_vault, &user, DEPOSIT), &[&f.user])
+        .await
+        .unwrap();
+    lifecycle
+        .send(
+            stake_derived(
+                &f.system_state, &f.staking_state, &f.staking_account, &f.stake_balance, &f.deposit_vault,
+                &f.stake_vault, &user, &f.user_staked_lucra, &f.staked_lucra_mint, DEPOSIT,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+
+    // Unstaking waits for the drop the stake sat out
+    lifecycle.warp(lock.max(DAY)).await;
+    lifecycle
+        .send(
+            drop_reward_derived(
+                &f.system_state, &f.staking_state, &f.marinade_state, &f.reward, &f.staked_lucra_mint,
+                &f.msol_vault, &f.rewards_vault, &f.arb_coffer, &f.user_reward, &f.reward_mint,
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    lifecycle.refresh_oracles(&ORACLE_PRICES).await;
+    lifecycle
+        .send(
+            start_unstake_derived(
+                &f.system_state, &f.staking_state, &f.staking_account, &f.stake_balance, &f.stake_vault,
+                &f.pending_vault, &f.pending_withdrawal, &user, &f.user_staked_lucra, &f.staked_lucra_mint,
+                &SOL_USDC_ORACLE, &SOL_USDT_ORACLE, &LUCRA_SOL_ORACLE, DEPOSIT,
+            ),
+            &[&f.user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(lifecycle.token_balance(&f.pending_vault).await, DEPOSIT);
+    assert_eq!(lifecycle.mint_supply(&f.staked_lucra_mint).await, 0);
+
+    let cancel = |f: &Fixture| {
+        cancel_unstake(
+            &f.system_state, &f.staking_state, &f.staking_account, &f.stake_balance, &f.pending_withdrawal,
+            &f.pending_vault, &f.stake_vault, &f.user.pubkey(), &f.user_staked_lucra, &f.staked_lucra_mint,
+        )
+    };
+    lifecycle.warp(DAY).await;
+    let cancel_timestamp = lifecycle.clock().await.unix_timestamp;
+    lifecycle.send(cancel(f), &[&f.user]).await.unwrap();
+
+    // Back to how it stood right after staking, only the lock starts again
+    assert_eq!(lifecycle.token_balance(&f.pending_vault).await, 0);
+    assert_eq!(lifecycle.token_balance(&f.stake_vault).await, DEPOSIT);
+    assert_eq!(lifecycle.token_balance(&f.user_staked_lucra).await, staked_lucra);
+    assert_eq!(lifecycle.mint_supply(&f.staked_lucra_mint).await, staked_lucra);
+    let staking_account: StakingAccount = lifecycle.load(&f.staking_account).await;
+    assert_eq!(staking_account.total, DEPOSIT);
+    let stake_balance: StakeBalance = lifecycle.load(&f.stake_balance).await;
+    assert_eq!(stake_balance.last_stake_timestamp, cancel_timestamp);
+    // The restored stake sits the next drop out like a new one
+    assert_eq!(stake_balance.reward_cursor, 2);
+    assert!(lifecycle.account(&f.pending_withdrawal).await.is_none());
+    assert_eq!(lifecycle.lamports(&user).await, user_lamports + pending_withdrawal_rent);
+
+    // The withdrawal is gone, it can't be cancelled twice
+    assert_lucra_error(lifecycle.send(cancel(f), &[&f.user]).await, LucraErrorCode::InvalidAccountOwner);
+}
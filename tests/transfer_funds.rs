@@ -0,0 +1,173 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{as_dao, assert_lucra_error, pod_account, program_test, token_account, Harness};
+use lucra::{
+    error::LucraErrorCode,
+    helpers::constants::TRANSFER_DESTINATION_DELAY,
+    id,
+    instruction::{add_transfer_destination, remove_transfer_destination, transfer_funds_derived, transfer_native_funds},
+    pda::Pda,
+    state::SystemState,
+};
+use solana_program::{
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+};
+
+const SOL_COLLATERAL: u64 = 10 * LAMPORTS_PER_SOL;
+const MSOL: u64 = 5 * LAMPORTS_PER_SOL;
+
+struct Fixture {
+    dao: Keypair,
+    system_state: Pubkey,
+    msol_mint: Pubkey,
+    msol_vault: Pubkey,
+    treasury: Pubkey,
+    treasury_msol: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Fixture {
+            dao: Keypair::new(),
+            system_state: Pubkey::new_unique(),
+            msol_mint: Pubkey::new_unique(),
+            msol_vault: Pubkey::new_unique(),
+            treasury: Pubkey::new_unique(),
+            treasury_msol: Pubkey::new_unique(),
+        }
+    }
+
+    fn system_state(&self) -> SystemState {
+        let mut system_state = common::system_state(self.system_state);
+        system_state.dao_authority = self.dao.pubkey();
+        system_state.msol_vault.address = self.msol_vault;
+        system_state.msol_vault.authority_bump_seed = Pda::msol_vault_authority(&self.system_state).1;
+        system_state.sol_vault.address = Pda::sol_vault(&self.system_state).0;
+        system_state
+    }
+
+    fn program_test(&self) -> ProgramTest {
+        let mut program_test = program_test();
+        let msol_vault_authority = Pda::msol_vault_authority(&self.system_state).0;
+
+        program_test.add_account(self.system_state, pod_account(&self.system_state()));
+        program_test.add_account(
+            Pda::sol_vault(&self.system_state).0,
+            Account::new(Rent::default().minimum_balance(0) + SOL_COLLATERAL, 0, &id()),
+        );
+        program_test.add_account(self.msol_vault, token_account(self.msol_mint, msol_vault_authority, MSOL));
+        program_test.add_account(self.treasury, Account::new(LAMPORTS_PER_SOL, 0, &system_program::id()));
+        program_test.add_account(self.treasury_msol, token_account(self.msol_mint, self.treasury, 0));
+
+        program_test
+    }
+
+    fn as_dao(&self, instruction: Instruction) -> Instruction {
+        as_dao(&self.dao.pubkey(), instruction)
+    }
+}
+
+#[tokio::test]
+async fn test_destinations_wait_out_the_delay() {
+    let f = Fixture::new();
+    let mut dao = Harness::start(f.program_test()).await;
+    let payer = dao.context.payer.pubkey();
+
+    // Nothing can go anywhere before the allowlist exists
+    assert!(dao.send(f.as_dao(transfer_funds_derived(&f.system_state, &f.msol_vault, &f.treasury_msol, 1)), &[&f.dao]).await.is_err());
+    let send_msol = f.as_dao(transfer_funds_derived(&f.system_state, &f.msol_vault, &f.treasury_msol, MSOL));
+
+    dao.send(f.as_dao(add_transfer_destination(&f.system_state, &f.treasury_msol, &payer)), &[&f.dao]).await.unwrap();
+    assert_lucra_error(dao.send(send_msol.clone(), &[&f.dao]).await, LucraErrorCode::TransferDestinationNotActive);
+
+    dao.warp(TRANSFER_DESTINATION_DELAY - 1).await;
+    assert_lucra_error(dao.send(send_msol.clone(), &[&f.dao]).await, LucraErrorCode::TransferDestinationNotActive);
+
+    // An account that was never added stays refused after the delay
+    dao.warp(1).await;
+    let attacker = Pubkey::new_unique();
+    assert_lucra_error(
+        dao.send(f.as_dao(transfer_native_funds(&f.system_state, &attacker, 1)), &[&f.dao]).await,
+        LucraErrorCode::TransferDestinationNotActive,
+    );
+
+    dao.send(send_msol, &[&f.dao]).await.unwrap();
+    assert_eq!(dao.token_balance(&f.msol_vault).await, 0);
+    assert_eq!(dao.token_balance(&f.treasury_msol).await, MSOL);
+
+    // Adding the destination again doesn't restart its delay
+    assert_lucra_error(
+        dao.send(f.as_dao(add_transfer_destination(&f.system_state, &f.treasury_msol, &payer)), &[&f.dao]).await,
+        LucraErrorCode::AlreadyProcessed,
+    );
+}
+
+#[tokio::test]
+async fn test_a_destination_can_be_revoked_during_its_delay() {
+    let f = Fixture::new();
+    let mut dao = Harness::start(f.program_test()).await;
+    let payer = dao.context.payer.pubkey();
+    let send_msol = f.as_dao(transfer_funds_derived(&f.system_state, &f.msol_vault, &f.treasury_msol, MSOL));
+
+    dao.send(f.as_dao(add_transfer_destination(&f.system_state, &f.treasury_msol, &payer)), &[&f.dao]).await.unwrap();
+    dao.warp(TRANSFER_DESTINATION_DELAY - 1).await;
+
+    // Only the DAO can remove it
+    let not_dao = Keypair::new();
+    let mut instruction = remove_transfer_destination(&f.system_state, &f.treasury_msol);
+    instruction.accounts[3].pubkey = not_dao.pubkey();
+    assert_lucra_error(dao.send(instruction, &[&not_dao]).await, LucraErrorCode::InvalidAccountInput);
+
+    dao.send(f.as_dao(remove_transfer_destination(&f.system_state, &f.treasury_msol)), &[&f.dao]).await.unwrap();
+    dao.warp(1).await;
+    assert_lucra_error(dao.send(send_msol.clone(), &[&f.dao]).await, LucraErrorCode::TransferDestinationNotActive);
+    assert_eq!(dao.token_balance(&f.msol_vault).await, MSOL);
+
+    // Removing it twice is refused, adding it back waits out the whole delay again
+    assert_lucra_error(
+        dao.send(f.as_dao(remove_transfer_destination(&f.system_state, &f.treasury_msol)), &[&f.dao]).await,
+        LucraErrorCode::TransferDestinationNotActive,
+    );
+    dao.send(f.as_dao(add_transfer_destination(&f.system_state, &f.treasury_msol, &payer)), &[&f.dao]).await.unwrap();
+    dao.warp(TRANSFER_DESTINATION_DELAY - 1).await;
+    assert_lucra_error(dao.send(send_msol.clone(), &[&f.dao]).await, LucraErrorCode::TransferDestinationNotActive);
+    dao.warp(1).await;
+    dao.send(send_msol, &[&f.dao]).await.unwrap();
+    assert_eq!(dao.token_balance(&f.treasury_msol).await, MSOL);
+}
+
+#[tokio::test]
+async fn test_native_transfers_come_out_of_the_sol_vault() {
+    let f = Fixture::new();
+    let mut dao = Harness::start(f.program_test()).await;
+    let payer = dao.context.payer.pubkey();
+    let sol_vault = Pda::sol_vault(&f.system_state).0;
+    let treasury_lamports = dao.lamports(&f.treasury).await;
+
+    dao.send(f.as_dao(add_transfer_destination(&f.system_state, &f.treasury, &payer)), &[&f.dao]).await.unwrap();
+    dao.warp(TRANSFER_DESTINATION_DELAY).await;
+
+    dao.send(f.as_dao(transfer_native_funds(&f.system_state, &f.treasury, SOL_COLLATERAL / 2)), &[&f.dao]).await.unwrap();
+    assert_eq!(dao.lamports(&f.treasury).await, treasury_lamports + SOL_COLLATERAL / 2);
+    assert_eq!(dao.lamports(&sol_vault).await, Rent::default().minimum_balance(0) + SOL_COLLATERAL / 2);
+
+    // The vault keeps its rent exempt minimum
+    assert!(dao.send(f.as_dao(transfer_native_funds(&f.system_state, &f.treasury, SOL_COLLATERAL)), &[&f.dao]).await.is_err());
+
+    // Only the DAO can send
+    let not_dao = Keypair::new();
+    let mut instruction = transfer_native_funds(&f.system_state, &f.treasury, 1);
+    instruction.accounts[1].pubkey = not_dao.pubkey();
+    assert_lucra_error(dao.send(instruction, &[&not_dao]).await, LucraErrorCode::InvalidAccountInput);
+}